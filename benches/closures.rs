@@ -0,0 +1,38 @@
+//! Running time of the same script walked and compiled into closures (`--closures`),
+//! run with `cargo bench --bench closures`. Only the standard library is used, so the
+//! numbers are rough: compare runs on one machine.
+#![allow(clippy::result_large_err)]
+use std::time::{Duration, Instant};
+
+use rlox_interpreter::{interpreter::Interpreter, run, run_compiled};
+
+const RUNS: u32 = 5;
+
+const SOURCE: &str = "fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+fib(20);
+var sum = 0;
+for (var i = 0; i < 100000; i = i + 1) { sum = sum + i; }
+class Point { init(x) { this.x = x; } get() { return this.x; } }
+for (var i = 0; i < 10000; i = i + 1) { sum = sum + Point(i).get(); }";
+
+/// Mean time of a run in a new interpreter, after one run to warm up
+fn measure(
+    run: fn(String, &mut Interpreter) -> Result<(), rlox_interpreter::RunError>,
+) -> Duration {
+    run(SOURCE.to_string(), &mut Interpreter::new()).expect("the benchmark script runs");
+    let started = Instant::now();
+    for _ in 0..RUNS {
+        run(SOURCE.to_string(), &mut Interpreter::new()).expect("the benchmark script runs");
+    }
+    started.elapsed() / RUNS
+}
+
+fn main() {
+    let walked = measure(|source, interpreter| run(source, interpreter, false));
+    let compiled = measure(run_compiled);
+    println!("tree walker: {walked:.2?} per run");
+    println!(
+        "closures:    {compiled:.2?} per run ({:.2}x)",
+        walked.as_secs_f64() / compiled.as_secs_f64()
+    );
+}
@@ -0,0 +1,102 @@
+//! Time of each pipeline stage (scanner, parser, resolver, interpreter) and of a whole run
+//! for a few representative programs, measured by criterion. Run with
+//! `cargo bench --bench pipeline`, the reports are written to `target/criterion`.
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rlox_interpreter::{interpreter::Interpreter, run, run_timed, StageTimings};
+
+const PROGRAMS: [(&str, &str); 4] = [
+    (
+        "fib",
+        "fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+print fib(22);",
+    ),
+    (
+        "string building",
+        "var text = \"\";
+for (var i = 0; i < 5000; i = i + 1) { text = text + \"line \" + i + \"\\n\"; }
+print len(text);",
+    ),
+    (
+        "class-heavy",
+        "class Shape { init(size) { this.size = size; } area() { return 0; } }
+class Square < Shape { area() { return this.size * this.size + super.area(); } }
+class Circle < Shape { area() { return 3 * this.size * this.size; } }
+var total = 0;
+for (var i = 0; i < 20000; i = i + 1) {
+  var shape = Square(i);
+  if (i > 10000) shape = Circle(i);
+  total = total + shape.area() + shape.size;
+}
+print total;",
+    ),
+    (
+        "deep closures",
+        "fun level(depth, value) {
+  fun leaf() { return value; }
+  if (depth == 0) return leaf;
+  var inner = level(depth - 1, value + 1);
+  fun add() { return inner() + depth; }
+  return add;
+}
+var sum = 0;
+for (var i = 0; i < 200; i = i + 1) { sum = sum + level(100, i)(); }
+print sum;",
+    ),
+];
+
+/// Reads the time of one stage
+type Stage = fn(&StageTimings) -> Duration;
+
+/// Time spent in one stage over `iterations` runs, in a new interpreter each time
+fn measure_stage(source: &str, iterations: u64, stage: Stage) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..iterations {
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        let (result, timings) = run_timed(source.to_string(), &mut interpreter, false);
+        result.expect("the benchmark program runs");
+        total += stage(&timings);
+    }
+    total
+}
+
+fn pipeline(criterion: &mut Criterion) {
+    let stages: [(&str, Stage); 4] = [
+        ("scan", |timings| timings.scan),
+        ("parse", |timings| timings.parse),
+        ("resolve", |timings| timings.resolve),
+        ("execute", |timings| timings.execute),
+    ];
+    for (name, source) in PROGRAMS {
+        let mut group = criterion.benchmark_group(name);
+        for (stage_name, stage) in stages {
+            group.bench_function(stage_name, |bencher| {
+                bencher.iter_custom(|iterations| measure_stage(source, iterations, stage))
+            });
+        }
+        // A whole run without the stopwatch between the stages
+        group.bench_function("end to end", |bencher| {
+            bencher.iter_custom(|iterations| {
+                let mut total = Duration::ZERO;
+                for _ in 0..iterations {
+                    let mut interpreter = Interpreter::new();
+                    interpreter.capture_output();
+                    let started = Instant::now();
+                    black_box(run(source.to_string(), &mut interpreter, false))
+                        .expect("the benchmark program runs");
+                    total += started.elapsed();
+                }
+                total
+            })
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, pipeline);
+criterion_main!(benches);
@@ -0,0 +1,25 @@
+//! Scanning time of a keyword-heavy source, run with `cargo bench --bench scanner`.
+//! Only the standard library is used, so the numbers are rough: compare runs on one machine.
+use std::{hint::black_box, time::Instant};
+
+use rlox_interpreter::scanner::Scanner;
+
+const RUNS: u32 = 20;
+
+fn main() {
+    let line = "class A < B { fun f(a, b) { if (a and b or nil) return this; \
+                else while (true) break; var x = false; print super.f; } }\n";
+    let source = line.repeat(2_000);
+    // The first run warms up the allocator and the caches
+    black_box(Scanner::new(source.clone()).scan_tokens());
+
+    let started = Instant::now();
+    for _ in 0..RUNS {
+        black_box(Scanner::new(black_box(source.clone())).scan_tokens());
+    }
+    let elapsed = started.elapsed() / RUNS;
+    println!(
+        "scan keyword-heavy source ({} KiB): {elapsed:.2?} per run",
+        source.len() / 1024
+    );
+}
@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rlox_interpreter::{error::capture_diagnostics, parser::Parser, scanner::Scanner};
+
+// Run with `cargo +nightly fuzz run parser -- -timeout=5` so infinite loops are reported too
+fuzz_target!(|data: &[u8]| {
+    let source = String::from_utf8_lossy(data).to_string();
+    capture_diagnostics(|| {
+        let tokens = Scanner::new(source).scan_tokens();
+        Parser::new(tokens.clone()).parse();
+        Parser::new(tokens.clone()).parse_repl();
+        let _ = Parser::new(tokens).parse_expression();
+    });
+});
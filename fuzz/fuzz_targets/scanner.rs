@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rlox_interpreter::{error::capture_diagnostics, scanner::Scanner};
+
+// Any input has to be scanned into tokens ending with EOF, reporting errors instead of panicking
+fuzz_target!(|data: &[u8]| {
+    let source = String::from_utf8_lossy(data).to_string();
+    capture_diagnostics(|| {
+        let tokens = Scanner::new(source).scan_tokens();
+        assert!(tokens.last().is_some_and(|token| token.lexeme.is_empty()));
+    });
+});
@@ -1,108 +1,265 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, Write};
 
-#[derive(Debug, Clone)]
-struct TreeType {
-    struct_name: String,
-    fields: Vec<String>,
+/// Fields added to every node, assigned by its generated `new` constructor
+const NODE_FIELDS: [(&str, &str); 2] = [("uid", "usize"), ("span", "Span")];
+
+/// An enum of syntax tree nodes (`Expr`, `Stmt`) declared in the grammar file
+#[derive(Debug, Clone, PartialEq)]
+pub struct AstBase {
+    pub name: String,
+    /// The nodes are compared and hashed by their `uid`
+    pub require_hash: bool,
+    pub tree_types: Vec<TreeType>,
 }
 
-pub fn define_ast(
-    output_dir: &str,
-    base_name: String,
-    tree_types: &[String],
-    require_hash: bool,
-) -> io::Result<()> {
-    let path = format!("{output_dir}/{}.rs", base_name.to_lowercase());
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeType {
+    pub struct_name: String,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub field_type: String,
+}
+
+/// Reads the grammar at `grammar_path` and writes one `<base>.rs` file per base in `output_dir`,
+/// plus `walker.rs` with a visitor that goes through all of them, `ast_printer.rs` and `ast_codec.rs`
+pub fn generate(grammar_path: &str, output_dir: &str) -> io::Result<()> {
+    let grammar = fs::read_to_string(grammar_path)?;
+    let bases = parse_grammar(&grammar).map_err(|message| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{grammar_path}: {message}"),
+        )
+    })?;
+    for base in &bases {
+        define_ast(output_dir, base, &bases)?;
+    }
+    let mut file = File::create(format!("{output_dir}/walker.rs"))?;
+    write_walker(&mut file, &bases)?;
+    let mut file = File::create(format!("{output_dir}/ast_printer.rs"))?;
+    write_printer(&mut file, &bases)?;
+    let mut file = File::create(format!("{output_dir}/ast_codec.rs"))?;
+    write_codec(&mut file, &bases)
+}
+
+/// Parses the bases of a grammar, `#` starts a comment:
+///
+/// ```text
+/// base Expr hash
+/// Binary : usize uid, Box<Expr> left, Token operator, Box<Expr> right
+/// ```
+pub fn parse_grammar(grammar: &str) -> Result<Vec<AstBase>, String> {
+    let mut bases: Vec<AstBase> = Vec::new();
+    for (idx, line) in grammar.lines().enumerate() {
+        let line_error = |message: String| format!("line {}: {message}", idx + 1);
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(declaration) = line.strip_prefix("base ") {
+            let base = match declaration.split_whitespace().collect::<Vec<_>>()[..] {
+                [name] => (name, false),
+                [name, "hash"] => (name, true),
+                _ => return Err(line_error(format!("Invalid base '{line}'."))),
+            };
+            bases.push(AstBase {
+                name: base.0.to_string(),
+                require_hash: base.1,
+                tree_types: Vec::new(),
+            });
+            continue;
+        }
+        let Some(base) = bases.last_mut() else {
+            return Err(line_error(
+                "Expect 'base <Name>' before the first node.".to_string(),
+            ));
+        };
+        let tree_type = parse_tree_type(line).map_err(line_error)?;
+        if let Some(field) = tree_type
+            .fields
+            .iter()
+            .find(|field| NODE_FIELDS.iter().any(|(name, _)| field.name == *name))
+        {
+            return Err(line_error(format!(
+                "'{}' is added to every node, remove it from '{}'.",
+                field.name, tree_type.struct_name
+            )));
+        }
+        if base
+            .tree_types
+            .iter()
+            .any(|other| other.struct_name == tree_type.struct_name)
+        {
+            return Err(line_error(format!(
+                "'{}' is declared twice.",
+                tree_type.struct_name
+            )));
+        }
+        base.tree_types.push(tree_type);
+    }
+    Ok(bases)
+}
+
+/// `Name : Type field, Type field`, a type can't contain spaces
+fn parse_tree_type(line: &str) -> Result<TreeType, String> {
+    let Some((tree_name, fields)) = line.split_once(':') else {
+        return Err(format!("Expect ':' after the node name in '{line}'."));
+    };
+    let struct_name = tree_name.trim();
+    if !is_identifier(struct_name) {
+        return Err(format!("Invalid node name '{struct_name}'."));
+    }
+    let fields = fields
+        .split(',')
+        .map(
+            |field| match field.split_whitespace().collect::<Vec<_>>()[..] {
+                [field_type, name] if is_identifier(name) => Ok(Field {
+                    name: name.to_string(),
+                    field_type: field_type.to_string(),
+                }),
+                _ => Err(format!(
+                    "Invalid field '{}' in '{struct_name}', expect '<Type> <name>'.",
+                    field.trim()
+                )),
+            },
+        )
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(TreeType {
+        struct_name: struct_name.to_string(),
+        fields,
+    })
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Writes `<base>.rs` in `output_dir`, the other bases of the grammar are imported when used
+pub fn define_ast(output_dir: &str, base: &AstBase, bases: &[AstBase]) -> io::Result<()> {
+    let path = format!("{output_dir}/{}.rs", base.name.to_lowercase());
     let mut file = File::create(path).expect("Failed to create file on specified location");
+    write_ast(&mut file, base, bases)
+}
 
-    writeln!(&mut file, "use crate::token::*;")?;
-    writeln!(&mut file, "use crate::object::*;")?;
-    if base_name.to_lowercase().contains("stmt") {
-        writeln!(&mut file, "use crate::expr::*;")?;
+/// The source of the module of `base`
+pub fn write_ast(file: &mut impl Write, base: &AstBase, bases: &[AstBase]) -> io::Result<()> {
+    writeln!(file, "use crate::ast::*;")?;
+    writeln!(file, "use crate::token::*;")?;
+    if uses_type(base, "Object") {
+        writeln!(file, "use crate::object::*;")?;
+    }
+    for other in bases.iter().filter(|other| other.name != base.name) {
+        if uses_type(base, &other.name) {
+            writeln!(file, "use crate::{}::*;", other.name.to_lowercase())?;
+        }
     }
-    if require_hash {
-        writeln!(&mut file, "use std::hash::Hash;")?;
+    if base.require_hash {
+        writeln!(file, "use std::hash::Hash;")?;
     }
-    writeln!(&mut file)?;
+    writeln!(file)?;
 
     // define Visitor trait
-    define_visitor(&mut file, &base_name, tree_types)?;
+    define_visitor(file, base)?;
 
     // define Base
-    define_base(&mut file, &base_name, tree_types)?;
-    writeln!(&mut file)?;
-
-    for tree_type in tree_types {
-        // Get the tree name and the fields
-        let (tree_name, fields) = tree_type.split_once(':').unwrap();
-        // Struct name
-        let struct_name = tree_name.trim().to_string();
-        // Struct fields
-        let field_vec: Vec<String> = fields.split(',').map(str::to_string).collect();
-        // Define Struct
-        define_type(
-            &mut file,
-            &base_name.trim(),
-            TreeType {
-                struct_name,
-                fields: field_vec,
-            },
-        )?
+    define_base(file, base)?;
+    writeln!(file)?;
+
+    for tree_type in &base.tree_types {
+        define_type(file, &base.name, tree_type)?;
+        impl_constructor(file, &base.name, tree_type, bases)?;
     }
 
     // Implement Base Type
-    impl_base_type(&mut file, &base_name, tree_types, require_hash)?;
-    if require_hash {
-        impl_partial_eq_hash(&mut file, &base_name)?;
+    impl_base_type(file, base)?;
+    impl_spanned(file, base)?;
+    if base.require_hash {
+        impl_partial_eq_hash(file, &base.name)?;
     }
-    writeln!(&mut file)?;
+    writeln!(file)?;
 
     Ok(())
 }
 
-fn define_visitor(file: &mut File, base_name: &str, tree_types: &[String]) -> io::Result<()> {
-    writeln!(file, "pub trait {}Visitor<T> {{", base_name)?;
-    for tree_type in tree_types {
-        let (tree_name, _) = tree_type.split_once(':').unwrap();
+/// Whether a field of any node mentions the type `name`, e.g. `Option<Box<Expr>>` uses `Expr`
+fn uses_type(base: &AstBase, name: &str) -> bool {
+    base.tree_types
+        .iter()
+        .flat_map(|tree_type| &tree_type.fields)
+        .any(|field| field_uses_type(field, name))
+}
+
+fn field_uses_type(field: &Field, name: &str) -> bool {
+    field
+        .field_type
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .any(|part| part == name)
+}
+
+/// Fields made of tokens or nodes know where they are, the span of a node covers them
+/// `ForIn` becomes `for_in` in the names of the visitor methods and constructors
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (idx, char) in name.chars().enumerate() {
+        if char.is_uppercase() && idx > 0 {
+            snake.push('_');
+        }
+        snake.extend(char.to_lowercase());
+    }
+    snake
+}
+
+fn is_spanned(field: &Field, bases: &[AstBase]) -> bool {
+    field_uses_type(field, "Token") || bases.iter().any(|base| field_uses_type(field, &base.name))
+}
+
+fn define_visitor(file: &mut impl Write, base: &AstBase) -> io::Result<()> {
+    writeln!(file, "pub trait {}Visitor<T> {{", base.name)?;
+    for tree_type in &base.tree_types {
         writeln!(
             file,
             "    fn visit_{}_{}(&mut self, {}: &{}{}) -> T;",
-            tree_name.trim().to_lowercase(),
-            base_name.trim().to_lowercase(),
-            base_name.trim().to_lowercase(),
-            tree_name.trim(),
-            base_name.trim(),
+            snake_case(&tree_type.struct_name),
+            base.name.to_lowercase(),
+            base.name.to_lowercase(),
+            tree_type.struct_name,
+            base.name,
         )?;
     }
     writeln!(file, "}}")?;
     Ok(())
 }
 
-fn define_base(file: &mut File, base_name: &str, tree_types: &[String]) -> io::Result<()> {
+fn define_base(file: &mut impl Write, base: &AstBase) -> io::Result<()> {
     writeln!(file, "#[derive(Debug, Clone)]")?;
-    writeln!(file, "pub enum {} {{", base_name)?;
-    for tree_type in tree_types {
-        let (tree_name, _) = tree_type.split_once(':').unwrap();
+    writeln!(file, "pub enum {} {{", base.name)?;
+    for tree_type in &base.tree_types {
         writeln!(
             file,
             "    {}({}{}),",
-            tree_name.trim(),
-            tree_name.trim(),
-            base_name
+            tree_type.struct_name, tree_type.struct_name, base.name
         )?;
     }
     writeln!(file, "}}")?;
     Ok(())
 }
 
-fn define_type(file: &mut File, base_name: &str, tree_type: TreeType) -> io::Result<()> {
+fn define_type(file: &mut impl Write, base_name: &str, tree_type: &TreeType) -> io::Result<()> {
     // Define Struct type
     writeln!(file, "#[derive(Debug, Clone)]")?;
     writeln!(file, "pub struct {}{} {{", tree_type.struct_name, base_name)?;
-    for field in tree_type.fields {
-        let (field_type, field_name) = field.trim().split_once(' ').unwrap();
-        writeln!(file, "    pub {}: {},", field_name, field_type)?;
+    for (name, field_type) in NODE_FIELDS {
+        writeln!(file, "    pub {}: {},", name, field_type)?;
+    }
+    for field in &tree_type.fields {
+        writeln!(file, "    pub {}: {},", field.name, field.field_type)?;
     }
 
     writeln!(file, "}}",)?;
@@ -110,46 +267,135 @@ fn define_type(file: &mut File, base_name: &str, tree_type: TreeType) -> io::Res
     Ok(())
 }
 
-fn impl_base_type(
-    file: &mut File,
+/// `new` takes the declared fields, gives the node a fresh uid and the span covering its fields
+fn impl_constructor(
+    file: &mut impl Write,
     base_name: &str,
-    tree_types: &[String],
-    require_hash: bool,
+    tree_type: &TreeType,
+    bases: &[AstBase],
 ) -> io::Result<()> {
+    let struct_name = format!("{}{}", tree_type.struct_name, base_name);
+    let spans: Vec<String> = tree_type
+        .fields
+        .iter()
+        .filter(|field| is_spanned(field, bases))
+        .map(|field| format!("{}.span()", field.name))
+        .collect();
+    let span = match spans.is_empty() {
+        true => "Span::default()".to_string(),
+        false => format!("Span::covering([{}])", spans.join(", ")),
+    };
+    writeln!(file, "impl {} {{", struct_name)?;
+    writeln!(
+        file,
+        "    pub fn new({}) -> {} {{",
+        parameters(tree_type),
+        struct_name
+    )?;
+    writeln!(file, "        {} {{", struct_name)?;
+    writeln!(file, "            uid: next_uid(),")?;
+    writeln!(file, "            span: {},", span)?;
+    for field in &tree_type.fields {
+        writeln!(file, "            {},", field.name)?;
+    }
+    writeln!(file, "        }}")?;
+    writeln!(file, "    }}")?;
+    writeln!(file, "}}")?;
+    writeln!(file)?;
+    Ok(())
+}
+
+/// `name: Type, name: Type` of the declared fields
+fn parameters(tree_type: &TreeType) -> String {
+    tree_type
+        .fields
+        .iter()
+        .map(|field| format!("{}: {}", field.name, field.field_type))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn impl_base_type(file: &mut impl Write, base: &AstBase) -> io::Result<()> {
+    let base_name = &base.name;
+    let lower_base_name = base.name.to_lowercase();
     writeln!(file, "impl {} {{", base_name)?;
+    for tree_type in &base.tree_types {
+        let arguments: Vec<&str> = tree_type
+            .fields
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect();
+        writeln!(
+            file,
+            "    pub fn new_{}({}) -> {} {{",
+            snake_case(&tree_type.struct_name),
+            parameters(tree_type),
+            base_name
+        )?;
+        writeln!(
+            file,
+            "        {}::{}({}{}::new({}))",
+            base_name,
+            tree_type.struct_name,
+            tree_type.struct_name,
+            base_name,
+            arguments.join(", ")
+        )?;
+        writeln!(file, "    }}")?;
+        writeln!(file)?;
+    }
+    writeln!(
+        file,
+        "    /// Same node, located at `span` instead of the span of its fields"
+    )?;
+    writeln!(
+        file,
+        "    pub fn with_span(mut self, span: Span) -> {} {{",
+        base_name
+    )?;
+    writeln!(file, "        match &mut self {{",)?;
+    for tree_type in &base.tree_types {
+        writeln!(
+            file,
+            "            {}::{}(node) => node.span = span,",
+            base_name, tree_type.struct_name,
+        )?;
+    }
+    writeln!(file, "        }}",)?;
+    writeln!(file, "        self")?;
+    writeln!(file, "    }}")?;
+    writeln!(file)?;
     writeln!(
         file,
         "    pub fn accept<T>(&self, visitor: &mut dyn {}Visitor<T>) -> T {{",
         base_name
     )?;
     writeln!(file, "        match self {{",)?;
-    for tree_type in tree_types.iter() {
-        let (tree_name, _) = tree_type.split_once(':').unwrap();
+    for tree_type in &base.tree_types {
+        let lower_tree_name = snake_case(&tree_type.struct_name);
         writeln!(
             file,
             "            {}::{}({}_{}) => visitor.visit_{}_{}({}_{}),",
-            base_name.trim(),
-            tree_name.trim(),
-            tree_name.trim().to_lowercase(),
-            base_name.trim().to_lowercase(),
-            tree_name.trim().to_lowercase(),
-            base_name.trim().to_lowercase(),
-            tree_name.trim().to_lowercase(),
-            base_name.trim().to_lowercase(),
+            base_name,
+            tree_type.struct_name,
+            lower_tree_name,
+            lower_base_name,
+            lower_tree_name,
+            lower_base_name,
+            lower_tree_name,
+            lower_base_name,
         )?;
     }
     writeln!(file, "        }}",)?;
     writeln!(file, "    }}")?;
-    if require_hash {
+    if base.require_hash {
         writeln!(file, "    fn get_uid(&self) -> usize {{")?;
         writeln!(file, "        match self {{",)?;
-        for tree_type in tree_types.iter() {
-            let (tree_name, _) = tree_type.split_once(':').unwrap();
+        for tree_type in &base.tree_types {
             writeln!(
                 file,
                 "            {}::{}(expr) => expr.uid,",
-                base_name.trim(),
-                tree_name.trim(),
+                base_name, tree_type.struct_name,
             )?;
         }
         writeln!(file, "        }}",)?;
@@ -160,7 +406,348 @@ fn impl_base_type(
     Ok(())
 }
 
-fn impl_partial_eq_hash(file: &mut File, base_name: &str) -> io::Result<()> {
+/// The `Walker` trait has a default method per base and node that walks the nodes below it.
+/// A single trait covers every base, so nodes of one base can hold nodes of the other.
+pub fn write_walker(file: &mut impl Write, bases: &[AstBase]) -> io::Result<()> {
+    for base in bases {
+        writeln!(file, "use crate::{}::*;", base.name.to_lowercase())?;
+    }
+    writeln!(file)?;
+
+    writeln!(
+        file,
+        "/// Goes through every node below the one it starts from. A pass overrides the methods of"
+    )?;
+    writeln!(
+        file,
+        "/// the nodes it cares about, and calls the matching `default_walk_*` to go on deeper."
+    )?;
+    writeln!(file, "pub trait Walker {{")?;
+    for base in bases {
+        let lower_base_name = base.name.to_lowercase();
+        writeln!(
+            file,
+            "    fn walk_{0}(&mut self, {0}: &{1}) {{",
+            lower_base_name, base.name
+        )?;
+        writeln!(file, "        default_walk_{0}(self, {0})", lower_base_name)?;
+        writeln!(file, "    }}")?;
+        writeln!(file)?;
+        for tree_type in &base.tree_types {
+            writeln!(
+                file,
+                "    fn walk_{0}_{1}(&mut self, {1}: &{2}{3}) {{",
+                snake_case(&tree_type.struct_name),
+                lower_base_name,
+                tree_type.struct_name,
+                base.name
+            )?;
+            writeln!(
+                file,
+                "        default_walk_{}_{1}(self, {1})",
+                snake_case(&tree_type.struct_name),
+                lower_base_name
+            )?;
+            writeln!(file, "    }}")?;
+            writeln!(file)?;
+        }
+    }
+    writeln!(file, "}}")?;
+
+    for base in bases {
+        let lower_base_name = base.name.to_lowercase();
+        writeln!(file)?;
+        writeln!(
+            file,
+            "pub fn default_walk_{0}<W: Walker + ?Sized>(walker: &mut W, {0}: &{1}) {{",
+            lower_base_name, base.name
+        )?;
+        writeln!(file, "    match {} {{", lower_base_name)?;
+        for tree_type in &base.tree_types {
+            let lower_tree_name = snake_case(&tree_type.struct_name);
+            writeln!(
+                file,
+                "        {}::{}({2}_{3}) => walker.walk_{2}_{3}({2}_{3}),",
+                base.name, tree_type.struct_name, lower_tree_name, lower_base_name
+            )?;
+        }
+        writeln!(file, "    }}")?;
+        writeln!(file, "}}")?;
+
+        for tree_type in &base.tree_types {
+            let lines: Vec<String> = tree_type
+                .fields
+                .iter()
+                .filter_map(|field| {
+                    let value = format!("&{}.{}", lower_base_name, field.name);
+                    walk_field(&value, &field.field_type, bases, 1)
+                })
+                .flatten()
+                .collect();
+            // Nothing to walk into, the parameters are unused
+            let unused = match lines.is_empty() {
+                true => "_",
+                false => "",
+            };
+            writeln!(file)?;
+            write!(
+                file,
+                "pub fn default_walk_{}_{}<W: Walker + ?Sized>({unused}walker: &mut W, {unused}{}: &{}{}) {{",
+                snake_case(&tree_type.struct_name),
+                lower_base_name,
+                lower_base_name,
+                tree_type.struct_name,
+                base.name
+            )?;
+            if !lines.is_empty() {
+                writeln!(file)?;
+            }
+            for line in lines {
+                writeln!(file, "{line}")?;
+            }
+            writeln!(file, "}}")?;
+        }
+    }
+    Ok(())
+}
+
+/// `AstPrinter` prints any node as an s-expression with the names of its fields,
+/// `(unary :operator - :right (literal :value 1))`
+pub fn write_printer(file: &mut impl Write, bases: &[AstBase]) -> io::Result<()> {
+    for base in bases {
+        writeln!(file, "use crate::{}::*;", base.name.to_lowercase())?;
+    }
+    writeln!(file)?;
+
+    writeln!(
+        file,
+        "/// Prints a syntax tree as an s-expression, every node with the names of its fields"
+    )?;
+    writeln!(file, "#[derive(Default)]")?;
+    writeln!(file, "pub struct AstPrinter;")?;
+    writeln!(file)?;
+    writeln!(file, "impl AstPrinter {{")?;
+    writeln!(file, "    pub fn new() -> AstPrinter {{")?;
+    writeln!(file, "        AstPrinter {{}}")?;
+    writeln!(file, "    }}")?;
+    for base in bases {
+        let lower_base_name = base.name.to_lowercase();
+        writeln!(file)?;
+        writeln!(
+            file,
+            "    pub fn print_{0}(&mut self, {0}: &{1}) -> String {{",
+            lower_base_name, base.name
+        )?;
+        writeln!(file, "        {}.accept(self)", lower_base_name)?;
+        writeln!(file, "    }}")?;
+    }
+    writeln!(file, "}}")?;
+
+    for base in bases {
+        let lower_base_name = base.name.to_lowercase();
+        writeln!(file)?;
+        writeln!(file, "impl {}Visitor<String> for AstPrinter {{", base.name)?;
+        for (idx, tree_type) in base.tree_types.iter().enumerate() {
+            let lower_tree_name = snake_case(&tree_type.struct_name);
+            let template: String = tree_type
+                .fields
+                .iter()
+                .map(|field| match field.field_type.starts_with("Vec<") {
+                    true => format!(" :{} [{{}}]", field.name),
+                    false => format!(" :{} {{}}", field.name),
+                })
+                .collect();
+            if idx > 0 {
+                writeln!(file)?;
+            }
+            writeln!(
+                file,
+                "    fn visit_{0}_{1}(&mut self, {1}: &{2}{3}) -> String {{",
+                lower_tree_name, lower_base_name, tree_type.struct_name, base.name
+            )?;
+            writeln!(file, "        format!(")?;
+            writeln!(file, "            \"({lower_tree_name}{template})\",")?;
+            for field in &tree_type.fields {
+                let place = format!("{}.{}", lower_base_name, field.name);
+                writeln!(
+                    file,
+                    "            {},",
+                    print_field(&place, &field.field_type, bases)
+                )?;
+            }
+            writeln!(file, "        )")?;
+            writeln!(file, "    }}")?;
+        }
+        writeln!(file, "}}")?;
+    }
+    Ok(())
+}
+
+/// `Encode` and `Decode` of every base, the traits and the encoding of the field types live
+/// in `crate::cache`. A node is its span, the index of its kind and its fields in order.
+/// The nodes of a `hash` base are map keys, `expr_key` (for `Expr`) saves what the map
+/// holds for them after the fields and puts it back for the decoded node.
+pub fn write_codec(file: &mut impl Write, bases: &[AstBase]) -> io::Result<()> {
+    writeln!(file, "use crate::ast::*;")?;
+    writeln!(file, "use crate::cache::*;")?;
+    for base in bases {
+        writeln!(file, "use crate::{}::*;", base.name.to_lowercase())?;
+    }
+    for base in bases {
+        let lower_base_name = base.name.to_lowercase();
+        writeln!(file)?;
+        writeln!(file, "impl Encode for {} {{", base.name)?;
+        writeln!(file, "    fn encode(&self, encoder: &mut Encoder) {{")?;
+        writeln!(file, "        self.span().encode(encoder);")?;
+        writeln!(file, "        match self {{")?;
+        for (idx, tree_type) in base.tree_types.iter().enumerate() {
+            writeln!(
+                file,
+                "            {}::{}(node) => {{",
+                base.name, tree_type.struct_name
+            )?;
+            writeln!(file, "                encoder.tag({idx});")?;
+            for field in &tree_type.fields {
+                writeln!(file, "                node.{}.encode(encoder);", field.name)?;
+            }
+            writeln!(file, "            }}")?;
+        }
+        writeln!(file, "        }}")?;
+        if base.require_hash {
+            writeln!(file, "        encoder.{lower_base_name}_key(self);")?;
+        }
+        writeln!(file, "    }}")?;
+        writeln!(file, "}}")?;
+        writeln!(file)?;
+        writeln!(file, "impl Decode for {} {{", base.name)?;
+        writeln!(
+            file,
+            "    fn decode(decoder: &mut Decoder) -> Result<{}, String> {{",
+            base.name
+        )?;
+        writeln!(file, "        let span = Span::decode(decoder)?;")?;
+        writeln!(file, "        let node = match decoder.tag()? {{")?;
+        for (idx, tree_type) in base.tree_types.iter().enumerate() {
+            let arguments = vec!["Decode::decode(decoder)?"; tree_type.fields.len()];
+            writeln!(
+                file,
+                "            {idx} => {}::new_{}({}),",
+                base.name,
+                snake_case(&tree_type.struct_name),
+                arguments.join(", ")
+            )?;
+        }
+        writeln!(
+            file,
+            "            tag => return Err(format!(\"Unknown {} node {{tag}}.\")),",
+            base.name
+        )?;
+        writeln!(file, "        }};")?;
+        match base.require_hash {
+            true => writeln!(
+                file,
+                "        decoder.{lower_base_name}_key(node.with_span(span))"
+            )?,
+            false => writeln!(file, "        Ok(node.with_span(span))")?,
+        }
+        writeln!(file, "    }}")?;
+        writeln!(file, "}}")?;
+    }
+    Ok(())
+}
+
+/// Expression printing the value at `place` of type `field_type`: nodes as s-expressions,
+/// tokens by their lexeme and a missing optional value as `nil`. The items of a list are
+/// joined, the caller puts the `[]` around them.
+fn print_field(place: &str, field_type: &str, bases: &[AstBase]) -> String {
+    // `item` is already a reference
+    let reference = match place {
+        "item" => place.to_string(),
+        _ => format!("&{place}"),
+    };
+    if let Some(base) = bases.iter().find(|base| base.name == field_type) {
+        return format!("self.print_{}({reference})", base.name.to_lowercase());
+    }
+    if field_type == "Token" {
+        return format!("{place}.lexeme");
+    }
+    let wrapped = field_type
+        .strip_suffix('>')
+        .and_then(|field_type| field_type.split_once('<'));
+    match wrapped {
+        Some(("Box", inner)) => print_field(place, inner, bases),
+        Some(("Option", inner)) => format!(
+            "match {reference} {{ Some(item) => {}, None => \"nil\".to_string() }}",
+            print_nested("item", inner, bases)
+        ),
+        Some(("Vec", inner)) => format!(
+            "{place}.iter().map(|item| {}).collect::<Vec<_>>().join(\" \")",
+            print_nested("item", inner, bases)
+        ),
+        _ => place.to_string(),
+    }
+}
+
+/// Like `print_field` but always a `String`, with the `[]` around a list
+fn print_nested(place: &str, field_type: &str, bases: &[AstBase]) -> String {
+    let printed = print_field(place, field_type, bases);
+    match (
+        field_type.starts_with("Vec<"),
+        field_type == "Token" || printed == place,
+    ) {
+        (true, _) => format!("format!(\"[{{}}]\", {printed})"),
+        (false, true) => format!("{printed}.to_string()"),
+        (false, false) => printed,
+    }
+}
+
+/// Statements walking into `value` of type `field_type`, through `Box`, `Option` and `Vec`.
+/// `None` when it holds no node.
+fn walk_field(
+    value: &str,
+    field_type: &str,
+    bases: &[AstBase],
+    depth: usize,
+) -> Option<Vec<String>> {
+    let indent = "    ".repeat(depth);
+    if let Some(base) = bases.iter().find(|base| base.name == field_type) {
+        return Some(vec![format!(
+            "{indent}walker.walk_{}({value});",
+            base.name.to_lowercase()
+        )]);
+    }
+    let (wrapper, inner) = field_type.strip_suffix('>')?.split_once('<')?;
+    let (head, inner_value) = match wrapper {
+        "Box" => return walk_field(value, inner, bases, depth),
+        "Option" => (format!("{indent}if let Some(item) = {value} {{"), "item"),
+        "Vec" => (format!("{indent}for item in {value} {{"), "item"),
+        _ => return None,
+    };
+    let mut lines = vec![head];
+    lines.extend(walk_field(inner_value, inner, bases, depth + 1)?);
+    lines.push(format!("{indent}}}"));
+    Some(lines)
+}
+
+fn impl_spanned(file: &mut impl Write, base: &AstBase) -> io::Result<()> {
+    writeln!(file, "impl Spanned for {} {{", base.name)?;
+    writeln!(file, "    fn span(&self) -> Span {{")?;
+    writeln!(file, "        match self {{",)?;
+    for tree_type in &base.tree_types {
+        writeln!(
+            file,
+            "            {}::{}(node) => node.span,",
+            base.name, tree_type.struct_name,
+        )?;
+    }
+    writeln!(file, "        }}",)?;
+    writeln!(file, "    }}")?;
+    writeln!(file, "}}")?;
+    writeln!(file)?;
+    Ok(())
+}
+
+fn impl_partial_eq_hash(file: &mut impl Write, base_name: &str) -> io::Result<()> {
     writeln!(file, "impl PartialEq for {} {{", base_name)?;
     writeln!(file, "    fn eq(&self, other: &Self) -> bool {{")?;
     writeln!(file, "        self.get_uid() == other.get_uid()",)?;
@@ -183,3 +770,249 @@ fn impl_partial_eq_hash(file: &mut File, base_name: &str) -> io::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod generate_ast_tests {
+    use super::*;
+
+    const GRAMMAR: &str = "# Statements
+base Stmt
+Print : Token keyword, Box<Expr> expression
+
+base Expr hash
+Literal : Object value  # a constant
+";
+
+    fn generated(base: &AstBase, bases: &[AstBase]) -> String {
+        let mut output = Vec::new();
+        write_ast(&mut output, base, bases).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_parse_grammar() {
+        // Arrange
+        let expected_literal = TreeType {
+            struct_name: "Literal".to_string(),
+            fields: vec![Field {
+                name: "value".to_string(),
+                field_type: "Object".to_string(),
+            }],
+        };
+        // Act
+        let bases = parse_grammar(GRAMMAR).unwrap();
+        // Assert
+        assert_eq!(bases.len(), 2);
+        assert_eq!(
+            (bases[0].name.as_str(), bases[0].require_hash),
+            ("Stmt", false)
+        );
+        assert_eq!(bases[0].tree_types[0].fields[1].field_type, "Box<Expr>");
+        assert_eq!(
+            (bases[1].name.as_str(), bases[1].require_hash),
+            ("Expr", true)
+        );
+        assert_eq!(bases[1].tree_types, vec![expected_literal]);
+    }
+
+    #[test]
+    fn test_snake_case() {
+        // Act
+        let names: Vec<String> = ["Literal", "ForIn"]
+            .iter()
+            .map(|name| snake_case(name))
+            .collect();
+        // Assert
+        assert_eq!(names, ["literal", "for_in"]);
+    }
+
+    #[test]
+    fn test_invalid_grammar() {
+        // Arrange
+        let grammars = [
+            "Print : Token keyword",
+            "base Stmt\nPrint Token keyword",
+            "base Stmt\nPrint : Token",
+            "base Expr hash\nLiteral : usize uid, Object value",
+            "base Stmt\nBreak : Token keyword\nBreak : Token keyword",
+            "base Stmt Expr",
+        ];
+        // Act
+        let errors: Vec<String> = grammars
+            .iter()
+            .map(|grammar| parse_grammar(grammar).unwrap_err())
+            .collect();
+        // Assert
+        assert_eq!(
+            errors,
+            vec![
+                "line 1: Expect 'base <Name>' before the first node.",
+                "line 2: Expect ':' after the node name in 'Print Token keyword'.",
+                "line 2: Invalid field 'Token' in 'Print', expect '<Type> <name>'.",
+                "line 2: 'uid' is added to every node, remove it from 'Literal'.",
+                "line 3: 'Break' is declared twice.",
+                "line 1: Invalid base 'base Stmt Expr'.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_ast_imports_and_visitor() {
+        // Arrange
+        let bases = parse_grammar(GRAMMAR).unwrap();
+        // Act
+        let stmt = generated(&bases[0], &bases);
+        let expr = generated(&bases[1], &bases);
+        // Assert
+        assert!(
+            stmt.starts_with("use crate::ast::*;\nuse crate::token::*;\nuse crate::expr::*;\n\n")
+        );
+        assert!(stmt.contains("    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> T;"));
+        assert!(stmt.contains(
+            "pub struct PrintStmt {\n    pub uid: usize,\n    pub span: Span,\n    pub keyword: Token,\n    pub expression: Box<Expr>,\n}"
+        ));
+        assert!(!stmt.contains("impl Hash"));
+        assert!(expr.starts_with(
+            "use crate::ast::*;\nuse crate::token::*;\nuse crate::object::*;\nuse std::hash::Hash;\n\n"
+        ));
+        assert!(expr.contains(
+            "            Expr::Literal(literal_expr) => visitor.visit_literal_expr(literal_expr),"
+        ));
+        assert!(expr.contains("            Expr::Literal(expr) => expr.uid,"));
+        assert!(expr.contains("impl Hash for Expr {"));
+    }
+
+    #[test]
+    fn test_write_ast_constructors() {
+        // Arrange
+        let bases = parse_grammar(GRAMMAR).unwrap();
+        // Act
+        let stmt = generated(&bases[0], &bases);
+        let expr = generated(&bases[1], &bases);
+        // Assert
+        assert!(stmt.contains(
+            "impl PrintStmt {
+    pub fn new(keyword: Token, expression: Box<Expr>) -> PrintStmt {
+        PrintStmt {
+            uid: next_uid(),
+            span: Span::covering([keyword.span(), expression.span()]),
+            keyword,
+            expression,
+        }
+    }
+}"
+        ));
+        assert!(stmt.contains(
+            "    pub fn new_print(keyword: Token, expression: Box<Expr>) -> Stmt {
+        Stmt::Print(PrintStmt::new(keyword, expression))
+    }"
+        ));
+        assert!(stmt.contains("            Stmt::Print(node) => node.span,"));
+        // Nothing to take the span of, the parser sets it with `with_span`
+        assert!(expr.contains("            span: Span::default(),\n            value,"));
+        assert!(expr.contains("            Expr::Literal(node) => node.span = span,"));
+    }
+
+    #[test]
+    fn test_write_walker() {
+        // Arrange
+        let bases = parse_grammar(
+            "base Stmt
+If : Token keyword, Box<Expr> condition, Option<Box<Stmt>> else_branch, Vec<Stmt> body
+base Expr
+Literal : Object value",
+        )
+        .unwrap();
+        let mut output = Vec::new();
+        // Act
+        write_walker(&mut output, &bases).unwrap();
+        let walker = String::from_utf8(output).unwrap();
+        // Assert
+        assert!(walker.starts_with("use crate::stmt::*;\nuse crate::expr::*;\n"));
+        assert!(walker.contains(
+            "    fn walk_if_stmt(&mut self, stmt: &IfStmt) {
+        default_walk_if_stmt(self, stmt)
+    }"
+        ));
+        assert!(walker.contains(
+            "pub fn default_walk_if_stmt<W: Walker + ?Sized>(walker: &mut W, stmt: &IfStmt) {
+    walker.walk_expr(&stmt.condition);
+    if let Some(item) = &stmt.else_branch {
+        walker.walk_stmt(item);
+    }
+    for item in &stmt.body {
+        walker.walk_stmt(item);
+    }
+}"
+        ));
+        assert!(walker.contains(
+            "pub fn default_walk_literal_expr<W: Walker + ?Sized>(_walker: &mut W, _expr: &LiteralExpr) {}\n"
+        ));
+    }
+
+    #[test]
+    fn test_write_codec() {
+        // Arrange
+        let bases = parse_grammar(GRAMMAR).unwrap();
+        let mut output = Vec::new();
+        // Act
+        write_codec(&mut output, &bases).unwrap();
+        let codec = String::from_utf8(output).unwrap();
+        // Assert
+        assert!(codec.starts_with(
+            "use crate::ast::*;\nuse crate::cache::*;\nuse crate::stmt::*;\nuse crate::expr::*;\n"
+        ));
+        assert!(codec.contains(
+            "            Stmt::Print(node) => {
+                encoder.tag(0);
+                node.keyword.encode(encoder);
+                node.expression.encode(encoder);
+            }"
+        ));
+        assert!(codec.contains(
+            "            0 => Stmt::new_print(Decode::decode(decoder)?, Decode::decode(decoder)?),"
+        ));
+        assert!(!codec.contains("encoder.stmt_key"));
+        assert!(codec.contains("        encoder.expr_key(self);\n"));
+        assert!(codec.contains("        decoder.expr_key(node.with_span(span))\n"));
+    }
+
+    #[test]
+    fn test_write_printer() {
+        // Arrange
+        let bases = parse_grammar(
+            "base Stmt
+Function : Token name, Vec<Token> params, Option<Box<Expr>> value, Vec<Stmt> body
+base Expr
+Literal : Object value",
+        )
+        .unwrap();
+        let mut output = Vec::new();
+        // Act
+        write_printer(&mut output, &bases).unwrap();
+        let printer = String::from_utf8(output).unwrap();
+        // Assert
+        assert!(printer.contains(
+            "    pub fn print_stmt(&mut self, stmt: &Stmt) -> String {
+        stmt.accept(self)
+    }"
+        ));
+        assert!(printer.contains(
+            "    fn visit_function_stmt(&mut self, stmt: &FunctionStmt) -> String {
+        format!(
+            \"(function :name {} :params [{}] :value {} :body [{}])\",
+            stmt.name.lexeme,
+            stmt.params.iter().map(|item| item.lexeme.to_string()).collect::<Vec<_>>().join(\" \"),
+            match &stmt.value { Some(item) => self.print_expr(item), None => \"nil\".to_string() },
+            stmt.body.iter().map(|item| self.print_stmt(item)).collect::<Vec<_>>().join(\" \"),
+        )
+    }"
+        ));
+        assert!(printer.contains(
+            "        format!(
+            \"(literal :value {})\",
+            expr.value,
+        )"
+        ));
+    }
+}
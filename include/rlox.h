@@ -0,0 +1,38 @@
+/* C interface of the rlox interpreter, built as a cdylib by `cargo build --release`
+ * (target/release/librlox_interpreter.so, .dylib or .dll). */
+#ifndef RLOX_H
+#define RLOX_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Returned by the calls given a null pointer or a string that is not UTF-8 */
+#define RLOX_INVALID_ARGUMENT (-1)
+
+typedef struct RloxInterpreter RloxInterpreter;
+
+/* Native function taking Lox numbers, `user_data` is the pointer given on registration */
+typedef double (*RloxNative)(void *user_data, const double *args, size_t argc);
+
+RloxInterpreter *rlox_new(void);
+void rlox_free(RloxInterpreter *handle);
+
+/* Runs a script, globals of previous runs stay defined.
+ * Returns 0 on success, 65 on compile errors and 70 after a runtime error. */
+int rlox_run(RloxInterpreter *handle, const char *source);
+
+/* Output and diagnostics of the last run, owned by the interpreter and valid until its next run */
+const char *rlox_stdout(const RloxInterpreter *handle);
+const char *rlox_errors(const RloxInterpreter *handle);
+
+int rlox_register_native(RloxInterpreter *handle, const char *name, size_t arity,
+                         RloxNative callback, void *user_data);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
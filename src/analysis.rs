@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::{collect_diagnostics, Diagnostic},
+    incremental::{IncrementalParser, TextEdit},
+    interpreter::Interpreter,
+    resolver::{Reference, Resolver},
+    scanner::Comment,
+    stmt::*,
+    token::{Token, TokenType},
+    type_checker::TypeChecker,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Class,
+    Method,
+    Function,
+    Variable,
+    Parameter,
+}
+
+/// A declaration of the script
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: Token,
+    pub kind: SymbolKind,
+    /// Short signature, e.g. `fun add(a, b)` or `class Circle < Shape`
+    pub detail: String,
+    /// Methods of a class, functions and classes declared inside a function
+    pub children: Vec<Symbol>,
+}
+
+/// What the editor tooling knows about one version of a script: its diagnostics,
+/// declarations and where every variable use was declared.
+/// Lines start at 1 like in the tokens, columns start at 0.
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    /// Outline of the script
+    pub symbols: Vec<Symbol>,
+    pub comments: Vec<Comment>,
+    tokens: Vec<Token>,
+    /// Every declaration (parameters and locals included) by the position of its name
+    declarations: HashMap<(usize, usize), Symbol>,
+    /// Declaration of every resolved variable use by the position of the use
+    definitions: HashMap<(usize, usize), Token>,
+}
+
+impl Analysis {
+    /// Scans, parses and resolves the script without running it
+    pub fn analyze(source: &str) -> Analysis {
+        Analysis::from_parse(&IncrementalParser::new(source))
+    }
+
+    /// Resolves the script an `IncrementalParser` holds, after its latest edit
+    pub fn from_parse(parse: &IncrementalParser) -> Analysis {
+        let mut analysis = Analysis {
+            diagnostics: parse.diagnostics(),
+            tokens: parse.tokens(),
+            comments: parse.comments(),
+            ..Analysis::default()
+        };
+        let statements = parse.statements();
+        let parse_failed = parse.had_error();
+
+        // The statements that failed to parse are missing, resolving the rest still finds
+        // definitions but its errors could be caused by the missing code
+        let mut interpreter = Interpreter::new();
+        let mut references = Vec::new();
+        let resolver_diagnostics = collect_diagnostics(|| {
+            let mut resolver = Resolver::new(&mut interpreter);
+            resolver.resolve(&statements);
+            references = resolver.references;
+            TypeChecker::new(false).check(&statements);
+        });
+        if !parse_failed {
+            analysis.diagnostics.extend(resolver_diagnostics);
+        }
+
+        let mut globals = Vec::new();
+        analysis.symbols = analysis.collect(&statements, None, Some(&mut globals));
+        analysis.link(references, &globals);
+        analysis
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// Kind of the declaration an identifier declares or refers to
+    pub fn symbol_kind(&self, token: &Token) -> Option<SymbolKind> {
+        let key = (token.line, token.column);
+        let declaration = match self.definitions.get(&key) {
+            Some(declaration) => (declaration.line, declaration.column),
+            None => key,
+        };
+        self.declarations
+            .get(&declaration)
+            .map(|symbol| symbol.kind)
+    }
+
+    /// Identifier or keyword under the position
+    pub fn token_at(&self, line: usize, column: usize) -> Option<&Token> {
+        self.tokens.iter().find(|token| {
+            token.line == line
+                && token.column <= column
+                && column < token.column + token.lexeme.chars().count()
+        })
+    }
+
+    /// Declaration of the variable, function or class named at the position
+    pub fn definition(&self, line: usize, column: usize) -> Option<&Token> {
+        let token = self.token_at(line, column)?;
+        let key = (token.line, token.column);
+        match self.declarations.get(&key) {
+            Some(symbol) => Some(&symbol.name),
+            None => self.definitions.get(&key),
+        }
+    }
+
+    /// Markdown describing the declaration named at the position
+    pub fn hover(&self, line: usize, column: usize) -> Option<String> {
+        let declaration = self.definition(line, column)?;
+        let symbol = self
+            .declarations
+            .get(&(declaration.line, declaration.column))?;
+        Some(format!(
+            "```lox\n{}\n```\nDeclared on line {}.",
+            symbol.detail, symbol.name.line
+        ))
+    }
+
+    /// Records every declaration and returns the outline of `statements`.
+    /// `globals` collects the top-level names when the statements are at the top level.
+    fn collect(
+        &mut self,
+        statements: &[Stmt],
+        class: Option<&str>,
+        mut globals: Option<&mut Vec<Token>>,
+    ) -> Vec<Symbol> {
+        let mut outline = Vec::new();
+        for statement in statements {
+            let symbol = match statement {
+                Stmt::Var(stmt) => Symbol {
+                    name: stmt.name.clone(),
+                    kind: SymbolKind::Variable,
+                    detail: format!("var {}", stmt.name.lexeme),
+                    children: Vec::new(),
+                },
+                Stmt::Function(stmt) => {
+                    let (kind, prefix) = match class {
+                        Some(class) => (SymbolKind::Method, format!("{class}.")),
+                        None => (SymbolKind::Function, "fun ".to_string()),
+                    };
+                    let params: Vec<String> =
+                        stmt.params.iter().map(|param| param.lexeme()).collect();
+                    for param in &stmt.params {
+                        self.declare(Symbol {
+                            name: param.clone(),
+                            kind: SymbolKind::Parameter,
+                            detail: format!("parameter {} of {}", param.lexeme, stmt.name.lexeme),
+                            children: Vec::new(),
+                        });
+                    }
+                    Symbol {
+                        name: stmt.name.clone(),
+                        kind,
+                        detail: format!("{prefix}{}({})", stmt.name.lexeme, params.join(", ")),
+                        children: self.collect(&stmt.body, None, None),
+                    }
+                }
+                Stmt::Class(stmt) => {
+                    let detail = match stmt.super_class.as_deref() {
+                        Some(crate::expr::Expr::Variable(super_class)) => {
+                            format!("class {} < {}", stmt.name.lexeme, super_class.name.lexeme)
+                        }
+                        _ => format!("class {}", stmt.name.lexeme),
+                    };
+                    Symbol {
+                        name: stmt.name.clone(),
+                        kind: SymbolKind::Class,
+                        detail,
+                        children: self.collect(&stmt.methods, Some(&stmt.name.lexeme), None),
+                    }
+                }
+                _ => {
+                    // Variables in nested blocks are locals and stay out of the outline
+                    outline.extend(self.collect(&nested_statements(statement), None, None));
+                    continue;
+                }
+            };
+            self.declare(symbol.clone());
+            if let Some(globals) = globals.as_deref_mut() {
+                globals.push(symbol.name.clone());
+            }
+            if globals.is_some() || symbol.kind != SymbolKind::Variable {
+                outline.push(symbol);
+            }
+        }
+        outline
+    }
+
+    fn declare(&mut self, symbol: Symbol) {
+        self.declarations
+            .insert((symbol.name.line, symbol.name.column), symbol);
+    }
+
+    /// Globals are not tracked by the resolver, their uses point at the latest top-level
+    /// declaration before them, or the first one when the use comes earlier (e.g. in a function)
+    fn link(&mut self, references: Vec<Reference>, globals: &[Token]) {
+        for reference in references {
+            if !reference.usage.is(TokenType::Identifier) {
+                continue;
+            }
+            let usage = (reference.usage.line, reference.usage.column);
+            let declaration = reference.declaration.or_else(|| {
+                let mut candidates = globals
+                    .iter()
+                    .filter(|global| global.lexeme == reference.usage.lexeme);
+                let first = candidates.clone().next();
+                candidates
+                    .rfind(|global| (global.line, global.column) <= usage)
+                    .or(first)
+                    .cloned()
+            });
+            if let Some(declaration) = declaration {
+                self.definitions.insert(usage, declaration);
+            }
+        }
+    }
+}
+
+/// Statements nested in a block, a branch or a loop body
+fn nested_statements(statement: &Stmt) -> Vec<Stmt> {
+    match statement {
+        Stmt::Block(stmt) => stmt.statements.clone(),
+        Stmt::If(stmt) => std::iter::once(stmt.then_branch.as_ref())
+            .chain(stmt.else_branch.as_deref())
+            .cloned()
+            .collect(),
+        Stmt::While(stmt) => vec![stmt.body.as_ref().clone()],
+        Stmt::For(stmt) => stmt
+            .initializer
+            .as_deref()
+            .into_iter()
+            .chain(std::iter::once(stmt.body.as_ref()))
+            .cloned()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Open scripts with their latest analysis. A script is only analyzed again when its text
+/// changes, so editors can send every change without paying for unchanged documents, and
+/// only the declarations a change touched are parsed again.
+#[derive(Debug, Default)]
+pub struct Workspace {
+    documents: HashMap<String, (String, IncrementalParser, Analysis)>,
+}
+
+impl Workspace {
+    pub fn new() -> Workspace {
+        Workspace::default()
+    }
+
+    pub fn update(&mut self, uri: &str, source: String) -> &Analysis {
+        let document = self.documents.entry(uri.to_string()).or_insert_with(|| {
+            let parse = IncrementalParser::new("");
+            let analysis = Analysis::from_parse(&parse);
+            (String::new(), parse, analysis)
+        });
+        if let Some(edit) = TextEdit::between(&document.0, &source) {
+            document.1.edit(&edit);
+            document.2 = Analysis::from_parse(&document.1);
+            document.0 = source;
+        }
+        &document.2
+    }
+
+    pub fn analysis(&self, uri: &str) -> Option<&Analysis> {
+        self.documents.get(uri).map(|(_, _, analysis)| analysis)
+    }
+
+    pub fn source(&self, uri: &str) -> Option<&str> {
+        self.documents
+            .get(uri)
+            .map(|(source, _, _)| source.as_str())
+    }
+
+    pub fn close(&mut self, uri: &str) {
+        self.documents.remove(uri);
+    }
+}
+
+#[cfg(test)]
+mod analysis_tests {
+    use super::*;
+
+    const SOURCE: &str = "var total = 0;
+fun add(a, b) {
+  var sum = a + b;
+  return sum;
+}
+class Counter {
+  inc() { total = add(total, 1); }
+}
+print add(1, 2);
+";
+
+    #[test]
+    fn test_definitions_of_locals_and_globals() {
+        // Act
+        let analysis = Analysis::analyze(SOURCE);
+        // Assert
+        let definition = |line, column| {
+            analysis
+                .definition(line, column)
+                .map(|t| (t.line, t.column))
+        };
+        // `a` in `a + b` is the first parameter
+        assert_eq!(definition(3, 12), Some((2, 8)));
+        // `sum` in the return statement
+        assert_eq!(definition(4, 9), Some((3, 6)));
+        // `add` and `total` used inside a method are globals
+        assert_eq!(definition(7, 18), Some((2, 4)));
+        assert_eq!(definition(7, 10), Some((1, 4)));
+        assert_eq!(definition(9, 0), None);
+        assert!(analysis.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_outline_and_hover() {
+        // Act
+        let analysis = Analysis::analyze(SOURCE);
+        // Assert
+        let outline: Vec<(&str, SymbolKind, usize)> = analysis
+            .symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol.name.lexeme.as_str(),
+                    symbol.kind,
+                    symbol.children.len(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            outline,
+            vec![
+                ("total", SymbolKind::Variable, 0),
+                ("add", SymbolKind::Function, 0),
+                ("Counter", SymbolKind::Class, 1)
+            ]
+        );
+        assert_eq!(analysis.symbols[2].children[0].detail, "Counter.inc()");
+        assert_eq!(
+            analysis.hover(9, 7),
+            Some("```lox\nfun add(a, b)\n```\nDeclared on line 2.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_diagnostics_and_workspace_updates() {
+        // Arrange
+        let mut workspace = Workspace::new();
+        // Act
+        let broken = workspace.update("a.lox", "var x = ;\n{ var unused; }".to_string());
+        let broken_diagnostics: Vec<String> =
+            broken.diagnostics.iter().map(|d| d.text.clone()).collect();
+        let fixed = workspace.update("a.lox", "{ var unused; }".to_string());
+        // Assert
+        assert_eq!(
+            broken_diagnostics,
+            vec!["[Line 1] - Error[P0101] at ';': Expect expression."]
+        );
+        assert_eq!(fixed.diagnostics.len(), 1);
+        assert!(fixed.diagnostics[0].is_warning);
+        assert_eq!(
+            fixed.diagnostics[0].token.as_ref().map(|t| t.column),
+            Some(6)
+        );
+    }
+}
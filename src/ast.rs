@@ -0,0 +1,93 @@
+//! Support for the syntax trees generated from `lox.ast`: every node gets a unique id and
+//! the span of the source it was parsed from when it is built with its `new` constructor.
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::token::Token;
+
+/// Shared by every thread, so the nodes of interpreters running in parallel never get the
+/// same id
+static UID: AtomicUsize = AtomicUsize::new(0);
+
+pub fn next_uid() -> usize {
+    UID.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Where a node is in the source, from the start of its first token to the end of its last
+/// one. Lines start at 1 and columns at 0 like in the tokens, the default span is unknown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Span {
+    pub fn is_unknown(&self) -> bool {
+        self.line == 0
+    }
+
+    /// The smallest span containing both, an unknown span is ignored
+    pub fn to(self, other: Span) -> Span {
+        if self.is_unknown() {
+            return other;
+        }
+        if other.is_unknown() {
+            return self;
+        }
+        let (line, column) = (self.line, self.column).min((other.line, other.column));
+        let (end_line, end_column) =
+            (self.end_line, self.end_column).max((other.end_line, other.end_column));
+        Span {
+            line,
+            column,
+            end_line,
+            end_column,
+        }
+    }
+
+    /// The smallest span containing all of them
+    pub fn covering(spans: impl IntoIterator<Item = Span>) -> Span {
+        spans.into_iter().fold(Span::default(), Span::to)
+    }
+}
+
+/// Anything a node can be built from that knows where it is in the source
+pub trait Spanned {
+    fn span(&self) -> Span;
+}
+
+impl Spanned for Token {
+    /// Tokens are located on the line where they end, a string can start lines before
+    fn span(&self) -> Span {
+        let breaks = self.lexeme.matches('\n').count();
+        let end_column = match self.lexeme.rsplit_once('\n') {
+            Some((_, last_line)) => last_line.chars().count(),
+            None => self.column + self.lexeme.chars().count(),
+        };
+        Span {
+            line: self.line - breaks,
+            column: self.column,
+            end_line: self.line,
+            end_column,
+        }
+    }
+}
+
+impl<T: Spanned> Spanned for Box<T> {
+    fn span(&self) -> Span {
+        self.as_ref().span()
+    }
+}
+
+impl<T: Spanned> Spanned for Option<T> {
+    fn span(&self) -> Span {
+        self.as_ref().map(Spanned::span).unwrap_or_default()
+    }
+}
+
+impl<T: Spanned> Spanned for Vec<T> {
+    fn span(&self) -> Span {
+        Span::covering(self.iter().map(Spanned::span))
+    }
+}
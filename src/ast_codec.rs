@@ -0,0 +1,2 @@
+//! `Encode` and `Decode` of every node for the cache, generated by `build.rs` from `lox.ast`
+include!(concat!(env!("OUT_DIR"), "/ast_codec.rs"));
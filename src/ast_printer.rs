@@ -0,0 +1,2 @@
+//! S-expression printer of every node, generated by `build.rs` from `lox.ast`
+include!(concat!(env!("OUT_DIR"), "/ast_printer.rs"));
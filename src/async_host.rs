@@ -0,0 +1,307 @@
+//! Natives returning futures, for embedders with an async runtime such as tokio. The script runs
+//! on a thread of its own and waits there while an async native is awaited; its future is
+//! polled by the host's executor through `ScriptRun`, so no worker thread of the host blocks.
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+    thread,
+};
+
+use crate::{
+    error::LoxErrorResult,
+    error_codes::{I0328, N0536, N0538},
+    interpreter::Interpreter,
+    object::Object,
+    run_captured, RunOutput,
+};
+
+/// Values passed between a script and the async natives, the ones that can leave its thread
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostValue {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Number(f64),
+    String(String),
+}
+
+impl HostValue {
+    /// `None` for values that only live in the interpreter, such as instances and lists
+    pub fn from_object(value: &Object) -> Option<HostValue> {
+        match value {
+            Object::Nil => Some(HostValue::Nil),
+            Object::Bool(value) => Some(HostValue::Bool(*value)),
+            Object::Int(value) => Some(HostValue::Int(*value)),
+            Object::Number(value) => Some(HostValue::Number(*value)),
+            Object::String(value) => Some(HostValue::String(value.to_string())),
+            _ => None,
+        }
+    }
+
+    pub fn into_object(self) -> Object {
+        match self {
+            HostValue::Nil => Object::Nil,
+            HostValue::Bool(value) => Object::Bool(value),
+            HostValue::Int(value) => Object::Int(value),
+            HostValue::Number(value) => Object::Number(value),
+            HostValue::String(value) => Object::String(value.into()),
+        }
+    }
+}
+
+/// What an async native resolves to, an error message becomes a runtime error of the call
+pub type HostFuture = Pin<Box<dyn Future<Output = Result<HostValue, String>> + Send>>;
+
+pub type AsyncNative = Arc<dyn Fn(Vec<HostValue>) -> HostFuture + Send + Sync>;
+
+type Setup = Box<dyn FnOnce() -> Interpreter + Send>;
+
+/// Sent by the script's thread to `ScriptRun`
+enum Message {
+    Call {
+        native: usize,
+        arguments: Vec<HostValue>,
+        reply: Sender<Result<HostValue, String>>,
+    },
+    Done(RunOutput),
+}
+
+/// Waker of the task awaiting `ScriptRun`, stored on every poll
+type SharedWaker = Arc<Mutex<Option<Waker>>>;
+
+/// Runs scripts whose async natives are awaited by the host, see `interpret_async`
+pub struct AsyncInterpreter {
+    setup: Setup,
+    natives: Vec<(String, usize, AsyncNative)>,
+}
+
+impl AsyncInterpreter {
+    /// Scripts run in an `Interpreter::new()`
+    pub fn new() -> AsyncInterpreter {
+        AsyncInterpreter::with_setup(Interpreter::new)
+    }
+
+    /// Scripts run in the interpreter `setup` makes on their thread, e.g. with more capabilities
+    pub fn with_setup(setup: impl FnOnce() -> Interpreter + Send + 'static) -> AsyncInterpreter {
+        AsyncInterpreter {
+            setup: Box::new(setup),
+            natives: Vec::new(),
+        }
+    }
+
+    /// Defines a global native whose calls wait for the future it returns
+    pub fn with_async_native<F>(
+        mut self,
+        name: &str,
+        arity: usize,
+        native: impl Fn(Vec<HostValue>) -> F + Send + Sync + 'static,
+    ) -> AsyncInterpreter
+    where
+        F: Future<Output = Result<HostValue, String>> + Send + 'static,
+    {
+        let native: AsyncNative = Arc::new(move |arguments| Box::pin(native(arguments)));
+        self.natives.push((name.to_string(), arity, native));
+        self
+    }
+
+    /// Starts the script on its own thread, awaiting the returned future drives its async
+    /// natives and gives what it printed like `run_captured`. `None` if the script panicked.
+    pub fn interpret_async(self, source: &str) -> ScriptRun {
+        let (messages, received) = mpsc::channel();
+        let waker = SharedWaker::default();
+        let declared: Vec<(String, usize)> = self
+            .natives
+            .iter()
+            .map(|(name, arity, _)| (name.clone(), *arity))
+            .collect();
+        let (setup, source, thread_waker) = (self.setup, source.to_string(), Arc::clone(&waker));
+        thread::spawn(move || {
+            // Wakes the task when the thread ends, even by a panic
+            let _wake = WakeOnDrop(Arc::clone(&thread_waker));
+            let mut interpreter = setup();
+            for (native, (name, arity)) in declared.into_iter().enumerate() {
+                let (messages, waker) = (messages.clone(), Arc::clone(&thread_waker));
+                let global = name.clone();
+                interpreter.define_native(&global, arity, move |_, arguments, token| {
+                    let arguments = arguments
+                        .iter()
+                        .map(|argument| {
+                            HostValue::from_object(argument).ok_or_else(|| {
+                                LoxErrorResult::interpreter_error(
+                                    token.line,
+                                    N0538.with([name.as_str().into(), argument.to_string().into()]),
+                                )
+                            })
+                        })
+                        .collect::<Result<Vec<HostValue>, LoxErrorResult>>()?;
+                    let (reply, result) = mpsc::channel();
+                    send(
+                        &messages,
+                        &waker,
+                        Message::Call {
+                            native,
+                            arguments,
+                            reply,
+                        },
+                    );
+                    match result.recv() {
+                        Ok(Ok(value)) => Ok(value.into_object()),
+                        Ok(Err(message)) => Err(LoxErrorResult::interpreter_error(
+                            token.line,
+                            N0536.with([name.as_str().into(), message.into()]),
+                        )),
+                        Err(_) => Err(LoxErrorResult::interpreter_error(
+                            token.line,
+                            I0328.with([name.as_str().into()]),
+                        )),
+                    }
+                });
+            }
+            let output = run_captured(&source, &mut interpreter);
+            send(&messages, &thread_waker, Message::Done(output));
+        });
+        ScriptRun {
+            received,
+            waker,
+            natives: self
+                .natives
+                .into_iter()
+                .map(|(_, _, native)| native)
+                .collect(),
+            pending: None,
+        }
+    }
+}
+
+impl Default for AsyncInterpreter {
+    fn default() -> AsyncInterpreter {
+        AsyncInterpreter::new()
+    }
+}
+
+fn send(messages: &Sender<Message>, waker: &SharedWaker, message: Message) {
+    // The task stopped awaiting the script when this fails, nobody is left to wake
+    if messages.send(message).is_ok() {
+        wake(waker);
+    }
+}
+
+fn wake(waker: &SharedWaker) {
+    if let Some(waker) = waker.lock().ok().and_then(|waker| waker.clone()) {
+        waker.wake();
+    }
+}
+
+struct WakeOnDrop(SharedWaker);
+
+impl Drop for WakeOnDrop {
+    fn drop(&mut self) {
+        wake(&self.0);
+    }
+}
+
+/// A script started by `interpret_async`. Dropping it cancels the async native being
+/// awaited, the script goes on without it.
+pub struct ScriptRun {
+    received: Receiver<Message>,
+    waker: SharedWaker,
+    natives: Vec<AsyncNative>,
+    /// Future of the async native the script is waiting for, and where to send its value
+    pending: Option<(HostFuture, Sender<Result<HostValue, String>>)>,
+}
+
+impl Future for ScriptRun {
+    type Output = Option<RunOutput>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<RunOutput>> {
+        let this = self.get_mut();
+        if let Ok(mut waker) = this.waker.lock() {
+            *waker = Some(cx.waker().clone());
+        }
+        loop {
+            if let Some((future, reply)) = &mut this.pending {
+                let Poll::Ready(result) = future.as_mut().poll(cx) else {
+                    return Poll::Pending;
+                };
+                let _ = reply.send(result);
+                this.pending = None;
+            }
+            match this.received.try_recv() {
+                Ok(Message::Call {
+                    native,
+                    arguments,
+                    reply,
+                }) => this.pending = Some(((this.natives[native])(arguments), reply)),
+                Ok(Message::Done(output)) => return Poll::Ready(Some(output)),
+                Err(TryRecvError::Empty) => return Poll::Pending,
+                Err(TryRecvError::Disconnected) => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod async_host_tests {
+    use super::*;
+    use std::{future, task::Wake, thread::Thread};
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Polls the future on this thread until it is ready, parking between the polls
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    fn assert_send<T: Send>(value: T) -> T {
+        value
+    }
+
+    #[test]
+    fn test_async_natives_are_awaited() {
+        // Arrange
+        let interpreter = AsyncInterpreter::new().with_async_native("double", 1, |arguments| {
+            // Not ready on the first poll, like a future waiting for a socket
+            let mut polled = false;
+            future::poll_fn(move |cx| match (polled, &arguments[0]) {
+                (false, _) => {
+                    polled = true;
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+                (true, HostValue::Int(value)) => Poll::Ready(Ok(HostValue::Int(value * 2))),
+                (true, value) => Poll::Ready(Err(format!("Cannot double {value:?}."))),
+            })
+        });
+        let source = "print double(21) + double(1);\nprint double(\"a\");\nprint double(clock);";
+        // Act
+        let output = block_on(assert_send(interpreter.interpret_async(source)));
+        // Assert
+        assert_eq!(
+            output,
+            Some(RunOutput {
+                stdout: "44\n".to_string(),
+                diagnostics: "[Line 2] - Error[N0536]: Native 'double' failed: Cannot double String(\"a\").\n[Line 3] - Error[N0538]: Arguments of 'double' must be nil, booleans, numbers or strings, got <fun native clock>.\n".to_string(),
+                exit_code: 70,
+            })
+        );
+    }
+}
@@ -0,0 +1,588 @@
+//! Compiled scripts kept on disk (`--cache`). The resolved syntax tree of a script is saved
+//! under the hash of its source and of the options it was checked with, a later run of the
+//! same source with the same options loads it instead of scanning, parsing and resolving the
+//! script again.
+// Expressions are hashed by their uid, the cells in the constants they hold never change it
+#![allow(clippy::mutable_key_type)]
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+
+use crate::{
+    ast::Span,
+    digest::sha256,
+    expr::Expr,
+    interpreter::Interpreter,
+    object::Object,
+    stmt::Stmt,
+    token::{Token, TokenType},
+};
+
+/// Start of every cache file, followed by its key, the source it was compiled from and the
+/// SHA-256 of the encoded statements after it
+const MAGIC: &[u8] = b"LOXC";
+
+/// Version of the encoding, bumped whenever the syntax tree or the way it is written changes
+const FORMAT_VERSION: u32 = 3;
+
+/// Tells apart the temporary files of the cache files written at once by one process
+static TEMPORARY_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Token types by their index in a cache file, in the order of their declaration
+const TOKEN_TYPES: [TokenType; 47] = [
+    TokenType::LeftParen,
+    TokenType::RightParen,
+    TokenType::LeftBrace,
+    TokenType::RightBrace,
+    TokenType::Comma,
+    TokenType::Dot,
+    TokenType::Minus,
+    TokenType::Plus,
+    TokenType::Colon,
+    TokenType::Semicolon,
+    TokenType::Slash,
+    TokenType::Star,
+    TokenType::Question,
+    TokenType::Bang,
+    TokenType::BangEqual,
+    TokenType::Equal,
+    TokenType::EqualEqual,
+    TokenType::Greater,
+    TokenType::GreaterEqual,
+    TokenType::Less,
+    TokenType::LessEqual,
+    TokenType::Arrow,
+    TokenType::Identifier,
+    TokenType::String,
+    TokenType::Number,
+    TokenType::And,
+    TokenType::Class,
+    TokenType::Else,
+    TokenType::False,
+    TokenType::Fun,
+    TokenType::For,
+    TokenType::If,
+    TokenType::Nil,
+    TokenType::Or,
+    TokenType::Print,
+    TokenType::Return,
+    TokenType::Super,
+    TokenType::This,
+    TokenType::True,
+    TokenType::Var,
+    TokenType::While,
+    TokenType::Break,
+    TokenType::Continue,
+    TokenType::In,
+    TokenType::Yield,
+    TokenType::Debugger,
+    TokenType::Eof,
+];
+
+/// A part of the syntax tree written to a cache file
+pub trait Encode {
+    fn encode(&self, encoder: &mut Encoder);
+}
+
+/// A part of the syntax tree read back from a cache file, `Err` when the file is corrupt
+pub trait Decode: Sized {
+    fn decode(decoder: &mut Decoder) -> Result<Self, String>;
+}
+
+/// Bytes of a cache file, the resolved distances of the expressions come from `locals`
+pub struct Encoder<'a> {
+    bytes: Vec<u8>,
+    locals: &'a HashMap<Expr, usize>,
+}
+
+impl<'a> Encoder<'a> {
+    pub fn new(locals: &'a HashMap<Expr, usize>) -> Encoder<'a> {
+        Encoder {
+            bytes: Vec::new(),
+            locals,
+        }
+    }
+
+    /// Which kind of node or which variant follows
+    pub fn tag(&mut self, tag: usize) {
+        self.bytes.push(tag as u8);
+    }
+
+    /// How many scopes away the variable of `expr` was resolved, `None` for globals
+    pub fn expr_key(&mut self, expr: &Expr) {
+        self.locals.get(expr).copied().encode(self);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Reads a cache file, the resolved distances of the decoded expressions go to `locals`
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    locals: &'a mut HashMap<Expr, usize>,
+    /// Added to the lines of the decoded tokens and spans
+    line_shift: isize,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(bytes: &'a [u8], locals: &'a mut HashMap<Expr, usize>) -> Decoder<'a> {
+        Decoder {
+            bytes,
+            position: 0,
+            locals,
+            line_shift: 0,
+        }
+    }
+
+    /// Decodes the nodes as if their source had moved by `shift` lines, for statements an
+    /// edit above them moved without changing them
+    pub fn shifting_lines(mut self, shift: isize) -> Decoder<'a> {
+        self.line_shift = shift;
+        self
+    }
+
+    /// Decodes a line and moves it by `line_shift`, line 0 stays unknown
+    fn line(&mut self) -> Result<usize, String> {
+        match usize::decode(self)? {
+            0 => Ok(0),
+            line => line
+                .checked_add_signed(self.line_shift)
+                .ok_or_else(|| format!("Line {line} cannot move by {} lines.", self.line_shift)),
+        }
+    }
+
+    pub fn tag(&mut self) -> Result<usize, String> {
+        Ok(self.take(1)?[0] as usize)
+    }
+
+    /// Keeps the distance saved by `Encoder::expr_key` for the decoded `expr`
+    pub fn expr_key(&mut self, expr: Expr) -> Result<Expr, String> {
+        if let Some(distance) = Option::<usize>::decode(self)? {
+            self.locals.insert(expr.clone(), distance);
+        }
+        Ok(expr)
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self.position.saturating_add(count);
+        let bytes = self
+            .bytes
+            .get(self.position..end)
+            .ok_or("The cache file ends too early.")?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn is_done(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    /// Bytes not decoded yet
+    fn rest(&self) -> &'a [u8] {
+        &self.bytes[self.position..]
+    }
+}
+
+impl Encode for u64 {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.bytes.extend(self.to_le_bytes());
+    }
+}
+
+impl Decode for u64 {
+    fn decode(decoder: &mut Decoder) -> Result<u64, String> {
+        let bytes = decoder.take(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap_or_default()))
+    }
+}
+
+impl Encode for usize {
+    fn encode(&self, encoder: &mut Encoder) {
+        (*self as u64).encode(encoder);
+    }
+}
+
+impl Decode for usize {
+    fn decode(decoder: &mut Decoder) -> Result<usize, String> {
+        usize::try_from(u64::decode(decoder)?).map_err(|err| err.to_string())
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.len().encode(encoder);
+        encoder.bytes.extend(self.as_bytes());
+    }
+}
+
+impl Decode for String {
+    fn decode(decoder: &mut Decoder) -> Result<String, String> {
+        let length = usize::decode(decoder)?;
+        let bytes = decoder.take(length)?;
+        String::from_utf8(bytes.to_vec()).map_err(|err| err.to_string())
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            Some(value) => {
+                encoder.tag(1);
+                value.encode(encoder);
+            }
+            None => encoder.tag(0),
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(decoder: &mut Decoder) -> Result<Option<T>, String> {
+        match decoder.tag()? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(decoder)?)),
+            tag => Err(format!("Unknown option {tag}.")),
+        }
+    }
+}
+
+impl<T: Encode> Encode for [T] {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.len().encode(encoder);
+        for item in self {
+            item.encode(encoder);
+        }
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.as_slice().encode(encoder);
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(decoder: &mut Decoder) -> Result<Vec<T>, String> {
+        let length = usize::decode(decoder)?;
+        // Not allocated up front, a corrupt length runs out of bytes instead of memory
+        let mut items = Vec::new();
+        for _ in 0..length {
+            items.push(T::decode(decoder)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<T: Encode> Encode for Box<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.as_ref().encode(encoder);
+    }
+}
+
+impl<T: Decode> Decode for Box<T> {
+    fn decode(decoder: &mut Decoder) -> Result<Box<T>, String> {
+        Ok(Box::new(T::decode(decoder)?))
+    }
+}
+
+impl Encode for Span {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.line.encode(encoder);
+        self.column.encode(encoder);
+        self.end_line.encode(encoder);
+        self.end_column.encode(encoder);
+    }
+}
+
+impl Decode for Span {
+    fn decode(decoder: &mut Decoder) -> Result<Span, String> {
+        Ok(Span {
+            line: decoder.line()?,
+            column: usize::decode(decoder)?,
+            end_line: decoder.line()?,
+            end_column: usize::decode(decoder)?,
+        })
+    }
+}
+
+/// Only the constants of the source appear in the syntax tree
+impl Encode for Object {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            Object::Nil => encoder.tag(0),
+            Object::Bool(value) => {
+                encoder.tag(1);
+                encoder.tag(*value as usize);
+            }
+            Object::Int(value) => {
+                encoder.tag(2);
+                (*value as u64).encode(encoder);
+            }
+            Object::Number(value) => {
+                encoder.tag(3);
+                value.to_bits().encode(encoder);
+            }
+            Object::String(value) => {
+                encoder.tag(4);
+                value.to_string().encode(encoder);
+            }
+            #[cfg(feature = "bigint")]
+            Object::BigInt(value) => {
+                encoder.tag(5);
+                value.to_string().encode(encoder);
+            }
+            value => unreachable!("{value} is not a constant of the source"),
+        }
+    }
+}
+
+impl Decode for Object {
+    fn decode(decoder: &mut Decoder) -> Result<Object, String> {
+        match decoder.tag()? {
+            0 => Ok(Object::Nil),
+            1 => Ok(Object::Bool(decoder.tag()? == 1)),
+            2 => Ok(Object::Int(u64::decode(decoder)? as i64)),
+            3 => Ok(Object::Number(f64::from_bits(u64::decode(decoder)?))),
+            4 => Ok(Object::String(String::decode(decoder)?.into())),
+            #[cfg(feature = "bigint")]
+            5 => {
+                let digits = String::decode(decoder)?;
+                match digits.parse::<BigInt>().ok() {
+                    Some(value) => Ok(Object::BigInt(crate::shared::Rc::new(value))),
+                    None => Err(format!("Invalid integer {digits}.")),
+                }
+            }
+            tag => Err(format!("Unknown constant {tag}.")),
+        }
+    }
+}
+
+impl Encode for Token {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.tag(self.token_type.clone() as usize);
+        self.lexeme.encode(encoder);
+        self.literal.encode(encoder);
+        self.line.encode(encoder);
+        self.column.encode(encoder);
+    }
+}
+
+impl Decode for Token {
+    fn decode(decoder: &mut Decoder) -> Result<Token, String> {
+        let tag = decoder.tag()?;
+        let token_type = TOKEN_TYPES
+            .get(tag)
+            .ok_or(format!("Unknown token type {tag}."))?;
+        Ok(Token {
+            token_type: token_type.clone(),
+            lexeme: String::decode(decoder)?,
+            literal: Object::decode(decoder)?,
+            line: decoder.line()?,
+            column: usize::decode(decoder)?,
+        })
+    }
+}
+
+/// FNV-1a, unlike the hasher of the standard library it is the same in every build
+pub fn source_hash(source: &str) -> u64 {
+    source.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// What the compiled statements depend on besides the source: the encoding, the interpreter
+/// that wrote them, its features and the options the checks ran with
+pub fn cache_key(interpreter: &Interpreter) -> String {
+    let features = [
+        ("bigint", cfg!(feature = "bigint")),
+        ("checksums", cfg!(feature = "checksums")),
+        ("net", cfg!(feature = "net")),
+    ]
+    .iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(name, _)| *name)
+    .collect::<Vec<_>>()
+    .join(",");
+    format!(
+        "format={FORMAT_VERSION} version={} features=[{features}] strict={} warnings={:?}",
+        env!("CARGO_PKG_VERSION"),
+        interpreter.strict,
+        interpreter.warnings,
+    )
+}
+
+/// The cache file of `source` in `dir`, named after the hash of the key and the source
+pub fn cache_path(dir: &Path, key: &str, source: &str) -> PathBuf {
+    let hash = source_hash(&format!("{key}\n{source}"));
+    dir.join(format!("{hash:016x}.loxc"))
+}
+
+/// Writes the compiled statements of `source` to its cache file in `dir`, with the distances
+/// the resolver put in the locals of `interpreter` for their variables. The file is written
+/// under a temporary name then renamed, a run never loads one half written.
+pub fn save(
+    dir: &Path,
+    source: &str,
+    statements: &[Stmt],
+    interpreter: &Interpreter,
+) -> io::Result<()> {
+    let key = cache_key(interpreter);
+    let mut body = Encoder::new(&interpreter.locals);
+    statements.encode(&mut body);
+    let body = body.into_bytes();
+    let mut encoder = Encoder::new(&interpreter.locals);
+    encoder.bytes.extend(MAGIC);
+    key.encode(&mut encoder);
+    source.to_string().encode(&mut encoder);
+    sha256(&body).encode(&mut encoder);
+    encoder.bytes.extend(body);
+    fs::create_dir_all(dir)?;
+    let path = cache_path(dir, &key, source);
+    let count = TEMPORARY_FILES.fetch_add(1, Ordering::Relaxed);
+    let temporary = path.with_extension(format!("{}-{count}.tmp", process::id()));
+    let written =
+        fs::write(&temporary, encoder.into_bytes()).and_then(|_| fs::rename(&temporary, &path));
+    if written.is_err() {
+        fs::remove_file(&temporary).ok();
+    }
+    written
+}
+
+/// The statements `save` wrote for `source`, their variables resolved for `interpreter`.
+/// `None` when there is no cache file for the source yet, or it was written by another
+/// version of the interpreter, with other options, for another source with the same hash,
+/// or is corrupt: its statements must match the checksum before they are decoded.
+pub fn load(dir: &Path, source: &str, interpreter: &mut Interpreter) -> Option<Vec<Stmt>> {
+    let key = cache_key(interpreter);
+    let bytes = fs::read(cache_path(dir, &key, source)).ok()?;
+    let mut locals = HashMap::new();
+    let mut decoder = Decoder::new(&bytes, &mut locals);
+    if decoder.take(MAGIC.len()).ok()? != MAGIC
+        || String::decode(&mut decoder).ok()? != key
+        || String::decode(&mut decoder).ok()? != source
+        || String::decode(&mut decoder).ok()? != sha256(decoder.rest())
+    {
+        return None;
+    }
+    let statements = Vec::<Stmt>::decode(&mut decoder).ok()?;
+    if !decoder.is_done() {
+        return None;
+    }
+    interpreter.locals.extend(locals);
+    Some(statements)
+}
+
+#[cfg(test)]
+mod cache_tests {
+    use super::*;
+    use crate::{ast_printer::AstPrinter, compile, run_cached};
+
+    #[test]
+    fn test_token_types_are_in_declaration_order() {
+        // Assert
+        for (idx, token_type) in TOKEN_TYPES.iter().enumerate() {
+            assert_eq!(token_type.clone() as usize, idx);
+        }
+    }
+
+    #[test]
+    fn test_load_saved_statements() {
+        // Arrange
+        let dir = std::env::temp_dir().join(format!("rlox-cache-{}", std::process::id()));
+        let source = "class A { greet() { return \"A\"; } }
+class B < A { greet() { return super.greet() + \"B\"; } }
+fun counter() { var n = 0; fun next() { n = n + 1; return n; } return next; }
+var next = counter();
+next();
+print next() + 0.5;
+print B().greet();";
+        let mut interpreter = Interpreter::new();
+        let statements = compile(source.to_string(), &mut interpreter, false).unwrap();
+        save(&dir, source, &statements, &interpreter).unwrap();
+        let mut fresh = Interpreter::new();
+        // Act
+        let loaded = load(&dir, source, &mut fresh);
+        let changed = load(&dir, "print 1;", &mut Interpreter::new());
+        let mut strict = Interpreter::new();
+        strict.strict = true;
+        let other_options = load(&dir, source, &mut strict);
+        let captured = fresh.capture_output();
+        let ran = run_cached(source.to_string(), &mut fresh, &dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        // Assert
+        let print = |statements: &[Stmt]| {
+            let mut printer = AstPrinter::new();
+            let printed: Vec<String> = statements.iter().map(|s| printer.print_stmt(s)).collect();
+            printed.join("\n")
+        };
+        assert_eq!(
+            loaded.map(|loaded| print(&loaded)),
+            Some(print(&statements))
+        );
+        assert!(changed.is_none());
+        assert!(other_options.is_none());
+        assert!(ran.is_ok());
+        assert_eq!(captured.borrow().stdout, "2.5\n\"AB\"\n");
+    }
+
+    #[test]
+    fn test_scripts_with_diagnostics_are_not_cached() {
+        // Arrange
+        let dir = std::env::temp_dir().join(format!("rlox-cache-checks-{}", std::process::id()));
+        let source = "var n: number = \"one\";\nprint n;";
+        let denied = || {
+            let mut interpreter = Interpreter::new();
+            interpreter.warnings = crate::error::WarningLevel::Deny;
+            interpreter
+        };
+        // Act
+        let lenient = run_cached(source.to_string(), &mut Interpreter::new(), &dir);
+        let cached = std::fs::read_dir(&dir).map_or(0, |files| files.count());
+        let first = run_cached(source.to_string(), &mut denied(), &dir);
+        let second = run_cached(source.to_string(), &mut denied(), &dir);
+        std::fs::remove_dir_all(&dir).ok();
+        // Assert
+        assert!(lenient.is_ok());
+        assert_eq!(cached, 0);
+        assert!(matches!(first, Err(crate::RunError::Compile)));
+        assert!(matches!(second, Err(crate::RunError::Compile)));
+    }
+
+    #[test]
+    fn test_corrupt_cache_files_are_not_loaded() {
+        // Arrange
+        let dir = std::env::temp_dir().join(format!("rlox-cache-corrupt-{}", std::process::id()));
+        let source = "var greeting = \"hi\";\nfun greet(name) { return greeting + name; }\nprint greet(\"!\");";
+        let mut interpreter = Interpreter::new();
+        let statements = compile(source.to_string(), &mut interpreter, false).unwrap();
+        save(&dir, source, &statements, &interpreter).unwrap();
+        let path = cache_path(&dir, &cache_key(&interpreter), source);
+        let bytes = std::fs::read(&path).unwrap();
+        let files = std::fs::read_dir(&dir).unwrap().count();
+        // Act
+        let flipped: Vec<bool> = (bytes.len() - 64..bytes.len())
+            .map(|idx| {
+                let mut corrupt = bytes.clone();
+                corrupt[idx] ^= 0x10;
+                std::fs::write(&path, corrupt).unwrap();
+                load(&dir, source, &mut Interpreter::new()).is_none()
+            })
+            .collect();
+        std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+        let torn = load(&dir, source, &mut Interpreter::new());
+        std::fs::remove_dir_all(&dir).unwrap();
+        // Assert
+        assert_eq!(files, 1);
+        assert!(flipped.iter().all(|missed| *missed));
+        assert!(torn.is_none());
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt::{self, Display, Formatter};
+
+/// What a native reaches outside of the script, denied capabilities keep scripts sandboxed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Capability {
+    /// The console: `readLine` and `eprint`
+    Io,
+    /// Files: `readFile`, `writeFile` and `appendFile`
+    Fs,
+    /// The network: `fetch`, `httpGet` and the `tcp...` natives, with the `net` feature
+    Net,
+    /// The system clock: `clock`, `now`, `clockMonotonic`, `sleep` and `bench`
+    Time,
+    /// The process: `arg`, `argc` and `exit`
+    Env,
+    /// Other programs: `exec`
+    Process,
+}
+
+const ALL: [Capability; 6] = [
+    Capability::Io,
+    Capability::Fs,
+    Capability::Net,
+    Capability::Time,
+    Capability::Env,
+    Capability::Process,
+];
+
+impl Capability {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Capability::Io => "io",
+            Capability::Fs => "fs",
+            Capability::Net => "net",
+            Capability::Time => "time",
+            Capability::Env => "env",
+            Capability::Process => "process",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Capability> {
+        ALL.into_iter().find(|capability| capability.name() == name)
+    }
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Capabilities granted to the natives of an interpreter, every one unless restricted
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    /// Sorted, so the same capabilities compare equal whatever order they were granted in
+    granted: Vec<Capability>,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities::all()
+    }
+}
+
+impl Capabilities {
+    pub fn all() -> Capabilities {
+        Capabilities {
+            granted: ALL.to_vec(),
+        }
+    }
+
+    pub fn none() -> Capabilities {
+        Capabilities {
+            granted: Vec::new(),
+        }
+    }
+
+    /// Only the listed capabilities, separated by commas as in `--allow=fs,time`
+    pub fn parse(list: &str) -> Result<Capabilities, String> {
+        let mut capabilities = Capabilities::none();
+        for name in list
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            let capability = Capability::from_name(name)
+                .ok_or_else(|| format!("Unknown capability '{name}'."))?;
+            capabilities = capabilities.grant(capability);
+        }
+        Ok(capabilities)
+    }
+
+    pub fn grant(mut self, capability: Capability) -> Capabilities {
+        if !self.allows(capability) {
+            self.granted.push(capability);
+            self.granted.sort();
+        }
+        self
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.granted.contains(&capability)
+    }
+}
+
+#[cfg(test)]
+mod capabilities_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_capabilities() {
+        // Act
+        let granted = Capabilities::parse("fs, time");
+        let empty = Capabilities::parse("");
+        let unknown = Capabilities::parse("fs,gpu");
+        // Assert
+        assert_eq!(
+            granted,
+            Ok(Capabilities::none()
+                .grant(Capability::Fs)
+                .grant(Capability::Time))
+        );
+        assert_eq!(empty, Ok(Capabilities::none()));
+        assert_eq!(unknown, Err("Unknown capability 'gpu'.".to_string()));
+    }
+}
@@ -0,0 +1,992 @@
+use crate::{
+    capabilities::Capabilities,
+    config::ProjectConfig,
+    coverage::CoverageFormat,
+    debug_log::DebugCategories,
+    error::WarningLevel,
+    linter::{LintConfig, Rule},
+    locale::Language,
+    recorder::TraceQuery,
+    transpiler::Target,
+};
+
+pub const USAGE: &str = "Usage: r-lox interpreter [options] [script | -] [args...]
+       r-lox interpreter [options] -e <code> [-e <code>...] [args...]
+       r-lox interpreter fmt [--check] [--minify] <script>
+       r-lox interpreter lint [--disable <rules>] [--max-params <n>] <script>
+       r-lox interpreter test <dir>
+       r-lox interpreter transpile [--target=js] <script>
+       r-lox interpreter replay <trace> [<variable> <line> [<iteration>]]
+       r-lox interpreter lsp
+       r-lox interpreter serve [--port <n>] [--allow=<capabilities>]
+       r-lox interpreter --explain <code>
+
+Options:
+  -e, --eval <code>
+             Run the code instead of a script file, the code of repeated
+             flags is joined by newlines. Every other argument is handed
+             to the code through arg(i)
+  -i, --interactive
+             Open the prompt once the script or the --eval code ran, with
+             its variables, functions and classes still defined
+  --watch    Run the script again every time the file changes, its
+             functions and classes are redefined but the global
+             variables keep their value
+  --profile  Print the calls and time spent in every function after the run
+  --stats    Print how many statements, calls, environments and cloned
+             values the run took, and its deepest call, after the run
+  --trace    Print every executed line, call and runtime error to stderr
+  --debug=<categories>
+             Log the interpreter's internals to stderr, any of 'scope'
+             (resolver scopes), 'env' (environments and the ones closures
+             capture) and 'calls' (arguments bound to parameters), or 'all'
+  --trace-export
+             Write the begin and end of every call to 'trace.json' in
+             Chrome's trace-event format (about://tracing, Perfetto)
+  --deterministic
+             Pin clock() to a virtual counter and seed random() so every
+             run prints the same
+  --ieee-division
+             Dividing by zero gives Infinity or NaN instead of a runtime error
+  --closures Compile the script into a tree of closures before running it
+             instead of walking its syntax tree, usually faster
+  --strict   Conditions must be booleans and '+' doesn't turn numbers
+             into strings, both are runtime errors otherwise. Values
+             not matching their type annotations are compile errors
+  --warnings=allow|warn|deny
+             Hide the resolver's and the type checker's warnings, print
+             them (the default) or refuse to run a script with any
+  --tab-width=<n>
+             Columns a tab moves to the next multiple of in the columns
+             and source lines of diagnostics (4 unless given)
+  --allow=<capabilities>
+             Only let the natives use these capabilities, comma separated:
+             io (console), fs (files), net (http), time (clock), env (arguments,
+             exit), process (exec). The others raise a runtime error when
+             called
+  --fs-root=<dir>
+             Confine readFile, writeFile and appendFile to the directory,
+             their paths are relative to it
+  --no-prelude
+             Leave out the Lox functions defined before the script runs
+             (max, min, abs, range)
+  --prelude=<file>
+             Run the Lox file before the script, after the default prelude
+  --cache=<dir>
+             Keep the compiled script in the directory, later runs of the
+             same source skip scanning, parsing and resolving it
+  --time     Print how long scanning, parsing, resolving and executing
+             took after the run, after every evaluation in the prompt
+  --record=<file>
+             Write every executed line and variable write to the file,
+             for looking into the run afterwards with 'replay'
+  --coverage[=summary|annotated|lcov]
+             Report the executed lines of the script after the run,
+             lcov is written to 'lcov.info'
+  --emit=highlight-json
+             Print the highlighting categories of the script's source
+             ranges as JSON instead of running it
+  --types, --emit=types
+             Print every declaration with its annotated or inferred type
+             instead of running the script
+  --lang=en|es
+             Language of the diagnostics, English unless LOX_LANG or
+             LANG name another one
+  --explain <code>
+             Describe the error code shown in a diagnostic, like
+             'Error[P0103]', with an example reporting it
+
+Commands:
+  fmt        Print the script formatted in the canonical style
+             (--check only reports whether the file is already formatted),
+             or on a single line without optional spaces with --minify
+  lint       Report suspicious code, exits with 1 when anything is found.
+             Rules (names or codes, comma separated for --disable):
+             unused-variable W0601, unused-parameter W0602, shadowing W0603,
+             empty-block W0604, constant-condition W0605,
+             too-many-parameters W0606 (limit set by --max-params, default 5)
+  test       Run every .lox file in a directory and compare its output
+             with the '// expect: ' and '// expect runtime error: ' comments,
+             '*_test.lox' files pass when every test() call passed,
+             scripts run as with --deterministic
+  transpile  Print the script translated to another language, JavaScript
+             is the only target for now
+  replay     Print every variable write of a trace written by --record,
+             or with a variable and a line the value it had when the line
+             ran for the nth time (1 unless an iteration is given)
+  lsp        Start a language server speaking LSP over stdin and stdout
+  serve      Answer prompt lines sent over TCP or WebSocket on the local
+             host (port 7878 unless given), every connection with its own
+             globals. A line may run 5 seconds, print 1 MiB and nest 1024
+             calls. Only the time capability is granted unless --allow is
+             given
+
+Settings of 'lox.toml', or '.loxrc', in the script's directory apply to runs
+and fmt unless given as flags: strict, warnings, allow, prelude, tab_width
+and, under [fmt], indent_width, max_width and minify";
+
+/// Port of `serve` without `--port`
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// Columns of a tab in the scripts run without `--tab-width`
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
+/// Output printed by `--emit` instead of running the script
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Emit {
+    HighlightJson,
+    /// Declarations with their annotated or inferred types, also `--types`
+    Types,
+}
+
+impl Emit {
+    pub fn from_name(name: &str) -> Option<Emit> {
+        match name {
+            "highlight-json" => Some(Emit::HighlightJson),
+            "types" => Some(Emit::Types),
+            _ => None,
+        }
+    }
+}
+
+/// What the interpreter was asked to do with the script
+#[derive(Debug, Default, PartialEq)]
+pub enum Command {
+    #[default]
+    Run,
+    Fmt {
+        check: bool,
+        /// One line without the optional spaces
+        minify: bool,
+    },
+    Lint(LintConfig),
+    /// Runs the scripts found in the directory given as `script`
+    Test,
+    Transpile(Target),
+    /// Looks into the trace given as `script`, every write without a query
+    Replay(Option<TraceQuery>),
+    Lsp,
+    /// The prompt over TCP, see `server`
+    Serve {
+        port: u16,
+    },
+    /// Describes an error code, see `error_codes`
+    Explain(String),
+}
+
+/// Command line options. Flags go before the script path, everything after it
+/// is handed to the script through `arg(i)`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Options {
+    pub command: Command,
+    pub script: Option<String>,
+    pub script_args: Vec<String>,
+    /// Code of every `--eval` flag, in order, run instead of a script
+    pub eval: Vec<String>,
+    /// `-i`: the prompt follows the run
+    pub interactive: bool,
+    pub watch: bool,
+    pub profile: bool,
+    pub stats: bool,
+    pub trace: bool,
+    /// Categories logged by `--debug`
+    pub debug: DebugCategories,
+    pub trace_export: bool,
+    pub deterministic: bool,
+    pub ieee_division: bool,
+    pub strict: bool,
+    /// Level given by `--warnings`
+    pub warnings: Option<WarningLevel>,
+    /// Language given by `--lang`
+    pub lang: Option<Language>,
+    pub closures: bool,
+    pub no_prelude: bool,
+    /// Script given by `--prelude`, run before the script
+    pub prelude: Option<String>,
+    /// Capabilities granted by `--allow`, all of them without it
+    pub allow: Option<Capabilities>,
+    /// Directory given by `--fs-root`
+    pub fs_root: Option<String>,
+    /// Directory of the compiled scripts given by `--cache`
+    pub cache: Option<String>,
+    /// Columns of a tab given by `--tab-width`
+    pub tab_width: Option<usize>,
+    pub time: bool,
+    /// Trace file written by `--record`
+    pub record: Option<String>,
+    pub coverage: Option<CoverageFormat>,
+    pub emit: Option<Emit>,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options::default();
+        if args.first().is_some_and(|arg| arg == "fmt") {
+            return Options::parse_fmt(&args[1..]);
+        }
+        if args.first().is_some_and(|arg| arg == "lint") {
+            return Options::parse_lint(&args[1..]);
+        }
+        if args.first().is_some_and(|arg| arg == "test") {
+            return match &args[1..] {
+                [dir] => Ok(Options {
+                    command: Command::Test,
+                    script: Some(dir.to_string()),
+                    ..Options::default()
+                }),
+                _ => Err("Command 'test' expects a single directory.".to_string()),
+            };
+        }
+        if args.first().is_some_and(|arg| arg == "transpile") {
+            return Options::parse_transpile(&args[1..]);
+        }
+        if args.first().is_some_and(|arg| arg == "replay") {
+            return Options::parse_replay(&args[1..]);
+        }
+        if args.first().is_some_and(|arg| arg == "serve") {
+            return Options::parse_serve(&args[1..]);
+        }
+        if args.first().is_some_and(|arg| arg == "--explain") {
+            return match &args[1..] {
+                [code] => Ok(Options {
+                    command: Command::Explain(code.to_string()),
+                    ..Options::default()
+                }),
+                _ => Err("Option '--explain' expects a single error code.".to_string()),
+            };
+        }
+        if args.first().is_some_and(|arg| arg == "lsp") {
+            return match &args[1..] {
+                [] => Ok(Options {
+                    command: Command::Lsp,
+                    ..Options::default()
+                }),
+                _ => Err("Command 'lsp' takes no arguments.".to_string()),
+            };
+        }
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--watch" => options.watch = true,
+                "-i" | "--interactive" => options.interactive = true,
+                "--profile" => options.profile = true,
+                "--stats" => options.stats = true,
+                "--trace" => options.trace = true,
+                "--trace-export" => options.trace_export = true,
+                "--debug" => options.debug = DebugCategories::all(),
+                flag if flag.starts_with("--debug=") => {
+                    options.debug = DebugCategories::parse(&flag["--debug=".len()..])?;
+                }
+                "--deterministic" => options.deterministic = true,
+                "--ieee-division" => options.ieee_division = true,
+                "--strict" => options.strict = true,
+                "--closures" => options.closures = true,
+                "--no-prelude" => options.no_prelude = true,
+                "--time" => options.time = true,
+                "--types" => options.emit = Some(Emit::Types),
+                "-e" | "--eval" => {
+                    let code = args
+                        .next()
+                        .ok_or_else(|| format!("Option '{arg}' expects code to run."))?;
+                    options.eval.push(code.to_string());
+                }
+                flag if flag.starts_with("--eval=") => {
+                    options.eval.push(flag["--eval=".len()..].to_string());
+                }
+                flag if flag.starts_with("--allow=") => {
+                    options.allow = Some(Capabilities::parse(&flag["--allow=".len()..])?);
+                }
+                flag if flag.starts_with("--tab-width=") => {
+                    let width = &flag["--tab-width=".len()..];
+                    options.tab_width = Some(
+                        width
+                            .parse()
+                            .ok()
+                            .filter(|width| *width > 0)
+                            .ok_or_else(|| format!("Invalid tab width '{width}'."))?,
+                    );
+                }
+                flag if flag.starts_with("--warnings=") => {
+                    let level = &flag["--warnings=".len()..];
+                    options.warnings = Some(
+                        WarningLevel::from_name(level)
+                            .ok_or_else(|| format!("Unknown warning level '{level}'."))?,
+                    );
+                }
+                flag if flag.starts_with("--lang=") => {
+                    let name = &flag["--lang=".len()..];
+                    options.lang = Some(
+                        Language::from_name(name)
+                            .ok_or_else(|| format!("Unknown language '{name}'."))?,
+                    );
+                }
+                flag if flag.starts_with("--prelude=") => {
+                    options.prelude = Some(flag["--prelude=".len()..].to_string());
+                }
+                flag if flag.starts_with("--fs-root=") => {
+                    options.fs_root = Some(flag["--fs-root=".len()..].to_string());
+                }
+                flag if flag.starts_with("--cache=") => {
+                    options.cache = Some(flag["--cache=".len()..].to_string());
+                }
+                flag if flag.starts_with("--record=") => {
+                    options.record = Some(flag["--record=".len()..].to_string());
+                }
+                "--coverage" => options.coverage = Some(CoverageFormat::default()),
+                flag if flag.starts_with("--coverage=") => {
+                    let format = &flag["--coverage=".len()..];
+                    options.coverage = Some(
+                        CoverageFormat::from_name(format)
+                            .ok_or_else(|| format!("Unknown coverage format '{format}'."))?,
+                    );
+                }
+                flag if flag.starts_with("--emit=") => {
+                    let kind = &flag["--emit=".len()..];
+                    options.emit = Some(
+                        Emit::from_name(kind)
+                            .ok_or_else(|| format!("Unknown emit kind '{kind}'."))?,
+                    );
+                }
+                flag if flag.starts_with("--") => return Err(format!("Unknown option '{flag}'.")),
+                // With `--eval` there is no script, the first argument is already one of its own
+                _ if !options.eval.is_empty() => {
+                    options.script_args = std::iter::once(arg).chain(args).cloned().collect();
+                    break;
+                }
+                script => {
+                    options.script = Some(script.to_string());
+                    options.script_args = args.cloned().collect();
+                    break;
+                }
+            }
+        }
+        if options.interactive
+            && options.eval.is_empty()
+            && matches!(options.script.as_deref(), None | Some("-"))
+        {
+            return Err("Option '-i' requires a script file or '--eval' code.".to_string());
+        }
+        if options.interactive && (options.watch || options.emit.is_some()) {
+            return Err(
+                "Option '-i' cannot be combined with '--watch', '--emit' or '--types'.".to_string(),
+            );
+        }
+        if options.watch && matches!(options.script.as_deref(), None | Some("-")) {
+            return Err("Option '--watch' requires a script file.".to_string());
+        }
+        if options.watch
+            && (options.profile
+                || options.stats
+                || options.trace_export
+                || options.coverage.is_some()
+                || options.record.is_some())
+        {
+            return Err("Option '--watch' cannot be combined with reports.".to_string());
+        }
+        if options.cache.is_some() && matches!(options.script.as_deref(), None | Some("-")) {
+            return Err("Option '--cache' requires a script file.".to_string());
+        }
+        if options.cache.is_some() && (options.watch || options.time || options.closures) {
+            return Err(
+                "Option '--cache' cannot be combined with '--watch', '--time' or '--closures'."
+                    .to_string(),
+            );
+        }
+        if options.closures && (options.watch || options.time) {
+            return Err(
+                "Option '--closures' cannot be combined with '--watch' or '--time'.".to_string(),
+            );
+        }
+        if options.coverage.is_some() && matches!(options.script.as_deref(), None | Some("-")) {
+            return Err("Option '--coverage' requires a script file.".to_string());
+        }
+        if options.emit.is_some() && matches!(options.script.as_deref(), None | Some("-")) {
+            return Err("Options '--emit' and '--types' require a script file.".to_string());
+        }
+        Ok(options)
+    }
+
+    /// Fills in the settings of the project configuration the flags left out
+    pub fn with_config(mut self, config: &ProjectConfig) -> Options {
+        self.strict |= config.strict.unwrap_or(false);
+        self.warnings = self.warnings.or(config.warnings);
+        self.tab_width = self.tab_width.or(config.tab_width);
+        self.allow = self.allow.or_else(|| config.allow.clone());
+        self.prelude = self.prelude.or_else(|| {
+            config
+                .prelude
+                .as_ref()
+                .map(|path| path.display().to_string())
+        });
+        self
+    }
+
+    fn parse_fmt(args: &[String]) -> Result<Options, String> {
+        let mut check = false;
+        let mut minify = false;
+        let mut script = None;
+        for arg in args {
+            match arg.as_str() {
+                "--check" => check = true,
+                "--minify" => minify = true,
+                flag if flag.starts_with("--") => return Err(format!("Unknown option '{flag}'.")),
+                path if script.is_none() => script = Some(path.to_string()),
+                _ => return Err("Command 'fmt' expects a single script file.".to_string()),
+            }
+        }
+        if script.is_none() {
+            return Err("Command 'fmt' requires a script file.".to_string());
+        }
+        Ok(Options {
+            command: Command::Fmt { check, minify },
+            script,
+            ..Options::default()
+        })
+    }
+
+    fn parse_serve(args: &[String]) -> Result<Options, String> {
+        let mut port = DEFAULT_PORT;
+        let mut allow = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let value = match arg.as_str() {
+                "--port" => args.next().map(String::as_str).unwrap_or_default(),
+                flag if flag.starts_with("--port=") => &flag["--port=".len()..],
+                flag if flag.starts_with("--allow=") => {
+                    allow = Some(Capabilities::parse(&flag["--allow=".len()..])?);
+                    continue;
+                }
+                other => return Err(format!("Unknown option '{other}' for command 'serve'.")),
+            };
+            port = value
+                .parse()
+                .map_err(|_| format!("Expected a port number, got '{value}'."))?;
+        }
+        Ok(Options {
+            command: Command::Serve { port },
+            allow,
+            ..Options::default()
+        })
+    }
+
+    fn parse_transpile(args: &[String]) -> Result<Options, String> {
+        let mut target = Target::default();
+        let mut script = None;
+        for arg in args {
+            match arg.as_str() {
+                flag if flag.starts_with("--target=") => {
+                    let name = &flag["--target=".len()..];
+                    target = Target::from_name(name)
+                        .ok_or_else(|| format!("Unknown transpile target '{name}'."))?;
+                }
+                flag if flag.starts_with("--") => return Err(format!("Unknown option '{flag}'.")),
+                path if script.is_none() => script = Some(path.to_string()),
+                _ => return Err("Command 'transpile' expects a single script file.".to_string()),
+            }
+        }
+        if script.is_none() {
+            return Err("Command 'transpile' requires a script file.".to_string());
+        }
+        Ok(Options {
+            command: Command::Transpile(target),
+            script,
+            ..Options::default()
+        })
+    }
+
+    fn parse_replay(args: &[String]) -> Result<Options, String> {
+        let number = |value: &String| {
+            value
+                .parse()
+                .ok()
+                .filter(|number| *number > 0)
+                .ok_or_else(|| format!("Expected a line or an iteration, got '{value}'."))
+        };
+        let query = match args {
+            [_] => None,
+            [_, name, line] => Some(TraceQuery {
+                name: name.to_string(),
+                line: number(line)?,
+                iteration: 1,
+            }),
+            [_, name, line, iteration] => Some(TraceQuery {
+                name: name.to_string(),
+                line: number(line)?,
+                iteration: number(iteration)?,
+            }),
+            _ => {
+                return Err(
+                    "Command 'replay' expects a trace, optionally a variable and a line."
+                        .to_string(),
+                )
+            }
+        };
+        Ok(Options {
+            command: Command::Replay(query),
+            script: Some(args[0].to_string()),
+            ..Options::default()
+        })
+    }
+
+    fn parse_lint(args: &[String]) -> Result<Options, String> {
+        let mut config = LintConfig::default();
+        let mut script = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--disable" => {
+                    let rules = args
+                        .next()
+                        .ok_or("Option '--disable' expects rule names.")?;
+                    for name in rules.split(',') {
+                        let rule = Rule::from_name(name.trim())
+                            .ok_or_else(|| format!("Unknown lint rule '{name}'."))?;
+                        config.disabled.push(rule);
+                    }
+                }
+                "--max-params" => {
+                    config.max_params = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .ok_or("Option '--max-params' expects a number.")?;
+                }
+                flag if flag.starts_with("--") => return Err(format!("Unknown option '{flag}'.")),
+                path if script.is_none() => script = Some(path.to_string()),
+                _ => return Err("Command 'lint' expects a single script file.".to_string()),
+            }
+        }
+        if script.is_none() {
+            return Err("Command 'lint' requires a script file.".to_string());
+        }
+        Ok(Options {
+            command: Command::Lint(config),
+            script,
+            ..Options::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod cli_tests {
+    use super::*;
+    use crate::debug_log::DebugCategory;
+
+    fn make_args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_script_and_arguments() {
+        // Act
+        let options = Options::parse(&make_args(&["script.lox", "--watch", "b"]));
+        // Assert
+        assert_eq!(
+            options,
+            Ok(Options {
+                command: Command::Run,
+                script: Some("script.lox".to_string()),
+                script_args: make_args(&["--watch", "b"]),
+                ..Options::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_eval_flag() {
+        // Act
+        let options = Options::parse(&make_args(&[
+            "-e",
+            "print arg(0);",
+            "--strict",
+            "--eval=print 2;",
+            "a.lox",
+            "-e",
+        ]));
+        let missing_code = Options::parse(&make_args(&["--eval"]));
+        let watched = Options::parse(&make_args(&["--watch", "-e", "print 1;"]));
+        // Assert
+        assert_eq!(
+            options,
+            Ok(Options {
+                eval: make_args(&["print arg(0);", "print 2;"]),
+                script_args: make_args(&["a.lox", "-e"]),
+                strict: true,
+                ..Options::default()
+            })
+        );
+        assert_eq!(
+            missing_code,
+            Err("Option '--eval' expects code to run.".to_string())
+        );
+        assert!(watched.is_err());
+    }
+
+    #[test]
+    fn test_parse_interactive_flag() {
+        // Act
+        let script = Options::parse(&make_args(&["-i", "script.lox", "a"]));
+        let eval = Options::parse(&make_args(&["--interactive", "-e", "var x = 1;"]));
+        let stdin = Options::parse(&make_args(&["-i", "-"]));
+        let watched = Options::parse(&make_args(&["-i", "--watch", "script.lox"]));
+        // Assert
+        assert!(script.is_ok_and(|options| options.interactive && options.script_args == ["a"]));
+        assert!(eval.is_ok_and(|options| options.interactive));
+        assert_eq!(
+            stdin,
+            Err("Option '-i' requires a script file or '--eval' code.".to_string())
+        );
+        assert!(watched.is_err());
+    }
+
+    #[test]
+    fn test_parse_watch_flag() {
+        // Act
+        let options = Options::parse(&make_args(&["--watch", "script.lox"]));
+        let missing_script = Options::parse(&make_args(&["--watch"]));
+        // Assert
+        assert!(options.is_ok_and(|options| options.watch));
+        assert!(missing_script.is_err());
+    }
+
+    #[test]
+    fn test_parse_deterministic_flag() {
+        // Act
+        let options = Options::parse(&make_args(&["--deterministic", "script.lox", "b"]));
+        // Assert
+        assert!(options.is_ok_and(|options| options.deterministic && options.script_args == ["b"]));
+    }
+
+    #[test]
+    fn test_parse_ieee_division_flag() {
+        // Act
+        let options = Options::parse(&make_args(&["--ieee-division", "script.lox"]));
+        // Assert
+        assert!(options.is_ok_and(|options| options.ieee_division && !options.deterministic));
+    }
+
+    #[test]
+    fn test_parse_strict_flag() {
+        // Act
+        let options = Options::parse(&make_args(&["--strict", "script.lox"]));
+        // Assert
+        assert!(options.is_ok_and(|options| options.strict));
+    }
+
+    #[test]
+    fn test_parse_trace_export_flag() {
+        // Act
+        let options = Options::parse(&make_args(&["--trace-export", "script.lox"]));
+        let watched = Options::parse(&make_args(&["--trace-export", "--watch", "script.lox"]));
+        // Assert
+        assert!(options.is_ok_and(|options| options.trace_export && !options.trace));
+        assert!(watched.is_err());
+    }
+
+    #[test]
+    fn test_parse_stats_flag() {
+        // Act
+        let options = Options::parse(&make_args(&["--stats", "script.lox"]));
+        // Assert
+        assert!(options.is_ok_and(|options| options.stats && !options.profile));
+    }
+
+    #[test]
+    fn test_parse_debug_flag() {
+        // Act
+        let options = Options::parse(&make_args(&["--debug=scope,calls", "script.lox"]));
+        let all = Options::parse(&make_args(&["--debug", "script.lox"]));
+        let unknown = Options::parse(&make_args(&["--debug=gc", "script.lox"]));
+        // Assert
+        assert_eq!(
+            options.map(|options| options.debug),
+            Ok(DebugCategories::default()
+                .with(DebugCategory::Scope)
+                .with(DebugCategory::Calls))
+        );
+        assert_eq!(all.map(|options| options.debug), Ok(DebugCategories::all()));
+        assert_eq!(
+            unknown.map(|options| options.debug),
+            Err("Unknown debug category 'gc'.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_explain() {
+        // Act
+        let options = Options::parse(&make_args(&["--explain", "P0103"]));
+        let missing = Options::parse(&make_args(&["--explain"]));
+        // Assert
+        assert_eq!(
+            options.map(|options| options.command),
+            Ok(Command::Explain("P0103".to_string()))
+        );
+        assert_eq!(
+            missing,
+            Err("Option '--explain' expects a single error code.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_lang() {
+        // Act
+        let spanish = Options::parse(&make_args(&["--lang=es", "script.lox"]));
+        let unknown = Options::parse(&make_args(&["--lang=xx", "script.lox"]));
+        // Assert
+        assert_eq!(
+            spanish.map(|options| options.lang),
+            Ok(Some(Language::Spanish))
+        );
+        assert_eq!(
+            unknown.map(|options| options.lang),
+            Err("Unknown language 'xx'.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_flags_win_over_config() {
+        // Arrange
+        let config = ProjectConfig::parse(
+            "strict = true\nwarnings = \"deny\"\nallow = [\"fs\"]\nprelude = \"lib.lox\"\ntab_width = 8",
+            std::path::Path::new("project"),
+        )
+        .unwrap();
+        // Act
+        let options = Options::parse(&make_args(&["--warnings=allow", "--allow=time", "a.lox"]))
+            .unwrap()
+            .with_config(&config);
+        let tab_width = Options::parse(&make_args(&["--tab-width=2", "a.lox"]))
+            .unwrap()
+            .with_config(&config);
+        // Assert
+        assert!(options.strict);
+        assert_eq!(options.warnings, Some(WarningLevel::Allow));
+        assert_eq!(options.allow, Some(Capabilities::parse("time").unwrap()));
+        assert_eq!(options.tab_width, Some(8));
+        assert_eq!(tab_width.tab_width, Some(2));
+        assert_eq!(
+            options.prelude,
+            Some(
+                std::path::Path::new("project")
+                    .join("lib.lox")
+                    .display()
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_no_prelude_flag() {
+        // Act
+        let options = Options::parse(&make_args(&["--no-prelude", "script.lox"]));
+        // Assert
+        assert!(options.is_ok_and(|options| options.no_prelude));
+    }
+
+    #[test]
+    fn test_parse_allow_flag() {
+        // Act
+        let options = Options::parse(&make_args(&["--allow=fs,time", "script.lox"]));
+        let unknown = Options::parse(&make_args(&["--allow=gpu", "script.lox"]));
+        // Assert
+        assert_eq!(
+            options.map(|options| options.allow),
+            Ok(Some(Capabilities::parse("time,fs").unwrap()))
+        );
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn test_parse_fs_root_flag() {
+        // Act
+        let options = Options::parse(&make_args(&["--fs-root=data", "script.lox"]));
+        // Assert
+        assert!(options.is_ok_and(|options| options.fs_root.as_deref() == Some("data")));
+    }
+
+    #[test]
+    fn test_parse_closures_flag() {
+        // Act
+        let options = Options::parse(&make_args(&["--closures", "script.lox"]));
+        let watched = Options::parse(&make_args(&["--closures", "--watch", "script.lox"]));
+        // Assert
+        assert!(options.is_ok_and(|options| options.closures));
+        assert!(watched.is_err());
+    }
+
+    #[test]
+    fn test_parse_cache_flag() {
+        // Act
+        let options = Options::parse(&make_args(&["--cache=.lox-cache", "script.lox"]));
+        let prompt = Options::parse(&make_args(&["--cache=.lox-cache"]));
+        let timed = Options::parse(&make_args(&["--cache=.lox-cache", "--time", "script.lox"]));
+        // Assert
+        assert!(options.is_ok_and(|options| options.cache.as_deref() == Some(".lox-cache")));
+        assert!(prompt.is_err());
+        assert!(timed.is_err());
+    }
+
+    #[test]
+    fn test_parse_time_flag() {
+        // Act
+        let options = Options::parse(&make_args(&["--time", "script.lox"]));
+        // Assert
+        assert!(options.is_ok_and(|options| options.time));
+    }
+
+    #[test]
+    fn test_parse_unknown_flag() {
+        // Act
+        let options = Options::parse(&make_args(&["--nope", "script.lox"]));
+        // Assert
+        assert!(options.is_err());
+    }
+
+    #[test]
+    fn test_parse_fmt_command() {
+        // Act
+        let options = Options::parse(&make_args(&["fmt", "--check", "script.lox"]));
+        let minify = Options::parse(&make_args(&["fmt", "--minify", "script.lox"]));
+        let missing_script = Options::parse(&make_args(&["fmt", "--check"]));
+        // Assert
+        assert_eq!(
+            options,
+            Ok(Options {
+                command: Command::Fmt {
+                    check: true,
+                    minify: false
+                },
+                script: Some("script.lox".to_string()),
+                ..Options::default()
+            })
+        );
+        assert_eq!(
+            minify.map(|options| options.command),
+            Ok(Command::Fmt {
+                check: false,
+                minify: true
+            })
+        );
+        assert!(missing_script.is_err());
+    }
+
+    #[test]
+    fn test_parse_lint_command() {
+        // Act
+        let options = Options::parse(&make_args(&[
+            "lint",
+            "--disable",
+            "shadowing,W0604",
+            "--max-params",
+            "3",
+            "script.lox",
+        ]));
+        let unknown_rule = Options::parse(&make_args(&["lint", "--disable", "nope", "a.lox"]));
+        // Assert
+        assert_eq!(
+            options.map(|options| options.command),
+            Ok(Command::Lint(LintConfig {
+                disabled: vec![Rule::Shadowing, Rule::EmptyBlock],
+                max_params: 3,
+            }))
+        );
+        assert!(unknown_rule.is_err());
+    }
+
+    #[test]
+    fn test_parse_replay_command() {
+        // Act
+        let writes = Options::parse(&make_args(&["replay", "run.trace"]));
+        let query = Options::parse(&make_args(&["replay", "run.trace", "x", "42", "3"]));
+        let bad_line = Options::parse(&make_args(&["replay", "run.trace", "x", "0"]));
+        // Assert
+        assert_eq!(
+            writes.map(|options| options.command),
+            Ok(Command::Replay(None))
+        );
+        assert_eq!(
+            query.map(|options| options.command),
+            Ok(Command::Replay(Some(TraceQuery {
+                name: "x".to_string(),
+                line: 42,
+                iteration: 3,
+            })))
+        );
+        assert!(bad_line.is_err());
+    }
+
+    #[test]
+    fn test_parse_serve_command() {
+        // Act
+        let default = Options::parse(&make_args(&["serve"]));
+        let port = Options::parse(&make_args(&["serve", "--port", "9000", "--allow=time,fs"]));
+        let invalid = Options::parse(&make_args(&["serve", "--port=http"]));
+        // Assert
+        assert_eq!(
+            default.map(|options| (options.command, options.allow)),
+            Ok((Command::Serve { port: DEFAULT_PORT }, None))
+        );
+        assert_eq!(
+            port.map(|options| (options.command, options.allow)),
+            Ok((
+                Command::Serve { port: 9000 },
+                Some(Capabilities::parse("fs,time").unwrap())
+            ))
+        );
+        assert_eq!(
+            invalid.map(|options| options.command),
+            Err("Expected a port number, got 'http'.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_coverage_flag() {
+        // Act
+        let summary = Options::parse(&make_args(&["--coverage", "script.lox"]));
+        let lcov = Options::parse(&make_args(&["--coverage=lcov", "script.lox"]));
+        let unknown = Options::parse(&make_args(&["--coverage=html", "script.lox"]));
+        // Assert
+        assert_eq!(
+            summary.map(|options| options.coverage),
+            Ok(Some(CoverageFormat::Summary))
+        );
+        assert_eq!(
+            lcov.map(|options| options.coverage),
+            Ok(Some(CoverageFormat::Lcov))
+        );
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn test_parse_emit_flag() {
+        // Act
+        let highlight = Options::parse(&make_args(&["--emit=highlight-json", "script.lox"]));
+        let unknown = Options::parse(&make_args(&["--emit=tokens", "script.lox"]));
+        let missing_script = Options::parse(&make_args(&["--emit=highlight-json"]));
+        let types = Options::parse(&make_args(&["--types", "script.lox"]));
+        // Assert
+        assert_eq!(
+            highlight.map(|options| options.emit),
+            Ok(Some(Emit::HighlightJson))
+        );
+        assert_eq!(types.map(|options| options.emit), Ok(Some(Emit::Types)));
+        assert!(unknown.is_err());
+        assert!(missing_script.is_err());
+    }
+
+    #[test]
+    fn test_parse_transpile_command() {
+        // Act
+        let options = Options::parse(&make_args(&["transpile", "--target=js", "script.lox"]));
+        let unknown = Options::parse(&make_args(&["transpile", "--target=c", "script.lox"]));
+        // Assert
+        assert_eq!(
+            options,
+            Ok(Options {
+                command: Command::Transpile(Target::JavaScript),
+                script: Some("script.lox".to_string()),
+                ..Options::default()
+            })
+        );
+        assert!(unknown.is_err());
+    }
+}
@@ -0,0 +1,521 @@
+//! Closure compilation (`--closures`): every resolved statement and expression is turned into a
+//! boxed closure once, before the run. Running them skips the `accept` dispatch of the visitor
+//! and looks the resolved distance of a variable up once instead of on every access.
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    ops::Deref,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    ast::Spanned,
+    environment::{Environment, EnvironmentRef},
+    error::LoxErrorResult,
+    expr::*,
+    interpreter::Interpreter,
+    lox_function::LoxFunction,
+    lox_generator::LoxIterator,
+    object::Object,
+    shared::{MaybeSync, Rc},
+    stmt::*,
+    token::TokenType,
+};
+
+pub type CompiledExpr = Box<dyn Compiled<Object>>;
+pub type CompiledStmt = Box<dyn Compiled<()>>;
+type CompiledArguments = Box<dyn Compiled<Vec<Object>>>;
+
+/// A compiled statement or expression, `Send + Sync` with the `sync` feature
+pub trait Compiled<T>: Fn(&mut Interpreter) -> Result<T, LoxErrorResult> + MaybeSync {}
+
+impl<T, F> Compiled<T> for F where F: Fn(&mut Interpreter) -> Result<T, LoxErrorResult> + MaybeSync {}
+
+/// Slot of a global, looked up on the first run of the closure and kept for the next ones
+struct CachedSlot(AtomicUsize);
+
+impl CachedSlot {
+    const EMPTY: usize = usize::MAX;
+
+    fn new() -> CachedSlot {
+        CachedSlot(AtomicUsize::new(CachedSlot::EMPTY))
+    }
+
+    fn get(&self) -> Option<usize> {
+        let slot = self.0.load(Ordering::Relaxed);
+        (slot != CachedSlot::EMPTY).then_some(slot)
+    }
+
+    fn set(&self, slot: usize) {
+        self.0.store(slot, Ordering::Relaxed);
+    }
+}
+
+/// Compiled statements run one after the other, a script or the body of a function
+pub struct CompiledBlock {
+    statements: Vec<CompiledStmt>,
+}
+
+impl Deref for CompiledBlock {
+    type Target = [CompiledStmt];
+
+    fn deref(&self) -> &[CompiledStmt] {
+        &self.statements
+    }
+}
+
+impl Debug for CompiledBlock {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "<compiled block of {} statements>",
+            self.statements.len()
+        )
+    }
+}
+
+/// Runs compiled statements in `environment`, like `Interpreter::execute_block`
+pub fn run_block(
+    interpreter: &mut Interpreter,
+    block: &CompiledBlock,
+    environment: EnvironmentRef,
+) -> Result<(), LoxErrorResult> {
+    run_in(interpreter, environment, |interpreter| {
+        block
+            .iter()
+            .try_for_each(|statement| statement(interpreter))
+    })
+}
+
+fn run_in(
+    interpreter: &mut Interpreter,
+    environment: EnvironmentRef,
+    run: impl FnOnce(&mut Interpreter) -> Result<(), LoxErrorResult>,
+) -> Result<(), LoxErrorResult> {
+    let previous_env = interpreter.replace_environment(environment);
+    let result = run(interpreter);
+    interpreter.replace_environment(previous_env);
+    result
+}
+
+/// Compiles statements resolved for `interpreter`, which then runs them with
+/// `Interpreter::interpret_compiled`. The functions they declare run compiled bodies,
+/// generators and `super` are still walked.
+pub struct ClosureCompiler<'a> {
+    interpreter: &'a Interpreter,
+}
+
+impl ClosureCompiler<'_> {
+    pub fn new(interpreter: &Interpreter) -> ClosureCompiler<'_> {
+        ClosureCompiler { interpreter }
+    }
+
+    pub fn compile(&self, statements: &[Stmt]) -> CompiledBlock {
+        CompiledBlock {
+            statements: statements.iter().map(|stmt| self.statement(stmt)).collect(),
+        }
+    }
+
+    /// The hooks still see every statement before it runs
+    fn statement(&self, stmt: &Stmt) -> CompiledStmt {
+        let run = self.statement_body(stmt);
+        let stmt = stmt.clone();
+        Box::new(move |interpreter| {
+            interpreter.statement_hooks(&stmt)?;
+            run(interpreter)
+        })
+    }
+
+    fn statement_body(&self, stmt: &Stmt) -> CompiledStmt {
+        match stmt {
+            Stmt::Expression(stmt) => {
+                let expression = self.expression(&stmt.expression);
+                Box::new(move |interpreter| expression(interpreter).map(|_| ()))
+            }
+            Stmt::Print(stmt) => {
+                let expression = self.expression(&stmt.expression);
+                let line = stmt.keyword.line;
+                Box::new(move |interpreter| {
+                    let value = expression(interpreter)?;
+                    interpreter.print(&value.to_string(), line)
+                })
+            }
+            Stmt::Var(stmt) => {
+                let name = stmt.name.clone();
+                let initializer = stmt.initializer.as_ref().map(|expr| self.expression(expr));
+                Box::new(move |interpreter| {
+                    if interpreter.keeps_global(&name) {
+                        return Ok(());
+                    }
+                    let value = match &initializer {
+                        Some(initializer) => initializer(interpreter)?,
+                        None => Object::Nil,
+                    };
+                    interpreter.define_variable(&name, value);
+                    Ok(())
+                })
+            }
+            Stmt::Block(stmt) => {
+                let block = self.compile(&stmt.statements);
+                Box::new(move |interpreter| {
+                    let environment = Environment::new_enclosing(interpreter.environment());
+                    run_block(interpreter, &block, environment)
+                })
+            }
+            Stmt::If(stmt) => {
+                let condition = self.expression(&stmt.condition);
+                let then_branch = self.statement(&stmt.then_branch);
+                let else_branch = stmt.else_branch.as_ref().map(|stmt| self.statement(stmt));
+                let line = stmt.keyword.line;
+                Box::new(move |interpreter| {
+                    let value = condition(interpreter)?;
+                    if interpreter.is_condition_true(value, line, "if")? {
+                        then_branch(interpreter)
+                    } else if let Some(else_branch) = &else_branch {
+                        else_branch(interpreter)
+                    } else {
+                        Ok(())
+                    }
+                })
+            }
+            Stmt::While(stmt) => {
+                let condition = self.expression(&stmt.condition);
+                let body = self.statement(&stmt.body);
+                let (line, label) = (stmt.keyword.line, stmt.label.clone());
+                Box::new(move |interpreter| {
+                    loop {
+                        let value = condition(interpreter)?;
+                        if !interpreter.is_condition_true(value, line, "while")? {
+                            break;
+                        }
+                        match body(interpreter) {
+                            Err(err) if err.breaks(label.as_ref()) => break,
+                            Err(err) if !err.continues(label.as_ref()) => return Err(err),
+                            _ => {}
+                        }
+                    }
+                    Ok(())
+                })
+            }
+            Stmt::For(stmt) => {
+                let initializer = stmt.initializer.as_ref().map(|stmt| self.statement(stmt));
+                let condition = stmt.condition.as_ref().map(|expr| self.expression(expr));
+                let increment = stmt.increment.as_ref().map(|expr| self.expression(expr));
+                let body = self.statement(&stmt.body);
+                let (line, label) = (stmt.keyword.line, stmt.label.clone());
+                Box::new(move |interpreter| {
+                    // The initializer lives in its own scope, as in `Interpreter::visit_for_stmt`
+                    let scope = Environment::new_enclosing(interpreter.environment());
+                    run_in(interpreter, scope, |interpreter| {
+                        if let Some(initializer) = &initializer {
+                            initializer(interpreter)?;
+                        }
+                        loop {
+                            if let Some(condition) = &condition {
+                                let value = condition(interpreter)?;
+                                if !interpreter.is_condition_true(value, line, "for")? {
+                                    break;
+                                }
+                            }
+                            match body(interpreter) {
+                                Err(err) if err.breaks(label.as_ref()) => break,
+                                Err(err) if !err.continues(label.as_ref()) => return Err(err),
+                                _ => {}
+                            }
+                            if let Some(increment) = &increment {
+                                increment(interpreter)?;
+                            }
+                        }
+                        Ok(())
+                    })
+                })
+            }
+            Stmt::ForIn(stmt) => {
+                let iterable = self.expression(&stmt.iterable);
+                let body = self.statement(&stmt.body);
+                let (line, name, label) =
+                    (stmt.keyword.line, stmt.name.clone(), stmt.label.clone());
+                Box::new(move |interpreter| {
+                    let mut values = LoxIterator::new(iterable(interpreter)?, line)?;
+                    while let Some(value) = values.next(interpreter, line)? {
+                        // Every iteration has its own variable, closures made in the body keep theirs
+                        interpreter.variable_written(&name, &value);
+                        let environment = Environment::new_enclosing(interpreter.environment());
+                        environment.borrow_mut().define(name.lexeme.clone(), value);
+                        match run_in(interpreter, environment, |interpreter| body(interpreter)) {
+                            Err(err) if err.breaks(label.as_ref()) => break,
+                            Err(err) if !err.continues(label.as_ref()) => return Err(err),
+                            _ => {}
+                        }
+                    }
+                    Ok(())
+                })
+            }
+            Stmt::Break(stmt) => {
+                let label = stmt.label.clone();
+                Box::new(move |_| Err(LoxErrorResult::break_signal(label.as_ref())))
+            }
+            Stmt::Continue(stmt) => {
+                let label = stmt.label.clone();
+                Box::new(move |_| Err(LoxErrorResult::continue_signal(label.as_ref())))
+            }
+            Stmt::Function(stmt) => {
+                let body = Rc::new(self.compile(&stmt.body));
+                let declaration = stmt.clone();
+                Box::new(move |interpreter| {
+                    let function = LoxFunction::new(&declaration, interpreter.environment(), false)
+                        .with_compiled_body(Rc::clone(&body));
+                    interpreter
+                        .environment()
+                        .borrow_mut()
+                        .define(declaration.name.lexeme(), Object::Function(function));
+                    Ok(())
+                })
+            }
+            Stmt::Return(stmt) => match stmt.value.as_deref() {
+                // `return f(...);` is a tail call, as in `Interpreter::visit_return_stmt`
+                Some(Expr::Call(call)) => {
+                    let callee = self.expression(&call.callee);
+                    let arguments = self.arguments(&call.arguments);
+                    let paren = call.paren.clone();
+                    Box::new(move |interpreter| {
+                        let callee = callee(interpreter)?;
+                        let arguments = arguments(interpreter)?;
+                        interpreter.tail_call_value(callee, arguments, &paren)
+                    })
+                }
+                Some(value) => {
+                    let value = self.expression(value);
+                    Box::new(move |interpreter| {
+                        Err(LoxErrorResult::return_signal(value(interpreter)?))
+                    })
+                }
+                None => Box::new(|_| Err(LoxErrorResult::return_signal(Object::Nil))),
+            },
+            Stmt::Class(stmt) => {
+                let bodies: HashMap<String, Rc<CompiledBlock>> = stmt
+                    .methods
+                    .iter()
+                    .filter_map(|method| match method {
+                        Stmt::Function(method) => Some((
+                            method.name.lexeme.clone(),
+                            Rc::new(self.compile(&method.body)),
+                        )),
+                        _ => None,
+                    })
+                    .collect();
+                let declaration = stmt.clone();
+                Box::new(move |interpreter| {
+                    interpreter.declare_class(&declaration, |method, closure, is_initializer| {
+                        let function = LoxFunction::new(method, closure, is_initializer);
+                        match bodies.get(&method.name.lexeme) {
+                            Some(body) => function.with_compiled_body(Rc::clone(body)),
+                            None => function,
+                        }
+                    })
+                })
+            }
+            // Pauses happen in the hooks, which already saw the statement
+            Stmt::Debugger(_) => Box::new(|_| Ok(())),
+            // Only reached outside of a generator, the walker reports it
+            Stmt::Yield(_) => {
+                let stmt = stmt.clone();
+                Box::new(move |interpreter| stmt.accept(interpreter))
+            }
+        }
+    }
+
+    fn expression(&self, expr: &Expr) -> CompiledExpr {
+        match expr {
+            Expr::Literal(literal) => {
+                let value = literal.value.clone();
+                Box::new(move |_| Ok(value.clone()))
+            }
+            Expr::Grouping(grouping) => self.expression(&grouping.expression),
+            Expr::Unary(unary) => {
+                let right = self.expression(&unary.right);
+                let operator = unary.operator.clone();
+                Box::new(move |interpreter| {
+                    let right = right(interpreter)?;
+                    interpreter.unary(&operator, right)
+                })
+            }
+            Expr::Binary(binary) => {
+                let left = self.expression(&binary.left);
+                let right = self.expression(&binary.right);
+                let operator = binary.operator.clone();
+                Box::new(move |interpreter| {
+                    let left = left(interpreter)?;
+                    let right = right(interpreter)?;
+                    interpreter.binary(&operator, left, right)
+                })
+            }
+            Expr::Logical(logical) => {
+                let left = self.expression(&logical.left);
+                let right = self.expression(&logical.right);
+                let is_or = logical.operator.is(TokenType::Or);
+                Box::new(move |interpreter| {
+                    let left = left(interpreter)?;
+                    // `or` stops at a truthy operand, `and` at a falsy one
+                    match interpreter.is_truthy(left.clone()) == is_or {
+                        true => Ok(left),
+                        false => right(interpreter),
+                    }
+                })
+            }
+            Expr::Ternary(ternary) => {
+                let condition = self.expression(&ternary.condition);
+                let then_branch = self.expression(&ternary.then_branch);
+                let else_branch = self.expression(&ternary.else_branch);
+                let line = ternary.condition.span().line;
+                Box::new(move |interpreter| {
+                    let value = condition(interpreter)?;
+                    match interpreter.is_condition_true(value, line, "?:")? {
+                        true => then_branch(interpreter),
+                        false => else_branch(interpreter),
+                    }
+                })
+            }
+            Expr::Variable(variable) => {
+                let name = variable.name.clone();
+                match self.distance(expr) {
+                    Some(distance) => {
+                        Box::new(move |interpreter| interpreter.variable_at(&name, Some(distance)))
+                    }
+                    // The slot of the global is cached in the closure once found
+                    None => {
+                        let cached = CachedSlot::new();
+                        Box::new(move |interpreter| {
+                            let slot = interpreter.global_slot(&name, cached.get())?;
+                            cached.set(slot);
+                            Ok(interpreter.read_global(slot))
+                        })
+                    }
+                }
+            }
+            Expr::This(this) => {
+                let (keyword, distance) = (this.keyword.clone(), self.distance(expr));
+                Box::new(move |interpreter| interpreter.variable_at(&keyword, distance))
+            }
+            Expr::Assign(assign) => {
+                let value = self.expression(&assign.value);
+                let name = assign.name.clone();
+                match self.distance(expr) {
+                    Some(distance) => Box::new(move |interpreter| {
+                        let value = value(interpreter)?;
+                        interpreter.assign_variable(&name, Some(distance), value)
+                    }),
+                    None => {
+                        let cached = CachedSlot::new();
+                        Box::new(move |interpreter| {
+                            let value = value(interpreter)?;
+                            let slot = interpreter.global_slot(&name, cached.get())?;
+                            cached.set(slot);
+                            Ok(interpreter.write_global(&name, slot, value))
+                        })
+                    }
+                }
+            }
+            Expr::Call(call) => {
+                let callee = self.expression(&call.callee);
+                let arguments = self.arguments(&call.arguments);
+                let paren = call.paren.clone();
+                Box::new(move |interpreter| {
+                    let callee = callee(interpreter)?;
+                    let arguments = arguments(interpreter)?;
+                    interpreter.call_value(callee, arguments, &paren)
+                })
+            }
+            Expr::Get(get) => {
+                let object = self.expression(&get.object);
+                let name = get.name.clone();
+                Box::new(move |interpreter| {
+                    let object = object(interpreter)?;
+                    interpreter.get_property(object, &name)
+                })
+            }
+            Expr::Set(set) => {
+                let object = self.expression(&set.object);
+                let value = self.expression(&set.value);
+                let name = set.name.clone();
+                Box::new(move |interpreter| {
+                    let instance = Interpreter::fields_of(object(interpreter)?, &name)?;
+                    let value = value(interpreter)?;
+                    interpreter.set_field(&instance, &name, value)
+                })
+            }
+            Expr::Super(_) => {
+                let expr = expr.clone();
+                Box::new(move |interpreter| interpreter.evaluate(&expr))
+            }
+        }
+    }
+
+    /// Evaluates the arguments of a call from left to right
+    fn arguments(&self, arguments: &[Expr]) -> CompiledArguments {
+        let arguments: Vec<CompiledExpr> = arguments
+            .iter()
+            .map(|argument| self.expression(argument))
+            .collect();
+        Box::new(move |interpreter| {
+            let mut values = interpreter.argument_buffer();
+            for argument in &arguments {
+                match argument(interpreter) {
+                    Ok(value) => values.push(value),
+                    Err(err) => {
+                        interpreter.recycle_arguments(values);
+                        return Err(err);
+                    }
+                }
+            }
+            Ok(values)
+        })
+    }
+
+    /// Scopes between the variable of `expr` and its declaration, `None` for a global
+    fn distance(&self, expr: &Expr) -> Option<usize> {
+        self.interpreter.locals.get(expr).copied()
+    }
+}
+
+#[cfg(test)]
+mod closure_compiler_tests {
+    use super::*;
+    use crate::{run_captured, run_compiled, shared::RefCell, stats::Stats};
+
+    #[test]
+    fn test_compiled_run_matches_walker() {
+        // Arrange
+        let source =
+            "fun count(n) { var i = 0; while (true) { i = i + 1; yield i; if (i == n) return; } }
+class Shape { init(name) { this.name = name; } area() { return 0; } }
+class Square < Shape { init(side) { super.init(\"square\"); this.side = side; }
+  area() { return this.side * this.side + super.area(); } }
+var total = 0;
+outer: for (var i = 0; i < 5; i = i + 1) {
+  for (var x in count(3)) { if (x == 2) continue outer; total = total + x; }
+}
+fun loop(n, acc) { if (n == 0) return acc; return loop(n - 1, acc + n); }
+var square = Square(3);
+print total;
+print square.area() + loop(1000, 0);
+print square.name + (total > 3 ? \" many\" : \" few\");
+print missing;";
+        let walked = run_captured(source, &mut Interpreter::new());
+        let mut interpreter = Interpreter::new();
+        let stats = Rc::new(RefCell::new(Stats::new()));
+        interpreter.add_hooks(stats.clone());
+        let captured = interpreter.capture_output();
+        // Act
+        let result = run_compiled(source.to_string(), &mut interpreter);
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(captured.borrow().stdout, walked.stdout);
+        assert_eq!(walked.stdout, "5\n500509\n\"square many\"\n");
+        assert!(captured
+            .borrow()
+            .stderr
+            .contains("Undefined variable 'missing'."));
+        assert!(stats.borrow().statements > 0);
+    }
+}
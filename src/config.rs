@@ -0,0 +1,250 @@
+//! Project settings read from `lox.toml`, or `.loxrc`, in the directory of the script, so a
+//! team runs and formats its scripts the same way. Both files use the same subset of TOML:
+//!
+//! ```toml
+//! strict = true
+//! warnings = "deny"       # "allow", "warn" or "deny"
+//! allow = ["fs", "time"]  # capabilities, like --allow
+//! prelude = "lib.lox"     # run before the script, relative to the file
+//! tab_width = 4           # columns of a tab in diagnostics, like --tab-width
+//!
+//! [fmt]
+//! indent_width = 4
+//! max_width = 100
+//! minify = false
+//! ```
+//!
+//! Flags given on the command line win over the file.
+use std::path::{Path, PathBuf};
+
+use crate::{capabilities::Capabilities, error::WarningLevel, formatter::Formatter};
+
+/// Files looked for, the first one found is read
+pub const CONFIG_FILES: [&str; 2] = ["lox.toml", ".loxrc"];
+
+/// Settings of the file, `None` for the ones it leaves out
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectConfig {
+    pub strict: Option<bool>,
+    pub warnings: Option<WarningLevel>,
+    pub allow: Option<Capabilities>,
+    /// Resolved against the directory of the file
+    pub prelude: Option<PathBuf>,
+    pub tab_width: Option<usize>,
+    pub indent_width: Option<usize>,
+    pub max_width: Option<usize>,
+    pub minify: Option<bool>,
+}
+
+/// Value of a setting
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Bool(bool),
+    Integer(usize),
+    Array(Vec<String>),
+}
+
+impl Value {
+    fn parse(text: &str) -> Option<Value> {
+        match text {
+            "true" => return Some(Value::Bool(true)),
+            "false" => return Some(Value::Bool(false)),
+            _ => {}
+        }
+        if let Some(items) = text
+            .strip_prefix('[')
+            .and_then(|text| text.strip_suffix(']'))
+        {
+            return items
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| match Value::parse(item) {
+                    Some(Value::String(item)) => Some(item),
+                    _ => None,
+                })
+                .collect::<Option<Vec<String>>>()
+                .map(Value::Array);
+        }
+        if let Some(text) = text
+            .strip_prefix('"')
+            .and_then(|text| text.strip_suffix('"'))
+        {
+            return (!text.contains('"')).then(|| Value::String(text.to_string()));
+        }
+        text.parse().ok().map(Value::Integer)
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Bool(_) => "a boolean",
+            Value::Integer(_) => "a number",
+            Value::Array(_) => "an array",
+        }
+    }
+}
+
+impl ProjectConfig {
+    /// Settings of the first file of `CONFIG_FILES` in `dir`, the defaults without one
+    pub fn find(dir: &Path) -> Result<ProjectConfig, String> {
+        for name in CONFIG_FILES {
+            let path = dir.join(name);
+            if !path.is_file() {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path)
+                .map_err(|err| format!("Could not read '{}': {err}", path.display()))?;
+            return ProjectConfig::parse(&text, dir)
+                .map_err(|message| format!("{}: {message}", path.display()));
+        }
+        Ok(ProjectConfig::default())
+    }
+
+    /// Settings of a file in `dir`
+    pub fn parse(text: &str, dir: &Path) -> Result<ProjectConfig, String> {
+        let mut config = ProjectConfig::default();
+        let mut section = String::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {message}", index + 1);
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                section = name.trim().to_string();
+                if section != "fmt" {
+                    return Err(error(format!("Unknown section '[{section}]'.")));
+                }
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("Expected 'key = value', got '{line}'.")))?;
+            let key = key.trim();
+            let value = Value::parse(value.trim())
+                .ok_or_else(|| error(format!("Invalid value for '{key}'.")))?;
+            config.set(&section, key, value, dir).map_err(error)?;
+        }
+        Ok(config)
+    }
+
+    fn set(&mut self, section: &str, key: &str, value: Value, dir: &Path) -> Result<(), String> {
+        let expected =
+            |kind: &str| format!("Setting '{key}' expects {kind}, got {}.", value.kind());
+        match (section, key, &value) {
+            ("", "strict", Value::Bool(strict)) => self.strict = Some(*strict),
+            ("", "strict", _) => return Err(expected("a boolean")),
+            ("", "warnings", Value::String(level)) => {
+                self.warnings = Some(WarningLevel::from_name(level).ok_or_else(|| {
+                    format!("Unknown warning level '{level}', expected allow, warn or deny.")
+                })?);
+            }
+            ("", "warnings", _) => return Err(expected("a string")),
+            ("", "allow", Value::Array(names)) => {
+                self.allow = Some(Capabilities::parse(&names.join(","))?);
+            }
+            ("", "allow", _) => return Err(expected("an array")),
+            ("", "prelude", Value::String(path)) => self.prelude = Some(dir.join(path)),
+            ("", "prelude", _) => return Err(expected("a string")),
+            ("", "tab_width", Value::Integer(width)) => self.tab_width = Some(*width),
+            ("", "tab_width", _) => return Err(expected("a number")),
+            ("fmt", "indent_width", Value::Integer(width)) => self.indent_width = Some(*width),
+            ("fmt", "max_width", Value::Integer(width)) => self.max_width = Some(*width),
+            ("fmt", "indent_width" | "max_width", _) => return Err(expected("a number")),
+            ("fmt", "minify", Value::Bool(minify)) => self.minify = Some(*minify),
+            ("fmt", "minify", _) => return Err(expected("a boolean")),
+            ("", _, _) => return Err(format!("Unknown setting '{key}'.")),
+            _ => return Err(format!("Unknown setting '{key}' in '[{section}]'.")),
+        }
+        Ok(())
+    }
+
+    /// Formatter with the `[fmt]` settings, `minify` given on the command line wins
+    pub fn formatter(&self, minify: bool) -> Formatter {
+        let formatter = match minify || self.minify.unwrap_or(false) {
+            true => Formatter::minified(),
+            false => Formatter::new(),
+        };
+        let formatter = match self.indent_width {
+            Some(width) => formatter.with_indent_width(width),
+            None => formatter,
+        };
+        match self.max_width {
+            Some(width) => formatter.with_max_width(width),
+            None => formatter,
+        }
+    }
+}
+
+/// The line without its `#` comment, a `#` inside a string is kept
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (index, char) in line.char_indices() {
+        match char {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+    use crate::capabilities::Capability;
+
+    #[test]
+    fn test_parse_config() {
+        // Arrange
+        let text = "# Team settings\nstrict = true\nwarnings = \"deny\"\nallow = [\"fs\", \"time\"]\nprelude = \"lib/#prelude.lox\" # helpers\ntab_width = 8\n\n[fmt]\nindent_width = 4\nminify = false\n";
+        // Act
+        let config = ProjectConfig::parse(text, Path::new("project"));
+        // Assert
+        assert_eq!(
+            config,
+            Ok(ProjectConfig {
+                strict: Some(true),
+                warnings: Some(WarningLevel::Deny),
+                allow: Some(
+                    Capabilities::none()
+                        .grant(Capability::Fs)
+                        .grant(Capability::Time)
+                ),
+                prelude: Some(Path::new("project").join("lib/#prelude.lox")),
+                tab_width: Some(8),
+                indent_width: Some(4),
+                max_width: None,
+                minify: Some(false),
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        // Act
+        let unknown = ProjectConfig::parse("strict = true\ncolor = 1", Path::new("."));
+        let mistyped = ProjectConfig::parse("[fmt]\nindent_width = \"4\"", Path::new("."));
+        let level = ProjectConfig::parse("warnings = \"loud\"", Path::new("."));
+        let section = ProjectConfig::parse("[lint]", Path::new("."));
+        // Assert
+        assert_eq!(unknown, Err("line 2: Unknown setting 'color'.".to_string()));
+        assert_eq!(
+            mistyped,
+            Err("line 2: Setting 'indent_width' expects a number, got a string.".to_string())
+        );
+        assert_eq!(
+            level,
+            Err("line 1: Unknown warning level 'loud', expected allow, warn or deny.".to_string())
+        );
+        assert_eq!(
+            section,
+            Err("line 1: Unknown section '[lint]'.".to_string())
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{expr::*, hooks::InterpreterHooks, interpreter::Interpreter, stmt::*};
+
+/// Output format of `--coverage`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CoverageFormat {
+    /// Percentage of executed lines and the list of the missed ones
+    #[default]
+    Summary,
+    /// The source with the hit count of every executable line
+    Annotated,
+    /// An lcov tracefile, written to `lcov.info`
+    Lcov,
+}
+
+impl CoverageFormat {
+    pub fn from_name(name: &str) -> Option<CoverageFormat> {
+        match name {
+            "summary" => Some(CoverageFormat::Summary),
+            "annotated" => Some(CoverageFormat::Annotated),
+            "lcov" => Some(CoverageFormat::Lcov),
+            _ => None,
+        }
+    }
+}
+
+/// How many times each source line started executing a statement
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    hits: BTreeMap<usize, usize>,
+}
+
+impl Coverage {
+    pub fn new() -> Coverage {
+        Coverage::default()
+    }
+
+    pub fn record(&mut self, statement: &Stmt) {
+        if let Some(line) = statement_line(statement) {
+            *self.hits.entry(line).or_default() += 1;
+        }
+    }
+
+    pub fn hits(&self, line: usize) -> usize {
+        self.hits.get(&line).copied().unwrap_or(0)
+    }
+
+    /// Renders the report for the script the statements were parsed from
+    pub fn report(
+        &self,
+        format: CoverageFormat,
+        path: &str,
+        source: &str,
+        statements: &[Stmt],
+    ) -> String {
+        let executable = executable_lines(statements);
+        match format {
+            CoverageFormat::Summary => self.summary(path, &executable),
+            CoverageFormat::Annotated => self.annotated(source, &executable),
+            CoverageFormat::Lcov => self.lcov(path, &executable),
+        }
+    }
+
+    fn summary(&self, path: &str, executable: &BTreeSet<usize>) -> String {
+        let missed: Vec<usize> = executable
+            .iter()
+            .copied()
+            .filter(|line| self.hits(*line) == 0)
+            .collect();
+        let covered = executable.len() - missed.len();
+        let percentage = match executable.len() {
+            0 => 100.0,
+            total => covered as f64 * 100.0 / total as f64,
+        };
+        let mut summary = format!(
+            "Coverage of '{path}': {covered}/{} lines ({percentage:.1}%)\n",
+            executable.len()
+        );
+        if !missed.is_empty() {
+            summary.push_str(&format!("Missed lines: {}\n", line_ranges(&missed)));
+        }
+        summary
+    }
+
+    fn annotated(&self, source: &str, executable: &BTreeSet<usize>) -> String {
+        let mut annotated = String::new();
+        for (idx, text) in source.lines().enumerate() {
+            let line = idx + 1;
+            let count = match (executable.contains(&line), self.hits(line)) {
+                (false, _) => String::new(),
+                (true, 0) => "#####".to_string(),
+                (true, hits) => hits.to_string(),
+            };
+            annotated.push_str(&format!("{count:>8} | {text}\n"));
+        }
+        annotated
+    }
+
+    fn lcov(&self, path: &str, executable: &BTreeSet<usize>) -> String {
+        let mut lcov = format!("SF:{path}\n");
+        for line in executable {
+            lcov.push_str(&format!("DA:{line},{}\n", self.hits(*line)));
+        }
+        let covered = executable
+            .iter()
+            .filter(|line| self.hits(**line) > 0)
+            .count();
+        lcov.push_str(&format!(
+            "LF:{}\nLH:{covered}\nend_of_record\n",
+            executable.len()
+        ));
+        lcov
+    }
+}
+
+/// Formats sorted line numbers compactly, e.g. `3, 7-9`
+fn line_ranges(lines: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == *line => *end = *line,
+            _ => ranges.push((*line, *line)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{start}-{end}"),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Lines where a statement starts, including the ones in function and method bodies
+pub fn executable_lines(statements: &[Stmt]) -> BTreeSet<usize> {
+    let mut lines = BTreeSet::new();
+    for statement in statements {
+        collect_lines(statement, &mut lines);
+    }
+    lines
+}
+
+fn collect_lines(statement: &Stmt, lines: &mut BTreeSet<usize>) {
+    if let Some(line) = statement_line(statement) {
+        lines.insert(line);
+    }
+    let children: Vec<&Stmt> = match statement {
+        Stmt::Block(stmt) => stmt.statements.iter().collect(),
+        Stmt::Class(stmt) => stmt.methods.iter().collect(),
+        Stmt::Function(stmt) => stmt.body.iter().collect(),
+        Stmt::If(stmt) => std::iter::once(stmt.then_branch.as_ref())
+            .chain(stmt.else_branch.as_deref())
+            .collect(),
+        Stmt::While(stmt) => vec![stmt.body.as_ref()],
+        Stmt::For(stmt) => stmt
+            .initializer
+            .as_deref()
+            .into_iter()
+            .chain(std::iter::once(stmt.body.as_ref()))
+            .collect(),
+        _ => Vec::new(),
+    };
+    for child in children {
+        // Methods are recorded when they run, the class declaration covers its own line
+        if let (Stmt::Class(_), Stmt::Function(method)) = (statement, child) {
+            lines.extend(executable_lines(&method.body));
+            continue;
+        }
+        collect_lines(child, lines);
+    }
+}
+
+impl InterpreterHooks for Coverage {
+    fn on_statement(&mut self, _interpreter: &mut Interpreter, statement: &Stmt) {
+        self.record(statement);
+    }
+}
+
+/// Line a statement is reported on, `None` for blocks which only group other statements
+pub fn statement_line(statement: &Stmt) -> Option<usize> {
+    match statement {
+        Stmt::Block(_) => None,
+        Stmt::Class(stmt) => Some(stmt.name.line),
+        Stmt::Expression(stmt) => expression_line(&stmt.expression),
+        Stmt::For(stmt) => Some(stmt.keyword.line),
+        Stmt::ForIn(stmt) => Some(stmt.keyword.line),
+        Stmt::Function(stmt) => Some(stmt.name.line),
+        Stmt::If(stmt) => Some(stmt.keyword.line),
+        Stmt::Print(stmt) => Some(stmt.keyword.line),
+        Stmt::Return(stmt) => Some(stmt.keyword.line),
+        Stmt::Var(stmt) => Some(stmt.name.line),
+        Stmt::While(stmt) => Some(stmt.keyword.line),
+        Stmt::Break(stmt) => Some(stmt.keyword.line),
+        Stmt::Continue(stmt) => Some(stmt.keyword.line),
+        Stmt::Yield(stmt) => Some(stmt.keyword.line),
+        Stmt::Debugger(stmt) => Some(stmt.keyword.line),
+    }
+}
+
+/// Line of the leftmost token of an expression
+fn expression_line(expression: &Expr) -> Option<usize> {
+    match expression {
+        Expr::Assign(expr) => Some(expr.name.line),
+        Expr::Binary(expr) => expression_line(&expr.left),
+        Expr::Call(expr) => expression_line(&expr.callee),
+        Expr::Get(expr) => expression_line(&expr.object),
+        Expr::Grouping(expr) => expression_line(&expr.expression),
+        Expr::Literal(_) => None,
+        Expr::Logical(expr) => expression_line(&expr.left),
+        Expr::Set(expr) => expression_line(&expr.object),
+        Expr::This(expr) => Some(expr.keyword.line),
+        Expr::Super(expr) => Some(expr.keyword.line),
+        Expr::Unary(expr) => Some(expr.operator.line),
+        Expr::Ternary(expr) => expression_line(&expr.condition),
+        Expr::Variable(expr) => Some(expr.name.line),
+    }
+}
+
+#[cfg(test)]
+mod coverage_tests {
+
+    use crate::{
+        parser::Parser,
+        scanner::Scanner,
+        shared::{Rc, RefCell},
+    };
+
+    use super::*;
+
+    const SOURCE: &str = "fun check(n) {
+  if (n > 1) {
+    print \"big\";
+  } else {
+    print \"small\";
+  }
+}
+for (var i = 0; i < 3; i = i + 1) {
+  check(i);
+}
+";
+
+    fn run_with_coverage(source: &str) -> (Coverage, Vec<Stmt>) {
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        let coverage = Rc::new(RefCell::new(Coverage::new()));
+        interpreter.add_hooks(coverage.clone());
+        crate::run(source.to_string(), &mut interpreter, false).ok();
+        let statements = Parser::new(Scanner::new(source.to_string()).scan_tokens()).parse();
+        let coverage = std::mem::take(&mut *coverage.borrow_mut());
+        (coverage, statements)
+    }
+
+    #[test]
+    fn test_coverage_hits_and_summary() {
+        // Arrange
+        let (coverage, statements) = run_with_coverage(SOURCE);
+        // Act
+        let summary = coverage.report(CoverageFormat::Summary, "a.lox", SOURCE, &statements);
+        // Assert
+        assert_eq!(coverage.hits(2), 3);
+        assert_eq!(coverage.hits(3), 1);
+        assert_eq!(coverage.hits(5), 2);
+        assert_eq!(coverage.hits(9), 3);
+        assert_eq!(summary, "Coverage of 'a.lox': 6/6 lines (100.0%)\n");
+    }
+
+    #[test]
+    fn test_coverage_annotated_and_lcov() {
+        // Arrange
+        let source = "var a = 1;\nif (a > 1) {\n  print a;\n}\n";
+        let (coverage, statements) = run_with_coverage(source);
+        // Act
+        let annotated = coverage.report(CoverageFormat::Annotated, "a.lox", source, &statements);
+        let lcov = coverage.report(CoverageFormat::Lcov, "a.lox", source, &statements);
+        // Assert
+        assert_eq!(
+            annotated,
+            "       1 | var a = 1;\n       1 | if (a > 1) {\n   ##### |   print a;\n         | }\n"
+        );
+        assert_eq!(
+            lcov,
+            "SF:a.lox\nDA:1,1\nDA:2,1\nDA:3,0\nLF:3\nLH:2\nend_of_record\n"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! Debug logging of the interpreter's internals (`--debug=scope,env,calls`), to see why a
+//! closure captured the variable it did. Every line is written to stderr as
+//! `[debug <category>] <message>`; categories are enabled per thread, like the `--stats`
+//! counters, and cost a single check when they are off.
+use std::{
+    cell::{Cell, RefCell},
+    fmt::{self, Display, Formatter},
+};
+
+thread_local! {
+    /// One bit per enabled category
+    static ENABLED: Cell<u8> = const { Cell::new(0) };
+    /// Lines kept by `capture_debug_log` instead of being written to stderr
+    static CAPTURED: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DebugCategory {
+    /// Scopes the resolver pushes and pops, with the variables they declared
+    Scope,
+    /// Environments created at runtime, the ones they enclose and the ones closures capture
+    Env,
+    /// Calls of Lox functions with the arguments bound to their parameters
+    Calls,
+}
+
+const ALL: [DebugCategory; 3] = [
+    DebugCategory::Scope,
+    DebugCategory::Env,
+    DebugCategory::Calls,
+];
+
+impl DebugCategory {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DebugCategory::Scope => "scope",
+            DebugCategory::Env => "env",
+            DebugCategory::Calls => "calls",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<DebugCategory> {
+        ALL.into_iter().find(|category| category.name() == name)
+    }
+
+    fn bit(&self) -> u8 {
+        1 << *self as u8
+    }
+}
+
+impl Display for DebugCategory {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Categories to log, none by default
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DebugCategories {
+    bits: u8,
+}
+
+impl DebugCategories {
+    pub fn all() -> DebugCategories {
+        ALL.into_iter()
+            .fold(DebugCategories::default(), DebugCategories::with)
+    }
+
+    /// The listed categories, separated by commas as in `--debug=scope,calls`, or `all`
+    pub fn parse(list: &str) -> Result<DebugCategories, String> {
+        let mut categories = DebugCategories::default();
+        for name in list
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            categories = match name {
+                "all" => DebugCategories::all(),
+                _ => categories.with(
+                    DebugCategory::from_name(name)
+                        .ok_or_else(|| format!("Unknown debug category '{name}'."))?,
+                ),
+            };
+        }
+        Ok(categories)
+    }
+
+    pub fn with(self, category: DebugCategory) -> DebugCategories {
+        DebugCategories {
+            bits: self.bits | category.bit(),
+        }
+    }
+
+    pub fn contains(&self, category: DebugCategory) -> bool {
+        self.bits & category.bit() != 0
+    }
+}
+
+/// Logs the categories on this thread from now on, replacing the ones enabled before
+pub fn enable(categories: DebugCategories) {
+    ENABLED.with(|enabled| enabled.set(categories.bits));
+}
+
+pub fn is_enabled(category: DebugCategory) -> bool {
+    ENABLED.with(|enabled| enabled.get() & category.bit() != 0)
+}
+
+/// Logs the message when the category is enabled, it is only built then
+pub fn log(category: DebugCategory, message: impl FnOnce() -> String) {
+    if !is_enabled(category) {
+        return;
+    }
+    let line = format!("[debug {category}] {}", message());
+    let line = CAPTURED.with(|captured| match captured.borrow_mut().as_mut() {
+        Some(lines) => {
+            lines.push(line);
+            None
+        }
+        None => Some(line),
+    });
+    if let Some(line) = line {
+        eprintln!("{line}");
+    }
+}
+
+/// Runs `f` with the categories enabled and returns the lines they logged
+pub fn capture_debug_log(categories: DebugCategories, f: impl FnOnce()) -> Vec<String> {
+    let enabled = ENABLED.with(|enabled| enabled.replace(categories.bits));
+    let previous = CAPTURED.with(|captured| captured.replace(Some(Vec::new())));
+    f();
+    ENABLED.with(|bits| bits.set(enabled));
+    CAPTURED
+        .with(|captured| captured.replace(previous))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod debug_log_tests {
+    use super::*;
+    use crate::{interpreter::Interpreter, run};
+
+    #[test]
+    fn test_closure_scopes_are_logged() {
+        // Arrange
+        let mut interpreter = Interpreter::without_prelude();
+        interpreter.capture_output();
+        let source = "fun outer(a) {\n  fun inner() { return a; }\n  return inner;\n}\nouter(1)();";
+        let categories = DebugCategories::parse("scope, env,calls").unwrap();
+        // Act
+        let lines = capture_debug_log(categories, || {
+            run(source.to_string(), &mut interpreter, false).unwrap();
+        });
+        // Assert
+        assert_eq!(
+            lines,
+            [
+                "[debug scope] push scope 1",
+                "[debug scope] push scope 2",
+                "[debug scope] resolve 'a' on line 2 to scope 1 (1 up)",
+                "[debug scope] pop scope 2 []",
+                "[debug scope] resolve 'inner' on line 3 to scope 1 (0 up)",
+                "[debug scope] pop scope 1 [a, inner]",
+                "[debug scope] resolve 'outer' on line 5 as a global",
+                "[debug env] fun outer captures env #1",
+                "[debug env] create env #2 enclosing env #1",
+                "[debug calls] call outer(a = 1) in env #2",
+                "[debug env] fun inner captures env #2",
+                "[debug env] create env #3 enclosing env #2",
+                "[debug calls] call inner() in env #3",
+            ]
+        );
+        assert!(!is_enabled(DebugCategory::Env));
+    }
+}
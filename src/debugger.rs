@@ -0,0 +1,131 @@
+use std::collections::VecDeque;
+
+use crate::{hooks::InterpreterHooks, interpreter::Interpreter, stmt::Stmt};
+
+const HELP: &str = "Debugger commands:
+  <code>        Run statements or print an expression in the paused scope
+  :env          List the variables visible from the paused scope
+  :continue     Resume the script (same as an empty line, :c or Ctrl-D)
+  :help         Show this message";
+
+/// Stops the script at every `debugger;` statement and reads commands until it is resumed.
+/// Without a debugger registered on the interpreter the statement does nothing.
+#[derive(Debug, Clone, Default)]
+pub struct Debugger {
+    /// Commands answered at the pauses instead of reading them from the console
+    commands: Option<VecDeque<String>>,
+}
+
+impl Debugger {
+    /// Reads the commands from the console
+    pub fn new() -> Debugger {
+        Debugger::default()
+    }
+
+    pub fn with_commands(commands: impl IntoIterator<Item = String>) -> Debugger {
+        Debugger {
+            commands: Some(commands.into_iter().collect()),
+        }
+    }
+
+    fn pause(&mut self, interpreter: &mut Interpreter, line: usize) {
+        interpreter.write_line(&format!(
+            "Paused at line {line}. Type ':help' for the debugger commands."
+        ));
+        let was_debugging = interpreter.debugging;
+        interpreter.debugging = true;
+        while let Some(command) = self.read_command(interpreter) {
+            match command.trim() {
+                "" | ":continue" | ":c" => break,
+                ":env" => {
+                    let description = interpreter.environment().borrow().describe();
+                    interpreter.write_line(description.trim_end());
+                }
+                ":help" => interpreter.write_line(HELP),
+                source => evaluate(interpreter, source),
+            }
+            if interpreter.exit_code.is_some() {
+                break;
+            }
+        }
+        interpreter.debugging = was_debugging;
+    }
+
+    fn read_command(&mut self, interpreter: &Interpreter) -> Option<String> {
+        match &mut self.commands {
+            Some(commands) => commands.pop_front(),
+            None => read_console_command(interpreter),
+        }
+    }
+}
+
+impl InterpreterHooks for Debugger {
+    fn on_statement(&mut self, interpreter: &mut Interpreter, statement: &Stmt) {
+        if let Stmt::Debugger(stmt) = statement {
+            self.pause(interpreter, stmt.keyword.line);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_console_command(interpreter: &Interpreter) -> Option<String> {
+    let completer = |line: &str| crate::repl::complete(line, interpreter);
+    crate::line_editor::LineEditor::new().read_line("debug> ", &completer)
+}
+
+/// There is no console to read from in the browser, the script resumes right away
+#[cfg(target_arch = "wasm32")]
+fn read_console_command(_interpreter: &Interpreter) -> Option<String> {
+    None
+}
+
+/// Runs the code in the paused environment. It is not resolved, so variables are looked
+/// up by name while debugging.
+fn evaluate(interpreter: &mut Interpreter, source: &str) {
+    if let Ok(statements) = crate::parse(source.to_string(), true) {
+        // Errors were reported, the pause goes on
+        let _ = interpreter.interpret(&statements);
+    }
+}
+
+#[cfg(test)]
+mod debugger_tests {
+    use super::*;
+    use crate::shared::{Rc, RefCell};
+
+    #[test]
+    fn test_debugger_inspects_and_changes_locals() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let output = interpreter.capture_output();
+        let commands = ["x", "print y;", "y = 10;", "missing", ":continue"];
+        let debugger = Debugger::with_commands(commands.map(String::from));
+        interpreter.add_hooks(Rc::new(RefCell::new(debugger)));
+        let source = "fun f(x) {\n  var y = x * 2;\n  debugger;\n  return y;\n}\nprint f(3);";
+        // Act
+        crate::run(source.to_string(), &mut interpreter, false).ok();
+        // Assert
+        let output = output.borrow();
+        assert_eq!(
+            output.stdout,
+            "Paused at line 3. Type ':help' for the debugger commands.\n3\n6\n10\n"
+        );
+        assert_eq!(
+            output.stderr,
+            "[Line 1] - Error[I0304]: Undefined variable 'missing'.\n"
+        );
+        assert!(!interpreter.debugging);
+    }
+
+    #[test]
+    fn test_debugger_resumes_without_commands() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let output = interpreter.capture_output();
+        interpreter.add_hooks(Rc::new(RefCell::new(Debugger::with_commands([]))));
+        // Act
+        crate::run("debugger;\nprint 1;".to_string(), &mut interpreter, false).ok();
+        // Assert
+        assert!(output.borrow().stdout.ends_with("\n1\n"));
+    }
+}
@@ -1,47 +1,127 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::collections::HashMap;
 
-use crate::{error::*, object::*, token::*};
+use crate::{
+    debug_log::{self, DebugCategory},
+    error::*,
+    error_codes::I0304,
+    object::*,
+    shared::{Rc, RefCell},
+    stats,
+    token::*,
+};
 
 pub type EnvironmentRef = Rc<RefCell<Environment>>;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Environment {
-    values: HashMap<String, Object>,
+    values: Values,
     pub enclosing: Option<EnvironmentRef>,
+    /// Number of the environment in the `--debug=env` log
+    id: usize,
+}
+
+/// Variables of a scope by name, each one in a slot that stays the same when it is defined
+/// again, so a node can keep the slot of a global instead of looking its name up every time
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Values {
+    slots: HashMap<String, usize>,
+    objects: Vec<Object>,
+}
+
+impl Values {
+    fn insert(&mut self, name: String, value: Object) {
+        match self.slots.get(&name) {
+            Some(&slot) => self.objects[slot] = value,
+            None => {
+                self.slots.insert(name, self.objects.len());
+                self.objects.push(value);
+            }
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&Object> {
+        self.slots.get(name).map(|&slot| &self.objects[slot])
+    }
+
+    fn contains_key(&self, name: &str) -> bool {
+        self.slots.contains_key(name)
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &String> {
+        self.slots.keys()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&String, &Object)> {
+        self.slots
+            .iter()
+            .map(|(name, &slot)| (name, &self.objects[slot]))
+    }
 }
 
 impl Environment {
     pub fn new() -> EnvironmentRef {
+        let id = stats::count_environment();
+        debug_log::log(DebugCategory::Env, || format!("create env #{id}"));
         Rc::new(RefCell::new(Environment {
-            values: HashMap::new(),
+            values: Values::default(),
             enclosing: None,
+            id,
         }))
     }
 
     pub fn new_enclosing(enclosing: EnvironmentRef) -> EnvironmentRef {
+        let id = stats::count_environment();
+        debug_log::log(DebugCategory::Env, || {
+            format!("create env #{id} enclosing env #{}", enclosing.borrow().id)
+        });
         Rc::new(RefCell::new(Environment {
-            values: HashMap::new(),
+            values: Values::default(),
             enclosing: Some(enclosing),
+            id,
         }))
     }
 
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
     pub fn define(&mut self, name: String, value: Object) {
         self.values.insert(name, value);
     }
 
+    /// Whether the variable is defined in this scope, the enclosing ones are not searched
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
     pub fn get(&self, name: &Token) -> Result<Object, LoxErrorResult> {
         if let Some(value) = self.values.get(&name.lexeme) {
             Ok(value.clone())
         } else if let Some(enclosing) = &self.enclosing {
             return enclosing.borrow().get(name);
         } else {
-            Err(LoxErrorResult::interpreter_error(
-                name.line,
-                &format!("Undefined variable '{}'.", name.lexeme),
-            ))
+            Err(Environment::undefined(name))
         }
     }
 
+    pub fn undefined(name: &Token) -> LoxErrorResult {
+        LoxErrorResult::interpreter_error(name.line, I0304.with([name.lexeme().into()]))
+    }
+
+    /// Slot of a variable of this scope, it keeps it for as long as the scope lives
+    pub fn slot(&self, name: &str) -> Option<usize> {
+        self.values.slots.get(name).copied()
+    }
+
+    /// Value in a slot given by `slot`
+    pub fn get_slot(&self, slot: usize) -> Object {
+        self.values.objects[slot].clone()
+    }
+
+    pub fn set_slot(&mut self, slot: usize, value: Object) {
+        self.values.objects[slot] = value;
+    }
+
     pub fn assign(&mut self, name: &Token, value: Object) -> Result<Object, LoxErrorResult> {
         if self.values.contains_key(&name.lexeme) {
             self.define(name.lexeme(), value);
@@ -52,10 +132,57 @@ impl Environment {
             return env.borrow_mut().assign(name, value);
         }
 
-        Err(LoxErrorResult::interpreter_error(
-            name.line,
-            &format!("Undefined variable '{}'.", name.lexeme),
-        ))
+        Err(Environment::undefined(name))
+    }
+
+    /// Lists the variables defined in this scope (not the enclosing ones), sorted by name
+    pub fn entries(&self) -> Vec<(String, Object)> {
+        let mut entries: Vec<(String, Object)> = self
+            .values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        entries.sort_by(|(left, _), (right, _)| left.cmp(right));
+        entries
+    }
+
+    /// Names of every variable reachable from this scope, including the enclosing ones
+    pub fn visible_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.values.keys().cloned().collect();
+        if let Some(enclosing) = &self.enclosing {
+            names.extend(enclosing.borrow().visible_names());
+        }
+        names
+    }
+
+    /// Variables of this scope and of the enclosing ones, innermost scope first,
+    /// without the globals. For a function's closure, these are what it captured.
+    pub fn captured_scopes(&self) -> Vec<Vec<(String, Object)>> {
+        let Some(enclosing) = &self.enclosing else {
+            return Vec::new();
+        };
+        let mut scopes = vec![self.entries()];
+        scopes.extend(enclosing.borrow().captured_scopes());
+        scopes
+    }
+
+    /// Lists every scope from this one out to the globals, one variable per line
+    pub fn describe(&self) -> String {
+        self.describe_scopes(0)
+    }
+
+    fn describe_scopes(&self, depth: usize) -> String {
+        let mut description = match self.enclosing {
+            Some(_) => format!("[scope {depth}]\n"),
+            None => "[globals]\n".to_string(),
+        };
+        for (name, value) in self.entries() {
+            description.push_str(&format!("  {name} = {value}\n"));
+        }
+        if let Some(enclosing) = &self.enclosing {
+            description.push_str(&enclosing.borrow().describe_scopes(depth + 1));
+        }
+        description
     }
 
     /// Gets the enclosing environment recursively until it hits the last enclosing one
@@ -184,6 +311,44 @@ mod environment_test {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_slot_stays_when_redefined() {
+        // Arrange
+        let env = Environment::new();
+        env.borrow_mut()
+            .define("first".to_string(), Object::Number(1.0));
+        env.borrow_mut()
+            .define("second".to_string(), Object::Number(2.0));
+        // Act
+        env.borrow_mut()
+            .define("first".to_string(), Object::Bool(true));
+        let slot = env.borrow().slot("first");
+        // Assert
+        assert_eq!(slot, Some(0));
+        assert_eq!(env.borrow().get_slot(0), Object::Bool(true));
+        assert_eq!(env.borrow().slot("missing"), None);
+    }
+
+    #[test]
+    fn test_can_list_entries_sorted_by_name() {
+        // Arrange
+        let env = Environment::new();
+        env.borrow_mut()
+            .define("b_variable".to_string(), Object::Number(2.0));
+        env.borrow_mut()
+            .define("a_variable".to_string(), Object::Number(1.0));
+        // Act
+        let entries = env.borrow().entries();
+        // Assert
+        assert_eq!(
+            entries,
+            vec![
+                ("a_variable".to_string(), Object::Number(1.0)),
+                ("b_variable".to_string(), Object::Number(2.0)),
+            ]
+        );
+    }
+
     #[test]
     fn test_can_enclose_an_environment() {
         // Arrange
@@ -216,6 +381,34 @@ mod environment_test {
         assert_eq!(result.ok().unwrap(), Object::Number(123.0));
     }
 
+    #[test]
+    fn test_captured_scopes_leave_out_the_globals() {
+        // Arrange
+        let globals = Environment::new();
+        globals
+            .borrow_mut()
+            .define("global".to_string(), Object::Int(0));
+        let outer = Environment::new_enclosing(Rc::clone(&globals));
+        outer.borrow_mut().define("b".to_string(), Object::Int(2));
+        outer.borrow_mut().define("a".to_string(), Object::Int(1));
+        let inner = Environment::new_enclosing(Rc::clone(&outer));
+        inner.borrow_mut().define("c".to_string(), Object::Int(3));
+        // Act
+        let scopes = inner.borrow().captured_scopes();
+        // Assert
+        assert_eq!(
+            scopes,
+            vec![
+                vec![("c".to_string(), Object::Int(3))],
+                vec![
+                    ("a".to_string(), Object::Int(1)),
+                    ("b".to_string(), Object::Int(2))
+                ],
+            ]
+        );
+        assert!(globals.borrow().captured_scopes().is_empty());
+    }
+
     #[test]
     fn test_cannot_read_from_enclosed_environment() {
         // Arrange
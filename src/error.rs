@@ -1,18 +1,168 @@
+use std::{cell::RefCell, fmt};
+
 use crate::{
+    error_codes::{ErrorCode, Message},
+    locale::Language,
+    lox_function::LoxFunction,
     object::Object,
+    source_map::SourceMap,
     token::{Token, TokenType},
 };
 
+thread_local! {
+    /// Diagnostics reported while `collect_diagnostics` runs, instead of going to stderr
+    static CAPTURED_DIAGNOSTICS: RefCell<Option<Vec<Diagnostic>>> = const { RefCell::new(None) };
+    /// Source of the script `with_snippets` runs, its lines are shown under the diagnostics
+    static SNIPPET_SOURCE: RefCell<Option<SourceMap>> = const { RefCell::new(None) };
+}
+
+/// A reported error or warning with the location it points at
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub line: usize,
+    /// Token the diagnostic is about, `None` when only the line is known
+    pub token: Option<Token>,
+    pub message: String,
+    /// Code of the message, see `error_codes`
+    pub code: Option<&'static str>,
+    pub is_warning: bool,
+    /// The diagnostic as it is printed on the console
+    pub text: String,
+}
+
+impl Diagnostic {
+    /// Line, column and length of the text the diagnostic points at,
+    /// the whole line when only the line is known
+    pub fn span(&self, source_map: &SourceMap) -> (usize, usize, usize) {
+        match &self.token {
+            Some(token) => (token.line, token.column, token.lexeme.chars().count()),
+            None => {
+                let length = source_map
+                    .display_line(self.line)
+                    .map_or(0, |text| text.chars().count());
+                (self.line, 0, length)
+            }
+        }
+    }
+
+    /// The diagnostic followed by the line of the source it points at
+    pub fn render(&self, source_map: &SourceMap) -> String {
+        let (line, column, length) = self.span(source_map);
+        match source_map.snippet(line, column, length) {
+            Some(snippet) => format!("{}\n{snippet}", self.text),
+            None => self.text.clone(),
+        }
+    }
+}
+
+/// Runs `f` and returns every diagnostic it reported, one per line
+pub fn capture_diagnostics(f: impl FnOnce()) -> String {
+    collect_diagnostics(f)
+        .iter()
+        .map(|diagnostic| format!("{}\n", diagnostic.text))
+        .collect()
+}
+
+/// Reports a diagnostic again, e.g. one kept by `collect_diagnostics` to be filtered
+pub fn report_diagnostic(diagnostic: Diagnostic) {
+    CAPTURED_DIAGNOSTICS.with(|captured| match captured.borrow_mut().as_mut() {
+        Some(diagnostics) => diagnostics.push(diagnostic),
+        None => print_diagnostic(&diagnostic),
+    })
+}
+
+/// Runs `f`, the diagnostics it prints are followed by the line of `source_map` they point
+/// at. The command line runs scripts this way, captured diagnostics keep a single line.
+pub fn with_snippets<T>(source_map: SourceMap, f: impl FnOnce() -> T) -> T {
+    let previous = SNIPPET_SOURCE.with(|source| source.replace(Some(source_map)));
+    let result = f();
+    SNIPPET_SOURCE.with(|source| source.replace(previous));
+    result
+}
+
+fn print_diagnostic(diagnostic: &Diagnostic) {
+    SNIPPET_SOURCE.with(|source| match source.borrow().as_ref() {
+        Some(source_map) => eprintln!("{}", diagnostic.render(source_map)),
+        None => eprintln!("{}", diagnostic.text),
+    })
+}
+
+/// What the warnings of the resolver and the type checker do (`--warnings`)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum WarningLevel {
+    /// Not reported
+    Allow,
+    #[default]
+    Warn,
+    /// Reported, and the script is not run
+    Deny,
+}
+
+impl WarningLevel {
+    pub fn from_name(name: &str) -> Option<WarningLevel> {
+        match name {
+            "allow" => Some(WarningLevel::Allow),
+            "warn" => Some(WarningLevel::Warn),
+            "deny" => Some(WarningLevel::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// Runs `f` and returns every diagnostic it reported
+pub fn collect_diagnostics(f: impl FnOnce()) -> Vec<Diagnostic> {
+    let previous = CAPTURED_DIAGNOSTICS.with(|captured| captured.replace(Some(Vec::new())));
+    f();
+    CAPTURED_DIAGNOSTICS
+        .with(|captured| captured.replace(previous))
+        .unwrap_or_default()
+}
+
 #[derive(Debug)]
 pub enum LoxErrorResult {
-    SystemError { message: String },
-    Lexical { line: usize, message: String },
-    Parser { token: Token, message: String },
-    Interpreter { line: usize, message: String },
-    Resolver { token: Token, message: String },
-    Warning { token: Token, message: String },
-    ControlFlowBreak,
-    ControlFlowReturn { value: Object },
+    SystemError {
+        message: String,
+    },
+    Lexical {
+        line: usize,
+        message: Message,
+    },
+    Parser {
+        token: Token,
+        message: Message,
+    },
+    Interpreter {
+        line: usize,
+        message: Message,
+    },
+    Resolver {
+        token: Token,
+        message: Message,
+    },
+    Warning {
+        token: Token,
+        message: Message,
+    },
+    /// Raised by `break`, the label names the loop it stops, the innermost one without
+    ControlFlowBreak {
+        label: Option<String>,
+    },
+    /// Raised by `continue`, like `break`
+    ControlFlowContinue {
+        label: Option<String>,
+    },
+    ControlFlowReturn {
+        value: Object,
+    },
+    /// Raised by `return f(...);`, the returning function runs `f` in its own frame
+    ControlFlowTailCall {
+        function: Box<LoxFunction>,
+        arguments: Vec<Object>,
+    },
+    /// Raised by `exit(code)`, unwinds every call up to `Interpreter::interpret`
+    ControlFlowExit {
+        code: i32,
+    },
 }
 
 impl LoxErrorResult {
@@ -24,49 +174,40 @@ impl LoxErrorResult {
         error
     }
 
-    pub fn lexical_error(line: usize, message: &str) -> LoxErrorResult {
-        LoxErrorResult::Lexical {
-            line,
-            message: message.to_string(),
-        }
+    pub fn lexical_error(line: usize, message: Message) -> LoxErrorResult {
+        LoxErrorResult::Lexical { line, message }
     }
 
-    pub fn parse_error(token: Token, message: &str) -> LoxErrorResult {
-        LoxErrorResult::Parser {
-            token,
-            message: message.to_string(),
-        }
+    pub fn parse_error(token: Token, message: Message) -> LoxErrorResult {
+        LoxErrorResult::Parser { token, message }
     }
 
-    pub fn interpreter_error(line: usize, message: &str) -> LoxErrorResult {
-        LoxErrorResult::Interpreter {
-            line,
-            message: message.to_string(),
-        }
+    pub fn interpreter_error(line: usize, message: Message) -> LoxErrorResult {
+        LoxErrorResult::Interpreter { line, message }
     }
 
-    pub fn resolver_error(token: Token, message: &str) -> LoxErrorResult {
-        let error = LoxErrorResult::Resolver {
-            token,
-            message: message.to_string(),
-        };
+    pub fn resolver_error(token: Token, message: Message) -> LoxErrorResult {
+        let error = LoxErrorResult::Resolver { token, message };
         error.report();
         error
     }
 
-    pub fn warning(token: Token, message: &str) -> LoxErrorResult {
-        let warning = LoxErrorResult::Warning {
-            message: message.to_string(),
-            token,
-        };
+    pub fn warning(token: Token, message: Message) -> LoxErrorResult {
+        let warning = LoxErrorResult::Warning { message, token };
         warning.report();
         warning
     }
 
-    pub fn break_signal() -> LoxErrorResult {
-        let error = LoxErrorResult::ControlFlowBreak {};
-        error.report();
-        error
+    pub fn break_signal(label: Option<&Token>) -> LoxErrorResult {
+        LoxErrorResult::ControlFlowBreak {
+            label: label.map(Token::lexeme),
+        }
+    }
+
+    pub fn continue_signal(label: Option<&Token>) -> LoxErrorResult {
+        LoxErrorResult::ControlFlowContinue {
+            label: label.map(Token::lexeme),
+        }
     }
 
     pub fn return_signal(value: Object) -> LoxErrorResult {
@@ -75,39 +216,170 @@ impl LoxErrorResult {
         error
     }
 
-    pub fn is_control_break(&self) -> bool {
-        matches!(&self, LoxErrorResult::ControlFlowBreak { .. })
+    pub fn tail_call_signal(function: LoxFunction, arguments: Vec<Object>) -> LoxErrorResult {
+        LoxErrorResult::ControlFlowTailCall {
+            function: Box::new(function),
+            arguments,
+        }
+    }
+
+    pub fn exit_signal(code: i32) -> LoxErrorResult {
+        LoxErrorResult::ControlFlowExit { code }
+    }
+
+    /// Whether this is a `break` stopping the loop with the given label
+    pub fn breaks(&self, loop_label: Option<&Token>) -> bool {
+        match self {
+            LoxErrorResult::ControlFlowBreak { label } => targets(label, loop_label),
+            _ => false,
+        }
+    }
+
+    /// Whether this is a `continue` going on with the loop with the given label
+    pub fn continues(&self, loop_label: Option<&Token>) -> bool {
+        match self {
+            LoxErrorResult::ControlFlowContinue { label } => targets(label, loop_label),
+            _ => false,
+        }
     }
 
     pub fn report(&self) {
         match self {
-            LoxErrorResult::SystemError { message } => {
-                eprintln!("System error: {message}");
+            LoxErrorResult::ControlFlowBreak { .. }
+            | LoxErrorResult::ControlFlowContinue { .. }
+            | LoxErrorResult::ControlFlowReturn { .. }
+            | LoxErrorResult::ControlFlowTailCall { .. }
+            | LoxErrorResult::ControlFlowExit { .. } => {}
+            _ => CAPTURED_DIAGNOSTICS.with(|captured| match captured.borrow_mut().as_mut() {
+                Some(diagnostics) => diagnostics.push(self.diagnostic()),
+                None => print_diagnostic(&self.diagnostic()),
+            }),
+        }
+    }
+
+    /// Message of a diagnostic, `None` for the signals and system errors
+    pub fn message(&self) -> Option<&Message> {
+        match self {
+            LoxErrorResult::Lexical { message, .. }
+            | LoxErrorResult::Parser { message, .. }
+            | LoxErrorResult::Resolver { message, .. }
+            | LoxErrorResult::Warning { message, .. }
+            | LoxErrorResult::Interpreter { message, .. } => Some(message),
+            _ => None,
+        }
+    }
+
+    /// Code of the diagnostic, `None` for a message without one, e.g. one of `error()`
+    pub fn code(&self) -> Option<&'static ErrorCode> {
+        self.message().and_then(Message::code)
+    }
+
+    /// The message in the current language
+    pub fn localized_message(&self) -> Option<String> {
+        self.message()
+            .map(|message| message.text(Language::current()))
+    }
+
+    /// `[P0103]` with the code of the diagnostic, nothing without one
+    fn code_tag(&self) -> String {
+        self.code()
+            .map(|code| format!("[{}]", code.code))
+            .unwrap_or_default()
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        let (line, token) = match self {
+            LoxErrorResult::Lexical { line, .. } | LoxErrorResult::Interpreter { line, .. } => {
+                (*line, None)
             }
-            LoxErrorResult::Lexical { line, message } => {
-                eprintln!("[Line {}] - Error: {}", line, message)
+            LoxErrorResult::Parser { token, .. }
+            | LoxErrorResult::Resolver { token, .. }
+            | LoxErrorResult::Warning { token, .. } => (token.line, Some(token.clone())),
+            _ => (0, None),
+        };
+        let message = self.localized_message().unwrap_or_else(|| self.to_string());
+        Diagnostic {
+            line,
+            token,
+            message,
+            code: self.code().map(|code| code.code),
+            is_warning: matches!(self, LoxErrorResult::Warning { .. }),
+            text: self.to_string(),
+        }
+    }
+}
+
+/// An unlabeled `break` or `continue` is meant for the innermost loop, a labeled one
+/// for the loop with its label
+fn targets(label: &Option<String>, loop_label: Option<&Token>) -> bool {
+    match label {
+        None => true,
+        Some(label) => loop_label.is_some_and(|loop_label| loop_label.lexeme == *label),
+    }
+}
+
+/// Runtime errors of `Interpreter::interpret`, each one stopped its statement and was reported
+#[derive(Debug)]
+pub struct RuntimeErrorSummary {
+    pub count: usize,
+    pub last: LoxErrorResult,
+}
+
+impl fmt::Display for RuntimeErrorSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.count {
+            1 => write!(f, "1 runtime error: {}", self.last),
+            count => write!(f, "{count} runtime errors, the last one: {}", self.last),
+        }
+    }
+}
+
+impl fmt::Display for LoxErrorResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let words = Language::current().words();
+        let tag = self.code_tag();
+        let message = self.localized_message().unwrap_or_default();
+        match self {
+            LoxErrorResult::SystemError { message } => write!(f, "System error: {message}"),
+            LoxErrorResult::Lexical { line, .. } | LoxErrorResult::Interpreter { line, .. } => {
+                write!(
+                    f,
+                    "[{} {line}] - {}{tag}: {message}",
+                    words.line, words.error
+                )
             }
-            LoxErrorResult::Parser { token, message }
-            | LoxErrorResult::Resolver { token, message } => {
+            LoxErrorResult::Parser { token, .. } | LoxErrorResult::Resolver { token, .. } => {
+                let (line, error) = (words.line, words.error);
                 if token.is(TokenType::Eof) {
-                    eprintln!("[Line {}] - Error at end: {}", token.line, message)
+                    write!(
+                        f,
+                        "[{line} {}] - {error}{tag} {}: {message}",
+                        token.line, words.at_end
+                    )
                 } else {
-                    eprintln!(
-                        "[Line {}] - Error at '{}': {}",
-                        token.line, token.lexeme, message
+                    write!(
+                        f,
+                        "[{line} {}] - {error}{tag} {} '{}': {message}",
+                        token.line, words.at, token.lexeme
                     )
-                };
+                }
             }
-            LoxErrorResult::Interpreter { line, message } => {
-                eprintln!("[Line {}] - Error: {}", line, message)
+            LoxErrorResult::ControlFlowBreak { label: None } => write!(f, "break"),
+            LoxErrorResult::ControlFlowBreak { label: Some(label) } => write!(f, "break {label}"),
+            LoxErrorResult::ControlFlowContinue { label: None } => write!(f, "continue"),
+            LoxErrorResult::ControlFlowContinue { label: Some(label) } => {
+                write!(f, "continue {label}")
             }
-            LoxErrorResult::ControlFlowBreak | LoxErrorResult::ControlFlowReturn { .. } => {}
-            LoxErrorResult::Warning { token, message } => {
-                eprintln!(
-                    "[Line {}] - Warning: '{}': {}",
-                    token.line, token.lexeme, message
-                )
+            LoxErrorResult::ControlFlowReturn { value } => write!(f, "return {value}"),
+            LoxErrorResult::ControlFlowTailCall { function, .. } => {
+                write!(f, "return {function}(...)")
             }
+            LoxErrorResult::ControlFlowExit { code } => write!(f, "exit {code}"),
+            LoxErrorResult::Warning { token, .. } => write!(
+                f,
+                "[{} {}] - {}{tag}: '{}': {message}",
+                words.line, token.line, words.warning, token.lexeme
+            ),
         }
     }
 }
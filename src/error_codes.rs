@@ -0,0 +1,1183 @@
+//! Stable codes of the diagnostics, shown in their output as `Error[P0103]` and explained by
+//! `--explain P0103`. The first letter is the stage reporting it: `L` scanner, `P` parser,
+//! `R` resolver, `I` interpreter, `T` type checker and `N` native functions.
+//!
+//! Every diagnostic is raised with its code and the arguments standing for the `{}` of the
+//! template, e.g. `I0304.with([name.into()])`, so its text can be translated. Messages given
+//! by scripts, like the one of `error()`, are `Message::Plain` and have no code.
+use std::fmt::{self, Write};
+
+use crate::locale::Language;
+
+/// A kind of diagnostic with the message it is reported with
+#[derive(Debug, PartialEq)]
+pub struct ErrorCode {
+    pub code: &'static str,
+    /// The message with `{}` for the parts that vary
+    pub message: &'static str,
+    /// Why it is reported and how to fix it
+    pub explanation: &'static str,
+    /// A script reporting it
+    pub example: &'static str,
+}
+
+const fn entry(
+    code: &'static str,
+    message: &'static str,
+    explanation: &'static str,
+    example: &'static str,
+) -> ErrorCode {
+    ErrorCode {
+        code,
+        message,
+        explanation,
+        example,
+    }
+}
+
+pub static L0001: ErrorCode = entry(
+    "L0001",
+    "Unterminated string.",
+    "A string literal runs to the end of the file without its closing '\"'. Strings may span lines, so the missing quote is reported on the last line of the file.",
+    "print \"hello;",
+);
+
+pub static L0002: ErrorCode = entry(
+    "L0002",
+    "Unterminated block comment.",
+    "A '/*' comment runs to the end of the file without its closing '*/'.",
+    "/* the comment never ends\nprint 1;",
+);
+
+pub static L0003: ErrorCode = entry(
+    "L0003",
+    "Unexpected character. -> '{}'",
+    "The character cannot start any token of Lox. It is often a character of another language, like '@', '#' or '$'.",
+    "var total = 1 @ 2;",
+);
+
+pub static L0004: ErrorCode = entry(
+    "L0004",
+    "Missing digits in the exponent of number '{}'.",
+    "An 'e' or 'E' in a number must be followed by the digits of the exponent, with an optional sign.",
+    "print 1e;",
+);
+
+pub static L0005: ErrorCode = entry(
+    "L0005",
+    "Missing digits in {} number '{}'.",
+    "A '0x', '0o' or '0b' prefix must be followed by at least one digit of its base.",
+    "print 0x;",
+);
+
+pub static L0006: ErrorCode = entry(
+    "L0006",
+    "Invalid digit '{}' in {} number '{}'.",
+    "The digit doesn't belong to the base of the number: '0b' numbers only take 0 and 1, '0o' numbers 0 to 7.",
+    "print 0b102;",
+);
+
+pub static L0007: ErrorCode = entry(
+    "L0007",
+    "Misplaced '_' separator in number '{}'.",
+    "An '_' may only separate two digits, it cannot start or end a number or follow another '_'.",
+    "print 1__000;",
+);
+
+pub static L0008: ErrorCode = entry(
+    "L0008",
+    "The 'bigint' feature is needed for number '{}'.",
+    "The integer doesn't fit in 64 bits. Build the interpreter with the 'bigint' feature to use integers of any size.",
+    "print 99999999999999999999;",
+);
+
+pub static P0101: ErrorCode = entry(
+    "P0101",
+    "Expect expression.",
+    "An expression was expected, like a literal, a variable or a call, but the token cannot start one.",
+    "var a = ;",
+);
+
+pub static P0102: ErrorCode = entry(
+    "P0102",
+    "Expect ')' after {}.",
+    "A '(' was opened without the ')' closing it.",
+    "print (1 + 2;",
+);
+
+pub static P0103: ErrorCode = entry(
+    "P0103",
+    "Expect ';' after {}.",
+    "Statements end with a ';'. When the next token is on another line, it is reported at the end of the statement, on the line missing the ';'.",
+    "var a = 1\nprint a;",
+);
+
+pub static P0104: ErrorCode = entry(
+    "P0104",
+    "Expect '(' after {}.",
+    "'if', 'while' and 'for' take their condition, and declarations their parameters, between parentheses.",
+    "if true { print 1; }",
+);
+
+pub static P0105: ErrorCode = entry(
+    "P0105",
+    "Expect '}' after {} opened at line {}.",
+    "A '{' was opened without the '}' closing it, the error points at the line it was opened on.",
+    "fun f() {\n  print 1;",
+);
+
+pub static P0106: ErrorCode = entry(
+    "P0106",
+    "Expect '{' before {} body.",
+    "The body of a class or a function must be a block between braces.",
+    "fun f() print 1;",
+);
+
+pub static P0107: ErrorCode = entry(
+    "P0107",
+    "Expect {} name.",
+    "A declaration must be followed by the name it declares, an identifier.",
+    "var = 1;",
+);
+
+pub static P0108: ErrorCode = entry(
+    "P0108",
+    "Expect property name after '.'.",
+    "A '.' must be followed by the name of the property or method to get.",
+    "print a.1;",
+);
+
+pub static P0109: ErrorCode = entry(
+    "P0109",
+    "Expect '.' after super.",
+    "'super' can only be used to call a method of the superclass, as in 'super.method()'.",
+    "class A {}\nclass B < A { f() { super(); } }",
+);
+
+pub static P0110: ErrorCode = entry(
+    "P0110",
+    "Expect super class method name.",
+    "'super.' must be followed by the name of a method of the superclass.",
+    "class A {}\nclass B < A { f() { super.1; } }",
+);
+
+pub static P0111: ErrorCode = entry(
+    "P0111",
+    "Invalid assignment target.",
+    "Only variables and properties can be assigned to, not the result of an expression.",
+    "var a = 1;\na + 1 = 2;",
+);
+
+pub static P0112: ErrorCode = entry(
+    "P0112",
+    "Cannot have more than 255 {}.",
+    "A function takes at most 255 parameters, and a call passes at most 255 arguments. Pass a list or an instance instead.",
+    "// 256 parameters\nfun f(p1, p2, p3, /* ... */ p256) {}",
+);
+
+pub static P0113: ErrorCode = entry(
+    "P0113",
+    "Expect ':' after then branch of ternary operator.",
+    "A conditional expression needs both branches, as in 'condition ? then : else'.",
+    "print true ? 1;",
+);
+
+pub static P0114: ErrorCode = entry(
+    "P0114",
+    "Expect 'while' or 'for' after loop label.",
+    "Only loops can be labeled, for 'break' and 'continue' to name them.",
+    "outer: print 1;",
+);
+
+pub static P0115: ErrorCode = entry(
+    "P0115",
+    "Expect end of expression.",
+    "The ':ast' command of the prompt takes a single expression, something follows it.",
+    "// At the prompt\n:ast 1 2",
+);
+
+pub static P0116: ErrorCode = entry(
+    "P0116",
+    "{} too deeply nested.",
+    "Expressions and statements are nested too deeply for the parser, which would overflow its stack. Split them with variables or functions.",
+    "// 300 nested parentheses\nprint ((((/* ... */ 1 /* ... */))));",
+);
+
+pub static R0201: ErrorCode = entry(
+    "R0201",
+    "Variable is declared but never used.",
+    "A local variable is never read. Remove it, or read it where it was meant to be used. This is a warning, the script still runs.",
+    "fun f() {\n  var unused = 1;\n}",
+);
+
+pub static R0202: ErrorCode = entry(
+    "R0202",
+    "Already a variable with this name in this scope.",
+    "A local scope declares the same name twice. Give the second variable another name or assign to the first one.",
+    "fun f() {\n  var a = 1;\n  var a = 2;\n  print a;\n}",
+);
+
+pub static R0203: ErrorCode = entry(
+    "R0203",
+    "Cannot read local variable in its own initializer.",
+    "A local variable is read by the expression giving its first value, before it has one.",
+    "fun f() {\n  var a = a + 1;\n}",
+);
+
+pub static R0204: ErrorCode = entry(
+    "R0204",
+    "Cannot return from top-level code.",
+    "'return' only leaves a function, the script itself cannot return. Use 'exit()' to stop it.",
+    "return 1;",
+);
+
+pub static R0205: ErrorCode = entry(
+    "R0205",
+    "Cannot return a value from an initializer.",
+    "'init' always returns the new instance, a bare 'return;' may leave it early.",
+    "class A {\n  init() { return 1; }\n}",
+);
+
+pub static R0206: ErrorCode = entry(
+    "R0206",
+    "Cannot return a value from a generator.",
+    "A function using 'yield' hands out its values with 'yield', a bare 'return;' may end it early.",
+    "fun g() {\n  yield 1;\n  return 2;\n}",
+);
+
+pub static R0207: ErrorCode = entry(
+    "R0207",
+    "Cannot use 'this' outside of a class",
+    "'this' is the instance a method was called on, there is none outside of methods.",
+    "print this;",
+);
+
+pub static R0208: ErrorCode = entry(
+    "R0208",
+    "Cannot use 'super' outside of a class.",
+    "'super' calls a method of the superclass, there is none outside of methods.",
+    "super.f();",
+);
+
+pub static R0209: ErrorCode = entry(
+    "R0209",
+    "Cannot use 'super' in a class with no supper class.",
+    "'super' calls a method of the superclass, but the class doesn't inherit from one. Declare it as 'class B < A'.",
+    "class A {\n  f() { super.f(); }\n}",
+);
+
+pub static R0210: ErrorCode = entry(
+    "R0210",
+    "A class cannot inherit from itself.",
+    "The superclass of a class must be another class.",
+    "class A < A {}",
+);
+
+pub static R0211: ErrorCode = entry(
+    "R0211",
+    "'{}' can only be used inside loops.",
+    "'break' and 'continue' stop or go on with the loop around them, there is none here.",
+    "break;",
+);
+
+pub static R0212: ErrorCode = entry(
+    "R0212",
+    "No enclosing loop is labeled '{}'.",
+    "A labeled 'break' or 'continue' must be inside the loop with its label.",
+    "while (true) { break outer; }",
+);
+
+pub static R0213: ErrorCode = entry(
+    "R0213",
+    "A loop with this label is already enclosing this one.",
+    "Nested loops need different labels for 'break' and 'continue' to tell them apart.",
+    "outer: while (true) { outer: while (true) { break outer; } }",
+);
+
+pub static R0214: ErrorCode = entry(
+    "R0214",
+    "Cannot yield from top-level code.",
+    "'yield' turns a function into a generator, the script itself cannot yield.",
+    "yield 1;",
+);
+
+pub static R0215: ErrorCode = entry(
+    "R0215",
+    "Cannot yield from an initializer.",
+    "'init' returns the new instance, it cannot be a generator.",
+    "class A {\n  init() { yield 1; }\n}",
+);
+
+pub static R0216: ErrorCode = entry(
+    "R0216",
+    "Cannot redeclare native module '{}'.",
+    "The global is a module of natives registered by the program embedding the interpreter, a script cannot declare it again.",
+    "var math = 1;",
+);
+
+pub static R0217: ErrorCode = entry(
+    "R0217",
+    "Cannot assign to native module '{}'.",
+    "The global is a module of natives registered by the program embedding the interpreter, a script cannot replace it.",
+    "math = 1;",
+);
+
+pub static I0301: ErrorCode = entry(
+    "I0301",
+    "Operand must be a number.",
+    "'-' only negates numbers.",
+    "print -\"a\";",
+);
+
+pub static I0302: ErrorCode = entry(
+    "I0302",
+    "Operands must be strings or numbers for '+' operation.",
+    "'+' adds numbers and joins strings, a string and a number are joined too. Other values cannot be added.",
+    "print 1 + nil;",
+);
+
+pub static I0303: ErrorCode = entry(
+    "I0303",
+    "Operands must be two numbers or two strings for '{}' operation.",
+    "Comparisons take two numbers, or two strings compared by their characters.",
+    "print 1 < \"2\";",
+);
+
+pub static I0304: ErrorCode = entry(
+    "I0304",
+    "Undefined variable '{}'.",
+    "No variable with this name is declared where it is used. Check its spelling, and that it is declared before the code using it runs.",
+    "print undefined;",
+);
+
+pub static I0305: ErrorCode = entry(
+    "I0305",
+    "Undefined property '{}'.",
+    "The instance has no field with this name, and its class no method with it.",
+    "class A {}\nprint A().missing;",
+);
+
+pub static I0306: ErrorCode = entry(
+    "I0306",
+    "Can only call functions and classes.",
+    "The value before '(' is not a function, a method or a class.",
+    "var a = 1;\na();",
+);
+
+pub static I0307: ErrorCode = entry(
+    "I0307",
+    "Only instances have properties.",
+    "'.' gets a property of an instance or a member of a module, other values have none.",
+    "var a = 1;\nprint a.b;",
+);
+
+pub static I0308: ErrorCode = entry(
+    "I0308",
+    "Only instances have fields.",
+    "Only the fields of instances can be set with '.'.",
+    "var a = 1;\na.b = 2;",
+);
+
+pub static I0309: ErrorCode = entry(
+    "I0309",
+    "Expected {} arguments but got {}.",
+    "A function was called with more or fewer arguments than it has parameters.",
+    "fun f(a, b) {}\nf(1);",
+);
+
+pub static I0310: ErrorCode = entry(
+    "I0310",
+    "Expected at least {} arguments but got {}.",
+    "A native taking any number of arguments was called with fewer than it needs.",
+    "format();",
+);
+
+pub static I0311: ErrorCode = entry(
+    "I0311",
+    "Expected {} arguments in class initializer but got {}.",
+    "A class is called with the arguments of its 'init' method, none without one.",
+    "class A {\n  init(a) {}\n}\nA();",
+);
+
+pub static I0312: ErrorCode = entry(
+    "I0312",
+    "Superclass must be a class, got {}.",
+    "The value after '<' in a class declaration is not a class.",
+    "var A = 3;\nclass B < A {}",
+);
+
+pub static I0313: ErrorCode = entry(
+    "I0313",
+    "Illegal expression. Division by zero is not allowed.",
+    "A number was divided by zero. Run with --ieee-division to get Infinity instead.",
+    "print 1 / 0;",
+);
+
+pub static I0314: ErrorCode = entry(
+    "I0314",
+    "Illegal expression. Zero divided by zero is undefined.",
+    "Zero was divided by zero. Run with --ieee-division to get NaN instead.",
+    "print 0 / 0;",
+);
+
+pub static I0315: ErrorCode = entry(
+    "I0315",
+    "Operands must be numbers for '{}' operation.",
+    "Arithmetic other than '+' and '*' only takes numbers.",
+    "print \"a\" - 1;",
+);
+
+pub static I0316: ErrorCode = entry(
+    "I0316",
+    "Operands must be numbers or a string and a number for '*' operation.",
+    "'*' multiplies numbers, or repeats a string a number of times.",
+    "print \"a\" * \"b\";",
+);
+
+pub static I0317: ErrorCode = entry(
+    "I0317",
+    "Operands must be a string and a non-negative integer for '*' operation, got {}.",
+    "A string can only be repeated a whole, non-negative number of times.",
+    "print \"a\" * -1;",
+);
+
+pub static I0318: ErrorCode = entry(
+    "I0318",
+    "Operands must be two numbers or two strings for '+' operation in strict mode.",
+    "With --strict, '+' doesn't turn numbers into strings. Call 'toString' on the number first.",
+    "// Run with --strict\nprint \"a\" + 1;",
+);
+
+pub static I0319: ErrorCode = entry(
+    "I0319",
+    "Condition of '{}' must be a boolean in strict mode, got {}.",
+    "With --strict, conditions are not truthy or falsy, they must be 'true' or 'false'. Compare the value instead, e.g. 'x != nil'.",
+    "// Run with --strict\nif (1) print 1;",
+);
+
+pub static I0320: ErrorCode = entry(
+    "I0320",
+    "Can only iterate over lists, strings and generators, got {}.",
+    "A 'for (var x in ...)' loop goes over the elements of a list, the characters of a string or the values of a generator.",
+    "for (var x in nil) print x;",
+);
+
+pub static I0321: ErrorCode = entry(
+    "I0321",
+    "Can only yield inside a generator.",
+    "'yield' ran outside of a generator, e.g. in code evaluated by the debugger.",
+    "yield 1;",
+);
+
+pub static I0322: ErrorCode = entry(
+    "I0322",
+    "Generator '{}' is already running.",
+    "A generator asked for its own next value while it was computing one.",
+    "var g;\nfun gen() { yield next(g); }\ng = gen();\nnext(g);",
+);
+
+pub static I0323: ErrorCode = entry(
+    "I0323",
+    "Undefined member '{}' of module '{}'.",
+    "The module has no function with this name.",
+    "import \"lib.lox\" as lib;\nlib.missing();",
+);
+
+pub static I0324: ErrorCode = entry(
+    "I0324",
+    "String of {} bytes exceeds the limit of {} bytes.",
+    "The interpreter was configured with a limit on the length of strings, and a string grew past it.",
+    "var s = \"a\";\nwhile (true) s = s + s;",
+);
+
+pub static I0325: ErrorCode = entry(
+    "I0325",
+    "List of {} elements exceeds the limit of {} elements.",
+    "The interpreter was configured with a limit on the length of lists, and a list grew past it.",
+    "var l = list();\nwhile (true) push(l, 1);",
+);
+
+pub static I0326: ErrorCode = entry(
+    "I0326",
+    "Instance exceeds the limit of {} fields.",
+    "The interpreter was configured with a limit on the fields of instances, and an instance got more.",
+    "class A {}\nvar a = A();\na.x = 1;",
+);
+
+pub static I0327: ErrorCode = entry(
+    "I0327",
+    "Native '{}' needs the '{}' capability, which was not granted.",
+    "The script was run with --allow, and the capability the native needs was left out. Add it to the list.",
+    "// Run with --allow=io\nprint readFile(\"data.txt\");",
+);
+
+pub static I0328: ErrorCode = entry(
+    "I0328",
+    "The call to '{}' was cancelled.",
+    "The program embedding the interpreter cancelled the call before it returned.",
+    "fun slow() { while (true) {} }\nslow();",
+);
+
+pub static I0329: ErrorCode = entry(
+    "I0329",
+    "Value returned by 'main' must be an integer between 0 and 255, got {}.",
+    "The value returned by 'main' is the exit code of the process, it must be a valid one.",
+    "fun main() { return 300; }",
+);
+
+pub static I0330: ErrorCode = entry(
+    "I0330",
+    "Unsupported binary operation.",
+    "An operator the interpreter doesn't know how to evaluate, which should not happen. Please report it.",
+    "print 1 + 2;",
+);
+
+pub static I0331: ErrorCode = entry(
+    "I0331",
+    "Unsupported unary operator",
+    "An operator the interpreter doesn't know how to evaluate, which should not happen. Please report it.",
+    "print -1;",
+);
+
+pub static I0332: ErrorCode = entry(
+    "I0332",
+    "Run exceeded the time limit of {} ms.",
+    "The interpreter was configured with a limit on how long one run may take, and the script was still running when it passed.",
+    "while (true) {}",
+);
+
+pub static I0333: ErrorCode = entry(
+    "I0333",
+    "Output of {} bytes exceeds the limit of {} bytes.",
+    "The interpreter was configured with a limit on how much one run may print, and the script printed past it.",
+    "while (true) print \"again\";",
+);
+
+pub static I0334: ErrorCode = entry(
+    "I0334",
+    "'main' must not declare parameters, it declares {}.",
+    "The script's 'main' is called without arguments once its top-level code ran, so it cannot take any. Read the command line arguments with 'arg' and 'argc' instead.",
+    "fun main(args) {\n  return 0;\n}",
+);
+
+pub static I0335: ErrorCode = entry(
+    "I0335",
+    "Calls are nested deeper than the limit of {} calls.",
+    "A function kept calling itself, or other functions, without returning, past the call depth the host allows. Make sure the recursion reaches its base case, or write it as a loop.",
+    "fun forever(n) {\n  return 1 + forever(n);\n}\nforever(1);",
+);
+
+pub static T0401: ErrorCode = entry(
+    "T0401",
+    "Unknown type '{}'.",
+    "A type annotation names neither a built-in type (Any, nil, Bool, Number, String, List, Function) nor a class.",
+    "var a: Integer = 1;",
+);
+
+pub static T0402: ErrorCode = entry(
+    "T0402",
+    "Variable '{}' is declared as {} but initialized with {}.",
+    "The first value of a variable doesn't match its annotation. This is a warning, an error with --strict.",
+    "var a: Number = \"one\";",
+);
+
+pub static T0403: ErrorCode = entry(
+    "T0403",
+    "Variable '{}' is declared as {}, cannot assign {}.",
+    "The assigned value doesn't match the annotation of the variable. This is a warning, an error with --strict.",
+    "var a: Number = 1;\na = \"one\";",
+);
+
+pub static T0404: ErrorCode = entry(
+    "T0404",
+    "Parameter '{}' of '{}' expects {}, got {}.",
+    "An argument doesn't match the annotation of its parameter. This is a warning, an error with --strict.",
+    "fun f(a: Number) {}\nf(\"one\");",
+);
+
+pub static T0405: ErrorCode = entry(
+    "T0405",
+    "'{}' returns {}, cannot return {}.",
+    "A returned value doesn't match the return annotation of the function. This is a warning, an error with --strict.",
+    "fun f() -> Number { return \"one\"; }",
+);
+
+pub static T0406: ErrorCode = entry(
+    "T0406",
+    "Expected {} arguments but got {}.",
+    "A function declared in the script is called with more or fewer arguments than it has parameters. This is a warning, an error with --strict.",
+    "fun f(a: Number) {}\nf(1, 2);",
+);
+
+pub static N0501: ErrorCode = entry(
+    "N0501",
+    "Argument of '{}' must be {}.",
+    "The native was called with a value of another type than it takes.",
+    "print len(1);",
+);
+
+pub static N0502: ErrorCode = entry(
+    "N0502",
+    "Arguments of '{}' must be {}.",
+    "The native was called with values of other types than it takes.",
+    "print split(1, 2);",
+);
+
+pub static N0503: ErrorCode = entry(
+    "N0503",
+    "First argument of '{}' must be {}.",
+    "The native was called with a first argument of another type than it takes.",
+    "print get(1, 0);",
+);
+
+pub static N0504: ErrorCode = entry(
+    "N0504",
+    "Second argument of '{}' must be {}.",
+    "The native was called with a second argument of another type than it takes.",
+    "print exec(\"ls\", \"-l\");",
+);
+
+pub static N0505: ErrorCode = entry(
+    "N0505",
+    "Arguments of native '{}' must be {}.",
+    "A native registered by the program embedding the interpreter was called with values of other types than it takes.",
+    "print scale(\"a\");",
+);
+
+pub static N0506: ErrorCode = entry(
+    "N0506",
+    "Argument index must be a non-negative integer.",
+    "'arg' takes the position of a command line argument, starting at 0.",
+    "print arg(-1);",
+);
+
+pub static N0507: ErrorCode = entry(
+    "N0507",
+    "Index must be a non-negative integer.",
+    "Positions in strings start at 0 and are whole numbers.",
+    "print codePointAt(\"a\", 0.5);",
+);
+
+pub static N0508: ErrorCode = entry(
+    "N0508",
+    "Index {} is out of range for a list of {} elements.",
+    "The position is past the last element of the list or string, positions start at 0.",
+    "var l = list();\nprint get(l, 0);",
+);
+
+pub static N0509: ErrorCode = entry(
+    "N0509",
+    "Index {} is out of range for a string of {} characters.",
+    "The position is past the last character of the string, positions start at 0.",
+    "print codePointAt(\"a\", 1);",
+);
+
+pub static N0510: ErrorCode = entry(
+    "N0510",
+    "Range {}..{} is out of range for a list of {} elements.",
+    "The end of a range of 'slice' cannot be past the last element of the list.",
+    "var l = list();\nprint slice(l, 0, 1);",
+);
+
+pub static N0511: ErrorCode = entry(
+    "N0511",
+    "Range {}..{} ends before it starts.",
+    "The end of the slice must not come before its start.",
+    "print slice(\"abc\", 2, 1);",
+);
+
+pub static N0512: ErrorCode = entry(
+    "N0512",
+    "Cannot pop from an empty list.",
+    "'pop' removes the last element, the list has none.",
+    "pop(list());",
+);
+
+pub static N0513: ErrorCode = entry(
+    "N0513",
+    "Cannot convert {} to an integer.",
+    "The number is not finite, or too large for an integer.",
+    "print int(1e30);",
+);
+
+pub static N0514: ErrorCode = entry(
+    "N0514",
+    "Code point {} is not a Unicode scalar value.",
+    "The number is not the code of a character, e.g. one of the surrogates from 55296 to 57343.",
+    "print fromCodePoint(55296);",
+);
+
+pub static N0515: ErrorCode = entry(
+    "N0515",
+    "Comparator of 'sort' must return a number.",
+    "The function given to 'sort' must return a negative number, zero or a positive number.",
+    "var l = list();\npush(l, 2);\npush(l, 1);\nfun less(a, b) { return a < b; }\nsort(l, less);",
+);
+
+pub static N0516: ErrorCode = entry(
+    "N0516",
+    "Bounds of 'randomInt' must be integers with the lower one first.",
+    "'randomInt' takes the smallest and the largest integer it may return, in that order.",
+    "print randomInt(10, 1);",
+);
+
+pub static N0517: ErrorCode = entry(
+    "N0517",
+    "Seed of 'randomSeed' must be an integer.",
+    "'randomSeed' takes a whole number.",
+    "randomSeed(1.5);",
+);
+
+pub static N0518: ErrorCode = entry(
+    "N0518",
+    "Iterations of 'bench' must be a positive integer.",
+    "'bench' runs the function the given number of times, at least once.",
+    "fun f() {}\nbench(f, 0);",
+);
+
+pub static N0519: ErrorCode = entry(
+    "N0519",
+    "Sleep duration must be a non-negative number of milliseconds.",
+    "'sleep' cannot wait a negative time.",
+    "sleep(-1);",
+);
+
+pub static N0520: ErrorCode = entry(
+    "N0520",
+    "Exit code must be an integer between 0 and 255.",
+    "'exit' takes the exit code of the process, it must be a valid one.",
+    "exit(300);",
+);
+
+pub static N0521: ErrorCode = entry(
+    "N0521",
+    "Template of 'format' has {} placeholders but got {} values.",
+    "Every '{}' of the template of 'format' takes one value, and every value needs a '{}'.",
+    "print format(\"{} and {}\", 1);",
+);
+
+pub static N0522: ErrorCode = entry(
+    "N0522",
+    "Unmatched '{}' in the template of 'format', use '{}'.",
+    "A single '{' or '}' in the template of 'format' must be doubled to be printed.",
+    "print format(\"{\", 1);",
+);
+
+pub static N0523: ErrorCode = entry(
+    "N0523",
+    "Assertion failed: {}",
+    "The condition given to 'assert' is false or nil, the message is the second argument.",
+    "assert(1 > 2, \"math is broken\");",
+);
+
+pub static N0524: ErrorCode = entry(
+    "N0524",
+    "Expected {}, got {}.",
+    "The two values given to 'expectEq' differ, the expected one comes second.",
+    "expectEq(1 + 1, 3);",
+);
+
+pub static N0525: ErrorCode = entry(
+    "N0525",
+    "Could not read file '{}': {}.",
+    "The file doesn't exist, or cannot be read.",
+    "print readFile(\"missing.txt\");",
+);
+
+pub static N0526: ErrorCode = entry(
+    "N0526",
+    "Could not write file '{}': {}.",
+    "The file cannot be written, e.g. because its directory doesn't exist.",
+    "writeFile(\"missing/out.txt\", \"text\");",
+);
+
+pub static N0527: ErrorCode = entry(
+    "N0527",
+    "Could not run '{}': {}.",
+    "The command given to 'exec' cannot be started, e.g. because it is not installed.",
+    "exec(\"no-such-program\", list());",
+);
+
+pub static N0528: ErrorCode = entry(
+    "N0528",
+    "Native function '{}' is not available on this platform.",
+    "The native needs something the platform doesn't have, e.g. a monotonic clock in WebAssembly.",
+    "sleep(10);",
+);
+
+pub static N0529: ErrorCode = entry(
+    "N0529",
+    "The system time is not available on this platform.",
+    "The platform doesn't tell the time, e.g. WebAssembly without a host.",
+    "print clock();",
+);
+
+pub static N0530: ErrorCode = entry(
+    "N0530",
+    "Cannot fetch '{}', {}.",
+    "Only plain 'http://' URLs can be fetched, and at most a few redirects are followed.",
+    "print httpGet(\"https://example.com\", nil);",
+);
+
+pub static N0531: ErrorCode = entry(
+    "N0531",
+    "Cannot fetch '{}': {}.",
+    "The request failed, e.g. because the host cannot be reached.",
+    "print httpGet(\"http://localhost:1\", nil);",
+);
+
+pub static N0532: ErrorCode = entry(
+    "N0532",
+    "Invalid port '{}' in '{}'.",
+    "The port of the URL must be a number from 0 to 65535.",
+    "print httpGet(\"http://localhost:port\", nil);",
+);
+
+pub static N0533: ErrorCode = entry(
+    "N0533",
+    "Missing host in '{}'.",
+    "The URL has no host to connect to.",
+    "print httpGet(\"http://\", nil);",
+);
+
+pub static N0534: ErrorCode = entry(
+    "N0534",
+    "Cannot connect to '{}': {}.",
+    "'tcpConnect' cannot reach the host, e.g. because nothing listens on the port.",
+    "tcpConnect(\"localhost\", 1);",
+);
+
+pub static N0535: ErrorCode = entry(
+    "N0535",
+    "Connection error in '{}': {}.",
+    "Sending or receiving on the connection failed, e.g. because the other side closed it.",
+    "var connection = tcpConnect(\"example.com\", 80);\ntcpClose(connection);\ntcpSend(connection, \"hello\");",
+);
+
+pub static N0536: ErrorCode = entry(
+    "N0536",
+    "Native '{}' failed: {}",
+    "An async native of the embedder resolved to an error, the text after ':' is the one it gave.",
+    "// With an async native 'fetchUser' that fails\nfetchUser(1);",
+);
+
+pub static N0537: ErrorCode = entry(
+    "N0537",
+    "Second argument of '{}' must be an integer from {} to {}, got {}.",
+    "The number of digits of 'toFixed' goes from 0 to 100, the one of 'toPrecision' from 1 to 100 and the base of 'toStringBase' from 2 to 36.",
+    "print toFixed(1.5, -1);",
+);
+
+pub static N0538: ErrorCode = entry(
+    "N0538",
+    "Arguments of '{}' must be nil, booleans, numbers or strings, got {}.",
+    "Only these values can be passed to an async native of the embedder, as they leave the thread of the script.",
+    "// With an async native 'fetchUser'\nfetchUser(clock);",
+);
+
+pub static N0539: ErrorCode = entry(
+    "N0539",
+    "Range {}..{} is out of range for a string of {} characters.",
+    "The end of a range of 'slice' cannot be past the last character of the string.",
+    "print slice(\"abc\", 0, 5);",
+);
+
+pub static N0540: ErrorCode = entry(
+    "N0540",
+    "{} {} is not between {} and {}.",
+    "A part of the date given to 'dateFromParts' is out of range, e.g. a 13th month or a 31st of April.",
+    "print dateFromParts(2024, 13, 1, 0, 0, 0);",
+);
+
+pub static N0541: ErrorCode = entry(
+    "N0541",
+    "Unknown time format specifier '%{}'.",
+    "'timeFormat' takes '%Y', '%m', '%d', '%H', '%M', '%S', '%f' and '%%' in its format.",
+    "print timeFormat(0, \"%Q\");",
+);
+
+pub static N0542: ErrorCode = entry(
+    "N0542",
+    "Time format ends with an incomplete '%'.",
+    "A '%' in the format of 'timeFormat' must be followed by a specifier, write '%%' for the character itself.",
+    "print timeFormat(0, \"100%\");",
+);
+
+pub static W0601: ErrorCode = entry(
+    "W0601",
+    "Variable is declared but never used.",
+    "The linter found a local variable that is never read. Remove it, or use it if it was meant to be.",
+    "fun f() {\n  var unused = 1;\n}",
+);
+
+pub static W0602: ErrorCode = entry(
+    "W0602",
+    "Parameter is never used.",
+    "The linter found a parameter the function never reads. Remove it, or disable the unused-parameter rule when the signature is imposed.",
+    "fun f(x) {\n  return 1;\n}",
+);
+
+pub static W0603: ErrorCode = entry(
+    "W0603",
+    "Declaration shadows a variable from an enclosing scope.",
+    "The linter found a declaration hiding a variable of the same name from an enclosing scope, the outer one cannot be reached inside. Rename one of them.",
+    "var a = 1;\nfun f() {\n  var a = 2;\n  return a;\n}",
+);
+
+pub static W0604: ErrorCode = entry(
+    "W0604",
+    "Block is empty.",
+    "The linter found a block without statements, usually left over or unfinished code. Remove it or fill it in.",
+    "if (ready) {}",
+);
+
+pub static W0605: ErrorCode = entry(
+    "W0605",
+    "Condition is always the same.",
+    "The linter found a condition that is a literal, so the same branch is always taken. 'while (true)' is allowed for endless loops.",
+    "if (true) print 1;",
+);
+
+pub static W0606: ErrorCode = entry(
+    "W0606",
+    "Function has {} parameters, more than the allowed {}.",
+    "The linter found a function with more parameters than --max-params allows, 5 by default. Group related parameters in an instance.",
+    "fun f(a, b, c, d, e, f) {}",
+);
+
+/// Every code, in the order of their numbers
+pub static ERROR_CODES: &[&ErrorCode] = &[
+    &L0001, &L0002, &L0003, &L0004, &L0005, &L0006, &L0007, &L0008, &P0101, &P0102, &P0103, &P0104,
+    &P0105, &P0106, &P0107, &P0108, &P0109, &P0110, &P0111, &P0112, &P0113, &P0114, &P0115, &P0116,
+    &R0201, &R0202, &R0203, &R0204, &R0205, &R0206, &R0207, &R0208, &R0209, &R0210, &R0211, &R0212,
+    &R0213, &R0214, &R0215, &R0216, &R0217, &I0301, &I0302, &I0303, &I0304, &I0305, &I0306, &I0307,
+    &I0308, &I0309, &I0310, &I0311, &I0312, &I0313, &I0314, &I0315, &I0316, &I0317, &I0318, &I0319,
+    &I0320, &I0321, &I0322, &I0323, &I0324, &I0325, &I0326, &I0327, &I0328, &I0329, &I0330, &I0331,
+    &I0332, &I0333, &I0334, &I0335, &T0401, &T0402, &T0403, &T0404, &T0405, &T0406, &N0501, &N0502,
+    &N0503, &N0504, &N0505, &N0506, &N0507, &N0508, &N0509, &N0510, &N0511, &N0512, &N0513, &N0514,
+    &N0515, &N0516, &N0517, &N0518, &N0519, &N0520, &N0521, &N0522, &N0523, &N0524, &N0525, &N0526,
+    &N0527, &N0528, &N0529, &N0530, &N0531, &N0532, &N0533, &N0534, &N0535, &N0536, &N0537, &N0538,
+    &N0539, &N0540, &N0541, &N0542, &W0601, &W0602, &W0603, &W0604, &W0605, &W0606,
+];
+
+/// The code with this name, in any case
+pub fn find(code: &str) -> Option<&'static ErrorCode> {
+    ERROR_CODES
+        .iter()
+        .copied()
+        .find(|error_code| error_code.code.eq_ignore_ascii_case(code))
+}
+
+/// A part of a message standing for one `{}` of its template
+#[derive(Debug, Clone, PartialEq)]
+pub enum Argument {
+    /// A name, a value or a text from outside, shown as it is in every language
+    Text(String),
+    /// A phrase of the interpreter, like `variable declaration`, translated with the message
+    Term(&'static str),
+}
+
+/// A phrase of the interpreter for a `{}`, see `Argument::Term`
+pub fn term(phrase: &'static str) -> Argument {
+    Argument::Term(phrase)
+}
+
+impl From<&str> for Argument {
+    fn from(text: &str) -> Argument {
+        Argument::Text(text.to_string())
+    }
+}
+
+impl From<String> for Argument {
+    fn from(text: String) -> Argument {
+        Argument::Text(text)
+    }
+}
+
+/// Message of a diagnostic
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// A message of the registry with the arguments for its `{}`
+    Coded {
+        code: &'static ErrorCode,
+        arguments: Vec<Argument>,
+    },
+    /// Text given by a script to `error()` or by an embedder, reported as it is without a code
+    Plain(String),
+}
+
+impl Message {
+    pub fn code(&self) -> Option<&'static ErrorCode> {
+        match self {
+            Message::Coded { code, .. } => Some(code),
+            Message::Plain(_) => None,
+        }
+    }
+
+    /// The message in the language, the terms translated and the texts as they are
+    pub fn text(&self, language: Language) -> String {
+        let (code, arguments) = match self {
+            Message::Coded { code, arguments } => (code, arguments),
+            Message::Plain(text) => return text.clone(),
+        };
+        let mut pieces = language.template(code).split("{}");
+        let mut text = pieces.next().unwrap_or_default().to_string();
+        for (piece, argument) in pieces.zip(arguments) {
+            match argument {
+                Argument::Text(argument) => text.push_str(argument),
+                Argument::Term(phrase) => language.push_term(&mut text, phrase),
+            }
+            text.push_str(piece);
+        }
+        text
+    }
+}
+
+/// The message in the current language
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text(Language::current()))
+    }
+}
+
+impl ErrorCode {
+    /// Letter of the stage reporting it
+    pub fn stage(&self) -> char {
+        self.code.chars().next().unwrap_or_default()
+    }
+
+    pub fn placeholders(&self) -> usize {
+        self.message.matches("{}").count()
+    }
+
+    /// The message of this code with the arguments for its `{}`, in their order
+    pub fn with<const N: usize>(&'static self, arguments: [Argument; N]) -> Message {
+        debug_assert_eq!(N, self.placeholders(), "arguments of {}", self.code);
+        Message::Coded {
+            code: self,
+            arguments: arguments.into(),
+        }
+    }
+
+    /// Text printed by `--explain`, the parts that vary shown as `<...>`
+    pub fn explain(&self) -> String {
+        let mut text = format!(
+            "{}: {}\n\n{}\n\nExample:\n\n",
+            self.code,
+            Language::current().template(self).replace("{}", "<...>"),
+            self.explanation
+        );
+        for line in self.example.lines() {
+            let _ = writeln!(text, "    {line}");
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod error_codes_tests {
+    use super::*;
+    use crate::{capabilities::Capabilities, interpreter::Interpreter, run_captured};
+
+    /// Examples only reporting their code with an embedder, a limit, a feature, a platform
+    /// or the linter
+    const NOT_REPRODUCED: [&str; 34] = [
+        "L0008", "P0112", "P0115", "P0116", "R0216", "R0217", "I0321", "I0323", "I0324", "I0325",
+        "I0326", "I0328", "I0330", "I0331", "I0332", "I0333", "I0335", "N0505", "N0528", "N0529",
+        "N0530", "N0531", "N0532", "N0533", "N0534", "N0535", "N0536", "N0538", "W0601", "W0602",
+        "W0603", "W0604", "W0605", "W0606",
+    ];
+
+    #[test]
+    fn test_codes_are_unique() {
+        // Act
+        let mut codes: Vec<&str> = ERROR_CODES.iter().map(|code| code.code).collect();
+        codes.sort();
+        codes.dedup();
+        // Assert
+        assert_eq!(codes.len(), ERROR_CODES.len());
+        assert!(codes
+            .iter()
+            .all(|code| code.len() == 5 && "LPRITNW".contains(&code[..1])));
+    }
+
+    #[test]
+    fn test_examples_report_their_code() {
+        // Arrange
+        let examples = ERROR_CODES
+            .iter()
+            .filter(|error_code| !NOT_REPRODUCED.contains(&error_code.code));
+        // Act
+        let mismatches: Vec<String> = examples
+            .filter_map(|error_code| {
+                let mut interpreter = Interpreter::new();
+                interpreter.strict = error_code.example.starts_with("// Run with --strict");
+                if error_code.example.starts_with("// Run with --allow=io") {
+                    interpreter.capabilities = Capabilities::parse("io").unwrap();
+                }
+                let output = run_captured(error_code.example, &mut interpreter);
+                let tag = format!("[{}]", error_code.code);
+                (!output.diagnostics.contains(&tag))
+                    .then(|| format!("{} reported:\n{}", error_code.code, output.diagnostics))
+            })
+            .collect();
+        // Assert
+        assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+    }
+
+    #[test]
+    fn test_message_text() {
+        // Act
+        let undefined = I0304.with(["x".into()]);
+        let semicolon = P0103.with([term("variable declaration")]);
+        let plain = Message::Plain("Undefined variable 'x'.".to_string());
+        // Assert
+        assert_eq!(undefined.text(Language::English), "Undefined variable 'x'.");
+        assert_eq!(
+            semicolon.text(Language::English),
+            "Expect ';' after variable declaration."
+        );
+        assert_eq!(undefined.code(), find("i0304"));
+        assert_eq!(plain.code(), None);
+    }
+
+    #[test]
+    fn test_messages_of_scripts_have_no_code() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        // Act
+        let error = run_captured("error(\"Operand must be a number.\");", &mut interpreter);
+        let assertion = run_captured("assert(false, \"Cannot read file.\");", &mut interpreter);
+        // Assert
+        assert_eq!(
+            error.diagnostics,
+            "[Line 1] - Error: Operand must be a number.\n"
+        );
+        assert_eq!(
+            assertion.diagnostics,
+            "[Line 1] - Error[N0523]: Assertion failed: Cannot read file.\n"
+        );
+    }
+
+    #[test]
+    fn test_explain() {
+        // Act
+        let text = find("L0001").map(ErrorCode::explain);
+        // Assert
+        assert_eq!(
+            text.as_deref(),
+            Some("L0001: Unterminated string.\n\nA string literal runs to the end of the file without its closing '\"'. Strings may span lines, so the missing quote is reported on the last line of the file.\n\nExample:\n\n    print \"hello;\n")
+        );
+    }
+
+    #[test]
+    fn test_explain_placeholders() {
+        // Act
+        let text = P0103.explain();
+        // Assert
+        assert!(
+            text.starts_with("P0103: Expect ';' after <...>.\n\nStatements end with a ';'."),
+            "{text}"
+        );
+    }
+}
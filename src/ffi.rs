@@ -0,0 +1,219 @@
+//! C interface of the `cdylib`, declared for C and C++ in `include/rlox.h`.
+//! Strings handed out stay owned by the interpreter and are valid until its next run.
+
+use std::{
+    ffi::{c_char, c_int, c_void, CStr, CString},
+    ptr,
+};
+
+use crate::{
+    error::LoxErrorResult,
+    error_codes::{term, N0505},
+    interpreter::Interpreter,
+    object::Object,
+    run_captured,
+};
+
+/// Returned by the calls given a null pointer or a string that is not UTF-8
+pub const RLOX_INVALID_ARGUMENT: c_int = -1;
+
+/// Native function implemented in C. Lox numbers are passed in `args`,
+/// the returned number is the result of the call.
+pub type RloxNative = extern "C" fn(user_data: *mut c_void, args: *const f64, argc: usize) -> f64;
+
+/// An interpreter with the output of its last run
+pub struct RloxInterpreter {
+    interpreter: Interpreter,
+    stdout: CString,
+    errors: CString,
+}
+
+/// Creates an interpreter, release it with `rlox_free`
+#[no_mangle]
+pub extern "C" fn rlox_new() -> *mut RloxInterpreter {
+    Box::into_raw(Box::new(RloxInterpreter {
+        interpreter: Interpreter::new(),
+        stdout: CString::default(),
+        errors: CString::default(),
+    }))
+}
+
+/// # Safety
+/// `handle` must come from `rlox_new` and not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_free(handle: *mut RloxInterpreter) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Runs a script, globals defined by previous runs stay available.
+/// Returns 0 on success, 65 when it did not compile and 70 after a runtime error.
+///
+/// # Safety
+/// `handle` must come from `rlox_new` and `source` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_run(handle: *mut RloxInterpreter, source: *const c_char) -> c_int {
+    let (Some(handle), Some(source)) = (handle.as_mut(), to_str(source)) else {
+        return RLOX_INVALID_ARGUMENT;
+    };
+    let output = run_captured(source, &mut handle.interpreter);
+    handle.stdout = to_c_string(output.stdout);
+    handle.errors = to_c_string(output.diagnostics);
+    output.exit_code
+}
+
+/// What the last run printed, null for an invalid handle
+///
+/// # Safety
+/// `handle` must come from `rlox_new`.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_stdout(handle: *const RloxInterpreter) -> *const c_char {
+    handle
+        .as_ref()
+        .map_or(ptr::null(), |handle| handle.stdout.as_ptr())
+}
+
+/// Compile errors, warnings and runtime errors of the last run, one per line
+///
+/// # Safety
+/// `handle` must come from `rlox_new`.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_errors(handle: *const RloxInterpreter) -> *const c_char {
+    handle
+        .as_ref()
+        .map_or(ptr::null(), |handle| handle.errors.as_ptr())
+}
+
+/// Defines a global native function taking `arity` numbers. `user_data` is given back
+/// to every call and must outlive the interpreter. With the `sync` feature the interpreter
+/// can move to another thread, `user_data` must then be usable from it.
+///
+/// # Safety
+/// `handle` must come from `rlox_new` and `name` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_register_native(
+    handle: *mut RloxInterpreter,
+    name: *const c_char,
+    arity: usize,
+    callback: RloxNative,
+    user_data: *mut c_void,
+) -> c_int {
+    let (Some(handle), Some(name)) = (handle.as_mut(), to_str(name)) else {
+        return RLOX_INVALID_ARGUMENT;
+    };
+    let native_name = name.to_string();
+    let user_data = UserData(user_data);
+    handle
+        .interpreter
+        .define_native(name, arity, move |_, arguments, token| {
+            let mut numbers = Vec::with_capacity(arguments.len());
+            for argument in arguments {
+                match argument.as_number() {
+                    Some(number) => numbers.push(number),
+                    None => {
+                        return Err(LoxErrorResult::interpreter_error(
+                            token.line,
+                            N0505.with([native_name.as_str().into(), term("numbers")]),
+                        ))
+                    }
+                }
+            }
+            let result = callback(user_data.pointer(), numbers.as_ptr(), numbers.len());
+            Ok(Object::Number(result))
+        });
+    0
+}
+
+/// Pointer a C host registered a native with, only handed back to its callback
+struct UserData(*mut c_void);
+
+impl UserData {
+    /// Through a method so closures capture the whole `UserData`, not the bare pointer
+    fn pointer(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+// SAFETY: the pointer is never dereferenced here, `rlox_register_native` requires it to be
+// usable from the thread the interpreter runs on
+#[cfg(feature = "sync")]
+unsafe impl Send for UserData {}
+#[cfg(feature = "sync")]
+unsafe impl Sync for UserData {}
+
+unsafe fn to_str<'a>(text: *const c_char) -> Option<&'a str> {
+    match text.is_null() {
+        true => None,
+        false => CStr::from_ptr(text).to_str().ok(),
+    }
+}
+
+/// Interior NUL characters can't be represented in C strings, they are dropped
+fn to_c_string(text: String) -> CString {
+    CString::new(text.replace('\0', "")).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod ffi_tests {
+    use super::*;
+
+    extern "C" fn scale(user_data: *mut c_void, args: *const f64, argc: usize) -> f64 {
+        let factor = unsafe { *(user_data as *const f64) };
+        let args = unsafe { std::slice::from_raw_parts(args, argc) };
+        args.iter().sum::<f64>() * factor
+    }
+
+    fn read(text: *const c_char) -> String {
+        unsafe { CStr::from_ptr(text) }
+            .to_string_lossy()
+            .to_string()
+    }
+
+    #[test]
+    fn test_run_with_registered_native() {
+        // Arrange
+        let mut factor = 10.0;
+        let handle = rlox_new();
+        let name = CString::new("scale").unwrap_or_default();
+        let define = CString::new("var base = scale(1, 2);").unwrap_or_default();
+        let source = CString::new("print base + 1;\nprint scale(\"a\", 1);").unwrap_or_default();
+        // Act
+        let registered = unsafe {
+            rlox_register_native(
+                handle,
+                name.as_ptr(),
+                2,
+                scale,
+                &mut factor as *mut f64 as *mut c_void,
+            )
+        };
+        let defined = unsafe { rlox_run(handle, define.as_ptr()) };
+        let status = unsafe { rlox_run(handle, source.as_ptr()) };
+        let (stdout, errors) = unsafe { (read(rlox_stdout(handle)), read(rlox_errors(handle))) };
+        unsafe { rlox_free(handle) };
+        // Assert
+        assert_eq!((registered, defined, status), (0, 0, 70));
+        assert_eq!(stdout, "31\n");
+        assert_eq!(
+            errors,
+            "[Line 2] - Error[N0505]: Arguments of native 'scale' must be numbers.\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        // Arrange
+        let handle = rlox_new();
+        let broken = CString::new("print ;").unwrap_or_default();
+        // Act
+        let null_source = unsafe { rlox_run(handle, ptr::null()) };
+        let compile_error = unsafe { rlox_run(handle, broken.as_ptr()) };
+        let null_handle = unsafe { rlox_stdout(ptr::null()) };
+        unsafe { rlox_free(handle) };
+        // Assert
+        assert_eq!(null_source, RLOX_INVALID_ARGUMENT);
+        assert_eq!(compile_error, 65);
+        assert!(null_handle.is_null());
+    }
+}
@@ -0,0 +1,993 @@
+use std::collections::VecDeque;
+
+use crate::{
+    ast::{Span, Spanned},
+    expr::*,
+    object::Object,
+    scanner::Comment,
+    stmt::*,
+    token::Token,
+};
+
+const INDENT_WIDTH: usize = 2;
+/// Calls, parameter lists and operations longer than this are split over several lines
+const MAX_WIDTH: usize = 80;
+
+/// Re-emits parsed statements as canonically formatted Lox source.
+/// Comments are not part of the AST, the scanner's are given with `with_comments` and written
+/// back before the statement or the closing brace that follows them, or at the end of the
+/// line of the statement they were after.
+pub struct Formatter {
+    output: String,
+    indent: usize,
+    /// Not written yet, in source order
+    comments: VecDeque<Comment>,
+    /// Last line of the source statement written last, a comment on it stays at its end
+    last_line: usize,
+    /// Position of the closing brace of the block being written, its comments stay inside
+    block_end: (usize, usize),
+    /// One line without the optional spaces, see `minified`
+    minify: bool,
+    /// Spaces per level of indentation
+    indent_width: usize,
+    max_width: usize,
+    /// Width of what goes before and after the expression being formatted on its line,
+    /// e.g. `print ` and `;`
+    line_prefix: usize,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Formatter::new()
+    }
+}
+
+impl Formatter {
+    pub fn new() -> Formatter {
+        Formatter {
+            output: String::new(),
+            indent: 0,
+            comments: VecDeque::new(),
+            last_line: 0,
+            block_end: (usize::MAX, 0),
+            minify: false,
+            indent_width: INDENT_WIDTH,
+            max_width: MAX_WIDTH,
+            line_prefix: 0,
+        }
+    }
+
+    /// Indents every level by `indent_width` spaces instead of 2
+    pub fn with_indent_width(mut self, indent_width: usize) -> Formatter {
+        self.indent_width = indent_width;
+        self
+    }
+
+    /// Splits calls, parameter lists and operations longer than `max_width` instead of 80
+    /// characters
+    pub fn with_max_width(mut self, max_width: usize) -> Formatter {
+        self.max_width = max_width;
+        self
+    }
+
+    /// Keeps the comments of the formatted source, found by `Scanner::comments`. Minifying
+    /// drops them anyway.
+    pub fn with_comments(mut self, comments: &[Comment]) -> Formatter {
+        self.comments = comments.iter().cloned().collect();
+        self
+    }
+
+    /// Emits the whole script on one line, with only the spaces between words and no comments
+    pub fn minified() -> Formatter {
+        Formatter {
+            minify: true,
+            ..Formatter::new()
+        }
+    }
+
+    /// Spaces of `levels` levels of indentation
+    fn indentation(&self, levels: usize) -> String {
+        " ".repeat(levels * self.indent_width)
+    }
+
+    /// A space, unless minifying
+    fn space(&self) -> &'static str {
+        match self.minify {
+            true => "",
+            false => " ",
+        }
+    }
+
+    /// Starts a new line, unless minifying
+    fn line_break(&mut self) {
+        if !self.minify {
+            self.output.push('\n');
+        }
+    }
+
+    pub fn format(&mut self, statements: &[Stmt]) -> String {
+        self.output.clear();
+        for (idx, statement) in statements.iter().enumerate() {
+            // Top level functions and classes are kept apart by a blank line
+            let is_declaration = |stmt: &Stmt| matches!(stmt, Stmt::Function(_) | Stmt::Class(_));
+            if idx > 0 && (is_declaration(statement) || is_declaration(&statements[idx - 1])) {
+                self.line_break();
+            }
+            self.write_stmt(statement);
+        }
+        self.write_comments_before(usize::MAX, 0);
+        if self.minify && !self.output.is_empty() {
+            self.output.push('\n');
+        }
+        self.last_line = 0;
+        std::mem::take(&mut self.output)
+    }
+
+    fn write_stmt(&mut self, statement: &Stmt) {
+        let span = statement.span();
+        self.write_comments_before(span.line, span.column);
+        statement.accept(self);
+        self.end_statement(span);
+    }
+
+    /// A comment after the statement on the line where it ends stays at the end of that line
+    fn end_statement(&mut self, span: Span) {
+        if span.is_unknown() {
+            return;
+        }
+        self.last_line = span.end_line;
+        self.write_comments_before(span.end_line + 1, 0);
+    }
+
+    /// Writes the comments found before `line` and `column` of the source that are inside
+    /// the block being written. One on the line where the last statement ended goes at the
+    /// end of it, the others on their own line.
+    fn write_comments_before(&mut self, line: usize, column: usize) {
+        let end = (line, column).min(self.block_end);
+        while self.has_comment_before(end) {
+            let Some(comment) = self.comments.pop_front() else {
+                break;
+            };
+            if self.minify {
+                continue;
+            }
+            if comment.line == self.last_line && self.output.ends_with('\n') {
+                self.output.pop();
+                self.output.push(' ');
+                self.output.push_str(&comment.text);
+                self.output.push('\n');
+            } else {
+                self.write_line(&comment.text);
+                self.last_line = 0;
+            }
+        }
+    }
+
+    fn has_comment_before(&self, position: (usize, usize)) -> bool {
+        self.comments
+            .front()
+            .is_some_and(|comment| (comment.line, comment.column) < position)
+    }
+
+    /// Position of the closing brace at the end of `span`, the end of the enclosing block
+    /// when the span is unknown
+    fn closing_brace(&self, span: Span) -> (usize, usize) {
+        match span.is_unknown() {
+            true => self.block_end,
+            false => (span.end_line, span.end_column.saturating_sub(1)),
+        }
+    }
+
+    /// Writes the statements of a block and the comments before its closing brace, at the
+    /// end of `span`, one level deeper
+    fn write_block_body(&mut self, statements: &[Stmt], span: Span) {
+        let closing_brace = self.closing_brace(span);
+        let block_end = std::mem::replace(&mut self.block_end, closing_brace);
+        self.indent += 1;
+        for statement in statements {
+            self.write_stmt(statement);
+        }
+        self.write_comments_before(usize::MAX, 0);
+        self.indent -= 1;
+        self.block_end = block_end;
+    }
+
+    /// An empty block is written `{}` unless it has comments
+    fn is_empty_block(&self, statements: &[Stmt], span: Span) -> bool {
+        statements.is_empty() && (self.minify || !self.has_comment_before(self.closing_brace(span)))
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if self.minify {
+            // Words of consecutive lines, `else` and `print`, still need a space between them
+            let is_word = |c: char| c.is_alphanumeric() || c == '_';
+            if self.output.ends_with(is_word) && line.starts_with(is_word) {
+                self.output.push(' ');
+            }
+            self.output.push_str(line);
+            return;
+        }
+        let indentation = self.indentation(self.indent);
+        self.output.push_str(&indentation);
+        self.output.push_str(line);
+        self.output.push('\n');
+    }
+
+    /// Writes `header {` followed by the statements and the closing brace, which ends `span`
+    fn write_block(&mut self, header: &str, statements: &[Stmt], span: Span) {
+        self.write_braced(header, statements, span);
+        self.line_break();
+    }
+
+    /// Same as `write_block` but leaves the cursor after `}` so `else` can follow it
+    fn write_braced(&mut self, header: &str, statements: &[Stmt], span: Span) {
+        let opening = match header.is_empty() {
+            true => "{".to_string(),
+            false => format!("{header}{}{{", self.space()),
+        };
+        if self.is_empty_block(statements, span) {
+            self.write_line(&format!("{opening}}}"));
+        } else {
+            self.write_line(&opening);
+            self.write_block_body(statements, span);
+            self.write_line("}");
+        }
+        // Drop the newline, the caller decides what goes after the brace
+        if !self.minify {
+            self.output.pop();
+        }
+    }
+
+    /// Writes the body of `if`/`while`/`for`: blocks open on the header line,
+    /// any other statement goes indented on the next line
+    fn write_body(&mut self, header: &str, body: &Stmt) {
+        match body {
+            Stmt::Block(block) => self.write_block(header, &block.statements, block.span),
+            _ => {
+                self.write_line(header);
+                self.indent += 1;
+                self.write_stmt(body);
+                self.indent -= 1;
+            }
+        }
+    }
+
+    fn expr(&mut self, expression: &Expr) -> String {
+        expression.accept(self)
+    }
+
+    /// Formats an expression written after `prefix` on its line and followed by a `;`
+    fn statement_expr(&mut self, prefix: &str, expression: &Expr) -> String {
+        let line_prefix = std::mem::replace(&mut self.line_prefix, prefix.len() + 1);
+        let formatted = self.expr(expression);
+        self.line_prefix = line_prefix;
+        formatted
+    }
+
+    /// Formats `items` inside parentheses, one per line when they don't fit in one line
+    fn parenthesized_list(&mut self, prefix: &str, items: Vec<String>) -> String {
+        let single_line = format!("{prefix}({})", items.join(&format!(",{}", self.space())));
+        if items.is_empty()
+            || self.minify
+            || self.indent * self.indent_width + single_line.len() <= self.max_width
+        {
+            return single_line;
+        }
+        let item_indent = self.indentation(self.indent + 1);
+        let mut multi_line = format!("{prefix}(\n");
+        for item in items {
+            multi_line.push_str(&format!("{item_indent}{item},\n"));
+        }
+        // A trailing comma is not valid Lox, so remove it from the last item
+        multi_line.truncate(multi_line.len() - 2);
+        multi_line.push_str(&format!("\n{})", self.indentation(self.indent)));
+        multi_line
+    }
+
+    fn function_signature(&mut self, prefix: &str, function: &FunctionStmt) -> String {
+        let params = function
+            .params
+            .iter()
+            .zip(&function.param_annotations)
+            .map(|(param, annotation)| format!("{}{}", param.lexeme, self.annotated(annotation)))
+            .collect();
+        let signature =
+            self.parenthesized_list(&format!("{prefix}{}", function.name.lexeme), params);
+        match &function.return_annotation {
+            Some(annotation) => format!("{signature}{0}->{0}{1}", self.space(), annotation.lexeme),
+            None => signature,
+        }
+    }
+
+    /// Statement written inside the parentheses of a `for` header, without the final ';'
+    fn for_clause(&mut self, statement: &Option<Box<Stmt>>) -> String {
+        match statement.as_deref() {
+            Some(Stmt::Var(stmt)) => self.var_declaration(stmt),
+            Some(Stmt::Expression(stmt)) => self.expr(&stmt.expression),
+            _ => String::new(),
+        }
+    }
+
+    fn var_declaration(&mut self, stmt: &VarStmt) -> String {
+        let declaration = format!(
+            "var {}{}",
+            stmt.name.lexeme,
+            self.annotated(&stmt.annotation)
+        );
+        match &stmt.initializer {
+            Some(initializer) => {
+                let prefix = format!("{declaration}{0}={0}", self.space());
+                let initializer = self.statement_expr(&prefix, initializer);
+                format!("{prefix}{initializer}")
+            }
+            None => declaration,
+        }
+    }
+
+    /// `outer: ` in front of a labeled loop
+    fn loop_label(&self, label: &Option<Token>) -> String {
+        match label {
+            Some(label) => format!("{}:{}", label.lexeme, self.space()),
+            None => String::new(),
+        }
+    }
+
+    /// `: Number` after an annotated name, nothing otherwise
+    fn annotated(&self, annotation: &Option<Token>) -> String {
+        match annotation {
+            Some(annotation) => format!(":{}{}", self.space(), annotation.lexeme),
+            None => String::new(),
+        }
+    }
+
+    /// The operands around the operator, spaced unless minifying and the operator is a symbol.
+    /// Operations that don't fit in one line go on after the operator on the next one.
+    fn operation(&mut self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        let space = match operator.lexeme.starts_with(char::is_alphabetic) {
+            true => " ",
+            false => self.space(),
+        };
+        let (left, right) = (self.expr(left), self.expr(right));
+        let single_line = format!("{left}{space}{}{space}{right}", operator.lexeme);
+        let width = self.indent * self.indent_width + self.line_prefix + single_line.len();
+        if self.minify || !left.contains('\n') && width <= self.max_width {
+            return single_line;
+        }
+        let operand_indent = self.indentation(self.indent + 1);
+        format!("{left} {}\n{operand_indent}{right}", operator.lexeme)
+    }
+}
+
+impl StmtVisitor<()> for Formatter {
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) {
+        self.write_block("", &stmt.statements, stmt.span);
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &ClassStmt) {
+        let space = self.space();
+        let header = match &stmt.super_class {
+            Some(super_class) => format!(
+                "class {}{space}<{space}{}",
+                stmt.name.lexeme,
+                self.expr(super_class)
+            ),
+            None => format!("class {}", stmt.name.lexeme),
+        };
+        if self.is_empty_block(&stmt.methods, stmt.span) {
+            self.write_line(&format!("{header}{space}{{}}"));
+            return;
+        }
+        self.write_line(&format!("{header}{space}{{"));
+        let closing_brace = self.closing_brace(stmt.span);
+        let block_end = std::mem::replace(&mut self.block_end, closing_brace);
+        self.indent += 1;
+        for (idx, method) in stmt.methods.iter().enumerate() {
+            if idx > 0 {
+                self.line_break();
+            }
+            if let Stmt::Function(method) = method {
+                self.write_comments_before(method.span.line, method.span.column);
+                let signature = self.function_signature("", method);
+                self.write_block(&signature, &method.body, method.span);
+                self.end_statement(method.span);
+            }
+        }
+        self.write_comments_before(usize::MAX, 0);
+        self.indent -= 1;
+        self.block_end = block_end;
+        self.write_line("}");
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) {
+        let expression = self.statement_expr("", &stmt.expression);
+        self.write_line(&format!("{expression};"));
+    }
+
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) {
+        let space = self.space();
+        let initializer = self.for_clause(&stmt.initializer);
+        let condition = match &stmt.condition {
+            Some(condition) => format!("{space}{}", self.expr(condition)),
+            None => String::new(),
+        };
+        let increment = match &stmt.increment {
+            Some(increment) => format!("{space}{}", self.expr(increment)),
+            None => String::new(),
+        };
+        let header = format!(
+            "{}for{space}({initializer};{condition};{increment})",
+            self.loop_label(&stmt.label)
+        );
+        self.write_body(&header, &stmt.body);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &FunctionStmt) {
+        let signature = self.function_signature("fun ", stmt);
+        self.write_block(&signature, &stmt.body, stmt.span);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) {
+        let header = format!("if{}({})", self.space(), self.expr(&stmt.condition));
+        let (Stmt::Block(then_block), Some(else_branch)) =
+            (stmt.then_branch.as_ref(), &stmt.else_branch)
+        else {
+            self.write_body(&header, &stmt.then_branch);
+            if let Some(else_branch) = &stmt.else_branch {
+                self.write_body("else", else_branch);
+            }
+            return;
+        };
+        // `} else {` and `} else if (...) {` chains stay on the closing brace line
+        self.write_braced(&header, &then_block.statements, then_block.span);
+        match else_branch.as_ref() {
+            Stmt::Block(else_block) => {
+                let space = self.space();
+                self.output.push_str(&format!("{space}else{space}{{"));
+                self.write_block_tail(&else_block.statements, else_block.span);
+            }
+            Stmt::If(_) => {
+                self.output.push_str(&format!("{}else ", self.space()));
+                // The nested `if` is written at the current position, not on a new line
+                let mut nested = Formatter {
+                    indent: self.indent,
+                    comments: std::mem::take(&mut self.comments),
+                    last_line: self.last_line,
+                    block_end: self.block_end,
+                    minify: self.minify,
+                    indent_width: self.indent_width,
+                    max_width: self.max_width,
+                    ..Formatter::new()
+                };
+                nested.write_stmt(else_branch);
+                self.comments = nested.comments;
+                self.output.push_str(nested.output.trim_start_matches(' '));
+            }
+            _ => {
+                self.output.push_str(&format!("{}else", self.space()));
+                self.line_break();
+                self.indent += 1;
+                self.write_stmt(else_branch);
+                self.indent -= 1;
+            }
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) {
+        let expression = self.statement_expr("print ", &stmt.expression);
+        self.write_line(&format!("print {expression};"));
+    }
+
+    fn visit_for_in_stmt(&mut self, stmt: &ForInStmt) {
+        let header = format!(
+            "{}for{}(var {} in {})",
+            self.loop_label(&stmt.label),
+            self.space(),
+            stmt.name.lexeme,
+            self.expr(&stmt.iterable)
+        );
+        self.write_body(&header, &stmt.body);
+    }
+
+    fn visit_yield_stmt(&mut self, stmt: &YieldStmt) {
+        match &stmt.value {
+            Some(value) => {
+                let value = self.statement_expr("yield ", value);
+                self.write_line(&format!("yield {value};"))
+            }
+            None => self.write_line("yield;"),
+        }
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) {
+        match &stmt.value {
+            Some(value) => {
+                let value = self.statement_expr("return ", value);
+                self.write_line(&format!("return {value};"))
+            }
+            None => self.write_line("return;"),
+        }
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) {
+        let declaration = self.var_declaration(stmt);
+        self.write_line(&format!("{declaration};"));
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) {
+        let header = format!(
+            "{}while{}({})",
+            self.loop_label(&stmt.label),
+            self.space(),
+            self.expr(&stmt.condition)
+        );
+        self.write_body(&header, &stmt.body);
+    }
+
+    fn visit_break_stmt(&mut self, stmt: &BreakStmt) {
+        self.write_line(&jump("break", &stmt.label));
+    }
+
+    fn visit_continue_stmt(&mut self, stmt: &ContinueStmt) {
+        self.write_line(&jump("continue", &stmt.label));
+    }
+
+    fn visit_debugger_stmt(&mut self, _stmt: &DebuggerStmt) {
+        self.write_line("debugger;");
+    }
+}
+
+/// `break;` or `break outer;`
+fn jump(keyword: &str, label: &Option<Token>) -> String {
+    match label {
+        Some(label) => format!("{keyword} {};", label.lexeme),
+        None => format!("{keyword};"),
+    }
+}
+
+impl Formatter {
+    /// Writes the statements of a block whose `{` was already written, plus the closing brace
+    /// ending `span`
+    fn write_block_tail(&mut self, statements: &[Stmt], span: Span) {
+        if self.is_empty_block(statements, span) {
+            self.output.push('}');
+            self.line_break();
+            return;
+        }
+        self.line_break();
+        self.write_block_body(statements, span);
+        self.write_line("}");
+    }
+}
+
+impl ExprVisitor<String> for Formatter {
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> String {
+        format!(
+            "{}{space}={space}{}",
+            expr.name.lexeme,
+            self.expr(&expr.value),
+            space = self.space()
+        )
+    }
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> String {
+        self.operation(&expr.left, &expr.operator, &expr.right)
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> String {
+        let callee = self.expr(&expr.callee);
+        let arguments = expr
+            .arguments
+            .iter()
+            .map(|argument| self.expr(argument))
+            .collect();
+        self.parenthesized_list(&callee, arguments)
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> String {
+        format!("{}.{}", self.expr(&expr.object), expr.name.lexeme)
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> String {
+        format!("({})", self.expr(&expr.expression))
+    }
+
+    fn visit_literal_expr(&mut self, expr: &LiteralExpr) -> String {
+        match expr.value {
+            // `1.0` has to stay a float, `Display` would print it as the integer `1`
+            Object::Number(number) => format!("{number:?}"),
+            // Keeps a `BigInt` that fits an `Int` from turning into one
+            #[cfg(feature = "bigint")]
+            Object::BigInt(ref int) => format!("{int}n"),
+            _ => expr.value.to_string(),
+        }
+    }
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> String {
+        self.operation(&expr.left, &expr.operator, &expr.right)
+    }
+
+    fn visit_set_expr(&mut self, expr: &SetExpr) -> String {
+        format!(
+            "{}.{}{space}={space}{}",
+            self.expr(&expr.object),
+            expr.name.lexeme,
+            self.expr(&expr.value),
+            space = self.space()
+        )
+    }
+
+    fn visit_this_expr(&mut self, _expr: &ThisExpr) -> String {
+        "this".to_string()
+    }
+
+    fn visit_super_expr(&mut self, expr: &SuperExpr) -> String {
+        format!("super.{}", expr.method.lexeme)
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> String {
+        format!("{}{}", expr.operator.lexeme, self.expr(&expr.right))
+    }
+
+    fn visit_ternary_expr(&mut self, expr: &TernaryExpr) -> String {
+        format!(
+            "{}{space}?{space}{}{space}:{space}{}",
+            self.expr(&expr.condition),
+            self.expr(&expr.then_branch),
+            self.expr(&expr.else_branch),
+            space = self.space()
+        )
+    }
+
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) -> String {
+        expr.name.lexeme()
+    }
+}
+
+#[cfg(test)]
+mod formatter_tests {
+    use crate::{
+        ast_printer::AstPrinter, parse, parser::Parser, scanner::Scanner, utils::random::Random,
+    };
+
+    use super::*;
+
+    /// Syntax tree of the source as s-expressions, `None` if it doesn't parse
+    fn syntax_tree(source: &str) -> Option<Vec<String>> {
+        let statements = parse(source.to_string(), false).ok()?;
+        let mut printer = AstPrinter::new();
+        Some(
+            statements
+                .iter()
+                .map(|statement| printer.print_stmt(statement))
+                .collect(),
+        )
+    }
+
+    /// Asserts that the source emitted by both styles parses into the same tree
+    fn assert_roundtrip(source: &str) {
+        let statements = parse(source.to_string(), false).unwrap();
+        let expected = syntax_tree(source);
+        for mut formatter in [Formatter::new(), Formatter::minified()] {
+            let emitted = formatter.format(&statements);
+            assert_eq!(
+                syntax_tree(&emitted),
+                expected,
+                "{source}\nemitted as\n{emitted}"
+            );
+        }
+    }
+
+    fn random_expression(random: &mut Random, depth: usize) -> String {
+        let operators = ["+", "-", "*", "/", "<", "<=", "==", "!=", "and", "or"];
+        let choice = match depth {
+            0 => random.range(0, 3),
+            _ => random.range(0, 10),
+        };
+        let number = random.range(0, 100);
+        let name = ["a", "b", "nil", "true", "this"][random.range(0, 4) as usize];
+        let operator = operators[random.range(0, 9) as usize];
+        let mut next = || random_expression(random, depth - 1);
+        match choice {
+            0 => number.to_string(),
+            1 => "\"text\"".to_string(),
+            2 => "1.5".to_string(),
+            3 => name.to_string(),
+            4 => format!("-{}", next()),
+            5 => format!("!{}", next()),
+            6 => format!("{} {operator} {}", next(), next()),
+            7 => format!("({})", next()),
+            8 => format!("f({}, {}).field", next(), next()),
+            9 => format!("{} ? {} : {}", next(), next(), next()),
+            _ => format!("(a = {})", next()),
+        }
+    }
+
+    fn random_statement(random: &mut Random, depth: usize) -> String {
+        let choice = match depth {
+            0 => random.range(0, 1),
+            _ => random.range(0, 7),
+        };
+        let expression = random_expression(random, 3);
+        let mut next = || random_statement(random, depth - 1);
+        match choice {
+            0 => format!("print {expression};"),
+            1 => format!("var v = {expression};"),
+            2 => format!("if ({expression}) {{ {} }} else {{ {} }}", next(), next()),
+            3 => format!("while ({expression}) {{ {} {} }}", next(), next()),
+            4 => format!(
+                "for (var i = 0; i < {expression}; i = i + 1) {{ {} }}",
+                next()
+            ),
+            5 => format!("fun f(a, b) {{ {} return {expression}; }}", next()),
+            6 => format!("class C < D {{ m() {{ {} }} }}", next()),
+            _ => format!("{{ {} }}", next()),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_of_random_programs() {
+        // Arrange
+        let mut random = Random::new(7);
+        for _ in 0..200 {
+            let source = (0..3)
+                .map(|_| random_statement(&mut random, 3))
+                .collect::<Vec<String>>()
+                .join("\n");
+            // Act & Assert
+            assert_roundtrip(&source);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_of_test_files() {
+        for entry in std::fs::read_dir("test_files").unwrap() {
+            // Arrange
+            let source = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            // Act & Assert
+            if syntax_tree(&source).is_some() {
+                assert_roundtrip(&source);
+            }
+        }
+    }
+
+    #[test]
+    fn test_minified() {
+        // Arrange
+        let source = "class B < A { get() { return super.get() and !this.x; } }\n\
+            outer: for (var i = 0; i < 3; i = i + 1) if (i == 1) print i; else { var s: String = \"a b\"; }";
+        // Act
+        let minified = Formatter::minified().format(&parse(source.to_string(), false).unwrap());
+        // Assert
+        assert_eq!(
+            minified,
+            "class B<A{get(){return super.get() and !this.x;}}outer:for(var i=0;i<3;i=i+1)if(i==1)print i;else{var s:String=\"a b\";}\n"
+        );
+    }
+
+    #[test]
+    fn test_indent_and_max_width() {
+        // Arrange
+        let source = "fun f(first, second) { if (first) { print g(first, second); } }";
+        let statements = parse(source.to_string(), false).unwrap();
+        // Act
+        let formatted = Formatter::new()
+            .with_indent_width(4)
+            .with_max_width(20)
+            .format(&statements);
+        // Assert
+        assert_eq!(
+            formatted,
+            "fun f(first, second) {\n    if (first) {\n        print g(\n            first,\n            second\n        );\n    }\n}\n"
+        );
+    }
+
+    fn format_source(source: &str) -> String {
+        let tokens = Scanner::new(source.to_string()).scan_tokens();
+        let statements = Parser::new(tokens).parse();
+        Formatter::new().format(&statements)
+    }
+
+    #[test]
+    fn test_format_statements() {
+        // Arrange
+        let source = "var a=1;for(var i=0;i<3;i=i+1){print i*(a+-1);}\
+            if(a>1)print a;else if(a<0){a=0;}else{print \"s\";}";
+        // Act
+        let formatted = format_source(source);
+        // Assert
+        assert_eq!(
+            formatted,
+            "var a = 1;
+for (var i = 0; i < 3; i = i + 1) {
+  print i * (a + -1);
+}
+if (a > 1)
+  print a;
+else
+  if (a < 0) {
+    a = 0;
+  } else {
+    print \"s\";
+  }
+"
+        );
+    }
+
+    #[test]
+    fn test_format_else_without_block_after_a_block() {
+        // Arrange
+        let source = "if (a) { print 1; } else print 2;\nif(a){}else print 3;";
+        // Act
+        let formatted = format_source(source);
+        let minified = Formatter::minified().format(&parse(source.to_string(), false).unwrap());
+        // Assert
+        assert_eq!(
+            formatted,
+            "if (a) {
+  print 1;
+} else
+  print 2;
+if (a) {} else
+  print 3;
+"
+        );
+        assert_eq!(format_source(&formatted), formatted);
+        assert_eq!(
+            minified,
+            "if(a){print 1;}else print 2;if(a){}else print 3;\n"
+        );
+    }
+
+    #[test]
+    fn test_format_declarations() {
+        // Arrange
+        let source = "class B<A{init(x){this.x=x;}get(){return super.get();}}\
+            fun f(){} var b=B(1); fun g(n:Number,s)->String{var t:String=s;return t;}";
+        // Act
+        let formatted = format_source(source);
+        // Assert
+        assert_eq!(
+            formatted,
+            "class B < A {
+  init(x) {
+    this.x = x;
+  }
+
+  get() {
+    return super.get();
+  }
+}
+
+fun f() {}
+
+var b = B(1);
+
+fun g(n: Number, s) -> String {
+  var t: String = s;
+  return t;
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_format_wraps_long_calls() {
+        // Arrange
+        let source = "print someFunction(firstArgument, secondArgument, thirdArgument, fourthArgument, fifth);";
+        // Act
+        let formatted = format_source(source);
+        // Assert
+        assert_eq!(
+            formatted,
+            "print someFunction(
+  firstArgument,
+  secondArgument,
+  thirdArgument,
+  fourthArgument,
+  fifth
+);
+"
+        );
+    }
+
+    #[test]
+    fn test_format_wraps_long_operations() {
+        // Arrange
+        let source = "var total = someLongName + anotherLongName * factor - yetAnotherName / divisor + lastOne;";
+        // Act
+        let formatted = format_source(source);
+        // Assert
+        assert_eq!(
+            formatted,
+            "var total = someLongName + anotherLongName * factor - yetAnotherName / divisor +
+  lastOne;
+"
+        );
+    }
+
+    fn format_with_comments(source: &str) -> String {
+        let mut scanner = Scanner::new(source.to_string());
+        let statements = Parser::new(scanner.scan_tokens()).parse();
+        Formatter::new()
+            .with_comments(scanner.comments())
+            .format(&statements)
+    }
+
+    #[test]
+    fn test_format_keeps_comments() {
+        // Arrange
+        let source = "// header
+var a=1; // trailing
+/* block */ class A { // class line
+m() { return 1; } // after method
+// last in class
+}
+fun f() {
+  // only a comment
+}
+if (a) { print 1; } else { print 2;
+// end of else
+}
+// at the end";
+        // Act
+        let formatted = format_with_comments(source);
+        // Assert
+        assert_eq!(
+            formatted,
+            "// header
+var a = 1; // trailing
+
+/* block */
+class A {
+  // class line
+  m() {
+    return 1;
+  } // after method
+  // last in class
+}
+
+fun f() {
+  // only a comment
+}
+
+if (a) {
+  print 1;
+} else {
+  print 2;
+  // end of else
+}
+// at the end
+"
+        );
+        assert_eq!(format_with_comments(&formatted), formatted);
+    }
+
+    #[test]
+    fn test_format_keeps_the_comments_of_test_files() {
+        for entry in std::fs::read_dir("test_files").unwrap() {
+            // Arrange
+            let source = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            if syntax_tree(&source).is_none() {
+                continue;
+            }
+            let mut scanner = Scanner::new(source.clone());
+            scanner.scan_tokens();
+            // Act
+            let formatted = format_with_comments(&source);
+            // Assert
+            for comment in scanner.comments() {
+                assert!(formatted.contains(&comment.text), "{}", comment.text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_is_idempotent() {
+        // Arrange
+        let source = "if (a) { print 1; } else if (b) { print 2; } else { print 3; }";
+        // Act
+        let formatted = format_source(source);
+        // Assert
+        assert_eq!(format_source(&formatted), formatted);
+    }
+}
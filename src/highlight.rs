@@ -0,0 +1,252 @@
+use crate::{
+    analysis::{Analysis, SymbolKind},
+    interpreter::Interpreter,
+    object::Object,
+    token::{Token, TokenType},
+    utils::json::Json,
+};
+
+/// What a range of source is, for editors and renderers to pick its color
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Category {
+    Keyword,
+    /// `true`, `false` and `nil`
+    Constant,
+    String,
+    Number,
+    Comment,
+    Operator,
+    Punctuation,
+    Class,
+    Function,
+    Method,
+    Parameter,
+    Variable,
+    /// Name after a `.`, fields and methods of instances
+    Property,
+}
+
+impl Category {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::Keyword => "keyword",
+            Category::Constant => "constant",
+            Category::String => "string",
+            Category::Number => "number",
+            Category::Comment => "comment",
+            Category::Operator => "operator",
+            Category::Punctuation => "punctuation",
+            Category::Class => "class",
+            Category::Function => "function",
+            Category::Method => "method",
+            Category::Parameter => "parameter",
+            Category::Variable => "variable",
+            Category::Property => "property",
+        }
+    }
+}
+
+/// A classified range, always within a single line.
+/// Lines start at 1, columns and lengths are counted in characters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub category: Category,
+}
+
+/// Classifies every token and comment of the script, sorted by position
+pub fn highlight(source: &str) -> Vec<Highlight> {
+    classify(&Analysis::analyze(source))
+}
+
+/// Same as `highlight` for an already analyzed script
+pub fn classify(analysis: &Analysis) -> Vec<Highlight> {
+    // Natives and the functions of the prelude
+    let natives: Vec<String> = Interpreter::new()
+        .globals
+        .borrow()
+        .entries()
+        .into_iter()
+        .filter(|(_, value)| matches!(value, Object::NativeFunction(_) | Object::Function(_)))
+        .map(|(name, _)| name)
+        .collect();
+    let mut highlights = Vec::new();
+    let mut previous: Option<&Token> = None;
+    for token in analysis.tokens() {
+        let category = match token.token_type {
+            TokenType::Eof => continue,
+            TokenType::String => Category::String,
+            TokenType::Number => Category::Number,
+            TokenType::True | TokenType::False | TokenType::Nil => Category::Constant,
+            TokenType::Identifier if previous.is_some_and(|token| token.is(TokenType::Dot)) => {
+                Category::Property
+            }
+            TokenType::Identifier => match analysis.symbol_kind(token) {
+                Some(SymbolKind::Class) => Category::Class,
+                Some(SymbolKind::Function) => Category::Function,
+                Some(SymbolKind::Method) => Category::Method,
+                Some(SymbolKind::Parameter) => Category::Parameter,
+                Some(SymbolKind::Variable) => Category::Variable,
+                None if natives.contains(&token.lexeme) => Category::Function,
+                None => Category::Variable,
+            },
+            TokenType::LeftParen
+            | TokenType::RightParen
+            | TokenType::LeftBrace
+            | TokenType::RightBrace
+            | TokenType::Comma
+            | TokenType::Dot
+            | TokenType::Semicolon => Category::Punctuation,
+            TokenType::Minus
+            | TokenType::Plus
+            | TokenType::Slash
+            | TokenType::Star
+            | TokenType::Question
+            | TokenType::Colon
+            | TokenType::Bang
+            | TokenType::BangEqual
+            | TokenType::Equal
+            | TokenType::EqualEqual
+            | TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual
+            | TokenType::Arrow => Category::Operator,
+            _ => Category::Keyword,
+        };
+        // Tokens are located on the line where they end, strings can start lines before
+        let start_line = token.line - token.lexeme.matches('\n').count();
+        push_lines(
+            &mut highlights,
+            start_line,
+            token.column,
+            &token.lexeme,
+            category,
+        );
+        previous = Some(token);
+    }
+    for comment in &analysis.comments {
+        push_lines(
+            &mut highlights,
+            comment.line,
+            comment.column,
+            &comment.text,
+            Category::Comment,
+        );
+    }
+    highlights.sort_by_key(|highlight| (highlight.line, highlight.column));
+    highlights
+}
+
+/// Splits a possibly multi-line text in one highlight per line
+fn push_lines(
+    highlights: &mut Vec<Highlight>,
+    line: usize,
+    column: usize,
+    text: &str,
+    category: Category,
+) {
+    for (idx, part) in text.split('\n').enumerate() {
+        let length = part.trim_end_matches('\r').chars().count();
+        if length == 0 {
+            continue;
+        }
+        highlights.push(Highlight {
+            line: line + idx,
+            column: if idx == 0 { column } else { 0 },
+            length,
+            category,
+        });
+    }
+}
+
+/// Highlights as a JSON array, the format of `--emit=highlight-json`
+pub fn to_json(highlights: &[Highlight]) -> Json {
+    Json::Array(
+        highlights
+            .iter()
+            .map(|highlight| {
+                Json::object(vec![
+                    ("line", Json::Number(highlight.line as f64)),
+                    ("column", Json::Number(highlight.column as f64)),
+                    ("length", Json::Number(highlight.length as f64)),
+                    ("category", Json::string(highlight.category.name())),
+                ])
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod highlight_tests {
+    use super::*;
+
+    fn categories(source: &str) -> Vec<(usize, usize, usize, &'static str)> {
+        highlight(source)
+            .iter()
+            .map(|h| (h.line, h.column, h.length, h.category.name()))
+            .collect()
+    }
+
+    #[test]
+    fn test_highlight_identifier_kinds() {
+        // Act
+        let highlights = categories("class A { m(p) { return p.x; } }\nprint clock() + A;");
+        // Assert
+        assert_eq!(
+            highlights,
+            vec![
+                (1, 0, 5, "keyword"),
+                (1, 6, 1, "class"),
+                (1, 8, 1, "punctuation"),
+                (1, 10, 1, "method"),
+                (1, 11, 1, "punctuation"),
+                (1, 12, 1, "parameter"),
+                (1, 13, 1, "punctuation"),
+                (1, 15, 1, "punctuation"),
+                (1, 17, 6, "keyword"),
+                (1, 24, 1, "parameter"),
+                (1, 25, 1, "punctuation"),
+                (1, 26, 1, "property"),
+                (1, 27, 1, "punctuation"),
+                (1, 29, 1, "punctuation"),
+                (1, 31, 1, "punctuation"),
+                (2, 0, 5, "keyword"),
+                (2, 6, 5, "function"),
+                (2, 11, 1, "punctuation"),
+                (2, 12, 1, "punctuation"),
+                (2, 14, 1, "operator"),
+                (2, 16, 1, "class"),
+                (2, 17, 1, "punctuation"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_highlight_comments_and_multiline_strings() {
+        // Act
+        let highlights = categories("// note\nvar s = \"a\nbc\"; /* x\ny */ nil");
+        // Assert
+        assert_eq!(
+            highlights,
+            vec![
+                (1, 0, 7, "comment"),
+                (2, 0, 3, "keyword"),
+                (2, 4, 1, "variable"),
+                (2, 6, 1, "operator"),
+                (2, 8, 2, "string"),
+                (3, 0, 3, "string"),
+                (3, 3, 1, "punctuation"),
+                (3, 5, 4, "comment"),
+                (4, 0, 4, "comment"),
+                (4, 5, 3, "constant"),
+            ]
+        );
+        assert_eq!(
+            to_json(&highlight("nil")).to_string(),
+            r#"[{"line":1,"column":0,"length":3,"category":"constant"}]"#
+        );
+    }
+}
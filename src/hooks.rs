@@ -0,0 +1,69 @@
+use std::fmt;
+
+use crate::{
+    error::LoxErrorResult,
+    interpreter::Interpreter,
+    object::Object,
+    shared::{MaybeSync, Rc, RefCell},
+    stmt::Stmt,
+    token::Token,
+};
+
+/// Callbacks run by the interpreter while it executes a script, every one does nothing
+/// unless implemented. The profiler, coverage, tracing, recording and the debugger are built
+/// on them.
+pub trait InterpreterHooks: MaybeSync {
+    /// Before the statement runs
+    fn on_statement(&mut self, _interpreter: &mut Interpreter, _statement: &Stmt) {}
+
+    /// A Lox function or a native (`<native name>`) was called
+    fn on_function_enter(&mut self, _interpreter: &mut Interpreter, _name: &str) {}
+
+    /// The call returned, also when it failed
+    fn on_function_exit(&mut self, _interpreter: &mut Interpreter, _name: &str) {}
+
+    /// A `var` or a `for` loop defined the variable, or an assignment changed it
+    fn on_variable_write(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        _name: &Token,
+        _value: &Object,
+    ) {
+    }
+
+    /// A runtime error stopped the script, before it is reported
+    fn on_error(&mut self, _interpreter: &mut Interpreter, _error: &LoxErrorResult) {}
+}
+
+pub type HooksRef = Rc<RefCell<dyn InterpreterHooks>>;
+
+/// Hooks registered on an interpreter, run in registration order
+#[derive(Clone, Default)]
+pub struct HookList {
+    hooks: Vec<HooksRef>,
+}
+
+impl fmt::Debug for HookList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HookList({} hooks)", self.hooks.len())
+    }
+}
+
+impl HookList {
+    pub fn push(&mut self, hooks: HooksRef) {
+        self.hooks.push(hooks);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &HooksRef> {
+        self.hooks.iter()
+    }
+
+    /// Adds the hooks of `other` after these
+    pub fn extend(&mut self, other: HookList) {
+        self.hooks.extend(other.hooks);
+    }
+}
@@ -0,0 +1,358 @@
+//! Blocking HTTP/1.1 client of the `fetch` and `httpGet` natives, with the `net` feature.
+//! `http://` requests are written on a `TcpStream`, `https://` ones need the `tls` feature
+//! and go through ureq. Redirects are followed here for both.
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use crate::error_codes::{term, Message, N0530, N0531, N0532, N0533};
+
+/// How long connecting, sending the request and every read of the response may take
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Redirects followed before giving up
+const MAX_REDIRECTS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    /// Names in lowercase, in the order the server sent them
+    pub headers: Vec<(String, String)>,
+    /// Decoded as UTF-8, invalid sequences replaced
+    pub body: String,
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Parts of an `http[s]://host[:port][/path][?query][#fragment]` URL
+#[derive(Debug, PartialEq)]
+struct Url {
+    secure: bool,
+    host: String,
+    port: u16,
+    /// Path and query sent in the request, the fragment stays on the client
+    path: String,
+}
+
+impl Url {
+    fn parse(url: &str) -> Result<Url, Message> {
+        let (secure, rest) = match (url.strip_prefix("http://"), url.strip_prefix("https://")) {
+            (Some(rest), _) => (false, rest),
+            (_, Some(rest)) if cfg!(feature = "tls") => (true, rest),
+            (_, Some(_)) => {
+                return Err(N0530.with([url.into(), term("https needs the 'tls' feature")]))
+            }
+            _ => {
+                let reason = term("the URL must start with 'http://' or 'https://'");
+                return Err(N0530.with([url.into(), reason]));
+            }
+        };
+        // The authority ends at the path, the query or the fragment, whichever comes first
+        let (authority, target) = rest.split_at(rest.find(['/', '?', '#']).unwrap_or(rest.len()));
+        let target = target.split('#').next().unwrap_or_default();
+        let path = match target.starts_with('/') {
+            true => target.to_string(),
+            false => format!("/{target}"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => match port.parse() {
+                Ok(port) => (host, port),
+                Err(_) => return Err(N0532.with([port.into(), url.into()])),
+            },
+            None => (authority, if secure { 443 } else { 80 }),
+        };
+        if host.is_empty() {
+            return Err(N0533.with([url.into()]));
+        }
+        Ok(Url {
+            secure,
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+
+    /// `scheme://host:port`, for the redirects to a path on the same server
+    fn origin(&self) -> String {
+        let scheme = if self.secure { "https" } else { "http" };
+        format!("{scheme}://{}:{}", self.host, self.port)
+    }
+}
+
+/// Sends a GET request with the headers and follows the redirects
+pub fn get(url: &str, headers: &[(String, String)]) -> Result<Response, Message> {
+    let mut url = url.to_string();
+    for _ in 0..=MAX_REDIRECTS {
+        let response = request(&Url::parse(&url)?, headers)
+            .map_err(|err| N0531.with([url.as_str().into(), err.to_string().into()]))?;
+        match (response.status, response.header("location")) {
+            (301 | 302 | 303 | 307 | 308, Some(location)) => {
+                url = match location.starts_with('/') {
+                    true => format!("{}{location}", Url::parse(&url)?.origin()),
+                    false => location.to_string(),
+                };
+            }
+            _ => return Ok(response),
+        }
+    }
+    Err(N0530.with([url.into(), term("too many redirects")]))
+}
+
+fn request(url: &Url, headers: &[(String, String)]) -> std::io::Result<Response> {
+    #[cfg(feature = "tls")]
+    if url.secure {
+        return tls::request(url, headers);
+    }
+    let address = format!("{}:{}", url.host, url.port);
+    let mut stream = TcpStream::connect(&address)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut head = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nUser-Agent: rlox\r\n",
+        url.path, url.host
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    let mut bytes = Vec::new();
+    stream.read_to_end(&mut bytes)?;
+    parse_response(&bytes)
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "malformed response"))
+}
+
+fn parse_response(bytes: &[u8]) -> Option<Response> {
+    let head_end = bytes.windows(4).position(|window| window == b"\r\n\r\n")?;
+    let head = String::from_utf8_lossy(&bytes[..head_end]);
+    let mut lines = head.split("\r\n");
+    // `HTTP/1.1 200 OK`
+    let status = lines.next()?.split(' ').nth(1)?.parse().ok()?;
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+    let mut response = Response {
+        status,
+        headers,
+        body: String::new(),
+    };
+    let mut body = &bytes[head_end + 4..];
+    if let Some(length) = response
+        .header("content-length")
+        .and_then(|length| length.parse().ok())
+    {
+        body = body.get(..length)?;
+    }
+    let body = match response.header("transfer-encoding") {
+        Some(encoding) if encoding.eq_ignore_ascii_case("chunked") => dechunk(body)?,
+        _ => body.to_vec(),
+    };
+    response.body = String::from_utf8_lossy(&body).into_owned();
+    Some(response)
+}
+
+/// Body sent in chunks, each after its size in hexadecimal, up to a chunk of size 0
+fn dechunk(mut bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = bytes.windows(2).position(|window| window == b"\r\n")?;
+        let size = String::from_utf8_lossy(&bytes[..line_end]);
+        // Chunk extensions follow a `;`
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        if size == 0 {
+            return Some(body);
+        }
+        let chunk = bytes.get(line_end + 2..line_end + 2 + size)?;
+        body.extend_from_slice(chunk);
+        bytes = bytes.get(line_end + 4 + size..)?;
+    }
+}
+
+/// `https://` requests through ureq, which verifies the certificates against the webpki roots
+#[cfg(feature = "tls")]
+mod tls {
+    use std::io::{Error, Read};
+
+    use super::{Response, Url, TIMEOUT};
+
+    pub(super) fn request(url: &Url, headers: &[(String, String)]) -> std::io::Result<Response> {
+        // `get` follows the redirects itself, the same way for both schemes
+        let agent = ureq::AgentBuilder::new()
+            .timeout(TIMEOUT)
+            .redirects(0)
+            .user_agent("rlox")
+            .build();
+        let mut request = agent.get(&format!("{}{}", url.origin(), url.path));
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        let response = match request.call() {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(ureq::Error::Transport(err)) => return Err(Error::other(err.to_string())),
+        };
+        let status = response.status();
+        let headers = response
+            .headers_names()
+            .into_iter()
+            .filter_map(|name| {
+                let value = response.header(&name)?.to_string();
+                Some((name.to_lowercase(), value))
+            })
+            .collect();
+        let mut body = Vec::new();
+        response.into_reader().read_to_end(&mut body)?;
+        Ok(Response {
+            status,
+            headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod http_tests {
+    use super::*;
+    use std::{net::TcpListener, thread};
+
+    /// Address of a server answering the next `responses.len()` connections
+    fn serve(responses: Vec<&'static str>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request).unwrap();
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        format!("http://{address}")
+    }
+
+    #[test]
+    fn test_get_follows_redirects_and_decodes_chunks() {
+        // Arrange
+        let base = serve(vec![
+            "HTTP/1.1 302 Found\r\nLocation: /data\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n7\r\n, world\r\n0\r\n\r\n",
+        ]);
+        // Act
+        let response = get(&format!("{base}/start"), &[]);
+        // Assert
+        let response = response.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.header("Content-Type"), Some("text/plain"));
+        assert_eq!(response.body, "hello, world");
+    }
+
+    #[test]
+    fn test_get_sends_the_query_without_the_fragment() {
+        // Arrange
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let length = stream.read(&mut request).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                .unwrap();
+            String::from_utf8_lossy(&request[..length]).into_owned()
+        });
+        // Act
+        let response = get(&format!("http://{address}?q=1#top"), &[]);
+        // Assert
+        assert_eq!(response.map(|response| response.body), Ok("ok".to_string()));
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /?q=1 HTTP/1.1\r\n"), "{request}");
+    }
+
+    #[cfg(not(feature = "tls"))]
+    #[test]
+    fn test_https_needs_the_tls_feature() {
+        use crate::locale::Language;
+        // Act
+        let secure = get("https://example.com", &[]);
+        // Assert
+        assert_eq!(
+            secure.map_err(|message| message.text(Language::English)),
+            Err("Cannot fetch 'https://example.com', https needs the 'tls' feature.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_url() {
+        // Act
+        let full = Url::parse("http://localhost:8080/a?b=c");
+        let bare = Url::parse("http://example.com");
+        // Assert
+        assert_eq!(
+            full,
+            Ok(Url {
+                secure: false,
+                host: "localhost".to_string(),
+                port: 8080,
+                path: "/a?b=c".to_string()
+            })
+        );
+        assert_eq!(
+            bare.map(|url| (url.port, url.path)),
+            Ok((80, "/".to_string()))
+        );
+        assert!(Url::parse("ftp://example.com").is_err());
+    }
+
+    #[test]
+    fn test_parse_url_ends_the_authority_at_the_query_or_the_fragment() {
+        // Act
+        let query = Url::parse("http://host?q=1");
+        let fragment = Url::parse("http://host:8080#top");
+        let path_fragment = Url::parse("http://host/a/b?c=d#e");
+        let empty_host = Url::parse("http://?q=1");
+        // Assert
+        assert_eq!(
+            query.map(|url| (url.host, url.port, url.path)),
+            Ok(("host".to_string(), 80, "/?q=1".to_string()))
+        );
+        assert_eq!(
+            fragment.map(|url| (url.host, url.port, url.path)),
+            Ok(("host".to_string(), 8080, "/".to_string()))
+        );
+        assert_eq!(
+            path_fragment.map(|url| url.path),
+            Ok("/a/b?c=d".to_string())
+        );
+        assert!(empty_host.is_err());
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn test_parse_https_url() {
+        // Act
+        let url = Url::parse("https://example.com/a");
+        // Assert
+        assert_eq!(
+            url,
+            Ok(Url {
+                secure: true,
+                host: "example.com".to_string(),
+                port: 443,
+                path: "/a".to_string()
+            })
+        );
+        assert_eq!(
+            Url::parse("https://example.com:8443").map(|url| url.origin()),
+            Ok("https://example.com:8443".to_string())
+        );
+    }
+}
@@ -0,0 +1,485 @@
+//! Parsing for editors, where a script changes a few characters at a time. The script is kept
+//! as chunks of whole lines, each with the tokens and top-level declarations it holds; an edit
+//! only scans and parses again the chunks it touches. Chunks with diagnostics are parsed again
+//! with every edit, along with the lines between them and the edit: once the script parses
+//! without errors its statements are the ones a full parse gives.
+// Expressions are hashed by their uid, the cells in the constants they hold never change it
+#![allow(clippy::mutable_key_type)]
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use crate::{
+    cache::{Decode, Decoder, Encode, Encoder},
+    error::{collect_diagnostics, Diagnostic},
+    error_codes::{L0001, L0002},
+    object::Object,
+    parser::Parser,
+    scanner::{Comment, Scanner},
+    source_map::SourceMap,
+    stmt::Stmt,
+    token::{Token, TokenType},
+};
+
+/// Text replacing the characters from `start` to `end`, lines start at 1 and columns, counted
+/// in characters, at 0. Positions past the end of a line or of the script are clamped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub text: String,
+}
+
+impl TextEdit {
+    /// The smallest edit turning `old` into `new`, `None` when they are equal
+    pub fn between(old: &str, new: &str) -> Option<TextEdit> {
+        if old == new {
+            return None;
+        }
+        let old: Vec<char> = old.chars().collect();
+        let new: Vec<char> = new.chars().collect();
+        let prefix = old
+            .iter()
+            .zip(&new)
+            .take_while(|(old, new)| old == new)
+            .count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(old, new)| old == new)
+            .count();
+        Some(TextEdit {
+            start: position(&old[..prefix]),
+            end: position(&old[..old.len() - suffix]),
+            text: new[prefix..new.len() - suffix].iter().collect(),
+        })
+    }
+}
+
+/// Line and column right after `text`
+fn position(text: &[char]) -> (usize, usize) {
+    let line = text.iter().filter(|char| **char == '\n').count() + 1;
+    let column = text.iter().rev().take_while(|char| **char != '\n').count();
+    (line, column)
+}
+
+/// Lines `first_line..=last_line` of the script, no token or comment goes past them
+#[derive(Debug)]
+struct Chunk {
+    first_line: usize,
+    last_line: usize,
+    /// Without the end of file
+    tokens: Vec<Token>,
+    comments: Vec<Comment>,
+    statements: Vec<Stmt>,
+    diagnostics: Vec<Diagnostic>,
+    had_error: bool,
+}
+
+impl Chunk {
+    /// Parsed again by the next edit, wherever it is
+    fn is_dirty(&self) -> bool {
+        self.had_error || !self.diagnostics.is_empty()
+    }
+
+    /// Moves the chunk `shift` lines down, or up when negative
+    fn shift(&mut self, shift: isize) -> Result<(), String> {
+        let moved = |line: usize| {
+            line.checked_add_signed(shift)
+                .ok_or_else(|| format!("Line {line} cannot move by {shift} lines."))
+        };
+        self.first_line = moved(self.first_line)?;
+        self.last_line = moved(self.last_line)?;
+        for token in &mut self.tokens {
+            token.line = moved(token.line)?;
+        }
+        for comment in &mut self.comments {
+            comment.line = moved(comment.line)?;
+        }
+        let locals = HashMap::new();
+        let mut encoder = Encoder::new(&locals);
+        self.statements.encode(&mut encoder);
+        let bytes = encoder.into_bytes();
+        let mut locals = HashMap::new();
+        let mut decoder = Decoder::new(&bytes, &mut locals).shifting_lines(shift);
+        self.statements = Vec::decode(&mut decoder)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct IncrementalParser {
+    /// Without their line breaks
+    lines: Vec<String>,
+    /// Cover every line, in order
+    chunks: Vec<Chunk>,
+    /// Lines scanned and parsed by the last edit
+    reparsed_lines: RangeInclusive<usize>,
+}
+
+impl IncrementalParser {
+    /// Scans and parses the whole script
+    pub fn new(source: &str) -> IncrementalParser {
+        let source = source.strip_prefix('\u{FEFF}').unwrap_or(source);
+        let lines: Vec<String> = source
+            .replace("\r\n", "\n")
+            .split('\n')
+            .map(String::from)
+            .collect();
+        let last_line = lines.len();
+        let mut parser = IncrementalParser {
+            lines,
+            chunks: Vec::new(),
+            reparsed_lines: 1..=last_line,
+        };
+        parser.chunks = parser.parse_lines(1, last_line);
+        parser
+    }
+
+    /// Applies the edit, scanning and parsing again the chunks it touches and the dirty ones
+    pub fn edit(&mut self, edit: &TextEdit) {
+        let (start, end) = match edit.start <= edit.end {
+            true => (self.clamp(edit.start), self.clamp(edit.end)),
+            false => (self.clamp(edit.end), self.clamp(edit.start)),
+        };
+        let text = edit.text.replace("\r\n", "\n");
+        let before: String = self.lines[start.0 - 1].chars().take(start.1).collect();
+        let after: String = self.lines[end.0 - 1].chars().skip(end.1).collect();
+        let replaced: Vec<String> = format!("{before}{text}{after}")
+            .split('\n')
+            .map(String::from)
+            .collect();
+        let shift = replaced.len() as isize - (end.0 - start.0 + 1) as isize;
+        self.lines.splice(start.0 - 1..end.0, replaced);
+
+        // Chunks touched by the edit, and the dirty ones with every chunk in between
+        let touched = |chunk: &Chunk| chunk.first_line <= end.0 && start.0 <= chunk.last_line;
+        let first = self
+            .chunks
+            .iter()
+            .position(|chunk| touched(chunk) || chunk.is_dirty())
+            .unwrap_or(0);
+        let last = self
+            .chunks
+            .iter()
+            .rposition(|chunk| touched(chunk) || chunk.is_dirty())
+            .unwrap_or(self.chunks.len() - 1);
+        let first_line = self.chunks[first].first_line;
+        let last_line = self.chunks[last]
+            .last_line
+            .checked_add_signed(shift)
+            .unwrap_or(first_line)
+            .max(first_line);
+
+        let mut following = self.chunks.split_off(last + 1);
+        self.chunks.truncate(first);
+        let parsed = self.parse_lines(first_line, last_line);
+        let parsed_lines = first_line..=parsed.last().map_or(last_line, |chunk| chunk.last_line);
+        self.chunks.extend(parsed);
+        self.reparsed_lines = parsed_lines.clone();
+        // An unterminated string or comment took the following chunks
+        if *parsed_lines.end() > last_line {
+            return;
+        }
+        for chunk in &mut following {
+            if shift != 0 && chunk.shift(shift).is_err() {
+                // Never happens for the chunks below an edit, parse them again if it does
+                let parsed = self.parse_lines(*parsed_lines.start(), self.lines.len());
+                self.chunks.truncate(first);
+                self.chunks.extend(parsed);
+                self.reparsed_lines = *parsed_lines.start()..=self.lines.len();
+                return;
+            }
+        }
+        self.chunks.extend(following);
+    }
+
+    /// Top-level statements of the script, without the ones that failed to parse
+    pub fn statements(&self) -> Vec<Stmt> {
+        self.chunks
+            .iter()
+            .flat_map(|chunk| chunk.statements.iter().cloned())
+            .collect()
+    }
+
+    /// Tokens of the script, ending with the end of file like `Scanner::scan_tokens`
+    pub fn tokens(&self) -> Vec<Token> {
+        let last_line = self.lines.len();
+        let column = self.lines[last_line - 1].chars().count();
+        self.chunks
+            .iter()
+            .flat_map(|chunk| chunk.tokens.iter().cloned())
+            .chain(std::iter::once(
+                Token::new(TokenType::Eof, "".to_owned(), Object::Nil, last_line).at_column(column),
+            ))
+            .collect()
+    }
+
+    pub fn comments(&self) -> Vec<Comment> {
+        self.chunks
+            .iter()
+            .flat_map(|chunk| chunk.comments.iter().cloned())
+            .collect()
+    }
+
+    /// Diagnostics of the scanner and the parser
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.chunks
+            .iter()
+            .flat_map(|chunk| chunk.diagnostics.iter().cloned())
+            .collect()
+    }
+
+    pub fn had_error(&self) -> bool {
+        self.chunks.iter().any(|chunk| chunk.had_error)
+    }
+
+    pub fn source(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// Lines scanned and parsed by the last edit, all of them after `new`
+    pub fn reparsed_lines(&self) -> RangeInclusive<usize> {
+        self.reparsed_lines.clone()
+    }
+
+    /// The position moved inside the script
+    fn clamp(&self, (line, column): (usize, usize)) -> (usize, usize) {
+        let line = line.clamp(1, self.lines.len());
+        (line, column.min(self.lines[line - 1].chars().count()))
+    }
+
+    /// Scans and parses the lines, in one chunk when anything is wrong with them, else in as
+    /// many chunks as possible. An unterminated string or comment goes on to the end of the
+    /// script, like in a full scan.
+    fn parse_lines(&self, first_line: usize, last_line: usize) -> Vec<Chunk> {
+        let source = self.lines[first_line - 1..last_line].join("\n");
+        let mut tokens = Vec::new();
+        let mut comments = Vec::new();
+        let mut statements = Vec::new();
+        let mut had_error = false;
+        let mut unterminated = false;
+        let diagnostics = collect_diagnostics(|| {
+            let mut scanner =
+                Scanner::from_source_map(SourceMap::new(&source).starting_at_line(first_line));
+            tokens = scanner.scan_tokens();
+            comments = scanner.comments().to_vec();
+            had_error = !scanner.errors().is_empty();
+            unterminated = scanner.errors().iter().any(
+                |error| matches!(error.code(), Some(code) if code == &L0001 || code == &L0002),
+            );
+            let mut parser = Parser::new(tokens.clone());
+            statements = parser.parse_with_lines();
+            had_error |= parser.had_error;
+            // The end of the lines, which the chunk doesn't keep
+            tokens.pop();
+        });
+        if unterminated && last_line < self.lines.len() {
+            return self.parse_lines(first_line, self.lines.len());
+        }
+        if had_error || !diagnostics.is_empty() {
+            return vec![Chunk {
+                first_line,
+                last_line,
+                tokens,
+                comments,
+                statements: statements.into_iter().map(|(stmt, _)| stmt).collect(),
+                diagnostics,
+                had_error,
+            }];
+        }
+
+        // A chunk can end after a line no statement, token or comment goes past
+        let mut joined = vec![false; last_line - first_line];
+        let mut join = |lines: RangeInclusive<usize>| {
+            for line in *lines.start()..*lines.end() {
+                joined[line - first_line] = true;
+            }
+        };
+        for (_, lines) in &statements {
+            join(lines.clone());
+        }
+        for token in &tokens {
+            join(token.line - token.lexeme.matches('\n').count()..=token.line);
+        }
+        for comment in &comments {
+            join(comment.line..=comment.line + comment.text.matches('\n').count());
+        }
+
+        let mut chunks: Vec<Chunk> = Vec::new();
+        let mut statements = statements.into_iter().peekable();
+        let mut tokens = tokens.into_iter().peekable();
+        let mut comments = comments.into_iter().peekable();
+        let mut start = first_line;
+        for line in first_line..=last_line {
+            if line < last_line && joined[line - first_line] {
+                continue;
+            }
+            let mut chunk = Chunk {
+                first_line: start,
+                last_line: line,
+                tokens: Vec::new(),
+                comments: Vec::new(),
+                statements: Vec::new(),
+                diagnostics: Vec::new(),
+                had_error: false,
+            };
+            while let Some(token) = tokens.next_if(|token| token.line <= line) {
+                chunk.tokens.push(token);
+            }
+            while let Some(comment) = comments.next_if(|comment| comment.line <= line) {
+                chunk.comments.push(comment);
+            }
+            while let Some((stmt, _)) = statements.next_if(|(_, lines)| *lines.start() <= line) {
+                chunk.statements.push(stmt);
+            }
+            chunks.push(chunk);
+            start = line + 1;
+        }
+        chunks
+    }
+}
+
+#[cfg(test)]
+mod incremental_tests {
+    use super::*;
+    use crate::utils::random::Random;
+
+    const SOURCE: &str = "// Shapes
+class Shape {
+  init(size) { this.size = size; }
+  area() { return 0; }
+}
+
+fun describe(shape) {
+  var text = \"a shape
+of size \";
+  return text + shape.size;
+}
+/* counts
+   them */
+var count = 0;
+for (var i = 0; i < 3; i = i + 1) { count = count + describe(Shape(i)).size; }
+print count;
+";
+
+    /// Tokens with their positions, the end of file included
+    fn positions(tokens: &[Token]) -> Vec<(TokenType, String, usize, usize)> {
+        tokens
+            .iter()
+            .map(|token| {
+                let (line, column) = (token.line, token.column);
+                (token.token_type.clone(), token.lexeme.clone(), line, column)
+            })
+            .collect()
+    }
+
+    fn encoded(statements: &[Stmt]) -> Vec<u8> {
+        let locals = HashMap::new();
+        let mut encoder = Encoder::new(&locals);
+        statements.to_vec().encode(&mut encoder);
+        encoder.into_bytes()
+    }
+
+    /// Asserts that the incremental parse matches a full parse of its source
+    fn assert_matches_full_parse(parse: &IncrementalParser) {
+        let source = parse.source();
+        let mut tokens = Vec::new();
+        let mut statements = Vec::new();
+        let mut had_error = false;
+        collect_diagnostics(|| {
+            let mut scanner = Scanner::new(source.clone());
+            tokens = scanner.scan_tokens();
+            had_error = !scanner.errors().is_empty();
+            let mut parser = Parser::new(tokens.clone());
+            statements = parser.parse();
+            had_error |= parser.had_error;
+        });
+        assert_eq!(positions(&parse.tokens()), positions(&tokens), "{source}");
+        assert_eq!(parse.had_error(), had_error, "{source}");
+        if !had_error {
+            assert_eq!(
+                encoded(&parse.statements()),
+                encoded(&statements),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_random_edits_match_a_full_parse() {
+        // Arrange
+        let fragments = [
+            "",
+            "x",
+            "{",
+            "}",
+            ";",
+            "\n",
+            "\n\n",
+            "\"",
+            "// note\n",
+            "/*",
+            "*/",
+            "var q = 1;\n",
+            "fun g() {\n",
+            "print 2;",
+        ];
+        let mut random = Random::new(11);
+        let mut parse = IncrementalParser::new(SOURCE);
+        for step in 0..300 {
+            let source: Vec<char> = parse.source().chars().collect();
+            let start = random.range(0, source.len() as i64) as usize;
+            let end = (start + random.range(0, 6) as usize).min(source.len());
+            let text = fragments[random.range(0, fragments.len() as i64 - 1) as usize];
+            let mut edited: String = source[..start].iter().collect();
+            edited.push_str(text);
+            edited.extend(&source[end..]);
+            // Back to the original script from time to time, which parses without errors
+            if step % 10 == 9 {
+                edited = SOURCE.to_string();
+            }
+            // Act
+            if let Some(edit) = TextEdit::between(&parse.source(), &edited) {
+                parse.edit(&edit);
+            }
+            // Assert
+            assert_eq!(parse.source(), edited);
+            assert_matches_full_parse(&parse);
+        }
+    }
+
+    #[test]
+    fn test_edit_parses_only_the_enclosing_declaration() {
+        // Arrange
+        let mut parse = IncrementalParser::new(SOURCE);
+        let edit = TextEdit {
+            start: (10, 9),
+            end: (10, 13),
+            text: "\"size \" +\n    text".to_string(),
+        };
+        // Act
+        parse.edit(&edit);
+        // Assert
+        assert_eq!(parse.reparsed_lines(), 7..=12);
+        assert!(!parse.had_error());
+        assert_eq!(parse.statements().len(), 5);
+        assert_eq!(parse.tokens().last().unwrap().line, 18);
+        assert_matches_full_parse(&parse);
+    }
+
+    #[test]
+    fn test_text_edit_between() {
+        // Act
+        let edit = TextEdit::between("var a = 1;\nprint a;", "var a = 1;\nprint b + a;");
+        // Assert
+        assert_eq!(
+            edit,
+            Some(TextEdit {
+                start: (2, 6),
+                end: (2, 6),
+                text: "b + ".to_string(),
+            })
+        );
+        assert_eq!(TextEdit::between("a", "a"), None);
+    }
+}
@@ -1,58 +1,854 @@
 use std::{
-    collections::HashMap,
-    rc::Rc,
-    time::{SystemTime, UNIX_EPOCH},
+    collections::{HashMap, VecDeque},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+
 use crate::{
-    environment::*, error::*, expr::*, lox_callable::*, lox_class::LoxClass,
-    lox_function::LoxFunction, lox_native_function::*, object::*, stmt::*, token::*,
+    ast::Spanned,
+    capabilities::Capabilities,
+    closure_compiler::CompiledBlock,
+    environment::*,
+    error::*,
+    error_codes::{
+        I0301, I0303, I0305, I0306, I0307, I0308, I0312, I0313, I0314, I0318, I0319, I0321, I0324,
+        I0325, I0326, I0329, I0330, I0331, I0332, I0333, I0334, I0335,
+    },
+    expr::*,
+    hooks::{HookList, HooksRef, InterpreterHooks},
+    lox_callable::*,
+    lox_class::LoxClass,
+    lox_function::LoxFunction,
+    lox_generator::LoxIterator,
+    lox_instance::LoxInstanceRef,
+    lox_module::LoxModule,
+    lox_native_function::{LoxNativeFunction, NativeFn},
+    natives,
+    object::*,
+    shared::{Rc, RefCell},
+    stmt::*,
+    test_runner::TestOutcome,
+    token::*,
+    utils::random::Random,
+    walker::Walker,
+    RunError,
 };
 
+/// Destination of `print` and of the runtime errors
+#[derive(Debug, Clone, Default)]
+pub enum Output {
+    #[default]
+    Console,
+    Captured(Rc<RefCell<CapturedOutput>>),
+}
+
+#[derive(Debug, Default)]
+pub struct CapturedOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Source of the lines returned by `readLine()`
+#[derive(Debug, Clone, Default)]
+pub enum Input {
+    #[default]
+    Console,
+    /// Lines given in advance, `readLine()` returns `nil` once they are consumed
+    Lines(Rc<RefCell<VecDeque<String>>>),
+}
+
+impl Input {
+    /// Next line without its line break, `None` at the end of the input
+    pub fn read_line(&self) -> Option<String> {
+        match self {
+            Input::Console => {
+                let mut line = String::new();
+                match io::stdin().read_line(&mut line) {
+                    Ok(0) | Err(_) => None,
+                    Ok(_) => {
+                        let length = line.trim_end_matches(['\n', '\r']).len();
+                        line.truncate(length);
+                        Some(line)
+                    }
+                }
+            }
+            Input::Lines(lines) => lines.borrow_mut().pop_front(),
+        }
+    }
+}
+
+/// Where `readFile`, `writeFile` and `appendFile` work
+#[derive(Debug, Clone, Default)]
+pub enum FileSystem {
+    #[default]
+    Disk,
+    /// Files kept in memory by path, for tests and sandboxed embedders
+    Memory(Rc<RefCell<HashMap<String, String>>>),
+    /// Files on disk under this canonical directory only, paths are relative to it (`--fs-root`)
+    Root(PathBuf),
+}
+
+impl FileSystem {
+    /// Confines the file natives to `dir`, which must exist
+    pub fn root(dir: &str) -> io::Result<FileSystem> {
+        Ok(FileSystem::Root(Path::new(dir).canonicalize()?))
+    }
+
+    pub fn read(&self, path: &str) -> io::Result<String> {
+        match self {
+            FileSystem::Disk => std::fs::read_to_string(path),
+            FileSystem::Root(root) => std::fs::read_to_string(confine(root, path)?),
+            FileSystem::Memory(files) => {
+                files.borrow().get(path).cloned().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, "No such file in memory")
+                })
+            }
+        }
+    }
+
+    /// Replaces the file, or adds to its end with `append`, creating it when missing
+    pub fn write(&self, path: &str, contents: &str, append: bool) -> io::Result<()> {
+        match self {
+            FileSystem::Disk if append => std::fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)?
+                .write_all(contents.as_bytes()),
+            FileSystem::Disk => std::fs::write(path, contents),
+            FileSystem::Root(root) => {
+                FileSystem::Disk.write(&confine(root, path)?.to_string_lossy(), contents, append)
+            }
+            FileSystem::Memory(files) => {
+                let mut files = files.borrow_mut();
+                let file = files.entry(path.to_string()).or_default();
+                if !append {
+                    file.clear();
+                }
+                file.push_str(contents);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Real location of `path` under `root`, refused when `..` or a symbolic link leads out of it.
+/// A file that doesn't exist yet must be in an existing directory.
+fn confine(root: &Path, path: &str) -> io::Result<PathBuf> {
+    let outside = || {
+        io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "The path leads out of the file root",
+        )
+    };
+    // An absolute path replaces the root, it is only accepted when it lies under it
+    let joined = root.join(path);
+    let resolved = match joined.canonicalize() {
+        Ok(resolved) => resolved,
+        // A dangling link would be followed by a write
+        Err(err) if err.kind() == io::ErrorKind::NotFound && joined.symlink_metadata().is_err() => {
+            let name = joined.file_name().ok_or_else(outside)?;
+            let parent = joined.parent().ok_or_else(outside)?.canonicalize()?;
+            parent.join(name)
+        }
+        Err(err) => return Err(err),
+    };
+    match resolved.starts_with(root) {
+        true => Ok(resolved),
+        false => Err(outside()),
+    }
+}
+
+/// Where `clock`, `now` and `clockMonotonic` read the time
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Clock {
+    #[default]
+    System,
+    /// Milliseconds that start at 0 and advance by one on every reading,
+    /// `sleep` moves them forward instead of waiting
+    Virtual(f64),
+}
+
+impl Clock {
+    /// Next reading of the virtual clock, `None` when the system time applies
+    pub fn tick(&mut self) -> Option<f64> {
+        match self {
+            Clock::System => None,
+            Clock::Virtual(millis) => {
+                *millis += 1.0;
+                Some(*millis)
+            }
+        }
+    }
+}
+
+/// What dividing a number by zero gives
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DivisionByZero {
+    /// A runtime error, telling `0 / 0` apart from `x / 0`
+    #[default]
+    Error,
+    /// `Infinity`, `-Infinity` or `NaN` as IEEE 754 floats do (`--ieee-division`)
+    Ieee,
+}
+
+/// Seed of `random()` in deterministic mode, until the script calls `randomSeed()`
+pub const DETERMINISTIC_SEED: u64 = 0;
+
+/// Argument vectors kept for reuse, enough for the calls nested in a typical program
+pub const MAX_ARGUMENT_BUFFERS: usize = 64;
+
+/// Caps on the size of values, on the time and on the output of a run: a runaway script
+/// such as `while (true) s = s + s;` raises a runtime error instead of taking every byte
+/// of memory
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// In bytes
+    pub max_string_length: usize,
+    pub max_list_length: usize,
+    /// Fields of one instance
+    pub max_fields: usize,
+    /// Time a call to `interpret` may take, checked before every statement. None by default,
+    /// there is no clock to read on `wasm32-unknown-unknown`.
+    pub max_run_time: Option<Duration>,
+    /// Bytes `print` may write during a call to `interpret`
+    pub max_output_length: usize,
+    /// Calls of Lox functions and methods nested at once, calls in tail position do not
+    /// count. A deeper recursion would overflow the host stack and abort the process.
+    pub max_call_depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_string_length: 1 << 26,
+            max_list_length: 1 << 24,
+            max_fields: 1 << 16,
+            max_run_time: None,
+            max_output_length: usize::MAX,
+            max_call_depth: usize::MAX,
+        }
+    }
+}
+
+/// Utility functions written in Lox, defined by `Interpreter::new`
+pub const PRELUDE: &str = include_str!("prelude.lox");
+
+/// Chooses what scripts see at startup, for hosts running plugins:
+/// `InterpreterBuilder::new().without_default_natives().with_natives(natives).with_prelude(source)`.
+/// By default it builds the same interpreter as `Interpreter::new`.
+pub struct InterpreterBuilder {
+    default_natives: bool,
+    default_prelude: bool,
+    natives: Vec<LoxNativeFunction>,
+    preludes: Vec<String>,
+    capabilities: Capabilities,
+    limits: Limits,
+}
+
+impl Default for InterpreterBuilder {
+    fn default() -> Self {
+        InterpreterBuilder::new()
+    }
+}
+
+impl InterpreterBuilder {
+    pub fn new() -> InterpreterBuilder {
+        InterpreterBuilder {
+            default_natives: true,
+            default_prelude: true,
+            natives: Vec::new(),
+            preludes: Vec::new(),
+            capabilities: Capabilities::all(),
+            limits: Limits::default(),
+        }
+    }
+
+    /// Leaves out the natives of `natives::default_natives`, pick some of them back with
+    /// `with_natives` (e.g. everything but the file access)
+    pub fn without_default_natives(mut self) -> InterpreterBuilder {
+        self.default_natives = false;
+        self
+    }
+
+    /// Leaves out the functions of `prelude.lox`
+    pub fn without_default_prelude(mut self) -> InterpreterBuilder {
+        self.default_prelude = false;
+        self
+    }
+
+    /// Natives defined after the default ones, replacing those with the same name
+    pub fn with_natives(
+        mut self,
+        natives: impl IntoIterator<Item = LoxNativeFunction>,
+    ) -> InterpreterBuilder {
+        self.natives.extend(natives);
+        self
+    }
+
+    /// Only grants these capabilities: the default natives needing another one are left out,
+    /// any other native needing one raises a runtime error when called
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> InterpreterBuilder {
+        self.capabilities = capabilities;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> InterpreterBuilder {
+        self.limits = limits;
+        self
+    }
+
+    /// Lox source run into the globals after the default prelude, preludes run in the order
+    /// they were added and see the natives
+    pub fn with_prelude(mut self, source: &str) -> InterpreterBuilder {
+        self.preludes.push(source.to_string());
+        self
+    }
+
+    /// Fails when a prelude doesn't compile or raises a runtime error, already reported
+    pub fn build(self) -> Result<Interpreter, RunError> {
+        let mut interpreter = Interpreter::empty();
+        let granted = |native: &LoxNativeFunction| {
+            native
+                .capability
+                .is_none_or(|capability| self.capabilities.allows(capability))
+        };
+        if self.default_natives {
+            for native in natives::default_natives().into_iter().filter(granted) {
+                interpreter
+                    .globals
+                    .borrow_mut()
+                    .define(native.name.clone(), Object::NativeFunction(native));
+            }
+        }
+        interpreter.capabilities = self.capabilities;
+        interpreter.limits = self.limits;
+        for native in self.natives {
+            interpreter
+                .globals
+                .borrow_mut()
+                .define(native.name.clone(), Object::NativeFunction(native));
+        }
+        if self.default_prelude {
+            crate::run(PRELUDE.to_string(), &mut interpreter, false)?;
+            interpreter.prelude = true;
+        }
+        for prelude in self.preludes {
+            crate::run(prelude, &mut interpreter, false)?;
+        }
+        Ok(interpreter)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Interpreter {
     environment: EnvironmentRef,
     pub globals: EnvironmentRef,
     pub locals: HashMap<Expr, usize>,
+    /// Slots in `globals` of the globals read or assigned by `Variable` and `Assign` nodes,
+    /// by the uid of the node, filled the first time the node runs
+    global_slots: HashMap<usize, usize>,
+    /// Emptied argument vectors of finished calls, filled again by the next calls
+    argument_buffers: Vec<Vec<Object>>,
+    /// Extra command line arguments given after the script path
+    pub script_args: Vec<String>,
+    pub output: Output,
+    pub input: Input,
+    pub files: FileSystem,
+    /// Profiler, coverage, tracer, debugger or an embedder's own instrumentation
+    hooks: HookList,
+    /// Set while a `debugger;` pause evaluates code that was not resolved, variables
+    /// are then looked up by name through the enclosing scopes
+    pub debugging: bool,
+    /// Set once the script called `exit(code)`, nothing else runs afterwards
+    pub exit_code: Option<i32>,
+    /// State of `random()` and `randomInt()`, `randomSeed()` resets it for reproducible runs
+    pub random: Random,
+    pub clock: Clock,
+    pub division_by_zero: DivisionByZero,
+    /// Conditions must be booleans and `+` doesn't turn numbers into strings (`--strict`)
+    pub strict: bool,
+    pub warnings: WarningLevel,
+    /// Set while `reload` runs a changed script, global variables that already exist
+    /// keep their value instead of running their initializer again
+    pub keep_globals: bool,
+    /// Outcomes of the `test("name", fn)` calls, in the order they ran
+    pub tests: Vec<TestOutcome>,
+    /// Expected and actual values of the last failed `expectEq`, for the test running it
+    pub mismatch: Option<(String, String)>,
+    /// Whether the functions of `prelude.lox` were defined
+    pub prelude: bool,
+    /// Natives needing a capability that is not granted raise a runtime error (`--allow`)
+    pub capabilities: Capabilities,
+    pub limits: Limits,
+    /// When the current run passes `limits.max_run_time`
+    deadline: Option<Instant>,
+    /// Bytes printed by the current run, against `limits.max_output_length`
+    printed: usize,
+    /// Calls of Lox functions running, against `limits.max_call_depth`
+    call_depth: usize,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
 }
 
 impl Interpreter {
+    /// Interpreter with the natives and the Lox functions of `prelude.lox` defined
     pub fn new() -> Interpreter {
+        let mut interpreter = Interpreter::without_prelude();
+        // The prelude ships with the binary, the tests make sure it runs without errors
+        if crate::run(PRELUDE.to_string(), &mut interpreter, false).is_ok() {
+            interpreter.prelude = true;
+        }
+        interpreter
+    }
+
+    /// Interpreter with only the natives defined (`--no-prelude`)
+    pub fn without_prelude() -> Interpreter {
+        let interpreter = Interpreter::empty();
+        natives::define_natives(&interpreter.globals);
+        interpreter
+    }
+
+    /// Interpreter without a single global, `InterpreterBuilder` fills them in
+    fn empty() -> Interpreter {
         let globals = Environment::new();
-        globals.borrow_mut().define(
-            "clock".to_string(),
-            Object::NativeFunction(LoxNativeFunction {
-                name: "clock".to_string(),
-                arity: 0,
-                callable: |_, _| match SystemTime::now().duration_since(UNIX_EPOCH) {
-                    Ok(timestamp) => Ok(Object::Number(timestamp.as_millis() as f64)),
-                    Err(err) => Err(LoxErrorResult::system_error(&format!(
-                        "Clock returned an invalid duration: {}",
-                        &err.to_string()
-                    ))),
-                },
-            }),
-        );
         Interpreter {
             environment: globals.clone(),
             globals,
             locals: HashMap::new(),
+            global_slots: HashMap::new(),
+            argument_buffers: Vec::new(),
+            script_args: Vec::new(),
+            output: Output::Console,
+            input: Input::Console,
+            files: FileSystem::Disk,
+            hooks: HookList::default(),
+            debugging: false,
+            exit_code: None,
+            random: Random::default(),
+            clock: Clock::System,
+            division_by_zero: DivisionByZero::default(),
+            strict: false,
+            warnings: WarningLevel::default(),
+            keep_globals: false,
+            tests: Vec::new(),
+            mismatch: None,
+            prelude: false,
+            capabilities: Capabilities::all(),
+            limits: Limits::default(),
+            deadline: None,
+            printed: 0,
+            call_depth: 0,
         }
     }
 
-    pub fn interpret(&mut self, statements: &[Stmt]) {
+    /// Pins the clock and the random numbers so every run of a script prints the same,
+    /// whatever the machine (`--deterministic`)
+    pub fn make_deterministic(&mut self) {
+        self.clock = Clock::Virtual(0.0);
+        self.random = Random::new(DETERMINISTIC_SEED);
+    }
+
+    /// Keeps everything printed by the scripts in memory instead of writing it to the console
+    pub fn capture_output(&mut self) -> Rc<RefCell<CapturedOutput>> {
+        let captured = Rc::new(RefCell::new(CapturedOutput::default()));
+        self.output = Output::Captured(Rc::clone(&captured));
+        captured
+    }
+
+    /// Defines a global native function, replacing any previous global with the same name
+    pub fn define_native(&mut self, name: &str, arity: usize, callable: impl NativeFn + 'static) {
+        self.globals.borrow_mut().define(
+            name.to_string(),
+            Object::NativeFunction(LoxNativeFunction::new(name, arity, callable)),
+        );
+    }
+
+    /// Defines a global namespace holding the natives, scripts call them as `fs.read(path)`.
+    /// Registering a module again adds the natives to it, a global of another kind with
+    /// the same name is an error. Scripts cannot declare or assign a global with the name.
+    pub fn register_native_module(
+        &mut self,
+        name: &str,
+        natives: impl IntoIterator<Item = LoxNativeFunction>,
+    ) -> Result<(), String> {
+        let mut globals = self.globals.borrow_mut();
+        let module = match globals.slot(name).map(|slot| globals.get_slot(slot)) {
+            None => LoxModule::new(name, natives),
+            Some(Object::Module(module)) => {
+                let mut module = LoxModule::clone(&module);
+                module.extend(natives);
+                module
+            }
+            Some(value) => {
+                return Err(format!(
+                    "Cannot register native module '{name}', the global is already {value}."
+                ))
+            }
+        };
+        globals.define(name.to_string(), Object::Module(Rc::new(module)));
+        Ok(())
+    }
+
+    /// Whether the global `name` is a module of `register_native_module`
+    pub fn is_native_module(&self, name: &str) -> bool {
+        let globals = self.globals.borrow();
+        globals
+            .slot(name)
+            .is_some_and(|slot| matches!(globals.get_slot(slot), Object::Module(_)))
+    }
+
+    /// Calls a function, native or class with already evaluated arguments.
+    /// Natives use it to call back into Lox, errors are reported at `paren`.
+    pub fn call_value(
+        &mut self,
+        callee: Object,
+        arguments: Vec<Object>,
+        paren: &Token,
+    ) -> Result<Object, LoxErrorResult> {
+        match callee {
+            Object::Function(mut function) => {
+                function.check_arity(arguments.len(), paren)?;
+                function.call(self, arguments, paren)
+            }
+            Object::NativeFunction(mut native_function) => {
+                native_function.check_arity(arguments.len(), paren)?;
+                native_function.call(self, arguments, paren)
+            }
+            Object::Class(mut class) => {
+                class.check_arity(arguments.len(), paren)?;
+                class.call(self, arguments, paren)
+            }
+            _ => Err(LoxErrorResult::interpreter_error(
+                paren.line,
+                I0306.with([]),
+            )),
+        }
+    }
+
+    /// `return f(...);` hands `f` to the returning function instead of calling it, so
+    /// accumulator style recursion does not grow the host stack
+    fn tail_call(&mut self, expr: &CallExpr) -> Result<(), LoxErrorResult> {
+        let callee = self.evaluate(&expr.callee)?;
+        let arguments = self.evaluate_arguments(&expr.arguments)?;
+        self.tail_call_value(callee, arguments, &expr.paren)
+    }
+
+    /// Values of the arguments of a call, from left to right
+    fn evaluate_arguments(&mut self, arguments: &[Expr]) -> Result<Vec<Object>, LoxErrorResult> {
+        let mut values = self.argument_buffer();
+        for argument in arguments {
+            match self.evaluate(argument) {
+                Ok(value) => values.push(value),
+                Err(err) => {
+                    self.recycle_arguments(values);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    /// Empty vector for the arguments of a call, reusing one a finished call gave back
+    pub fn argument_buffer(&mut self) -> Vec<Object> {
+        self.argument_buffers.pop().unwrap_or_default()
+    }
+
+    /// Gives the vector of a call's arguments back once they were moved out of it
+    pub fn recycle_arguments(&mut self, mut arguments: Vec<Object>) {
+        // As many as calls are nested at once, a deep recursion would keep them all
+        if self.argument_buffers.len() < MAX_ARGUMENT_BUFFERS {
+            arguments.clear();
+            self.argument_buffers.push(arguments);
+        }
+    }
+
+    /// Signal of `return callee(arguments);`, only Lox functions are handed over
+    pub fn tail_call_value(
+        &mut self,
+        callee: Object,
+        arguments: Vec<Object>,
+        paren: &Token,
+    ) -> Result<(), LoxErrorResult> {
+        match callee {
+            Object::Function(function) => {
+                function.check_arity(arguments.len(), paren)?;
+                Err(LoxErrorResult::tail_call_signal(function, arguments))
+            }
+            callee => {
+                let value = self.call_value(callee, arguments, paren)?;
+                Err(LoxErrorResult::return_signal(value))
+            }
+        }
+    }
+
+    /// Fails when the string or the list is longer than `limits` allows
+    pub fn check_size(&self, value: &Object, line: usize) -> Result<(), LoxErrorResult> {
+        match value {
+            Object::String(text) => self.check_string_length(text.len(), line),
+            Object::List(elements) => self.check_list_length(elements.borrow().len(), line),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checked before building a string of `length` bytes
+    pub fn check_string_length(&self, length: usize, line: usize) -> Result<(), LoxErrorResult> {
+        match length > self.limits.max_string_length {
+            true => Err(LoxErrorResult::interpreter_error(
+                line,
+                I0324.with([
+                    length.to_string().into(),
+                    self.limits.max_string_length.to_string().into(),
+                ]),
+            )),
+            false => Ok(()),
+        }
+    }
+
+    pub fn check_list_length(&self, length: usize, line: usize) -> Result<(), LoxErrorResult> {
+        match length > self.limits.max_list_length {
+            true => Err(LoxErrorResult::interpreter_error(
+                line,
+                I0325.with([
+                    length.to_string().into(),
+                    self.limits.max_list_length.to_string().into(),
+                ]),
+            )),
+            false => Ok(()),
+        }
+    }
+
+    /// Output of `print`, fails once the run printed more than `limits` allows
+    pub fn print(&mut self, text: &str, line: usize) -> Result<(), LoxErrorResult> {
+        self.printed = self.printed.saturating_add(text.len() + 1);
+        if self.printed > self.limits.max_output_length {
+            return Err(LoxErrorResult::interpreter_error(
+                line,
+                I0333.with([
+                    self.printed.to_string().into(),
+                    self.limits.max_output_length.to_string().into(),
+                ]),
+            ));
+        }
+        self.write_line(text);
+        Ok(())
+    }
+
+    /// Prints a line to the console or appends it to the captured stdout
+    pub fn write_line(&self, text: &str) {
+        match &self.output {
+            Output::Console => println!("{text}"),
+            Output::Captured(captured) => {
+                captured.borrow_mut().stdout.push_str(&format!("{text}\n"))
+            }
+        }
+    }
+
+    /// Prints a line to stderr or appends it to the captured stderr
+    pub fn write_diagnostic(&self, text: &str) {
+        match &self.output {
+            Output::Console => eprintln!("{text}"),
+            Output::Captured(captured) => {
+                captured.borrow_mut().stderr.push_str(&format!("{text}\n"))
+            }
+        }
+    }
+
+    /// Environment where the code is currently being executed
+    pub fn environment(&self) -> EnvironmentRef {
+        Rc::clone(&self.environment)
+    }
+
+    /// Runs the next code in `environment`, returns the previous one to put back afterwards
+    pub fn replace_environment(&mut self, environment: EnvironmentRef) -> EnvironmentRef {
+        std::mem::replace(&mut self.environment, environment)
+    }
+
+    /// Runs the statements, a runtime error is reported and the next statement runs anyway.
+    /// Fails when any of them raised one, `exit(code)` is not an error.
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), RuntimeErrorSummary> {
+        self.start_run();
+        self.interpret_with(statements, Interpreter::execute)
+    }
+
+    /// Calls the script's top-level `fun main()` once its statements ran, unless the script
+    /// uses the name itself. An integer it returns becomes the exit code, like `exit(code)`,
+    /// any other value but `nil` is a runtime error. `main` must not declare parameters.
+    pub fn run_main(&mut self, statements: &[Stmt]) -> Result<(), RuntimeErrorSummary> {
+        let main = statements.iter().find_map(|statement| match statement {
+            Stmt::Function(function) if function.name.lexeme == "main" => Some(function),
+            _ => None,
+        });
+        let Some(main) = main else {
+            return Ok(());
+        };
+        let mut references = MainReferences(false);
+        for statement in statements {
+            references.walk_stmt(statement);
+        }
+        if references.0 {
+            return Ok(());
+        }
+        self.interpret_with(&[main], |interpreter, main| {
+            let name = &main.name;
+            if !main.params.is_empty() {
+                return Err(LoxErrorResult::interpreter_error(
+                    name.line,
+                    I0334.with([main.params.len().to_string().into()]),
+                ));
+            }
+            let function = interpreter.globals.borrow().get(name)?;
+            let value = interpreter.call_value(function, Vec::new(), name)?;
+            match (&value, value.as_integer()) {
+                (Object::Nil, _) => Ok(()),
+                (_, Some(code)) if (0..=255).contains(&code) => {
+                    Err(LoxErrorResult::exit_signal(code as i32))
+                }
+                _ => Err(LoxErrorResult::interpreter_error(
+                    name.line,
+                    I0329.with([value.to_string().into()]),
+                )),
+            }
+        })
+    }
+
+    /// Runs the statements compiled by `ClosureCompiler` like `interpret` runs the tree
+    pub fn interpret_compiled(
+        &mut self,
+        statements: &CompiledBlock,
+    ) -> Result<(), RuntimeErrorSummary> {
+        self.start_run();
+        self.interpret_with(statements, |interpreter, statement| statement(interpreter))
+    }
+
+    /// Starts the clock and the output count of `limits` over, `run_main` keeps them
+    fn start_run(&mut self) {
+        self.deadline = self.limits.max_run_time.map(|time| Instant::now() + time);
+        self.printed = 0;
+    }
+
+    fn interpret_with<T>(
+        &mut self,
+        statements: &[T],
+        execute: impl Fn(&mut Interpreter, &T) -> Result<(), LoxErrorResult>,
+    ) -> Result<(), RuntimeErrorSummary> {
+        let mut summary: Option<RuntimeErrorSummary> = None;
         for statement in statements {
-            match self.execute(statement) {
+            if self.exit_code.is_some() {
+                break;
+            }
+            match execute(self, statement) {
                 Ok(_) => (),
-                Err(err) => err.report(),
+                Err(LoxErrorResult::ControlFlowExit { code }) => self.exit_code = Some(code),
+                Err(err) => {
+                    self.run_hooks(|hooks, interpreter| hooks.on_error(interpreter, &err));
+                    match &self.output {
+                        Output::Console => err.report(),
+                        Output::Captured(_) => self.write_diagnostic(&err.to_string()),
+                    }
+                    let count = summary.as_ref().map_or(0, |summary| summary.count);
+                    summary = Some(RuntimeErrorSummary {
+                        count: count + 1,
+                        last: err,
+                    });
+                }
             }
         }
+        summary.map_or(Ok(()), Err)
+    }
+
+    /// Registers instrumentation, e.g. `interpreter.add_hooks(Rc::new(RefCell::new(Profiler::new())))`.
+    /// Keep a clone of the `Rc` to read what the hooks collected after the run.
+    pub fn add_hooks(&mut self, hooks: HooksRef) {
+        self.hooks.push(hooks);
+    }
+
+    /// Hooks registered so far, e.g. to carry them over to a new interpreter
+    pub fn hooks(&self) -> HookList {
+        self.hooks.clone()
+    }
+
+    /// Replaces the registered hooks, e.g. with those of the interpreter this one replaces
+    pub fn set_hooks(&mut self, hooks: HookList) {
+        self.hooks = hooks;
+    }
+
+    fn run_hooks(&mut self, mut run: impl FnMut(&mut dyn InterpreterHooks, &mut Interpreter)) {
+        if self.hooks.is_empty() {
+            return;
+        }
+        // Hooks may run code (the debugger does), which must not call them again
+        let hooks = std::mem::take(&mut self.hooks);
+        for hook in hooks.iter() {
+            run(&mut *hook.borrow_mut(), self);
+        }
+        let added = std::mem::replace(&mut self.hooks, hooks);
+        self.hooks.extend(added);
+    }
+
+    pub fn function_enter(&mut self, name: &str) {
+        self.run_hooks(|hooks, interpreter| hooks.on_function_enter(interpreter, name));
+    }
+
+    /// Counts a call of a Lox function, fails when `limits.max_call_depth` calls already run
+    pub fn enter_call(&mut self, line: usize) -> Result<(), LoxErrorResult> {
+        if self.call_depth >= self.limits.max_call_depth {
+            return Err(LoxErrorResult::interpreter_error(
+                line,
+                I0335.with([self.limits.max_call_depth.to_string().into()]),
+            ));
+        }
+        self.call_depth += 1;
+        Ok(())
+    }
+
+    /// Ends a call counted by `enter_call`
+    pub fn exit_call(&mut self) {
+        self.call_depth = self.call_depth.saturating_sub(1);
+    }
+
+    pub fn function_exit(&mut self, name: &str) {
+        self.run_hooks(|hooks, interpreter| hooks.on_function_exit(interpreter, name));
+    }
+
+    pub fn variable_written(&mut self, name: &Token, value: &Object) {
+        self.run_hooks(|hooks, interpreter| hooks.on_variable_write(interpreter, name, value));
     }
 
-    fn execute(&mut self, stmt: &Stmt) -> Result<(), LoxErrorResult> {
+    pub fn execute(&mut self, stmt: &Stmt) -> Result<(), LoxErrorResult> {
+        self.statement_hooks(stmt)?;
         stmt.accept(self)
     }
 
+    /// Tells the hooks that `stmt` is about to run
+    pub fn statement_hooks(&mut self, stmt: &Stmt) -> Result<(), LoxErrorResult> {
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            let limit = self.limits.max_run_time.unwrap_or_default().as_millis();
+            return Err(LoxErrorResult::interpreter_error(
+                stmt.span().line,
+                I0332.with([limit.to_string().into()]),
+            ));
+        }
+        if !self.hooks.is_empty() {
+            self.run_hooks(|hooks, interpreter| hooks.on_statement(interpreter, stmt));
+            // `exit()` typed at a debugger pause ends the script too
+            if let Some(code) = self.exit_code {
+                return Err(LoxErrorResult::exit_signal(code));
+            }
+        }
+        Ok(())
+    }
+
     pub fn resolve(&mut self, expression: &Expr, depth: usize) {
         self.locals.insert(expression.clone(), depth);
     }
@@ -74,11 +870,11 @@ impl Interpreter {
         result
     }
 
-    fn evaluate(&mut self, expr: &Expr) -> Result<Object, LoxErrorResult> {
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Object, LoxErrorResult> {
         expr.accept(self)
     }
 
-    fn is_truthy(&mut self, value: Object) -> bool {
+    pub fn is_truthy(&mut self, value: Object) -> bool {
         match value {
             Object::Nil => false,
             Object::Bool(val) => val,
@@ -86,28 +882,388 @@ impl Interpreter {
         }
     }
 
-    fn look_up_variable(&mut self, name: &Token, expr: &Expr) -> Result<Object, LoxErrorResult> {
-        if let Some(distance) = self.locals.get(expr) {
-            self.environment.borrow().get_at(*distance, name)
+    /// Truthiness of a condition, in strict mode only a boolean is accepted
+    pub fn is_condition_true(
+        &mut self,
+        value: Object,
+        line: usize,
+        construct: &str,
+    ) -> Result<bool, LoxErrorResult> {
+        match (self.strict, &value) {
+            (true, Object::Bool(_)) | (false, _) => Ok(self.is_truthy(value)),
+            (true, _) => Err(LoxErrorResult::interpreter_error(
+                line,
+                I0319.with([construct.into(), value.to_string().into()]),
+            )),
+        }
+    }
+
+    fn look_up_variable(
+        &mut self,
+        name: &Token,
+        expr: &Expr,
+        uid: usize,
+    ) -> Result<Object, LoxErrorResult> {
+        match self.locals.get(expr).copied() {
+            None if !self.debugging => {
+                let slot = self.cached_global_slot(name, uid)?;
+                Ok(self.read_global(slot))
+            }
+            distance => self.variable_at(name, distance),
+        }
+    }
+
+    /// Slot of the global `name` in `globals`, `cached` once the node using it found it.
+    /// Fails while the global is not defined.
+    pub fn global_slot(
+        &self,
+        name: &Token,
+        cached: Option<usize>,
+    ) -> Result<usize, LoxErrorResult> {
+        match cached {
+            Some(slot) => Ok(slot),
+            None => (self.globals.borrow().slot(&name.lexeme))
+                .ok_or_else(|| Environment::undefined(name)),
+        }
+    }
+
+    fn cached_global_slot(&mut self, name: &Token, uid: usize) -> Result<usize, LoxErrorResult> {
+        if let Some(&slot) = self.global_slots.get(&uid) {
+            return Ok(slot);
+        }
+        let slot = self.global_slot(name, None)?;
+        self.global_slots.insert(uid, slot);
+        Ok(slot)
+    }
+
+    pub fn read_global(&self, slot: usize) -> Object {
+        self.globals.borrow().get_slot(slot)
+    }
+
+    /// Assigns the global in `slot`, returns the value
+    pub fn write_global(&mut self, name: &Token, slot: usize, value: Object) -> Object {
+        self.globals.borrow_mut().set_slot(slot, value.clone());
+        self.variable_written(name, &value);
+        value
+    }
+
+    /// Value of the variable `distance` scopes away, a global without a distance
+    pub fn variable_at(
+        &self,
+        name: &Token,
+        distance: Option<usize>,
+    ) -> Result<Object, LoxErrorResult> {
+        if let Some(distance) = distance {
+            self.environment.borrow().get_at(distance, name)
+        } else if self.debugging {
+            self.environment.borrow().get(name)
         } else {
             self.globals.borrow().get(name)
         }
     }
 
+    fn execute_for_loop(&mut self, stmt: &ForStmt) -> Result<(), LoxErrorResult> {
+        if let Some(initializer) = &stmt.initializer {
+            self.execute(initializer)?;
+        }
+        loop {
+            if let Some(condition) = &stmt.condition {
+                let value = self.evaluate(condition)?;
+                if !self.is_condition_true(value, stmt.keyword.line, "for")? {
+                    break;
+                }
+            }
+            match self.execute(&stmt.body) {
+                Err(err) if err.breaks(stmt.label.as_ref()) => break,
+                // `continue` still runs the increment
+                Err(err) if !err.continues(stmt.label.as_ref()) => return Err(err),
+                _ => {}
+            }
+            if let Some(increment) = &stmt.increment {
+                self.evaluate(increment)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The superclass has to be a class when the declaration runs, it is reported
+    /// on its own line since it can be any variable
     fn evaluate_super_class(
         &mut self,
         super_class_expr: &Expr,
-        name: &Token,
     ) -> Result<LoxClass, LoxErrorResult> {
         let variable_expr = self.evaluate(super_class_expr)?;
         match variable_expr {
             Object::Class(lox_class) => Ok(lox_class),
+            value => Err(LoxErrorResult::interpreter_error(
+                super_class_expr.span().line,
+                I0312.with([value.to_string().into()]),
+            )),
+        }
+    }
+
+    /// Applies a binary operator to the values of its operands
+    pub fn binary(
+        &mut self,
+        operator: &Token,
+        left: Object,
+        right: Object,
+    ) -> Result<Object, LoxErrorResult> {
+        match operator.token_type {
+            TokenType::Minus => match left - right {
+                Ok(result) => Ok(result),
+                Err(message) => Err(LoxErrorResult::interpreter_error(operator.line, message)),
+            },
+            TokenType::Slash if self.division_by_zero == DivisionByZero::Error => {
+                match (left.as_number(), right.as_number()) {
+                    (Some(dividend), Some(0.0)) => Err(LoxErrorResult::interpreter_error(
+                        operator.line,
+                        match dividend == 0.0 {
+                            true => I0314.with([]),
+                            false => I0313.with([]),
+                        },
+                    )),
+                    _ => (left / right).map_err(|message| {
+                        LoxErrorResult::interpreter_error(operator.line, message)
+                    }),
+                }
+            }
+            TokenType::Slash => match left / right {
+                Ok(result) => Ok(result),
+                Err(message) => Err(LoxErrorResult::interpreter_error(operator.line, message)),
+            },
+            TokenType::Star => {
+                // A repeated string is checked before it is allocated
+                if let (Object::String(text), count) | (count, Object::String(text)) =
+                    (&left, &right)
+                {
+                    let count = count
+                        .as_integer()
+                        .and_then(|count| usize::try_from(count).ok());
+                    if let Some(count) = count {
+                        let length = text.len().saturating_mul(count);
+                        self.check_string_length(length, operator.line)?;
+                    }
+                }
+                (left * right)
+                    .map_err(|message| LoxErrorResult::interpreter_error(operator.line, message))
+            }
+            TokenType::Plus
+                if self.strict
+                    && matches!(
+                        (&left, &right),
+                        (Object::String(_), right) | (right, Object::String(_))
+                            if right.as_number().is_some()
+                    ) =>
+            {
+                Err(LoxErrorResult::interpreter_error(
+                    operator.line,
+                    I0318.with([]),
+                ))
+            }
+            TokenType::Plus => match left + right {
+                Ok(result) => {
+                    self.check_size(&result, operator.line)?;
+                    Ok(result)
+                }
+                Err(message) => Err(LoxErrorResult::interpreter_error(operator.line, message)),
+            },
+            TokenType::Greater => match (left, right) {
+                (left, right) if left.as_number().is_some() && right.as_number().is_some() => {
+                    Ok(Object::Bool(left > right))
+                }
+                (Object::String(left), Object::String(right)) => Ok(Object::Bool(left > right)),
+                _ => Err(LoxErrorResult::interpreter_error(
+                    operator.line,
+                    I0303.with([">".into()]),
+                )),
+            },
+            TokenType::GreaterEqual => match (left, right) {
+                (left, right) if left.as_number().is_some() && right.as_number().is_some() => {
+                    Ok(Object::Bool(left >= right))
+                }
+                (Object::String(left), Object::String(right)) => Ok(Object::Bool(left >= right)),
+                _ => Err(LoxErrorResult::interpreter_error(
+                    operator.line,
+                    I0303.with([">=".into()]),
+                )),
+            },
+            TokenType::Less => match (left, right) {
+                (left, right) if left.as_number().is_some() && right.as_number().is_some() => {
+                    Ok(Object::Bool(left < right))
+                }
+                (Object::String(left), Object::String(right)) => Ok(Object::Bool(left < right)),
+                _ => Err(LoxErrorResult::interpreter_error(
+                    operator.line,
+                    I0303.with(["<".into()]),
+                )),
+            },
+            TokenType::LessEqual => match (left, right) {
+                (left, right) if left.as_number().is_some() && right.as_number().is_some() => {
+                    Ok(Object::Bool(left <= right))
+                }
+                (Object::String(left), Object::String(right)) => Ok(Object::Bool(left <= right)),
+                _ => Err(LoxErrorResult::interpreter_error(
+                    operator.line,
+                    I0303.with(["<=".into()]),
+                )),
+            },
+            TokenType::BangEqual => Ok(Object::Bool(left != right)),
+            TokenType::EqualEqual => Ok(Object::Bool(left == right)),
             _ => Err(LoxErrorResult::interpreter_error(
-                name.line,
-                "Superclass must be a class.",
+                operator.line,
+                I0330.with([]),
+            )),
+        }
+    }
+
+    /// Applies a unary operator to the value of its operand
+    pub fn unary(&mut self, operator: &Token, right: Object) -> Result<Object, LoxErrorResult> {
+        match operator.token_type {
+            TokenType::Bang => Ok(Object::Bool(!self.is_truthy(right))),
+            TokenType::Minus => match right {
+                Object::Number(val) => Ok(Object::Number(-val)),
+                #[cfg(not(feature = "bigint"))]
+                Object::Int(val) => Ok(val
+                    .checked_neg()
+                    .map_or(Object::Number(-(val as f64)), Object::Int)),
+                // `-i64::MIN` overflows into a `BigInt`
+                #[cfg(feature = "bigint")]
+                Object::Int(val) => Ok(val
+                    .checked_neg()
+                    .map_or_else(|| Object::BigInt(Rc::new(-&BigInt::from(val))), Object::Int)),
+                #[cfg(feature = "bigint")]
+                Object::BigInt(val) => Ok(Object::BigInt(Rc::new(-&*val))),
+                _ => Err(LoxErrorResult::interpreter_error(
+                    operator.line,
+                    I0301.with([]),
+                )),
+            },
+            _ => Err(LoxErrorResult::interpreter_error(
+                operator.line,
+                I0331.with([]),
             )),
         }
     }
+
+    /// Property `name` of an instance
+    pub fn get_property(&self, object: Object, name: &Token) -> Result<Object, LoxErrorResult> {
+        match object {
+            Object::ClassInstance(instance) => instance.borrow().get(name, instance.clone()),
+            Object::Module(module) => module.get(name),
+            _ => Err(LoxErrorResult::interpreter_error(name.line, I0307.with([]))),
+        }
+    }
+
+    /// The instance a field `name` is set on, checked before the value is evaluated
+    pub fn fields_of(object: Object, name: &Token) -> Result<LoxInstanceRef, LoxErrorResult> {
+        match object {
+            Object::ClassInstance(instance) => Ok(instance),
+            _ => Err(LoxErrorResult::interpreter_error(name.line, I0308.with([]))),
+        }
+    }
+
+    pub fn set_field(
+        &self,
+        instance: &LoxInstanceRef,
+        name: &Token,
+        value: Object,
+    ) -> Result<Object, LoxErrorResult> {
+        let mut instance = instance.borrow_mut();
+        if !instance.has_field(&name.lexeme) && instance.field_count() >= self.limits.max_fields {
+            return Err(LoxErrorResult::interpreter_error(
+                name.line,
+                I0326.with([self.limits.max_fields.to_string().into()]),
+            ));
+        }
+        instance.set(name, value.clone());
+        Ok(value)
+    }
+
+    /// Whether a `var` at the top level leaves an existing global alone, see `keep_globals`
+    pub fn keeps_global(&self, name: &Token) -> bool {
+        let is_global = Rc::ptr_eq(&self.environment, &self.globals);
+        self.keep_globals && is_global && self.globals.borrow().is_defined(&name.lexeme)
+    }
+
+    /// Defines a variable in the current scope
+    pub fn define_variable(&mut self, name: &Token, value: Object) {
+        self.variable_written(name, &value);
+        self.environment.borrow_mut().define(name.lexeme(), value);
+    }
+
+    /// Assigns the variable `distance` scopes away, a global without a distance
+    pub fn assign_variable(
+        &mut self,
+        name: &Token,
+        distance: Option<usize>,
+        value: Object,
+    ) -> Result<Object, LoxErrorResult> {
+        if let Some(distance) = distance {
+            self.environment
+                .borrow_mut()
+                .assign_at(distance, name, &value);
+        } else if self.debugging {
+            self.environment.borrow_mut().assign(name, value.clone())?;
+        } else {
+            self.globals.borrow_mut().assign(name, value.clone())?;
+        }
+        self.variable_written(name, &value);
+        Ok(value)
+    }
+
+    /// Runs a class declaration, `make_method` turns every method into a function
+    /// closing over the scope of the class
+    pub fn declare_class(
+        &mut self,
+        stmt: &ClassStmt,
+        make_method: impl Fn(&FunctionStmt, EnvironmentRef, bool) -> LoxFunction,
+    ) -> Result<(), LoxErrorResult> {
+        let super_class: Option<Box<LoxClass>> = match stmt.super_class.clone() {
+            Some(expr) => Some(Box::new(self.evaluate_super_class(&expr)?)),
+            None => None,
+        };
+
+        self.environment
+            .borrow_mut()
+            .define(stmt.name.lexeme(), Object::Nil);
+
+        if let Some(class) = super_class.clone() {
+            self.environment = Environment::new_enclosing(self.environment.clone());
+            self.environment
+                .borrow_mut()
+                .define("super".to_string(), Object::Class(*class));
+        }
+
+        let mut methods: HashMap<String, LoxFunction> = HashMap::new();
+
+        for stmt in &stmt.methods {
+            match stmt {
+                Stmt::Function(method) => {
+                    let function = make_method(
+                        method,
+                        self.environment.clone(),
+                        method.name.lexeme.eq("init"),
+                    );
+                    methods.insert(method.name.lexeme(), function);
+                }
+                _ => panic!("Not a method!"),
+            }
+        }
+
+        let class = LoxClass::new(stmt.name.lexeme(), super_class.clone(), methods);
+
+        if super_class.is_some() {
+            if let Some(previous_environment) = &self.environment.clone().borrow().enclosing {
+                self.environment = previous_environment.clone();
+            }
+        }
+
+        self.environment
+            .borrow_mut()
+            .assign(&stmt.name, Object::Class(class))?;
+        Ok(())
+    }
 }
 
 impl StmtVisitor<Result<(), LoxErrorResult>> for Interpreter {
@@ -118,20 +1274,19 @@ impl StmtVisitor<Result<(), LoxErrorResult>> for Interpreter {
 
     fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> Result<(), LoxErrorResult> {
         let value = self.evaluate(&stmt.expression)?;
-        println!("{value}");
-        Ok(())
+        self.print(&value.to_string(), stmt.keyword.line)
     }
 
     fn visit_var_stmt(&mut self, stmt: &VarStmt) -> Result<(), LoxErrorResult> {
+        if self.keeps_global(&stmt.name) {
+            return Ok(());
+        }
         let initializer = if let Some(init_value) = &stmt.initializer {
             self.evaluate(init_value)?
         } else {
             Object::Nil
         };
-
-        self.environment
-            .borrow_mut()
-            .define(stmt.name.lexeme(), initializer);
+        self.define_variable(&stmt.name, initializer);
         Ok(())
     }
 
@@ -142,7 +1297,7 @@ impl StmtVisitor<Result<(), LoxErrorResult>> for Interpreter {
 
     fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Result<(), LoxErrorResult> {
         let condition = self.evaluate(&stmt.condition)?;
-        if self.is_truthy(condition) {
+        if self.is_condition_true(condition, stmt.keyword.line, "if")? {
             self.execute(&stmt.then_branch)
         } else if let Some(else_branch) = &stmt.else_branch {
             self.execute(else_branch)
@@ -155,162 +1310,99 @@ impl StmtVisitor<Result<(), LoxErrorResult>> for Interpreter {
         loop {
             let condition_is_truthy = {
                 let condition = self.evaluate(&stmt.condition)?;
-                self.is_truthy(condition)
+                self.is_condition_true(condition, stmt.keyword.line, "while")?
             };
             // Break the loop when the condition is false
             if !condition_is_truthy {
                 break;
             }
             // Execute the body of the loop
-            // If there is an error or break statement it does an exit
-            if let Err(err) = self.execute(&stmt.body) {
-                if err.is_control_break() {
-                    break;
-                }
-                return Err(err);
-            };
+            // If there is an error or a break statement for this loop it does an exit
+            match self.execute(&stmt.body) {
+                Err(err) if err.breaks(stmt.label.as_ref()) => break,
+                Err(err) if !err.continues(stmt.label.as_ref()) => return Err(err),
+                _ => {}
+            }
         }
         Ok(())
     }
 
-    fn visit_break_stmt(&mut self, _stmt: &BreakStmt) -> Result<(), LoxErrorResult> {
-        Err(LoxErrorResult::break_signal())
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) -> Result<(), LoxErrorResult> {
+        // The initializer lives in its own scope, as in `{ var i = 0; while (...) {...} }`
+        let previous_env = Rc::clone(&self.environment);
+        self.environment = Environment::new_enclosing(Rc::clone(&previous_env));
+        let result = self.execute_for_loop(stmt);
+        self.environment = previous_env;
+        result
     }
 
-    fn visit_function_stmt(&mut self, stmt: &FunctionStmt) -> Result<(), LoxErrorResult> {
-        let function = LoxFunction::new(stmt, Rc::clone(&self.environment), false);
-        self.environment
-            .borrow_mut()
-            .define(stmt.name.lexeme(), Object::Function(function));
+    fn visit_for_in_stmt(&mut self, stmt: &ForInStmt) -> Result<(), LoxErrorResult> {
+        let iterable = self.evaluate(&stmt.iterable)?;
+        let mut values = LoxIterator::new(iterable, stmt.keyword.line)?;
+        while let Some(value) = values.next(self, stmt.keyword.line)? {
+            // Every iteration has its own variable, closures made in the body keep theirs
+            self.variable_written(&stmt.name, &value);
+            let environment = Environment::new_enclosing(Rc::clone(&self.environment));
+            environment
+                .borrow_mut()
+                .define(stmt.name.lexeme.clone(), value);
+            match self.execute_block(std::slice::from_ref(&stmt.body), environment) {
+                Err(err) if err.breaks(stmt.label.as_ref()) => break,
+                Err(err) if !err.continues(stmt.label.as_ref()) => return Err(err),
+                _ => {}
+            }
+        }
         Ok(())
     }
 
-    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Result<(), LoxErrorResult> {
-        let return_value = if let Some(value) = &stmt.value {
-            self.evaluate(value)?
-        } else {
-            Object::Nil
-        };
-        Err(LoxErrorResult::return_signal(return_value))
+    fn visit_break_stmt(&mut self, stmt: &BreakStmt) -> Result<(), LoxErrorResult> {
+        Err(LoxErrorResult::break_signal(stmt.label.as_ref()))
     }
 
-    fn visit_class_stmt(&mut self, stmt: &ClassStmt) -> Result<(), LoxErrorResult> {
-        let super_class: Option<Box<LoxClass>> = match stmt.super_class.clone() {
-            Some(expr) => Some(Box::new(self.evaluate_super_class(&expr, &stmt.name)?)),
-            None => None,
-        };
-
-        self.environment
-            .borrow_mut()
-            .define(stmt.name.lexeme(), Object::Nil);
-
-        if let Some(class) = super_class.clone() {
-            self.environment = Environment::new_enclosing(self.environment.clone());
-            self.environment
-                .borrow_mut()
-                .define("super".to_string(), Object::Class(*class));
-        }
-
-        let mut methods: HashMap<String, LoxFunction> = HashMap::new();
-
-        for stmt in &stmt.methods {
-            match stmt {
-                Stmt::Function(method) => {
-                    let function = LoxFunction::new(
-                        method,
-                        self.environment.clone(),
-                        method.name.lexeme.eq("init"),
-                    );
-                    methods.insert(method.name.lexeme(), function);
-                }
-                _ => panic!("Not a method!"),
-            }
-        }
+    fn visit_continue_stmt(&mut self, stmt: &ContinueStmt) -> Result<(), LoxErrorResult> {
+        Err(LoxErrorResult::continue_signal(stmt.label.as_ref()))
+    }
 
-        let class = LoxClass::new(stmt.name.lexeme(), super_class.clone(), methods);
+    /// Statements with a `yield` run in `LoxGenerator::resume`, the resolver rejects the others
+    fn visit_yield_stmt(&mut self, stmt: &YieldStmt) -> Result<(), LoxErrorResult> {
+        Err(LoxErrorResult::interpreter_error(
+            stmt.keyword.line,
+            I0321.with([]),
+        ))
+    }
 
-        if super_class.is_some() {
-            if let Some(previous_environment) = &self.environment.clone().borrow().enclosing {
-                self.environment = previous_environment.clone();
-            }
-        }
+    /// Pauses happen in the `Debugger` hook, without one the statement does nothing
+    fn visit_debugger_stmt(&mut self, _stmt: &DebuggerStmt) -> Result<(), LoxErrorResult> {
+        Ok(())
+    }
 
+    fn visit_function_stmt(&mut self, stmt: &FunctionStmt) -> Result<(), LoxErrorResult> {
+        let function = LoxFunction::new(stmt, Rc::clone(&self.environment), false);
         self.environment
             .borrow_mut()
-            .assign(&stmt.name, Object::Class(class))?;
+            .define(stmt.name.lexeme(), Object::Function(function));
         Ok(())
     }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Result<(), LoxErrorResult> {
+        let return_value = match stmt.value.as_deref() {
+            Some(Expr::Call(call)) => return self.tail_call(call),
+            Some(value) => self.evaluate(value)?,
+            None => Object::Nil,
+        };
+        Err(LoxErrorResult::return_signal(return_value))
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &ClassStmt) -> Result<(), LoxErrorResult> {
+        self.declare_class(stmt, LoxFunction::new)
+    }
 }
 
 impl ExprVisitor<Result<Object, LoxErrorResult>> for Interpreter {
     fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> Result<Object, LoxErrorResult> {
         let left = self.evaluate(&expr.left)?;
         let right = self.evaluate(&expr.right)?;
-
-        match expr.operator.token_type {
-            TokenType::Minus => match left - right {
-                Ok(result) => Ok(result),
-                Err(message) => Err(LoxErrorResult::interpreter_error(
-                    expr.operator.line,
-                    &message,
-                )),
-            },
-            TokenType::Slash => match left / right {
-                Ok(result) => Ok(result),
-                Err(message) => Err(LoxErrorResult::interpreter_error(
-                    expr.operator.line,
-                    &message,
-                )),
-            },
-            TokenType::Star => match left * right {
-                Ok(result) => Ok(result),
-                Err(message) => Err(LoxErrorResult::interpreter_error(
-                    expr.operator.line,
-                    &message,
-                )),
-            },
-            TokenType::Plus => match left + right {
-                Ok(result) => Ok(result),
-                Err(message) => Err(LoxErrorResult::interpreter_error(
-                    expr.operator.line,
-                    &message,
-                )),
-            },
-            TokenType::Greater => match (left, right) {
-                (Object::Number(left), Object::Number(right)) => Ok(Object::Bool(left > right)),
-                _ => Err(LoxErrorResult::interpreter_error(
-                    expr.operator.line,
-                    "Operands must be numbers for '>' operation.",
-                )),
-            },
-            TokenType::GreaterEqual => match (left, right) {
-                (Object::Number(left), Object::Number(right)) => Ok(Object::Bool(left >= right)),
-                _ => Err(LoxErrorResult::interpreter_error(
-                    expr.operator.line,
-                    "Operands must be numbers for '>=' operation.",
-                )),
-            },
-            TokenType::Less => match (left, right) {
-                (Object::Number(left), Object::Number(right)) => Ok(Object::Bool(left < right)),
-                _ => Err(LoxErrorResult::interpreter_error(
-                    expr.operator.line,
-                    "Operands must be numbers for '<' operation.",
-                )),
-            },
-            TokenType::LessEqual => match (left, right) {
-                (Object::Number(left), Object::Number(right)) => Ok(Object::Bool(left <= right)),
-                _ => Err(LoxErrorResult::interpreter_error(
-                    expr.operator.line,
-                    "Operands must be numbers for '<=' operation.",
-                )),
-            },
-            TokenType::BangEqual => Ok(Object::Bool(left != right)),
-            TokenType::EqualEqual => Ok(Object::Bool(left == right)),
-            _ => Err(LoxErrorResult::interpreter_error(
-                expr.operator.line,
-                "Unsupported binary operation.",
-            )),
-        }
+        self.binary(&expr.operator, left, right)
     }
 
     fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> Result<Object, LoxErrorResult> {
@@ -323,48 +1415,31 @@ impl ExprVisitor<Result<Object, LoxErrorResult>> for Interpreter {
 
     fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> Result<Object, LoxErrorResult> {
         let right = self.evaluate(&expr.right)?;
-
-        match expr.operator.token_type {
-            TokenType::Bang => Ok(Object::Bool(!self.is_truthy(right))),
-            TokenType::Minus => match right {
-                Object::Number(val) => Ok(Object::Number(-val)),
-                _ => Err(LoxErrorResult::interpreter_error(
-                    expr.operator.line,
-                    "Operand must be a number.",
-                )),
-            },
-            _ => Err(LoxErrorResult::interpreter_error(
-                expr.operator.line,
-                "Unsupported unary operator",
-            )),
-        }
+        self.unary(&expr.operator, right)
     }
 
     fn visit_ternary_expr(&mut self, expr: &TernaryExpr) -> Result<Object, LoxErrorResult> {
         let condition = self.evaluate(&expr.condition)?;
-        match self.is_truthy(condition) {
+        let line = expr.condition.span().line;
+        match self.is_condition_true(condition, line, "?:")? {
             true => self.evaluate(&expr.then_branch),
             false => self.evaluate(&expr.else_branch),
         }
     }
 
     fn visit_variable_expr(&mut self, expr: &VariableExpr) -> Result<Object, LoxErrorResult> {
-        self.look_up_variable(&expr.name, &Expr::Variable(expr.clone()))
+        self.look_up_variable(&expr.name, &Expr::Variable(expr.clone()), expr.uid)
     }
 
     fn visit_assign_expr(&mut self, expr: &AssignExpr) -> Result<Object, LoxErrorResult> {
         let value = self.evaluate(&expr.value)?;
-        let local_value = self.locals.get(&Expr::Assign(expr.clone()));
-        if let Some(distance) = local_value {
-            self.environment
-                .borrow_mut()
-                .assign_at(*distance, &expr.name, &value);
-        } else {
-            self.globals
-                .borrow_mut()
-                .assign(&expr.name, value.clone())?;
+        match self.locals.get(&Expr::Assign(expr.clone())).copied() {
+            None if !self.debugging => {
+                let slot = self.cached_global_slot(&expr.name, expr.uid)?;
+                Ok(self.write_global(&expr.name, slot, value))
+            }
+            distance => self.assign_variable(&expr.name, distance, value),
         }
-        Ok(value)
     }
 
     fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> Result<Object, LoxErrorResult> {
@@ -383,87 +1458,51 @@ impl ExprVisitor<Result<Object, LoxErrorResult>> for Interpreter {
 
     fn visit_call_expr(&mut self, expr: &CallExpr) -> Result<Object, LoxErrorResult> {
         let callee = self.evaluate(&expr.callee)?;
-
-        let mut arguments: Vec<Object> = Vec::new();
-
-        for argument in &expr.arguments {
-            arguments.push(self.evaluate(argument)?);
-        }
-
-        match callee {
-            Object::Function(mut function) => {
-                function.check_arity(arguments.len(), &expr.paren)?;
-                function.call(self, arguments)
-            }
-            Object::NativeFunction(mut native_function) => {
-                native_function.check_arity(arguments.len(), &expr.paren)?;
-                native_function.call(self, arguments)
-            }
-            Object::Class(mut class) => {
-                class.check_arity(arguments.len(), &expr.paren)?;
-                class.call(self, arguments)
-            }
-            _ => Err(LoxErrorResult::interpreter_error(
-                expr.paren.line,
-                "Can only call functions and classes.",
-            )),
-        }
+        let arguments = self.evaluate_arguments(&expr.arguments)?;
+        self.call_value(callee, arguments, &expr.paren)
     }
 
     fn visit_get_expr(&mut self, expr: &GetExpr) -> Result<Object, LoxErrorResult> {
         let object = self.evaluate(&expr.object)?;
-
-        match object {
-            Object::ClassInstance(instance) => {
-                Ok(instance.borrow().get(&expr.name, instance.clone())?)
-            }
-            _ => Err(LoxErrorResult::interpreter_error(
-                expr.name.line,
-                "Only instances have properties.",
-            )),
-        }
+        self.get_property(object, &expr.name)
     }
 
     fn visit_set_expr(&mut self, expr: &SetExpr) -> Result<Object, LoxErrorResult> {
         let object = self.evaluate(&expr.object)?;
-
-        match object {
-            Object::ClassInstance(instance) => {
-                let value = self.evaluate(&expr.value)?;
-                instance.borrow_mut().set(&expr.name, value.clone());
-                Ok(value)
-            }
-            _ => Err(LoxErrorResult::interpreter_error(
-                expr.name.line,
-                "Only instances have fields.",
-            )),
-        }
+        let instance = Interpreter::fields_of(object, &expr.name)?;
+        let value = self.evaluate(&expr.value)?;
+        self.set_field(&instance, &expr.name, value)
     }
 
     fn visit_this_expr(&mut self, expr: &ThisExpr) -> Result<Object, LoxErrorResult> {
-        self.look_up_variable(&expr.keyword, &Expr::This(expr.clone()))
+        self.look_up_variable(&expr.keyword, &Expr::This(expr.clone()), expr.uid)
     }
 
     fn visit_super_expr(&mut self, expr: &SuperExpr) -> Result<Object, LoxErrorResult> {
         let super_token = Token::new(TokenType::Super, "super".to_string(), Object::Nil, 0);
         let this_token = Token::new(TokenType::This, "this".to_string(), Object::Nil, 0);
 
-        let distance = self
-            .locals
-            .get(&Expr::Super(expr.clone()))
-            .expect("Super class has to be resolved");
+        let (super_class, instance) = match self.locals.get(&Expr::Super(expr.clone())) {
+            // the instance is always right inside where the super class is stored
+            Some(distance) => (
+                self.environment.borrow().get_at(*distance, &super_token)?,
+                self.environment
+                    .borrow()
+                    .get_at(*distance - 1, &this_token)?,
+            ),
+            // Unresolved code typed at a `debugger;` pause
+            None => (
+                self.environment.borrow().get(&super_token)?,
+                self.environment.borrow().get(&this_token)?,
+            ),
+        };
 
-        let super_class = match self.environment.borrow().get_at(*distance, &super_token)? {
+        let super_class = match super_class {
             Object::Class(super_class) => super_class,
             _ => panic!("Expected 'object' to be a 'super' class!"),
         };
 
-        // the instance is always right inside where the super class is stored
-        let instance = match self
-            .environment
-            .borrow()
-            .get_at(*distance - 1, &this_token)?
-        {
+        let instance = match instance {
             Object::ClassInstance(instance) => instance,
             _ => panic!("Expected 'object' to be a class instance!"),
         };
@@ -472,39 +1511,45 @@ impl ExprVisitor<Result<Object, LoxErrorResult>> for Interpreter {
             Some(method) => Ok(Object::Function(method.bind(instance))),
             None => Err(LoxErrorResult::interpreter_error(
                 expr.method.line,
-                &format!("Undefined property '{}'.", expr.method.lexeme),
+                I0305.with([expr.method.lexeme().into()]),
             )),
         }
     }
 }
 
+/// Whether the script reads or assigns `main`, then it calls its `main` itself
+struct MainReferences(bool);
+
+impl Walker for MainReferences {
+    fn walk_variable_expr(&mut self, expr: &VariableExpr) {
+        self.0 |= expr.name.lexeme == "main";
+    }
+
+    fn walk_assign_expr(&mut self, expr: &AssignExpr) {
+        self.0 |= expr.name.lexeme == "main";
+        self.walk_expr(&expr.value);
+    }
+}
+
 #[cfg(test)]
 mod interpreter_tests {
     use super::*;
+    use crate::capabilities::Capability;
 
     fn make_literal(obj: Object) -> Box<Expr> {
-        Box::new(Expr::Literal(LiteralExpr { value: obj, uid: 0 }))
+        Box::new(Expr::new_literal(obj))
     }
 
     fn make_literal_number(num: f64) -> Box<Expr> {
-        Box::new(Expr::Literal(LiteralExpr {
-            value: Object::Number(num),
-            uid: 0,
-        }))
+        Box::new(Expr::new_literal(Object::Number(num)))
     }
 
     fn make_literal_string(str_val: &str) -> Box<Expr> {
-        Box::new(Expr::Literal(LiteralExpr {
-            value: Object::String(str_val.to_string()),
-            uid: 0,
-        }))
+        Box::new(Expr::new_literal(Object::String(str_val.into())))
     }
 
     fn make_literal_bool(value: bool) -> Box<Expr> {
-        Box::new(Expr::Literal(LiteralExpr {
-            value: Object::Bool(value),
-            uid: 0,
-        }))
+        Box::new(Expr::new_literal(Object::Bool(value)))
     }
 
     fn make_token_operator(token_type: TokenType, operator: &str) -> Token {
@@ -535,12 +1580,11 @@ mod interpreter_tests {
                 &token.lexeme,
                 operand.1.clone()
             );
-            let binary_expr: BinaryExpr = BinaryExpr {
-                left: make_literal(operand.0.to_owned()),
-                operator: token.to_owned(),
-                right: make_literal(operand.1.to_owned()),
-                uid: 0,
-            };
+            let binary_expr = BinaryExpr::new(
+                make_literal(operand.0.to_owned()),
+                token.to_owned(),
+                make_literal(operand.1.to_owned()),
+            );
             // Act
             let result = interpreter.visit_binary_expr(&binary_expr);
             // Assert
@@ -565,6 +1609,7 @@ mod interpreter_tests {
                     &message_for_ok
                 );
             } else if let Some(LoxErrorResult::Interpreter { line: _, message }) = result.err() {
+                let message = message.to_string();
                 assert!(message.contains(&token.lexeme), "{}", &message_for_err);
                 assert!(message.contains("Operands must be"), "{}", &message_for_err);
             }
@@ -578,33 +1623,49 @@ mod interpreter_tests {
             (Object::Number(3.0), Object::Number(1.0)),
             (Object::Number(3.0), Object::Number(3.0)),
             // Errors
-            (Object::String("4.0".to_string()), Object::Nil),
-            (Object::Nil, Object::String("2.0".to_string())),
-            (Object::Bool(true), Object::String("2.0".to_string())),
+            (Object::String("4.0".into()), Object::Nil),
+            (Object::Nil, Object::String("2.0".into())),
+            (Object::Bool(true), Object::String("2.0".into())),
             (Object::Bool(true), Object::Number(3.0)),
             (Object::Bool(true), Object::Bool(false)),
         ]
     }
 
-    fn get_test_string_operands() -> Vec<(Object, Object)> {
+    fn get_test_comparison_operands() -> Vec<(Object, Object)> {
         // (left, right) values
-        vec![
+        let mut operands = get_test_number_operands();
+        operands.extend(vec![
+            (
+                Object::String("apple".into()),
+                Object::String("banana".into()),
+            ),
             (
-                Object::String("Hi, ".to_string()),
-                Object::String("Rusty".to_string()),
+                Object::String("pear".into()),
+                Object::String("peach".into()),
             ),
+            (Object::String("lox".into()), Object::String("lox".into())),
+            // Errors
+            (Object::String("3.0".into()), Object::Number(3.0)),
+        ]);
+        operands
+    }
+
+    fn get_test_string_operands() -> Vec<(Object, Object)> {
+        // (left, right) values
+        vec![
             (
-                Object::String("To".to_string()),
-                Object::String("gether".to_string()),
+                Object::String("Hi, ".into()),
+                Object::String("Rusty".into()),
             ),
+            (Object::String("To".into()), Object::String("gether".into())),
             (
-                Object::String("Split".to_string()),
-                Object::String(" two".to_string()),
+                Object::String("Split".into()),
+                Object::String(" two".into()),
             ),
-            (Object::String("4.0".to_string()), Object::Number(3.0)),
-            (Object::Number(3.0), Object::String("2.0".to_string())),
+            (Object::String("4.0".into()), Object::Number(3.0)),
+            (Object::Number(3.0), Object::String("2.0".into())),
             // Errors
-            (Object::Bool(true), Object::String("2.0".to_string())),
+            (Object::Bool(true), Object::String("2.0".into())),
             (Object::Bool(true), Object::Number(3.0)),
             (Object::Bool(true), Object::Bool(false)),
         ]
@@ -615,24 +1676,261 @@ mod interpreter_tests {
         vec![
             // True
             (Object::Number(3.0), Object::Number(3.0)),
-            (
-                Object::String("4.0".to_string()),
-                Object::String("4.0".to_string()),
-            ),
+            (Object::String("4.0".into()), Object::String("4.0".into())),
             (Object::Bool(true), Object::Bool(true)),
             (Object::Bool(false), Object::Bool(false)),
             (Object::Nil, Object::Nil),
             // False
             (Object::Bool(false), Object::Bool(true)),
             (Object::Number(2.0), Object::Number(3.0)),
-            (Object::String("4.0".to_string()), Object::Number(4.0)),
-            (Object::Number(3.0), Object::String("3.0".to_string())),
-            (Object::Bool(true), Object::String("2.0".to_string())),
+            (Object::String("4.0".into()), Object::Number(4.0)),
+            (Object::Number(3.0), Object::String("3.0".into())),
+            (Object::Bool(true), Object::String("2.0".into())),
             (Object::Bool(true), Object::Number(3.0)),
             (Object::Bool(true), Object::Bool(false)),
         ]
     }
 
+    #[test]
+    fn test_prelude() {
+        // Arrange
+        let source = "print max(1, 2); print min(1, 2); print abs(-3); print range(0, 3);";
+        // Act
+        let output = crate::run_captured(source, &mut Interpreter::new());
+        let without = crate::run_captured("print max;", &mut Interpreter::without_prelude());
+        // Assert
+        assert_eq!(output.stdout, "2\n1\n3\n[0, 1, 2]\n");
+        assert_eq!(output.diagnostics, "");
+        assert_eq!(
+            without.diagnostics,
+            "[Line 1] - Error[I0304]: Undefined variable 'max'.\n"
+        );
+    }
+
+    #[test]
+    fn test_global_slots() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let declare = "fun read() { return late; } print read();";
+        let define =
+            "var late = 1; late = late + read(); print read(); var late = \"again\"; print read();";
+        // Act
+        let undefined = crate::run_captured(declare, &mut interpreter);
+        let defined = crate::run_captured(define, &mut interpreter);
+        // Assert
+        assert_eq!(
+            undefined.diagnostics,
+            "[Line 1] - Error[I0304]: Undefined variable 'late'.\n"
+        );
+        assert_eq!(defined.stdout, "2\n\"again\"\n");
+        assert_eq!(defined.diagnostics, "");
+    }
+
+    #[test]
+    fn test_argument_buffers_are_reused() {
+        // Arrange
+        let mut interpreter = Interpreter::without_prelude();
+        let source = "fun add(a, b) { return a + b; } var total = 0; \
+            for (var i = 0; i < 100; i = i + 1) total = add(total, add(i, 1)); print total;";
+        // Act
+        let run = crate::run_captured(source, &mut interpreter);
+        let failed = crate::run_captured("add(1, missing);", &mut interpreter);
+        // Assert
+        assert_eq!(run.stdout, "5050\n");
+        assert_ne!(failed.diagnostics, "");
+        assert_eq!(interpreter.argument_buffers.len(), 2);
+        assert!(interpreter
+            .argument_buffers
+            .iter()
+            .all(|buffer| buffer.is_empty() && buffer.capacity() >= 2));
+    }
+
+    #[test]
+    fn test_native_modules() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let upper = LoxNativeFunction::new("upper", 1, |_, arguments, _| match &arguments[0] {
+            Object::String(text) => Ok(Object::String(text.to_uppercase().into())),
+            _ => Ok(Object::Nil),
+        });
+        let length = LoxNativeFunction::new("length", 1, |_, arguments, _| match &arguments[0] {
+            Object::String(text) => Ok(Object::Int(text.chars().count() as i64)),
+            _ => Ok(Object::Nil),
+        });
+        // Act
+        let registered = interpreter.register_native_module("text", [upper]);
+        let extended = interpreter.register_native_module("text", [length]);
+        let collision = interpreter.register_native_module("len", []);
+        let run = crate::run_captured(
+            "print text; print text.upper(\"lox\"); print text.length(\"lox\");\nprint text.lower;",
+            &mut interpreter,
+        );
+        let declared = crate::run_captured("var text = 1;", &mut interpreter);
+        let assigned = crate::run_captured(
+            "fun f() { text = 1; } { var text = 2; print text; }",
+            &mut interpreter,
+        );
+        // Assert
+        assert!(registered.is_ok() && extended.is_ok());
+        assert!(collision.is_err());
+        assert_eq!(run.stdout, "<module text>\n\"LOX\"\n3\n");
+        assert_eq!(
+            run.diagnostics,
+            "[Line 2] - Error[I0323]: Undefined member 'lower' of module 'text'.\n"
+        );
+        assert_eq!(
+            declared.diagnostics,
+            "[Line 1] - Error[R0216] at 'text': Cannot redeclare native module 'text'.\n"
+        );
+        assert_eq!(
+            assigned.diagnostics,
+            "[Line 1] - Error[R0217] at 'text': Cannot assign to native module 'text'.\n"
+        );
+        assert!(interpreter.is_native_module("text"));
+    }
+
+    #[test]
+    fn test_interpreter_builder() {
+        // Arrange
+        let kept = natives::default_natives()
+            .into_iter()
+            .filter(|native| native.name == "len");
+        let greet =
+            LoxNativeFunction::new("greet", 0, |_, _, _| Ok(Object::String("hello".into())));
+        let source = "print shout(); print len(\"abc\"); print clock;";
+        // Act
+        let mut interpreter = InterpreterBuilder::new()
+            .without_default_natives()
+            .without_default_prelude()
+            .with_natives(kept)
+            .with_natives([greet])
+            .with_prelude("fun shout() { return greet() + \"!\"; }")
+            .build()
+            .unwrap();
+        let output = crate::run_captured(source, &mut interpreter);
+        let mut broken = None;
+        let reported = capture_diagnostics(|| {
+            broken = Some(InterpreterBuilder::new().with_prelude("print;").build());
+        });
+        // Assert
+        assert_eq!(output.stdout, "\"hello!\"\n3\n");
+        assert_eq!(
+            output.diagnostics,
+            "[Line 1] - Error[I0304]: Undefined variable 'clock'.\n"
+        );
+        assert!(matches!(broken, Some(Err(RunError::Compile))));
+        assert_eq!(
+            reported,
+            "[Line 1] - Error[P0101] at ';': Expect expression.\n"
+        );
+    }
+
+    #[test]
+    fn test_builder_leaves_out_denied_natives() {
+        // Arrange
+        let capabilities = Capabilities::none().grant(Capability::Time);
+        // Act
+        let interpreter = InterpreterBuilder::new()
+            .with_capabilities(capabilities)
+            .build()
+            .unwrap();
+        // Assert
+        let globals = interpreter.globals.borrow();
+        assert!(globals.is_defined("clock"));
+        assert!(!globals.is_defined("readFile"));
+        assert!(globals.is_defined("len"));
+    }
+
+    #[test]
+    fn test_value_size_limits() {
+        // Arrange
+        let limits = Limits {
+            max_string_length: 64,
+            max_list_length: 3,
+            max_fields: 2,
+            ..Limits::default()
+        };
+        let mut interpreter = InterpreterBuilder::new()
+            .with_limits(limits)
+            .build()
+            .unwrap();
+        let source = "var s = \"ab\";
+while (true) s = s + s;
+print \"ab\" * 100;
+var l = list();
+while (true) push(l, 1);
+class Box {}
+var box = Box();
+box.a = 1;
+box.b = 2;
+box.a = 3;
+box.c = 4;";
+        // Act
+        let output = crate::run_captured(source, &mut interpreter);
+        // Assert
+        assert_eq!(
+            output.diagnostics,
+            "[Line 2] - Error[I0324]: String of 128 bytes exceeds the limit of 64 bytes.
+[Line 3] - Error[I0324]: String of 200 bytes exceeds the limit of 64 bytes.
+[Line 5] - Error[I0325]: List of 4 elements exceeds the limit of 3 elements.
+[Line 11] - Error[I0326]: Instance exceeds the limit of 2 fields.
+"
+        );
+    }
+
+    #[test]
+    fn test_call_depth_limit() {
+        // Arrange
+        let limits = Limits {
+            max_call_depth: 8,
+            ..Limits::default()
+        };
+        let mut interpreter = InterpreterBuilder::new()
+            .without_default_prelude()
+            .with_limits(limits)
+            .build()
+            .unwrap();
+        let source = "fun deep(n) { if (n == 0) return 0; return 1 + deep(n - 1); }
+fun loop(n) { if (n == 0) return 0; return loop(n - 1); }
+print deep(7);
+print loop(100);
+print deep(8);
+print deep(7);";
+        // Act
+        let output = crate::run_captured(source, &mut interpreter);
+        // Assert
+        assert_eq!(output.stdout, "7\n0\n7\n");
+        assert_eq!(
+            output.diagnostics,
+            "[Line 1] - Error[I0335]: Calls are nested deeper than the limit of 8 calls.\n"
+        );
+    }
+
+    #[test]
+    fn test_file_root_confines_paths() {
+        // Arrange
+        let base = std::env::temp_dir().join(format!("rlox-root-{}", std::process::id()));
+        let root = base.join("root");
+        std::fs::create_dir_all(root.join("data")).unwrap();
+        std::fs::write(base.join("secret.txt"), "secret").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(base.join("secret.txt"), root.join("link.txt")).unwrap();
+        let files = FileSystem::root(&root.to_string_lossy()).unwrap();
+        // Act
+        let written = files.write("data/notes.txt", "kept", false);
+        let read = files.read("data/../data/notes.txt");
+        let escaped = files.read("../secret.txt");
+        let absolute = files.write(&base.join("new.txt").to_string_lossy(), "", false);
+        let linked = files.read("link.txt");
+        std::fs::remove_dir_all(&base).unwrap();
+        // Assert
+        assert!(written.is_ok());
+        assert_eq!(read.ok(), Some("kept".to_string()));
+        assert!(escaped.is_err_and(|err| err.kind() == io::ErrorKind::PermissionDenied));
+        assert!(absolute.is_err());
+        assert!(linked.is_err());
+    }
+
     #[test]
     fn test_subtraction() {
         let token = make_token_operator(TokenType::Minus, "-");
@@ -671,6 +1969,76 @@ mod interpreter_tests {
         run_binary_test(token, operands, results);
     }
 
+    #[test]
+    fn test_division_by_zero() {
+        // Arrange
+        let token = make_token_operator(TokenType::Slash, "/");
+        let divide = |left: f64, right: f64| {
+            BinaryExpr::new(
+                make_literal_number(left),
+                token.clone(),
+                make_literal_number(right),
+            )
+        };
+        let mut strict = Interpreter::new();
+        let mut ieee = Interpreter::new();
+        ieee.division_by_zero = DivisionByZero::Ieee;
+        // Act
+        let by_zero = strict.visit_binary_expr(&divide(1.0, 0.0));
+        let zero_by_zero = strict.visit_binary_expr(&divide(0.0, 0.0));
+        let overflow = strict.visit_binary_expr(&divide(1e308, 1e-10));
+        let ieee_by_zero = ieee.visit_binary_expr(&divide(-1.0, 0.0));
+        let ieee_zero_by_zero = ieee.visit_binary_expr(&divide(0.0, 0.0));
+        // Assert
+        assert!(
+            matches!(by_zero, Err(LoxErrorResult::Interpreter { message, .. })
+            if message.to_string() == "Illegal expression. Division by zero is not allowed.")
+        );
+        assert!(
+            matches!(zero_by_zero, Err(LoxErrorResult::Interpreter { message, .. })
+            if message.to_string() == "Illegal expression. Zero divided by zero is undefined.")
+        );
+        assert_eq!(overflow.ok(), Some(Object::Number(f64::INFINITY)));
+        assert!(ieee_by_zero.is_ok_and(|value| value.to_string() == "-Infinity"));
+        assert!(ieee_zero_by_zero.is_ok_and(|value| value.to_string() == "NaN"));
+    }
+
+    #[test]
+    fn test_integer_arithmetic() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let mut evaluate = |left: Object, token_type: TokenType, lexeme: &str, right: Object| {
+            let binary_expr = BinaryExpr::new(
+                make_literal(left),
+                make_token_operator(token_type, lexeme),
+                make_literal(right),
+            );
+            interpreter.visit_binary_expr(&binary_expr)
+        };
+        // Act
+        let sum = evaluate(Object::Int(2), TokenType::Plus, "+", Object::Int(3));
+        let mixed = evaluate(Object::Int(2), TokenType::Star, "*", Object::Number(1.5));
+        let overflow = evaluate(Object::Int(i64::MAX), TokenType::Plus, "+", Object::Int(1));
+        let quotient = evaluate(Object::Int(7), TokenType::Slash, "/", Object::Int(2));
+        let equal = evaluate(
+            Object::Int(1),
+            TokenType::EqualEqual,
+            "==",
+            Object::Number(1.0),
+        );
+        // Assert
+        assert!(matches!(sum, Ok(Object::Int(5))));
+        assert!(matches!(mixed, Ok(Object::Number(number)) if number == 3.0));
+        #[cfg(not(feature = "bigint"))]
+        assert!(matches!(overflow, Ok(Object::Number(number)) if number == i64::MAX as f64 + 1.0));
+        #[cfg(feature = "bigint")]
+        assert!(
+            matches!(overflow, Ok(Object::BigInt(int)) if int.to_string() == "9223372036854775808")
+        );
+        assert!(matches!(quotient, Ok(Object::Number(number)) if number == 3.5));
+        assert_eq!(equal.ok(), Some(Object::Bool(true)));
+    }
+
     #[test]
     fn test_multiplication() {
         let token = make_token_operator(TokenType::Star, "*");
@@ -690,6 +2058,36 @@ mod interpreter_tests {
         run_binary_test(token, operands, results);
     }
 
+    #[test]
+    fn test_string_repetition() {
+        let token = make_token_operator(TokenType::Star, "*");
+        // Operands and results
+        let text = |value: &str| Object::String(value.into());
+        let operands: Vec<(Object, Object)> = vec![
+            (text("ab"), Object::Int(3)),
+            (Object::Int(2), text("ab")),
+            (text("ab"), Object::Number(2.0)),
+            (text("ab"), Object::Int(0)),
+            // Errors
+            (text("ab"), Object::Int(-1)),
+            (text("ab"), Object::Number(1.5)),
+            (text("ab"), text("ab")),
+            (text("ab"), Object::Nil),
+        ];
+        let results: Vec<(bool, Object)> = vec![
+            (true, text("ababab")),
+            (true, text("abab")),
+            (true, text("abab")),
+            (true, text("")),
+            // Errors
+            (false, Object::Nil),
+            (false, Object::Nil),
+            (false, Object::Nil),
+            (false, Object::Nil),
+        ];
+        run_binary_test(token, operands, results);
+    }
+
     #[test]
     fn test_addition() {
         let token = make_token_operator(TokenType::Plus, "+");
@@ -700,7 +2098,7 @@ mod interpreter_tests {
             (true, Object::Number(4.0)), // 3.0 , 1.0
             (true, Object::Number(6.0)), // 3.0 , 3.0
             // Errors
-            (false, Object::String("43".to_string())),
+            (false, Object::String("43".into())),
             (false, Object::Nil),
             (false, Object::Nil),
             (false, Object::Nil),
@@ -715,11 +2113,11 @@ mod interpreter_tests {
         // Operands and results
         let operands: Vec<(Object, Object)> = get_test_string_operands();
         let results: Vec<(bool, Object)> = vec![
-            (true, Object::String("Hi, Rusty".to_string())),
-            (true, Object::String("Together".to_string())),
-            (true, Object::String("Split two".to_string())),
-            (true, Object::String("4.03".to_string())),
-            (true, Object::String("32.0".to_string())),
+            (true, Object::String("Hi, Rusty".into())),
+            (true, Object::String("Together".into())),
+            (true, Object::String("Split two".into())),
+            (true, Object::String("4.03".into())),
+            (true, Object::String("32.0".into())),
             // Errors
             (false, Object::Nil),
             (false, Object::Nil),
@@ -732,7 +2130,7 @@ mod interpreter_tests {
     fn test_greater_than_operator() {
         let token = make_token_operator(TokenType::Greater, ">");
         // Operands and results
-        let operands: Vec<(Object, Object)> = get_test_number_operands();
+        let operands: Vec<(Object, Object)> = get_test_comparison_operands();
         let results: Vec<(bool, Object)> = vec![
             (true, Object::Bool(false)), // 2.0 , 3.0
             (true, Object::Bool(true)),  // 3.0 , 1.0
@@ -743,6 +2141,11 @@ mod interpreter_tests {
             (false, Object::Nil),
             (false, Object::Nil),
             (false, Object::Nil),
+            (true, Object::Bool(false)), // "apple" , "banana"
+            (true, Object::Bool(true)),  // "pear" , "peach"
+            (true, Object::Bool(false)), // "lox" , "lox"
+            // Errors
+            (false, Object::Nil),
         ];
         run_binary_test(token, operands, results);
     }
@@ -751,7 +2154,7 @@ mod interpreter_tests {
     fn test_greater_than_or_equal_operator() {
         let token = make_token_operator(TokenType::GreaterEqual, ">=");
         // Operands and results
-        let operands: Vec<(Object, Object)> = get_test_number_operands();
+        let operands: Vec<(Object, Object)> = get_test_comparison_operands();
         let results: Vec<(bool, Object)> = vec![
             (true, Object::Bool(false)), // 2.0 , 3.0
             (true, Object::Bool(true)),  // 3.0 , 1.0
@@ -762,6 +2165,11 @@ mod interpreter_tests {
             (false, Object::Nil),
             (false, Object::Nil),
             (false, Object::Nil),
+            (true, Object::Bool(false)), // "apple" , "banana"
+            (true, Object::Bool(true)),  // "pear" , "peach"
+            (true, Object::Bool(true)),  // "lox" , "lox"
+            // Errors
+            (false, Object::Nil),
         ];
         run_binary_test(token, operands, results);
     }
@@ -770,7 +2178,7 @@ mod interpreter_tests {
     fn test_less_than_operator() {
         let token = make_token_operator(TokenType::Less, "<");
         // Operands and results
-        let operands: Vec<(Object, Object)> = get_test_number_operands();
+        let operands: Vec<(Object, Object)> = get_test_comparison_operands();
         let results: Vec<(bool, Object)> = vec![
             (true, Object::Bool(true)),  // 2.0 , 3.0
             (true, Object::Bool(false)), // 3.0 , 1.0
@@ -781,6 +2189,11 @@ mod interpreter_tests {
             (false, Object::Nil),
             (false, Object::Nil),
             (false, Object::Nil),
+            (true, Object::Bool(true)),  // "apple" , "banana"
+            (true, Object::Bool(false)), // "pear" , "peach"
+            (true, Object::Bool(false)), // "lox" , "lox"
+            // Errors
+            (false, Object::Nil),
         ];
         run_binary_test(token, operands, results);
     }
@@ -789,19 +2202,21 @@ mod interpreter_tests {
     fn test_less_than_or_equal_operator() {
         let token = make_token_operator(TokenType::LessEqual, "<=");
         // Operands and results
-        let operands: Vec<(Object, Object)> = get_test_number_operands();
+        let operands: Vec<(Object, Object)> = get_test_comparison_operands();
         let results: Vec<(bool, Object)> = vec![
             (true, Object::Bool(true)),  // 2.0 , 3.0
             (true, Object::Bool(false)), // 3.0 , 1.0
             (true, Object::Bool(true)),  // 3.0 , 3.0
+            // Errors
             (false, Object::Nil),
             (false, Object::Nil),
             (false, Object::Nil),
             (false, Object::Nil),
             (false, Object::Nil),
-            (false, Object::Nil),
-            (false, Object::Nil),
-            (false, Object::Nil),
+            (true, Object::Bool(true)),  // "apple" , "banana"
+            (true, Object::Bool(false)), // "pear" , "peach"
+            (true, Object::Bool(true)),  // "lox" , "lox"
+            // Errors
             (false, Object::Nil),
         ];
         run_binary_test(token, operands, results);
@@ -855,42 +2270,35 @@ mod interpreter_tests {
     fn test_ternary_operator() {
         // Arrange
         let mut interpreter = Interpreter::new();
-        let ternary: TernaryExpr = TernaryExpr {
-            condition: Box::new(Expr::Binary(BinaryExpr {
-                left: make_literal_number(69.0),
-                operator: make_token_operator(TokenType::EqualEqual, "=="),
-                right: make_literal_number(69.0),
-                uid: 0,
-            })),
-            then_branch: make_literal_string("Ohhh yeaahhh!"),
-            else_branch: make_literal_string(":c"),
-            uid: 0,
-        };
+        let ternary = TernaryExpr::new(
+            Box::new(Expr::new_binary(
+                make_literal_number(69.0),
+                make_token_operator(TokenType::EqualEqual, "=="),
+                make_literal_number(69.0),
+            )),
+            make_literal_string("Ohhh yeaahhh!"),
+            make_literal_string(":c"),
+        );
 
         // Act
         let result = interpreter.visit_ternary_expr(&ternary);
         // Assert
         assert!(result.is_ok());
-        assert_eq!(
-            result.ok(),
-            Some(Object::String("Ohhh yeaahhh!".to_string()))
-        );
+        assert_eq!(result.ok(), Some(Object::String("Ohhh yeaahhh!".into())));
     }
 
     #[test]
     fn test_unary_minus() {
         // Arrange
         let mut interpreter = Interpreter::new();
-        let unary_expr_1 = UnaryExpr {
-            operator: make_token_operator(TokenType::Minus, "-"),
-            right: make_literal_number(123.0),
-            uid: 0,
-        };
-        let unary_expr_2 = UnaryExpr {
-            operator: make_token_operator(TokenType::Minus, "-"),
-            right: make_literal_string("Coffee"),
-            uid: 0,
-        };
+        let unary_expr_1 = UnaryExpr::new(
+            make_token_operator(TokenType::Minus, "-"),
+            make_literal_number(123.0),
+        );
+        let unary_expr_2 = UnaryExpr::new(
+            make_token_operator(TokenType::Minus, "-"),
+            make_literal_string("Coffee"),
+        );
 
         // Act
         let result_1 = interpreter.visit_unary_expr(&unary_expr_1);
@@ -907,11 +2315,10 @@ mod interpreter_tests {
     fn test_unary_bang() {
         // Arrange
         let mut interpreter = Interpreter::new();
-        let unary_expr = UnaryExpr {
-            operator: make_token_operator(TokenType::Bang, "!"),
-            right: make_literal_bool(false),
-            uid: 0,
-        };
+        let unary_expr = UnaryExpr::new(
+            make_token_operator(TokenType::Bang, "!"),
+            make_literal_bool(false),
+        );
 
         // Act
         let result = interpreter.visit_unary_expr(&unary_expr);
@@ -926,10 +2333,7 @@ mod interpreter_tests {
         let mut interpreter = Interpreter::new();
         let initializer = make_literal_number(123.0);
         let name = make_token_identifier("my_variable");
-        let var_stmt = VarStmt {
-            name: name.clone(),
-            initializer: Some(initializer),
-        };
+        let var_stmt = VarStmt::new(name.clone(), Some(initializer), None);
 
         // Act
         let result = interpreter.visit_var_stmt(&var_stmt);
@@ -944,10 +2348,7 @@ mod interpreter_tests {
         let mut interpreter = Interpreter::new();
         let initializer = make_literal(Object::Nil);
         let name = make_token_identifier("my_variable");
-        let var_stmt = VarStmt {
-            name: name.clone(),
-            initializer: Some(initializer),
-        };
+        let var_stmt = VarStmt::new(name.clone(), Some(initializer), None);
 
         // Act
         let result = interpreter.visit_var_stmt(&var_stmt);
@@ -966,14 +2367,8 @@ mod interpreter_tests {
         let mut interpreter = Interpreter::new();
         let name = make_token_identifier("my_variable");
         let initializer = make_literal_number(123.0);
-        let var_stmt = VarStmt {
-            name: name.clone(),
-            initializer: Some(initializer),
-        };
-        let var_expr = VariableExpr {
-            name: name.clone(),
-            uid: 0,
-        };
+        let var_stmt = VarStmt::new(name.clone(), Some(initializer), None);
+        let var_expr = VariableExpr::new(name.clone());
 
         // Act
         let result = interpreter.visit_var_stmt(&var_stmt);
@@ -990,7 +2385,7 @@ mod interpreter_tests {
         // Arrange
         let mut interpreter = Interpreter::new();
         let name = make_token_identifier("my_variable");
-        let var_expr = VariableExpr { name, uid: 0 };
+        let var_expr = VariableExpr::new(name);
 
         // Act
         let result = interpreter.visit_variable_expr(&var_expr);
@@ -1005,17 +2400,10 @@ mod interpreter_tests {
 
         let name = make_token_identifier("my_variable");
         let initializer = make_literal_number(123.0);
-        let var_stmt = VarStmt {
-            name: name.clone(),
-            initializer: Some(initializer),
-        };
+        let var_stmt = VarStmt::new(name.clone(), Some(initializer), None);
 
         let value = make_literal_number(321.0);
-        let assign_expr = AssignExpr {
-            name,
-            value,
-            uid: 0,
-        };
+        let assign_expr = AssignExpr::new(name, value);
 
         // Act
         let result = interpreter.visit_var_stmt(&var_stmt);
@@ -1034,11 +2422,7 @@ mod interpreter_tests {
 
         let name = make_token_identifier("my_variable");
         let value = make_literal_number(321.0);
-        let assign_expr = AssignExpr {
-            name,
-            value,
-            uid: 0,
-        };
+        let assign_expr = AssignExpr::new(name, value);
 
         // Act
         let result = interpreter.visit_assign_expr(&assign_expr);
@@ -1053,12 +2437,7 @@ mod interpreter_tests {
         let left = make_literal_bool(false);
         let operator = make_token_operator(TokenType::Or, "or");
         let right = make_literal_bool(true);
-        let logical_expr = LogicalExpr {
-            left,
-            operator,
-            right,
-            uid: 0,
-        };
+        let logical_expr = LogicalExpr::new(left, operator, right);
         // Act
         let result = interpreter.visit_logical_expr(&logical_expr);
         // Assert
@@ -1073,12 +2452,7 @@ mod interpreter_tests {
         let left = make_literal_bool(false);
         let operator = make_token_operator(TokenType::And, "and");
         let right = make_literal_bool(true);
-        let logical_expr = LogicalExpr {
-            left,
-            operator,
-            right,
-            uid: 0,
-        };
+        let logical_expr = LogicalExpr::new(left, operator, right);
         // Act
         let result = interpreter.visit_logical_expr(&logical_expr);
         // Assert
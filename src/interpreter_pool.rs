@@ -0,0 +1,145 @@
+//! Worker threads running scripts in parallel, each in an interpreter of its own. Interpreters
+//! share nothing mutable: their values stay on their thread, the diagnostics they capture and
+//! the `--stats` counters are per thread, and syntax tree ids come from an atomic counter.
+use std::{
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{interpreter::Interpreter, run_captured, RunOutput};
+
+/// A script to run and where to send what it printed
+type Job = (String, Sender<RunOutput>);
+
+/// Makes the interpreter of every script
+type Factory = Arc<dyn Fn() -> Interpreter + Send + Sync>;
+
+#[derive(Debug)]
+pub struct InterpreterPool {
+    /// `None` once dropped, which ends the workers
+    jobs: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl InterpreterPool {
+    /// `size` workers running every script in an `Interpreter::new()`
+    pub fn new(size: usize) -> InterpreterPool {
+        InterpreterPool::with_factory(size, Interpreter::new)
+    }
+
+    /// `size` workers running every script in a new interpreter made by `factory`, e.g. with
+    /// more capabilities. Scripts never see each other's globals.
+    pub fn with_factory(
+        size: usize,
+        factory: impl Fn() -> Interpreter + Send + Sync + 'static,
+    ) -> InterpreterPool {
+        let (jobs, received) = mpsc::channel::<Job>();
+        let received = Arc::new(Mutex::new(received));
+        let factory: Factory = Arc::new(factory);
+        let workers = (0..size.max(1))
+            .map(|_| {
+                let received = Arc::clone(&received);
+                let factory = Arc::clone(&factory);
+                thread::spawn(move || work(&received, &factory))
+            })
+            .collect();
+        InterpreterPool {
+            jobs: Some(jobs),
+            workers,
+        }
+    }
+
+    /// Runs a script like `run_captured` on the next free worker and waits for it to end.
+    /// `None` if the script made its worker panic.
+    pub fn run(&self, source: &str) -> Option<RunOutput> {
+        self.submit(source).recv().ok()
+    }
+
+    /// Runs the scripts in parallel, their outputs are in the same order
+    pub fn run_all(&self, sources: &[&str]) -> Vec<Option<RunOutput>> {
+        let outputs: Vec<Receiver<RunOutput>> =
+            sources.iter().map(|source| self.submit(source)).collect();
+        outputs.iter().map(|output| output.recv().ok()).collect()
+    }
+
+    /// Receives the output of the script once a worker ran it
+    fn submit(&self, source: &str) -> Receiver<RunOutput> {
+        let (reply, output) = mpsc::channel();
+        if let Some(jobs) = &self.jobs {
+            // Only fails when every worker panicked, then `output` is closed too
+            let _ = jobs.send((source.to_string(), reply));
+        }
+        output
+    }
+}
+
+/// Runs the jobs until the pool is dropped
+fn work(received: &Mutex<Receiver<Job>>, factory: &Factory) {
+    loop {
+        // The lock is released before the script runs, the other workers take the next jobs
+        let job = match received.lock() {
+            Ok(received) => received.recv(),
+            Err(_) => return,
+        };
+        let Ok((source, reply)) = job else {
+            return;
+        };
+        let _ = reply.send(run_captured(&source, &mut factory()));
+    }
+}
+
+impl Drop for InterpreterPool {
+    /// Waits for the scripts that are running, the workers then end
+    fn drop(&mut self) {
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod interpreter_pool_tests {
+    use super::*;
+
+    #[test]
+    fn test_scripts_run_in_parallel_in_isolation() {
+        // Arrange
+        let pool = Arc::new(InterpreterPool::new(4));
+        let sources: Vec<String> = (0..32)
+            .map(|i| {
+                format!(
+                    "var seen = 0;\nfun bump() {{ seen = seen + 1; }}\nfor (var i = 0; i < {i}; i = i + 1) bump();\nprint seen;\nprint nil + {i};"
+                )
+            })
+            .collect();
+        let sources: Vec<&str> = sources.iter().map(String::as_str).collect();
+        // Act
+        let callers: Vec<_> = (0..4)
+            .map(|_| {
+                let pool = Arc::clone(&pool);
+                thread::spawn(move || pool.run("print \"other\";"))
+            })
+            .collect();
+        let outputs = pool.run_all(&sources);
+        // Assert
+        for (i, output) in outputs.into_iter().enumerate() {
+            assert_eq!(
+                output,
+                Some(RunOutput {
+                    stdout: format!("{i}\n"),
+                    diagnostics:
+                        "[Line 5] - Error[I0302]: Operands must be strings or numbers for '+' operation.\n"
+                            .to_string(),
+                    exit_code: 70,
+                })
+            );
+        }
+        for caller in callers {
+            assert_eq!(caller.join().unwrap().unwrap().stdout, "\"other\"\n");
+        }
+    }
+}
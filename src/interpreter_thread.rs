@@ -0,0 +1,122 @@
+//! An interpreter that lives on a thread of its own, for multi-threaded embedders.
+//! `Interpreter` shares its environments and values through `Rc` and can't leave the thread
+//! that made it unless built with the `sync` feature (see `shared`), but `InterpreterThread`
+//! is `Send` and `Sync` either way: a web server can keep one per worker, or share one behind
+//! an `Arc`, and run scripts on it from any thread.
+use std::{
+    sync::mpsc::{self, Sender},
+    thread::{self, JoinHandle},
+};
+
+use crate::{interpreter::Interpreter, run_captured, RunOutput};
+
+/// A script to run and where to send what it printed
+type Job = (String, Sender<RunOutput>);
+
+#[derive(Debug)]
+pub struct InterpreterThread {
+    /// `None` once dropped, which ends the thread
+    jobs: Option<Sender<Job>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl InterpreterThread {
+    /// Thread running scripts in an `Interpreter::new()`
+    pub fn spawn() -> InterpreterThread {
+        InterpreterThread::spawn_with(Interpreter::new)
+    }
+
+    /// Thread running scripts in the interpreter `setup` makes there, e.g. with more
+    /// capabilities or hooks
+    pub fn spawn_with(setup: impl FnOnce() -> Interpreter + Send + 'static) -> InterpreterThread {
+        let (jobs, received) = mpsc::channel::<Job>();
+        let thread = thread::spawn(move || {
+            let mut interpreter = setup();
+            for (source, reply) in received {
+                // The caller may have stopped waiting, the next job runs anyway
+                let _ = reply.send(run_captured(&source, &mut interpreter));
+            }
+        });
+        InterpreterThread {
+            jobs: Some(jobs),
+            thread: Some(thread),
+        }
+    }
+
+    /// Runs a script like `run_captured` and waits for it to end. Scripts run one after the
+    /// other and see the globals of the earlier ones. `None` once a script made the thread
+    /// panic.
+    pub fn run(&self, source: &str) -> Option<RunOutput> {
+        let (reply, output) = mpsc::channel();
+        self.jobs.as_ref()?.send((source.to_string(), reply)).ok()?;
+        output.recv().ok()
+    }
+}
+
+impl Default for InterpreterThread {
+    fn default() -> InterpreterThread {
+        InterpreterThread::spawn()
+    }
+}
+
+impl Drop for InterpreterThread {
+    /// Waits for the script that is running, the thread then ends
+    fn drop(&mut self) {
+        self.jobs.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod interpreter_thread_tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn test_interpreter_moves_to_another_thread() {
+        // Arrange
+        fn assert_send<T: Send>() {}
+        assert_send::<Interpreter>();
+        let mut interpreter = Interpreter::new();
+        run_captured("var count = 41;", &mut interpreter);
+        // Act
+        let output = thread::spawn(move || run_captured("print count + 1;", &mut interpreter))
+            .join()
+            .unwrap();
+        // Assert
+        assert_eq!(output.stdout, "42\n");
+    }
+
+    #[test]
+    fn test_scripts_run_from_other_threads() {
+        // Arrange
+        assert_send_sync::<InterpreterThread>();
+        let interpreter = Arc::new(InterpreterThread::spawn());
+        interpreter.run("var count = 0;\nfun bump() { count = count + 1; return count; }");
+        // Act
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let interpreter = Arc::clone(&interpreter);
+                thread::spawn(move || interpreter.run("bump();"))
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        let output = interpreter.run("print bump();\nprint -\"a\";");
+        // Assert
+        assert_eq!(
+            output,
+            Some(RunOutput {
+                stdout: "5\n".to_string(),
+                diagnostics: "[Line 2] - Error[I0301]: Operand must be a number.\n".to_string(),
+                exit_code: 70,
+            })
+        );
+    }
+}
@@ -0,0 +1,725 @@
+#![allow(clippy::result_large_err)]
+// Modules
+pub mod analysis;
+pub mod ast;
+pub mod ast_codec;
+pub mod ast_printer;
+pub mod async_host;
+pub mod cache;
+pub mod capabilities;
+pub mod cli;
+pub mod closure_compiler;
+pub mod config;
+pub mod coverage;
+pub mod debug_log;
+pub mod debugger;
+pub mod digest;
+pub mod environment;
+pub mod error;
+pub mod error_codes;
+pub mod expr;
+pub mod ffi;
+pub mod formatter;
+pub mod highlight;
+pub mod hooks;
+#[cfg(feature = "net")]
+pub mod http;
+pub mod incremental;
+pub mod interpreter;
+pub mod interpreter_pool;
+pub mod interpreter_thread;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod line_editor;
+pub mod linter;
+pub mod locale;
+pub mod lox_callable;
+pub mod lox_class;
+pub mod lox_foreign;
+pub mod lox_function;
+pub mod lox_generator;
+pub mod lox_instance;
+pub mod lox_module;
+pub mod lox_native_function;
+pub mod lsp;
+pub mod natives;
+pub mod object;
+pub mod parser;
+pub mod profiler;
+pub mod recorder;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod repl;
+pub mod resolver;
+pub mod scanner;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server;
+pub mod shared;
+pub mod source_map;
+pub mod stats;
+pub mod stmt;
+#[cfg(feature = "net")]
+pub mod tcp;
+pub mod test_runner;
+pub mod token;
+pub mod trace_export;
+pub mod tracer;
+pub mod transpiler;
+pub mod type_checker;
+pub mod utils;
+pub mod walker;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod watch;
+// Imports
+use std::{
+    fmt, io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    closure_compiler::ClosureCompiler,
+    error::{
+        capture_diagnostics, collect_diagnostics, report_diagnostic, RuntimeErrorSummary,
+        WarningLevel,
+    },
+    interpreter::Interpreter,
+    parser::Parser,
+    resolver::Resolver,
+    scanner::Scanner,
+    stmt::Stmt,
+    type_checker::TypeChecker,
+};
+
+/// Why a script failed, its errors were already reported
+#[derive(Debug)]
+pub enum RunError {
+    /// The script could not be read
+    Io(io::Error),
+    /// Lexical, parse or resolution errors, nothing was run
+    Compile,
+    Runtime(RuntimeErrorSummary),
+}
+
+impl RunError {
+    /// Exit code of the process, as in `sysexits.h`
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RunError::Io(_) => 74,
+            RunError::Compile => 65,
+            RunError::Runtime(_) => 70,
+        }
+    }
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::Io(err) => write!(f, "{err}"),
+            RunError::Compile => write!(f, "The script did not compile."),
+            RunError::Runtime(summary) => write!(f, "{summary}"),
+        }
+    }
+}
+
+pub fn run_file(path: &str, interpreter: &mut Interpreter) -> Result<(), RunError> {
+    let source = std::fs::read_to_string(path).map_err(RunError::Io)?;
+    run(source, interpreter, false)
+}
+
+/// What a script printed and reported when run with `run_captured`
+#[derive(Debug, Default, PartialEq)]
+pub struct RunOutput {
+    pub stdout: String,
+    /// Compile errors, warnings and runtime errors, one per line
+    pub diagnostics: String,
+    /// 0 on success, 65 when the script did not compile, 70 after a runtime error
+    /// or the code given to `exit(code)`
+    pub exit_code: i32,
+}
+
+/// Runs a script without touching the console, for embedders such as the browser playground
+pub fn run_captured(source: &str, interpreter: &mut Interpreter) -> RunOutput {
+    let captured = interpreter.capture_output();
+    // A previous run may have called `exit()`, this one starts anyway
+    interpreter.exit_code = None;
+    let mut result = Ok(());
+    let mut diagnostics = capture_diagnostics(|| {
+        result = run(source.to_string(), interpreter, false);
+    });
+    let captured = captured.borrow();
+    diagnostics.push_str(&captured.stderr);
+    let exit_code = match (result, interpreter.exit_code) {
+        (Err(RunError::Compile), _) => 65,
+        (_, Some(code)) => code,
+        (Err(err), None) => err.exit_code(),
+        (Ok(()), None) => 0,
+    };
+    RunOutput {
+        stdout: captured.stdout.clone(),
+        diagnostics,
+        exit_code,
+    }
+}
+
+/// How long each stage of a run took (`--time`, `:time` in the prompt)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StageTimings {
+    pub scan: Duration,
+    pub parse: Duration,
+    pub resolve: Duration,
+    pub execute: Duration,
+}
+
+impl StageTimings {
+    pub fn total(&self) -> Duration {
+        self.scan + self.parse + self.resolve + self.execute
+    }
+}
+
+impl fmt::Display for StageTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "scan {:.3} ms, parse {:.3} ms, resolve {:.3} ms, execute {:.3} ms (total {:.3} ms)",
+            millis(self.scan),
+            millis(self.parse),
+            millis(self.resolve),
+            millis(self.execute),
+            millis(self.total())
+        )
+    }
+}
+
+/// Adds the time since the previous lap to a stage of a timed run
+struct Stopwatch<'a> {
+    timings: Option<(&'a mut StageTimings, Instant)>,
+}
+
+impl<'a> Stopwatch<'a> {
+    /// Never reads the clock, which `wasm32-unknown-unknown` doesn't have
+    fn off() -> Stopwatch<'a> {
+        Stopwatch { timings: None }
+    }
+
+    fn start(timings: &'a mut StageTimings) -> Stopwatch<'a> {
+        Stopwatch {
+            timings: Some((timings, Instant::now())),
+        }
+    }
+
+    fn lap(&mut self, stage: fn(&mut StageTimings) -> &mut Duration) {
+        if let Some((timings, started)) = &mut self.timings {
+            let now = Instant::now();
+            *stage(timings) += now - *started;
+            *started = now;
+        }
+    }
+}
+
+/// Scans and parses a script, `RunError::Compile` when it has lexical or syntax errors
+/// (already reported). `repl` lets the last expression go without its `;`.
+pub fn parse(source: String, repl: bool) -> Result<Vec<Stmt>, RunError> {
+    parse_stages(source, repl, &mut Stopwatch::off())
+}
+
+fn parse_stages(
+    source: String,
+    repl: bool,
+    stopwatch: &mut Stopwatch,
+) -> Result<Vec<Stmt>, RunError> {
+    // Lexical Analysis
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+    stopwatch.lap(|timings| &mut timings.scan);
+    if !scanner.errors().is_empty() {
+        return Err(RunError::Compile); // The tokens are incomplete, parsing them would only add confusing errors.
+    }
+
+    // Parsing
+    let mut parser = Parser::new(tokens);
+    let statements = match repl {
+        true => parser.parse_repl(),
+        false => parser.parse(),
+    };
+    stopwatch.lap(|timings| &mut timings.parse);
+    match parser.had_error {
+        true => Err(RunError::Compile),
+        false => Ok(statements),
+    }
+}
+
+/// Parses a script and resolves its variables for `interpreter`, the statements are then
+/// ready to run. Tools that only need the syntax tree should stop at `parse`.
+pub fn compile(
+    source: String,
+    interpreter: &mut Interpreter,
+    repl: bool,
+) -> Result<Vec<Stmt>, RunError> {
+    compile_stages(source, interpreter, repl, &mut Stopwatch::off())
+}
+
+fn compile_stages(
+    source: String,
+    interpreter: &mut Interpreter,
+    repl: bool,
+    stopwatch: &mut Stopwatch,
+) -> Result<Vec<Stmt>, RunError> {
+    let statements = parse_stages(source, repl, stopwatch)?;
+    let warnings = interpreter.warnings;
+    let mut had_error = false;
+    let mut check = || {
+        // Resolving
+        let mut resolver = Resolver::new(interpreter);
+        resolver.resolve(&statements);
+        if resolver.had_error {
+            had_error = true;
+            return;
+        }
+        // Type annotations, mismatches only fail in strict mode
+        let mut checker = TypeChecker::new(interpreter.strict);
+        checker.check(&statements);
+        had_error = checker.had_error;
+    };
+    match warnings {
+        WarningLevel::Warn => check(),
+        _ => {
+            let diagnostics = collect_diagnostics(check);
+            for diagnostic in diagnostics {
+                if diagnostic.is_warning {
+                    had_error |= warnings == WarningLevel::Deny;
+                    if warnings == WarningLevel::Allow {
+                        continue;
+                    }
+                }
+                report_diagnostic(diagnostic);
+            }
+        }
+    }
+    stopwatch.lap(|timings| &mut timings.resolve);
+    match had_error {
+        true => Err(RunError::Compile),
+        false => Ok(statements),
+    }
+}
+
+/// Fails with `RunError::Compile` when the source has lexical, parse or resolution errors
+pub fn run(source: String, interpreter: &mut Interpreter, repl: bool) -> Result<(), RunError> {
+    run_stages(source, interpreter, repl, &mut Stopwatch::off())
+}
+
+/// Runs a changed script again in the session that already ran it (`--watch`, `:load` in
+/// the prompt). Its functions and classes are redefined and its statements run again, but
+/// the global variables that already exist keep their value.
+pub fn reload(source: String, interpreter: &mut Interpreter) -> Result<(), RunError> {
+    // A previous run may have called `exit()`, this one starts anyway
+    interpreter.exit_code = None;
+    interpreter.keep_globals = true;
+    let result = run(source, interpreter, false);
+    interpreter.keep_globals = false;
+    result
+}
+
+/// Runs a script like `run`, but its resolved syntax tree is loaded from `cache_dir` when an
+/// earlier run of the same source with the same options saved it there, and saved there
+/// otherwise (`--cache`). Only scripts the checks had nothing to report about are saved, so
+/// loading one skips no warning or type error. A cache that cannot be written is reported
+/// and the script runs anyway.
+pub fn run_cached(
+    source: String,
+    interpreter: &mut Interpreter,
+    cache_dir: &Path,
+) -> Result<(), RunError> {
+    let statements = match cache::load(cache_dir, &source, interpreter) {
+        Some(statements) => statements,
+        None => {
+            let mut compiled = Err(RunError::Compile);
+            let diagnostics = collect_diagnostics(|| {
+                compiled = compile(source.clone(), interpreter, false);
+            });
+            let clean = diagnostics.is_empty();
+            for diagnostic in diagnostics {
+                report_diagnostic(diagnostic);
+            }
+            let statements = compiled?;
+            if clean {
+                if let Err(err) = cache::save(cache_dir, &source, &statements, interpreter) {
+                    eprintln!(
+                        "Could not write the cache to '{}': {err}",
+                        cache_dir.display()
+                    );
+                }
+            }
+            statements
+        }
+    };
+    interpreter
+        .interpret(&statements)
+        .and_then(|_| interpreter.run_main(&statements))
+        .map_err(RunError::Runtime)
+}
+
+/// Runs a script like `run`, but compiles its statements into closures first (`--closures`)
+pub fn run_compiled(source: String, interpreter: &mut Interpreter) -> Result<(), RunError> {
+    let statements = compile(source, interpreter, false)?;
+    let compiled = ClosureCompiler::new(interpreter).compile(&statements);
+    interpreter
+        .interpret_compiled(&compiled)
+        .and_then(|_| interpreter.run_main(&statements))
+        .map_err(RunError::Runtime)
+}
+
+/// Runs like `run` and measures how long each stage took, up to the one that failed.
+/// Not for `wasm32-unknown-unknown`, where there is no clock to read.
+pub fn run_timed(
+    source: String,
+    interpreter: &mut Interpreter,
+    repl: bool,
+) -> (Result<(), RunError>, StageTimings) {
+    let mut timings = StageTimings::default();
+    let result = run_stages(
+        source,
+        interpreter,
+        repl,
+        &mut Stopwatch::start(&mut timings),
+    );
+    (result, timings)
+}
+
+fn run_stages(
+    source: String,
+    interpreter: &mut Interpreter,
+    repl: bool,
+    stopwatch: &mut Stopwatch,
+) -> Result<(), RunError> {
+    let statements = compile_stages(source, interpreter, repl, stopwatch)?;
+    // Run Interpreter
+    let result = match repl {
+        true => interpreter.interpret(&statements),
+        false => interpreter
+            .interpret(&statements)
+            .and_then(|_| interpreter.run_main(&statements)),
+    }
+    .map_err(RunError::Runtime);
+    stopwatch.lap(|timings| &mut timings.execute);
+    result
+}
+
+#[cfg(test)]
+mod lib_tests {
+    use super::*;
+
+    #[test]
+    fn test_run_captured() {
+        // Act
+        let source = "{ var unused; }\nprint 1 + 2;\nprint -\"a\";\nprint 4;";
+        let output = run_captured(source, &mut Interpreter::new());
+        // Assert
+        assert_eq!(
+            output,
+            RunOutput {
+                stdout: "3\n4\n".to_string(),
+                diagnostics: "[Line 1] - Warning[R0201]: 'unused': Variable is declared but never used.\n[Line 3] - Error[I0301]: Operand must be a number.\n".to_string(),
+                exit_code: 70,
+            }
+        );
+    }
+
+    #[test]
+    fn test_warning_levels() {
+        // Arrange
+        let source = "{ var unused; }\nprint 1;";
+        let run_with = |warnings| {
+            let mut interpreter = Interpreter::new();
+            interpreter.warnings = warnings;
+            run_captured(source, &mut interpreter)
+        };
+        let warning = "[Line 1] - Warning[R0201]: 'unused': Variable is declared but never used.\n";
+        // Act
+        let allowed = run_with(WarningLevel::Allow);
+        let denied = run_with(WarningLevel::Deny);
+        // Assert
+        assert_eq!(
+            allowed,
+            RunOutput {
+                stdout: "1\n".to_string(),
+                diagnostics: String::new(),
+                exit_code: 0,
+            }
+        );
+        assert_eq!(
+            denied,
+            RunOutput {
+                stdout: String::new(),
+                diagnostics: warning.to_string(),
+                exit_code: 65,
+            }
+        );
+    }
+
+    #[test]
+    fn test_reload_keeps_global_variables() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let output = interpreter.capture_output();
+        let first = "var count = 0;\nfun bump() { count = count + 1; }\nbump();";
+        let changed = "var count = 0;\nvar step = 10;\nfun bump() { count = count + step; }\nbump();\nprint count;";
+        // Act
+        run(first.to_string(), &mut interpreter, false).ok();
+        let result = reload(changed.to_string(), &mut interpreter);
+        run(
+            "var count = 0; print count;".to_string(),
+            &mut interpreter,
+            false,
+        )
+        .ok();
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(output.borrow().stdout, "11\n0\n");
+    }
+
+    #[test]
+    fn test_prompt_after_script_sees_its_globals() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let output = interpreter.capture_output();
+        let script = "var count = 2;\nfun double(n) { return n * 2; }\nclass Point { init(x) { this.x = x; } }";
+        // Act
+        run(script.to_string(), &mut interpreter, false).ok();
+        for line in ["print double(count);", "count = Point(5).x;", "count"] {
+            run(line.to_string(), &mut interpreter, true).ok();
+        }
+        // Assert
+        assert_eq!(output.borrow().stdout, "4\n5\n");
+    }
+
+    #[test]
+    fn test_main_return_value_is_the_exit_code() {
+        // Arrange
+        let returns = "print \"top\";\nfun main() { print \"main\"; return 3; }";
+        let called = "fun main() { print \"main\"; return 3; }\nmain();";
+        let nothing = "fun main() { print \"main\"; }";
+        let invalid = "fun main() {\n  return 1.5;\n}";
+        let text = "fun main() { return \"done\"; }";
+        let parameters = "fun main(args, env) { print args + env; }";
+        // Act
+        let outputs = [returns, called, nothing, invalid, text, parameters]
+            .map(|source| run_captured(source, &mut Interpreter::new()));
+        // Assert
+        assert_eq!(
+            outputs.each_ref().map(|output| output.exit_code),
+            [3, 0, 0, 70, 70, 70]
+        );
+        assert_eq!(outputs[0].stdout, "\"top\"\n\"main\"\n");
+        assert_eq!(outputs[1].stdout, "\"main\"\n");
+        assert_eq!(outputs[2].stdout, "\"main\"\n");
+        assert_eq!(
+            outputs[3].diagnostics,
+            "[Line 1] - Error[I0329]: Value returned by 'main' must be an integer between 0 and 255, got 1.5.\n"
+        );
+        assert_eq!(
+            outputs[4].diagnostics,
+            "[Line 1] - Error[I0329]: Value returned by 'main' must be an integer between 0 and 255, got \"done\".\n"
+        );
+        assert_eq!(outputs[5].stdout, "");
+        assert_eq!(
+            outputs[5].diagnostics,
+            "[Line 1] - Error[I0334]: 'main' must not declare parameters, it declares 2.\n"
+        );
+    }
+
+    #[test]
+    fn test_run_timed() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        // Act
+        let (result, timings) = run_timed("print 1;".to_string(), &mut interpreter, false);
+        let (compile_error, failed) = run_timed("print ;".to_string(), &mut interpreter, false);
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(
+            timings.total(),
+            timings.scan + timings.parse + timings.resolve + timings.execute
+        );
+        assert!(timings.to_string().starts_with("scan "));
+        assert!(matches!(compile_error, Err(RunError::Compile)));
+        assert_eq!(failed.resolve, Duration::ZERO);
+        assert_eq!(failed.execute, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_run_error_summary() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        // Act
+        let source = "print -\"a\";\nprint 1;\nprint nil + 1;";
+        let runtime = run(source.to_string(), &mut interpreter, false);
+        let mut compile = Ok(());
+        capture_diagnostics(|| compile = run("print ;".to_string(), &mut interpreter, false));
+        let printed_to_stderr = run_captured("eprint(1);", &mut Interpreter::new());
+        // Assert
+        assert!(matches!(&runtime, Err(RunError::Runtime(summary)) if summary.count == 2));
+        assert_eq!(
+            runtime.err().map(|err| err.to_string()).as_deref(),
+            Some("2 runtime errors, the last one: [Line 3] - Error[I0302]: Operands must be strings or numbers for '+' operation.")
+        );
+        assert!(matches!(compile, Err(RunError::Compile)));
+        assert_eq!(printed_to_stderr.exit_code, 0);
+    }
+
+    #[test]
+    fn test_pipeline_stages() {
+        // Arrange
+        let source = "return 1;".to_string();
+        let mut parsed = Err(RunError::Compile);
+        let mut compiled = Ok(Vec::new());
+        // Act
+        let diagnostics = capture_diagnostics(|| {
+            parsed = parse(source.clone(), false);
+            compiled = compile(source, &mut Interpreter::new(), false);
+        });
+        // Assert
+        assert!(parsed.is_ok_and(|statements| statements.len() == 1));
+        assert!(matches!(compiled, Err(RunError::Compile)));
+        assert_eq!(
+            diagnostics,
+            "[Line 1] - Error[R0204] at 'return': Cannot return from top-level code.\n"
+        );
+    }
+
+    #[test]
+    fn test_run_captured_exit() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        // Act
+        let source = "fun stop() { { exit(3); } print 1; }\nstop();\nprint 2;";
+        let output = run_captured(source, &mut interpreter);
+        let next_run = run_captured("print 4;", &mut interpreter);
+        // Assert
+        assert_eq!((output.stdout.as_str(), output.exit_code), ("", 3));
+        assert_eq!((next_run.stdout.as_str(), next_run.exit_code), ("4\n", 0));
+    }
+
+    #[test]
+    fn test_run_captured_deterministic() {
+        // Arrange
+        let source =
+            "var start = clock();\nsleep(1000);\nprint clock() - start;\nprint randomInt(1, 1000);";
+        let run_deterministic = || {
+            let mut interpreter = Interpreter::new();
+            interpreter.make_deterministic();
+            run_captured(source, &mut interpreter).stdout
+        };
+        // Act
+        let first = run_deterministic();
+        let second = run_deterministic();
+        // Assert
+        assert_eq!(first, second);
+        assert!(first.starts_with("1001\n"));
+    }
+
+    #[test]
+    fn test_run_captured_strict() {
+        // Arrange
+        let source = "if (1) print \"one\";\nprint \"n\" + 1;\nwhile (nil) {}\nprint 1 > 0 ? \"a\" + \"b\" : nil;";
+        let mut interpreter = Interpreter::new();
+        interpreter.strict = true;
+        // Act
+        let strict = run_captured(source, &mut interpreter);
+        let standard = run_captured(source, &mut Interpreter::new());
+        // Assert
+        assert_eq!(strict.stdout, "\"ab\"\n");
+        assert_eq!(
+            strict.diagnostics,
+            "[Line 1] - Error[I0319]: Condition of 'if' must be a boolean in strict mode, got 1.
+[Line 2] - Error[I0318]: Operands must be two numbers or two strings for '+' operation in strict mode.
+[Line 3] - Error[I0319]: Condition of 'while' must be a boolean in strict mode, got nil.
+"
+        );
+        assert_eq!(standard.stdout, "\"one\"\n\"n1\"\n\"ab\"\n");
+    }
+
+    #[test]
+    fn test_type_annotations() {
+        // Arrange
+        let source = "fun half(n: Number) -> Number { return n / 2; }\nvar text: String = half(3);\nprint text;";
+        let mut interpreter = Interpreter::new();
+        interpreter.strict = true;
+        // Act
+        let standard = run_captured(source, &mut Interpreter::new());
+        let strict = run_captured(source, &mut interpreter);
+        // Assert
+        assert_eq!(standard.stdout, "1.5\n");
+        assert_eq!(
+            standard.diagnostics,
+            "[Line 2] - Warning[T0402]: 'text': Variable 'text' is declared as String but initialized with Number.\n"
+        );
+        assert_eq!(strict.stdout, "");
+        assert_eq!(strict.exit_code, 65);
+    }
+
+    #[test]
+    #[cfg(feature = "bigint")]
+    fn test_big_ints() {
+        // Arrange
+        let source = "var factorial = 1;
+for (var i = 1; i <= 25; i = i + 1) factorial = factorial * i;
+print factorial;
+print factorial - factorial + 1 == 1;
+print -9223372036854775807 - 2;
+print 2n * 3 > 5;
+print \"n = \" + 10n;
+print factorial / 1e25;
+print factorial / 25;
+print 10n / 4;
+print toStringBase(factorial, 16);
+print toStringBase(-factorial, 36);";
+        // Act
+        let output = run_captured(source, &mut Interpreter::new());
+        // Assert
+        assert_eq!(
+            output.stdout,
+            "15511210043330985984000000\ntrue\n-9223372036854775809\ntrue\n\"n = 10\"\n1.5511210043330985\n620448401733239439360000\n2.5\n\"cd4a0619fb0907bc00000\"\n\"-1y5v922m44xs00000\"\n"
+        );
+    }
+
+    #[test]
+    fn test_tail_calls_do_not_grow_the_stack() {
+        // Arrange
+        let source = "fun count(n, acc) { if (n == 0) return acc; return count(n - 1, acc + 1); }
+fun isEven(n) { if (n == 0) return true; return isOdd(n - 1); }
+fun isOdd(n) { if (n == 0) return false; return isEven(n - 1); }
+print count(100000, 0);
+print isEven(100001);
+fun wrong() { return count(1); }
+wrong();";
+        // Act
+        let output = run_captured(source, &mut Interpreter::new());
+        // Assert
+        assert_eq!(output.stdout, "100000\nfalse\n");
+        assert_eq!(
+            output.diagnostics,
+            "[Line 6] - Error[I0309]: Expected 2 arguments but got 1.\n"
+        );
+    }
+
+    #[test]
+    fn test_generators() {
+        // Arrange
+        let source = "fun naturals() { var n = 0; while (true) { n = n + 1; yield n; } }
+fun take(values, count) { for (var value in values) { if (count == 0) return; count = count - 1; yield value; } }
+for (var n in take(naturals(), 3)) print n;
+var letters = take(\"abc\", 2);
+print next(letters); print next(letters); print next(letters);
+for (var n in 4) print n;";
+        // Act
+        let output = run_captured(source, &mut Interpreter::new());
+        // Assert
+        assert_eq!(output.stdout, "1\n2\n3\n\"a\"\n\"b\"\nnil\n");
+        assert_eq!(
+            output.diagnostics,
+            "[Line 6] - Error[I0320]: Can only iterate over lists, strings and generators, got 4.\n"
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use std::io::{self, IsTerminal, Read, Write};
+
+/// Returns the byte offset where the word being completed starts and the candidates for it
+pub type Completer<'a> = dyn Fn(&str) -> (usize, Vec<String>) + 'a;
+
+const CTRL_D: u8 = 0x04;
+const BACKSPACE: u8 = 0x08;
+const TAB: u8 = b'\t';
+const ESCAPE: u8 = 0x1b;
+const DELETE: u8 = 0x7f;
+
+/// Minimal line editor for the prompt: backspace, history (up/down) and tab completion.
+/// The terminal stays in raw mode from the first line read until the editor is dropped.
+/// It falls back to a plain buffered read when stdin is not a terminal.
+#[derive(Default)]
+pub struct LineEditor {
+    history: Vec<String>,
+    raw_mode: Option<RawMode>,
+}
+
+/// Puts the terminal in non-canonical mode without echo and restores it when dropped.
+/// Ctrl-C still interrupts the process, the terminal is restored before it ends.
+#[cfg(unix)]
+struct RawMode {
+    saved: libc::termios,
+}
+
+/// Settings of the terminal before raw mode, for the SIGINT handler
+#[cfg(unix)]
+static SAVED_TERMINAL: std::sync::OnceLock<libc::termios> = std::sync::OnceLock::new();
+
+#[cfg(unix)]
+impl RawMode {
+    fn enable() -> Option<RawMode> {
+        // SAFETY: `termios` is plain data filled by `tcgetattr`, stdin is a terminal
+        unsafe {
+            let mut saved: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut saved) != 0 {
+                return None;
+            }
+            let mut raw = saved;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            SAVED_TERMINAL.get_or_init(|| saved);
+            let handler = restore_and_interrupt as extern "C" fn(libc::c_int);
+            libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                libc::signal(libc::SIGINT, libc::SIG_DFL);
+                return None;
+            }
+            Some(RawMode { saved })
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // SAFETY: restores the settings read by `tcgetattr` in `enable`
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.saved);
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+        }
+    }
+}
+
+/// SIGINT handler, only calls async-signal-safe functions
+#[cfg(unix)]
+extern "C" fn restore_and_interrupt(signal: libc::c_int) {
+    // SAFETY: the default action of the signal ends the process as if there were no handler
+    unsafe {
+        if let Some(saved) = SAVED_TERMINAL.get() {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, saved);
+        }
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
+
+/// Without termios there is no raw mode, lines are read buffered
+#[cfg(not(unix))]
+struct RawMode;
+
+#[cfg(not(unix))]
+impl RawMode {
+    fn enable() -> Option<RawMode> {
+        None
+    }
+}
+
+impl LineEditor {
+    pub fn new() -> LineEditor {
+        LineEditor::default()
+    }
+
+    /// Reads one line (without the trailing newline), `None` at the end of the input
+    pub fn read_line(&mut self, prompt: &str, completer: &Completer) -> Option<String> {
+        print!("{prompt}");
+        let _ = io::stdout().flush();
+        if self.raw_mode.is_none() && io::stdin().is_terminal() {
+            self.raw_mode = RawMode::enable();
+        }
+        let line = match self.raw_mode {
+            Some(_) => self.read_raw_line(prompt, completer),
+            None => read_buffered_line(),
+        }?;
+        if !line.trim().is_empty() {
+            self.history.push(line.clone());
+        }
+        Some(line)
+    }
+
+    fn read_raw_line(&mut self, prompt: &str, completer: &Completer) -> Option<String> {
+        let mut line = String::new();
+        let mut history_index = self.history.len();
+        let mut stdout = io::stdout();
+        loop {
+            let byte = read_byte()?;
+            match byte {
+                b'\n' | b'\r' => {
+                    println!();
+                    return Some(line);
+                }
+                CTRL_D if line.is_empty() => {
+                    println!();
+                    return None;
+                }
+                BACKSPACE | DELETE => {
+                    if line.pop().is_some() {
+                        print!("\x08 \x08");
+                    }
+                }
+                TAB => {
+                    let (start, candidates) = completer(&line);
+                    let completed = complete_word(&line[start..], &candidates);
+                    if completed.len() > line.len() - start {
+                        print!("{}", &completed[line.len() - start..]);
+                        line.replace_range(start.., &completed);
+                    } else if candidates.len() > 1 {
+                        println!();
+                        println!("{}", candidates.join("  "));
+                        print!("{prompt}{line}");
+                    }
+                }
+                ESCAPE => {
+                    // Arrow keys arrive as `ESC [ A` (up) and `ESC [ B` (down)
+                    if read_byte()? != b'[' {
+                        continue;
+                    }
+                    let entry = match read_byte()? {
+                        b'A' if history_index > 0 => {
+                            history_index -= 1;
+                            self.history[history_index].clone()
+                        }
+                        b'B' if history_index < self.history.len() => {
+                            history_index += 1;
+                            self.history.get(history_index).cloned().unwrap_or_default()
+                        }
+                        _ => continue,
+                    };
+                    // Erase the current line before showing the history entry
+                    print!("\r\x1b[K{prompt}{entry}");
+                    line = entry;
+                }
+                byte if byte.is_ascii_control() => {}
+                byte => {
+                    let character = read_utf8_char(byte)?;
+                    line.push(character);
+                    print!("{character}");
+                }
+            }
+            let _ = stdout.flush();
+        }
+    }
+}
+
+/// Longest completion of `word` shared by every candidate
+fn complete_word(word: &str, candidates: &[String]) -> String {
+    let Some(first) = candidates.first() else {
+        return word.to_string();
+    };
+    let mut common = first.clone();
+    for candidate in &candidates[1..] {
+        let shared = common
+            .chars()
+            .zip(candidate.chars())
+            .take_while(|(left, right)| left == right)
+            .map(|(character, _)| character.len_utf8())
+            .sum();
+        common.truncate(shared);
+    }
+    match common.len() > word.len() {
+        true => common,
+        false => word.to_string(),
+    }
+}
+
+fn read_buffered_line() -> Option<String> {
+    let mut line = String::new();
+    match io::stdin().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim_end_matches(['\n', '\r']).to_string()),
+    }
+}
+
+fn read_byte() -> Option<u8> {
+    let mut buffer = [0u8; 1];
+    match io::stdin().read(&mut buffer) {
+        Ok(1) => Some(buffer[0]),
+        _ => None,
+    }
+}
+
+/// Reads the continuation bytes of a multi-byte character
+fn read_utf8_char(first: u8) -> Option<char> {
+    let length = match first {
+        0xf0..=0xff => 4,
+        0xe0..=0xef => 3,
+        0xc0..=0xdf => 2,
+        _ => 1,
+    };
+    let mut bytes = vec![first];
+    for _ in 1..length {
+        bytes.push(read_byte()?);
+    }
+    Some(
+        String::from_utf8_lossy(&bytes)
+            .chars()
+            .next()
+            .unwrap_or('?'),
+    )
+}
+
+#[cfg(test)]
+mod line_editor_tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_word_with_common_prefix() {
+        // Arrange
+        let candidates = vec!["counter".to_string(), "count".to_string()];
+        // Act
+        let completed = complete_word("co", &candidates);
+        // Assert
+        assert_eq!(completed, "count");
+    }
+
+    #[test]
+    fn test_complete_word_without_candidates() {
+        // Act
+        let completed = complete_word("xyz", &[]);
+        // Assert
+        assert_eq!(completed, "xyz");
+    }
+}
@@ -0,0 +1,432 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    error_codes::{ErrorCode, Message, W0601, W0602, W0603, W0604, W0605, W0606},
+    expr::*,
+    object::Object,
+    stmt::*,
+    token::{Token, TokenType},
+    walker::Walker,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    UnusedVariable,
+    UnusedParameter,
+    Shadowing,
+    EmptyBlock,
+    ConstantCondition,
+    TooManyParameters,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Rule::UnusedVariable,
+        Rule::UnusedParameter,
+        Rule::Shadowing,
+        Rule::EmptyBlock,
+        Rule::ConstantCondition,
+        Rule::TooManyParameters,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::UnusedVariable => "unused-variable",
+            Rule::UnusedParameter => "unused-parameter",
+            Rule::Shadowing => "shadowing",
+            Rule::EmptyBlock => "empty-block",
+            Rule::ConstantCondition => "constant-condition",
+            Rule::TooManyParameters => "too-many-parameters",
+        }
+    }
+
+    /// Stable code of the findings, `rlox --explain W0604` describes the rule
+    pub fn code(&self) -> &'static ErrorCode {
+        match self {
+            Rule::UnusedVariable => &W0601,
+            Rule::UnusedParameter => &W0602,
+            Rule::Shadowing => &W0603,
+            Rule::EmptyBlock => &W0604,
+            Rule::ConstantCondition => &W0605,
+            Rule::TooManyParameters => &W0606,
+        }
+    }
+
+    /// The rule with this name or this code, in any case
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL
+            .into_iter()
+            .find(|rule| rule.name() == name || rule.code().code.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Which rules run and the limits they check against
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintConfig {
+    pub disabled: Vec<Rule>,
+    pub max_params: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            disabled: Vec::new(),
+            max_params: 5,
+        }
+    }
+}
+
+impl LintConfig {
+    pub fn is_enabled(&self, rule: Rule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+}
+
+#[derive(Debug)]
+pub struct Finding {
+    pub rule: Rule,
+    pub token: Token,
+    pub message: Message,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[Line {}] - Warning[{}] at '{}': {} [{}]",
+            self.token.line,
+            self.rule.code().code,
+            self.token.lexeme,
+            self.message,
+            self.rule.name()
+        )
+    }
+}
+
+struct Binding {
+    token: Token,
+    is_param: bool,
+    is_used: bool,
+}
+
+/// Static checks over the parsed statements. Unlike the resolver it never stops at the
+/// first problem and reports nothing by itself: every finding is returned to the caller.
+pub struct Linter {
+    config: LintConfig,
+    scopes: Vec<HashMap<String, Binding>>,
+    findings: Vec<Finding>,
+}
+
+impl Linter {
+    pub fn new(config: LintConfig) -> Linter {
+        Linter {
+            config,
+            scopes: Vec::new(),
+            findings: Vec::new(),
+        }
+    }
+
+    pub fn lint(mut self, statements: &[Stmt]) -> Vec<Finding> {
+        // Globals are only tracked to detect shadowing, they may be used from anywhere
+        self.begin_scope();
+        self.lint_stmts(statements);
+        self.scopes.pop();
+        self.findings.sort_by_key(|finding| finding.token.line);
+        self.findings
+    }
+
+    fn lint_stmts(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            statement.accept(self);
+        }
+    }
+
+    fn lint_expr(&mut self, expression: &Expr) {
+        self.walk_expr(expression)
+    }
+
+    fn report(&mut self, rule: Rule, token: &Token, message: Message) {
+        if self.config.is_enabled(rule) {
+            self.findings.push(Finding {
+                rule,
+                token: token.clone(),
+                message,
+            });
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        let mut unused: Vec<Binding> = scope.into_values().filter(|b| !b.is_used).collect();
+        unused
+            .sort_by(|a, b| (a.token.line, &a.token.lexeme).cmp(&(b.token.line, &b.token.lexeme)));
+        for binding in unused {
+            let rule = match binding.is_param {
+                true => Rule::UnusedParameter,
+                false => Rule::UnusedVariable,
+            };
+            self.report(rule, &binding.token, rule.code().with([]));
+        }
+    }
+
+    fn declare(&mut self, name: &Token, is_param: bool) {
+        let shadows = self
+            .scopes
+            .iter()
+            .rev()
+            .skip(1)
+            .any(|scope| scope.contains_key(&name.lexeme));
+        if shadows {
+            self.report(Rule::Shadowing, name, W0603.with([]));
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(
+                name.lexeme(),
+                Binding {
+                    token: name.clone(),
+                    is_param,
+                    is_used: false,
+                },
+            );
+        }
+    }
+
+    fn mark_used(&mut self, name: &Token) {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(binding) = scope.get_mut(&name.lexeme) {
+                binding.is_used = true;
+                return;
+            }
+        }
+    }
+
+    fn lint_function(&mut self, function: &FunctionStmt) {
+        if function.params.len() > self.config.max_params {
+            let message = W0606.with([
+                function.params.len().to_string().into(),
+                self.config.max_params.to_string().into(),
+            ]);
+            self.report(Rule::TooManyParameters, &function.name, message);
+        }
+        self.begin_scope();
+        for param in &function.params {
+            self.declare(param, true);
+        }
+        self.lint_stmts(&function.body);
+        self.end_scope();
+    }
+
+    /// `while (true)` is the usual way to write an endless loop, so loops only get a
+    /// finding for the other literals
+    fn check_condition(&mut self, condition: &Expr, token: &Token, is_loop: bool) {
+        let mut condition = condition;
+        while let Expr::Grouping(grouping) = condition {
+            condition = &grouping.expression;
+        }
+        if let Expr::Literal(literal) = condition {
+            if !(is_loop && literal.value == Object::Bool(true)) {
+                self.report(Rule::ConstantCondition, token, W0605.with([]));
+            }
+        }
+    }
+}
+
+impl StmtVisitor<()> for Linter {
+    /// Every block is checked, the body of a statement or a `{}` on its own
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) {
+        if stmt.statements.is_empty() {
+            let brace = Token::new(
+                TokenType::LeftBrace,
+                "{".to_string(),
+                Object::Nil,
+                stmt.span.line,
+            )
+            .at_column(stmt.span.column);
+            self.report(Rule::EmptyBlock, &brace, W0604.with([]));
+        }
+        self.begin_scope();
+        self.lint_stmts(&stmt.statements);
+        self.end_scope();
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &ClassStmt) {
+        self.declare(&stmt.name, false);
+        if let Some(super_class) = &stmt.super_class {
+            self.lint_expr(super_class);
+        }
+        for method in &stmt.methods {
+            if let Stmt::Function(method) = method {
+                self.lint_function(method);
+            }
+        }
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) {
+        self.lint_expr(&stmt.expression);
+    }
+
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) {
+        self.begin_scope();
+        if let Some(initializer) = &stmt.initializer {
+            initializer.accept(self);
+        }
+        if let Some(condition) = &stmt.condition {
+            self.check_condition(condition, &stmt.keyword, true);
+            self.lint_expr(condition);
+        }
+        if let Some(increment) = &stmt.increment {
+            self.lint_expr(increment);
+        }
+        stmt.body.accept(self);
+        self.end_scope();
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &FunctionStmt) {
+        self.declare(&stmt.name, false);
+        self.lint_function(stmt);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) {
+        self.check_condition(&stmt.condition, &stmt.keyword, false);
+        self.lint_expr(&stmt.condition);
+        stmt.then_branch.accept(self);
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) {
+        self.lint_expr(&stmt.expression);
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) {
+        if let Some(value) = &stmt.value {
+            self.lint_expr(value);
+        }
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) {
+        if let Some(initializer) = &stmt.initializer {
+            self.lint_expr(initializer);
+        }
+        self.declare(&stmt.name, false);
+    }
+
+    fn visit_for_in_stmt(&mut self, stmt: &ForInStmt) {
+        self.lint_expr(&stmt.iterable);
+        self.begin_scope();
+        self.declare(&stmt.name, false);
+        stmt.body.accept(self);
+        self.end_scope();
+    }
+
+    fn visit_yield_stmt(&mut self, stmt: &YieldStmt) {
+        if let Some(value) = &stmt.value {
+            self.lint_expr(value);
+        }
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) {
+        self.check_condition(&stmt.condition, &stmt.keyword, true);
+        self.lint_expr(&stmt.condition);
+        stmt.body.accept(self);
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &BreakStmt) {}
+
+    fn visit_continue_stmt(&mut self, _stmt: &ContinueStmt) {}
+
+    fn visit_debugger_stmt(&mut self, _stmt: &DebuggerStmt) {}
+}
+
+/// Expressions only matter for the variables they use
+impl Walker for Linter {
+    fn walk_variable_expr(&mut self, expr: &VariableExpr) {
+        self.mark_used(&expr.name);
+    }
+}
+
+#[cfg(test)]
+mod linter_tests {
+    use crate::{parser::Parser, scanner::Scanner};
+
+    use super::*;
+
+    fn lint_source(source: &str, config: LintConfig) -> Vec<(Rule, usize)> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens();
+        let statements = Parser::new(tokens).parse();
+        Linter::new(config)
+            .lint(&statements)
+            .into_iter()
+            .map(|finding| (finding.rule, finding.token.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_reports_every_rule() {
+        // Arrange
+        let source = "var a = 1;
+fun f(x, y) { var unused; var a = y; return a; }
+if (true) print a;
+while (a) {}
+fun g(a1, a2, a3, a4, a5, a6) {}
+{ {} }";
+        // Act
+        let findings = lint_source(source, LintConfig::default());
+        // Assert
+        assert_eq!(
+            findings,
+            vec![
+                (Rule::Shadowing, 2),
+                (Rule::UnusedVariable, 2),
+                (Rule::UnusedParameter, 2),
+                (Rule::ConstantCondition, 3),
+                (Rule::EmptyBlock, 4),
+                (Rule::TooManyParameters, 5),
+                (Rule::UnusedParameter, 5),
+                (Rule::UnusedParameter, 5),
+                (Rule::UnusedParameter, 5),
+                (Rule::UnusedParameter, 5),
+                (Rule::UnusedParameter, 5),
+                (Rule::UnusedParameter, 5),
+                (Rule::EmptyBlock, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_finding_shows_the_rule_code() {
+        // Arrange
+        let tokens = Scanner::new("print 1;\n  {}".to_string()).scan_tokens();
+        let statements = Parser::new(tokens).parse();
+        // Act
+        let findings = Linter::new(LintConfig::default()).lint(&statements);
+        // Assert
+        assert_eq!(
+            findings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec!["[Line 2] - Warning[W0604] at '{': Block is empty. [empty-block]"]
+        );
+        assert_eq!(findings[0].token.column, 2);
+        assert_eq!(Rule::from_name("w0604"), Some(Rule::EmptyBlock));
+    }
+
+    #[test]
+    fn test_lint_with_disabled_rules() {
+        // Arrange
+        let source = "fun f(x) { if (x) {} while (true) { print x; } }";
+        let config = LintConfig {
+            disabled: vec![Rule::EmptyBlock],
+            ..LintConfig::default()
+        };
+        // Act
+        let findings = lint_source(source, config);
+        // Assert
+        assert!(findings.is_empty());
+    }
+}
@@ -0,0 +1,627 @@
+//! Diagnostics in other languages than English, chosen with `--lang=es` or the `LOX_LANG` and
+//! `LANG` environment variables. Messages are looked up by their code in `error_codes`, the
+//! arguments standing for the `{}` of the English template are put into the translated one,
+//! names and values as they are and the phrases of the interpreter (`Argument::Term`) from
+//! `SPANISH_TERMS`. Text given by scripts, like the one of `error()`, is never translated.
+use std::{
+    cell::Cell,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use crate::error_codes::ErrorCode;
+
+/// Language of every thread, set once by `set_language`
+static LANGUAGE: AtomicU8 = AtomicU8::new(Language::English as u8);
+
+thread_local! {
+    /// Language set by `with_language` for this thread only
+    static OVERRIDE: Cell<Option<Language>> = const { Cell::new(None) };
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Language {
+    #[default]
+    English,
+    Spanish,
+}
+
+const LANGUAGES: [Language; 2] = [Language::English, Language::Spanish];
+
+/// Words printed around the messages, as in `[Line 3] - Error at 'x': ...`
+pub struct Words {
+    pub line: &'static str,
+    pub error: &'static str,
+    pub warning: &'static str,
+    pub at: &'static str,
+    pub at_end: &'static str,
+}
+
+impl Language {
+    /// The language of a code like `es`, or of a locale like `es_ES.UTF-8`
+    pub fn from_name(name: &str) -> Option<Language> {
+        let code = name.split(['_', '-', '.']).next().unwrap_or_default();
+        match code.to_ascii_lowercase().as_str() {
+            "en" => Some(Language::English),
+            "es" => Some(Language::Spanish),
+            _ => None,
+        }
+    }
+
+    /// The language of `LOX_LANG`, or else of `LANG`, `None` for one without translations
+    pub fn from_env() -> Option<Language> {
+        ["LOX_LANG", "LANG"]
+            .into_iter()
+            .find_map(|name| std::env::var(name).ok())
+            .and_then(|name| Language::from_name(&name))
+    }
+
+    pub fn current() -> Language {
+        OVERRIDE.with(Cell::get).unwrap_or_else(|| {
+            let language = LANGUAGE.load(Ordering::Relaxed);
+            LANGUAGES[usize::from(language)]
+        })
+    }
+
+    pub fn words(&self) -> Words {
+        match self {
+            Language::English => Words {
+                line: "Line",
+                error: "Error",
+                warning: "Warning",
+                at: "at",
+                at_end: "at end",
+            },
+            Language::Spanish => Words {
+                line: "Línea",
+                error: "Error",
+                warning: "Aviso",
+                at: "en",
+                at_end: "al final",
+            },
+        }
+    }
+
+    /// A phrase of the interpreter standing for a `{}` of a message, in this language
+    pub fn term(&self, phrase: &'static str) -> &'static str {
+        let catalog = match self {
+            Language::English => return phrase,
+            Language::Spanish => SPANISH_TERMS,
+        };
+        catalog
+            .iter()
+            .find(|(english, _)| *english == phrase)
+            .map_or(phrase, |(_, translation)| translation)
+    }
+
+    /// Appends the phrase to a message in this language, in Spanish `de el` contracts to `del`
+    pub fn push_term(&self, text: &mut String, phrase: &'static str) {
+        let term = self.term(phrase);
+        match (self, term.strip_prefix("el ")) {
+            (Language::Spanish, Some(rest)) if text.ends_with(" de ") => {
+                text.truncate(text.len() - 1);
+                text.push_str("l ");
+                text.push_str(rest);
+            }
+            _ => text.push_str(term),
+        }
+    }
+
+    /// Template of the code's message in this language, the English one without a translation
+    pub fn template(&self, error_code: &ErrorCode) -> &'static str {
+        let catalog = match self {
+            Language::English => return error_code.message,
+            Language::Spanish => SPANISH,
+        };
+        catalog
+            .iter()
+            .find(|(code, _)| *code == error_code.code)
+            .map_or(error_code.message, |(_, template)| template)
+    }
+}
+
+/// Reports diagnostics in the language from now on, on every thread
+pub fn set_language(language: Language) {
+    LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+/// Runs `f` with diagnostics of this thread in the language
+pub fn with_language<T>(language: Language, f: impl FnOnce() -> T) -> T {
+    let previous = OVERRIDE.with(|current| current.replace(Some(language)));
+    let result = f();
+    OVERRIDE.with(|current| current.set(previous));
+    result
+}
+
+/// Messages in Spanish, with the `{}` in the order of the English ones
+const SPANISH: &[(&str, &str)] = &[
+    ("L0001", "Cadena sin terminar."),
+    ("L0002", "Comentario de bloque sin terminar."),
+    ("L0003", "Carácter inesperado. -> '{}'"),
+    (
+        "L0004",
+        "Faltan los dígitos del exponente en el número '{}'.",
+    ),
+    ("L0005", "Faltan dígitos en el número {} '{}'."),
+    ("L0006", "Dígito '{}' no válido en el número {} '{}'."),
+    ("L0007", "Separador '_' mal colocado en el número '{}'."),
+    (
+        "L0008",
+        "Se necesita la característica 'bigint' para el número '{}'.",
+    ),
+    ("P0101", "Se esperaba una expresión."),
+    ("P0102", "Se esperaba ')' después de {}."),
+    ("P0103", "Se esperaba ';' después de {}."),
+    ("P0104", "Se esperaba '(' después de {}."),
+    (
+        "P0105",
+        "Se esperaba '}' para cerrar {} abierto en la línea {}.",
+    ),
+    ("P0106", "Se esperaba '{' antes del cuerpo de {}."),
+    ("P0107", "Se esperaba el nombre de {}."),
+    (
+        "P0108",
+        "Se esperaba el nombre de una propiedad después de '.'.",
+    ),
+    ("P0109", "Se esperaba '.' después de super."),
+    (
+        "P0110",
+        "Se esperaba el nombre de un método de la superclase.",
+    ),
+    ("P0111", "Destino de asignación no válido."),
+    ("P0112", "El límite de {} es 255."),
+    (
+        "P0113",
+        "Se esperaba ':' después de la primera rama del operador ternario.",
+    ),
+    (
+        "P0114",
+        "Se esperaba 'while' o 'for' después de la etiqueta del bucle.",
+    ),
+    ("P0115", "Se esperaba el final de la expresión."),
+    ("P0116", "{}: anidamiento demasiado profundo."),
+    ("R0201", "La variable se declara pero nunca se usa."),
+    (
+        "R0202",
+        "Ya hay una variable con este nombre en este ámbito.",
+    ),
+    (
+        "R0203",
+        "No se puede leer una variable local en su propio inicializador.",
+    ),
+    (
+        "R0204",
+        "No se puede retornar desde el código de nivel superior.",
+    ),
+    (
+        "R0205",
+        "No se puede retornar un valor desde un inicializador.",
+    ),
+    ("R0206", "No se puede retornar un valor desde un generador."),
+    ("R0207", "No se puede usar 'this' fuera de una clase"),
+    ("R0208", "No se puede usar 'super' fuera de una clase."),
+    (
+        "R0209",
+        "No se puede usar 'super' en una clase sin superclase.",
+    ),
+    ("R0210", "Una clase no puede heredar de sí misma."),
+    ("R0211", "'{}' solo se puede usar dentro de bucles."),
+    (
+        "R0212",
+        "Ningún bucle que lo contenga tiene la etiqueta '{}'.",
+    ),
+    ("R0213", "Un bucle con esta etiqueta ya contiene a este."),
+    (
+        "R0214",
+        "No se puede usar 'yield' en el código de nivel superior.",
+    ),
+    ("R0215", "No se puede usar 'yield' en un inicializador."),
+    (
+        "R0216",
+        "No se puede volver a declarar el módulo nativo '{}'.",
+    ),
+    ("R0217", "No se puede asignar al módulo nativo '{}'."),
+    ("I0301", "El operando debe ser un número."),
+    ("I0302", "Los operandos de '+' deben ser cadenas o números."),
+    (
+        "I0303",
+        "Los operandos de '{}' deben ser dos números o dos cadenas.",
+    ),
+    ("I0304", "La variable '{}' no está definida."),
+    ("I0305", "La propiedad '{}' no está definida."),
+    ("I0306", "Solo se pueden llamar funciones y clases."),
+    ("I0307", "Solo las instancias tienen propiedades."),
+    ("I0308", "Solo las instancias tienen campos."),
+    ("I0309", "Se esperaban {} argumentos pero se recibieron {}."),
+    (
+        "I0310",
+        "Se esperaban al menos {} argumentos pero se recibieron {}.",
+    ),
+    (
+        "I0311",
+        "Se esperaban {} argumentos en el inicializador de la clase pero se recibieron {}.",
+    ),
+    ("I0312", "La superclase debe ser una clase, se obtuvo {}."),
+    (
+        "I0313",
+        "Expresión no válida. No se permite dividir entre cero.",
+    ),
+    (
+        "I0314",
+        "Expresión no válida. Cero dividido entre cero no está definido.",
+    ),
+    ("I0315", "Los operandos de '{}' deben ser números."),
+    (
+        "I0316",
+        "Los operandos de '*' deben ser números, o una cadena y un número.",
+    ),
+    (
+        "I0317",
+        "Los operandos de '*' deben ser una cadena y un entero no negativo, se obtuvo {}.",
+    ),
+    (
+        "I0318",
+        "Los operandos de '+' deben ser dos números o dos cadenas en modo estricto.",
+    ),
+    (
+        "I0319",
+        "La condición de '{}' debe ser un booleano en modo estricto, se obtuvo {}.",
+    ),
+    (
+        "I0320",
+        "Solo se puede iterar sobre listas, cadenas y generadores, se obtuvo {}.",
+    ),
+    (
+        "I0321",
+        "Solo se puede usar 'yield' dentro de un generador.",
+    ),
+    ("I0322", "El generador '{}' ya se está ejecutando."),
+    ("I0323", "El miembro '{}' del módulo '{}' no está definido."),
+    (
+        "I0324",
+        "Una cadena de {} bytes supera el límite de {} bytes.",
+    ),
+    (
+        "I0325",
+        "Una lista de {} elementos supera el límite de {} elementos.",
+    ),
+    ("I0326", "La instancia supera el límite de {} campos."),
+    (
+        "I0327",
+        "La función nativa '{}' necesita la capacidad '{}', que no se concedió.",
+    ),
+    ("I0328", "La llamada a '{}' se canceló."),
+    (
+        "I0329",
+        "El valor devuelto por 'main' debe ser un entero entre 0 y 255, se obtuvo {}.",
+    ),
+    ("I0330", "Operación binaria no soportada."),
+    ("I0331", "Operador unario no soportado"),
+    ("I0332", "La ejecución superó el límite de tiempo de {} ms."),
+    (
+        "I0333",
+        "Una salida de {} bytes supera el límite de {} bytes.",
+    ),
+    ("I0334", "'main' no debe declarar parámetros, declara {}."),
+    (
+        "I0335",
+        "Las llamadas se anidan más allá del límite de {} llamadas.",
+    ),
+    ("T0401", "Tipo desconocido '{}'."),
+    (
+        "T0402",
+        "La variable '{}' se declara como {} pero se inicializa con {}.",
+    ),
+    (
+        "T0403",
+        "La variable '{}' se declara como {}, no se le puede asignar {}.",
+    ),
+    (
+        "T0404",
+        "El parámetro '{}' de '{}' espera {}, se obtuvo {}.",
+    ),
+    ("T0405", "'{}' devuelve {}, no puede devolver {}."),
+    ("T0406", "Se esperaban {} argumentos pero se recibieron {}."),
+    ("N0501", "El argumento de '{}' debe ser {}."),
+    ("N0502", "Los argumentos de '{}' deben ser {}."),
+    ("N0503", "El primer argumento de '{}' debe ser {}."),
+    ("N0504", "El segundo argumento de '{}' debe ser {}."),
+    (
+        "N0505",
+        "Los argumentos de la función nativa '{}' deben ser {}.",
+    ),
+    (
+        "N0506",
+        "El índice del argumento debe ser un entero no negativo.",
+    ),
+    ("N0507", "El índice debe ser un entero no negativo."),
+    (
+        "N0508",
+        "El índice {} está fuera del rango de una lista de {} elementos.",
+    ),
+    (
+        "N0509",
+        "El índice {} está fuera del rango de una cadena de {} caracteres.",
+    ),
+    (
+        "N0510",
+        "El rango {}..{} está fuera del rango de una lista de {} elementos.",
+    ),
+    ("N0511", "El rango {}..{} termina antes de empezar."),
+    ("N0512", "No se puede sacar un elemento de una lista vacía."),
+    ("N0513", "No se puede convertir {} en un entero."),
+    (
+        "N0514",
+        "El punto de código {} no es un valor escalar Unicode.",
+    ),
+    ("N0515", "El comparador de 'sort' debe devolver un número."),
+    (
+        "N0516",
+        "Los límites de 'randomInt' deben ser enteros, el menor primero.",
+    ),
+    ("N0517", "La semilla de 'randomSeed' debe ser un entero."),
+    (
+        "N0518",
+        "Las iteraciones de 'bench' deben ser un entero positivo.",
+    ),
+    (
+        "N0519",
+        "La duración de la pausa debe ser un número no negativo de milisegundos.",
+    ),
+    (
+        "N0520",
+        "El código de salida debe ser un entero entre 0 y 255.",
+    ),
+    (
+        "N0521",
+        "La plantilla de 'format' tiene {} marcadores pero recibió {} valores.",
+    ),
+    (
+        "N0522",
+        "'{}' sin pareja en la plantilla de 'format', usa '{}'.",
+    ),
+    ("N0523", "La aserción falló: {}"),
+    ("N0524", "Se esperaba {}, se obtuvo {}."),
+    ("N0525", "No se pudo leer el archivo '{}': {}."),
+    ("N0526", "No se pudo escribir el archivo '{}': {}."),
+    ("N0527", "No se pudo ejecutar '{}': {}."),
+    (
+        "N0528",
+        "La función nativa '{}' no está disponible en esta plataforma.",
+    ),
+    (
+        "N0529",
+        "La hora del sistema no está disponible en esta plataforma.",
+    ),
+    ("N0530", "No se puede descargar '{}', {}."),
+    ("N0531", "No se puede descargar '{}': {}."),
+    ("N0532", "Puerto '{}' no válido en '{}'."),
+    ("N0533", "Falta el host en '{}'."),
+    ("N0534", "No se puede conectar a '{}': {}."),
+    ("N0535", "Error de conexión en '{}': {}."),
+    ("N0536", "La función nativa '{}' falló: {}"),
+    (
+        "N0537",
+        "El segundo argumento de '{}' debe ser un entero de {} a {}, se obtuvo {}.",
+    ),
+    (
+        "N0538",
+        "Los argumentos de '{}' deben ser nil, booleanos, números o cadenas, se obtuvo {}.",
+    ),
+    (
+        "N0539",
+        "El rango {}..{} está fuera del rango de una cadena de {} caracteres.",
+    ),
+    ("N0540", "{} {} no está entre {} y {}."),
+    (
+        "N0541",
+        "Especificador de formato de hora desconocido '%{}'.",
+    ),
+    ("N0542", "El formato de hora termina con un '%' incompleto."),
+    ("W0601", "La variable se declara pero nunca se usa."),
+    ("W0602", "El parámetro nunca se usa."),
+    (
+        "W0603",
+        "La declaración oculta una variable de un ámbito exterior.",
+    ),
+    ("W0604", "El bloque está vacío."),
+    ("W0605", "La condición siempre es la misma."),
+    (
+        "W0606",
+        "La función tiene {} parámetros, más de los {} permitidos.",
+    ),
+];
+
+/// Phrases of the interpreter standing for a `{}`, with the article the Spanish templates expect
+const SPANISH_TERMS: &[(&str, &str)] = &[
+    ("hexadecimal", "hexadecimal"),
+    ("binary", "binario"),
+    ("Statement", "Sentencia"),
+    ("Expression", "Expresión"),
+    ("arguments", "los argumentos"),
+    ("parameters", "los parámetros"),
+    ("parameter", "el parámetro"),
+    ("condition", "la condición"),
+    ("expression", "la expresión"),
+    ("for clauses", "las cláusulas de for"),
+    ("for-in clause", "la cláusula de for-in"),
+    ("if condition", "la condición de if"),
+    ("loop condition", "la condición del bucle"),
+    ("variable declaration", "la declaración de variable"),
+    ("return value", "el valor de retorno"),
+    ("yield value", "el valor de yield"),
+    ("'print' value", "el valor de 'print'"),
+    ("value", "el valor"),
+    ("function name", "el nombre de la función"),
+    ("method name", "el nombre del método"),
+    ("function", "la función"),
+    ("method", "el método"),
+    ("class", "la clase"),
+    ("superclass", "la superclase"),
+    ("variable", "la variable"),
+    ("type", "el tipo"),
+    ("class body", "el cuerpo de la clase"),
+    ("block", "el bloque"),
+    ("Month", "Mes"),
+    ("Day", "Día"),
+    ("Hour", "Hora"),
+    ("Minute", "Minuto"),
+    ("Second", "Segundo"),
+    ("Millisecond", "Milisegundo"),
+    ("a string", "una cadena"),
+    ("strings", "cadenas"),
+    ("a number", "un número"),
+    ("numbers", "números"),
+    ("an integer", "un entero"),
+    ("integers", "enteros"),
+    ("a positive integer", "un entero positivo"),
+    ("a list", "una lista"),
+    ("a list or a string", "una lista o una cadena"),
+    ("a list and a string", "una lista y una cadena"),
+    (
+        "a list and a value, or two strings",
+        "una lista y un valor, o dos cadenas",
+    ),
+    ("a generator", "un generador"),
+    ("a function or an instance", "una función o una instancia"),
+    ("an instance or nil", "una instancia o nil"),
+    ("a path string", "una cadena con una ruta"),
+    ("a path and a string", "una ruta y una cadena"),
+    ("a command string", "una cadena con un comando"),
+    ("a URL string", "una cadena con una URL"),
+    (
+        "a timestamp in milliseconds",
+        "una marca de tiempo en milisegundos",
+    ),
+    (
+        "a timestamp in milliseconds and a format string",
+        "una marca de tiempo en milisegundos y una cadena de formato",
+    ),
+    (
+        "a host string and a port from 0 to 65535",
+        "una cadena con el host y un puerto de 0 a 65535",
+    ),
+    (
+        "a connection from 'tcpConnect'",
+        "una conexión de 'tcpConnect'",
+    ),
+    (
+        "https needs the 'tls' feature",
+        "https necesita la característica 'tls'",
+    ),
+    (
+        "the URL must start with 'http://' or 'https://'",
+        "la URL debe empezar por 'http://' o 'https://'",
+    ),
+    ("too many redirects", "demasiadas redirecciones"),
+];
+
+#[cfg(test)]
+mod locale_tests {
+    use super::*;
+    use crate::{error_codes::ERROR_CODES, interpreter::Interpreter, run_captured};
+
+    #[test]
+    fn test_every_code_is_translated() {
+        for error_code in ERROR_CODES {
+            // Act
+            let template = SPANISH.iter().find(|(code, _)| *code == error_code.code);
+            // Assert
+            let Some((_, template)) = template else {
+                panic!("{} has no Spanish message", error_code.code);
+            };
+            assert_eq!(
+                template.matches("{}").count(),
+                error_code.placeholders(),
+                "{}",
+                error_code.code
+            );
+        }
+        assert_eq!(SPANISH.len(), ERROR_CODES.len());
+    }
+
+    #[test]
+    fn test_every_term_is_translated() {
+        // Arrange
+        let sources = [
+            include_str!("parser.rs"),
+            include_str!("natives.rs"),
+            include_str!("http.rs"),
+            include_str!("ffi.rs"),
+        ];
+        let phrases = sources
+            .iter()
+            .flat_map(|source| source.split("term(\"").skip(1))
+            .filter_map(|rest| rest.split_once("\")").map(|(phrase, _)| phrase));
+        // Phrases given to `term` through a parameter
+        let passed = [
+            "hexadecimal",
+            "binary",
+            "Statement",
+            "Expression",
+            "function",
+            "method",
+            "class body",
+            "block",
+            "Month",
+            "Day",
+            "Hour",
+            "Minute",
+            "Second",
+            "Millisecond",
+        ];
+        for phrase in phrases.chain(passed) {
+            // Act
+            let translated = SPANISH_TERMS.iter().any(|(english, _)| *english == phrase);
+            // Assert
+            assert!(translated, "'{phrase}' has no Spanish translation");
+        }
+    }
+
+    #[test]
+    fn test_spanish_diagnostics() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let run = |source: &str, interpreter: &mut Interpreter| {
+            with_language(Language::Spanish, || {
+                run_captured(source, interpreter).diagnostics
+            })
+        };
+        // Act
+        let runtime = run("print undefined;", &mut interpreter);
+        let parser = run("var a = 1", &mut interpreter);
+        let warning = run("fun f() {\n  var unused = 1;\n}", &mut interpreter);
+        let parameter = run("fun f(1) {}", &mut interpreter);
+        let script = run("error(\"Undefined variable 'x'.\");", &mut interpreter);
+        // Assert
+        assert_eq!(
+            runtime,
+            "[Línea 1] - Error[I0304]: La variable 'undefined' no está definida.\n"
+        );
+        assert_eq!(
+            parser,
+            "[Línea 1] - Error[P0103] al final: Se esperaba ';' después de la declaración de variable.\n"
+        );
+        assert_eq!(
+            warning,
+            "[Línea 2] - Aviso[R0201]: 'unused': La variable se declara pero nunca se usa.\n"
+        );
+        assert_eq!(
+            parameter,
+            "[Línea 1] - Error[P0107] en '1': Se esperaba el nombre del parámetro.\n"
+        );
+        assert_eq!(script, "[Línea 1] - Error: Undefined variable 'x'.\n");
+        assert_eq!(Language::current(), Language::English);
+    }
+
+    #[test]
+    fn test_language_from_name() {
+        // Act
+        let locale = Language::from_name("es_ES.UTF-8");
+        let code = Language::from_name("EN");
+        let unknown = Language::from_name("C");
+        // Assert
+        assert_eq!(locale, Some(Language::Spanish));
+        assert_eq!(code, Some(Language::English));
+        assert_eq!(unknown, None);
+    }
+}
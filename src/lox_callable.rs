@@ -6,6 +6,7 @@ pub trait LoxCallable {
         &mut self,
         interpreter: &mut Interpreter,
         arguments: Vec<Object>,
+        current_token: &Token,
     ) -> Result<Object, LoxErrorResult>;
     fn check_arity(
         &self,
@@ -4,7 +4,7 @@ use std::{
 };
 
 use crate::{
-    error::LoxErrorResult, interpreter::Interpreter, lox_callable::LoxCallable,
+    error::LoxErrorResult, error_codes::I0311, interpreter::Interpreter, lox_callable::LoxCallable,
     lox_function::LoxFunction, lox_instance::LoxInstance, object::Object, token::Token,
 };
 
@@ -27,6 +27,15 @@ impl LoxClass {
             methods,
         }
     }
+    /// Names of the methods of this class and the inherited ones
+    pub fn method_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.methods.keys().cloned().collect();
+        if let Some(class) = &self.super_class {
+            names.extend(class.method_names());
+        }
+        names
+    }
+
     pub fn find_method(&self, name: &str) -> Option<LoxFunction> {
         if let Some(method) = self.methods.get(name) {
             Some(method.clone())
@@ -66,12 +75,13 @@ impl LoxCallable for LoxClass {
         &mut self,
         interpreter: &mut Interpreter,
         arguments: Vec<Object>,
+        current_token: &Token,
     ) -> Result<Object, LoxErrorResult> {
         let instance = LoxInstance::new(self.clone());
         if let Some(initializer) = self.find_method("init") {
             initializer
                 .bind(instance.clone())
-                .call(interpreter, arguments)?;
+                .call(interpreter, arguments, current_token)?;
         }
         Ok(Object::ClassInstance(instance))
     }
@@ -80,11 +90,7 @@ impl LoxCallable for LoxClass {
         if args_len != self.arity() {
             return Err(LoxErrorResult::interpreter_error(
                 current_token.line,
-                &format!(
-                    "Expected {} arguments in class initializer but got {}.",
-                    self.arity(),
-                    args_len
-                ),
+                I0311.with([self.arity().to_string().into(), args_len.to_string().into()]),
             ));
         }
         Ok(())
@@ -0,0 +1,55 @@
+use std::{
+    any::Any,
+    fmt::{self, Debug, Display, Formatter},
+};
+
+use crate::shared::{MaybeSync, Rc, RefCell};
+
+/// Host value handed to scripts by natives, such as a socket. Scripts can only pass it
+/// around; the natives that made it get the value back with `borrow_mut`.
+pub struct LoxForeign {
+    /// Shown by `print`, `<foreign TcpConnection>`
+    pub type_name: &'static str,
+    value: RefCell<Box<dyn ForeignValue>>,
+}
+
+/// Value a `LoxForeign` can hold, `Send + Sync` with the `sync` feature
+pub trait ForeignValue: Any + MaybeSync {
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any + MaybeSync> ForeignValue for T {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub type LoxForeignRef = Rc<LoxForeign>;
+
+impl LoxForeign {
+    pub fn new<T: ForeignValue>(type_name: &'static str, value: T) -> LoxForeignRef {
+        Rc::new(LoxForeign {
+            type_name,
+            value: RefCell::new(Box::new(value)),
+        })
+    }
+
+    /// Runs `f` on the value, `None` if it isn't a `T` or is already borrowed
+    pub fn borrow_mut<T: Any, R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let mut value = self.value.try_borrow_mut().ok()?;
+        // `**` reaches the held value, the `Box` is a `ForeignValue` too
+        (**value).as_any_mut().downcast_mut::<T>().map(f)
+    }
+}
+
+impl Debug for LoxForeign {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "LoxForeign({})", self.type_name)
+    }
+}
+
+impl Display for LoxForeign {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "<foreign {}>", self.type_name)
+    }
+}
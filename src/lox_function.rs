@@ -1,9 +1,19 @@
 use std::fmt::{self, Display, Formatter};
-use std::rc::Rc;
 
+use crate::closure_compiler::{run_block, CompiledBlock};
+use crate::lox_generator::{contains_yield, LoxGenerator};
 use crate::lox_instance::*;
 use crate::{
-    environment::*, error::*, interpreter::*, lox_callable::*, object::*, stmt::*, token::*,
+    debug_log::{self, DebugCategory},
+    environment::*,
+    error::*,
+    error_codes::I0309,
+    interpreter::*,
+    lox_callable::*,
+    object::*,
+    shared::Rc,
+    stmt::*,
+    token::*,
 };
 
 #[derive(Debug, Clone)]
@@ -11,6 +21,10 @@ pub struct LoxFunction {
     declaration: Box<FunctionStmt>,
     closure: EnvironmentRef,
     is_initializer: bool,
+    /// The body contains `yield`, calls return a generator running it
+    is_generator: bool,
+    /// Body compiled by `ClosureCompiler`, run instead of walking the declaration
+    compiled: Option<Rc<CompiledBlock>>,
 }
 
 impl LoxFunction {
@@ -19,13 +33,35 @@ impl LoxFunction {
         closure: EnvironmentRef,
         is_initializer: bool,
     ) -> LoxFunction {
+        debug_log::log(DebugCategory::Env, || {
+            format!(
+                "fun {} captures env #{}",
+                declaration.name.lexeme,
+                closure.borrow().id()
+            )
+        });
         LoxFunction {
             declaration: Box::new(declaration.clone()),
             closure,
             is_initializer,
+            is_generator: contains_yield(&declaration.body),
+            compiled: None,
         }
     }
 
+    /// Same function, running the compiled `body` of its declaration
+    pub fn with_compiled_body(self, body: Rc<CompiledBlock>) -> LoxFunction {
+        LoxFunction {
+            compiled: Some(body),
+            ..self
+        }
+    }
+
+    /// Scopes the function captured, innermost first, see `Environment::captured_scopes`
+    pub fn captured_scopes(&self) -> Vec<Vec<(String, Object)>> {
+        self.closure.borrow().captured_scopes()
+    }
+
     pub fn bind(&self, instance: LoxInstanceRef) -> LoxFunction {
         let environment = Environment::new_enclosing(self.closure.clone());
 
@@ -37,6 +73,8 @@ impl LoxFunction {
             declaration: self.declaration.clone(),
             closure: environment,
             is_initializer: self.is_initializer,
+            is_generator: self.is_generator,
+            compiled: self.compiled.clone(),
         }
     }
 }
@@ -46,17 +84,85 @@ impl LoxCallable for LoxFunction {
         &mut self,
         interpreter: &mut Interpreter,
         arguments: Vec<Object>,
+        current_token: &Token,
+    ) -> Result<Object, LoxErrorResult> {
+        interpreter.enter_call(current_token.line)?;
+        let mut name = self.declaration.name.lexeme.clone();
+        interpreter.function_enter(&name);
+        let mut result = self.execute_body(interpreter, arguments);
+        // Calls in tail position run here one after the other instead of nesting on the host stack
+        while let Err(LoxErrorResult::ControlFlowTailCall {
+            function,
+            arguments,
+        }) = result
+        {
+            interpreter.function_exit(&name);
+            name = function.declaration.name.lexeme.clone();
+            interpreter.function_enter(&name);
+            result = function.execute_body(interpreter, arguments);
+        }
+        interpreter.function_exit(&name);
+        interpreter.exit_call();
+        result
+    }
+
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn check_arity(&self, args_len: usize, current_token: &Token) -> Result<(), LoxErrorResult> {
+        if args_len != self.arity() {
+            return Err(LoxErrorResult::interpreter_error(
+                current_token.line,
+                I0309.with([self.arity().to_string().into(), args_len.to_string().into()]),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl LoxFunction {
+    fn execute_body(
+        &self,
+        interpreter: &mut Interpreter,
+        mut arguments: Vec<Object>,
     ) -> Result<Object, LoxErrorResult> {
         let environment = Environment::new_enclosing(Rc::clone(&self.closure));
-        for (idx, param) in self.declaration.params.iter().enumerate() {
+        debug_log::log(DebugCategory::Calls, || {
+            let bindings: Vec<String> = self
+                .declaration
+                .params
+                .iter()
+                .zip(&arguments)
+                .map(|(param, argument)| format!("{} = {argument}", param.lexeme))
+                .collect();
+            format!(
+                "call {}({}) in env #{}",
+                self.declaration.name.lexeme,
+                bindings.join(", "),
+                environment.borrow().id()
+            )
+        });
+        for (param, argument) in self.declaration.params.iter().zip(arguments.drain(..)) {
             environment
                 .borrow_mut()
-                .define(param.lexeme.clone(), arguments[idx].clone());
+                .define(param.lexeme.clone(), argument);
+        }
+        interpreter.recycle_arguments(arguments);
+
+        if self.is_generator {
+            let name = &self.declaration.name.lexeme;
+            let generator = LoxGenerator::new(name, &self.declaration.body, environment);
+            return Ok(Object::Generator(Rc::new(generator)));
         }
 
         let this = Token::new(TokenType::This, "this".to_string(), Object::Nil, 0);
 
-        match interpreter.execute_block(&self.declaration.body, environment) {
+        let result = match &self.compiled {
+            Some(body) => run_block(interpreter, body, environment),
+            None => interpreter.execute_block(&self.declaration.body, environment),
+        };
+        match result {
             Ok(_) => {
                 if self.is_initializer {
                     self.closure.borrow().get_at(0, &this)?;
@@ -72,20 +178,6 @@ impl LoxCallable for LoxFunction {
             Err(err) => Err(err),
         }
     }
-
-    fn arity(&self) -> usize {
-        self.declaration.params.len()
-    }
-
-    fn check_arity(&self, args_len: usize, current_token: &Token) -> Result<(), LoxErrorResult> {
-        if args_len != self.arity() {
-            return Err(LoxErrorResult::interpreter_error(
-                current_token.line,
-                &format!("Expected {} arguments but got {}.", self.arity(), args_len),
-            ));
-        }
-        Ok(())
-    }
 }
 
 impl Display for LoxFunction {
@@ -0,0 +1,390 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::{
+    environment::*,
+    error::*,
+    error_codes::{I0320, I0322},
+    interpreter::Interpreter,
+    object::{LoxListRef, Object},
+    shared::{Rc, RefCell},
+    stmt::*,
+    token::Token,
+};
+
+pub type LoxGeneratorRef = Rc<LoxGenerator>;
+
+/// What a call to a function containing `yield` returns. The body runs up to the next
+/// `yield` on every `resume`, the statements left to run are kept as frames since the
+/// host stack of the call is gone by then.
+#[derive(Debug)]
+pub struct LoxGenerator {
+    name: String,
+    /// Innermost frame last, empty once the body finished
+    frames: RefCell<Vec<Frame>>,
+}
+
+#[derive(Debug)]
+enum Frame {
+    /// Statements of a block, of a branch or of a loop body, run one after the other
+    Statements {
+        statements: Rc<Vec<Stmt>>,
+        next: usize,
+        environment: EnvironmentRef,
+    },
+    While {
+        stmt: Rc<WhileStmt>,
+        body: Rc<Vec<Stmt>>,
+        environment: EnvironmentRef,
+    },
+    /// `environment` is the scope of the initializer, `started` once the body ran
+    For {
+        stmt: Rc<ForStmt>,
+        body: Rc<Vec<Stmt>>,
+        started: bool,
+        environment: EnvironmentRef,
+    },
+    ForIn {
+        stmt: Rc<ForInStmt>,
+        body: Rc<Vec<Stmt>>,
+        values: LoxIterator,
+        environment: EnvironmentRef,
+    },
+}
+
+impl Frame {
+    fn statements(statements: Vec<Stmt>, environment: EnvironmentRef) -> Frame {
+        Frame::Statements {
+            statements: Rc::new(statements),
+            next: 0,
+            environment,
+        }
+    }
+
+    fn loop_label(&self) -> Option<Option<&Token>> {
+        match self {
+            Frame::Statements { .. } => None,
+            Frame::While { stmt, .. } => Some(stmt.label.as_ref()),
+            Frame::For { stmt, .. } => Some(stmt.label.as_ref()),
+            Frame::ForIn { stmt, .. } => Some(stmt.label.as_ref()),
+        }
+    }
+}
+
+impl LoxGenerator {
+    /// Generator about to run `body` in `environment`, where the arguments are defined
+    pub fn new(name: &str, body: &[Stmt], environment: EnvironmentRef) -> LoxGenerator {
+        LoxGenerator {
+            name: name.to_string(),
+            frames: RefCell::new(vec![Frame::statements(body.to_vec(), environment)]),
+        }
+    }
+
+    /// Runs the body up to its next `yield` and returns the yielded value,
+    /// `None` once the body finished
+    pub fn resume(
+        &self,
+        interpreter: &mut Interpreter,
+        line: usize,
+    ) -> Result<Option<Object>, LoxErrorResult> {
+        let Ok(mut frames) = self.frames.try_borrow_mut() else {
+            return Err(LoxErrorResult::interpreter_error(
+                line,
+                I0322.with([self.name.as_str().into()]),
+            ));
+        };
+        let previous_env = interpreter.environment();
+        let mut result = Ok(None);
+        while !frames.is_empty() {
+            match step(&mut frames, interpreter) {
+                Ok(Some(value)) => {
+                    result = Ok(Some(value));
+                    break;
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    if let Err(err) = unwind(&mut frames, err) {
+                        result = Err(err);
+                        break;
+                    }
+                }
+            }
+        }
+        interpreter.replace_environment(previous_env);
+        result
+    }
+}
+
+/// Runs the innermost frame by one statement or one loop iteration,
+/// returns the value of a `yield`
+fn step(
+    frames: &mut Vec<Frame>,
+    interpreter: &mut Interpreter,
+) -> Result<Option<Object>, LoxErrorResult> {
+    let Some(frame) = frames.last_mut() else {
+        return Ok(None);
+    };
+    match frame {
+        Frame::Statements {
+            statements,
+            next,
+            environment,
+        } => {
+            if *next >= statements.len() {
+                frames.pop();
+                return Ok(None);
+            }
+            let index = *next;
+            *next += 1;
+            let statements = Rc::clone(statements);
+            interpreter.replace_environment(Rc::clone(environment));
+            start(frames, interpreter, &statements[index])
+        }
+        Frame::While {
+            stmt,
+            body,
+            environment,
+        } => {
+            interpreter.replace_environment(Rc::clone(environment));
+            let value = interpreter.evaluate(&stmt.condition)?;
+            let body = Rc::clone(body);
+            let environment = Rc::clone(environment);
+            match interpreter.is_condition_true(value, stmt.keyword.line, "while")? {
+                true => frames.push(Frame::Statements {
+                    statements: body,
+                    next: 0,
+                    environment,
+                }),
+                false => {
+                    frames.pop();
+                }
+            }
+            Ok(None)
+        }
+        Frame::For {
+            stmt,
+            body,
+            started,
+            environment,
+        } => {
+            interpreter.replace_environment(Rc::clone(environment));
+            if let (true, Some(increment)) = (*started, &stmt.increment) {
+                interpreter.evaluate(increment)?;
+            }
+            *started = true;
+            let proceed = match &stmt.condition {
+                Some(condition) => {
+                    let value = interpreter.evaluate(condition)?;
+                    interpreter.is_condition_true(value, stmt.keyword.line, "for")?
+                }
+                None => true,
+            };
+            let body = Rc::clone(body);
+            let environment = Rc::clone(environment);
+            match proceed {
+                true => frames.push(Frame::Statements {
+                    statements: body,
+                    next: 0,
+                    environment,
+                }),
+                false => {
+                    frames.pop();
+                }
+            }
+            Ok(None)
+        }
+        Frame::ForIn {
+            stmt,
+            body,
+            values,
+            environment,
+        } => {
+            let line = stmt.keyword.line;
+            let Some(value) = values.next(interpreter, line)? else {
+                frames.pop();
+                return Ok(None);
+            };
+            interpreter.variable_written(&stmt.name, &value);
+            // Every iteration has its own variable, closures made in the body keep theirs
+            let iteration = Environment::new_enclosing(Rc::clone(environment));
+            iteration
+                .borrow_mut()
+                .define(stmt.name.lexeme.clone(), value);
+            let body = Rc::clone(body);
+            frames.push(Frame::Statements {
+                statements: body,
+                next: 0,
+                environment: iteration,
+            });
+            Ok(None)
+        }
+    }
+}
+
+/// Runs a statement without `yield` at once, a statement with one is pushed as a frame
+/// and the `yield` itself gives its value
+fn start(
+    frames: &mut Vec<Frame>,
+    interpreter: &mut Interpreter,
+    stmt: &Stmt,
+) -> Result<Option<Object>, LoxErrorResult> {
+    if !yields(stmt) {
+        interpreter.execute(stmt)?;
+        return Ok(None);
+    }
+    let environment = interpreter.environment();
+    match stmt {
+        Stmt::Yield(stmt) => match &stmt.value {
+            Some(value) => Ok(Some(interpreter.evaluate(value)?)),
+            None => Ok(Some(Object::Nil)),
+        },
+        Stmt::Block(block) => {
+            let scope = Environment::new_enclosing(environment);
+            frames.push(Frame::statements(block.statements.clone(), scope));
+            Ok(None)
+        }
+        Stmt::If(stmt) => {
+            let value = interpreter.evaluate(&stmt.condition)?;
+            let branch = match interpreter.is_condition_true(value, stmt.keyword.line, "if")? {
+                true => Some(&stmt.then_branch),
+                false => stmt.else_branch.as_ref(),
+            };
+            if let Some(branch) = branch {
+                frames.push(Frame::statements(vec![*branch.clone()], environment));
+            }
+            Ok(None)
+        }
+        Stmt::While(stmt) => {
+            frames.push(Frame::While {
+                stmt: Rc::new(stmt.clone()),
+                body: Rc::new(vec![*stmt.body.clone()]),
+                environment,
+            });
+            Ok(None)
+        }
+        Stmt::For(stmt) => {
+            // The initializer lives in its own scope, as in `Interpreter::visit_for_stmt`
+            let scope = Environment::new_enclosing(environment);
+            interpreter.replace_environment(Rc::clone(&scope));
+            if let Some(initializer) = &stmt.initializer {
+                interpreter.execute(initializer)?;
+            }
+            frames.push(Frame::For {
+                stmt: Rc::new(stmt.clone()),
+                body: Rc::new(vec![*stmt.body.clone()]),
+                started: false,
+                environment: scope,
+            });
+            Ok(None)
+        }
+        Stmt::ForIn(stmt) => {
+            let iterable = interpreter.evaluate(&stmt.iterable)?;
+            frames.push(Frame::ForIn {
+                stmt: Rc::new(stmt.clone()),
+                body: Rc::new(vec![*stmt.body.clone()]),
+                values: LoxIterator::new(iterable, stmt.keyword.line)?,
+                environment,
+            });
+            Ok(None)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Delivers a `break` or a `continue` to its loop, a `return` or an error ends the body
+fn unwind(frames: &mut Vec<Frame>, err: LoxErrorResult) -> Result<(), LoxErrorResult> {
+    match err {
+        LoxErrorResult::ControlFlowReturn { .. } => {
+            frames.clear();
+            Ok(())
+        }
+        LoxErrorResult::ControlFlowBreak { .. } | LoxErrorResult::ControlFlowContinue { .. } => {
+            while let Some(frame) = frames.last() {
+                if let Some(label) = frame.loop_label() {
+                    if err.breaks(label) {
+                        frames.pop();
+                        return Ok(());
+                    }
+                    // The loop frame goes on with its next iteration
+                    if err.continues(label) {
+                        return Ok(());
+                    }
+                }
+                frames.pop();
+            }
+            Err(err)
+        }
+        err => {
+            frames.clear();
+            Err(err)
+        }
+    }
+}
+
+/// Whether `yield` appears in the statements, outside of the functions and classes they declare
+pub fn contains_yield(statements: &[Stmt]) -> bool {
+    statements.iter().any(yields)
+}
+
+fn yields(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Yield(_) => true,
+        Stmt::Block(block) => contains_yield(&block.statements),
+        Stmt::If(stmt) => {
+            yields(&stmt.then_branch) || stmt.else_branch.as_deref().is_some_and(yields)
+        }
+        Stmt::While(stmt) => yields(&stmt.body),
+        Stmt::For(stmt) => yields(&stmt.body),
+        Stmt::ForIn(stmt) => yields(&stmt.body),
+        _ => false,
+    }
+}
+
+impl Display for LoxGenerator {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "<generator {}>", self.name)
+    }
+}
+
+/// Values a `for (var x in ...)` loop goes through
+#[derive(Debug)]
+pub enum LoxIterator {
+    /// Elements are read by index, the loop also sees the ones pushed while it runs
+    List(LoxListRef, usize),
+    Chars(std::vec::IntoIter<char>),
+    Generator(LoxGeneratorRef),
+}
+
+impl LoxIterator {
+    pub fn new(value: Object, line: usize) -> Result<LoxIterator, LoxErrorResult> {
+        match value {
+            Object::List(elements) => Ok(LoxIterator::List(elements, 0)),
+            Object::String(text) => Ok(LoxIterator::Chars(
+                text.chars().collect::<Vec<char>>().into_iter(),
+            )),
+            Object::Generator(generator) => Ok(LoxIterator::Generator(generator)),
+            value => Err(LoxErrorResult::interpreter_error(
+                line,
+                I0320.with([value.to_string().into()]),
+            )),
+        }
+    }
+
+    /// Next value, `None` once they are all gone
+    pub fn next(
+        &mut self,
+        interpreter: &mut Interpreter,
+        line: usize,
+    ) -> Result<Option<Object>, LoxErrorResult> {
+        match self {
+            LoxIterator::List(elements, index) => {
+                let element = elements.borrow().get(*index).cloned();
+                *index += 1;
+                Ok(element)
+            }
+            LoxIterator::Chars(chars) => Ok(chars
+                .next()
+                .map(|char| Object::String(char.to_string().into()))),
+            LoxIterator::Generator(generator) => generator.resume(interpreter, line),
+        }
+    }
+}
@@ -1,9 +1,14 @@
-use crate::{error::LoxErrorResult, lox_class::LoxClass, object::Object, token::Token};
+use crate::{
+    error::LoxErrorResult,
+    error_codes::I0305,
+    lox_class::LoxClass,
+    object::Object,
+    shared::{Rc, RefCell},
+    token::Token,
+};
 use std::{
-    cell::RefCell,
     collections::HashMap,
     fmt::{self, Display, Formatter},
-    rc::Rc,
 };
 
 #[derive(Debug, Clone)]
@@ -33,13 +38,52 @@ impl LoxInstance {
 
         Err(LoxErrorResult::interpreter_error(
             name.line,
-            &format!("Undefined property '{}'.", name.lexeme),
+            I0305.with([name.lexeme().into()]),
         ))
     }
 
     pub fn set(&mut self, name: &Token, value: Object) {
         self.fields.insert(name.lexeme(), value);
     }
+
+    /// Sets a field by name, for instances built by natives
+    pub fn set_field(&mut self, name: &str, value: Object) {
+        self.fields.insert(name.to_string(), value);
+    }
+
+    pub fn has_field(&self, name: &str) -> bool {
+        self.fields.contains_key(name)
+    }
+
+    pub fn field_count(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn field(&self, name: &str) -> Option<Object> {
+        self.fields.get(name).cloned()
+    }
+
+    pub fn class_name(&self) -> &str {
+        &self.class.name
+    }
+
+    /// Fields of this instance with their values, sorted by name
+    pub fn fields(&self) -> Vec<(String, Object)> {
+        let mut fields: Vec<(String, Object)> = self
+            .fields
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        fields.sort_by(|(left, _), (right, _)| left.cmp(right));
+        fields
+    }
+
+    /// Names of the fields and the methods (inherited ones included) of this instance
+    pub fn member_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.fields.keys().cloned().collect();
+        names.extend(self.class.method_names());
+        names
+    }
 }
 
 impl Display for LoxInstance {
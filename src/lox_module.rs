@@ -0,0 +1,62 @@
+use crate::{
+    error::LoxErrorResult, error_codes::I0323, lox_native_function::LoxNativeFunction,
+    object::Object, shared::Rc, token::Token,
+};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+};
+
+/// Namespace of natives a host registered together, scripts call them as `fs.read(path)`.
+/// Its members cannot be changed by scripts.
+#[derive(Debug, Clone)]
+pub struct LoxModule {
+    pub name: String,
+    members: HashMap<String, Object>,
+}
+
+pub type LoxModuleRef = Rc<LoxModule>;
+
+impl LoxModule {
+    /// Every native is named `<module>.<name>` in errors and reports, and is a member
+    /// under its own name
+    pub fn new(name: &str, natives: impl IntoIterator<Item = LoxNativeFunction>) -> LoxModule {
+        let mut module = LoxModule {
+            name: name.to_string(),
+            members: HashMap::new(),
+        };
+        module.extend(natives);
+        module
+    }
+
+    /// Adds the natives, replacing the members with the same name
+    pub fn extend(&mut self, natives: impl IntoIterator<Item = LoxNativeFunction>) {
+        for mut native in natives {
+            let member = std::mem::take(&mut native.name);
+            native.name = format!("{}.{member}", self.name);
+            self.members.insert(member, Object::NativeFunction(native));
+        }
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Object, LoxErrorResult> {
+        self.members.get(&name.lexeme).cloned().ok_or_else(|| {
+            LoxErrorResult::interpreter_error(
+                name.line,
+                I0323.with([name.lexeme().into(), self.name.as_str().into()]),
+            )
+        })
+    }
+
+    /// Names of the members, sorted
+    pub fn member_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.members.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+impl Display for LoxModule {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
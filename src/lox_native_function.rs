@@ -1,14 +1,63 @@
-use std::fmt::{self, Display, Formatter};
+use std::fmt::{self, Debug, Display, Formatter};
 
 use crate::{
-    error::*, interpreter::Interpreter, lox_callable::LoxCallable, object::Object, token::Token,
+    capabilities::Capability,
+    error::*,
+    error_codes::{I0309, I0310, I0327},
+    interpreter::Interpreter,
+    lox_callable::LoxCallable,
+    object::Object,
+    shared::{MaybeSync, Rc},
+    token::Token,
 };
 
-#[derive(Debug, Clone, PartialEq)]
+/// Rust implementation of a native function. Closures let embedders keep their own state.
+pub type NativeCallable = Rc<dyn NativeFn>;
+
+/// Signature of the natives, every closure with it is one. They are `Send + Sync` with the
+/// `sync` feature, like the rest of the interpreter.
+pub trait NativeFn:
+    Fn(&mut Interpreter, Vec<Object>, &Token) -> Result<Object, LoxErrorResult> + MaybeSync
+{
+}
+
+impl<F> NativeFn for F where
+    F: Fn(&mut Interpreter, Vec<Object>, &Token) -> Result<Object, LoxErrorResult> + MaybeSync
+{
+}
+
+#[derive(Clone)]
 pub struct LoxNativeFunction {
     pub name: String,
     pub arity: usize,
-    pub callable: fn(&mut Interpreter, Vec<Object>) -> Result<Object, LoxErrorResult>,
+    /// Takes `arity` arguments or more, like `format(template, ...)`
+    pub variadic: bool,
+    /// Checked on every call against `Interpreter::capabilities`
+    pub capability: Option<Capability>,
+    pub callable: NativeCallable,
+}
+
+impl LoxNativeFunction {
+    pub fn new(name: &str, arity: usize, callable: impl NativeFn + 'static) -> LoxNativeFunction {
+        LoxNativeFunction {
+            name: name.to_string(),
+            arity,
+            variadic: false,
+            capability: None,
+            callable: Rc::new(callable),
+        }
+    }
+
+    pub fn variadic(mut self) -> LoxNativeFunction {
+        self.variadic = true;
+        self
+    }
+
+    /// The native only runs while the capability is granted
+    pub fn requires(mut self, capability: Capability) -> LoxNativeFunction {
+        self.capability = Some(capability);
+        self
+    }
 }
 
 impl LoxCallable for LoxNativeFunction {
@@ -16,25 +65,55 @@ impl LoxCallable for LoxNativeFunction {
         &mut self,
         interpreter: &mut Interpreter,
         arguments: Vec<Object>,
+        current_token: &Token,
     ) -> Result<Object, LoxErrorResult> {
-        (self.callable)(interpreter, arguments)
+        if let Some(capability) = self.capability {
+            if !interpreter.capabilities.allows(capability) {
+                return Err(LoxErrorResult::interpreter_error(
+                    current_token.line,
+                    I0327.with([self.name.as_str().into(), capability.to_string().into()]),
+                ));
+            }
+        }
+        let name = format!("<native {}>", self.name);
+        interpreter.function_enter(&name);
+        let result = (self.callable)(interpreter, arguments, current_token);
+        interpreter.function_exit(&name);
+        result
     }
 
     fn arity(&self) -> usize {
-        0
+        self.arity
     }
 
     fn check_arity(&self, args_len: usize, current_token: &Token) -> Result<(), LoxErrorResult> {
-        if args_len != self.arity() {
+        if self.variadic && args_len < self.arity() {
             return Err(LoxErrorResult::interpreter_error(
                 current_token.line,
-                &format!("Expected {} arguments but got {}.", self.arity(), args_len),
+                I0310.with([self.arity().to_string().into(), args_len.to_string().into()]),
+            ));
+        }
+        if !self.variadic && args_len != self.arity() {
+            return Err(LoxErrorResult::interpreter_error(
+                current_token.line,
+                I0309.with([self.arity().to_string().into(), args_len.to_string().into()]),
             ));
         }
         Ok(())
     }
 }
 
+impl Debug for LoxNativeFunction {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("LoxNativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .field("variadic", &self.variadic)
+            .field("capability", &self.capability)
+            .finish()
+    }
+}
+
 impl Display for LoxNativeFunction {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "<fun native {}>", self.name)
@@ -0,0 +1,388 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    analysis::{Analysis, Symbol, SymbolKind, Workspace},
+    error::Diagnostic,
+    source_map::SourceMap,
+    token::Token,
+    utils::json::Json,
+};
+
+// JSON-RPC error codes
+const PARSE_ERROR: f64 = -32700.0;
+const INVALID_REQUEST: f64 = -32600.0;
+const METHOD_NOT_FOUND: f64 = -32601.0;
+
+/// Language server speaking LSP over stdio (`rlox lsp`). Documents are synchronized
+/// as a whole on every change. Positions use the character offsets of the tokens,
+/// which match the protocol's UTF-16 offsets for text in the Basic Multilingual Plane.
+#[derive(Debug, Default)]
+pub struct LanguageServer {
+    workspace: Workspace,
+    shutdown_requested: bool,
+}
+
+impl LanguageServer {
+    pub fn new() -> LanguageServer {
+        LanguageServer::default()
+    }
+
+    /// Serves messages until `exit`, returns the process exit code
+    pub fn serve(&mut self, input: &mut impl BufRead, output: &mut impl Write) -> io::Result<i32> {
+        while let Some(content) = read_message(input)? {
+            let (replies, exit_code) = match Json::parse(&content) {
+                Ok(message) => self.handle(&message),
+                Err(err) => (vec![error_reply(Json::Null, PARSE_ERROR, &err)], None),
+            };
+            for reply in replies {
+                write_message(output, &reply)?;
+            }
+            if let Some(exit_code) = exit_code {
+                return Ok(exit_code);
+            }
+        }
+        // The client went away without asking the server to exit
+        Ok(1)
+    }
+
+    /// Returns the messages to send back, and the exit code once the client asked to exit
+    pub fn handle(&mut self, message: &Json) -> (Vec<Json>, Option<i32>) {
+        let method = message.get("method").and_then(Json::as_str);
+        let params = message.get("params").unwrap_or(&Json::Null);
+        let id = message.get("id").cloned();
+        let result = match (method, id) {
+            (Some("exit"), _) => return (Vec::new(), Some(!self.shutdown_requested as i32)),
+            (Some("textDocument/didOpen"), None) => {
+                let document = params.get("textDocument").unwrap_or(&Json::Null);
+                let text = document.get("text").and_then(Json::as_str).unwrap_or("");
+                self.open(text_document_uri(params), text.to_string())
+            }
+            (Some("textDocument/didChange"), None) => {
+                // Full synchronization, the last change holds the whole text
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str)
+                    .unwrap_or("");
+                self.open(text_document_uri(params), text.to_string())
+            }
+            (Some("textDocument/didClose"), None) => {
+                let uri = text_document_uri(params);
+                self.workspace.close(&uri);
+                vec![publish_diagnostics(&uri, Vec::new())]
+            }
+            // Other notifications (`initialized`, `$/cancelRequest`, ...) need no answer
+            (_, None) => Vec::new(),
+            (Some(method), Some(id)) => vec![self.respond(method, params, id)],
+            (None, Some(id)) => vec![error_reply(id, INVALID_REQUEST, "Missing method.")],
+        };
+        (result, None)
+    }
+
+    fn respond(&mut self, method: &str, params: &Json, id: Json) -> Json {
+        let result = match method {
+            "initialize" => Json::object(vec![
+                (
+                    "capabilities",
+                    Json::object(vec![
+                        ("textDocumentSync", Json::Number(1.0)),
+                        ("definitionProvider", Json::Bool(true)),
+                        ("documentSymbolProvider", Json::Bool(true)),
+                        ("hoverProvider", Json::Bool(true)),
+                    ]),
+                ),
+                (
+                    "serverInfo",
+                    Json::object(vec![("name", Json::string("rlox"))]),
+                ),
+            ]),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Json::Null
+            }
+            "textDocument/definition" => {
+                let uri = text_document_uri(params);
+                match self.at_position(&uri, params, Analysis::definition) {
+                    Some(token) => Json::object(vec![
+                        ("uri", Json::String(uri)),
+                        ("range", token_range(token)),
+                    ]),
+                    None => Json::Null,
+                }
+            }
+            "textDocument/hover" => {
+                let uri = text_document_uri(params);
+                match self.at_position(&uri, params, Analysis::hover) {
+                    Some(value) => Json::object(vec![(
+                        "contents",
+                        Json::object(vec![
+                            ("kind", Json::string("markdown")),
+                            ("value", Json::String(value)),
+                        ]),
+                    )]),
+                    None => Json::Null,
+                }
+            }
+            "textDocument/documentSymbol" => {
+                match self.workspace.analysis(&text_document_uri(params)) {
+                    Some(analysis) => {
+                        Json::Array(analysis.symbols.iter().map(document_symbol).collect())
+                    }
+                    None => Json::Null,
+                }
+            }
+            _ => return error_reply(id, METHOD_NOT_FOUND, &format!("Unknown method '{method}'.")),
+        };
+        Json::object(vec![
+            ("jsonrpc", Json::string("2.0")),
+            ("id", id),
+            ("result", result),
+        ])
+    }
+
+    /// Analyzes the new text of a document and publishes its diagnostics
+    fn open(&mut self, uri: String, text: String) -> Vec<Json> {
+        let source_map = SourceMap::new(&text);
+        let analysis = self.workspace.update(&uri, text);
+        let diagnostics = analysis
+            .diagnostics
+            .iter()
+            .map(|diagnostic| lsp_diagnostic(diagnostic, &source_map))
+            .collect();
+        vec![publish_diagnostics(&uri, diagnostics)]
+    }
+
+    /// Runs a query on the analysis of the document at the request's position
+    fn at_position<'a, T>(
+        &'a self,
+        uri: &str,
+        params: &Json,
+        query: impl Fn(&'a Analysis, usize, usize) -> Option<T>,
+    ) -> Option<T> {
+        let position = params.get("position")?;
+        let line = position.get("line").and_then(Json::as_f64)? as usize;
+        let character = position.get("character").and_then(Json::as_f64)? as usize;
+        query(self.workspace.analysis(uri)?, line + 1, character)
+    }
+}
+
+/// Reads one message framed by a `Content-Length` header, `None` at the end of the input
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Missing Content-Length header.",
+        ));
+    };
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    String::from_utf8(content)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let content = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    output.flush()
+}
+
+fn text_document_uri(params: &Json) -> String {
+    params
+        .get("textDocument")
+        .and_then(|document| document.get("uri"))
+        .and_then(Json::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn error_reply(id: Json, code: f64, message: &str) -> Json {
+    Json::object(vec![
+        ("jsonrpc", Json::string("2.0")),
+        ("id", id),
+        (
+            "error",
+            Json::object(vec![
+                ("code", Json::Number(code)),
+                ("message", Json::string(message)),
+            ]),
+        ),
+    ])
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::object(vec![
+        ("jsonrpc", Json::string("2.0")),
+        ("method", Json::string("textDocument/publishDiagnostics")),
+        (
+            "params",
+            Json::object(vec![
+                ("uri", Json::string(uri)),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        ),
+    ])
+}
+
+fn lsp_diagnostic(diagnostic: &Diagnostic, source_map: &SourceMap) -> Json {
+    // Only the line is known for some errors, then the whole line is highlighted
+    let (line, column, length) = diagnostic.span(source_map);
+    let line = line.saturating_sub(1);
+    let range = range(line, column, line, column + length);
+    let severity = match diagnostic.is_warning {
+        true => 2.0,
+        false => 1.0,
+    };
+    let code = diagnostic.code.map(|code| ("code", Json::string(code)));
+    let fields = [("range", range), ("severity", Json::Number(severity))]
+        .into_iter()
+        .chain(code)
+        .chain([
+            ("source", Json::string("rlox")),
+            ("message", Json::String(diagnostic.message.clone())),
+        ]);
+    Json::object(fields.collect())
+}
+
+fn document_symbol(symbol: &Symbol) -> Json {
+    let kind = match symbol.kind {
+        SymbolKind::Class => 5.0,
+        SymbolKind::Method => 6.0,
+        SymbolKind::Function => 12.0,
+        SymbolKind::Variable | SymbolKind::Parameter => 13.0,
+    };
+    Json::object(vec![
+        ("name", Json::String(symbol.name.lexeme())),
+        ("detail", Json::String(symbol.detail.clone())),
+        ("kind", Json::Number(kind)),
+        ("range", token_range(&symbol.name)),
+        ("selectionRange", token_range(&symbol.name)),
+        (
+            "children",
+            Json::Array(symbol.children.iter().map(document_symbol).collect()),
+        ),
+    ])
+}
+
+fn token_range(token: &Token) -> Json {
+    let line = token.line.saturating_sub(1);
+    let end = token.column + token.lexeme.chars().count();
+    range(line, token.column, line, end)
+}
+
+fn range(start_line: usize, start: usize, end_line: usize, end: usize) -> Json {
+    let position = |line: usize, character: usize| {
+        Json::object(vec![
+            ("line", Json::Number(line as f64)),
+            ("character", Json::Number(character as f64)),
+        ])
+    };
+    Json::object(vec![
+        ("start", position(start_line, start)),
+        ("end", position(end_line, end)),
+    ])
+}
+
+#[cfg(test)]
+mod lsp_tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn frame(messages: &[&str]) -> Cursor<Vec<u8>> {
+        let framed: String = messages
+            .iter()
+            .map(|content| format!("Content-Length: {}\r\n\r\n{content}", content.len()))
+            .collect();
+        Cursor::new(framed.into_bytes())
+    }
+
+    fn replies(output: Vec<u8>) -> Vec<Json> {
+        let mut input = Cursor::new(output);
+        let mut replies = Vec::new();
+        while let Some(content) = read_message(&mut input).expect("Framed reply") {
+            replies.push(Json::parse(&content).expect("JSON reply"));
+        }
+        replies
+    }
+
+    #[test]
+    fn test_session_with_diagnostics_definition_and_hover() {
+        // Arrange
+        let open = r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.lox","languageId":"lox","version":1,"text":"fun twice(n) { return n * 2; }\nprint twice(1);\nprint missing"}}}"#;
+        let mut input = frame(&[
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+            r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#,
+            open,
+            r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{"textDocument":{"uri":"file:///a.lox"},"position":{"line":1,"character":8}}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"textDocument/hover","params":{"textDocument":{"uri":"file:///a.lox"},"position":{"line":0,"character":22}}}"#,
+            r#"{"jsonrpc":"2.0","id":4,"method":"workspace/unknown"}"#,
+            r#"{"jsonrpc":"2.0","id":5,"method":"shutdown"}"#,
+            r#"{"jsonrpc":"2.0","method":"exit"}"#,
+        ]);
+        let mut output = Vec::new();
+        // Act
+        let exit_code = LanguageServer::new().serve(&mut input, &mut output);
+        // Assert
+        let replies = replies(output);
+        assert_eq!(exit_code.ok(), Some(0));
+        assert_eq!(replies.len(), 6);
+        assert_eq!(
+            replies[1].to_string(),
+            r#"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///a.lox","diagnostics":[{"range":{"start":{"line":2,"character":13},"end":{"line":2,"character":13}},"severity":1,"code":"P0103","source":"rlox","message":"Expect ';' after 'print' value."}]}}"#
+        );
+        assert_eq!(
+            replies[2].get("result").map(Json::to_string),
+            Some(r#"{"uri":"file:///a.lox","range":{"start":{"line":0,"character":4},"end":{"line":0,"character":9}}}"#.to_string())
+        );
+        assert_eq!(
+            replies[3]
+                .get("result")
+                .and_then(|result| result.get("contents"))
+                .and_then(|contents| contents.get("value"))
+                .and_then(Json::as_str),
+            Some("```lox\nparameter n of twice\n```\nDeclared on line 1.")
+        );
+        assert_eq!(
+            replies[4]
+                .get("error")
+                .and_then(|error| error.get("code"))
+                .and_then(Json::as_f64),
+            Some(METHOD_NOT_FOUND)
+        );
+    }
+
+    #[test]
+    fn test_document_symbols() {
+        // Arrange
+        let mut server = LanguageServer::new();
+        let open = Json::parse(r#"{"method":"textDocument/didOpen","params":{"textDocument":{"uri":"b.lox","text":"class A { m() {} }"}}}"#).expect("JSON");
+        let request = Json::parse(r#"{"id":1,"method":"textDocument/documentSymbol","params":{"textDocument":{"uri":"b.lox"}}}"#).expect("JSON");
+        // Act
+        server.handle(&open);
+        let (replies, _) = server.handle(&request);
+        // Assert
+        assert_eq!(
+            replies[0].get("result").map(Json::to_string),
+            Some(r#"[{"name":"A","detail":"class A","kind":5,"range":{"start":{"line":0,"character":6},"end":{"line":0,"character":7}},"selectionRange":{"start":{"line":0,"character":6},"end":{"line":0,"character":7}},"children":[{"name":"m","detail":"A.m()","kind":6,"range":{"start":{"line":0,"character":10},"end":{"line":0,"character":11}},"selectionRange":{"start":{"line":0,"character":10},"end":{"line":0,"character":11}},"children":[]}]}]"#.to_string())
+        );
+    }
+}
@@ -1,78 +1,485 @@
-// Modules
-mod environment;
-mod error;
-mod expr;
-mod interpreter;
-mod lox_callable;
-mod lox_class;
-mod lox_function;
-mod lox_instance;
-mod lox_native_function;
-mod object;
-mod parser;
-mod resolver;
-mod scanner;
-mod stmt;
-mod token;
-mod utils;
+#![allow(clippy::result_large_err)]
 // Imports
 use std::env::args;
 
-use std::io::{self, Write};
+use std::{
+    io::{self, IsTerminal, Read},
+    path::{Path, PathBuf},
+};
 
-use crate::{interpreter::Interpreter, parser::Parser, resolver::Resolver, scanner::Scanner};
+use rlox_interpreter::{
+    capabilities::{Capabilities, Capability},
+    cli::{Command, Emit, Options, DEFAULT_TAB_WIDTH, USAGE},
+    compile,
+    config::ProjectConfig,
+    coverage::{Coverage, CoverageFormat},
+    debug_log::{self, DebugCategories},
+    debugger::Debugger,
+    error::{self, capture_diagnostics},
+    error_codes,
+    formatter::Formatter,
+    highlight,
+    interpreter::{DivisionByZero, FileSystem, Interpreter},
+    linter::{LintConfig, Linter},
+    locale::{self, Language},
+    lsp::LanguageServer,
+    parse,
+    parser::Parser,
+    profiler::Profiler,
+    recorder::{Recorder, Trace, TraceQuery},
+    repl, run, run_cached, run_compiled, run_timed,
+    scanner::Scanner,
+    server,
+    shared::{Rc, RefCell},
+    source_map::{self, SourceMap},
+    stats::Stats,
+    test_runner,
+    trace_export::TraceExporter,
+    tracer::Tracer,
+    transpiler::{JsTranspiler, Target},
+    type_checker, watch, RunError,
+};
 
 fn main() {
     // TODO: Add a way to handle print AST an arg
-    let args: Vec<String> = args().collect();
-    let mut interpreter = Interpreter::new();
-    match args.len() {
-        1 => run_prompt(&mut interpreter),
-        2 => run_file(&args[1], &mut interpreter).expect("Could not run file!"),
-        _ => {
-            eprintln!("Usage: r-lox interpreter [script]");
+    let args: Vec<String> = args().skip(1).collect();
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{message}\n\n{USAGE}");
             std::process::exit(64);
         }
+    };
+    locale::set_language(options.lang.or_else(Language::from_env).unwrap_or_default());
+    // Project settings only apply to running and formatting scripts
+    let config = match &options.command {
+        Command::Run | Command::Fmt { .. } => {
+            match ProjectConfig::find(&config_dir(options.script.as_deref())) {
+                Ok(config) => config,
+                Err(message) => {
+                    eprintln!("{message}");
+                    std::process::exit(78);
+                }
+            }
+        }
+        _ => ProjectConfig::default(),
+    };
+    let options = options.with_config(&config);
+    match (&options.command, &options.script) {
+        (Command::Fmt { check, minify }, Some(path)) => {
+            std::process::exit(format_file(path, *check, config.formatter(*minify)))
+        }
+        (Command::Lint(config), Some(path)) => std::process::exit(lint_file(path, config)),
+        (Command::Test, Some(dir)) => std::process::exit(test_runner::run_tests(dir)),
+        (Command::Transpile(target), Some(path)) => {
+            std::process::exit(transpile_file(path, *target))
+        }
+        (Command::Replay(query), Some(path)) => std::process::exit(replay_trace(path, query)),
+        (Command::Lsp, _) => std::process::exit(run_language_server()),
+        (Command::Serve { port }, _) => std::process::exit(serve_prompt(*port, &options)),
+        (Command::Explain(code), _) => std::process::exit(explain_code(code)),
+        _ => {}
+    }
+    // The language server above keeps counting a tab as a single column
+    source_map::set_tab_width(options.tab_width.unwrap_or(DEFAULT_TAB_WIDTH));
+    if let (Some(emit), Some(path)) = (options.emit, &options.script) {
+        std::process::exit(emit_file(path, emit));
+    }
+    if options.watch {
+        if let Some(path) = &options.script {
+            watch::watch_file(path, &|| new_interpreter(&options));
+        }
+        return;
+    }
+
+    let mut interpreter = new_interpreter(&options);
+    let profiler = options
+        .profile
+        .then(|| Rc::new(RefCell::new(Profiler::new())));
+    if let Some(profiler) = &profiler {
+        interpreter.add_hooks(profiler.clone());
+    }
+    let stats = options.stats.then(|| Rc::new(RefCell::new(Stats::new())));
+    if let Some(stats) = &stats {
+        interpreter.add_hooks(stats.clone());
+    }
+    let coverage = options
+        .coverage
+        .map(|_| Rc::new(RefCell::new(Coverage::new())));
+    if let Some(coverage) = &coverage {
+        interpreter.add_hooks(coverage.clone());
+    }
+    let exporter = options
+        .trace_export
+        .then(|| Rc::new(RefCell::new(TraceExporter::new())));
+    if let Some(exporter) = &exporter {
+        interpreter.add_hooks(exporter.clone());
+    }
+    let recorder = options
+        .record
+        .as_ref()
+        .map(|_| Rc::new(RefCell::new(Recorder::new())));
+    if let Some(recorder) = &recorder {
+        interpreter.add_hooks(recorder.clone());
+    }
+    let result = match options.script.as_deref() {
+        None if !options.eval.is_empty() => {
+            run_script(options.eval.join("\n"), &mut interpreter, &options)
+        }
+        // Piped input (no TTY) is read as a whole script instead of line by line
+        None if io::stdin().is_terminal() => {
+            repl::run_prompt(&mut interpreter, options.time, &|| {
+                new_interpreter(&options)
+            });
+            Ok(())
+        }
+        None | Some("-") => run_stdin(&mut interpreter, &options),
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(RunError::Io)
+            .and_then(|source| run_script(source, &mut interpreter, &options)),
+    };
+    if let Err(RunError::Io(err)) = &result {
+        eprintln!(
+            "Could not read '{}': {err}",
+            options.script.as_deref().unwrap_or("-")
+        );
+    }
+    // `-i` opens the prompt even after an error, exiting from the script skips it
+    let result = match (&result, options.interactive, interpreter.exit_code) {
+        (Err(RunError::Io(_)), _, _) | (_, false, _) | (_, _, Some(_)) => result,
+        _ => {
+            repl::run_prompt(&mut interpreter, options.time, &|| {
+                new_interpreter(&options)
+            });
+            Ok(())
+        }
+    };
+    // The report goes to stderr so it doesn't mix with the script's own output
+    if let Some(profiler) = &profiler {
+        eprint!("\n{}", profiler.borrow().report());
+    }
+    if let Some(stats) = &stats {
+        eprint!("\n{}", stats.borrow().report());
+    }
+    if let (Some(coverage), Some(format), Some(path)) =
+        (&coverage, options.coverage, options.script.as_deref())
+    {
+        report_coverage(&coverage.borrow(), format, path);
+    }
+    if let Some(exporter) = &exporter {
+        match std::fs::write("trace.json", exporter.borrow().to_json().to_string()) {
+            Ok(_) => eprintln!("Trace written to 'trace.json'."),
+            Err(err) => eprintln!("Could not write 'trace.json': {err}"),
+        }
+    }
+    if let (Some(recorder), Some(path)) = (&recorder, &options.record) {
+        if let Err(err) = std::fs::write(path, recorder.borrow().to_log()) {
+            eprintln!("Could not write '{path}': {err}");
+        }
+    }
+    if let Some(code) = interpreter.exit_code {
+        std::process::exit(code);
+    }
+    if let Err(err) = result {
+        std::process::exit(err.exit_code());
     }
 }
 
-fn run_file(path: &str, interpreter: &mut Interpreter) -> io::Result<()> {
-    let source = std::fs::read_to_string(path)?;
-    run(source, interpreter);
-    Ok(())
+/// Directory of `lox.toml`, the script's or the current one
+fn config_dir(script: Option<&str>) -> PathBuf {
+    match script {
+        Some(script) if script != "-" => Path::new(script)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+        _ => PathBuf::new(),
+    }
 }
 
-fn run_prompt(interpreter: &mut Interpreter) {
-    loop {
-        print!("> ");
-        let _ = io::stdout().flush();
-        let mut line = String::new();
-        io::stdin().read_line(&mut line).unwrap();
-        run(line, interpreter);
+/// Interpreter with the options shared by single and watched runs
+fn new_interpreter(options: &Options) -> Interpreter {
+    // The prelude is not logged, only the script
+    debug_log::enable(DebugCategories::default());
+    let mut interpreter = match options.no_prelude {
+        true => Interpreter::without_prelude(),
+        false => Interpreter::new(),
+    };
+    // Everything after the script path is exposed to the script through `arg(i)`
+    interpreter.script_args = options.script_args.clone();
+    if options.deterministic {
+        interpreter.make_deterministic();
+    }
+    if options.ieee_division {
+        interpreter.division_by_zero = DivisionByZero::Ieee;
+    }
+    interpreter.strict = options.strict;
+    interpreter.warnings = options.warnings.unwrap_or_default();
+    if let Some(capabilities) = &options.allow {
+        interpreter.capabilities = capabilities.clone();
     }
+    if let Some(dir) = &options.fs_root {
+        match FileSystem::root(dir) {
+            Ok(files) => interpreter.files = files,
+            Err(err) => {
+                eprintln!("Could not use '{dir}' as the file root: {err}");
+                std::process::exit(66);
+            }
+        }
+    }
+    if let Some(path) = &options.prelude {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("Could not read the prelude '{path}': {err}");
+                std::process::exit(66);
+            }
+        };
+        if let Err(err) = run(source, &mut interpreter, false) {
+            eprintln!("The prelude '{path}' failed.");
+            std::process::exit(err.exit_code());
+        }
+    }
+    debug_log::enable(options.debug);
+    if options.trace {
+        interpreter.add_hooks(Rc::new(RefCell::new(Tracer::new())));
+    }
+    // `debugger;` statements only pause when a debugger is registered
+    interpreter.add_hooks(Rc::new(RefCell::new(Debugger::new())));
+    interpreter
 }
 
-fn run(source: String, interpreter: &mut Interpreter) {
-    // Lexical Analysis
+/// Parses the script again to know its executable lines and prints or writes the report
+fn report_coverage(coverage: &Coverage, format: CoverageFormat, path: &str) {
+    let Ok(source) = std::fs::read_to_string(path) else {
+        eprintln!("Could not read '{path}' to report its coverage.");
+        return;
+    };
+    let mut statements = Vec::new();
+    // Any error in the script was already reported by the run itself
+    capture_diagnostics(|| {
+        statements = Parser::new(Scanner::new(source.clone()).scan_tokens()).parse();
+    });
+    let report = coverage.report(format, path, &source, &statements);
+    match format {
+        CoverageFormat::Lcov => match std::fs::write("lcov.info", report) {
+            Ok(_) => eprintln!("Coverage written to 'lcov.info'."),
+            Err(err) => eprintln!("Could not write 'lcov.info': {err}"),
+        },
+        _ => eprint!("\n{report}"),
+    }
+}
 
-    let mut scanner = Scanner::new(source);
-    let tokens = scanner.scan_tokens();
+/// Prints the formatted script, or with `check` only compares it against the file.
+/// Returns the process exit code.
+fn format_file(path: &str, check: bool, formatter: Formatter) -> i32 {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("Could not read '{path}': {err}");
+            return 66;
+        }
+    };
+    let Ok(statements) = parse(source.clone(), false) else {
+        return 65;
+    };
+    // The source scanned without errors, only its comments are needed now
+    let mut scanner = Scanner::new(source.clone());
+    scanner.scan_tokens();
+    let formatted = formatter
+        .with_comments(scanner.comments())
+        .format(&statements);
+    match check {
+        true if formatted != source => {
+            eprintln!("'{path}' is not formatted.");
+            1
+        }
+        true => 0,
+        false => {
+            print!("{formatted}");
+            0
+        }
+    }
+}
+
+/// Prints the script translated to the target language, returns the exit code
+fn transpile_file(path: &str, target: Target) -> i32 {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("Could not read '{path}': {err}");
+            return 66;
+        }
+    };
+    // Scripts the interpreter would refuse (e.g. `return` at the top level) are not translated
+    let Ok(statements) = compile(source, &mut Interpreter::new(), false) else {
+        return 65;
+    };
+    match target {
+        Target::JavaScript => print!("{}", JsTranspiler::new().transpile(&statements)),
+    }
+    0
+}
 
-    // Parsing
-    let mut parser = Parser::new(tokens);
-    let statements = parser.parse();
+/// Prints what `--emit` asked for instead of running the script, returns the exit code
+fn emit_file(path: &str, emit: Emit) -> i32 {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("Could not read '{path}': {err}");
+            return 66;
+        }
+    };
+    match emit {
+        Emit::HighlightJson => println!("{}", highlight::to_json(&highlight::highlight(&source))),
+        Emit::Types => {
+            let Ok(statements) = compile(source, &mut Interpreter::new(), false) else {
+                return 65;
+            };
+            for declaration in type_checker::declared_types(&statements) {
+                println!("{declaration}");
+            }
+        }
+    }
+    0
+}
 
-    if parser.had_error {
-        return; // Stop if there was a parse error.
+/// Prints every lint finding, returns 1 as exit code when there was any
+fn lint_file(path: &str, config: &LintConfig) -> i32 {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("Could not read '{path}': {err}");
+            return 66;
+        }
+    };
+    let Ok(statements) = parse(source, false) else {
+        return 65;
+    };
+    let findings = Linter::new(config.clone()).lint(&statements);
+    for finding in &findings {
+        println!("{finding}");
+    }
+    match findings.is_empty() {
+        true => 0,
+        false => 1,
     }
+}
 
-    // Resolving
-    let mut resolver = Resolver::new(interpreter);
-    resolver.resolve(&statements);
+/// Prints the writes of a recorded trace or answers the query, returns the exit code
+fn replay_trace(path: &str, query: &Option<TraceQuery>) -> i32 {
+    let log = match std::fs::read_to_string(path) {
+        Ok(log) => log,
+        Err(err) => {
+            eprintln!("Could not read '{path}': {err}");
+            return 66;
+        }
+    };
+    let trace = match Trace::from_log(&log) {
+        Ok(trace) => trace,
+        Err(message) => {
+            eprintln!("'{path}': {message}");
+            return 65;
+        }
+    };
+    let Some(query) = query else {
+        for write in trace.writes() {
+            println!("{write}");
+        }
+        println!("{trace}");
+        return 0;
+    };
+    match trace.value_at(query) {
+        Ok(value) => {
+            println!("{value}");
+            0
+        }
+        Err(message) => {
+            eprintln!("{message}");
+            1
+        }
+    }
+}
 
-    if resolver.had_error {
-        return; // Stop if there was a resolution error.
+/// Serves LSP over stdio, returns the process exit code
+fn run_language_server() -> i32 {
+    let mut stdout = io::stdout();
+    match LanguageServer::new().serve(&mut io::stdin().lock(), &mut stdout) {
+        Ok(exit_code) => exit_code,
+        Err(err) => {
+            eprintln!("Language server failed: {err}");
+            1
+        }
+    }
+}
+
+/// Prints the description of an error code, returns the process exit code
+fn explain_code(code: &str) -> i32 {
+    match error_codes::find(code) {
+        Some(error_code) => {
+            print!("{}", error_code.explain());
+            0
+        }
+        None => {
+            eprintln!("Unknown error code '{code}'.");
+            64
+        }
+    }
+}
+
+/// Connections only get the capabilities given by `--allow`, the clock without it
+fn serve_prompt(port: u16, options: &Options) -> i32 {
+    let capabilities = options
+        .allow
+        .clone()
+        .unwrap_or_else(|| Capabilities::none().grant(Capability::Time));
+    let new_interpreter = move || {
+        let mut interpreter = Interpreter::new();
+        interpreter.capabilities = capabilities.clone();
+        interpreter
+    };
+    match server::serve(port, new_interpreter) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("Could not serve the prompt on port {port}: {err}");
+            74
+        }
+    }
+}
+
+fn run_stdin(interpreter: &mut Interpreter, options: &Options) -> Result<(), RunError> {
+    let mut source = String::new();
+    io::stdin()
+        .read_to_string(&mut source)
+        .map_err(RunError::Io)?;
+    run_script(source, interpreter, options)
+}
+
+/// Runs a whole script, its diagnostics show the line they point at. With `--time` the
+/// stages' durations go to stderr afterwards.
+fn run_script(
+    source: String,
+    interpreter: &mut Interpreter,
+    options: &Options,
+) -> Result<(), RunError> {
+    let source_map = SourceMap::new(&source);
+    error::with_snippets(source_map, || run_source(source, interpreter, options))
+}
+
+fn run_source(
+    source: String,
+    interpreter: &mut Interpreter,
+    options: &Options,
+) -> Result<(), RunError> {
+    if let Some(dir) = &options.cache {
+        return run_cached(source, interpreter, Path::new(dir));
+    }
+    if options.closures {
+        return run_compiled(source, interpreter);
+    }
+    if !options.time {
+        return run(source, interpreter, false);
     }
-    // Run Interpreter
-    interpreter.interpret(&statements);
+    let (result, timings) = run_timed(source, interpreter, false);
+    eprintln!("\n{timings}");
+    result
 }
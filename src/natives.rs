@@ -0,0 +1,2331 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    sync::OnceLock,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+};
+
+#[cfg(feature = "checksums")]
+use crate::digest;
+use crate::{
+    capabilities::Capability,
+    environment::EnvironmentRef,
+    error::LoxErrorResult,
+    error_codes::*,
+    interpreter::{Clock, Interpreter},
+    locale::Language,
+    lox_class::LoxClass,
+    lox_instance::LoxInstance,
+    lox_native_function::LoxNativeFunction,
+    object::{LoxListRef, Object},
+    shared::Rc,
+    test_runner::{TestFailure, TestOutcome},
+    token::Token,
+    utils::{calendar::DateTime, random::Random},
+};
+#[cfg(feature = "net")]
+use crate::{http, lox_foreign::LoxForeign, tcp::TcpConnection};
+
+type NativeFn = fn(&mut Interpreter, Vec<Object>, &Token) -> Result<Object, LoxErrorResult>;
+
+/// Defines every built-in native function in the given (global) environment
+pub fn define_natives(globals: &EnvironmentRef) {
+    for native in default_natives() {
+        globals
+            .borrow_mut()
+            .define(native.name.clone(), Object::NativeFunction(native));
+    }
+}
+
+/// Every built-in native function, for embedders choosing which ones scripts see
+/// (`InterpreterBuilder::with_natives`)
+pub fn default_natives() -> Vec<LoxNativeFunction> {
+    let natives = vec![
+        native("clock", 0, clock).requires(Capability::Time),
+        native("clockMonotonic", 0, clock_monotonic).requires(Capability::Time),
+        native("sleep", 1, sleep).requires(Capability::Time),
+        native("bench", 2, bench).requires(Capability::Time),
+        native("timeFormat", 2, time_format),
+        native("now", 0, clock).requires(Capability::Time),
+        native("dateParts", 1, date_parts),
+        native("dateFromParts", 6, date_from_parts),
+        native("random", 0, random),
+        native("randomInt", 2, random_int),
+        native("randomSeed", 1, random_seed),
+        native("readLine", 0, read_line).requires(Capability::Io),
+        native("readFile", 1, read_file).requires(Capability::Fs),
+        native("writeFile", 2, write_file).requires(Capability::Fs),
+        native("appendFile", 2, append_file).requires(Capability::Fs),
+        native("split", 2, split),
+        native("join", 2, join),
+        native("parseNumber", 1, parse_number),
+        native("int", 1, int),
+        native("float", 1, float),
+        native("toString", 1, to_string),
+        native("hash", 1, hash),
+        native("toFixed", 2, to_fixed),
+        native("toPrecision", 2, to_precision),
+        native("toStringBase", 2, to_string_base),
+        native("format", 1, format).variadic(),
+        native("eprint", 1, eprint).requires(Capability::Io),
+        native("list", 0, list),
+        native("len", 1, len),
+        native("byteLen", 1, byte_len),
+        native("get", 2, get),
+        native("slice", 3, slice),
+        native("codePointAt", 2, code_point_at),
+        native("fromCodePoint", 1, from_code_point),
+        native("push", 2, push),
+        native("pop", 1, pop),
+        native("contains", 2, contains),
+        native("next", 1, next),
+        native("inspect", 1, inspect),
+        native("map", 2, map),
+        native("filter", 2, filter),
+        native("reduce", 3, reduce),
+        native("sort", 2, sort),
+        native("assert", 2, assert),
+        native("error", 1, error),
+        native("test", 2, test),
+        native("expectEq", 2, expect_eq),
+        native("exit", 1, exit).requires(Capability::Env),
+        native("exec", 2, exec).requires(Capability::Process),
+        native("argc", 0, argc).requires(Capability::Env),
+        native("arg", 1, arg).requires(Capability::Env),
+    ];
+    #[cfg(feature = "checksums")]
+    let natives = [
+        natives,
+        vec![native("sha256", 1, sha256), native("md5", 1, md5)],
+    ]
+    .concat();
+    #[cfg(feature = "net")]
+    let natives = [
+        natives,
+        vec![
+            native("fetch", 1, fetch).requires(Capability::Net),
+            native("httpGet", 2, http_get).requires(Capability::Net),
+            native("tcpConnect", 2, tcp_connect).requires(Capability::Net),
+            native("tcpSend", 2, tcp_send).requires(Capability::Net),
+            native("tcpRecv", 2, tcp_recv).requires(Capability::Net),
+            native("tcpClose", 1, tcp_close).requires(Capability::Net),
+        ],
+    ]
+    .concat();
+    natives
+}
+
+fn native(name: &str, arity: usize, callable: NativeFn) -> LoxNativeFunction {
+    LoxNativeFunction::new(name, arity, callable)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn clock(
+    interpreter: &mut Interpreter,
+    _: Vec<Object>,
+    _: &Token,
+) -> Result<Object, LoxErrorResult> {
+    if let Some(millis) = interpreter.clock.tick() {
+        return Ok(Object::Number(millis));
+    }
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(timestamp) => Ok(Object::Number(timestamp.as_millis() as f64)),
+        Err(err) => Err(LoxErrorResult::system_error(&format!(
+            "Clock returned an invalid duration: {}",
+            &err.to_string()
+        ))),
+    }
+}
+
+/// The system time is not available on `wasm32-unknown-unknown`, the host defines its own
+/// `clock` and `now` (the browser playground uses `Date.now()`)
+#[cfg(target_arch = "wasm32")]
+fn clock(
+    interpreter: &mut Interpreter,
+    _: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    if let Some(millis) = interpreter.clock.tick() {
+        return Ok(Object::Number(millis));
+    }
+    Err(LoxErrorResult::interpreter_error(
+        token.line,
+        N0529.with([]),
+    ))
+}
+
+/// Milliseconds elapsed since the first call, for benchmarks: unlike `clock`
+/// it never jumps when the system time is adjusted
+#[cfg(not(target_arch = "wasm32"))]
+fn clock_monotonic(
+    interpreter: &mut Interpreter,
+    _: Vec<Object>,
+    _: &Token,
+) -> Result<Object, LoxErrorResult> {
+    if let Some(millis) = interpreter.clock.tick() {
+        return Ok(Object::Number(millis));
+    }
+    static START: OnceLock<Instant> = OnceLock::new();
+    let start = START.get_or_init(Instant::now);
+    Ok(Object::Number(start.elapsed().as_secs_f64() * 1000.0))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn clock_monotonic(
+    interpreter: &mut Interpreter,
+    _: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    if let Some(millis) = interpreter.clock.tick() {
+        return Ok(Object::Number(millis));
+    }
+    Err(LoxErrorResult::interpreter_error(
+        token.line,
+        N0528.with(["clockMonotonic".into()]),
+    ))
+}
+
+/// Calls the function `iterations` times after a tenth as many warm-up calls and prints
+/// the fastest and the mean time of a call, returned as the list `[min, mean]` in milliseconds
+fn bench(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let iterations = match arguments[1].as_integer() {
+        Some(iterations) if iterations > 0 => iterations as usize,
+        _ => {
+            return Err(LoxErrorResult::interpreter_error(
+                token.line,
+                N0518.with([]),
+            ))
+        }
+    };
+    let function = &arguments[0];
+    for _ in 0..iterations.div_ceil(10) {
+        interpreter.call_value(function.clone(), Vec::new(), token)?;
+    }
+    let now = |interpreter: &mut Interpreter| {
+        clock_monotonic(interpreter, Vec::new(), token)
+            .map(|millis| millis.as_number().unwrap_or(0.0))
+    };
+    let (mut min, mut total) = (f64::INFINITY, 0.0);
+    for _ in 0..iterations {
+        let started = now(interpreter)?;
+        interpreter.call_value(function.clone(), Vec::new(), token)?;
+        let elapsed = now(interpreter)? - started;
+        min = min.min(elapsed);
+        total += elapsed;
+    }
+    let mean = total / iterations as f64;
+    interpreter.write_line(&format!(
+        "bench {function}: min {min:.3} ms, mean {mean:.3} ms over {iterations} iterations"
+    ));
+    Ok(Object::list(vec![
+        Object::Number(min),
+        Object::Number(mean),
+    ]))
+}
+
+/// Suspends the script for the given number of milliseconds
+#[cfg(not(target_arch = "wasm32"))]
+fn sleep(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let millis = sleep_duration(&arguments[0], token)?;
+    match &mut interpreter.clock {
+        Clock::Virtual(now) => *now += millis,
+        Clock::System => thread::sleep(Duration::from_secs_f64(millis / 1000.0)),
+    }
+    Ok(Object::Nil)
+}
+
+/// Blocking the only thread of the page is not possible in the browser,
+/// only the virtual clock can move forward
+#[cfg(target_arch = "wasm32")]
+fn sleep(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let millis = sleep_duration(&arguments[0], token)?;
+    match &mut interpreter.clock {
+        Clock::Virtual(now) => *now += millis,
+        Clock::System => {
+            return Err(LoxErrorResult::interpreter_error(
+                token.line,
+                N0528.with(["sleep".into()]),
+            ))
+        }
+    }
+    Ok(Object::Nil)
+}
+
+fn sleep_duration(value: &Object, token: &Token) -> Result<f64, LoxErrorResult> {
+    match value.as_number() {
+        Some(millis) if millis >= 0.0 && millis.is_finite() => Ok(millis),
+        _ => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0519.with([]),
+        )),
+    }
+}
+
+/// Formats a `clock()` timestamp in UTC, e.g. `timeFormat(clock(), "%Y-%m-%d %H:%M:%S")`
+fn time_format(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    match (arguments[0].as_number(), &arguments[1]) {
+        (Some(epoch_millis), Object::String(format)) if epoch_millis.is_finite() => {
+            DateTime::from_epoch_millis(epoch_millis)
+                .format(format)
+                .map(|text| Object::String(text.into()))
+                .map_err(|message| LoxErrorResult::interpreter_error(token.line, message))
+        }
+        _ => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0502.with([
+                "timeFormat".into(),
+                term("a timestamp in milliseconds and a format string"),
+            ]),
+        )),
+    }
+}
+
+/// Fields of a date as a `DateParts` instance: `year`, `month` (1 to 12), `day`, `hour`,
+/// `minute`, `second`, `millisecond` and `weekday` (0 for Sunday), in UTC
+fn date_parts(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let epoch_millis = match arguments[0].as_number() {
+        Some(epoch_millis) if epoch_millis.is_finite() => epoch_millis,
+        _ => {
+            return Err(LoxErrorResult::interpreter_error(
+                token.line,
+                N0501.with(["dateParts".into(), term("a timestamp in milliseconds")]),
+            ))
+        }
+    };
+    let date = DateTime::from_epoch_millis(epoch_millis);
+    let instance = LoxInstance::new(LoxClass::new("DateParts".to_string(), None, HashMap::new()));
+    let fields = [
+        ("year", date.year),
+        ("month", date.month),
+        ("day", date.day),
+        ("hour", date.hour),
+        ("minute", date.minute),
+        ("second", date.second),
+        ("millisecond", date.millisecond),
+        ("weekday", date.weekday),
+    ];
+    for (name, value) in fields {
+        instance.borrow_mut().set_field(name, Object::Int(value));
+    }
+    Ok(Object::ClassInstance(instance))
+}
+
+/// Timestamp of `dateFromParts(year, month, day, hour, minute, second)` in UTC
+fn date_from_parts(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let mut parts = Vec::with_capacity(arguments.len());
+    for argument in &arguments {
+        match argument.as_integer() {
+            Some(part) => parts.push(part),
+            _ => {
+                return Err(LoxErrorResult::interpreter_error(
+                    token.line,
+                    N0502.with(["dateFromParts".into(), term("integers")]),
+                ))
+            }
+        }
+    }
+    let date = DateTime {
+        year: parts[0],
+        month: parts[1],
+        day: parts[2],
+        hour: parts[3],
+        minute: parts[4],
+        second: parts[5],
+        millisecond: 0,
+        weekday: 0,
+    };
+    date.to_epoch_millis()
+        .map(Object::Number)
+        .map_err(|message| LoxErrorResult::interpreter_error(token.line, message))
+}
+
+/// Uniform number in `[0, 1)`
+fn random(
+    interpreter: &mut Interpreter,
+    _: Vec<Object>,
+    _: &Token,
+) -> Result<Object, LoxErrorResult> {
+    Ok(Object::Number(interpreter.random.next_f64()))
+}
+
+/// Uniform integer between both bounds, included: `randomInt(1, 6)` rolls a die
+fn random_int(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    match (arguments[0].as_integer(), arguments[1].as_integer()) {
+        (Some(low), Some(high)) if low <= high => {
+            Ok(Object::Int(interpreter.random.range(low, high)))
+        }
+        _ => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0516.with([]),
+        )),
+    }
+}
+
+/// Restarts the sequence of `random` and `randomInt`, the same seed gives the same numbers
+fn random_seed(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    match arguments[0].as_integer() {
+        Some(seed) => {
+            interpreter.random = Random::new(seed as u64);
+            Ok(Object::Nil)
+        }
+        _ => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0517.with([]),
+        )),
+    }
+}
+
+/// Next line of the input without its line break, `nil` at the end of the input
+fn read_line(
+    interpreter: &mut Interpreter,
+    _: Vec<Object>,
+    _: &Token,
+) -> Result<Object, LoxErrorResult> {
+    Ok(interpreter
+        .input
+        .read_line()
+        .map_or(Object::Nil, |line| Object::String(line.into())))
+}
+
+fn read_file(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let Object::String(path) = &arguments[0] else {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0501.with(["readFile".into(), term("a path string")]),
+        ));
+    };
+    let content = interpreter.files.read(path).map_err(|err| {
+        LoxErrorResult::interpreter_error(
+            token.line,
+            N0525.with([path.to_string().into(), err.to_string().into()]),
+        )
+    })?;
+    interpreter.check_string_length(content.len(), token.line)?;
+    Ok(Object::String(content.into()))
+}
+
+fn write_file(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    store_file(interpreter, arguments, token, "writeFile", false)
+}
+
+fn append_file(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    store_file(interpreter, arguments, token, "appendFile", true)
+}
+
+/// Shared by `writeFile` and `appendFile`, returns `true` once the file is written
+fn store_file(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+    name: &str,
+    append: bool,
+) -> Result<Object, LoxErrorResult> {
+    let (Object::String(path), Object::String(contents)) = (&arguments[0], &arguments[1]) else {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0502.with([name.into(), term("a path and a string")]),
+        ));
+    };
+    interpreter
+        .files
+        .write(path, contents, append)
+        .map(|_| Object::Bool(true))
+        .map_err(|err| {
+            LoxErrorResult::interpreter_error(
+                token.line,
+                N0526.with([path.to_string().into(), err.to_string().into()]),
+            )
+        })
+}
+
+/// List of the parts of a string around a separator, an empty separator splits
+/// the string in characters: `split("a,b", ",")` -> `["a", "b"]`
+fn split(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let (Object::String(text), Object::String(separator)) = (&arguments[0], &arguments[1]) else {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0502.with(["split".into(), term("strings")]),
+        ));
+    };
+    let parts = match separator.is_empty() {
+        true => text
+            .chars()
+            .map(|c| Object::String(c.to_string().into()))
+            .collect(),
+        false => text
+            .split(&**separator)
+            .map(|part| Object::String(part.to_string().into()))
+            .collect(),
+    };
+    let parts = Object::list(parts);
+    interpreter.check_size(&parts, token.line)?;
+    Ok(parts)
+}
+
+/// Elements of a list as text, separated by `separator`
+fn join(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let (Object::List(elements), Object::String(separator)) = (&arguments[0], &arguments[1]) else {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0502.with(["join".into(), term("a list and a string")]),
+        ));
+    };
+    let parts: Vec<String> = elements.borrow().iter().map(text_of).collect();
+    let joined = Object::String(parts.join(separator).into());
+    interpreter.check_size(&joined, token.line)?;
+    Ok(joined)
+}
+
+/// Number written in the string, surrounding whitespace allowed, or `nil`
+fn parse_number(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let Object::String(text) = &arguments[0] else {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0501.with(["parseNumber".into(), term("a string")]),
+        ));
+    };
+    if let Ok(int) = text.trim().parse::<i64>() {
+        return Ok(Object::Int(int));
+    }
+    Ok(match text.trim().parse::<f64>() {
+        Ok(number) if number.is_finite() => Object::Number(number),
+        _ => Object::Nil,
+    })
+}
+
+/// The number without its fractional part, as an `Int`
+fn int(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    match arguments[0] {
+        Object::Int(int) => Ok(Object::Int(int)),
+        #[cfg(feature = "bigint")]
+        Object::BigInt(_) => Ok(arguments[0].clone()),
+        // `as` saturates, the bounds keep values past them from turning into `i64::MAX`
+        Object::Number(number) if number.is_finite() && number.abs() < i64::MAX as f64 => {
+            Ok(Object::Int(number.trunc() as i64))
+        }
+        Object::Number(number) => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0513.with([Object::Number(number).to_string().into()]),
+        )),
+        _ => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0501.with(["int".into(), term("a number")]),
+        )),
+    }
+}
+
+/// The number as a float
+fn float(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    match arguments[0].as_number() {
+        Some(number) => Ok(Object::Number(number)),
+        None => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0501.with(["float".into(), term("a number")]),
+        )),
+    }
+}
+
+/// The value as `print` shows it, except strings which are returned as they are
+fn to_string(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    _: &Token,
+) -> Result<Object, LoxErrorResult> {
+    Ok(Object::String(text_of(&arguments[0]).into()))
+}
+
+/// Integer that is the same for equal values during a run: strings, numbers, booleans and
+/// `nil` by their value (`hash(1) == hash(1.0)`), lists and instances by their identity
+fn hash(_: &mut Interpreter, arguments: Vec<Object>, _: &Token) -> Result<Object, LoxErrorResult> {
+    let mut hasher = DefaultHasher::new();
+    match &arguments[0] {
+        Object::String(text) => text.hash(&mut hasher),
+        Object::Bool(value) => value.hash(&mut hasher),
+        Object::Nil => 0u8.hash(&mut hasher),
+        Object::List(elements) => Rc::as_ptr(elements).hash(&mut hasher),
+        Object::ClassInstance(instance) => Rc::as_ptr(instance).hash(&mut hasher),
+        Object::Generator(generator) => Rc::as_ptr(generator).hash(&mut hasher),
+        Object::Module(module) => Rc::as_ptr(module).hash(&mut hasher),
+        Object::Foreign(foreign) => Rc::as_ptr(foreign).hash(&mut hasher),
+        value => match (value.as_integer(), value.as_number()) {
+            (Some(int), _) => int.hash(&mut hasher),
+            (None, Some(number)) => number.to_bits().hash(&mut hasher),
+            // Functions and classes
+            (None, None) => value.to_string().hash(&mut hasher),
+        },
+    }
+    Ok(Object::Int(hasher.finish() as i64))
+}
+
+/// Hex SHA-256 digest of the UTF-8 bytes of the string
+#[cfg(feature = "checksums")]
+fn sha256(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let text = digest_argument(&arguments[0], "sha256", token)?;
+    Ok(Object::String(digest::sha256(text.as_bytes()).into()))
+}
+
+/// Hex MD5 digest of the UTF-8 bytes of the string, to check data against published sums
+#[cfg(feature = "checksums")]
+fn md5(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let text = digest_argument(&arguments[0], "md5", token)?;
+    Ok(Object::String(digest::md5(text.as_bytes()).into()))
+}
+
+#[cfg(feature = "checksums")]
+fn digest_argument<'a>(
+    value: &'a Object,
+    native: &str,
+    token: &Token,
+) -> Result<&'a str, LoxErrorResult> {
+    match value {
+        Object::String(text) => Ok(text),
+        _ => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0501.with([native.into(), term("a string")]),
+        )),
+    }
+}
+
+/// Response of a GET request to an `http://` URL, see `httpGet`
+#[cfg(feature = "net")]
+fn fetch(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    http_get(interpreter, vec![arguments[0].clone(), Object::Nil], token)
+}
+
+/// Response of a GET request to an `http://` URL as a `Response` instance with `status`,
+/// `body` and `headers`; the fields of the headers instance are the header names in lowercase
+/// with `_` for `-` (`response.headers.content_type`). The fields of the second argument,
+/// an instance or `nil`, are sent as headers the same way.
+#[cfg(feature = "net")]
+fn http_get(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let Object::String(url) = &arguments[0] else {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0503.with(["httpGet".into(), term("a URL string")]),
+        ));
+    };
+    let headers = match &arguments[1] {
+        Object::Nil => Vec::new(),
+        Object::ClassInstance(instance) => instance
+            .borrow()
+            .fields()
+            .into_iter()
+            .map(|(name, value)| (name.replace('_', "-"), text_of(&value)))
+            .collect(),
+        _ => {
+            return Err(LoxErrorResult::interpreter_error(
+                token.line,
+                N0504.with(["httpGet".into(), term("an instance or nil")]),
+            ))
+        }
+    };
+    let response = http::get(url, &headers)
+        .map_err(|message| LoxErrorResult::interpreter_error(token.line, message))?;
+    let body = Object::String(response.body.into());
+    interpreter.check_size(&body, token.line)?;
+    let header_fields =
+        LoxInstance::new(LoxClass::new("Headers".to_string(), None, HashMap::new()));
+    for (name, value) in response.headers {
+        header_fields
+            .borrow_mut()
+            .set_field(&name.replace('-', "_"), Object::String(value.into()));
+    }
+    let instance = LoxInstance::new(LoxClass::new("Response".to_string(), None, HashMap::new()));
+    let fields = [
+        ("status", Object::Int(response.status as i64)),
+        ("headers", Object::ClassInstance(header_fields)),
+        ("body", body),
+    ];
+    for (name, value) in fields {
+        instance.borrow_mut().set_field(name, value);
+    }
+    Ok(Object::ClassInstance(instance))
+}
+
+/// Connection to the TCP server at the host and port
+#[cfg(feature = "net")]
+fn tcp_connect(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let (Object::String(host), Some(port)) = (
+        &arguments[0],
+        arguments[1]
+            .as_integer()
+            .and_then(|port| u16::try_from(port).ok()),
+    ) else {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0502.with([
+                "tcpConnect".into(),
+                term("a host string and a port from 0 to 65535"),
+            ]),
+        ));
+    };
+    match TcpConnection::connect(host, port) {
+        Ok(connection) => Ok(Object::Foreign(LoxForeign::new(
+            "TcpConnection",
+            connection,
+        ))),
+        Err(err) => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0534.with([format!("{host}:{port}").into(), err.to_string().into()]),
+        )),
+    }
+}
+
+/// Sends the UTF-8 bytes of the string and returns their number
+#[cfg(feature = "net")]
+fn tcp_send(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let Object::String(data) = &arguments[1] else {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0504.with(["tcpSend".into(), term("a string")]),
+        ));
+    };
+    with_connection(&arguments[0], "tcpSend", token, |connection| {
+        connection.send(data.as_bytes())
+    })?;
+    Ok(Object::Int(data.len() as i64))
+}
+
+/// Text of the next bytes received, at most `maxLen`, or `nil` once the peer closed the
+/// connection. Waits for the server to send something.
+#[cfg(feature = "net")]
+fn tcp_recv(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let max_len = match arguments[1].as_integer() {
+        Some(max_len) if max_len > 0 => max_len as usize,
+        _ => {
+            return Err(LoxErrorResult::interpreter_error(
+                token.line,
+                N0504.with(["tcpRecv".into(), term("a positive integer")]),
+            ))
+        }
+    };
+    interpreter.check_string_length(max_len, token.line)?;
+    let received = with_connection(&arguments[0], "tcpRecv", token, |connection| {
+        connection.receive(max_len)
+    })?;
+    Ok(received.map_or(Object::Nil, |text| Object::String(text.into())))
+}
+
+/// Closes the connection, sending or receiving on it is then an error
+#[cfg(feature = "net")]
+fn tcp_close(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    with_connection(&arguments[0], "tcpClose", token, |connection| {
+        connection.close();
+        Ok(())
+    })?;
+    Ok(Object::Nil)
+}
+
+/// Runs `f` on the connection of a `tcpConnect` handle, its errors become runtime errors
+#[cfg(feature = "net")]
+fn with_connection<R>(
+    handle: &Object,
+    native: &str,
+    token: &Token,
+    f: impl FnOnce(&mut TcpConnection) -> std::io::Result<R>,
+) -> Result<R, LoxErrorResult> {
+    let result = match handle {
+        Object::Foreign(foreign) => foreign.borrow_mut(f),
+        _ => None,
+    };
+    match result {
+        Some(Ok(value)) => Ok(value),
+        Some(Err(err)) => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0535.with([native.into(), err.to_string().into()]),
+        )),
+        None => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0503.with([native.into(), term("a connection from 'tcpConnect'")]),
+        )),
+    }
+}
+
+/// Runs the program with the arguments, a list, and waits for it to finish. Returns a
+/// `ProcessResult` instance with `exitCode` (`nil` when a signal ended it), `stdout` and
+/// `stderr`. The program inherits the working directory and environment of the interpreter.
+fn exec(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let Object::String(command) = &arguments[0] else {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0503.with(["exec".into(), term("a command string")]),
+        ));
+    };
+    let args: Vec<String> = match &arguments[1] {
+        Object::List(elements) => elements.borrow().iter().map(text_of).collect(),
+        _ => {
+            return Err(LoxErrorResult::interpreter_error(
+                token.line,
+                N0504.with(["exec".into(), term("a list")]),
+            ))
+        }
+    };
+    let output = std::process::Command::new(&**command)
+        .args(&args)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|err| {
+            LoxErrorResult::interpreter_error(
+                token.line,
+                N0527.with([command.to_string().into(), err.to_string().into()]),
+            )
+        })?;
+    let stdout = Object::String(String::from_utf8_lossy(&output.stdout).into());
+    let stderr = Object::String(String::from_utf8_lossy(&output.stderr).into());
+    interpreter.check_size(&stdout, token.line)?;
+    interpreter.check_size(&stderr, token.line)?;
+    let instance = LoxInstance::new(LoxClass::new(
+        "ProcessResult".to_string(),
+        None,
+        HashMap::new(),
+    ));
+    let exit_code = output
+        .status
+        .code()
+        .map_or(Object::Nil, |code| Object::Int(code as i64));
+    let fields = [
+        ("exitCode", exit_code),
+        ("stdout", stdout),
+        ("stderr", stderr),
+    ];
+    for (name, value) in fields {
+        instance.borrow_mut().set_field(name, value);
+    }
+    Ok(Object::ClassInstance(instance))
+}
+
+/// The number with `digits` digits after the point, `toFixed(2.345, 2)` is `"2.35"`
+fn to_fixed(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let number = number_argument(&arguments[0], "toFixed", token)?;
+    let digits = digits_argument(&arguments[1], "toFixed", 0..=100, token)?;
+    if !number.is_finite() {
+        return Ok(Object::String(arguments[0].to_string().into()));
+    }
+    Ok(Object::String(format!("{number:.digits$}").into()))
+}
+
+/// The number rounded to `digits` significant digits, in exponent notation (`1.2e+21`)
+/// when the exponent is below -6 or not below `digits`
+fn to_precision(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let number = number_argument(&arguments[0], "toPrecision", token)?;
+    let digits = digits_argument(&arguments[1], "toPrecision", 1..=100, token)?;
+    if !number.is_finite() {
+        return Ok(Object::String(arguments[0].to_string().into()));
+    }
+    // The exponent after rounding, `9.99` to 2 digits is `10`
+    let scientific = format!("{number:.*e}", digits - 1);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i64 = exponent.parse().unwrap();
+    let text = match exponent < -6 || exponent >= digits as i64 {
+        true => format!("{mantissa}e{exponent:+}"),
+        false => format!("{number:.*}", (digits as i64 - 1 - exponent) as usize),
+    };
+    Ok(Object::String(text.into()))
+}
+
+/// The integer written in `base`, from 2 to 36, `toStringBase(255, 16)` is `"ff"`
+fn to_string_base(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    #[cfg(feature = "bigint")]
+    if let Object::BigInt(int) = &arguments[0] {
+        let base = digits_argument(&arguments[1], "toStringBase", 2..=36, token)?;
+        return Ok(Object::String(int.to_str_radix(base as u32).into()));
+    }
+    let Some(int) = arguments[0].as_integer() else {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0503.with(["toStringBase".into(), term("an integer")]),
+        ));
+    };
+    let base = digits_argument(&arguments[1], "toStringBase", 2..=36, token)? as u64;
+    let mut magnitude = int.unsigned_abs();
+    let mut digits = Vec::new();
+    loop {
+        digits.push(std::char::from_digit((magnitude % base) as u32, base as u32).unwrap());
+        magnitude /= base;
+        if magnitude == 0 {
+            break;
+        }
+    }
+    if int < 0 {
+        digits.push('-');
+    }
+    Ok(Object::String(
+        digits.iter().rev().collect::<String>().into(),
+    ))
+}
+
+fn number_argument(value: &Object, native: &str, token: &Token) -> Result<f64, LoxErrorResult> {
+    value.as_number().ok_or_else(|| {
+        LoxErrorResult::interpreter_error(token.line, N0503.with([native.into(), term("a number")]))
+    })
+}
+
+/// Second argument of the number formatting natives, an integer within `range`
+fn digits_argument(
+    value: &Object,
+    native: &str,
+    range: std::ops::RangeInclusive<i64>,
+    token: &Token,
+) -> Result<usize, LoxErrorResult> {
+    match value.as_integer() {
+        Some(digits) if range.contains(&digits) => Ok(digits as usize),
+        _ => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0537.with([
+                native.into(),
+                range.start().to_string().into(),
+                range.end().to_string().into(),
+                value.to_string().into(),
+            ]),
+        )),
+    }
+}
+
+/// The template with every `{}` replaced by the next value as `toString` shows it,
+/// `{{` and `}}` stand for literal braces: `format("{} + {} = {}", 1, 2, 3)`
+fn format(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let Object::String(template) = &arguments[0] else {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0503.with(["format".into(), term("a string")]),
+        ));
+    };
+    let mut values = arguments[1..].iter();
+    let mut placeholders = 0;
+    let mut formatted = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(char) = chars.next() {
+        match (char, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                formatted.push(char);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                placeholders += 1;
+                if let Some(value) = values.next() {
+                    formatted.push_str(&text_of(value));
+                }
+            }
+            ('{' | '}', _) => {
+                return Err(LoxErrorResult::interpreter_error(
+                    token.line,
+                    N0522.with([char.to_string().into(), format!("{char}{char}").into()]),
+                ))
+            }
+            _ => formatted.push(char),
+        }
+    }
+    if placeholders != arguments.len() - 1 {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0521.with([
+                placeholders.to_string().into(),
+                (arguments.len() - 1).to_string().into(),
+            ]),
+        ));
+    }
+    interpreter.check_string_length(formatted.len(), token.line)?;
+    Ok(Object::String(formatted.into()))
+}
+
+/// Prints the value as `toString` shows it to the error stream
+fn eprint(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    _: &Token,
+) -> Result<Object, LoxErrorResult> {
+    interpreter.write_diagnostic(&text_of(&arguments[0]));
+    Ok(Object::Nil)
+}
+
+fn text_of(value: &Object) -> String {
+    match value {
+        Object::String(text) => text.to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// A new empty list
+fn list(_: &mut Interpreter, _: Vec<Object>, _: &Token) -> Result<Object, LoxErrorResult> {
+    Ok(Object::list(Vec::new()))
+}
+
+/// Number of elements of a list or characters (code points) of a string, see `byteLen`
+fn len(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    match &arguments[0] {
+        Object::List(elements) => Ok(Object::Int(elements.borrow().len() as i64)),
+        Object::String(text) => Ok(Object::Int(text.chars().count() as i64)),
+        _ => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0501.with(["len".into(), term("a list or a string")]),
+        )),
+    }
+}
+
+/// Element of a list or character of a string at an index starting at 0, with `len` it is
+/// how scripts iterate: `for (var i = 0; i < len(items); i = i + 1) print get(items, i);`
+fn get(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let index = index_argument(&arguments[1], token)?;
+    let (element, out_of_range) = match &arguments[0] {
+        Object::List(elements) => {
+            let elements = elements.borrow();
+            let length = elements.len().to_string();
+            (
+                elements.get(index).cloned(),
+                N0508.with([index.to_string().into(), length.into()]),
+            )
+        }
+        Object::String(text) => {
+            let character = text.chars().nth(index);
+            let length = text.chars().count().to_string();
+            (
+                character.map(|character| Object::String(character.to_string().into())),
+                N0509.with([index.to_string().into(), length.into()]),
+            )
+        }
+        _ => {
+            return Err(LoxErrorResult::interpreter_error(
+                token.line,
+                N0503.with(["get".into(), term("a list or a string")]),
+            ))
+        }
+    };
+    element.ok_or_else(|| LoxErrorResult::interpreter_error(token.line, out_of_range))
+}
+
+/// Number of bytes of a string in UTF-8, more than `len` when it has non-ASCII characters
+fn byte_len(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    match &arguments[0] {
+        Object::String(text) => Ok(Object::Int(text.len() as i64)),
+        _ => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0501.with(["byteLen".into(), term("a string")]),
+        )),
+    }
+}
+
+/// Elements of a list or characters of a string from `start` up to `end` excluded,
+/// a list is copied
+fn slice(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let start = index_argument(&arguments[1], token)?;
+    let end = index_argument(&arguments[2], token)?;
+    let out_of_range = |code: &'static ErrorCode, length: usize| {
+        Err(LoxErrorResult::interpreter_error(
+            token.line,
+            code.with([
+                start.to_string().into(),
+                end.to_string().into(),
+                length.to_string().into(),
+            ]),
+        ))
+    };
+    if start > end {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0511.with([start.to_string().into(), end.to_string().into()]),
+        ));
+    }
+    match &arguments[0] {
+        Object::List(elements) => {
+            let elements = elements.borrow();
+            match elements.get(start..end) {
+                Some(part) => Ok(Object::list(part.to_vec())),
+                None => out_of_range(&N0510, elements.len()),
+            }
+        }
+        Object::String(text) => {
+            // Byte offsets of the characters, and of the end of the string
+            let offsets: Vec<usize> = text
+                .char_indices()
+                .map(|(offset, _)| offset)
+                .chain([text.len()])
+                .collect();
+            match (offsets.get(start), offsets.get(end)) {
+                (Some(&from), Some(&to)) => Ok(Object::String(text[from..to].into())),
+                _ => out_of_range(&N0539, text.chars().count()),
+            }
+        }
+        _ => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0503.with(["slice".into(), term("a list or a string")]),
+        )),
+    }
+}
+
+/// Unicode code point of the character of a string at an index starting at 0
+fn code_point_at(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let Object::String(text) = &arguments[0] else {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0503.with(["codePointAt".into(), term("a string")]),
+        ));
+    };
+    let index = index_argument(&arguments[1], token)?;
+    match text.chars().nth(index) {
+        Some(character) => Ok(Object::Int(character as i64)),
+        None => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0509.with([
+                index.to_string().into(),
+                text.chars().count().to_string().into(),
+            ]),
+        )),
+    }
+}
+
+/// String of the single character with the code point, which must be a Unicode scalar value
+/// (surrogates like `0xD800` are not)
+fn from_code_point(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let Some(code_point) = arguments[0].as_integer() else {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0501.with(["fromCodePoint".into(), term("an integer")]),
+        ));
+    };
+    match u32::try_from(code_point).ok().and_then(char::from_u32) {
+        Some(character) => Ok(Object::String(character.to_string().into())),
+        None => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0514.with([code_point.to_string().into()]),
+        )),
+    }
+}
+
+/// Index given to `get`, `slice` and `codePointAt`
+fn index_argument(value: &Object, token: &Token) -> Result<usize, LoxErrorResult> {
+    match value.as_integer() {
+        Some(index) if index >= 0 => Ok(index as usize),
+        _ => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0507.with([]),
+        )),
+    }
+}
+
+/// Adds the value at the end of the list
+fn push(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let elements = list_argument(&arguments[0], "push", token)?;
+    interpreter.check_list_length(elements.borrow().len() + 1, token.line)?;
+    elements.borrow_mut().push(arguments[1].clone());
+    Ok(Object::Nil)
+}
+
+/// Removes and returns the last element of the list
+fn pop(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let elements = list_argument(&arguments[0], "pop", token)?;
+    let last = elements.borrow_mut().pop();
+    last.ok_or_else(|| LoxErrorResult::interpreter_error(token.line, N0512.with([])))
+}
+
+/// Runs a generator up to its next `yield`, `nil` once it finished
+fn next(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    match &arguments[0] {
+        Object::Generator(generator) => Ok(generator
+            .resume(interpreter, token.line)?
+            .unwrap_or(Object::Nil)),
+        _ => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0501.with(["next".into(), term("a generator")]),
+        )),
+    }
+}
+
+/// What a closure captured, scope by scope from the innermost one, or the fields
+/// of an instance, one variable per line:
+/// `inspect(counter)` gives "<fun counter>\n[scope 0]\n  count = 1"
+fn inspect(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let (title, sections) = match &arguments[0] {
+        Object::Function(function) => {
+            let scopes = function.captured_scopes().into_iter().enumerate();
+            let sections = scopes.map(|(depth, scope)| (format!("[scope {depth}]"), scope));
+            (function.to_string(), sections.collect())
+        }
+        Object::ClassInstance(instance) => {
+            let instance = instance.borrow();
+            let title = format!("<{} instance>", instance.class_name());
+            (title, vec![("[fields]".to_string(), instance.fields())])
+        }
+        _ => {
+            return Err(LoxErrorResult::interpreter_error(
+                token.line,
+                N0501.with(["inspect".into(), term("a function or an instance")]),
+            ))
+        }
+    };
+    let mut lines = vec![title];
+    for (header, variables) in sections {
+        lines.push(header);
+        for (name, value) in variables {
+            lines.push(format!("  {name} = {value}"));
+        }
+    }
+    Ok(Object::String(lines.join("\n").into()))
+}
+
+/// Whether a list has an element equal to the value, or a string contains the substring
+fn contains(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    match (&arguments[0], &arguments[1]) {
+        (Object::List(elements), value) => Ok(Object::Bool(elements.borrow().contains(value))),
+        (Object::String(text), Object::String(part)) => Ok(Object::Bool(text.contains(&**part))),
+        _ => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0502.with([
+                "contains".into(),
+                term("a list and a value, or two strings"),
+            ]),
+        )),
+    }
+}
+
+/// New list with the results of calling the function with every element
+fn map(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let mut results = Vec::new();
+    // The callback may change the list, it iterates over the elements of the call time
+    let elements = list_argument(&arguments[0], "map", token)?.borrow().clone();
+    for element in elements {
+        results.push(interpreter.call_value(arguments[1].clone(), vec![element], token)?);
+    }
+    Ok(Object::list(results))
+}
+
+/// New list with the elements for which the function returns a truthy value
+fn filter(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let mut kept = Vec::new();
+    let elements = list_argument(&arguments[0], "filter", token)?
+        .borrow()
+        .clone();
+    for element in elements {
+        let keep = interpreter.call_value(arguments[1].clone(), vec![element.clone()], token)?;
+        if !matches!(keep, Object::Nil | Object::Bool(false)) {
+            kept.push(element);
+        }
+    }
+    Ok(Object::list(kept))
+}
+
+/// Folds the list from the left: `reduce(numbers, add, 0)`
+fn reduce(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let elements = list_argument(&arguments[0], "reduce", token)?
+        .borrow()
+        .clone();
+    let mut accumulator = arguments[2].clone();
+    for element in elements {
+        accumulator =
+            interpreter.call_value(arguments[1].clone(), vec![accumulator, element], token)?;
+    }
+    Ok(accumulator)
+}
+
+/// Sorts the list in place and returns it. The comparator returns a negative number when
+/// its first argument goes first, a positive one when it goes after and 0 to keep the order.
+fn sort(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let list = list_argument(&arguments[0], "sort", token)?;
+    let elements = list.borrow().clone();
+    let comparator = &arguments[1];
+    let mut goes_after = |left: &Object, right: &Object| {
+        let pair = vec![left.clone(), right.clone()];
+        match interpreter
+            .call_value(comparator.clone(), pair, token)?
+            .as_number()
+        {
+            Some(order) => Ok(order > 0.0),
+            None => Err(LoxErrorResult::interpreter_error(
+                token.line,
+                N0515.with([]),
+            )),
+        }
+    };
+    let sorted = merge_sort(elements, &mut goes_after)?;
+    *list.borrow_mut() = sorted;
+    Ok(arguments[0].clone())
+}
+
+/// Stable sort that stops at the first comparison error, a comparator that is not
+/// consistent only gives an unspecified order
+fn merge_sort(
+    mut elements: Vec<Object>,
+    goes_after: &mut impl FnMut(&Object, &Object) -> Result<bool, LoxErrorResult>,
+) -> Result<Vec<Object>, LoxErrorResult> {
+    if elements.len() <= 1 {
+        return Ok(elements);
+    }
+    let right = elements.split_off(elements.len() / 2);
+    let left = merge_sort(elements, goes_after)?;
+    let right = merge_sort(right, goes_after)?;
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let (mut left, mut right) = (left.into_iter().peekable(), right.into_iter().peekable());
+    while let (Some(first), Some(second)) = (left.peek(), right.peek()) {
+        let next = match goes_after(first, second)? {
+            true => right.next(),
+            false => left.next(),
+        };
+        merged.extend(next);
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+fn list_argument(
+    value: &Object,
+    native: &str,
+    token: &Token,
+) -> Result<LoxListRef, LoxErrorResult> {
+    match value {
+        Object::List(elements) => Ok(elements.clone()),
+        _ => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0503.with([native.into(), term("a list")]),
+        )),
+    }
+}
+
+/// Raises a runtime error at the call when the condition is falsey
+fn assert(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    match arguments[0] {
+        Object::Nil | Object::Bool(false) => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0523.with([text_of(&arguments[1]).into()]),
+        )),
+        _ => Ok(Object::Nil),
+    }
+}
+
+/// Runs the function as a named test: a runtime error in it fails the test without
+/// stopping the script. Returns whether it passed.
+fn test(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    let Object::String(name) = &arguments[0] else {
+        return Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0503.with(["test".into(), term("a string")]),
+        ));
+    };
+    interpreter.mismatch = None;
+    let failure = match interpreter.call_value(arguments[1].clone(), Vec::new(), token) {
+        Ok(_) => None,
+        Err(LoxErrorResult::Interpreter { line, message }) => Some(TestFailure {
+            line,
+            message: message.text(Language::current()),
+            mismatch: interpreter.mismatch.take(),
+        }),
+        Err(err) => return Err(err),
+    };
+    match &failure {
+        None => interpreter.write_line(&format!("test {name} ... ok")),
+        Some(failure) => interpreter.write_line(&format!(
+            "test {name} ... FAILED: [Line {}] {}",
+            failure.line, failure.message
+        )),
+    }
+    let passed = failure.is_none();
+    interpreter.tests.push(TestOutcome {
+        name: name.to_string(),
+        failure,
+    });
+    Ok(Object::Bool(passed))
+}
+
+/// Raises a runtime error showing both values unless `actual` equals `expected`.
+/// Lists are compared element by element, everything else as `==` does.
+fn expect_eq(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    if values_equal(&arguments[0], &arguments[1]) {
+        return Ok(Object::Nil);
+    }
+    let (actual, expected) = (arguments[0].to_string(), arguments[1].to_string());
+    let message = N0524.with([expected.as_str().into(), actual.as_str().into()]);
+    interpreter.mismatch = Some((expected, actual));
+    Err(LoxErrorResult::interpreter_error(token.line, message))
+}
+
+fn values_equal(left: &Object, right: &Object) -> bool {
+    elements_equal(left, right, &mut HashSet::new())
+}
+
+/// Compares lists element by element. A pair of lists met again while comparing them is
+/// taken as equal, so lists containing themselves compare without endless recursion.
+fn elements_equal(
+    left: &Object,
+    right: &Object,
+    compared: &mut HashSet<(*const (), *const ())>,
+) -> bool {
+    match (left, right) {
+        (Object::List(left), Object::List(right)) => {
+            if Rc::ptr_eq(left, right) {
+                return true;
+            }
+            let pair = (
+                Rc::as_ptr(left) as *const (),
+                Rc::as_ptr(right) as *const (),
+            );
+            if !compared.insert(pair) {
+                return true;
+            }
+            let (left, right) = (left.borrow(), right.borrow());
+            left.len() == right.len()
+                && left
+                    .iter()
+                    .zip(right.iter())
+                    .all(|(left, right)| elements_equal(left, right, compared))
+        }
+        _ => left == right,
+    }
+}
+
+/// Raises a runtime error with the given message
+fn error(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    Err(LoxErrorResult::interpreter_error(
+        token.line,
+        Message::Plain(text_of(&arguments[0])),
+    ))
+}
+
+/// Stops the script, the process ends with the given exit code
+fn exit(
+    _: &mut Interpreter,
+    arguments: Vec<Object>,
+    token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    match arguments[0].as_integer() {
+        Some(code) if (0..=255).contains(&code) => Err(LoxErrorResult::exit_signal(code as i32)),
+        _ => Err(LoxErrorResult::interpreter_error(
+            token.line,
+            N0520.with([]),
+        )),
+    }
+}
+
+/// Number of arguments given to the script: `rlox script.lox a b` -> 2
+fn argc(
+    interpreter: &mut Interpreter,
+    _: Vec<Object>,
+    _: &Token,
+) -> Result<Object, LoxErrorResult> {
+    Ok(Object::Int(interpreter.script_args.len() as i64))
+}
+
+/// Script argument at the given index or `nil` when it is out of range
+fn arg(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Object>,
+    current_token: &Token,
+) -> Result<Object, LoxErrorResult> {
+    match arguments[0].as_integer() {
+        Some(index) if index >= 0 => Ok(interpreter
+            .script_args
+            .get(index as usize)
+            .map_or(Object::Nil, |value| Object::String(value.as_str().into()))),
+        _ => Err(LoxErrorResult::interpreter_error(
+            current_token.line,
+            N0506.with([]),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod natives_tests {
+    use std::collections::{HashMap, VecDeque};
+
+    use crate::{
+        capabilities::Capabilities,
+        interpreter::{FileSystem, Input},
+        shared::{Rc, RefCell},
+        token::TokenType,
+    };
+
+    use super::*;
+
+    fn make_token_paren() -> Token {
+        Token::new(TokenType::RightParen, ")".to_string(), Object::Nil, 1)
+    }
+
+    #[test]
+    fn test_denied_capability() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        interpreter.capabilities = Capabilities::none().grant(Capability::Time);
+        // Act
+        let output = crate::run_captured("clock();\nreadFile(\"a.txt\");", &mut interpreter);
+        // Assert
+        assert_eq!(
+            output.diagnostics,
+            "[Line 2] - Error[I0327]: Native 'readFile' needs the 'fs' capability, which was not granted.\n"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_exec() {
+        // Arrange
+        let source = "var args = list();
+push(args, \"-c\");
+push(args, \"echo out $0; echo err >&2; exit 3\");
+push(args, 7);
+var result = exec(\"sh\", args);
+print result.exitCode;
+print result.stdout;
+print result.stderr;
+exec(\"no-such-program-rlox\", list());";
+        // Act
+        let output = crate::run_captured(source, &mut Interpreter::new());
+        // Assert
+        assert_eq!(output.stdout, "3\n\"out 7\n\"\n\"err\n\"\n");
+        assert!(output
+            .diagnostics
+            .starts_with("[Line 9] - Error[N0527]: Could not run 'no-such-program-rlox': "));
+    }
+
+    #[test]
+    fn test_bench() {
+        // Arrange
+        let source = "var calls = 0;
+fun work() { calls = calls + 1; }
+var timings = bench(work, 20);
+print calls;
+print timings;
+bench(work, 0);";
+        let mut interpreter = Interpreter::new();
+        // Each reading of the virtual clock advances it by a millisecond
+        interpreter.make_deterministic();
+        // Act
+        let output = crate::run_captured(source, &mut interpreter);
+        // Assert
+        assert_eq!(
+            output.stdout,
+            "bench <fun work>: min 1.000 ms, mean 1.000 ms over 20 iterations\n22\n[1, 1]\n"
+        );
+        assert_eq!(
+            output.diagnostics,
+            "[Line 6] - Error[N0518]: Iterations of 'bench' must be a positive integer.\n"
+        );
+    }
+
+    #[test]
+    fn test_test_and_expect_eq() {
+        // Arrange
+        let source = "fun lists() { var a = list(); push(a, list()); var b = list(); push(b, list()); expectEq(a, b); }
+fun sum() { expectEq(1 + 1, 3); }
+test(\"lists\", lists);
+test(\"sum\", sum);
+print \"after\";";
+        let mut interpreter = Interpreter::new();
+        // Act
+        let output = crate::run_captured(source, &mut interpreter);
+        // Assert
+        assert_eq!(
+            output.stdout,
+            "test lists ... ok\ntest sum ... FAILED: [Line 2] Expected 3, got 2.\n\"after\"\n"
+        );
+        assert_eq!(
+            interpreter.tests[1].failure,
+            Some(TestFailure {
+                line: 2,
+                message: "Expected 3, got 2.".to_string(),
+                mismatch: Some(("3".to_string(), "2".to_string())),
+            })
+        );
+    }
+
+    #[test]
+    fn test_expect_eq_on_self_containing_lists() {
+        // Arrange
+        let source = "var l = list();
+push(l, l);
+var a = list();
+push(a, 1);
+push(a, a);
+var b = list();
+push(b, 1);
+push(b, b);
+var c = list();
+push(c, 2);
+push(c, c);
+expectEq(l, l);
+expectEq(a, b);
+expectEq(a, c);";
+        let mut interpreter = Interpreter::new();
+        // Act
+        let output = crate::run_captured(source, &mut interpreter);
+        // Assert
+        assert_eq!(
+            output.diagnostics,
+            "[Line 14] - Error[N0524]: Expected [2, [...]], got [1, [...]].\n"
+        );
+    }
+
+    #[test]
+    fn test_inspect() {
+        // Arrange
+        let source = "fun makeCounter() { var count = 0; fun counter() { count = count + 1; } return counter; }
+var counter = makeCounter();
+counter();
+class Point { init(x, y) { this.y = y; this.x = x; } }
+print inspect(counter);
+print inspect(Point(1, 2));
+print inspect(clock);";
+        // Act
+        let output = crate::run_captured(source, &mut Interpreter::new());
+        // Assert
+        assert_eq!(
+            output.stdout,
+            "\"<fun counter>\n[scope 0]\n  count = 1\n  counter = <fun counter>\"
+\"<Point instance>\n[fields]\n  x = 1\n  y = 2\"
+"
+        );
+        assert_eq!(
+            output.diagnostics,
+            "[Line 7] - Error[N0501]: Argument of 'inspect' must be a function or an instance.\n"
+        );
+    }
+
+    #[test]
+    fn test_script_arguments() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        interpreter.script_args = vec!["first".to_string(), "second".to_string()];
+        let paren = make_token_paren();
+        // Act
+        let count = argc(&mut interpreter, vec![], &paren);
+        let second = arg(&mut interpreter, vec![Object::Number(1.0)], &paren);
+        let missing = arg(&mut interpreter, vec![Object::Number(2.0)], &paren);
+        let invalid = arg(&mut interpreter, vec![Object::Number(0.5)], &paren);
+        // Assert
+        assert_eq!(count.ok(), Some(Object::Number(2.0)));
+        assert_eq!(second.ok(), Some(Object::String("second".into())));
+        assert_eq!(missing.ok(), Some(Object::Nil));
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_time_format() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let paren = make_token_paren();
+        let format =
+            |millis: f64, format: &str| vec![Object::Number(millis), Object::String(format.into())];
+        // Act
+        let formatted = time_format(
+            &mut interpreter,
+            format(1_709_210_096_789.0, "%Y-%m-%d %H:%M:%S.%f %%"),
+            &paren,
+        );
+        let before_epoch = time_format(&mut interpreter, format(-1.0, "%Y-%m-%d %H:%M:%S"), &paren);
+        let unknown = time_format(&mut interpreter, format(0.0, "%Q"), &paren);
+        // Assert
+        assert_eq!(
+            formatted.ok(),
+            Some(Object::String("2024-02-29 12:34:56.789 %".into()))
+        );
+        assert_eq!(
+            before_epoch.ok(),
+            Some(Object::String("1969-12-31 23:59:59".into()))
+        );
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn test_seeded_random_numbers() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let paren = make_token_paren();
+        let roll = |interpreter: &mut Interpreter| {
+            random_seed(interpreter, vec![Object::Number(3.0)], &paren).ok();
+            let number = random(interpreter, vec![], &paren).ok();
+            let die = random_int(
+                interpreter,
+                vec![Object::Number(1.0), Object::Number(6.0)],
+                &paren,
+            )
+            .ok();
+            (number, die)
+        };
+        // Act
+        let first = roll(&mut interpreter);
+        let second = roll(&mut interpreter);
+        let reversed = random_int(
+            &mut interpreter,
+            vec![Object::Number(6.0), Object::Number(1.0)],
+            &paren,
+        );
+        // Assert
+        assert_eq!(first, second);
+        assert!(matches!(first.1, Some(Object::Int(n)) if (1..=6).contains(&n)));
+        assert!(reversed.is_err());
+    }
+
+    #[test]
+    fn test_input_and_files() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let files = Rc::new(RefCell::new(HashMap::new()));
+        interpreter.files = FileSystem::Memory(Rc::clone(&files));
+        interpreter.input = Input::Lines(Rc::new(RefCell::new(VecDeque::from([
+            "first line".to_string()
+        ]))));
+        let paren = make_token_paren();
+        let text = |value: &str| Object::String(value.into());
+        // Act
+        let line = read_line(&mut interpreter, vec![], &paren);
+        let end_of_input = read_line(&mut interpreter, vec![], &paren);
+        let written = write_file(&mut interpreter, vec![text("a.txt"), text("a")], &paren);
+        append_file(&mut interpreter, vec![text("a.txt"), text("b")], &paren).ok();
+        let contents = read_file(&mut interpreter, vec![text("a.txt")], &paren);
+        let missing = read_file(&mut interpreter, vec![text("b.txt")], &paren);
+        // Assert
+        assert_eq!(line.ok(), Some(text("first line")));
+        assert_eq!(end_of_input.ok(), Some(Object::Nil));
+        assert_eq!(written.ok(), Some(Object::Bool(true)));
+        assert_eq!(contents.ok(), Some(text("ab")));
+        assert!(missing.is_err());
+        assert_eq!(files.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_date_parts_round_trip() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let paren = make_token_paren();
+        let numbers = |values: &[f64]| values.iter().map(|v| Object::Number(*v)).collect();
+        // Act
+        let timestamp = date_from_parts(
+            &mut interpreter,
+            numbers(&[2024.0, 2.0, 29.0, 12.0, 34.0, 56.0]),
+            &paren,
+        );
+        let parts = date_parts(&mut interpreter, numbers(&[1_709_210_096_000.0]), &paren);
+        let invalid = date_from_parts(
+            &mut interpreter,
+            numbers(&[2023.0, 2.0, 29.0, 0.0, 0.0, 0.0]),
+            &paren,
+        );
+        // Assert
+        assert_eq!(timestamp.ok(), Some(Object::Number(1_709_210_096_000.0)));
+        let Ok(Object::ClassInstance(parts)) = parts else {
+            panic!("Expected a DateParts instance");
+        };
+        assert_eq!(parts.borrow().field("weekday"), Some(Object::Number(4.0)));
+        assert_eq!(parts.borrow().field("month"), Some(Object::Number(2.0)));
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_split_join_and_parse() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let paren = make_token_paren();
+        let text = |value: &str| Object::String(value.into());
+        // Act
+        let parts = split(&mut interpreter, vec![text("a,b,,c"), text(",")], &paren);
+        let characters = split(&mut interpreter, vec![text("hé"), text("")], &paren);
+        let joined = parts
+            .as_ref()
+            .ok()
+            .map(|parts| join(&mut interpreter, vec![parts.clone(), text("-")], &paren));
+        let mixed = Object::list(vec![Object::Number(1.5), Object::Nil, text("x")]);
+        let number = parse_number(&mut interpreter, vec![text(" 42.5 ")], &paren);
+        let not_number = parse_number(&mut interpreter, vec![text("4x")], &paren);
+        // Assert
+        assert_eq!(
+            parts.ok().map(|parts| parts.to_string()),
+            Some("[\"a\", \"b\", \"\", \"c\"]".to_string())
+        );
+        assert_eq!(
+            characters.ok().map(|parts| parts.to_string()),
+            Some("[\"h\", \"é\"]".to_string())
+        );
+        assert_eq!(joined.and_then(Result::ok), Some(text("a-b--c")));
+        assert_eq!(
+            to_string(&mut interpreter, vec![mixed], &paren).ok(),
+            Some(text("[1.5, nil, \"x\"]"))
+        );
+        assert_eq!(number.ok(), Some(Object::Number(42.5)));
+        assert_eq!(not_number.ok(), Some(Object::Nil));
+    }
+
+    #[test]
+    fn test_int_and_float() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let paren = make_token_paren();
+        // Act
+        let truncated = int(&mut interpreter, vec![Object::Number(-3.7)], &paren);
+        let too_large = int(&mut interpreter, vec![Object::Number(1e20)], &paren);
+        let not_number = int(&mut interpreter, vec![Object::Nil], &paren);
+        let promoted = float(&mut interpreter, vec![Object::Int(3)], &paren);
+        let parsed = parse_number(&mut interpreter, vec![Object::String("12".into())], &paren);
+        // Assert
+        assert!(matches!(truncated, Ok(Object::Int(-3))));
+        assert!(
+            matches!(too_large, Err(LoxErrorResult::Interpreter { message, .. })
+            if message.to_string() == "Cannot convert 100000000000000000000 to an integer.")
+        );
+        assert!(not_number.is_err());
+        assert!(matches!(promoted, Ok(Object::Number(number)) if number == 3.0));
+        assert!(matches!(parsed, Ok(Object::Int(12))));
+    }
+
+    #[test]
+    fn test_format_and_eprint() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let output = interpreter.capture_output();
+        let paren = make_token_paren();
+        let text = |value: &str| Object::String(value.into());
+        let values = Object::list(vec![Object::Int(1), text("a")]);
+        // Act
+        let formatted = format(
+            &mut interpreter,
+            vec![
+                text("{} + {} = {{{}}} {}"),
+                Object::Int(1),
+                Object::Number(0.5),
+                text("x"),
+                values,
+            ],
+            &paren,
+        );
+        let missing = format(&mut interpreter, vec![text("{} {}"), Object::Nil], &paren);
+        let unmatched = format(&mut interpreter, vec![text("{")], &paren);
+        eprint(&mut interpreter, vec![text("oops")], &paren).ok();
+        // Assert
+        assert_eq!(formatted.ok(), Some(text("1 + 0.5 = {x} [1, \"a\"]")));
+        assert!(
+            matches!(missing, Err(LoxErrorResult::Interpreter { message, .. })
+            if message.to_string() == "Template of 'format' has 2 placeholders but got 1 values.")
+        );
+        assert!(unmatched.is_err());
+        assert_eq!(output.borrow().stderr, "oops\n");
+        assert_eq!(output.borrow().stdout, "");
+    }
+
+    #[test]
+    fn test_hash() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let paren = make_token_paren();
+        let list = Object::list(vec![Object::Int(1)]);
+        let mut hash_of = |value: Object| hash(&mut interpreter, vec![value], &paren).ok();
+        // Act
+        let text = hash_of(Object::String("key".into()));
+        let same_text = hash_of(Object::String("key".into()));
+        let other_text = hash_of(Object::String("kez".into()));
+        let int = hash_of(Object::Int(1));
+        let float = hash_of(Object::Number(1.0));
+        let same_list = (hash_of(list.clone()), hash_of(list.clone()));
+        let equal_list = hash_of(Object::list(vec![Object::Int(1)]));
+        drop(list);
+        // Assert
+        assert_eq!(text, same_text);
+        assert_ne!(text, other_text);
+        assert_eq!(int, float);
+        assert_eq!(same_list.0, same_list.1);
+        assert_ne!(same_list.0, equal_list);
+    }
+
+    #[test]
+    #[cfg(feature = "checksums")]
+    fn test_checksums() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let paren = make_token_paren();
+        let text = || vec![Object::String("abc".into())];
+        // Act
+        let sha = sha256(&mut interpreter, text(), &paren);
+        let md = md5(&mut interpreter, text(), &paren);
+        let number = md5(&mut interpreter, vec![Object::Int(1)], &paren);
+        // Assert
+        assert_eq!(
+            sha.ok(),
+            Some(Object::String(
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".into()
+            ))
+        );
+        assert_eq!(
+            md.ok(),
+            Some(Object::String("900150983cd24fb0d6963f7d28e17f72".into()))
+        );
+        assert!(number.is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "net")]
+    fn test_tcp_natives() {
+        // Arrange
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 5];
+            stream.read_exact(&mut request).unwrap();
+            stream.write_all(&request).unwrap();
+        });
+        let mut interpreter = Interpreter::new();
+        let paren = make_token_paren();
+        let host = Object::String("127.0.0.1".into());
+        // Act
+        let connection = tcp_connect(
+            &mut interpreter,
+            vec![host, Object::Int(port as i64)],
+            &paren,
+        )
+        .unwrap();
+        let sent = tcp_send(
+            &mut interpreter,
+            vec![connection.clone(), Object::String("hello".into())],
+            &paren,
+        );
+        let received = tcp_recv(
+            &mut interpreter,
+            vec![connection.clone(), Object::Int(16)],
+            &paren,
+        );
+        tcp_close(&mut interpreter, vec![connection.clone()], &paren).unwrap();
+        let closed = tcp_recv(
+            &mut interpreter,
+            vec![connection.clone(), Object::Int(16)],
+            &paren,
+        );
+        let not_connection = tcp_close(&mut interpreter, vec![Object::Nil], &paren);
+        // Assert
+        assert_eq!(connection.to_string(), "<foreign TcpConnection>");
+        assert_eq!(sent.ok(), Some(Object::Int(5)));
+        assert_eq!(received.ok(), Some(Object::String("hello".into())));
+        assert_eq!(
+            closed.err().map(|err| err.to_string()),
+            Some(
+                "[Line 1] - Error[N0535]: Connection error in 'tcpRecv': the connection is closed."
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            not_connection.err().map(|err| err.to_string()),
+            Some("[Line 1] - Error[N0503]: First argument of 'tcpClose' must be a connection from 'tcpConnect'.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_number_formatting() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let paren = make_token_paren();
+        let mut call = |native: NativeFn, number: Object, digits: i64| {
+            native(&mut interpreter, vec![number, Object::Int(digits)], &paren)
+                .map(|value| value.to_string())
+                .map_err(|err| err.to_string())
+        };
+        // Act
+        let fixed = call(to_fixed, Object::Number(1234.5678), 2);
+        let padded = call(to_fixed, Object::Int(-3), 1);
+        let precise = call(to_precision, Object::Number(123.456), 4);
+        let small = call(to_precision, Object::Number(0.000001234), 2);
+        let large = call(to_precision, Object::Number(123456.0), 2);
+        let rounded_up = call(to_precision, Object::Number(9.99), 2);
+        let hex = call(to_string_base, Object::Int(255), 16);
+        let binary = call(to_string_base, Object::Int(-10), 2);
+        let bad_base = call(to_string_base, Object::Int(10), 37);
+        let fraction = call(to_string_base, Object::Number(1.5), 2);
+        // Assert
+        assert_eq!(fixed, Ok("\"1234.57\"".to_string()));
+        assert_eq!(padded, Ok("\"-3.0\"".to_string()));
+        assert_eq!(precise, Ok("\"123.5\"".to_string()));
+        assert_eq!(small, Ok("\"0.0000012\"".to_string()));
+        assert_eq!(large, Ok("\"1.2e+5\"".to_string()));
+        assert_eq!(rounded_up, Ok("\"10\"".to_string()));
+        assert_eq!(hex, Ok("\"ff\"".to_string()));
+        assert_eq!(binary, Ok("\"-1010\"".to_string()));
+        assert_eq!(
+            bad_base,
+            Err("[Line 1] - Error[N0537]: Second argument of 'toStringBase' must be an integer from 2 to 36, got 37.".to_string())
+        );
+        assert_eq!(
+            fraction,
+            Err(
+                "[Line 1] - Error[N0503]: First argument of 'toStringBase' must be an integer."
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_assert_and_error() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let paren = make_token_paren();
+        let message = || Object::String("broken".into());
+        // Act
+        let passed = assert(
+            &mut interpreter,
+            vec![Object::Number(0.0), message()],
+            &paren,
+        );
+        let failed = assert(&mut interpreter, vec![Object::Nil, message()], &paren);
+        let raised = error(&mut interpreter, vec![message()], &paren);
+        // Assert
+        assert_eq!(passed.ok(), Some(Object::Nil));
+        assert_eq!(
+            failed.err().map(|err| err.to_string()),
+            Some("[Line 1] - Error[N0523]: Assertion failed: broken".to_string())
+        );
+        assert_eq!(
+            raised.err().map(|err| err.to_string()),
+            Some("[Line 1] - Error: broken".to_string())
+        );
+    }
+
+    #[test]
+    fn test_higher_order_list_natives() {
+        // Arrange
+        let source = r#"
+            var numbers = split("3 1 2 1", " ");
+            numbers = map(numbers, parseNumber);
+            push(numbers, 5);
+            fun big(n) { return n > 1; }
+            fun add(total, n) { return total + n; }
+            fun descending(a, b) { return b - a; }
+            fun same(a, b) { return a == b; }
+            print filter(numbers, big);
+            print reduce(numbers, add, 0);
+            print sort(numbers, descending);
+            print pop(numbers) + len(numbers) * 10;
+            print contains(numbers, 3);
+            for (var i = 0; i < len(numbers); i = i + 1) print get(numbers, i);
+            sort(numbers, same);
+        "#;
+        // Act
+        let output = crate::run_captured(source, &mut Interpreter::new());
+        // Assert
+        assert_eq!(
+            output.stdout,
+            "[3, 2, 5]\n12\n[5, 3, 2, 1, 1]\n41\ntrue\n5\n3\n2\n1\n"
+        );
+        assert_eq!(
+            output.diagnostics,
+            "[Line 15] - Error[N0515]: Comparator of 'sort' must return a number.\n"
+        );
+    }
+
+    #[test]
+    fn test_unicode_strings() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let paren = make_token_paren();
+        let text = || Object::String("añ日🎉".into());
+        // Act
+        let length = len(&mut interpreter, vec![text()], &paren);
+        let third = get(&mut interpreter, vec![text(), Object::Number(2.0)], &paren);
+        let outside = get(&mut interpreter, vec![text(), Object::Number(4.0)], &paren);
+        let characters = split(
+            &mut interpreter,
+            vec![text(), Object::String("".into())],
+            &paren,
+        );
+        let bytes = byte_len(&mut interpreter, vec![text()], &paren);
+        let middle = slice(
+            &mut interpreter,
+            vec![text(), Object::Int(1), Object::Int(3)],
+            &paren,
+        );
+        let empty = slice(
+            &mut interpreter,
+            vec![text(), Object::Int(4), Object::Int(4)],
+            &paren,
+        );
+        let past_end = slice(
+            &mut interpreter,
+            vec![text(), Object::Int(2), Object::Int(5)],
+            &paren,
+        );
+        let code_point = code_point_at(&mut interpreter, vec![text(), Object::Int(3)], &paren);
+        let character = from_code_point(&mut interpreter, vec![Object::Int(0x65E5)], &paren);
+        let surrogate = from_code_point(&mut interpreter, vec![Object::Int(0xD800)], &paren);
+        // Assert
+        assert_eq!(length.ok(), Some(Object::Number(4.0)));
+        assert_eq!(third.ok(), Some(Object::String("日".into())));
+        assert_eq!(
+            outside.err().map(|err| err.to_string()),
+            Some(
+                "[Line 1] - Error[N0509]: Index 4 is out of range for a string of 4 characters."
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            characters.ok().map(|list| list.to_string()),
+            Some("[\"a\", \"ñ\", \"日\", \"🎉\"]".to_string())
+        );
+        assert_eq!(bytes.ok(), Some(Object::Int(10)));
+        assert_eq!(middle.ok(), Some(Object::String("ñ日".into())));
+        assert_eq!(empty.ok(), Some(Object::String("".into())));
+        assert_eq!(
+            past_end.err().map(|err| err.to_string()),
+            Some(
+                "[Line 1] - Error[N0539]: Range 2..5 is out of range for a string of 4 characters."
+                    .to_string()
+            )
+        );
+        assert_eq!(code_point.ok(), Some(Object::Int(0x1F389)));
+        assert_eq!(character.ok(), Some(Object::String("日".into())));
+        assert_eq!(
+            surrogate.err().map(|err| err.to_string()),
+            Some(
+                "[Line 1] - Error[N0514]: Code point 55296 is not a Unicode scalar value."
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_display_self_containing_list() {
+        // Arrange
+        let source = "var l = list();
+push(l, 1);
+push(l, l);
+var outer = list();
+push(outer, l);
+push(outer, l);
+print l;
+print toString(l);
+print outer;";
+        let mut interpreter = Interpreter::new();
+        // Act
+        let output = crate::run_captured(source, &mut interpreter);
+        // Assert
+        assert_eq!(
+            output.stdout,
+            "[1, [...]]\n\"[1, [...]]\"\n[[1, [...]], [1, [...]]]\n"
+        );
+        assert_eq!(output.diagnostics, "");
+    }
+
+    #[test]
+    fn test_combining_and_four_byte_characters() {
+        // Arrange
+        // `e` and a combining acute accent, then the 4-byte musical G clef
+        let source = "var text = \"cafe\u{301}\u{1D11E}!\";
+print len(text);
+print byteLen(text);
+print len(split(text, \"\"));
+print get(text, 4) == fromCodePoint(769);
+print get(text, 5) == fromCodePoint(119070);
+print codePointAt(text, 3);
+print codePointAt(text, 4);
+print codePointAt(text, 5);
+print slice(text, 3, 5) == \"e\u{301}\";
+print slice(text, 5, 7) == fromCodePoint(119070) + \"!\";
+print get(text, 7);
+print slice(text, 6, 8);
+print codePointAt(text, 7);";
+        let mut interpreter = Interpreter::new();
+        // Act
+        let output = crate::run_captured(source, &mut interpreter);
+        // Assert
+        assert_eq!(
+            output.stdout,
+            "7\n11\n7\ntrue\ntrue\n101\n769\n119070\ntrue\ntrue\n"
+        );
+        assert_eq!(
+            output.diagnostics,
+            "[Line 12] - Error[N0509]: Index 7 is out of range for a string of 7 characters.
+[Line 13] - Error[N0539]: Range 6..8 is out of range for a string of 7 characters.
+[Line 14] - Error[N0509]: Index 7 is out of range for a string of 7 characters.
+"
+        );
+    }
+}
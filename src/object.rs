@@ -1,92 +1,333 @@
-use std::{cmp::Ordering, fmt, ops::*};
+use std::{cell::RefCell as LocalCell, cmp::Ordering, fmt, ops::*};
+
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+#[cfg(feature = "bigint")]
+use num_traits::{ToPrimitive, Zero};
 
 use crate::{
-    lox_class::LoxClass, lox_function::LoxFunction, lox_instance::LoxInstanceRef,
+    error_codes::{Message, I0302, I0315, I0316, I0317},
+    lox_class::LoxClass,
+    lox_foreign::LoxForeignRef,
+    lox_function::LoxFunction,
+    lox_generator::LoxGeneratorRef,
+    lox_instance::LoxInstanceRef,
+    lox_module::LoxModuleRef,
     lox_native_function::LoxNativeFunction,
+    shared::{Rc, RefCell},
+    stats,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub enum Object {
-    String(String),
+    /// Strings are immutable, copies of the value share the text
+    String(Rc<str>),
+    /// Float, written with a fraction or an exponent (`1.5`, `1e3`) or too large for an `Int`
     Number(f64),
+    /// Integer literals (`42`, `0xFF`), stays exact until mixed with a float or overflowing
+    Int(i64),
+    /// Integer past the range of an `Int`, from an overflow or a literal with the `n` suffix
+    /// (`2n`), stays a `BigInt` until mixed with a float
+    #[cfg(feature = "bigint")]
+    BigInt(Rc<BigInt>),
     Bool(bool),
     Function(LoxFunction),
     NativeFunction(LoxNativeFunction),
     Class(LoxClass),
     ClassInstance(LoxInstanceRef),
+    /// Lists are shared: every copy of the value sees the same elements
+    List(LoxListRef),
+    /// Returned by a call to a function containing `yield`
+    Generator(LoxGeneratorRef),
+    /// Natives registered together by the host, see `Interpreter::register_native_module`
+    Module(LoxModuleRef),
+    /// Host value made by a native, such as the connection of `tcpConnect`
+    Foreign(LoxForeignRef),
     Nil,
 }
 
+pub type LoxListRef = Rc<RefCell<Vec<Object>>>;
+
+impl Clone for Object {
+    fn clone(&self) -> Self {
+        // Counted for `--stats`
+        stats::count_clone();
+        match self {
+            Object::String(string) => Object::String(string.clone()),
+            Object::Number(number) => Object::Number(*number),
+            Object::Int(int) => Object::Int(*int),
+            #[cfg(feature = "bigint")]
+            Object::BigInt(int) => Object::BigInt(Rc::clone(int)),
+            Object::Bool(bool) => Object::Bool(*bool),
+            Object::Function(function) => Object::Function(function.clone()),
+            Object::NativeFunction(function) => Object::NativeFunction(function.clone()),
+            Object::Class(class) => Object::Class(class.clone()),
+            Object::ClassInstance(instance) => Object::ClassInstance(Rc::clone(instance)),
+            Object::List(elements) => Object::List(Rc::clone(elements)),
+            Object::Generator(generator) => Object::Generator(Rc::clone(generator)),
+            Object::Module(module) => Object::Module(Rc::clone(module)),
+            Object::Foreign(foreign) => Object::Foreign(Rc::clone(foreign)),
+            Object::Nil => Object::Nil,
+        }
+    }
+}
+
+impl Object {
+    pub fn list(elements: Vec<Object>) -> Object {
+        Object::List(Rc::new(RefCell::new(elements)))
+    }
+
+    /// Value of an `Int` or a `Number` as a float
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Object::Number(number) => Some(*number),
+            Object::Int(int) => Some(*int as f64),
+            #[cfg(feature = "bigint")]
+            Object::BigInt(int) => int.to_f64(),
+            _ => None,
+        }
+    }
+
+    /// Value of an `Int`, or of a `Number` without a fractional part
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Object::Int(int) => Some(*int),
+            #[cfg(feature = "bigint")]
+            Object::BigInt(int) => int.to_i64(),
+            Object::Number(number) if number.fract() == 0.0 && number.abs() < i64::MAX as f64 => {
+                Some(*number as i64)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Operands of an arithmetic operation, an `Int` is promoted to a float when the other is one
+enum Numbers {
+    Ints(i64, i64),
+    Floats(f64, f64),
+    #[cfg(feature = "bigint")]
+    BigInts(BigInt, BigInt),
+}
+
+/// Operators applied by `Numbers::apply`
+#[derive(Clone, Copy)]
+enum Arithmetic {
+    Add,
+    Subtract,
+    Multiply,
+}
+
+impl Arithmetic {
+    /// `None` when the result overflows an `i64`
+    fn ints(self, left: i64, right: i64) -> Option<i64> {
+        match self {
+            Arithmetic::Add => left.checked_add(right),
+            Arithmetic::Subtract => left.checked_sub(right),
+            Arithmetic::Multiply => left.checked_mul(right),
+        }
+    }
+
+    fn floats(self, left: f64, right: f64) -> f64 {
+        match self {
+            Arithmetic::Add => left + right,
+            Arithmetic::Subtract => left - right,
+            Arithmetic::Multiply => left * right,
+        }
+    }
+
+    #[cfg(feature = "bigint")]
+    fn big_ints(self, left: &BigInt, right: &BigInt) -> BigInt {
+        match self {
+            Arithmetic::Add => left + right,
+            Arithmetic::Subtract => left - right,
+            Arithmetic::Multiply => left * right,
+        }
+    }
+}
+
+impl Numbers {
+    fn of(left: &Object, right: &Object) -> Option<Numbers> {
+        match (left, right) {
+            (Object::Int(left), Object::Int(right)) => Some(Numbers::Ints(*left, *right)),
+            #[cfg(feature = "bigint")]
+            (Object::BigInt(_) | Object::Int(_), Object::BigInt(_) | Object::Int(_)) => {
+                Some(Numbers::BigInts(left.as_big_int()?, right.as_big_int()?))
+            }
+            _ => Some(Numbers::Floats(left.as_number()?, right.as_number()?)),
+        }
+    }
+
+    /// `operation` on integers, a result that overflows an `i64` becomes a float,
+    /// or a `BigInt` with the `bigint` feature
+    fn apply(self, operation: Arithmetic) -> Object {
+        match self {
+            Numbers::Ints(left, right) => match operation.ints(left, right) {
+                Some(int) => Object::Int(int),
+                #[cfg(feature = "bigint")]
+                None => Object::BigInt(Rc::new(
+                    operation.big_ints(&BigInt::from(left), &BigInt::from(right)),
+                )),
+                #[cfg(not(feature = "bigint"))]
+                None => Object::Number(operation.floats(left as f64, right as f64)),
+            },
+            Numbers::Floats(left, right) => Object::Number(operation.floats(left, right)),
+            #[cfg(feature = "bigint")]
+            Numbers::BigInts(left, right) => {
+                Object::BigInt(Rc::new(operation.big_ints(&left, &right)))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "bigint")]
+impl Object {
+    /// Value of an `Int` or a `BigInt` as a `BigInt`
+    pub fn as_big_int(&self) -> Option<BigInt> {
+        match self {
+            Object::Int(int) => Some(BigInt::from(*int)),
+            Object::BigInt(int) => Some(BigInt::clone(int)),
+            _ => None,
+        }
+    }
+}
+
+thread_local! {
+    /// Lists being displayed, a list met again inside itself shows as `[...]`
+    static DISPLAYED_LISTS: LocalCell<Vec<*const ()>> = const { LocalCell::new(Vec::new()) };
+}
+
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Object::Number(num) if num.is_infinite() => match num.is_sign_negative() {
+                true => write!(f, "-Infinity"),
+                false => write!(f, "Infinity"),
+            },
             Object::Number(num) => write!(f, "{num}"),
+            Object::Int(int) => write!(f, "{int}"),
+            #[cfg(feature = "bigint")]
+            Object::BigInt(int) => write!(f, "{int}"),
             Object::String(val) => write!(f, "\"{val}\""),
             Object::Bool(val) => write!(f, "{val}"),
             Object::Nil => write!(f, "nil"),
             Object::Function(function) => write!(f, "{}", function),
             Object::NativeFunction(native_function) => write!(f, "{}", native_function),
             Object::Class(lox_class) => write!(f, "{}", lox_class),
-            Object::ClassInstance(lox_instance) => write!(f, "{}", lox_instance.borrow()),
+            Object::ClassInstance(lox_instance) => write!(f, "{}", *lox_instance.borrow()),
+            Object::Generator(generator) => write!(f, "{generator}"),
+            Object::Module(module) => write!(f, "{module}"),
+            Object::Foreign(foreign) => write!(f, "{foreign}"),
+            Object::List(elements) => {
+                let list = Rc::as_ptr(elements) as *const ();
+                let cycle = DISPLAYED_LISTS.with_borrow(|lists| lists.contains(&list));
+                if cycle {
+                    return write!(f, "[...]");
+                }
+                DISPLAYED_LISTS.with_borrow_mut(|lists| lists.push(list));
+                let result = write_elements(f, &elements.borrow());
+                DISPLAYED_LISTS.with_borrow_mut(|lists| lists.pop());
+                result
+            }
         }
     }
 }
 
+fn write_elements(f: &mut fmt::Formatter, elements: &[Object]) -> fmt::Result {
+    write!(f, "[")?;
+    for (idx, element) in elements.iter().enumerate() {
+        if idx > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{element}")?;
+    }
+    write!(f, "]")
+}
+
 impl Sub for Object {
-    type Output = Result<Object, String>;
+    type Output = Result<Object, Message>;
 
     fn sub(self, other: Self) -> Self::Output {
-        match (self, other) {
-            (Object::Number(left), Object::Number(right)) => Ok(Object::Number(left - right)),
-            _ => Err("Operands must be numbers for '-' operation.".to_string()),
+        match Numbers::of(&self, &other) {
+            Some(numbers) => Ok(numbers.apply(Arithmetic::Subtract)),
+            None => Err(I0315.with(["-".into()])),
         }
     }
 }
 
 impl Div for Object {
-    type Output = Result<Object, String>;
+    type Output = Result<Object, Message>;
 
     fn div(self, other: Self) -> Self::Output {
-        match (self, other) {
-            (Object::Number(left), Object::Number(right)) => {
-                let result = left / right;
-                match result.is_infinite() || result.is_nan() {
-                    true => Err("Illegal expression. Division by zero is not allowed.".to_string()),
-                    false => Ok(Object::Number(result)),
-                }
+        // A `BigInt` divided exactly stays one, the float of the quotient would round it
+        #[cfg(feature = "bigint")]
+        if let Some(Numbers::BigInts(left, right)) = Numbers::of(&self, &other) {
+            if !right.is_zero() && (&left % &right).is_zero() {
+                return Ok(Object::BigInt(Rc::new(left / right)));
             }
-            _ => Err("Operands must be numbers for '/' operation.".to_string()),
+        }
+        // Dividing always gives a float otherwise, `1 / 2` is `0.5`
+        match (self.as_number(), other.as_number()) {
+            (Some(left), Some(right)) => Ok(Object::Number(left / right)),
+            _ => Err(I0315.with(["/".into()])),
         }
     }
 }
 
 impl Mul for Object {
-    type Output = Result<Object, String>;
+    type Output = Result<Object, Message>;
 
     fn mul(self, other: Self) -> Self::Output {
-        match (self, other) {
-            (Object::Number(left), Object::Number(right)) => Ok(Object::Number(left * right)),
-            _ => Err("Operands must be numbers for '*' operation.".to_string()),
+        if let Some(numbers) = Numbers::of(&self, &other) {
+            return Ok(numbers.apply(Arithmetic::Multiply));
+        }
+        // `"ab" * 3` and `3 * "ab"` repeat the string
+        let (text, count) = match (&self, &other) {
+            (Object::String(text), count) | (count, Object::String(text))
+                if count.as_number().is_some() =>
+            {
+                (text, count)
+            }
+            _ => return Err(I0316.with([])),
+        };
+        match count
+            .as_integer()
+            .and_then(|count| usize::try_from(count).ok())
+        {
+            Some(count) if text.len().checked_mul(count).is_some() => {
+                Ok(Object::String(text.repeat(count).into()))
+            }
+            _ => Err(I0317.with([count.to_string().into()])),
         }
     }
 }
 
 impl Add for Object {
-    type Output = Result<Object, String>;
+    type Output = Result<Object, Message>;
 
     fn add(self, other: Self) -> Self::Output {
+        if let Some(numbers) = Numbers::of(&self, &other) {
+            return Ok(numbers.apply(Arithmetic::Add));
+        }
         match (self, other) {
-            (Object::Number(left), Object::Number(right)) => Ok(Object::Number(left + right)),
             (Object::String(left), Object::String(right)) => {
-                Ok(Object::String(format!("{left}{right}")))
+                Ok(Object::String(format!("{left}{right}").into()))
+            }
+            (Object::String(left), right @ (Object::Number(_) | Object::Int(_))) => {
+                Ok(Object::String(format!("{left}{right}").into()))
             }
-            (Object::String(left), Object::Number(right)) => {
-                Ok(Object::String(format!("{left}{right}")))
+            (left @ (Object::Number(_) | Object::Int(_)), Object::String(right)) => {
+                Ok(Object::String(format!("{left}{right}").into()))
             }
-            (Object::Number(left), Object::String(right)) => {
-                Ok(Object::String(format!("{left}{right}")))
+            #[cfg(feature = "bigint")]
+            (Object::String(left), right @ Object::BigInt(_)) => {
+                Ok(Object::String(format!("{left}{right}").into()))
             }
-            _ => Err("Operands must be strings or numbers for '+' operation.".to_string()),
+            #[cfg(feature = "bigint")]
+            (left @ Object::BigInt(_), Object::String(right)) => {
+                Ok(Object::String(format!("{left}{right}").into()))
+            }
+            _ => Err(I0302.with([])),
         }
     }
 }
@@ -94,9 +335,13 @@ impl Add for Object {
 impl PartialOrd for Object {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match (self, other) {
-            (Object::Number(left), Object::Number(right)) => left.partial_cmp(right),
             (Object::String(left), Object::String(right)) => left.partial_cmp(right),
-            _ => None,
+            _ => match Numbers::of(self, other)? {
+                Numbers::Ints(left, right) => left.partial_cmp(&right),
+                Numbers::Floats(left, right) => left.partial_cmp(&right),
+                #[cfg(feature = "bigint")]
+                Numbers::BigInts(left, right) => left.partial_cmp(&right),
+            },
         }
     }
 }
@@ -105,10 +350,14 @@ impl PartialEq for Object {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Object::String(left), Object::String(right)) => left == right,
-            (Object::Number(left), Object::Number(right)) => left == right,
             (Object::Bool(left), Object::Bool(right)) => left == right,
             (Object::Nil, Object::Nil) => true,
-            _ => false,
+            (Object::List(left), Object::List(right)) => Rc::ptr_eq(left, right),
+            (Object::Generator(left), Object::Generator(right)) => Rc::ptr_eq(left, right),
+            (Object::Module(left), Object::Module(right)) => Rc::ptr_eq(left, right),
+            (Object::Foreign(left), Object::Foreign(right)) => Rc::ptr_eq(left, right),
+            // `1 == 1.0`, like the comparisons
+            _ => self.partial_cmp(other) == Some(Ordering::Equal),
         }
     }
 }
@@ -1,30 +1,182 @@
-use crate::{error::*, expr::*, object::*, stmt::*, token::*};
+use std::{collections::HashSet, ops::RangeInclusive};
+
+use crate::{
+    ast::Spanned,
+    error::*,
+    error_codes::{
+        term, Argument, Message, P0101, P0102, P0103, P0104, P0105, P0106, P0107, P0108, P0109,
+        P0110, P0111, P0112, P0113, P0114, P0115, P0116,
+    },
+    expr::*,
+    object::*,
+    shared::Rc,
+    stmt::*,
+    token::*,
+};
 
 #[derive(Debug)]
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
     pub had_error: bool,
+    /// Allows a trailing expression without ';' which is echoed back (REPL only)
+    repl: bool,
+    /// Current nesting of statements and expressions, bounded by `max_nesting`
+    depth: usize,
+    max_nesting: usize,
+    /// Text of the string literals parsed so far, equal literals share one copy
+    constants: HashSet<Rc<str>>,
 }
 
-static mut UUID: usize = 0;
+/// Deepest nesting of statements and expressions accepted by default, deeper sources are
+/// rejected with an error instead of overflowing the stack
+pub const MAX_NESTING: usize = 100;
+
+/// How tightly an operator binds, from the loosest to the tightest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Assignment,
+    Ternary,
+    Or,
+    And,
+    Equality,
+    Comparison,
+    Term,
+    Factor,
+    Unary,
+    Call,
+}
+
+impl Precedence {
+    fn next(self) -> Precedence {
+        match self {
+            Precedence::Assignment => Precedence::Ternary,
+            Precedence::Ternary => Precedence::Or,
+            Precedence::Or => Precedence::And,
+            Precedence::And => Precedence::Equality,
+            Precedence::Equality => Precedence::Comparison,
+            Precedence::Comparison => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Unary,
+            Precedence::Unary | Precedence::Call => Precedence::Call,
+        }
+    }
+}
+
+/// Expression an infix operator builds from the expression on its left
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum InfixKind {
+    Binary,
+    Logical,
+    /// `condition ? then : else`
+    Ternary,
+    Assignment,
+    /// `callee(arguments)`
+    Call,
+    /// `object.property`
+    Get,
+}
 
-pub fn next_uid() -> usize {
-    unsafe {
-        UUID += 1;
-        UUID
+#[derive(Debug)]
+struct InfixRule {
+    token_type: TokenType,
+    precedence: Precedence,
+    kind: InfixKind,
+    right_associative: bool,
+}
+
+const fn rule(token_type: TokenType, precedence: Precedence, kind: InfixKind) -> InfixRule {
+    InfixRule {
+        token_type,
+        precedence,
+        kind,
+        right_associative: false,
     }
 }
 
+const fn right_rule(token_type: TokenType, precedence: Precedence, kind: InfixKind) -> InfixRule {
+    InfixRule {
+        right_associative: true,
+        ..rule(token_type, precedence, kind)
+    }
+}
+
+/// Every infix and postfix operator, a new operator only needs an entry here
+static INFIX_RULES: [InfixRule; 16] = [
+    right_rule(
+        TokenType::Equal,
+        Precedence::Assignment,
+        InfixKind::Assignment,
+    ),
+    right_rule(TokenType::Question, Precedence::Ternary, InfixKind::Ternary),
+    rule(TokenType::Or, Precedence::Or, InfixKind::Logical),
+    rule(TokenType::And, Precedence::And, InfixKind::Logical),
+    rule(
+        TokenType::BangEqual,
+        Precedence::Equality,
+        InfixKind::Binary,
+    ),
+    rule(
+        TokenType::EqualEqual,
+        Precedence::Equality,
+        InfixKind::Binary,
+    ),
+    rule(
+        TokenType::Greater,
+        Precedence::Comparison,
+        InfixKind::Binary,
+    ),
+    rule(
+        TokenType::GreaterEqual,
+        Precedence::Comparison,
+        InfixKind::Binary,
+    ),
+    rule(TokenType::Less, Precedence::Comparison, InfixKind::Binary),
+    rule(
+        TokenType::LessEqual,
+        Precedence::Comparison,
+        InfixKind::Binary,
+    ),
+    rule(TokenType::Minus, Precedence::Term, InfixKind::Binary),
+    rule(TokenType::Plus, Precedence::Term, InfixKind::Binary),
+    rule(TokenType::Slash, Precedence::Factor, InfixKind::Binary),
+    rule(TokenType::Star, Precedence::Factor, InfixKind::Binary),
+    rule(TokenType::LeftParen, Precedence::Call, InfixKind::Call),
+    rule(TokenType::Dot, Precedence::Call, InfixKind::Get),
+];
+
+fn infix_rule(token_type: &TokenType) -> Option<&'static InfixRule> {
+    INFIX_RULES
+        .iter()
+        .find(|rule| rule.token_type == *token_type)
+}
+
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Parser {
         Parser {
             tokens,
             current: 0,
             had_error: false,
+            repl: false,
+            depth: 0,
+            max_nesting: MAX_NESTING,
+            constants: HashSet::new(),
         }
     }
 
+    /// Changes the deepest nesting accepted. Every level takes stack space, raising it
+    /// may need a thread with a bigger stack.
+    pub fn with_max_nesting(mut self, max_nesting: usize) -> Parser {
+        self.max_nesting = max_nesting;
+        self
+    }
+
+    /// Parses a line typed in the prompt, where `1 + 2` is treated as `print 1 + 2;`
+    pub fn parse_repl(&mut self) -> Vec<Stmt> {
+        self.repl = true;
+        self.parse()
+    }
+
     pub fn parse(&mut self) -> Vec<Stmt> {
         let mut statements: Vec<Stmt> = Vec::new();
         while !self.is_at_end() {
@@ -35,7 +187,30 @@ impl Parser {
         statements
     }
 
+    /// Like `parse`, along with the lines every statement spans from its first token to its
+    /// last one, the `;` or `}` that ends it
+    pub fn parse_with_lines(&mut self) -> Vec<(Stmt, RangeInclusive<usize>)> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            let first_line = self.peek().line;
+            if let Some(statement) = self.declaration() {
+                statements.push((statement, first_line..=self.previous().line));
+            }
+        }
+        statements
+    }
+
+    /// Parses the tokens as one single expression, e.g. for the REPL `:ast` command
+    pub fn parse_expression(&mut self) -> Result<Expr, LoxErrorResult> {
+        let expr = self.expression()?;
+        if !self.is_at_end() {
+            return Err(LoxErrorResult::parse_error(self.peek(), P0115.with([])));
+        }
+        Ok(expr)
+    }
+
     fn declaration(&mut self) -> Option<Stmt> {
+        let first = self.peek();
         let declaration = if self.matches(&[TokenType::Class]) {
             self.class_declaration()
         } else if self.matches(&[TokenType::Fun]) {
@@ -47,7 +222,7 @@ impl Parser {
         };
         // Has a parse error
         match declaration {
-            Ok(statement) => Some(statement),
+            Ok(statement) => Some(self.spanning_from(&first, statement)),
             Err(err) => {
                 err.report();
                 self.had_error = true;
@@ -58,91 +233,136 @@ impl Parser {
     }
 
     fn class_declaration(&mut self) -> Result<Stmt, LoxErrorResult> {
-        let name = self.consume(TokenType::Identifier, "Expect class name.")?;
+        let name = self.consume(TokenType::Identifier, P0107.with([term("class")]))?;
 
         let super_class = if self.matches(&[TokenType::Less]) {
-            self.consume(TokenType::Identifier, "Expect superclass name.")?;
-            Some(Box::new(Expr::Variable(VariableExpr {
-                uid: next_uid(),
-                name: self.previous(),
-            })))
+            self.consume(TokenType::Identifier, P0107.with([term("superclass")]))?;
+            Some(Box::new(Expr::new_variable(self.previous())))
         } else {
             None
         };
 
-        self.consume(TokenType::LeftBrace, "Expect '{{' before class body.")?;
+        let open = self.consume(TokenType::LeftBrace, P0106.with([term("class")]))?;
 
         let mut methods: Vec<Stmt> = Vec::new();
         while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
             methods.push(self.function_declaration("method")?);
         }
 
-        self.consume(TokenType::RightBrace, "Expect '}}' after class body.")?;
+        self.consume_closing_brace(&open, "class body")?;
 
-        Ok(Stmt::Class(ClassStmt {
-            name,
-            methods,
-            super_class,
-        }))
+        Ok(Stmt::new_class(name, super_class, methods))
     }
 
-    fn function_declaration(&mut self, kind: &str) -> Result<Stmt, LoxErrorResult> {
-        let name = self.consume(TokenType::Identifier, &format!("Expect {kind} name."))?;
-        self.consume(
-            TokenType::LeftParen,
-            &format!("Expect '(' after {kind} name."),
-        )?;
+    fn function_declaration(&mut self, kind: &'static str) -> Result<Stmt, LoxErrorResult> {
+        let name = self.consume(TokenType::Identifier, P0107.with([term(kind)]))?;
+        let after_name = match kind {
+            "method" => term("method name"),
+            _ => term("function name"),
+        };
+        self.consume(TokenType::LeftParen, P0104.with([after_name]))?;
 
         let mut params: Vec<Token> = Vec::new();
+        let mut param_annotations: Vec<Option<Token>> = Vec::new();
         if !self.check(&TokenType::RightParen) {
             loop {
                 if params.len() >= 255 {
-                    LoxErrorResult::parse_error(
-                        self.peek(),
-                        "Cannot have more than 255 parameters.",
-                    );
+                    // Reported without stopping, the parser is not in a confused state
+                    LoxErrorResult::parse_error(self.peek(), P0112.with([term("parameters")]))
+                        .report();
+                    self.had_error = true;
                 }
 
-                params.push(self.consume(TokenType::Identifier, "Expect parameter name.")?);
+                params.push(self.consume(TokenType::Identifier, P0107.with([term("parameter")]))?);
+                param_annotations.push(self.type_annotation()?);
 
-                if !self.matches(&[TokenType::Comma]) {
+                // A trailing comma is allowed: `fun f(a, b,) {}`
+                if !self.matches(&[TokenType::Comma]) || self.check(&TokenType::RightParen) {
                     break;
                 }
             }
         }
-        self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+        self.consume(TokenType::RightParen, P0102.with([term("parameters")]))?;
+        let return_annotation = match self.matches(&[TokenType::Arrow]) {
+            true => Some(self.type_name()?),
+            false => None,
+        };
 
-        self.consume(
-            TokenType::LeftBrace,
-            &format!("Expect '{{' after before {kind} body."),
-        )?;
+        self.consume(TokenType::LeftBrace, P0106.with([term(kind)]))?;
         let body: Vec<Stmt> = self.block()?;
 
-        Ok(Stmt::Function(FunctionStmt { name, params, body }))
+        let function = Stmt::new_function(
+            name.clone(),
+            params,
+            body,
+            param_annotations,
+            return_annotation,
+        );
+        Ok(self.spanning_from(&name, function))
+    }
+
+    /// Type after a `:`, as in `var x: Number`, `None` without a `:`
+    fn type_annotation(&mut self) -> Result<Option<Token>, LoxErrorResult> {
+        match self.matches(&[TokenType::Colon]) {
+            true => Ok(Some(self.type_name()?)),
+            false => Ok(None),
+        }
+    }
+
+    /// A type is named by a class or one of the types of `TypeChecker`, `nil` included
+    fn type_name(&mut self) -> Result<Token, LoxErrorResult> {
+        if self.matches(&[TokenType::Identifier, TokenType::Nil]) {
+            return Ok(self.previous());
+        }
+        Err(LoxErrorResult::parse_error(
+            self.peek(),
+            P0107.with([term("type")]),
+        ))
     }
 
     fn var_declaration(&mut self) -> Result<Stmt, LoxErrorResult> {
-        let name = self.consume(TokenType::Identifier, "Expect variable name.")?;
+        let name = self.consume(TokenType::Identifier, P0107.with([term("variable")]))?;
+        let annotation = self.type_annotation()?;
         let initializer = if self.matches(&[TokenType::Equal]) {
             Some(Box::new(self.expression()?))
         } else {
             None
         };
 
-        self.consume(
-            TokenType::Semicolon,
-            "Expect ';' after variable declaration.",
-        )?;
+        self.consume_semicolon(term("variable declaration"))?;
 
-        Ok(Stmt::Var(VarStmt { name, initializer }))
+        Ok(Stmt::new_var(name, initializer, annotation))
     }
 
     fn statement(&mut self) -> Result<Stmt, LoxErrorResult> {
+        let first = self.peek();
+        let statement = self.nested(Self::parse_statement, "Statement")?;
+        Ok(self.spanning_from(&first, statement))
+    }
+
+    /// The statement located from its first token, `first`, to the `;` or `}` ending it,
+    /// instead of only over its fields
+    fn spanning_from(&self, first: &Token, statement: Stmt) -> Stmt {
+        statement.with_span(first.span().to(self.previous().span()))
+    }
+
+    fn parse_statement(&mut self) -> Result<Stmt, LoxErrorResult> {
+        if self.check(&TokenType::Identifier) && self.check_ahead(1, &TokenType::Colon) {
+            return self.labeled_statement();
+        }
         if self.matches(&[TokenType::Break]) {
             return self.break_statement();
         }
+        if self.matches(&[TokenType::Continue]) {
+            return self.continue_statement();
+        }
+        if self.matches(&[TokenType::Debugger]) {
+            let keyword = self.previous();
+            self.consume_semicolon("'debugger'".into())?;
+            return Ok(Stmt::new_debugger(keyword));
+        }
         if self.matches(&[TokenType::For]) {
-            return self.for_statement();
+            return self.for_statement(None);
         }
         if self.matches(&[TokenType::If]) {
             return self.if_statement();
@@ -154,24 +374,61 @@ impl Parser {
             return self.return_statement();
         }
         if self.matches(&[TokenType::While]) {
-            return self.while_statement();
+            return self.while_statement(None);
+        }
+        if self.matches(&[TokenType::Yield]) {
+            return self.yield_statement();
         }
         if self.matches(&[TokenType::LeftBrace]) {
-            return Ok(Stmt::Block(BlockStmt {
-                statements: self.block()?,
-            }));
+            // The block spans its braces, so that an empty one is located too
+            let open = self.previous().span();
+            let mut block = BlockStmt::new(self.block()?);
+            block.span = open.to(self.previous().span());
+            return Ok(Stmt::Block(block));
         }
         self.expression_statement()
     }
 
+    /// `outer: while (...)` names a loop so that nested loops can `break outer;`
+    fn labeled_statement(&mut self) -> Result<Stmt, LoxErrorResult> {
+        let label = self.advance();
+        self.advance();
+        if self.matches(&[TokenType::For]) {
+            return self.for_statement(Some(label));
+        }
+        if self.matches(&[TokenType::While]) {
+            return self.while_statement(Some(label));
+        }
+        Err(LoxErrorResult::parse_error(self.peek(), P0114.with([])))
+    }
+
     fn break_statement(&mut self) -> Result<Stmt, LoxErrorResult> {
         let keyword = self.previous().clone();
-        self.consume(TokenType::Semicolon, "Expect ';' after 'break'.")?;
-        Ok(Stmt::Break(BreakStmt { keyword }))
+        let label = self.jump_label();
+        self.consume_semicolon("'break'".into())?;
+        Ok(Stmt::new_break(keyword, label))
+    }
+
+    fn continue_statement(&mut self) -> Result<Stmt, LoxErrorResult> {
+        let keyword = self.previous().clone();
+        let label = self.jump_label();
+        self.consume_semicolon("'continue'".into())?;
+        Ok(Stmt::new_continue(keyword, label))
     }
 
-    fn for_statement(&mut self) -> Result<Stmt, LoxErrorResult> {
-        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
+    fn jump_label(&mut self) -> Option<Token> {
+        match self.matches(&[TokenType::Identifier]) {
+            true => Some(self.previous()),
+            false => None,
+        }
+    }
+
+    fn for_statement(&mut self, label: Option<Token>) -> Result<Stmt, LoxErrorResult> {
+        let keyword = self.previous();
+        self.consume(TokenType::LeftParen, P0104.with(["'for'".into()]))?;
+        if self.check(&TokenType::Var) && self.check_ahead(2, &TokenType::In) {
+            return self.for_in_statement(keyword, label);
+        }
 
         // Initializer
         let initializer = if self.matches(&[TokenType::Semicolon]) {
@@ -188,7 +445,7 @@ impl Parser {
         } else {
             None
         };
-        self.consume(TokenType::Semicolon, "Expect ';' after loop condition.")?;
+        self.consume_semicolon(term("loop condition"))?;
 
         // Increment
         let increment = if !self.check(&TokenType::RightParen) {
@@ -196,51 +453,41 @@ impl Parser {
         } else {
             None
         };
-        self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
-
-        // Get body
-        let mut body = self.statement()?;
-
-        // Check increment
-        if let Some(value) = increment {
-            body = Stmt::Block(BlockStmt {
-                statements: vec![
-                    body,
-                    Stmt::Expression(ExpressionStmt {
-                        expression: Box::new(value),
-                    }),
-                ],
-            })
-        }
+        self.consume(TokenType::RightParen, P0102.with([term("for clauses")]))?;
 
-        // Check condition
-        let while_condition = if let Some(result) = condition {
-            result
-        } else {
-            Expr::Literal(LiteralExpr {
-                uid: next_uid(),
-                value: Object::Bool(true),
-            })
-        };
-        body = Stmt::While(WhileStmt {
-            condition: Box::new(while_condition),
-            body: Box::new(body),
-        });
-
-        // Check initializer
-        if let Some(init_statement) = initializer {
-            body = Stmt::Block(BlockStmt {
-                statements: vec![init_statement, body],
-            })
-        }
+        let body = Box::new(self.statement()?);
 
-        Ok(body)
+        Ok(Stmt::new_for(
+            keyword,
+            initializer.map(Box::new),
+            condition.map(Box::new),
+            increment.map(Box::new),
+            body,
+            label,
+        ))
+    }
+
+    /// `for (var x in values) ...`, the `var` and the `(` are already checked
+    fn for_in_statement(
+        &mut self,
+        keyword: Token,
+        label: Option<Token>,
+    ) -> Result<Stmt, LoxErrorResult> {
+        self.advance();
+        let name = self.consume(TokenType::Identifier, P0107.with([term("variable")]))?;
+        self.advance();
+        let iterable = Box::new(self.expression()?);
+        self.consume(TokenType::RightParen, P0102.with([term("for-in clause")]))?;
+        let body = Box::new(self.statement()?);
+
+        Ok(Stmt::new_for_in(keyword, name, iterable, body, label))
     }
 
     fn if_statement(&mut self) -> Result<Stmt, LoxErrorResult> {
-        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let keyword = self.previous();
+        self.consume(TokenType::LeftParen, P0104.with(["'if'".into()]))?;
         let condition = Box::new(self.expression()?);
-        self.consume(TokenType::RightParen, "Expect ')' after if condition.")?;
+        self.consume(TokenType::RightParen, P0102.with([term("if condition")]))?;
 
         let then_branch = Box::new(self.statement()?);
         let else_branch = match self.matches(&[TokenType::Else]) {
@@ -248,19 +495,14 @@ impl Parser {
             false => None,
         };
 
-        Ok(Stmt::If(IfStmt {
-            condition,
-            then_branch,
-            else_branch,
-        }))
+        Ok(Stmt::new_if(keyword, condition, then_branch, else_branch))
     }
 
     fn print_statement(&mut self) -> Result<Stmt, LoxErrorResult> {
+        let keyword = self.previous();
         let value = self.expression()?;
-        self.consume(TokenType::Semicolon, "Expect ';' after 'print' value.")?;
-        Ok(Stmt::Print(PrintStmt {
-            expression: Box::new(value),
-        }))
+        self.consume_semicolon(term("'print' value"))?;
+        Ok(Stmt::new_print(keyword, Box::new(value)))
     }
 
     fn return_statement(&mut self) -> Result<Stmt, LoxErrorResult> {
@@ -271,23 +513,42 @@ impl Parser {
             None
         };
 
-        self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
-        Ok(Stmt::Return(ReturnStmt { keyword, value }))
+        self.consume_semicolon(term("return value"))?;
+        Ok(Stmt::new_return(keyword, value))
+    }
+
+    fn yield_statement(&mut self) -> Result<Stmt, LoxErrorResult> {
+        let keyword = self.previous();
+        let value = match self.check(&TokenType::Semicolon) {
+            true => None,
+            false => Some(Box::new(self.expression()?)),
+        };
+        self.consume_semicolon(term("yield value"))?;
+        Ok(Stmt::new_yield(keyword, value))
     }
 
-    fn while_statement(&mut self) -> Result<Stmt, LoxErrorResult> {
-        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+    fn while_statement(&mut self, label: Option<Token>) -> Result<Stmt, LoxErrorResult> {
+        let keyword = self.previous();
+        self.consume(TokenType::LeftParen, P0104.with(["'while'".into()]))?;
         let condition = self.expression()?;
-        self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+        self.consume(TokenType::RightParen, P0102.with([term("condition")]))?;
         let body = self.statement()?;
 
-        Ok(Stmt::While(WhileStmt {
-            condition: Box::new(condition),
-            body: Box::new(body),
-        }))
+        Ok(Stmt::new_while(
+            keyword,
+            Box::new(condition),
+            Box::new(body),
+            label,
+        ))
     }
 
     fn block(&mut self) -> Result<Vec<Stmt>, LoxErrorResult> {
+        self.nested(Self::parse_block, "Statement")
+    }
+
+    /// Called right after the `{`
+    fn parse_block(&mut self) -> Result<Vec<Stmt>, LoxErrorResult> {
+        let open = self.previous();
         let mut statements: Vec<Stmt> = Vec::new();
 
         while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
@@ -295,308 +556,178 @@ impl Parser {
                 statements.push(statement);
             }
         }
-        self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
+        self.consume_closing_brace(&open, "block")?;
         Ok(statements)
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, LoxErrorResult> {
         let expr = self.expression()?;
-        self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
-        Ok(Stmt::Expression(ExpressionStmt {
-            expression: Box::new(expr),
-        }))
-    }
-
-    fn expression(&mut self) -> Result<Expr, LoxErrorResult> {
-        self.assignment()
-    }
-
-    fn assignment(&mut self) -> Result<Expr, LoxErrorResult> {
-        let expr = self.ternary()?;
-
-        if self.matches(&[TokenType::Equal]) {
-            let equals = self.previous();
-            let value = self.assignment()?;
-
-            if let Expr::Variable(variable) = expr {
-                return Ok(Expr::Assign(AssignExpr {
-                    name: variable.name,
-                    value: Box::new(value),
-                    uid: next_uid(),
-                }));
-            } else if let Expr::Get(get) = expr {
-                return Ok(Expr::Set(SetExpr {
-                    uid: next_uid(),
-                    object: get.object,
-                    name: get.name,
-                    value: Box::new(value),
-                }));
-            }
-            return Err(LoxErrorResult::parse_error(
-                equals,
-                "Invalid assignment target.",
-            ));
+        if self.repl && self.is_at_end() {
+            // Echo the value of a bare expression typed in the prompt
+            let line = self.peek().line;
+            let keyword = Token::new(TokenType::Print, "print".to_string(), Object::Nil, line);
+            return Ok(Stmt::new_print(keyword, Box::new(expr)));
         }
-        Ok(expr)
+        self.consume_semicolon(term("value"))?;
+        Ok(Stmt::new_expression(Box::new(expr)))
     }
 
-    // Add ternary support with '?' and ':'
-    fn ternary(&mut self) -> Result<Expr, LoxErrorResult> {
-        let mut expr = self.logic_or()?;
-
-        // Check for "?" to begin a ternary expression
-        while self.matches(&[TokenType::Question]) {
-            let then_branch = self.expression()?; // "Then" expression
-            self.consume(
-                TokenType::Colon,
-                "Expect ':' after then branch of ternary operator.",
-            )?;
-            let else_branch = self.ternary()?; // "Else expression with right-associativity"
-
-            expr = Expr::Ternary(TernaryExpr {
-                condition: Box::new(expr),
-                then_branch: Box::new(then_branch),
-                else_branch: Box::new(else_branch),
-                uid: next_uid(),
-            })
-        }
-        Ok(expr)
-    }
-
-    fn logic_or(&mut self) -> Result<Expr, LoxErrorResult> {
-        let mut expr = self.logic_and()?;
-
-        while self.matches(&[TokenType::Or]) {
-            let operator = self.previous();
-            let right = self.logic_and()?;
-            expr = Expr::Logical(LogicalExpr {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-                uid: next_uid(),
-            });
-        }
-
-        Ok(expr)
-    }
-
-    fn logic_and(&mut self) -> Result<Expr, LoxErrorResult> {
-        let mut expr = self.equality()?;
-
-        while self.matches(&[TokenType::And]) {
-            let operator = self.previous();
-            let right = self.equality()?;
-            expr = Expr::Logical(LogicalExpr {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-                uid: next_uid(),
-            });
-        }
-        Ok(expr)
+    fn expression(&mut self) -> Result<Expr, LoxErrorResult> {
+        self.nested(
+            |parser| parser.parse_precedence(Precedence::Assignment),
+            "Expression",
+        )
     }
 
-    fn equality(&mut self) -> Result<Expr, LoxErrorResult> {
-        let mut expr = self.comparison()?;
-
-        while self.matches(&[TokenType::BangEqual, TokenType::EqualEqual]) {
-            let operator = self.previous();
-            let right = self.comparison()?;
-            expr = Expr::Binary(BinaryExpr {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-                uid: next_uid(),
-            });
+    /// Parses operators binding at least as tightly as `precedence` (precedence climbing):
+    /// a prefix expression, then every infix operator found in `INFIX_RULES` that is strong enough
+    fn parse_precedence(&mut self, precedence: Precedence) -> Result<Expr, LoxErrorResult> {
+        let mut expr = self.prefix()?;
+        while let Some(rule) = infix_rule(&self.peek().token_type) {
+            if rule.precedence < precedence {
+                break;
+            }
+            let operator = self.advance();
+            expr = self.infix(expr, operator, rule)?;
         }
-
         Ok(expr)
     }
 
-    fn comparison(&mut self) -> Result<Expr, LoxErrorResult> {
-        let mut expr = self.term()?;
-
-        while self.matches(&[
-            TokenType::Greater,
-            TokenType::GreaterEqual,
-            TokenType::Less,
-            TokenType::LessEqual,
-        ]) {
-            let operator = self.previous();
-            let right = self.term()?;
-            expr = Expr::Binary(BinaryExpr {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-                uid: next_uid(),
-            });
-        }
-
-        Ok(expr)
+    /// Right operand of an infix operator, left associative operators only take the ones
+    /// binding more tightly than themselves so `a - b - c` groups as `(a - b) - c`
+    fn operand(&mut self, rule: &InfixRule) -> Result<Expr, LoxErrorResult> {
+        let precedence = match rule.right_associative {
+            true => rule.precedence,
+            false => rule.precedence.next(),
+        };
+        self.nested(|parser| parser.parse_precedence(precedence), "Expression")
     }
 
-    fn term(&mut self) -> Result<Expr, LoxErrorResult> {
-        let mut expr = self.factor()?;
-
-        while self.matches(&[TokenType::Minus, TokenType::Plus]) {
+    fn prefix(&mut self) -> Result<Expr, LoxErrorResult> {
+        if self.matches(&[TokenType::Bang, TokenType::Minus]) {
             let operator = self.previous();
-            let right = self.factor()?;
-            expr = Expr::Binary(BinaryExpr {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-                uid: next_uid(),
-            });
+            let right = self.nested(
+                |parser| parser.parse_precedence(Precedence::Unary),
+                "Expression",
+            )?;
+            return Ok(Expr::new_unary(operator, Box::new(right)));
         }
-
-        Ok(expr)
+        self.primary()
     }
 
-    fn factor(&mut self) -> Result<Expr, LoxErrorResult> {
-        let mut expr = self.unary()?;
-
-        while self.matches(&[TokenType::Slash, TokenType::Star]) {
-            let operator = self.previous();
-            let right = self.unary()?;
-            expr = Expr::Binary(BinaryExpr {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-                uid: next_uid(),
-            });
-        }
-
+    fn infix(
+        &mut self,
+        left: Expr,
+        operator: Token,
+        rule: &InfixRule,
+    ) -> Result<Expr, LoxErrorResult> {
+        let left = Box::new(left);
+        let expr = match rule.kind {
+            InfixKind::Binary => Expr::new_binary(left, operator, Box::new(self.operand(rule)?)),
+            InfixKind::Logical => Expr::new_logical(left, operator, Box::new(self.operand(rule)?)),
+            InfixKind::Ternary => {
+                let then_branch = self.expression()?;
+                self.consume(TokenType::Colon, P0113.with([]))?;
+                Expr::new_ternary(left, Box::new(then_branch), Box::new(self.operand(rule)?))
+            }
+            InfixKind::Assignment => {
+                let value = Box::new(self.operand(rule)?);
+                match *left {
+                    Expr::Variable(variable) => Expr::new_assign(variable.name, value),
+                    Expr::Get(get) => Expr::new_set(get.object, get.name, value),
+                    _ => return Err(LoxErrorResult::parse_error(operator, P0111.with([]))),
+                }
+            }
+            InfixKind::Call => self.finish_call(left)?,
+            InfixKind::Get => {
+                Expr::new_get(left, self.consume(TokenType::Identifier, P0108.with([]))?)
+            }
+        };
         Ok(expr)
     }
 
-    fn unary(&mut self) -> Result<Expr, LoxErrorResult> {
-        if self.matches(&[TokenType::Bang, TokenType::Minus]) {
-            let operator = self.previous();
-            let right = Box::new(self.unary()?);
-            return Ok(Expr::Unary(UnaryExpr {
-                operator,
-                right,
-                uid: next_uid(),
-            }));
-        }
-        self.call()
-    }
-
     fn finish_call(&mut self, callee: Box<Expr>) -> Result<Expr, LoxErrorResult> {
         let mut arguments: Vec<Expr> = Vec::new();
 
         if !self.check(&TokenType::RightParen) {
             loop {
                 if arguments.len() >= 255 {
-                    LoxErrorResult::parse_error(
-                        self.peek(),
-                        "Cannot have more than 255 arguments.",
-                    );
+                    // Reported without stopping, the parser is not in a confused state
+                    LoxErrorResult::parse_error(self.peek(), P0112.with([term("arguments")]))
+                        .report();
+                    self.had_error = true;
                 }
                 arguments.push(self.expression()?);
-                if !self.matches(&[TokenType::Comma]) {
+                // A trailing comma is allowed: `f(a, b,)`
+                if !self.matches(&[TokenType::Comma]) || self.check(&TokenType::RightParen) {
                     break;
                 }
             }
         }
 
-        let paren = self.consume(TokenType::RightParen, "Expect ')' after arguments.")?;
+        let paren = self.consume(TokenType::RightParen, P0102.with([term("arguments")]))?;
 
-        Ok(Expr::Call(CallExpr {
-            callee,
-            paren,
-            arguments,
-            uid: next_uid(),
-        }))
+        Ok(Expr::new_call(callee, paren, arguments))
     }
 
-    fn call(&mut self) -> Result<Expr, LoxErrorResult> {
-        let mut expr = self.primary()?;
-
-        loop {
-            if self.matches(&[TokenType::LeftParen]) {
-                expr = self.finish_call(Box::new(expr))?;
-            } else if self.matches(&[TokenType::Dot]) {
-                let name =
-                    self.consume(TokenType::Identifier, "Expect property name after '.'.")?;
-                expr = Expr::Get(GetExpr {
-                    uid: next_uid(),
-                    object: Box::new(expr),
-                    name,
-                })
-            } else {
-                break;
+    /// Value of a literal out of the constant pool, numbers are small enough to be copied
+    fn constant(&mut self, value: Object) -> Object {
+        let Object::String(text) = value else {
+            return value;
+        };
+        match self.constants.get(&text) {
+            Some(shared) => Object::String(Rc::clone(shared)),
+            None => {
+                self.constants.insert(Rc::clone(&text));
+                Object::String(text)
             }
         }
-        Ok(expr)
     }
 
     fn primary(&mut self) -> Result<Expr, LoxErrorResult> {
+        // A literal only keeps its value, the span comes from its token
         if self.matches(&[TokenType::False]) {
-            return Ok(Expr::Literal(LiteralExpr {
-                value: Object::Bool(false),
-                uid: next_uid(),
-            }));
+            return Ok(Expr::new_literal(Object::Bool(false)).with_span(self.previous().span()));
         }
         if self.matches(&[TokenType::True]) {
-            return Ok(Expr::Literal(LiteralExpr {
-                value: Object::Bool(true),
-                uid: next_uid(),
-            }));
+            return Ok(Expr::new_literal(Object::Bool(true)).with_span(self.previous().span()));
         }
         if self.matches(&[TokenType::Nil]) {
-            return Ok(Expr::Literal(LiteralExpr {
-                value: Object::Nil,
-                uid: next_uid(),
-            }));
+            return Ok(Expr::new_literal(Object::Nil).with_span(self.previous().span()));
         }
         if self.matches(&[TokenType::Number, TokenType::String]) {
             let value = self.previous();
-            return Ok(Expr::Literal(LiteralExpr {
-                value: value.literal,
-                uid: next_uid(),
-            }));
+            let span = value.span();
+            let literal = self.constant(value.literal);
+            return Ok(Expr::new_literal(literal).with_span(span));
         }
 
         if self.matches(&[TokenType::Super]) {
             let keyword = self.previous();
-            self.consume(TokenType::Dot, "Expect '.' after super.")?;
-            let method = self.consume(TokenType::Identifier, "Expect super class method name.")?;
-            return Ok(Expr::Super(SuperExpr {
-                uid: next_uid(),
-                keyword,
-                method,
-            }));
+            self.consume(TokenType::Dot, P0109.with([]))?;
+            let method = self.consume(TokenType::Identifier, P0110.with([]))?;
+            return Ok(Expr::new_super(keyword, method));
         }
 
         if self.matches(&[TokenType::This]) {
             let keyword = self.previous();
-            return Ok(Expr::This(ThisExpr {
-                keyword,
-                uid: next_uid(),
-            }));
+            return Ok(Expr::new_this(keyword));
         }
 
         if self.matches(&[TokenType::Identifier]) {
             let name = self.previous();
-            return Ok(Expr::Variable(VariableExpr {
-                name,
-                uid: next_uid(),
-            }));
+            return Ok(Expr::new_variable(name));
         }
 
         if self.matches(&[TokenType::LeftParen]) {
+            let open = self.previous();
             let expression = Box::new(self.expression()?);
-            self.consume(TokenType::RightParen, "Expect ')' after expression.")?;
-            return Ok(Expr::Grouping(GroupingExpr {
-                expression,
-                uid: next_uid(),
-            }));
+            let close = self.consume(TokenType::RightParen, P0102.with([term("expression")]))?;
+            // The parentheses are part of the grouping
+            let span = open.span().to(close.span());
+            return Ok(Expr::new_grouping(expression).with_span(span));
         }
-        Err(LoxErrorResult::parse_error(
-            self.peek(),
-            "Expect expression.",
-        ))
+        Err(LoxErrorResult::parse_error(self.peek(), P0101.with([])))
     }
 
     // HELPERS
@@ -610,13 +741,44 @@ impl Parser {
         false
     }
 
-    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<Token, LoxErrorResult> {
+    fn consume(
+        &mut self,
+        token_type: TokenType,
+        message: Message,
+    ) -> Result<Token, LoxErrorResult> {
         match self.check(&token_type) {
             true => Ok(self.advance()),
             false => Err(LoxErrorResult::parse_error(self.peek(), message)),
         }
     }
 
+    /// A `;` missing at the end of a line is reported on the last token of that line
+    /// instead of on the first token of the next statement. `after` names what it ends.
+    fn consume_semicolon(&mut self, after: Argument) -> Result<Token, LoxErrorResult> {
+        if self.check(&TokenType::Semicolon) {
+            return Ok(self.advance());
+        }
+        let previous = self.previous();
+        let message = P0103.with([after]);
+        match self.peek().line > previous.line {
+            true => Err(LoxErrorResult::parse_error(previous, message)),
+            false => Err(LoxErrorResult::parse_error(self.peek(), message)),
+        }
+    }
+
+    /// A missing `}` is usually noticed far from where it belongs, often at the end of
+    /// the file, so the error names the line of the `{` left open
+    fn consume_closing_brace(
+        &mut self,
+        open: &Token,
+        what: &'static str,
+    ) -> Result<Token, LoxErrorResult> {
+        self.consume(
+            TokenType::RightBrace,
+            P0105.with([term(what), open.line.to_string().into()]),
+        )
+    }
+
     fn check(&mut self, token_type: &TokenType) -> bool {
         match self.is_at_end() {
             true => false,
@@ -624,6 +786,13 @@ impl Parser {
         }
     }
 
+    /// Whether the token `distance` tokens after the current one has the type
+    fn check_ahead(&self, distance: usize, token_type: &TokenType) -> bool {
+        self.tokens
+            .get(self.current + distance)
+            .is_some_and(|token| token.is(token_type.to_owned()))
+    }
+
     fn advance(&mut self) -> Token {
         if !self.is_at_end() {
             self.current += 1;
@@ -643,6 +812,25 @@ impl Parser {
         self.tokens[self.current - 1].clone()
     }
 
+    /// Runs a rule that may recurse, failing once the source nests deeper than `max_nesting`.
+    /// `kind` names what is nested in the error.
+    fn nested<T>(
+        &mut self,
+        rule: impl FnOnce(&mut Parser) -> Result<T, LoxErrorResult>,
+        kind: &'static str,
+    ) -> Result<T, LoxErrorResult> {
+        if self.depth >= self.max_nesting {
+            return Err(LoxErrorResult::parse_error(
+                self.peek(),
+                P0116.with([term(kind)]),
+            ));
+        }
+        self.depth += 1;
+        let result = rule(self);
+        self.depth -= 1;
+        result
+    }
+
     fn synchronize(&mut self) {
         self.advance();
 
@@ -657,6 +845,9 @@ impl Parser {
                 | TokenType::For
                 | TokenType::If
                 | TokenType::While
+                | TokenType::Break
+                | TokenType::Continue
+                | TokenType::Yield
                 | TokenType::Print
                 | TokenType::Return => return,
                 _ => {
@@ -666,3 +857,251 @@ impl Parser {
         }
     }
 }
+
+#[cfg(test)]
+mod parser_tests {
+    use crate::{ast::Span, ast_printer::AstPrinter, error::capture_diagnostics, scanner::Scanner};
+
+    use super::*;
+
+    fn parse_source(source: &str, repl: bool) -> (Vec<Stmt>, bool) {
+        let tokens = Scanner::new(source.to_string()).scan_tokens();
+        let mut parser = Parser::new(tokens);
+        let statements = match repl {
+            true => parser.parse_repl(),
+            false => parser.parse(),
+        };
+        (statements, parser.had_error)
+    }
+
+    #[test]
+    fn test_repl_echoes_trailing_expression() {
+        // Arrange
+        let source = "var a = 1; a + 2";
+        // Act
+        let (statements, had_error) = parse_source(source, true);
+        // Assert
+        assert!(!had_error);
+        assert_eq!(statements.len(), 2);
+        assert!(matches!(statements[1], Stmt::Print(_)));
+    }
+
+    #[test]
+    fn test_trailing_commas() {
+        // Act
+        let (statements, had_error) = parse_source("fun f(a, b,) {}\nf(\n  1,\n  2,\n);", false);
+        let errors: Vec<String> = ["f(,);", "f(1,,);", "fun g(,) {}", "f(1 2,);"]
+            .iter()
+            .map(|source| capture_diagnostics(|| assert!(parse_source(source, false).1)))
+            .collect();
+        // Assert
+        assert!(!had_error);
+        assert!(matches!(&statements[0], Stmt::Function(function) if function.params.len() == 2));
+        assert!(matches!(&statements[1], Stmt::Expression(stmt)
+            if matches!(stmt.expression.as_ref(), Expr::Call(call) if call.arguments.len() == 2)));
+        assert_eq!(
+            errors,
+            [
+                "[Line 1] - Error[P0101] at ',': Expect expression.\n",
+                "[Line 1] - Error[P0101] at ',': Expect expression.\n",
+                "[Line 1] - Error[P0107] at ',': Expect parameter name.\n",
+                "[Line 1] - Error[P0102] at '2': Expect ')' after arguments.\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_labeled_loops() {
+        // Arrange
+        let source = "outer: while (true) { for (;;) { continue outer; } }\nx: print 1;";
+        // Act
+        let (statements, had_error) = parse_source(source, false);
+        let error = capture_diagnostics(|| assert!(parse_source("x: print 1;", false).1));
+        // Assert
+        assert!(had_error);
+        assert!(matches!(&statements[0], Stmt::While(stmt)
+            if stmt.label.as_ref().is_some_and(|label| label.lexeme == "outer")));
+        assert_eq!(
+            error,
+            "[Line 1] - Error[P0114] at 'print': Expect 'while' or 'for' after loop label.\n"
+        );
+    }
+
+    #[test]
+    fn test_missing_semicolon_outside_repl() {
+        // Arrange
+        let source = "1 + 2";
+        // Act
+        let (_, had_error) = parse_source(source, false);
+        // Assert
+        assert!(had_error);
+    }
+
+    #[test]
+    fn test_deep_nesting_is_an_error() {
+        // Arrange
+        let source = format!("print {}1;", "(".repeat(10_000));
+        let blocks = format!("{}print 1;", "{ if (true) ".repeat(10_000));
+        // Act
+        // Unoptimized builds need a stack as big as the main thread's for `MAX_NESTING` levels
+        let parse = std::thread::Builder::new()
+            .stack_size(8 * 1024 * 1024)
+            .spawn(move || {
+                (
+                    parse_source(&source, false).1,
+                    parse_source(&blocks, false).1,
+                )
+            })
+            .expect("Could not spawn parser thread");
+        let (had_error, blocks_had_error) = parse.join().expect("Parser overflowed its stack");
+        // Assert
+        assert!(had_error);
+        assert!(blocks_had_error);
+    }
+
+    #[test]
+    fn test_configurable_nesting_limit() {
+        // Arrange
+        let parse = |source: &str| {
+            let tokens = Scanner::new(source.to_string()).scan_tokens();
+            let mut parser = Parser::new(tokens).with_max_nesting(4);
+            capture_diagnostics(|| {
+                parser.parse();
+            })
+        };
+        // Act
+        let shallow = parse("print ((1));");
+        let expression = parse("print ((((1))));");
+        let statements = parse("{ { { { print 1; } } } }");
+        // Assert
+        assert_eq!(shallow, "");
+        assert!(expression
+            .starts_with("[Line 1] - Error[P0116] at '(': Expression too deeply nested.\n"));
+        assert!(statements
+            .starts_with("[Line 1] - Error[P0116] at '{': Statement too deeply nested.\n"));
+    }
+
+    #[test]
+    fn test_operator_precedence_and_associativity() {
+        // Arrange
+        let sources = [
+            "1 - 2 - 3 * -4",
+            "a = b = c ? d : e ? f : g or h and i == j < k",
+            "-a.b(1) + !c",
+        ];
+        // Act
+        let printed: Vec<String> = sources
+            .iter()
+            .map(|source| {
+                let tokens = Scanner::new(source.to_string()).scan_tokens();
+                let expression = Parser::new(tokens).parse_expression();
+                expression.map_or_else(
+                    |err| err.to_string(),
+                    |expr| AstPrinter::new().print_expr(&expr),
+                )
+            })
+            .collect();
+        // Assert
+        assert_eq!(
+            printed,
+            [
+                "(binary :left (binary :left (literal :value 1) :operator - :right (literal :value 2)) \
+                 :operator - \
+                 :right (binary :left (literal :value 3) :operator * \
+                 :right (unary :operator - :right (literal :value 4))))",
+                "(assign :name a :value (assign :name b :value \
+                 (ternary :condition (variable :name c) :then_branch (variable :name d) \
+                 :else_branch (ternary :condition (variable :name e) :then_branch (variable :name f) \
+                 :else_branch (logical :left (variable :name g) :operator or \
+                 :right (logical :left (variable :name h) :operator and \
+                 :right (binary :left (variable :name i) :operator == \
+                 :right (binary :left (variable :name j) :operator < :right (variable :name k)))))))))",
+                "(binary :left (unary :operator - \
+                 :right (call :callee (get :object (variable :name a) :name b) :paren ) \
+                 :arguments [(literal :value 1)])) \
+                 :operator + :right (unary :operator ! :right (variable :name c)))",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_node_spans_and_uids() {
+        // Arrange
+        let source = "var total =\n  (1 + count) * f(\"a\");";
+        // Act
+        let (statements, _) = parse_source(source, false);
+        let Stmt::Var(var) = &statements[0] else {
+            panic!("Expected a var statement");
+        };
+        let initializer = var.initializer.as_ref().unwrap();
+        let Expr::Binary(binary) = initializer.as_ref() else {
+            panic!("Expected a binary expression");
+        };
+        let span = |line, column, end_line, end_column| Span {
+            line,
+            column,
+            end_line,
+            end_column,
+        };
+        // Assert
+        assert_eq!(statements[0].span(), span(1, 0, 2, 23));
+        assert_eq!(initializer.span(), span(2, 2, 2, 22));
+        assert_eq!(binary.left.span(), span(2, 2, 2, 13));
+        assert_eq!(binary.right.span(), span(2, 16, 2, 22));
+        assert_ne!(binary.left, binary.right);
+    }
+
+    #[test]
+    fn test_ast_printer_prints_statements() {
+        // Arrange
+        let source =
+            "class A < B { f(x: Number, y) -> nil { return; } } for (;;) print a.b = \"s\";";
+        // Act
+        let (statements, _) = parse_source(source, false);
+        let printed: Vec<String> = statements
+            .iter()
+            .map(|statement| AstPrinter::new().print_stmt(statement))
+            .collect();
+        // Assert
+        assert_eq!(
+            printed,
+            [
+                "(class :name A :super_class (variable :name B) \
+                 :methods [(function :name f :params [x y] :body [(return :keyword return :value nil)] \
+                 :param_annotations [Number nil] :return_annotation nil)])",
+                "(for :keyword for :initializer nil :condition nil :increment nil \
+                 :body (print :keyword print :expression (set :object (variable :name a) :name b \
+                 :value (literal :value \"s\"))) :label nil)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_equal_string_literals_share_text() {
+        // Arrange
+        let source = "print \"lox\"; print \"lox\" + \"other\";";
+        // Act
+        let (statements, _) = parse_source(source, false);
+        let literals: Vec<Rc<str>> = statements
+            .iter()
+            .flat_map(|statement| match statement {
+                Stmt::Print(print) => match print.expression.as_ref() {
+                    Expr::Binary(binary) => vec![binary.left.as_ref(), binary.right.as_ref()],
+                    expression => vec![expression],
+                },
+                _ => vec![],
+            })
+            .map(|expression| match expression {
+                Expr::Literal(LiteralExpr {
+                    value: Object::String(text),
+                    ..
+                }) => Rc::clone(text),
+                expression => panic!("Expected a string literal, got {expression:?}"),
+            })
+            .collect();
+        // Assert
+        assert_eq!(literals.len(), 3);
+        assert!(Rc::ptr_eq(&literals[0], &literals[1]));
+        assert!(!Rc::ptr_eq(&literals[0], &literals[2]));
+    }
+}
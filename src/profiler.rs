@@ -0,0 +1,129 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{hooks::InterpreterHooks, interpreter::Interpreter};
+
+/// Time spent in one function over the whole execution
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProfileEntry {
+    pub calls: usize,
+    /// Time from entering to leaving the function, including the functions it called
+    pub total: Duration,
+    /// Time spent in the function's own code only
+    pub self_time: Duration,
+}
+
+#[derive(Debug, Clone)]
+struct Frame {
+    name: String,
+    started: Instant,
+    /// Time spent in the calls made from this frame
+    children: Duration,
+}
+
+/// Collects call counts and timings of Lox functions and natives (`--profile`)
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    entries: HashMap<String, ProfileEntry>,
+    stack: Vec<Frame>,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    pub fn enter(&mut self, name: &str) {
+        self.stack.push(Frame {
+            name: name.to_string(),
+            started: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    pub fn exit(&mut self) {
+        let Some(frame) = self.stack.pop() else {
+            return;
+        };
+        let elapsed = frame.started.elapsed();
+        if let Some(caller) = self.stack.last_mut() {
+            caller.children += elapsed;
+        }
+        // Recursive calls are already inside the outermost call's total
+        let is_recursive = self.stack.iter().any(|caller| caller.name == frame.name);
+        let entry = self.entries.entry(frame.name).or_default();
+        entry.calls += 1;
+        entry.self_time += elapsed.saturating_sub(frame.children);
+        if !is_recursive {
+            entry.total += elapsed;
+        }
+    }
+
+    /// Entries sorted from the most to the least total time
+    pub fn entries(&self) -> Vec<(String, ProfileEntry)> {
+        let mut entries: Vec<(String, ProfileEntry)> = self
+            .entries
+            .iter()
+            .map(|(name, entry)| (name.clone(), entry.clone()))
+            .collect();
+        entries.sort_by(|(a_name, a), (b_name, b)| b.total.cmp(&a.total).then(a_name.cmp(b_name)));
+        entries
+    }
+
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "{:<24} {:>10} {:>12} {:>12}\n",
+            "function", "calls", "total", "self"
+        );
+        for (name, entry) in self.entries() {
+            report.push_str(&format!(
+                "{:<24} {:>10} {:>12} {:>12}\n",
+                name,
+                entry.calls,
+                format!("{:.2?}", entry.total),
+                format!("{:.2?}", entry.self_time)
+            ));
+        }
+        report
+    }
+}
+
+impl InterpreterHooks for Profiler {
+    fn on_function_enter(&mut self, _interpreter: &mut Interpreter, name: &str) {
+        self.enter(name);
+    }
+
+    fn on_function_exit(&mut self, _interpreter: &mut Interpreter, _name: &str) {
+        self.exit();
+    }
+}
+
+#[cfg(test)]
+mod profiler_tests {
+    use super::*;
+    use crate::shared::{Rc, RefCell};
+
+    #[test]
+    fn test_profile_recursive_functions() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let profiler = Rc::new(RefCell::new(Profiler::new()));
+        interpreter.add_hooks(profiler.clone());
+        let source = "fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+            fun run() { clock(); var result = fib(10); return result; }
+            run();";
+        // Act
+        crate::run(source.to_string(), &mut interpreter, false).ok();
+        let profiler = profiler.borrow();
+        let entries: HashMap<String, ProfileEntry> = profiler.entries().into_iter().collect();
+        // Assert
+        assert_eq!(entries["fib"].calls, 177);
+        assert_eq!(entries["run"].calls, 1);
+        assert_eq!(entries["<native clock>"].calls, 1);
+        assert!(entries["run"].total >= entries["fib"].total);
+        assert!(entries["fib"].self_time <= entries["fib"].total);
+        assert_eq!(profiler.entries()[0].0, "run");
+    }
+}
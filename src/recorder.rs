@@ -0,0 +1,260 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::{
+    coverage::statement_line, hooks::InterpreterHooks, interpreter::Interpreter, object::Object,
+    stmt::Stmt, token::Token,
+};
+
+/// One step of a recorded run
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEvent {
+    /// A statement on this line is about to run
+    Statement { line: usize },
+    /// A variable was defined or assigned, `value` written out with strings quoted
+    Write {
+        line: usize,
+        name: String,
+        value: String,
+    },
+}
+
+/// Records every executed statement and variable write of a run (`--record=<file>`).
+/// The log has an event per line: `s <line>` or `w <line> <name> <value>`.
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    events: Vec<TraceEvent>,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder::default()
+    }
+
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    pub fn to_log(&self) -> String {
+        let mut log = String::new();
+        for event in &self.events {
+            match event {
+                TraceEvent::Statement { line } => log.push_str(&format!("s {line}\n")),
+                TraceEvent::Write { line, name, value } => {
+                    // Values stay on their line, newlines in strings are escaped
+                    let value = value.replace('\\', "\\\\").replace('\n', "\\n");
+                    log.push_str(&format!("w {line} {name} {value}\n"));
+                }
+            }
+        }
+        log
+    }
+}
+
+impl InterpreterHooks for Recorder {
+    fn on_statement(&mut self, _interpreter: &mut Interpreter, statement: &Stmt) {
+        if let Some(line) = statement_line(statement) {
+            self.events.push(TraceEvent::Statement { line });
+        }
+    }
+
+    fn on_variable_write(&mut self, _interpreter: &mut Interpreter, name: &Token, value: &Object) {
+        self.events.push(TraceEvent::Write {
+            line: name.line,
+            name: name.lexeme.clone(),
+            value: value.to_string(),
+        });
+    }
+}
+
+/// The value a variable had when a line was reached (`replay <trace> x 42 3`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceQuery {
+    pub name: String,
+    pub line: usize,
+    /// Counted from 1, the nth statement run on the line
+    pub iteration: usize,
+}
+
+/// A recorded run read back from its log, for looking into it afterwards
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    events: Vec<TraceEvent>,
+}
+
+impl Trace {
+    pub fn from_log(log: &str) -> Result<Trace, String> {
+        let mut events = Vec::new();
+        for (index, entry) in log.lines().enumerate() {
+            let event = parse_event(entry)
+                .ok_or_else(|| format!("Line {} of the trace is not an event.", index + 1))?;
+            events.push(event);
+        }
+        Ok(Trace { events })
+    }
+
+    /// Every write with the line and the iteration of that line it happened in
+    pub fn writes(&self) -> Vec<String> {
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        let mut writes = Vec::new();
+        for event in &self.events {
+            match event {
+                TraceEvent::Statement { line } => {
+                    match runs.iter_mut().find(|run| run.0 == *line) {
+                        Some(run) => run.1 += 1,
+                        None => runs.push((*line, 1)),
+                    }
+                }
+                TraceEvent::Write { line, name, value } => {
+                    let iteration = runs
+                        .iter()
+                        .find(|run| run.0 == *line)
+                        .map_or(1, |run| run.1);
+                    writes.push(format!(
+                        "line {line}, iteration {iteration}: {name} = {value}"
+                    ));
+                }
+            }
+        }
+        writes
+    }
+
+    /// Answers the query with the last value written to a variable of that name before the
+    /// statement ran, whatever scope it was in
+    pub fn value_at(&self, query: &TraceQuery) -> Result<String, String> {
+        let TraceQuery {
+            name,
+            line,
+            iteration,
+        } = query;
+        let mut runs = 0;
+        let reached = self.events.iter().position(|event| match event {
+            TraceEvent::Statement { line: at } if at == line => {
+                runs += 1;
+                runs == *iteration
+            }
+            _ => false,
+        });
+        let Some(reached) = reached else {
+            return Err(match runs {
+                0 => format!("Line {line} never ran."),
+                _ => format!("Line {line} ran {runs} times, not {iteration}."),
+            });
+        };
+        self.events[..reached]
+            .iter()
+            .rev()
+            .find_map(|event| match event {
+                TraceEvent::Write {
+                    name: written,
+                    value,
+                    ..
+                } if written == name => Some(format!("{name} = {value}")),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                format!("'{name}' was not written before line {line}, iteration {iteration}.")
+            })
+    }
+}
+
+fn parse_event(entry: &str) -> Option<TraceEvent> {
+    let mut parts = entry.splitn(4, ' ');
+    let kind = parts.next()?;
+    let line = parts.next()?.parse().ok()?;
+    match kind {
+        "s" => Some(TraceEvent::Statement { line }),
+        "w" => Some(TraceEvent::Write {
+            line,
+            name: parts.next()?.to_string(),
+            value: unescape(parts.next()?),
+        }),
+        _ => None,
+    }
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(char) = chars.next() {
+        match (char, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                unescaped.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                unescaped.push('\\');
+                chars.next();
+            }
+            (char, _) => unescaped.push(char),
+        }
+    }
+    unescaped
+}
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let statements = self
+            .events
+            .iter()
+            .filter(|event| matches!(event, TraceEvent::Statement { .. }))
+            .count();
+        write!(
+            f,
+            "{statements} statements run, {} variable writes",
+            self.events.len() - statements
+        )
+    }
+}
+
+#[cfg(test)]
+mod recorder_tests {
+    use super::*;
+    use crate::shared::{Rc, RefCell};
+
+    fn record(source: &str) -> Trace {
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        let recorder = Rc::new(RefCell::new(Recorder::new()));
+        interpreter.add_hooks(recorder.clone());
+        crate::run(source.to_string(), &mut interpreter, false).ok();
+        let log = recorder.borrow().to_log();
+        Trace::from_log(&log).unwrap()
+    }
+
+    #[test]
+    fn test_value_at_an_iteration() {
+        // Arrange
+        let trace = record("var x = 0;\nfor (var i = 0; i < 5; i = i + 1) {\n  x = x + i;\n}");
+        let query = |name: &str, iteration| TraceQuery {
+            name: name.to_string(),
+            line: 3,
+            iteration,
+        };
+        // Act
+        let third = trace.value_at(&query("x", 3));
+        let missing = trace.value_at(&query("y", 1));
+        let too_far = trace.value_at(&query("x", 6));
+        // Assert
+        assert_eq!(third, Ok("x = 1".to_string()));
+        assert!(missing.is_err());
+        assert_eq!(too_far, Err("Line 3 ran 5 times, not 6.".to_string()));
+    }
+
+    #[test]
+    fn test_log_round_trip() {
+        // Arrange
+        let trace = record("var s = \"a\nb\";\nfor (var c in \"xy\") print c;");
+        // Act
+        let writes = trace.writes();
+        // Assert
+        assert_eq!(
+            writes,
+            [
+                "line 1, iteration 1: s = \"a\nb\"",
+                "line 3, iteration 1: c = \"x\"",
+                "line 3, iteration 2: c = \"y\""
+            ]
+        );
+        assert_eq!(trace.to_string(), "4 statements run, 3 variable writes");
+    }
+}
@@ -0,0 +1,266 @@
+use crate::{
+    ast_printer::AstPrinter,
+    interpreter::Interpreter,
+    line_editor::LineEditor,
+    object::Object,
+    parser::Parser,
+    scanner::{is_identifier_part, Scanner, KEYWORDS},
+    token::{Token, TokenType},
+    RunError,
+};
+
+const HELP: &str = "Commands:
+  :help         Show this message
+  :env          List the variables visible from the current scope
+  :ast <expr>   Print the parse tree of an expression
+  :reset        Discard every definition and start a fresh session
+  :load <file>  Execute a file into the current session, the variables
+                already defined keep their value
+  :time [code]  Time the stages of the code, or of every evaluation until
+                ':time' is typed again
+  :quit         Exit the prompt (same as Ctrl-D)";
+
+/// `timing` prints how long the stages of every evaluation took, as `:time` does.
+/// `:reset` replaces the session with one made by `new_interpreter`, which has to apply the
+/// same options (capabilities, file root, strict mode...) the first one was made with.
+pub fn run_prompt(
+    interpreter: &mut Interpreter,
+    mut timing: bool,
+    new_interpreter: &dyn Fn() -> Interpreter,
+) {
+    let mut editor = LineEditor::new();
+    loop {
+        let completer = |line: &str| complete(line, interpreter);
+        // End of input (Ctrl-D)
+        let Some(line) = editor.read_line("> ", &completer) else {
+            break;
+        };
+        match line.trim().strip_prefix(':') {
+            Some(command) => {
+                if !run_command(command, interpreter, &mut timing, new_interpreter) {
+                    break;
+                }
+            }
+            None => {
+                evaluate(line, interpreter, timing);
+                if interpreter.exit_code.is_some() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Runs code typed at the prompt, followed by the time of each stage when `timed`
+fn evaluate(source: String, interpreter: &mut Interpreter, timed: bool) {
+    // Errors were reported, the session goes on
+    match timed {
+        true => {
+            let (_, timings) = crate::run_timed(source, interpreter, true);
+            println!("{timings}");
+        }
+        false => {
+            let _ = crate::run(source, interpreter, true);
+        }
+    }
+}
+
+/// Runs a meta-command typed after ':', returns false when the prompt has to be closed
+fn run_command(
+    command: &str,
+    interpreter: &mut Interpreter,
+    timing: &mut bool,
+    new_interpreter: &dyn Fn() -> Interpreter,
+) -> bool {
+    let (name, argument) = match command.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (command, ""),
+    };
+    match name {
+        "help" => println!("{HELP}"),
+        "env" => print_environment(interpreter),
+        "ast" => print_ast(argument),
+        "reset" => {
+            // The profiler or coverage of the session keep collecting after the reset
+            let hooks = interpreter.hooks();
+            *interpreter = new_interpreter();
+            interpreter.set_hooks(hooks);
+            println!("Session reset.");
+        }
+        "load" => match argument {
+            "" => eprintln!("Usage: :load <file>"),
+            path => {
+                let result = std::fs::read_to_string(path)
+                    .map_err(RunError::Io)
+                    .and_then(|source| crate::reload(source, interpreter));
+                if let Err(RunError::Io(err)) = result {
+                    eprintln!("Could not load '{path}': {err}");
+                }
+            }
+        },
+        "time" => match argument {
+            "" => {
+                *timing = !*timing;
+                match timing {
+                    true => println!("Timing every evaluation."),
+                    false => println!("Timing off."),
+                }
+            }
+            code => evaluate(code.to_string(), interpreter, true),
+        },
+        "quit" | "q" => return false,
+        _ => eprintln!("Unknown command ':{name}'. Type ':help' to list the available commands."),
+    }
+    true
+}
+
+/// Prints every scope from the innermost one out to the globals
+fn print_environment(interpreter: &Interpreter) {
+    print!("{}", interpreter.environment().borrow().describe());
+}
+
+/// Completes the identifier at the end of `line` with keywords and visible variables,
+/// or with the fields and methods of an instance after a `.` (e.g. `point.x`)
+pub fn complete(line: &str, interpreter: &Interpreter) -> (usize, Vec<String>) {
+    let start = word_start(line);
+    let prefix = &line[start..];
+    let mut candidates = match line[..start].strip_suffix('.') {
+        Some(target) => member_names(target, interpreter),
+        None => {
+            let mut names = interpreter.environment().borrow().visible_names();
+            names.extend(KEYWORDS.iter().map(|(keyword, _)| keyword.to_string()));
+            names
+        }
+    };
+    candidates.retain(|name| name.starts_with(prefix));
+    candidates.sort();
+    candidates.dedup();
+    (start, candidates)
+}
+
+/// Byte offset where the identifier at the end of `text` starts
+fn word_start(text: &str) -> usize {
+    text.char_indices()
+        .rev()
+        .find(|(_, character)| !is_identifier_part(*character))
+        .map_or(0, |(idx, character)| idx + character.len_utf8())
+}
+
+/// Members of the instance a property chain like `a.b` points to. Only variables and fields
+/// are looked up so completing never runs any code.
+fn member_names(target: &str, interpreter: &Interpreter) -> Vec<String> {
+    let chain_start = target
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !(is_identifier_part(*c) || *c == '.'))
+        .map_or(0, |(idx, character)| idx + character.len_utf8());
+    let mut path = target[chain_start..].split('.');
+    let name_token =
+        |name: &str| Token::new(TokenType::Identifier, name.to_string(), Object::Nil, 0);
+
+    let root = path.next().unwrap_or_default();
+    let Ok(mut object) = interpreter.environment().borrow().get(&name_token(root)) else {
+        return Vec::new();
+    };
+    for name in path {
+        object = match object {
+            Object::ClassInstance(instance) => {
+                match instance.borrow().get(&name_token(name), instance.clone()) {
+                    Ok(value) => value,
+                    Err(_) => return Vec::new(),
+                }
+            }
+            Object::Module(module) => match module.get(&name_token(name)) {
+                Ok(value) => value,
+                Err(_) => return Vec::new(),
+            },
+            _ => return Vec::new(),
+        };
+    }
+    match object {
+        Object::ClassInstance(instance) => instance.borrow().member_names(),
+        Object::Module(module) => module.member_names(),
+        _ => Vec::new(),
+    }
+}
+
+fn print_ast(source: &str) {
+    if source.is_empty() {
+        eprintln!("Usage: :ast <expr>");
+        return;
+    }
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens();
+    if !scanner.errors().is_empty() {
+        return;
+    }
+    match Parser::new(tokens).parse_expression() {
+        Ok(expression) => println!("{}", AstPrinter::new().print_expr(&expression)),
+        Err(err) => err.report(),
+    }
+}
+
+#[cfg(test)]
+mod repl_tests {
+    use super::*;
+    use crate::capabilities::{Capabilities, Capability};
+
+    fn make_interpreter(source: &str) -> Interpreter {
+        let mut interpreter = Interpreter::new();
+        crate::run(source.to_string(), &mut interpreter, false).ok();
+        interpreter
+    }
+
+    #[test]
+    fn test_reset_keeps_the_options_of_the_session() {
+        // Arrange
+        let new_interpreter = || {
+            let mut interpreter = Interpreter::new();
+            interpreter.capabilities = Capabilities::none().grant(Capability::Time);
+            interpreter.strict = true;
+            interpreter
+        };
+        let mut interpreter = new_interpreter();
+        crate::run("var x = 1;".to_string(), &mut interpreter, true).ok();
+        // Act
+        run_command("reset", &mut interpreter, &mut false, &new_interpreter);
+        let output = crate::run_captured("print x;", &mut interpreter);
+        let denied = crate::run_captured("readFile(\"x\");", &mut interpreter);
+        // Assert
+        assert_eq!(output.exit_code, 70);
+        assert!(!interpreter.capabilities.allows(Capability::Fs));
+        assert!(interpreter.strict);
+        assert!(denied.diagnostics.contains("fs"), "{}", denied.diagnostics);
+    }
+
+    #[test]
+    fn test_complete_globals_and_keywords() {
+        // Arrange
+        let interpreter = make_interpreter("var counter = 1; var clamp = 2;");
+        // Act
+        let (start, candidates) = complete("print cl", &interpreter);
+        let (_, keywords) = complete("wh", &interpreter);
+        // Assert
+        assert_eq!(start, 6);
+        assert_eq!(
+            candidates,
+            vec!["clamp", "class", "clock", "clockMonotonic"]
+        );
+        assert_eq!(keywords, vec!["while"]);
+    }
+
+    #[test]
+    fn test_complete_instance_members() {
+        // Arrange
+        let interpreter = make_interpreter(
+            "class Point { move() {} } var point = Point(); point.x = 1; point.inner = Point();",
+        );
+        // Act
+        let (start, candidates) = complete("print point.", &interpreter);
+        let (_, nested) = complete("point.inner.m", &interpreter);
+        // Assert
+        assert_eq!(start, 12);
+        assert_eq!(candidates, vec!["inner", "move", "x"]);
+        assert_eq!(nested, vec!["move"]);
+    }
+}
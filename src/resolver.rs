@@ -1,6 +1,18 @@
 use std::collections::HashMap;
 
-use crate::{error::*, expr::*, interpreter::*, stmt::*, token::Token};
+use crate::{
+    debug_log::{self, DebugCategory},
+    error::*,
+    error_codes::{
+        ErrorCode, Message, R0201, R0202, R0203, R0204, R0205, R0206, R0207, R0208, R0209, R0210,
+        R0211, R0212, R0213, R0214, R0215, R0216, R0217,
+    },
+    expr::*,
+    interpreter::*,
+    lox_generator::contains_yield,
+    stmt::*,
+    token::Token,
+};
 
 #[derive(Debug, Clone)]
 pub struct VariableInfo {
@@ -19,6 +31,14 @@ impl VariableInfo {
     }
 }
 
+/// A variable use and the declaration it was resolved to
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub usage: Token,
+    /// `None` for globals, which are not tracked, and for `this` and `super`
+    pub declaration: Option<Token>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FunctionType {
     None,
@@ -38,25 +58,35 @@ pub struct Resolver<'a> {
     pub interpreter: &'a mut Interpreter,
     pub scopes: Vec<HashMap<String, VariableInfo>>,
     pub had_error: bool,
+    /// Every resolved variable use, in source order
+    pub references: Vec<Reference>,
     current_function: FunctionType,
     current_class: ClassType,
-    in_loop: bool,
+    /// Labels of the enclosing loops, innermost last
+    loops: Vec<Option<Token>>,
+    /// The current function contains `yield`
+    in_generator: bool,
 }
 
 impl Resolver<'_> {
-    pub fn new(interpreter: &mut Interpreter) -> Resolver {
+    pub fn new(interpreter: &mut Interpreter) -> Resolver<'_> {
         Resolver {
             interpreter,
             scopes: Vec::new(),
             current_function: FunctionType::None,
             current_class: ClassType::None,
             had_error: false,
-            in_loop: false,
+            references: Vec::new(),
+            loops: Vec::new(),
+            in_generator: false,
         }
     }
 
     fn begin_scope(&mut self) {
         self.scopes.push(HashMap::new());
+        debug_log::log(DebugCategory::Scope, || {
+            format!("push scope {}", self.scopes.len())
+        });
     }
 
     pub fn resolve(&mut self, statements: &[Stmt]) {
@@ -78,25 +108,37 @@ impl Resolver<'_> {
             Some(scope) => scope,
             _ => panic!("Cannot get a scope from an empty list!"),
         };
-        for (_, variable_info) in scope {
-            if !variable_info.is_used {
-                if let Some(token) = variable_info.token {
-                    LoxErrorResult::warning(token, "Variable is declared but never used.");
-                }
-            }
+        debug_log::log(DebugCategory::Scope, || {
+            let mut names: Vec<String> = scope
+                .iter()
+                .map(|(name, info)| match info.is_used {
+                    true => name.clone(),
+                    false => format!("{name} (unused)"),
+                })
+                .collect();
+            names.sort();
+            format!("pop scope {} [{}]", self.scopes.len() + 1, names.join(", "))
+        });
+        // Sorted so warnings come out in source order instead of the map's order
+        let mut unused: Vec<Token> = scope
+            .into_values()
+            .filter(|variable_info| !variable_info.is_used)
+            .filter_map(|variable_info| variable_info.token)
+            .collect();
+        unused.sort_by(|a, b| (a.line, &a.lexeme).cmp(&(b.line, &b.lexeme)));
+        for token in unused {
+            LoxErrorResult::warning(token, R0201.with([]));
         }
     }
 
     fn declare(&mut self, name: &Token) {
+        // Global variables are not tracked by the resolver
         let scope = match self.scopes.last_mut() {
             Some(scope) => scope,
-            _ => panic!("No 'scope' was found in the list of scopes!"),
+            _ => return self.reject_native_module(name, &R0216),
         };
         if scope.contains_key(&name.lexeme) {
-            LoxErrorResult::resolver_error(
-                name.clone(),
-                "Already a variable with this name in this scope.",
-            );
+            LoxErrorResult::resolver_error(name.clone(), R0202.with([]));
             self.had_error = true;
         }
         scope.insert(
@@ -105,10 +147,18 @@ impl Resolver<'_> {
         );
     }
 
+    /// The globals holding the natives registered as a module belong to the host
+    fn reject_native_module(&mut self, name: &Token, code: &'static ErrorCode) {
+        if self.interpreter.is_native_module(&name.lexeme) {
+            LoxErrorResult::resolver_error(name.clone(), code.with([name.lexeme().into()]));
+            self.had_error = true;
+        }
+    }
+
     fn define(&mut self, name: &Token) {
         let scope = match self.scopes.last_mut() {
             Some(scope) => scope,
-            _ => panic!("No 'scope' was found in the list of scopes!"),
+            _ => return,
         };
         if let Some(info) = scope.get_mut(&name.lexeme) {
             info.is_defined = true;
@@ -120,21 +170,48 @@ impl Resolver<'_> {
     /// So, if the variable was found in the current scope, we pass in 0. If it’s in the immediately enclosing scope, 1. You get the idea.
     /// The order of iteration it is really important!
     fn resolve_local(&mut self, expression: &Expr, name: &Token) {
+        let scopes_len = self.scopes.len();
         for (idx, scope) in self.scopes.iter_mut().enumerate().rev() {
             if let Some(info) = scope.get_mut(&name.lexeme) {
                 // Mark variable as used!
                 info.is_used = true;
                 // Resolve the variable
-                let depth = self.scopes.len() - 1 - idx;
+                let depth = scopes_len - 1 - idx;
+                debug_log::log(DebugCategory::Scope, || {
+                    format!(
+                        "resolve '{}' on line {} to scope {} ({depth} up)",
+                        name.lexeme,
+                        name.line,
+                        idx + 1
+                    )
+                });
                 self.interpreter.resolve(expression, depth);
+                self.references.push(Reference {
+                    usage: name.clone(),
+                    declaration: info.token.clone(),
+                });
                 return;
             }
         }
+        debug_log::log(DebugCategory::Scope, || {
+            format!(
+                "resolve '{}' on line {} as a global",
+                name.lexeme, name.line
+            )
+        });
+        self.references.push(Reference {
+            usage: name.clone(),
+            declaration: None,
+        });
     }
 
     fn resolve_function(&mut self, function: &FunctionStmt, function_type: FunctionType) {
         let enclosing_function = self.current_function.clone();
         self.current_function = function_type;
+        // Loops do not reach into a function body
+        let enclosing_loops = std::mem::take(&mut self.loops);
+        let enclosing_generator = self.in_generator;
+        self.in_generator = contains_yield(&function.body);
         self.begin_scope();
         for param in function.params.iter() {
             self.declare(param);
@@ -142,8 +219,46 @@ impl Resolver<'_> {
         }
         self.resolve(&function.body);
         self.end_scope();
+        self.loops = enclosing_loops;
+        self.in_generator = enclosing_generator;
         self.current_function = enclosing_function;
     }
+
+    fn begin_loop(&mut self, label: &Option<Token>) {
+        if let Some(label) = label {
+            let shadows = self
+                .loops
+                .iter()
+                .flatten()
+                .any(|outer| outer.lexeme == label.lexeme);
+            if shadows {
+                self.error(label, R0213.with([]));
+            }
+        }
+        self.loops.push(label.clone());
+    }
+
+    fn resolve_jump(&mut self, keyword: &Token, label: &Option<Token>) {
+        if self.loops.is_empty() {
+            self.error(keyword, R0211.with([keyword.lexeme().into()]));
+            return;
+        }
+        if let Some(label) = label {
+            let found = self
+                .loops
+                .iter()
+                .flatten()
+                .any(|outer| outer.lexeme == label.lexeme);
+            if !found {
+                self.error(label, R0212.with([label.lexeme().into()]));
+            }
+        }
+    }
+
+    fn error(&mut self, token: &Token, message: Message) {
+        LoxErrorResult::resolver_error(token.clone(), message);
+        self.had_error = true;
+    }
 }
 
 impl StmtVisitor<()> for Resolver<'_> {
@@ -178,20 +293,17 @@ impl StmtVisitor<()> for Resolver<'_> {
 
     fn visit_return_stmt(&mut self, stmt: &ReturnStmt) {
         if self.current_function == FunctionType::None {
-            LoxErrorResult::resolver_error(
-                stmt.keyword.clone(),
-                "Cannot return from top-level code.",
-            );
+            LoxErrorResult::resolver_error(stmt.keyword.clone(), R0204.with([]));
             self.had_error = true;
         }
         if let Some(value) = &stmt.value {
             if self.current_function == FunctionType::Initializer {
-                LoxErrorResult::resolver_error(
-                    stmt.keyword.clone(),
-                    "Cannot return a value from an initializer.",
-                );
+                LoxErrorResult::resolver_error(stmt.keyword.clone(), R0205.with([]));
                 self.had_error = true;
             }
+            if self.in_generator {
+                self.error(&stmt.keyword, R0206.with([]));
+            }
             self.resolve_expr(value);
         }
     }
@@ -205,21 +317,59 @@ impl StmtVisitor<()> for Resolver<'_> {
     }
 
     fn visit_while_stmt(&mut self, stmt: &WhileStmt) {
-        let nesting_loop = self.in_loop;
-        self.in_loop = true;
+        self.begin_loop(&stmt.label);
         self.resolve_expr(&stmt.condition);
         self.resolve_stmt(&stmt.body);
-        self.in_loop = nesting_loop;
+        self.loops.pop();
     }
 
-    fn visit_break_stmt(&mut self, stmt: &BreakStmt) {
-        if !self.in_loop {
-            LoxErrorResult::resolver_error(
-                stmt.keyword.clone(),
-                "'break' can only be used inside loops.",
-            );
-            self.had_error = true;
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) {
+        self.begin_scope();
+        if let Some(initializer) = &stmt.initializer {
+            self.resolve_stmt(initializer);
+        }
+        self.begin_loop(&stmt.label);
+        if let Some(condition) = &stmt.condition {
+            self.resolve_expr(condition);
+        }
+        if let Some(increment) = &stmt.increment {
+            self.resolve_expr(increment);
         }
+        self.resolve_stmt(&stmt.body);
+        self.loops.pop();
+        self.end_scope();
+    }
+
+    fn visit_debugger_stmt(&mut self, _stmt: &DebuggerStmt) {}
+
+    fn visit_for_in_stmt(&mut self, stmt: &ForInStmt) {
+        self.resolve_expr(&stmt.iterable);
+        self.begin_scope();
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+        self.begin_loop(&stmt.label);
+        self.resolve_stmt(&stmt.body);
+        self.loops.pop();
+        self.end_scope();
+    }
+
+    fn visit_yield_stmt(&mut self, stmt: &YieldStmt) {
+        match self.current_function {
+            FunctionType::None => self.error(&stmt.keyword, R0214.with([])),
+            FunctionType::Initializer => self.error(&stmt.keyword, R0215.with([])),
+            _ => {}
+        }
+        if let Some(value) = &stmt.value {
+            self.resolve_expr(value);
+        }
+    }
+
+    fn visit_break_stmt(&mut self, stmt: &BreakStmt) {
+        self.resolve_jump(&stmt.keyword, &stmt.label);
+    }
+
+    fn visit_continue_stmt(&mut self, stmt: &ContinueStmt) {
+        self.resolve_jump(&stmt.keyword, &stmt.label);
     }
 
     fn visit_class_stmt(&mut self, stmt: &ClassStmt) {
@@ -236,10 +386,7 @@ impl StmtVisitor<()> for Resolver<'_> {
             };
 
             if stmt.name.lexeme.eq(variable_expr.name.lexeme.as_str()) {
-                LoxErrorResult::resolver_error(
-                    stmt.name.clone(),
-                    "A class cannot inherit from itself.",
-                );
+                LoxErrorResult::resolver_error(variable_expr.name.clone(), R0210.with([]));
                 self.had_error = true;
             }
 
@@ -291,6 +438,13 @@ impl ExprVisitor<()> for Resolver<'_> {
     fn visit_assign_expr(&mut self, expr: &AssignExpr) {
         self.resolve_expr(&expr.value);
         self.resolve_local(&Expr::Assign(expr.clone()), &expr.name);
+        if !self
+            .scopes
+            .iter()
+            .any(|scope| scope.contains_key(&expr.name.lexeme))
+        {
+            self.reject_native_module(&expr.name, &R0217);
+        }
     }
 
     fn visit_binary_expr(&mut self, expr: &BinaryExpr) {
@@ -328,17 +482,13 @@ impl ExprVisitor<()> for Resolver<'_> {
     }
 
     fn visit_variable_expr(&mut self, expr: &VariableExpr) {
-        let scope = match self.scopes.last() {
-            Some(scope) => scope,
-            _ => panic!("No 'scope' was found in the list of scopes!"),
-        };
-
-        if let Some(variable_info) = scope.get(&expr.name.lexeme) {
+        if let Some(variable_info) = self
+            .scopes
+            .last()
+            .and_then(|scope| scope.get(&expr.name.lexeme))
+        {
             if !variable_info.is_defined {
-                LoxErrorResult::resolver_error(
-                    expr.name.clone(),
-                    "Cannot read local variable in its own initializer.",
-                );
+                LoxErrorResult::resolver_error(expr.name.clone(), R0203.with([]));
                 self.had_error = true;
             }
         }
@@ -357,10 +507,7 @@ impl ExprVisitor<()> for Resolver<'_> {
 
     fn visit_this_expr(&mut self, expr: &ThisExpr) {
         if self.current_class == ClassType::None {
-            LoxErrorResult::resolver_error(
-                expr.keyword.clone(),
-                "Cannot use 'this' outside of a class",
-            );
+            LoxErrorResult::resolver_error(expr.keyword.clone(), R0207.with([]));
             self.had_error = true;
             return;
         }
@@ -370,17 +517,11 @@ impl ExprVisitor<()> for Resolver<'_> {
     fn visit_super_expr(&mut self, expr: &SuperExpr) {
         match self.current_class {
             ClassType::None => {
-                LoxErrorResult::resolver_error(
-                    expr.keyword.clone(),
-                    "Cannot use 'super' outside of a class.",
-                );
+                LoxErrorResult::resolver_error(expr.keyword.clone(), R0208.with([]));
                 self.had_error = true;
             }
             ClassType::Class => {
-                LoxErrorResult::resolver_error(
-                    expr.keyword.clone(),
-                    "Cannot use 'super' in a class with no supper class.",
-                );
+                LoxErrorResult::resolver_error(expr.keyword.clone(), R0209.with([]));
                 self.had_error = true;
             }
             _ => {}
@@ -1,44 +1,157 @@
-use crate::{error::LoxErrorResult, object::Object, token::*};
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+
+#[cfg(feature = "bigint")]
+use crate::shared::Rc;
+use crate::{
+    error::LoxErrorResult,
+    error_codes::{
+        term, Argument, Message, L0001, L0002, L0003, L0004, L0005, L0006, L0007, L0008,
+    },
+    object::Object,
+    source_map::SourceMap,
+    token::*,
+};
+
+/// Every reserved word of the language with its token
+pub static KEYWORDS: [(&str, TokenType); 21] = [
+    ("and", TokenType::And),
+    ("class", TokenType::Class),
+    ("else", TokenType::Else),
+    ("false", TokenType::False),
+    ("for", TokenType::For),
+    ("fun", TokenType::Fun),
+    ("if", TokenType::If),
+    ("nil", TokenType::Nil),
+    ("or", TokenType::Or),
+    ("print", TokenType::Print),
+    ("return", TokenType::Return),
+    ("super", TokenType::Super),
+    ("this", TokenType::This),
+    ("true", TokenType::True),
+    ("var", TokenType::Var),
+    ("while", TokenType::While),
+    ("break", TokenType::Break),
+    ("continue", TokenType::Continue),
+    ("in", TokenType::In),
+    ("yield", TokenType::Yield),
+    ("debugger", TokenType::Debugger),
+];
+
+/// Keyword spelled by the characters, compared in place so that only identifiers
+/// allocate their name
+fn keyword(word: &[char]) -> Option<TokenType> {
+    // Keywords are ASCII, their length in bytes is their length in characters
+    KEYWORDS
+        .iter()
+        .find(|(keyword, _)| {
+            keyword.len() == word.len() && keyword.chars().eq(word.iter().copied())
+        })
+        .map(|(_, token_type)| token_type.clone())
+}
+
+/// Letters of any script and `_` start an identifier (`π`, `preço`). The standard library
+/// has no XID tables, the Alphabetic property is the closest to XID_Start.
+pub fn is_identifier_start(char: char) -> bool {
+    char.is_alphabetic() || char == '_'
+}
+
+/// Digits of any script, combining marks (a decomposed `é`) and the zero width
+/// (non-)joiners can follow the first character of an identifier
+pub fn is_identifier_part(char: char) -> bool {
+    char.is_alphanumeric()
+        || char == '_'
+        || matches!(
+            char,
+            '\u{0300}'..='\u{036F}'
+                | '\u{1AB0}'..='\u{1AFF}'
+                | '\u{1DC0}'..='\u{1DFF}'
+                | '\u{20D0}'..='\u{20FF}'
+                | '\u{FE20}'..='\u{FE2F}'
+                | '\u{200C}'
+                | '\u{200D}'
+        )
+}
+
+/// A comment skipped by the scanner, block comments can span several lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// Line where the comment starts
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+}
 
 pub struct Scanner {
-    source: Vec<char>,
+    source_map: SourceMap,
     tokens: Vec<Token>,
+    comments: Vec<Comment>,
+    /// Lexical errors, the scan goes on after each one so all of them are reported at once
+    errors: Vec<LoxErrorResult>,
     start: usize,
     current: usize,
-    line: usize,
-    column: usize,
 }
 
 impl Scanner {
+    /// A byte order mark is dropped and Windows line breaks (`\r\n`) become `\n`,
+    /// so they don't end up in comments and strings
     pub fn new(source: String) -> Scanner {
+        Scanner::from_source_map(SourceMap::new(&source))
+    }
+
+    pub fn from_source_map(source_map: SourceMap) -> Scanner {
         Scanner {
-            source: source.chars().collect(),
+            source_map,
             tokens: Vec::new(),
+            comments: Vec::new(),
+            errors: Vec::new(),
             start: 0,
             current: 0,
-            line: 1,
-            column: 0,
         }
     }
 
+    /// Columns a tab moves to the next multiple of, 1 counts it as a single character
+    /// like the language server and the highlighter expect
+    pub fn with_tab_width(mut self, tab_width: usize) -> Scanner {
+        self.source_map = self.source_map.with_tab_width(tab_width);
+        self
+    }
+
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
     pub fn scan_tokens(&mut self) -> Vec<Token> {
+        self.skip_shebang();
         while !self.is_at_end() {
             self.start = self.current;
-            match self.scan_token() {
-                Ok(_) => {}
-                Err(e) => e.report(),
+            if let Err(err) = self.scan_token() {
+                self.errors.push(err);
             }
         }
+        for error in &self.errors {
+            error.report();
+        }
 
-        self.tokens.push(Token::new(
-            TokenType::Eof,
-            "".to_owned(),
-            Object::Nil,
-            self.line,
-        ));
+        self.tokens.push(
+            Token::new(TokenType::Eof, "".to_owned(), Object::Nil, self.line())
+                .at_column(self.source_map.column(self.current)),
+        );
         self.tokens.clone()
     }
 
+    /// A first line such as `#!/usr/bin/env rlox` lets Unix run the script as an executable,
+    /// it is kept as a comment
+    fn skip_shebang(&mut self) {
+        if !self.source().starts_with(&['#', '!']) {
+            return;
+        }
+        while self.peek() != '\n' && !self.is_at_end() {
+            self.advance();
+        }
+        self.add_comment();
+    }
+
     fn scan_token(&mut self) -> Result<(), LoxErrorResult> {
         let _char = self.advance();
 
@@ -49,7 +162,13 @@ impl Scanner {
             '}' => self.add_token(TokenType::RightBrace),
             ',' => self.add_token(TokenType::Comma),
             '.' => self.add_token(TokenType::Dot),
-            '-' => self.add_token(TokenType::Minus),
+            '-' => {
+                if self.match_next_with('>') {
+                    self.add_token(TokenType::Arrow);
+                } else {
+                    self.add_token(TokenType::Minus);
+                }
+            }
             '+' => self.add_token(TokenType::Plus),
             ':' => self.add_token(TokenType::Colon),
             ';' => self.add_token(TokenType::Semicolon),
@@ -89,31 +208,30 @@ impl Scanner {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    self.add_comment();
                 } else if self.match_next_with('*') {
                     // Block comment start
-                    self.scan_block_comment()?
+                    let result = self.scan_block_comment();
+                    self.add_comment();
+                    result?
                 } else {
                     self.add_token(TokenType::Slash);
                 }
             }
-            ' ' | '\r' | '\t' => {}
-            '\n' => {
-                self.line += 1;
-                self.column = 0;
-            }
+            ' ' | '\r' | '\t' | '\n' => {}
             '"' => match self.add_string() {
                 Ok(_) => {}
                 Err(e) => return Err(e),
             },
             _ => {
                 if _char.is_ascii_digit() {
-                    self.add_number();
-                } else if _char.is_ascii_alphabetic() || _char == '_' {
+                    self.add_number(_char)?;
+                } else if is_identifier_start(_char) {
                     self.add_identifier();
                 } else {
                     return Err(LoxErrorResult::lexical_error(
-                        self.line,
-                        &format!("Unexpected character. -> '{_char}'"),
+                        self.line(),
+                        L0003.with([_char.to_string().into()]),
                     ));
                 }
             }
@@ -121,119 +239,288 @@ impl Scanner {
         Ok(())
     }
 
+    fn source(&self) -> &[char] {
+        self.source_map.chars()
+    }
+
+    /// Line of the current character, tokens are located on the line where they end
+    fn line(&self) -> usize {
+        self.source_map.line(self.current)
+    }
+
+    fn start_column(&self) -> usize {
+        self.source_map.column(self.start)
+    }
+
     fn is_at_end(&self) -> bool {
-        self.current >= self.source.len()
+        self.current >= self.source().len()
     }
 
     fn match_next_with(&mut self, expected: char) -> bool {
-        if self.is_at_end() || self.source[self.current] != expected {
+        if self.is_at_end() || self.source()[self.current] != expected {
             return false;
         }
         self.current += 1;
-        self.column += 1;
         true
     }
 
+    /// Block comments can be nested, only the `*/` matching the first `/*` ends the comment.
+    /// Nesting is counted instead of recursing so deeply nested comments can't overflow the stack.
     fn scan_block_comment(&mut self) -> Result<(), LoxErrorResult> {
+        let mut depth = 1;
         while !self.is_at_end() {
-            if self.match_next_with('*') && self.match_next_with('/') {
-                // End of current block comment */
-                return Ok(());
-            } else if self.match_next_with('/') && self.match_next_with('*') {
-                // Found nested block comment */,
-                self.scan_block_comment()?;
-            } else if self.advance() == '\n' {
-                // Advances with the next char
-                self.line += 1;
-                self.column = 0;
+            if self.peek() == '*' && self.peek_next() == '/' {
+                self.advance();
+                self.advance();
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(());
+                }
+            } else if self.peek() == '/' && self.peek_next() == '*' {
+                self.advance();
+                self.advance();
+                depth += 1;
+            } else {
+                self.advance();
             }
         }
         // Unclosed block comment error
-        Err(LoxErrorResult::lexical_error(
-            self.line,
-            "Unterminated block comment.",
-        ))
+        Err(LoxErrorResult::lexical_error(self.line(), L0002.with([])))
     }
 
     fn peek(&self) -> char {
         match self.is_at_end() {
             true => '\0',
-            false => self.source[self.current],
+            false => self.source()[self.current],
         }
     }
 
     fn peek_next(&self) -> char {
-        match self.current + 1 >= self.source.len() {
+        match self.current + 1 >= self.source().len() {
             true => '\0',
-            false => self.source[self.current + 1],
+            false => self.source()[self.current + 1],
         }
     }
 
+    /// Consumes the current character, at the end of the source it returns '\0' and stays there
     fn advance(&mut self) -> char {
-        let _char = if self.current >= self.source.len() {
-            self.source[self.current - 1]
-        } else {
-            self.source[self.current]
-        };
-        self.current += 1;
-        self.column += 1;
+        let _char = self.peek();
+        if !self.is_at_end() {
+            self.current += 1;
+        }
         _char
     }
 
     fn add_token_literal(&mut self, token_type: TokenType, literal: Object) {
-        let lexeme = self.source[self.start..self.current].iter().collect();
-        self.tokens
-            .push(Token::new(token_type, lexeme, literal, self.line));
+        let lexeme = self.source()[self.start..self.current].iter().collect();
+        self.tokens.push(
+            Token::new(token_type, lexeme, literal, self.line()).at_column(self.start_column()),
+        );
     }
 
     fn add_token(&mut self, token_type: TokenType) {
-        let lexeme = self.source[self.start..self.current].iter().collect();
-        self.tokens
-            .push(Token::new(token_type, lexeme, Object::Nil, self.line));
+        let lexeme = self.source()[self.start..self.current].iter().collect();
+        self.tokens.push(
+            Token::new(token_type, lexeme, Object::Nil, self.line()).at_column(self.start_column()),
+        );
+    }
+
+    fn add_comment(&mut self) {
+        let (line, column) = self.source_map.location(self.start);
+        self.comments.push(Comment {
+            line,
+            column,
+            text: self.source()[self.start..self.current].iter().collect(),
+        });
+    }
+
+    /// Errors found by `scan_tokens`, a source with any should not be parsed
+    pub fn errors(&self) -> &[LoxErrorResult] {
+        &self.errors
+    }
+
+    /// Comments found by `scan_tokens`, in source order
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
     }
 
     fn add_string(&mut self) -> Result<(), LoxErrorResult> {
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
-                self.column = 0;
-            }
             self.advance();
         }
 
         if self.is_at_end() {
-            return Err(LoxErrorResult::lexical_error(
-                self.line,
-                "Unterminated string.",
-            ));
+            return Err(LoxErrorResult::lexical_error(self.line(), L0001.with([])));
         }
         // The closing quote "
         self.advance();
         // Trim the surrounding quotes.
         // TODO: Handle escape sequence
-        let value: String = self.source[self.start + 1..self.current - 1]
+        let value: String = self.source()[self.start + 1..self.current - 1]
             .iter()
             .collect();
-        self.add_token_literal(TokenType::String, Object::String(value));
+        self.add_token_literal(TokenType::String, Object::String(value.into()));
         Ok(())
     }
 
-    fn add_number(&mut self) {
-        while self.peek().is_ascii_digit() {
-            self.advance();
-        }
+    /// Decimal numbers with an optional fraction and exponent (`1.5e3`),
+    /// or integers in hexadecimal (`0xFF`) and binary (`0b1010`)
+    fn add_number(&mut self, first: char) -> Result<(), LoxErrorResult> {
+        let value = match (first, self.peek()) {
+            ('0', 'x' | 'X') => {
+                self.advance();
+                self.scan_radix_digits(16, "hexadecimal")?
+            }
+            ('0', 'b' | 'B') => {
+                self.advance();
+                self.scan_radix_digits(2, "binary")?
+            }
+            _ => self.scan_decimal()?,
+        };
+        self.add_token_literal(TokenType::Number, value);
+        Ok(())
+    }
+
+    /// An `Int` without a fraction or an exponent, unless it is too large for one
+    fn scan_decimal(&mut self) -> Result<Object, LoxErrorResult> {
+        self.skip_decimal_digits();
+        let mut integral = true;
         // Look for a fractional part.
         if self.peek() == '.' && self.peek_next().is_ascii_digit() {
+            integral = false;
             // Consume the "."
             self.advance();
-            while self.peek().is_ascii_digit() {
+            self.skip_decimal_digits();
+        }
+        if matches!(self.peek(), 'e' | 'E') {
+            integral = false;
+            self.advance();
+            if matches!(self.peek(), '+' | '-') {
                 self.advance();
             }
+            if !self.peek().is_ascii_digit() {
+                return Err(self.number_error(|lexeme| L0004.with([lexeme])));
+            }
+            self.skip_decimal_digits();
+        }
+        self.check_separators(10)?;
+        let digits: String = self.source()[self.start..self.current]
+            .iter()
+            .filter(|char| **char != '_')
+            .collect();
+        if integral && self.scan_big_int_suffix()? {
+            return Ok(self.big_int(&digits, 10, 0.0));
+        }
+        Ok(match digits.parse::<i64>() {
+            Ok(int) => Object::Int(int),
+            Err(_) if integral => self.big_int(&digits, 10, digits.parse::<f64>().unwrap()),
+            Err(_) => Object::Number(digits.parse::<f64>().unwrap()),
+        })
+    }
+
+    /// Whether the integer ends with the `n` of a `BigInt` literal (`2n`), which needs
+    /// the `bigint` feature
+    fn scan_big_int_suffix(&mut self) -> Result<bool, LoxErrorResult> {
+        if !self.at_big_int_suffix() {
+            return Ok(false);
+        }
+        self.advance();
+        match cfg!(feature = "bigint") {
+            true => Ok(true),
+            false => Err(self.number_error(|lexeme| L0008.with([lexeme]))),
+        }
+    }
+
+    fn at_big_int_suffix(&self) -> bool {
+        self.peek() == 'n' && !is_identifier_part(self.peek_next())
+    }
+
+    /// A `BigInt` of the digits with the `bigint` feature, `float` without it
+    #[cfg(feature = "bigint")]
+    fn big_int(&self, digits: &str, radix: u32, _float: f64) -> Object {
+        Object::BigInt(Rc::new(
+            BigInt::parse_bytes(digits.as_bytes(), radix).unwrap(),
+        ))
+    }
+
+    #[cfg(not(feature = "bigint"))]
+    fn big_int(&self, _digits: &str, _radix: u32, float: f64) -> Object {
+        Object::Number(float)
+    }
+
+    /// Digits and the `_` separators between them (`1_000_000`)
+    fn skip_decimal_digits(&mut self) {
+        while self.peek().is_ascii_digit() || self.peek() == '_' {
+            self.advance();
+        }
+    }
+
+    /// A `_` separator has to sit between two digits, it can't start or end the number,
+    /// be doubled or touch the `.`, the exponent or a `0x`/`0b` prefix
+    fn check_separators(&self, radix: u32) -> Result<(), LoxErrorResult> {
+        let lexeme = &self.source()[self.start..self.current];
+        let is_digit = |index: Option<usize>| {
+            index
+                .and_then(|index| lexeme.get(index))
+                .is_some_and(|char| char.is_digit(radix))
+        };
+        for (index, char) in lexeme.iter().enumerate() {
+            if *char == '_' && !(is_digit(index.checked_sub(1)) && is_digit(Some(index + 1))) {
+                return Err(self.number_error(|lexeme| L0007.with([lexeme])));
+            }
         }
-        let value: f64 = String::from_iter(&self.source[self.start..self.current])
-            .parse::<f64>()
-            .unwrap();
-        self.add_token_literal(TokenType::Number, Object::Number(value));
+        Ok(())
+    }
+
+    /// Letters and digits right after the prefix belong to the literal, so `0x1G`
+    /// is reported as malformed instead of scanned as a number and an identifier
+    fn scan_radix_digits(
+        &mut self,
+        radix: u32,
+        name: &'static str,
+    ) -> Result<Object, LoxErrorResult> {
+        let digits_start = self.current;
+        // A last `n` is the suffix of a `BigInt` literal
+        while self.is_alphanumeric() && !self.at_big_int_suffix() {
+            self.advance();
+        }
+        if self.current == digits_start {
+            return Err(self.number_error(|lexeme| L0005.with([term(name), lexeme])));
+        }
+        self.check_separators(radix)?;
+        let digits: String = self.source()[digits_start..self.current]
+            .iter()
+            .filter(|char| **char != '_')
+            .collect();
+        let suffixed = self.scan_big_int_suffix()?;
+        // Exact as long as it fits an `i64`, a float past that
+        let mut int = Some(0i64);
+        let mut value = 0.0;
+        for digit in digits.chars() {
+            match digit.to_digit(radix) {
+                Some(digit) => {
+                    int = int
+                        .and_then(|int| int.checked_mul(radix as i64))
+                        .and_then(|int| int.checked_add(digit as i64));
+                    value = value * radix as f64 + digit as f64;
+                }
+                None => {
+                    return Err(self.number_error(|lexeme| {
+                        L0006.with([digit.to_string().into(), term(name), lexeme])
+                    }))
+                }
+            }
+        }
+        Ok(match int {
+            Some(int) if !suffixed => Object::Int(int),
+            _ => self.big_int(&digits, radix, value),
+        })
+    }
+
+    /// Error about the number being scanned, `message` gets its lexeme
+    fn number_error(&self, message: impl FnOnce(Argument) -> Message) -> LoxErrorResult {
+        let lexeme = String::from_iter(&self.source()[self.start..self.current]);
+        LoxErrorResult::lexical_error(self.line(), message(lexeme.into()))
     }
 
     fn add_identifier(&mut self) {
@@ -241,43 +528,249 @@ impl Scanner {
             self.advance();
         }
 
-        let value: String = self.source[self.start..self.current].iter().collect();
-        let token_type = self.get_keyword(&value);
-
-        match token_type {
-            Some(t_type) => match t_type {
-                TokenType::True => self.add_token_literal(t_type, Object::Bool(true)),
-                TokenType::False => self.add_token_literal(t_type, Object::Bool(false)),
-                _ => self.add_token(t_type),
-            },
-            None => self.add_token_literal(TokenType::Identifier, Object::String(value)),
+        match keyword(&self.source()[self.start..self.current]) {
+            Some(TokenType::True) => self.add_token_literal(TokenType::True, Object::Bool(true)),
+            Some(TokenType::False) => self.add_token_literal(TokenType::False, Object::Bool(false)),
+            Some(token_type) => self.add_token(token_type),
+            None => {
+                let value: String = self.source()[self.start..self.current].iter().collect();
+                self.add_token_literal(TokenType::Identifier, Object::String(value.into()))
+            }
         }
     }
 
     fn is_alphanumeric(&self) -> bool {
-        self.peek().is_ascii_alphabetic() || self.peek().is_ascii_digit() || self.peek() == '_'
-    }
-
-    fn get_keyword(&self, word: &str) -> Option<TokenType> {
-        match word {
-            "and" => Some(TokenType::And),
-            "class" => Some(TokenType::Class),
-            "else" => Some(TokenType::Else),
-            "false" => Some(TokenType::False),
-            "for" => Some(TokenType::For),
-            "fun" => Some(TokenType::Fun),
-            "if" => Some(TokenType::If),
-            "nil" => Some(TokenType::Nil),
-            "or" => Some(TokenType::Or),
-            "print" => Some(TokenType::Print),
-            "return" => Some(TokenType::Return),
-            "super" => Some(TokenType::Super),
-            "this" => Some(TokenType::This),
-            "true" => Some(TokenType::True),
-            "var" => Some(TokenType::Var),
-            "while" => Some(TokenType::While),
-            "break" => Some(TokenType::Break),
-            _ => None,
-        }
+        is_identifier_part(self.peek())
+    }
+}
+
+#[cfg(test)]
+mod scanner_tests {
+    use crate::error::capture_diagnostics;
+
+    use super::*;
+
+    #[test]
+    fn test_nested_block_comments() {
+        // Arrange
+        let source = format!("{}{} print 1;", "/*".repeat(10_000), "*/".repeat(10_000));
+        // Act
+        let mut tokens = Vec::new();
+        let diagnostics = capture_diagnostics(|| tokens = Scanner::new(source).scan_tokens());
+        // Assert
+        assert!(diagnostics.is_empty());
+        assert!(tokens[0].is(TokenType::Print));
+    }
+
+    #[test]
+    fn test_shebang_line() {
+        // Arrange
+        let mut scanner = Scanner::new("#!/usr/bin/env rlox\nprint 1;".to_string());
+        // Act
+        let tokens = scanner.scan_tokens();
+        let misplaced = capture_diagnostics(|| {
+            Scanner::new("print 1;\n#!/usr/bin/env rlox".to_string()).scan_tokens();
+        });
+        // Assert
+        assert!(tokens[0].is(TokenType::Print));
+        assert_eq!(tokens[0].line, 2);
+        assert_eq!(scanner.comments()[0].text, "#!/usr/bin/env rlox");
+        assert!(misplaced.starts_with("[Line 2] - Error[L0003]: Unexpected character."));
+    }
+
+    #[test]
+    #[cfg(not(feature = "bigint"))]
+    fn test_number_literals() {
+        // Arrange
+        let source = "0xFF 0b1010 1.5e3 2E-2 0x10000000000000000 07";
+        // Act
+        let tokens = Scanner::new(source.to_string()).scan_tokens();
+        // Assert
+        let values: Vec<Object> = tokens[..6]
+            .iter()
+            .map(|token| token.literal.clone())
+            .collect();
+        assert!(matches!(
+            values[..],
+            [
+                Object::Int(255),
+                Object::Int(10),
+                Object::Number(1500.0),
+                Object::Number(0.02),
+                Object::Number(18446744073709551616.0),
+                Object::Int(7)
+            ]
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "bigint")]
+    fn test_big_int_literals() {
+        // Arrange
+        let source = "0x10000000000000000 12n 0xFFn 1_000n 1e30 12 n";
+        // Act
+        let tokens = Scanner::new(source.to_string()).scan_tokens();
+        // Assert
+        let big_ints: Vec<String> = tokens[..4]
+            .iter()
+            .filter_map(|token| match &token.literal {
+                Object::BigInt(int) => Some(int.to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(big_ints, ["18446744073709551616", "12", "255", "1000"]);
+        assert!(matches!(tokens[4].literal, Object::Number(number) if number == 1e30));
+        assert!(matches!(tokens[5].literal, Object::Int(12)));
+        assert_eq!(tokens[6].lexeme, "n");
+    }
+
+    #[test]
+    #[cfg(not(feature = "bigint"))]
+    fn test_big_int_literals_need_the_feature() {
+        // Act
+        let diagnostics = capture_diagnostics(|| {
+            Scanner::new("12n 0xFFn;".to_string()).scan_tokens();
+        });
+        // Assert
+        assert_eq!(
+            diagnostics,
+            "[Line 1] - Error[L0008]: The 'bigint' feature is needed for number '12n'.
+[Line 1] - Error[L0008]: The 'bigint' feature is needed for number '0xFFn'.
+"
+        );
+    }
+
+    #[test]
+    fn test_malformed_number_literals() {
+        // Act
+        let diagnostics = capture_diagnostics(|| {
+            Scanner::new("0x 0x1G 0b102 1e 2e+;".to_string()).scan_tokens();
+        });
+        // Assert
+        assert_eq!(
+            diagnostics,
+            "[Line 1] - Error[L0005]: Missing digits in hexadecimal number '0x'.
+[Line 1] - Error[L0006]: Invalid digit 'G' in hexadecimal number '0x1G'.
+[Line 1] - Error[L0006]: Invalid digit '2' in binary number '0b102'.
+[Line 1] - Error[L0004]: Missing digits in the exponent of number '1e'.
+[Line 1] - Error[L0004]: Missing digits in the exponent of number '2e+'.
+"
+        );
+    }
+
+    #[test]
+    fn test_number_separators() {
+        // Arrange
+        let source = "1_000_000 0xFF_FF 0b1_0 1_0.2_5e1_0";
+        // Act
+        let tokens = Scanner::new(source.to_string()).scan_tokens();
+        let diagnostics = capture_diagnostics(|| {
+            Scanner::new("1_ 1__0 0x_F 1_.5 1_e3".to_string()).scan_tokens();
+        });
+        // Assert
+        let values: Vec<Object> = tokens[..4]
+            .iter()
+            .map(|token| token.literal.clone())
+            .collect();
+        assert_eq!(values, [1e6, 65535.0, 2.0, 10.25e10].map(Object::Number));
+        assert_eq!(
+            diagnostics,
+            "[Line 1] - Error[L0007]: Misplaced '_' separator in number '1_'.
+[Line 1] - Error[L0007]: Misplaced '_' separator in number '1__0'.
+[Line 1] - Error[L0007]: Misplaced '_' separator in number '0x_F'.
+[Line 1] - Error[L0007]: Misplaced '_' separator in number '1_.5'.
+[Line 1] - Error[L0007]: Misplaced '_' separator in number '1_e3'.
+"
+        );
+    }
+
+    #[test]
+    fn test_unicode_identifiers_and_strings() {
+        // Arrange
+        let source = "var π = 3.14; var preço = \"€ 5\"; var cafe\u{301} = 1; 日本 ✓";
+        // Act
+        let mut tokens = Vec::new();
+        let diagnostics =
+            capture_diagnostics(|| tokens = Scanner::new(source.to_string()).scan_tokens());
+        // Assert
+        let lexemes: Vec<&str> = tokens.iter().map(|token| token.lexeme.as_str()).collect();
+        assert_eq!(
+            lexemes,
+            [
+                "var",
+                "π",
+                "=",
+                "3.14",
+                ";",
+                "var",
+                "preço",
+                "=",
+                "\"€ 5\"",
+                ";",
+                "var",
+                "cafe\u{301}",
+                "=",
+                "1",
+                ";",
+                "日本",
+                ""
+            ]
+        );
+        assert_eq!(tokens[8].literal, Object::String("€ 5".into()));
+        assert_eq!(tokens[15].column, 48);
+        assert_eq!(
+            diagnostics,
+            "[Line 1] - Error[L0003]: Unexpected character. -> '✓'\n"
+        );
+    }
+
+    #[test]
+    fn test_all_lexical_errors_are_collected() {
+        // Arrange
+        let mut scanner = Scanner::new("var a = @;\nprint #;\n\"open".to_string());
+        // Act
+        let diagnostics = capture_diagnostics(|| {
+            scanner.scan_tokens();
+        });
+        // Assert
+        assert_eq!(scanner.errors().len(), 3);
+        assert_eq!(
+            diagnostics,
+            "[Line 1] - Error[L0003]: Unexpected character. -> '@'
+[Line 2] - Error[L0003]: Unexpected character. -> '#'
+[Line 3] - Error[L0001]: Unterminated string.
+"
+        );
+    }
+
+    #[test]
+    fn test_bom_crlf_and_tabs() {
+        // Arrange
+        let source = "\u{FEFF}var a = 1; // one\r\nprint \"a\r\nb\";\r\n\tprint\t a;";
+        // Act
+        let mut scanner = Scanner::new(source.to_string()).with_tab_width(4);
+        let tokens = scanner.scan_tokens();
+        let narrow = Scanner::new(source.to_string()).scan_tokens();
+        // Assert
+        let position = |token: &Token| (token.lexeme.clone(), token.line, token.column);
+        assert_eq!(position(&tokens[0]), ("var".to_string(), 1, 0));
+        assert_eq!(scanner.comments()[0].text, "// one");
+        assert_eq!(tokens[6].literal, Object::String("a\nb".into()));
+        assert_eq!(position(&tokens[8]), ("print".to_string(), 4, 4));
+        assert_eq!(position(&tokens[9]), ("a".to_string(), 4, 13));
+        assert_eq!(position(&narrow[9]), ("a".to_string(), 4, 8));
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        // Act
+        let diagnostics = capture_diagnostics(|| {
+            Scanner::new("/* /* */ *".to_string()).scan_tokens();
+        });
+        // Assert
+        assert_eq!(
+            diagnostics,
+            "[Line 1] - Error[L0002]: Unterminated block comment.\n"
+        );
     }
 }
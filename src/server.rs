@@ -0,0 +1,447 @@
+//! The prompt over TCP (`serve --port <n>`), for web playgrounds and editor integrations.
+//! Every connection gets an interpreter of its own and sends code one line at a time, the
+//! globals of a line stay defined for the next ones. Every line is answered with what it
+//! printed, `out <line>`, its diagnostics, `err <line>`, and `done <code>`, where the code is
+//! 0, 65 for compile errors, 70 for runtime errors or the one given to `exit(code)`, which
+//! also closes the connection.
+//!
+//! A connection opening with a WebSocket upgrade request (`GET / HTTP/1.1` with
+//! `Upgrade: websocket`) speaks the same protocol over WebSocket instead: every line of a
+//! text message is run, and the answer to each line comes back as one text message.
+//!
+//! Each line runs within `SESSION_LIMITS`: a runaway line raises a runtime error once it
+//! ran for too long, printed too much, built a value too large or nested its calls too deep.
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    digest::{base64, sha1},
+    error::capture_diagnostics,
+    interpreter::{CapturedOutput, Interpreter, Limits},
+    run,
+    shared::{Rc, RefCell},
+    RunError,
+};
+
+/// Limits of the interpreter of every connection, tighter than the defaults
+pub const SESSION_LIMITS: Limits = Limits {
+    max_string_length: 1 << 20,
+    max_list_length: 1 << 20,
+    max_fields: 1 << 10,
+    max_run_time: Some(Duration::from_secs(5)),
+    max_output_length: 1 << 20,
+    max_call_depth: 1 << 10,
+};
+
+/// Stack of the thread serving a connection, enough for `SESSION_LIMITS.max_call_depth`
+/// nested calls in a debug build. Overflowing it would abort every session, not just one.
+const SESSION_STACK_SIZE: usize = 64 << 20;
+
+/// Appended to the client's key to accept a WebSocket upgrade
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Longest WebSocket message a client may send, in bytes
+const MAX_MESSAGE_LENGTH: usize = 1 << 20;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+/// Accepts connections on the port of the local host until the process ends. Only local
+/// clients can connect, and every line they send runs within `SESSION_LIMITS`.
+pub fn serve(
+    port: u16,
+    new_interpreter: impl Fn() -> Interpreter + Send + Clone + 'static,
+) -> io::Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    eprintln!("Serving the prompt on {}", listener.local_addr()?);
+    serve_listener(listener, new_interpreter)
+}
+
+/// Serves every connection the listener accepts on a thread of its own
+fn serve_listener(
+    listener: TcpListener,
+    new_interpreter: impl Fn() -> Interpreter + Send + Clone + 'static,
+) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("Could not accept a connection: {err}");
+                continue;
+            }
+        };
+        let new_interpreter = new_interpreter.clone();
+        let session = thread::Builder::new()
+            .stack_size(SESSION_STACK_SIZE)
+            .spawn(move || {
+                let mut interpreter = new_interpreter();
+                interpreter.limits = SESSION_LIMITS;
+                let result = stream
+                    .try_clone()
+                    .and_then(|input| serve_connection(BufReader::new(input), stream, interpreter));
+                if let Err(err) = result {
+                    eprintln!("Connection closed: {err}");
+                }
+            });
+        if let Err(err) = session {
+            eprintln!("Could not start a session: {err}");
+        }
+    }
+    Ok(())
+}
+
+/// Serves WebSocket when the connection opens with a `GET` request, plain lines otherwise
+fn serve_connection(
+    mut input: impl BufRead,
+    output: impl Write,
+    interpreter: Interpreter,
+) -> io::Result<()> {
+    match input.fill_buf()?.starts_with(b"GET ") {
+        true => serve_websocket(input, output, interpreter),
+        false => serve_session(input, output, interpreter),
+    }
+}
+
+/// Runs the lines read from `input` and writes their answers to `output`, until the input
+/// ends or a line calls `exit()`
+pub fn serve_session(
+    input: impl BufRead,
+    mut output: impl Write,
+    interpreter: Interpreter,
+) -> io::Result<()> {
+    let mut session = Session::new(interpreter);
+    for line in input.lines() {
+        output.write_all(session.answer(line?).as_bytes())?;
+        output.flush()?;
+        if session.ended() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Accepts the WebSocket upgrade request read from `input`, then runs the lines of every
+/// text message and sends each answer as a message, until the client closes the connection
+/// or a line calls `exit()`
+pub fn serve_websocket(
+    mut input: impl BufRead,
+    mut output: impl Write,
+    interpreter: Interpreter,
+) -> io::Result<()> {
+    let mut key = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+                key = Some(value.trim().to_string());
+            }
+        }
+    }
+    let Some(key) = key else {
+        output.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+        return Ok(());
+    };
+    let accept = base64(&sha1(format!("{key}{WEBSOCKET_GUID}").as_bytes()));
+    output.write_all(
+        format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n"
+        )
+        .as_bytes(),
+    )?;
+    let mut session = Session::new(interpreter);
+    while let Some(message) = read_message(&mut input, &mut output)? {
+        for line in message.lines() {
+            write_frame(
+                &mut output,
+                OPCODE_TEXT,
+                session.answer(line.to_string()).as_bytes(),
+            )?;
+            if session.ended() {
+                write_frame(&mut output, OPCODE_CLOSE, &[])?;
+                return output.flush();
+            }
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// Interpreter of one connection, with its output captured
+struct Session {
+    interpreter: Interpreter,
+    captured: Rc<RefCell<CapturedOutput>>,
+}
+
+impl Session {
+    fn new(mut interpreter: Interpreter) -> Session {
+        let captured = interpreter.capture_output();
+        Session {
+            interpreter,
+            captured,
+        }
+    }
+
+    /// Runs the line and returns its `out`, `err` and `done` lines
+    fn answer(&mut self, line: String) -> String {
+        let interpreter = &mut self.interpreter;
+        let mut result = Ok(());
+        let diagnostics = capture_diagnostics(|| {
+            result = run(line, interpreter, true);
+        });
+        let printed = std::mem::take(&mut *self.captured.borrow_mut());
+        let code = match (result, self.interpreter.exit_code) {
+            (Err(RunError::Compile), _) => 65,
+            (_, Some(code)) => code,
+            (Err(err), None) => err.exit_code(),
+            (Ok(()), None) => 0,
+        };
+        let mut answer = String::new();
+        for line in printed.stdout.lines() {
+            answer.push_str(&format!("out {line}\n"));
+        }
+        for line in diagnostics.lines().chain(printed.stderr.lines()) {
+            answer.push_str(&format!("err {line}\n"));
+        }
+        answer.push_str(&format!("done {code}\n"));
+        answer
+    }
+
+    /// Set once a line called `exit()`, the connection closes
+    fn ended(&self) -> bool {
+        self.interpreter.exit_code.is_some()
+    }
+}
+
+/// Next text or binary message, its fragments joined. Pings are answered on the way.
+/// `None` once the client closed the connection, after answering its close frame.
+fn read_message(input: &mut impl Read, output: &mut impl Write) -> io::Result<Option<String>> {
+    let mut message = Vec::new();
+    loop {
+        let Some((fin, opcode, payload)) = read_frame(input)? else {
+            return Ok(None);
+        };
+        match opcode {
+            OPCODE_CLOSE => {
+                write_frame(output, OPCODE_CLOSE, &[])?;
+                output.flush()?;
+                return Ok(None);
+            }
+            OPCODE_PING => write_frame(output, OPCODE_PONG, &payload)?,
+            OPCODE_PONG => {}
+            OPCODE_TEXT | OPCODE_BINARY | OPCODE_CONTINUATION => {
+                if message.len() + payload.len() > MAX_MESSAGE_LENGTH {
+                    return Err(invalid_data("message too long"));
+                }
+                message.extend(payload);
+                if fin {
+                    return Ok(Some(String::from_utf8_lossy(&message).into_owned()));
+                }
+            }
+            _ => return Err(invalid_data("unknown opcode")),
+        }
+    }
+}
+
+/// Whether the frame is the last of its message, its opcode and its unmasked payload.
+/// `None` when the input ends before a new frame.
+fn read_frame(input: &mut impl Read) -> io::Result<Option<(bool, u8, Vec<u8>)>> {
+    let mut head = [0u8; 2];
+    match input.read_exact(&mut head) {
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+    let fin = head[0] & 0x80 != 0;
+    let opcode = head[0] & 0x0f;
+    let masked = head[1] & 0x80 != 0;
+    let length = match head[1] & 0x7f {
+        126 => {
+            let mut length = [0u8; 2];
+            input.read_exact(&mut length)?;
+            u16::from_be_bytes(length) as u64
+        }
+        127 => {
+            let mut length = [0u8; 8];
+            input.read_exact(&mut length)?;
+            u64::from_be_bytes(length)
+        }
+        length => length as u64,
+    };
+    if length > MAX_MESSAGE_LENGTH as u64 {
+        return Err(invalid_data("frame too long"));
+    }
+    let mut mask = [0u8; 4];
+    if masked {
+        input.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0u8; length as usize];
+    input.read_exact(&mut payload)?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    Ok(Some((fin, opcode, payload)))
+}
+
+/// Writes a whole unmasked message, as servers send them
+fn write_frame(output: &mut impl Write, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        length @ 0..=125 => frame.push(length as u8),
+        length @ 126..=0xffff => {
+            frame.push(126);
+            frame.extend((length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(127);
+            frame.extend((length as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    output.write_all(&frame)
+}
+
+fn invalid_data(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+#[cfg(test)]
+mod server_tests {
+    use super::*;
+
+    /// Frame as a client sends it, masked
+    fn client_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [1, 2, 3, 4];
+        let mut frame = vec![0x80 | opcode, 0x80 | payload.len() as u8];
+        frame.extend(mask);
+        frame.extend(
+            payload
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| byte ^ mask[i % 4]),
+        );
+        frame
+    }
+
+    #[test]
+    fn test_serve_session() {
+        // Arrange
+        let input =
+            "var name = \"lox\";\nname + \"!\"\nprint -name;\nprint (;\nexit(3);\nprint 1;\n";
+        let mut output = Vec::new();
+        // Act
+        let result = serve_session(input.as_bytes(), &mut output, Interpreter::new());
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "done 0\nout \"lox!\"\ndone 0\nerr [Line 1] - Error[I0301]: Operand must be a number.\ndone 70\nerr [Line 1] - Error[P0101] at ';': Expect expression.\ndone 65\ndone 3\n"
+        );
+    }
+
+    #[test]
+    fn test_serve_session_within_limits() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        interpreter.limits = Limits {
+            max_run_time: Some(Duration::from_millis(50)),
+            max_output_length: 8,
+            ..SESSION_LIMITS
+        };
+        let input = "while (true) {}\nfor (var i = 0; i < 9; i = i + 1) print i;\nprint 1;\n";
+        let mut output = Vec::new();
+        // Act
+        let result = serve_session(input.as_bytes(), &mut output, interpreter);
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "err [Line 1] - Error[I0332]: Run exceeded the time limit of 50 ms.
+done 70
+out 0
+out 1
+out 2
+out 3
+err [Line 1] - Error[I0333]: Output of 10 bytes exceeds the limit of 8 bytes.
+done 70
+out 1
+done 0
+"
+        );
+    }
+
+    #[test]
+    fn test_serve_websocket() {
+        // Arrange
+        let mut input = b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n".to_vec();
+        input.extend(client_frame(OPCODE_PING, b"hi"));
+        input.extend(client_frame(OPCODE_TEXT, b"var a = 2;\nprint a * 21;"));
+        input.extend(client_frame(OPCODE_CLOSE, &[]));
+        let mut output = Vec::new();
+        // Act
+        let result = serve_connection(&input[..], &mut output, Interpreter::new());
+        // Assert
+        assert!(result.is_ok());
+        let head_end = output
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .unwrap()
+            + 4;
+        let head = String::from_utf8_lossy(&output[..head_end]);
+        assert!(head.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+        assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+        let mut frames = &output[head_end..];
+        let mut messages = Vec::new();
+        while let Some((fin, opcode, payload)) = read_frame(&mut frames).unwrap() {
+            assert!(fin);
+            messages.push((opcode, String::from_utf8(payload).unwrap()));
+        }
+        assert_eq!(
+            messages,
+            [
+                (OPCODE_PONG, "hi".to_string()),
+                (OPCODE_TEXT, "done 0\n".to_string()),
+                (OPCODE_TEXT, "out 42\ndone 0\n".to_string()),
+                (OPCODE_CLOSE, String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_serve_survives_unbounded_recursion() {
+        // Arrange
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve_listener(listener, Interpreter::new));
+        let send = |line: &str| {
+            let mut stream = std::net::TcpStream::connect(address).unwrap();
+            stream
+                .write_all(format!("{line}\nexit(0);\n").as_bytes())
+                .unwrap();
+            let mut answer = String::new();
+            stream.read_to_string(&mut answer).unwrap();
+            answer
+        };
+        // Act
+        let recursion = send("fun f(n) { return 1 + f(n); } f(1);");
+        let next = send("print 1;");
+        // Assert
+        assert_eq!(
+            recursion,
+            "err [Line 1] - Error[I0335]: Calls are nested deeper than the limit of 1024 calls.
+done 70
+done 0
+"
+        );
+        assert_eq!(next, "out 1\ndone 0\ndone 0\n");
+    }
+}
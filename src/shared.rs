@@ -0,0 +1,26 @@
+//! Pointers the interpreter shares its values and environments through, `Rc` and `RefCell`
+//! unless the `sync` feature is on. With it they are `Arc` and `AtomicRefCell`: an
+//! `Interpreter` and every value it holds are `Send`, so an embedder can move one to another
+//! thread (e.g. a worker of an async web server), at the cost of atomic reference counts.
+//! Modules import them from here instead of `std`.
+
+#[cfg(feature = "sync")]
+pub use atomic_refcell::AtomicRefCell as RefCell;
+#[cfg(feature = "sync")]
+pub use std::sync::Arc as Rc;
+#[cfg(not(feature = "sync"))]
+pub use std::{cell::RefCell, rc::Rc};
+
+/// `Send + Sync` with the `sync` feature, every type has it without. The trait objects an
+/// interpreter holds (natives, hooks, foreign values) have it as a supertrait.
+#[cfg(feature = "sync")]
+pub trait MaybeSync: Send + Sync {}
+
+#[cfg(feature = "sync")]
+impl<T: Send + Sync + ?Sized> MaybeSync for T {}
+
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSync {}
+
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> MaybeSync for T {}
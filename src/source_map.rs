@@ -0,0 +1,228 @@
+use std::cell::Cell;
+
+thread_local! {
+    /// Tab width of the source maps made by `SourceMap::new` on this thread
+    static TAB_WIDTH: Cell<usize> = const { Cell::new(1) };
+}
+
+/// Tab width of the source maps made on this thread from now on, so the scanner's columns
+/// and the snippets under diagnostics follow it (`--tab-width`, `tab_width` in `lox.toml`).
+/// 1 by default, a tab is a single column as the language server and the highlighter expect.
+pub fn set_tab_width(tab_width: usize) {
+    TAB_WIDTH.set(tab_width.max(1));
+}
+
+/// The text of a script with the offsets where its lines start, so the scanner, the error
+/// reports, the formatter and the language server agree on where things are.
+/// Offsets count characters, lines start at 1 and columns at 0 like in the tokens.
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    source: Vec<char>,
+    /// Offset of the first character of every line
+    line_starts: Vec<usize>,
+    /// Columns a tab moves to the next multiple of, 1 counts it as a single character
+    tab_width: usize,
+    /// Line of the first character, above 1 when the source is a part of a script
+    first_line: usize,
+}
+
+impl SourceMap {
+    /// A byte order mark is dropped and Windows line breaks (`\r\n`) become `\n`,
+    /// so they don't end up in comments and strings
+    pub fn new(source: &str) -> SourceMap {
+        let source = source.strip_prefix('\u{FEFF}').unwrap_or(source);
+        let source: Vec<char> = source.replace("\r\n", "\n").chars().collect();
+        let line_starts = std::iter::once(0)
+            .chain(
+                source
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, char)| **char == '\n')
+                    .map(|(offset, _)| offset + 1),
+            )
+            .collect();
+        SourceMap {
+            source,
+            line_starts,
+            tab_width: TAB_WIDTH.get(),
+            first_line: 1,
+        }
+    }
+
+    /// Source that is a part of a script starting on `first_line`, such as the lines an
+    /// `IncrementalParser` scans again: its tokens get their lines in the script
+    pub fn starting_at_line(mut self, first_line: usize) -> SourceMap {
+        self.first_line = first_line.max(1);
+        self
+    }
+
+    pub fn with_tab_width(mut self, tab_width: usize) -> SourceMap {
+        self.tab_width = tab_width.max(1);
+        self
+    }
+
+    pub fn chars(&self) -> &[char] {
+        &self.source
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Line of the character at `offset`, the end of the source is on the last line
+    pub fn line(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|start| *start <= offset) + self.first_line - 1
+    }
+
+    /// Column of the character at `offset`, a tab moves it to the next tab stop
+    pub fn column(&self, offset: usize) -> usize {
+        let start = self.line_starts[self.line(offset) - self.first_line];
+        let offset = offset.min(self.source.len());
+        if self.tab_width == 1 {
+            return offset - start;
+        }
+        self.source[start..offset]
+            .iter()
+            .fold(0, |column, char| match char {
+                '\t' => (column / self.tab_width + 1) * self.tab_width,
+                _ => column + 1,
+            })
+    }
+
+    /// Line and column of the character at `offset`
+    pub fn location(&self, offset: usize) -> (usize, usize) {
+        (self.line(offset), self.column(offset))
+    }
+
+    /// Text of `line` without its line break, `None` past the last line
+    pub fn line_text(&self, line: usize) -> Option<String> {
+        let index = line.checked_sub(self.first_line)?;
+        let start = *self.line_starts.get(index)?;
+        let end = match self.line_starts.get(index + 1) {
+            Some(next) => next - 1,
+            None => self.source.len(),
+        };
+        Some(self.source[start..end].iter().collect())
+    }
+
+    /// Text of `line` with its tabs turned into spaces up to the next tab stop, so each
+    /// character is shown at its column
+    pub fn display_line(&self, line: usize) -> Option<String> {
+        let mut text = String::new();
+        let mut column = 0;
+        for char in self.line_text(line)?.chars() {
+            match char {
+                '\t' => {
+                    let stop = (column / self.tab_width + 1) * self.tab_width;
+                    text.push_str(&" ".repeat(stop - column));
+                    column = stop;
+                }
+                char => {
+                    text.push(char);
+                    column += 1;
+                }
+            }
+        }
+        Some(text)
+    }
+
+    /// The line with `length` columns from `column` underlined, as shown under a diagnostic.
+    /// Tabs are shown as spaces, the caret lines up whatever the tab width.
+    ///
+    /// ```text
+    ///  2 | print a +;
+    ///    |          ^
+    /// ```
+    pub fn snippet(&self, line: usize, column: usize, length: usize) -> Option<String> {
+        let text = self.display_line(line)?;
+        let gutter = " ".repeat(line.to_string().len());
+        Some(format!(
+            " {line} | {text}\n {gutter} | {}{}",
+            " ".repeat(column),
+            "^".repeat(length.max(1))
+        ))
+    }
+}
+
+#[cfg(test)]
+mod source_map_tests {
+    use super::*;
+    use crate::{error::collect_diagnostics, parser::Parser, scanner::Scanner};
+
+    #[test]
+    fn test_offset_lookups() {
+        // Arrange
+        let source_map = SourceMap::new("\u{FEFF}var a;\r\n\tprint a;\n");
+        let tabbed = source_map.clone().with_tab_width(4);
+        // Act
+        let print = source_map.chars().iter().position(|char| *char == 'p');
+        let end = source_map.chars().len();
+        // Assert
+        assert_eq!(source_map.line_count(), 3);
+        assert_eq!(source_map.location(0), (1, 0));
+        assert_eq!(source_map.location(print.unwrap()), (2, 1));
+        assert_eq!(tabbed.location(print.unwrap()), (2, 4));
+        assert_eq!(source_map.location(end), (3, 0));
+        assert_eq!(source_map.line_text(2).as_deref(), Some("\tprint a;"));
+        assert_eq!(source_map.line_text(3).as_deref(), Some(""));
+        assert_eq!(source_map.line_text(4), None);
+    }
+
+    #[test]
+    fn test_snippet() {
+        // Arrange
+        let source_map = SourceMap::new("var a = 1;\nprint a +;");
+        // Act
+        let snippet = source_map.snippet(2, 9, 1);
+        // Assert
+        assert_eq!(snippet.as_deref(), Some(" 2 | print a +;\n   |          ^"));
+        assert_eq!(source_map.snippet(3, 0, 1), None);
+    }
+
+    #[test]
+    fn test_snippet_after_tabs() {
+        // Arrange
+        let source = "if (a) {\n\tprint\ta +;\n}";
+        let narrow = SourceMap::new(source);
+        let tabbed = SourceMap::new(source).with_tab_width(4);
+        // Act
+        let semicolon = narrow.chars().iter().position(|char| *char == ';').unwrap();
+        // Assert
+        assert_eq!(
+            narrow.snippet(2, narrow.column(semicolon), 1).as_deref(),
+            Some(" 2 |  print a +;\n   |           ^")
+        );
+        assert_eq!(
+            tabbed.snippet(2, tabbed.column(semicolon), 1).as_deref(),
+            Some(" 2 |     print   a +;\n   |                ^")
+        );
+    }
+
+    #[test]
+    fn test_starting_at_line() {
+        // Arrange
+        let source_map = SourceMap::new("fun f() {\n  return 1;\n}").starting_at_line(7);
+        // Act
+        let location = source_map.location(14);
+        // Assert
+        assert_eq!(location, (8, 4));
+        assert_eq!(source_map.line_text(9).as_deref(), Some("}"));
+        assert_eq!(source_map.line_text(6), None);
+    }
+
+    #[test]
+    fn test_render_diagnostic() {
+        // Arrange
+        let source = "var a = 1;\nprint a +;";
+        let source_map = SourceMap::new(source);
+        // Act
+        let diagnostics = collect_diagnostics(|| {
+            Parser::new(Scanner::from_source_map(source_map.clone()).scan_tokens()).parse();
+        });
+        // Assert
+        assert_eq!(
+            diagnostics[0].render(&source_map),
+            "[Line 2] - Error[P0101] at ';': Expect expression.\n 2 | print a +;\n   |          ^"
+        );
+    }
+}
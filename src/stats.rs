@@ -0,0 +1,116 @@
+use std::cell::Cell;
+
+use crate::{hooks::InterpreterHooks, interpreter::Interpreter, stmt::Stmt};
+
+thread_local! {
+    /// Counted wherever they happen, `Stats` only reads how much they grew during the run
+    static ENVIRONMENTS_CREATED: Cell<usize> = const { Cell::new(0) };
+    static OBJECTS_CLONED: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Number of the environment among the ones created on this thread, starting at 1
+pub fn count_environment() -> usize {
+    ENVIRONMENTS_CREATED.with(|count| {
+        count.set(count.get() + 1);
+        count.get()
+    })
+}
+
+pub fn count_clone() {
+    OBJECTS_CLONED.with(|count| count.set(count.get() + 1));
+}
+
+/// Counters of one run (`--stats`), to see where the interpreter spends its work
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pub statements: usize,
+    /// Lox functions and natives
+    pub calls: usize,
+    pub max_depth: usize,
+    depth: usize,
+    environments_at_start: usize,
+    clones_at_start: usize,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stats {
+    pub fn new() -> Stats {
+        Stats {
+            statements: 0,
+            calls: 0,
+            max_depth: 0,
+            depth: 0,
+            environments_at_start: ENVIRONMENTS_CREATED.with(Cell::get),
+            clones_at_start: OBJECTS_CLONED.with(Cell::get),
+        }
+    }
+
+    /// Environments created since the stats were made, scopes of blocks, calls and loops
+    pub fn environments(&self) -> usize {
+        ENVIRONMENTS_CREATED.with(Cell::get) - self.environments_at_start
+    }
+
+    /// Values cloned since the stats were made, e.g. when reading a variable
+    pub fn clones(&self) -> usize {
+        OBJECTS_CLONED.with(Cell::get) - self.clones_at_start
+    }
+
+    pub fn report(&self) -> String {
+        [
+            ("statements executed", self.statements),
+            ("function calls", self.calls),
+            ("environments created", self.environments()),
+            ("objects cloned", self.clones()),
+            ("max call depth", self.max_depth),
+        ]
+        .iter()
+        .map(|(counter, value)| format!("{counter:<24} {value:>10}\n"))
+        .collect()
+    }
+}
+
+impl InterpreterHooks for Stats {
+    fn on_statement(&mut self, _interpreter: &mut Interpreter, _statement: &Stmt) {
+        self.statements += 1;
+    }
+
+    fn on_function_enter(&mut self, _interpreter: &mut Interpreter, _name: &str) {
+        self.calls += 1;
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+    }
+
+    fn on_function_exit(&mut self, _interpreter: &mut Interpreter, _name: &str) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use super::*;
+    use crate::shared::{Rc, RefCell};
+
+    #[test]
+    fn test_count_calls_and_depth() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        let stats = Rc::new(RefCell::new(Stats::new()));
+        interpreter.add_hooks(stats.clone());
+        let source = "fun down(n) {\n  if (n > 0) down(n - 1);\n}\ndown(3);";
+        // Act
+        crate::run(source.to_string(), &mut interpreter, false).ok();
+        // Assert
+        let stats = stats.borrow();
+        assert_eq!(stats.calls, 4);
+        assert_eq!(stats.max_depth, 4);
+        assert_eq!(stats.statements, 9);
+        assert!(stats.environments() >= 4);
+        assert!(stats.clones() > 0);
+    }
+}
@@ -0,0 +1,99 @@
+//! Connections of the `tcpConnect`, `tcpSend`, `tcpRecv` and `tcpClose` natives, with the
+//! `net` feature
+use std::{
+    io::{self, Read, Write},
+    net::{Shutdown, TcpStream},
+};
+
+/// Handed to scripts as a foreign object, see `LoxForeign`
+#[derive(Debug)]
+pub struct TcpConnection {
+    /// `None` once closed
+    stream: Option<TcpStream>,
+    /// Start of a character whose other bytes were not received yet
+    pending: Vec<u8>,
+}
+
+impl TcpConnection {
+    pub fn connect(host: &str, port: u16) -> io::Result<TcpConnection> {
+        Ok(TcpConnection {
+            stream: Some(TcpStream::connect((host, port))?),
+            pending: Vec::new(),
+        })
+    }
+
+    pub fn send(&mut self, data: &[u8]) -> io::Result<()> {
+        self.stream()?.write_all(data)
+    }
+
+    /// Text of the next bytes received, at most `max_len`, waiting for some to arrive.
+    /// A character cut at the end waits for the next call. `None` once the peer closed
+    /// the connection and everything was received.
+    pub fn receive(&mut self, max_len: usize) -> io::Result<Option<String>> {
+        let mut buffer = vec![0; max_len];
+        let read = self.stream()?.read(&mut buffer)?;
+        if read == 0 {
+            let rest = std::mem::take(&mut self.pending);
+            return Ok((!rest.is_empty()).then(|| String::from_utf8_lossy(&rest).into_owned()));
+        }
+        self.pending.extend_from_slice(&buffer[..read]);
+        let complete = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            // Invalid bytes are replaced, only an incomplete character at the end is kept
+            Err(err) if err.error_len().is_some() => self.pending.len(),
+            Err(err) => err.valid_up_to(),
+        };
+        let text: Vec<u8> = self.pending.drain(..complete).collect();
+        Ok(Some(String::from_utf8_lossy(&text).into_owned()))
+    }
+
+    pub fn close(&mut self) {
+        if let Some(stream) = self.stream.take() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    fn stream(&mut self) -> io::Result<&mut TcpStream> {
+        self.stream
+            .as_mut()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "the connection is closed"))
+    }
+}
+
+#[cfg(test)]
+mod tcp_tests {
+    use super::*;
+    use std::{net::TcpListener, thread};
+
+    #[test]
+    fn test_receive_keeps_split_characters() {
+        // Arrange
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 4];
+            stream.read_exact(&mut request).unwrap();
+            // "é" is two bytes, the first read ends between them
+            stream.write_all("aé".as_bytes()).unwrap();
+            request
+        });
+        let mut connection = TcpConnection::connect("127.0.0.1", port).unwrap();
+        // Act
+        connection.send(b"ping").unwrap();
+        let first = connection.receive(2).unwrap();
+        let second = connection.receive(2).unwrap();
+        let end = connection.receive(2).unwrap();
+        connection.close();
+        let closed = connection.send(b"x");
+        // Assert
+        assert_eq!(&server.join().unwrap(), b"ping");
+        assert_eq!(first.as_deref(), Some("a"));
+        assert_eq!(second.as_deref(), Some("é"));
+        assert_eq!(end, None);
+        assert_eq!(
+            closed.map_err(|err| err.kind()),
+            Err(io::ErrorKind::NotConnected)
+        );
+    }
+}
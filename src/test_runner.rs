@@ -0,0 +1,388 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{capture_diagnostics, LoxErrorResult},
+    error_codes::Message,
+    interpreter::Interpreter,
+    RunError,
+};
+
+const EXPECT: &str = "// expect: ";
+const EXPECT_RUNTIME_ERROR: &str = "// expect runtime error: ";
+/// With a message after `: `, the error expected on the line of the comment. Without one,
+/// the script must fail to compile but its diagnostics are not checked.
+const EXPECT_COMPILE_ERROR: &str = "// expect compile error";
+/// Scripts checked through their `test()` calls instead of expectation comments
+const TEST_SUFFIX: &str = "_test.lox";
+
+/// Result of one `test("name", fn)` call
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestOutcome {
+    pub name: String,
+    pub failure: Option<TestFailure>,
+}
+
+/// The runtime error that failed a test
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestFailure {
+    pub line: usize,
+    pub message: String,
+    /// Expected and actual values when `expectEq` failed
+    pub mismatch: Option<(String, String)>,
+}
+
+/// What a test script declares about its own output through comments
+#[derive(Debug, Default, PartialEq)]
+struct Expectations {
+    output: Vec<String>,
+    runtime_errors: Vec<String>,
+    compile_error: bool,
+    compile_errors: Vec<String>,
+}
+
+/// Runs every `.lox` file under `dir` and checks its output against the expectation
+/// comments, or for `*_test.lox` files that every `test()` passed.
+/// Returns the process exit code: 1 when any test failed.
+pub fn run_tests(dir: &str) -> i32 {
+    let mut files = Vec::new();
+    if let Err(err) = collect_scripts(Path::new(dir), &mut files) {
+        eprintln!("Could not read '{dir}': {err}");
+        return 66;
+    }
+    files.sort();
+
+    let mut failed = 0;
+    for file in &files {
+        let uses_tests = file.to_string_lossy().ends_with(TEST_SUFFIX);
+        let failures = match fs::read_to_string(file) {
+            Ok(source) if uses_tests => run_lox_tests(&source),
+            Ok(source) => run_test(&source),
+            Err(err) => vec![format!("Could not read the file: {err}")],
+        };
+        if !failures.is_empty() {
+            failed += 1;
+            println!("FAIL {}", file.display());
+            for failure in failures {
+                println!("     {failure}");
+            }
+        }
+    }
+    println!("Passed {} of {} tests.", files.len() - failed, files.len());
+    match failed {
+        0 => 0,
+        _ => 1,
+    }
+}
+
+fn collect_scripts(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_scripts(&path, files)?;
+        } else if path.extension().is_some_and(|extension| extension == "lox") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn parse_expectations(source: &str) -> Expectations {
+    let mut expectations = Expectations::default();
+    for (idx, line) in source.lines().enumerate() {
+        if let Some((_, expected)) = line.split_once(EXPECT) {
+            expectations.output.push(expected.to_string());
+        } else if let Some((_, message)) = line.split_once(EXPECT_RUNTIME_ERROR) {
+            // Runtime errors are reported with the line they happened on, compared without
+            // their code
+            let message = Message::Plain(message.to_string());
+            let error = LoxErrorResult::interpreter_error(idx + 1, message);
+            expectations.runtime_errors.push(error.to_string());
+        } else if let Some((_, rest)) = line.split_once(EXPECT_COMPILE_ERROR) {
+            expectations.compile_error = true;
+            // Compile errors are compared like runtime errors, without their location either
+            if let Some(message) = rest.strip_prefix(": ") {
+                let error =
+                    LoxErrorResult::interpreter_error(idx + 1, Message::Plain(message.to_string()));
+                expectations.compile_errors.push(error.to_string());
+            }
+        }
+    }
+    expectations
+}
+
+/// Runs one script and describes every difference with its expectations
+fn run_test(source: &str) -> Vec<String> {
+    let expectations = parse_expectations(source);
+    let mut interpreter = Interpreter::new();
+    // Expectations written against one machine must hold on every other
+    interpreter.make_deterministic();
+    let captured = interpreter.capture_output();
+    let mut result = Ok(());
+    let diagnostics = capture_diagnostics(|| {
+        result = crate::run(source.to_string(), &mut interpreter, false);
+    });
+    let compiled = !matches!(result, Err(RunError::Compile));
+    // Warnings are reported whether the script compiled or not, they are not checked
+    let compile_errors: Vec<String> = diagnostics
+        .lines()
+        .filter(|line| line.contains("] - Error"))
+        .map(|line| without_location(&without_code(line)))
+        .collect();
+
+    let mut failures = Vec::new();
+    match (compiled, expectations.compile_error) {
+        (true, true) => {
+            failures.push("Expected a compile error but the script compiled.".to_string());
+            return failures;
+        }
+        (false, false) => {
+            failures.push("Unexpected compile error.".to_string());
+            compare_lines(
+                "compile error",
+                &[],
+                compile_errors.iter().map(String::as_str),
+                &mut failures,
+            );
+            return failures;
+        }
+        (false, true) => {
+            if !expectations.compile_errors.is_empty() {
+                compare_lines(
+                    "compile error",
+                    &expectations.compile_errors,
+                    compile_errors.iter().map(String::as_str),
+                    &mut failures,
+                );
+            }
+            return failures;
+        }
+        (true, false) => {}
+    }
+    let captured = captured.borrow();
+    compare_lines(
+        "output",
+        &expectations.output,
+        captured.stdout.lines(),
+        &mut failures,
+    );
+    let errors: Vec<String> = captured.stderr.lines().map(without_code).collect();
+    compare_lines(
+        "runtime error",
+        &expectations.runtime_errors,
+        errors.iter().map(String::as_str),
+        &mut failures,
+    );
+    failures
+}
+
+/// Runs a script written with `test()` and `expectEq()`, its output is not checked.
+/// Describes every failed test and an error raised outside of any test.
+fn run_lox_tests(source: &str) -> Vec<String> {
+    let mut interpreter = Interpreter::new();
+    interpreter.make_deterministic();
+    let captured = interpreter.capture_output();
+    let result = crate::run(source.to_string(), &mut interpreter, false);
+
+    let mut failures = Vec::new();
+    for outcome in &interpreter.tests {
+        let Some(failure) = &outcome.failure else {
+            continue;
+        };
+        failures.push(format!(
+            "Test '{}' failed on line {}: {}",
+            outcome.name, failure.line, failure.message
+        ));
+        if let Some((expected, actual)) = &failure.mismatch {
+            failures.extend(diff_values(expected, actual));
+        }
+    }
+    match result {
+        Err(RunError::Compile) => failures.push("Unexpected compile error.".to_string()),
+        Err(_) => {
+            let error = captured
+                .borrow()
+                .stderr
+                .lines()
+                .last()
+                .unwrap_or("")
+                .to_string();
+            failures.push(format!("Error outside of a test: {error}"));
+        }
+        Ok(()) => {}
+    }
+    failures
+}
+
+/// Line by line difference of two values, `-` for the expected and `+` for the actual lines
+fn diff_values(expected: &str, actual: &str) -> Vec<String> {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut diff = Vec::new();
+    for idx in 0..expected.len().max(actual.len()) {
+        match (expected.get(idx), actual.get(idx)) {
+            (Some(expected), Some(actual)) if expected == actual => {
+                diff.push(format!("    {expected}"))
+            }
+            (expected, actual) => {
+                if let Some(expected) = expected {
+                    diff.push(format!("  - {expected}"));
+                }
+                if let Some(actual) = actual {
+                    diff.push(format!("  + {actual}"));
+                }
+            }
+        }
+    }
+    diff
+}
+
+/// The diagnostic without the code after `Error`, `Error[I0304]: ...` becomes `Error: ...`
+fn without_code(line: &str) -> String {
+    let Some(start) = line.find("Error[") else {
+        return line.to_string();
+    };
+    let code_start = start + "Error".len();
+    match line[code_start..].find(']') {
+        Some(end) => format!("{}{}", &line[..code_start], &line[code_start + end + 1..]),
+        None => line.to_string(),
+    }
+}
+
+/// The diagnostic without the token it was reported at, `Error at 'x': ...` becomes
+/// `Error: ...`
+fn without_location(line: &str) -> String {
+    let Some(start) = line.find("Error at ") else {
+        return line.to_string();
+    };
+    let location_start = start + "Error".len();
+    let rest = &line[location_start + " at ".len()..];
+    let location_length = match rest.strip_prefix('\'') {
+        Some(lexeme) => lexeme.find("':").map(|end| end + 2),
+        None => rest.find(':'),
+    };
+    match location_length {
+        Some(length) => format!("{}{}", &line[..location_start], &rest[length..]),
+        None => line.to_string(),
+    }
+}
+
+fn compare_lines<'a>(
+    kind: &str,
+    expected: &[String],
+    actual: impl Iterator<Item = &'a str>,
+    failures: &mut Vec<String>,
+) {
+    let actual: Vec<&str> = actual.collect();
+    for idx in 0..expected.len().max(actual.len()) {
+        match (expected.get(idx), actual.get(idx)) {
+            (Some(expected), Some(actual)) if expected != actual => {
+                failures.push(format!("Expected {kind} '{expected}', got '{actual}'."))
+            }
+            (Some(expected), None) => failures.push(format!("Missing {kind} '{expected}'.")),
+            (None, Some(actual)) => failures.push(format!("Unexpected {kind} '{actual}'.")),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_runner_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_expectations() {
+        // Arrange
+        let source = "print 1; // expect: 1\nprint -nil; // expect runtime error: Oops.";
+        // Act
+        let expectations = parse_expectations(source);
+        // Assert
+        assert_eq!(
+            expectations,
+            Expectations {
+                output: vec!["1".to_string()],
+                runtime_errors: vec!["[Line 2] - Error: Oops.".to_string()],
+                compile_error: false,
+                compile_errors: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_run_lox_tests_reports_failed_tests() {
+        // Arrange
+        let source = "fun passes() { expectEq(1, 1); }
+fun fails() { expectEq(\"a\nb\", \"a\nc\"); }
+test(\"passes\", passes);
+test(\"fails\", fails);
+print -nil;";
+        // Act
+        let failures = run_lox_tests(source);
+        // Assert
+        assert_eq!(
+            failures,
+            vec![
+                "Test 'fails' failed on line 4: Expected \"a\nc\", got \"a\nb\".",
+                "    \"a",
+                "  - c\"",
+                "  + b\"",
+                "Error outside of a test: [Line 7] - Error[I0301]: Operand must be a number.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_test_reports_differences() {
+        // Arrange
+        let passing = "print 1 + 1; // expect: 2\nprint -\"a\"; // expect runtime error: Operand must be a number.";
+        let failing = "print 1; // expect: 2\n// expect: 3";
+        // Act
+        let passing_failures = run_test(passing);
+        let failing_failures = run_test(failing);
+        // Assert
+        assert!(passing_failures.is_empty(), "{passing_failures:?}");
+        assert_eq!(
+            failing_failures,
+            vec![
+                "Expected output '2', got '1'.".to_string(),
+                "Missing output '3'.".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_test_compares_compile_errors() {
+        // Arrange
+        let passing = "var a = 1;
+return a; // expect compile error: Cannot return from top-level code.
+print this; // expect compile error: Cannot use 'this' outside of a class";
+        let failing = "print (; // expect compile error: Expect ')' after expression.
+{ // expect compile error: Expect '}' after block.";
+        let unchecked = "// expect compile error\nprint (;";
+        let unexpected = "print 1; // expect: 1\nprint (;";
+        // Act
+        let passing_failures = run_test(passing);
+        let failing_failures = run_test(failing);
+        let unchecked_failures = run_test(unchecked);
+        let unexpected_failures = run_test(unexpected);
+        // Assert
+        assert!(passing_failures.is_empty(), "{passing_failures:?}");
+        assert_eq!(
+            failing_failures,
+            vec![
+                "Expected compile error '[Line 1] - Error: Expect ')' after expression.', got '[Line 1] - Error: Expect expression.'.",
+                "Expected compile error '[Line 2] - Error: Expect '}' after block.', got '[Line 2] - Error: Expect '}' after block opened at line 2.'.",
+            ]
+        );
+        assert!(unchecked_failures.is_empty(), "{unchecked_failures:?}");
+        assert_eq!(
+            unexpected_failures,
+            vec![
+                "Unexpected compile error.",
+                "Unexpected compile error '[Line 2] - Error: Expect expression.'.",
+            ]
+        );
+    }
+}
@@ -8,6 +8,8 @@ pub struct Token {
     pub lexeme: String,
     pub literal: Object,
     pub line: usize,
+    /// Character offset of the token in its line, starting at 0
+    pub column: usize,
 }
 
 impl Token {
@@ -17,9 +19,15 @@ impl Token {
             lexeme,
             literal,
             line,
+            column: 0,
         }
     }
 
+    /// Same token, located at `column` of its line
+    pub fn at_column(self, column: usize) -> Token {
+        Token { column, ..self }
+    }
+
     pub fn is(&self, token_type: TokenType) -> bool {
         self.token_type == token_type
     }
@@ -65,6 +73,8 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    /// `->` before the return type of a function
+    Arrow,
 
     // Literals.
     Identifier,
@@ -89,6 +99,10 @@ pub enum TokenType {
     Var,
     While,
     Break,
+    Continue,
+    In,
+    Yield,
+    Debugger,
     // End of line
     Eof,
 }
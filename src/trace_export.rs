@@ -0,0 +1,139 @@
+use std::time::Instant;
+
+use crate::{hooks::InterpreterHooks, interpreter::Interpreter, utils::json::Json};
+
+/// Phase of a trace event, as Chrome names them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Begin,
+    End,
+}
+
+impl Phase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Begin => "B",
+            Phase::End => "E",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallEvent {
+    pub name: String,
+    pub phase: Phase,
+    /// Microseconds since the exporter was created
+    pub timestamp: f64,
+}
+
+/// Collects the begin and end of every Lox function and native call (`--trace-export`),
+/// written as Chrome's trace-event format for about://tracing or Perfetto
+#[derive(Debug, Clone)]
+pub struct TraceExporter {
+    started: Instant,
+    events: Vec<CallEvent>,
+}
+
+impl Default for TraceExporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TraceExporter {
+    pub fn new() -> TraceExporter {
+        TraceExporter {
+            started: Instant::now(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn events(&self) -> &[CallEvent] {
+        &self.events
+    }
+
+    fn push(&mut self, name: &str, phase: Phase) {
+        self.events.push(CallEvent {
+            name: name.to_string(),
+            phase,
+            timestamp: self.started.elapsed().as_secs_f64() * 1_000_000.0,
+        });
+    }
+
+    pub fn to_json(&self) -> Json {
+        let events = self
+            .events
+            .iter()
+            .map(|event| {
+                // Natives are reported as `<native name>` by the hooks
+                let category = match event.name.starts_with("<native") {
+                    true => "native",
+                    false => "function",
+                };
+                Json::object(vec![
+                    ("name", Json::string(&event.name)),
+                    ("cat", Json::string(category)),
+                    ("ph", Json::string(event.phase.name())),
+                    ("ts", Json::Number(event.timestamp)),
+                    ("pid", Json::Number(1.0)),
+                    ("tid", Json::Number(1.0)),
+                ])
+            })
+            .collect();
+        Json::object(vec![
+            ("traceEvents", Json::Array(events)),
+            ("displayTimeUnit", Json::string("ms")),
+        ])
+    }
+}
+
+impl InterpreterHooks for TraceExporter {
+    fn on_function_enter(&mut self, _interpreter: &mut Interpreter, name: &str) {
+        self.push(name, Phase::Begin);
+    }
+
+    fn on_function_exit(&mut self, _interpreter: &mut Interpreter, name: &str) {
+        self.push(name, Phase::End);
+    }
+}
+
+#[cfg(test)]
+mod trace_export_tests {
+    use super::*;
+    use crate::shared::{Rc, RefCell};
+
+    #[test]
+    fn test_export_nested_calls() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        let exporter = Rc::new(RefCell::new(TraceExporter::new()));
+        interpreter.add_hooks(exporter.clone());
+        let source = "fun outer() { return len(\"ab\") + 1; }\nvar x = outer();";
+        // Act
+        crate::run(source.to_string(), &mut interpreter, false).ok();
+        // Assert
+        let exporter = exporter.borrow();
+        let phases: Vec<(&str, &str)> = exporter
+            .events()
+            .iter()
+            .map(|event| (event.name.as_str(), event.phase.name()))
+            .collect();
+        assert_eq!(
+            phases,
+            [
+                ("outer", "B"),
+                ("<native len>", "B"),
+                ("<native len>", "E"),
+                ("outer", "E")
+            ]
+        );
+        let json = exporter.to_json();
+        let events = json.get("traceEvents").and_then(Json::as_array).unwrap();
+        assert_eq!(events[1].get("cat").and_then(Json::as_str), Some("native"));
+        assert!(events
+            .windows(2)
+            .all(|pair| pair[0].get("ts").and_then(Json::as_f64)
+                <= pair[1].get("ts").and_then(Json::as_f64)));
+    }
+}
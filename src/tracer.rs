@@ -0,0 +1,74 @@
+use crate::{
+    coverage::statement_line, error::LoxErrorResult, hooks::InterpreterHooks,
+    interpreter::Interpreter, stmt::Stmt,
+};
+
+/// Writes every executed line, call and runtime error to stderr (`--trace`),
+/// indented by the depth of the call
+#[derive(Debug, Clone, Default)]
+pub struct Tracer {
+    depth: usize,
+}
+
+impl Tracer {
+    pub fn new() -> Tracer {
+        Tracer::default()
+    }
+
+    fn trace(&self, interpreter: &Interpreter, event: &str) {
+        interpreter.write_diagnostic(&format!("[trace] {}{event}", "  ".repeat(self.depth)));
+    }
+}
+
+impl InterpreterHooks for Tracer {
+    fn on_statement(&mut self, interpreter: &mut Interpreter, statement: &Stmt) {
+        if let Some(line) = statement_line(statement) {
+            self.trace(interpreter, &format!("line {line}"));
+        }
+    }
+
+    fn on_function_enter(&mut self, interpreter: &mut Interpreter, name: &str) {
+        self.trace(interpreter, &format!("call {name}"));
+        self.depth += 1;
+    }
+
+    fn on_function_exit(&mut self, interpreter: &mut Interpreter, name: &str) {
+        self.depth = self.depth.saturating_sub(1);
+        self.trace(interpreter, &format!("return from {name}"));
+    }
+
+    fn on_error(&mut self, interpreter: &mut Interpreter, error: &LoxErrorResult) {
+        self.depth = 0;
+        self.trace(interpreter, &format!("error {error}"));
+    }
+}
+
+#[cfg(test)]
+mod tracer_tests {
+    use super::*;
+    use crate::shared::{Rc, RefCell};
+
+    #[test]
+    fn test_trace_lines_calls_and_errors() {
+        // Arrange
+        let mut interpreter = Interpreter::new();
+        let output = interpreter.capture_output();
+        interpreter.add_hooks(Rc::new(RefCell::new(Tracer::new())));
+        let source = "fun double(n) {\n  return n * 2;\n}\nprint double(1);\nprint -nil;";
+        // Act
+        crate::run(source.to_string(), &mut interpreter, false).ok();
+        // Assert
+        assert_eq!(
+            output.borrow().stderr,
+            "[trace] line 1
+[trace] line 4
+[trace] call double
+[trace]   line 2
+[trace] return from double
+[trace] line 5
+[trace] error [Line 5] - Error[I0301]: Operand must be a number.
+[Line 5] - Error[I0301]: Operand must be a number.
+"
+        );
+    }
+}
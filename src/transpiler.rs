@@ -0,0 +1,1471 @@
+//! Translates Lox programs to JavaScript that can run in Node.js or a browser.
+//! Lox semantics JavaScript doesn't share are kept through small helper functions
+//! emitted before the program, only the helpers the program needs are included:
+//! truthiness (`0` and `""` are true in Lox), `and`/`or` returning operands,
+//! `print` formatting, calling values that may be classes and bound methods, repeating
+//! strings with `*` (`"ab" * 3`).
+//! Runtime type errors (e.g. `1 + nil`) are not checked, JavaScript's coercions apply.
+
+use std::collections::HashMap;
+
+use crate::{
+    expr::*,
+    lox_generator::contains_yield,
+    object::Object,
+    stmt::*,
+    token::{Token, TokenType},
+    utils::json::Json,
+};
+
+const INDENT: &str = "  ";
+
+/// Language `rlox transpile --target=<target>` translates to
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Target {
+    #[default]
+    JavaScript,
+}
+
+impl Target {
+    pub fn from_name(name: &str) -> Option<Target> {
+        match name {
+            "js" | "javascript" => Some(Target::JavaScript),
+            _ => None,
+        }
+    }
+}
+
+/// Helpers in the order they are emitted, with the helpers they rely on
+const HELPERS: &[(&str, &[&str], &str)] = &[
+    (
+        "__truthy",
+        &[],
+        "function __truthy(value) {
+  return value !== null && value !== undefined && value !== false;
+}",
+    ),
+    (
+        "__and",
+        &["__truthy"],
+        "function __and(left, right) {
+  return __truthy(left) ? right() : left;
+}",
+    ),
+    (
+        "__or",
+        &["__truthy"],
+        "function __or(left, right) {
+  return __truthy(left) ? left : right();
+}",
+    ),
+    (
+        "__mul",
+        &[],
+        "function __mul(left, right) {
+  if (typeof left === \"string\") return left.repeat(right);
+  if (typeof right === \"string\") return right.repeat(left);
+  return left * right;
+}",
+    ),
+    (
+        "__isClass",
+        &[],
+        "function __isClass(value) {
+  return typeof value === \"function\" && /^class\\b/.test(Function.prototype.toString.call(value));
+}",
+    ),
+    (
+        "__call",
+        &["__isClass"],
+        "function __call(callee, ...args) {
+  return __isClass(callee) ? new callee(...args) : callee(...args);
+}",
+    ),
+    (
+        "__bindMethods",
+        &[],
+        "function __bindMethods(instance) {
+  for (let proto = Object.getPrototypeOf(instance); proto !== Object.prototype; proto = Object.getPrototypeOf(proto)) {
+    for (const name of Object.getOwnPropertyNames(proto)) {
+      if (name !== \"constructor\" && !Object.hasOwn(instance, name)) {
+        instance[name] = proto[name].bind(instance);
+      }
+    }
+  }
+}",
+    ),
+    (
+        "__str",
+        &["__isClass"],
+        "function __str(value) {
+  const methods = (proto) => Object.getOwnPropertyNames(proto).filter((name) => name !== \"constructor\");
+  if (value === null || value === undefined) {
+    return \"nil\";
+  } else if (typeof value === \"string\") {
+    return `\"${value}\"`;
+  } else if (Array.isArray(value)) {
+    return `[${value.map(__str).join(\", \")}]`;
+  } else if (__isClass(value)) {
+    return `<class ${value.name}> { methods: { ${methods(value.prototype).join(\", \")} } }>`;
+  } else if (typeof value === \"function\") {
+    return `<fun ${value.name.replace(/^bound /, \"\")}>`;
+  } else if (Object.prototype.toString.call(value) === \"[object Generator]\") {
+    // JavaScript generators don't know the name of their function
+    return \"<generator>\";
+  } else if (typeof value === \"object\") {
+    const proto = Object.getPrototypeOf(value);
+    const props = Object.keys(value).filter((name) => !(name in proto));
+    return `<${proto.constructor.name} instance> { props: { ${props.join(\", \")} }, methods: { ${methods(proto).join(\", \")} } }>`;
+  }
+  return String(value);
+}",
+    ),
+    (
+        "__print",
+        &["__str"],
+        "function __print(value) {
+  console.log(__str(value));
+}",
+    ),
+    (
+        "__args",
+        &[],
+        "const __args = typeof process === \"undefined\" ? [] : process.argv.slice(2);",
+    ),
+    (
+        "clock",
+        &[],
+        "function clock() {
+  return Date.now();
+}",
+    ),
+    (
+        "clockMonotonic",
+        &[],
+        "function clockMonotonic() {
+  return performance.now();
+}",
+    ),
+    (
+        "bench",
+        &["__call", "__str"],
+        "function bench(fn, iterations) {
+  if (!Number.isInteger(iterations) || iterations <= 0) throw new Error(\"Iterations of 'bench' must be a positive integer.\");
+  for (let i = 0; i < Math.ceil(iterations / 10); i++) __call(fn);
+  let min = Infinity;
+  let total = 0;
+  for (let i = 0; i < iterations; i++) {
+    const started = performance.now();
+    __call(fn);
+    const elapsed = performance.now() - started;
+    min = Math.min(min, elapsed);
+    total += elapsed;
+  }
+  const mean = total / iterations;
+  console.log(`bench ${__str(fn)}: min ${min.toFixed(3)} ms, mean ${mean.toFixed(3)} ms over ${iterations} iterations`);
+  return [min, mean];
+}",
+    ),
+    (
+        "sleep",
+        &[],
+        "function sleep(ms) {
+  Atomics.wait(new Int32Array(new SharedArrayBuffer(4)), 0, 0, ms);
+  return null;
+}",
+    ),
+    (
+        "timeFormat",
+        &[],
+        "function timeFormat(epochMs, format) {
+  const date = new Date(epochMs);
+  const pad = (value, width = 2) => String(value).padStart(width, \"0\");
+  const fields = {
+    Y: pad(date.getUTCFullYear(), 4),
+    m: pad(date.getUTCMonth() + 1),
+    d: pad(date.getUTCDate()),
+    H: pad(date.getUTCHours()),
+    M: pad(date.getUTCMinutes()),
+    S: pad(date.getUTCSeconds()),
+    f: pad(date.getUTCMilliseconds(), 3),
+    \"%\": \"%\",
+  };
+  return format.replace(/%(.)/g, (match, spec) => fields[spec] ?? match);
+}",
+    ),
+    (
+        "now",
+        &[],
+        "function now() {
+  return Date.now();
+}",
+    ),
+    (
+        "dateParts",
+        &[],
+        "class DateParts {}
+function dateParts(epochMs) {
+  const date = new Date(Math.floor(epochMs));
+  return Object.assign(new DateParts(), {
+    year: date.getUTCFullYear(),
+    month: date.getUTCMonth() + 1,
+    day: date.getUTCDate(),
+    hour: date.getUTCHours(),
+    minute: date.getUTCMinutes(),
+    second: date.getUTCSeconds(),
+    millisecond: date.getUTCMilliseconds(),
+    weekday: date.getUTCDay(),
+  });
+}",
+    ),
+    (
+        "dateFromParts",
+        &[],
+        "function dateFromParts(year, month, day, hour, minute, second) {
+  return Date.UTC(year, month - 1, day, hour, minute, second);
+}",
+    ),
+    (
+        "__nextRandom",
+        &[],
+        "let __randomState = BigInt(Math.floor(Math.random() * 2 ** 53));
+function __nextRandom() {
+  __randomState = BigInt.asUintN(64, __randomState + 0x9E3779B97F4A7C15n);
+  let value = __randomState;
+  value = BigInt.asUintN(64, (value ^ (value >> 30n)) * 0xBF58476D1CE4E5B9n);
+  value = BigInt.asUintN(64, (value ^ (value >> 27n)) * 0x94D049BB133111EBn);
+  return value ^ (value >> 31n);
+}",
+    ),
+    (
+        "random",
+        &["__nextRandom"],
+        "function random() {
+  return Number(__nextRandom() >> 11n) / 2 ** 53;
+}",
+    ),
+    (
+        "randomInt",
+        &["__nextRandom"],
+        "function randomInt(low, high) {
+  return low + Number(__nextRandom() % BigInt(high - low + 1));
+}",
+    ),
+    (
+        "randomSeed",
+        &["__nextRandom"],
+        "function randomSeed(seed) {
+  __randomState = BigInt.asUintN(64, BigInt(seed));
+  return null;
+}",
+    ),
+    (
+        "readLine",
+        &[],
+        "function readLine() {
+  const fs = require(\"fs\");
+  const bytes = [];
+  const byte = Buffer.alloc(1);
+  while (fs.readSync(0, byte, 0, 1, null) === 1 && byte[0] !== 10) {
+    bytes.push(byte[0]);
+  }
+  return bytes.length === 0 && byte[0] !== 10 ? null : Buffer.from(bytes).toString().replace(/\\r$/, \"\");
+}",
+    ),
+    (
+        "readFile",
+        &[],
+        "function readFile(path) {
+  return require(\"fs\").readFileSync(path, \"utf8\");
+}",
+    ),
+    (
+        "writeFile",
+        &[],
+        "function writeFile(path, contents) {
+  require(\"fs\").writeFileSync(path, contents);
+  return true;
+}",
+    ),
+    (
+        "appendFile",
+        &[],
+        "function appendFile(path, contents) {
+  require(\"fs\").appendFileSync(path, contents);
+  return true;
+}",
+    ),
+    (
+        "split",
+        &[],
+        "function split(text, separator) {
+  return separator === \"\" ? Array.from(text) : text.split(separator);
+}",
+    ),
+    (
+        "join",
+        &["toString"],
+        "function join(list, separator) {
+  return list.map(toString).join(separator);
+}",
+    ),
+    (
+        "parseNumber",
+        &[],
+        "function parseNumber(text) {
+  return /^\\s*[+-]?(\\d+\\.?\\d*|\\.\\d+)([eE][+-]?\\d+)?\\s*$/.test(text) ? Number(text) : null;
+}",
+    ),
+    (
+        "int",
+        &[],
+        "function int(value) {
+  return Math.trunc(value);
+}",
+    ),
+    (
+        "float",
+        &[],
+        "function float(value) {
+  return value;
+}",
+    ),
+    (
+        "toString",
+        &["__str"],
+        "function toString(value) {
+  return typeof value === \"string\" ? value : __str(value);
+}",
+    ),
+    (
+        "hash",
+        &[],
+        "const __identities = new WeakMap();
+let __lastIdentity = 0;
+function hash(value) {
+  if (typeof value === \"string\") {
+    let hash = 0;
+    for (const character of value) hash = Math.imul(hash, 31) + character.codePointAt(0) | 0;
+    return hash;
+  }
+  if (typeof value === \"number\") return Number.isInteger(value) ? value : hash(String(value));
+  if (value === null || typeof value === \"boolean\") return value ? 1 : 0;
+  if (!__identities.has(value)) __identities.set(value, ++__lastIdentity);
+  return __identities.get(value);
+}",
+    ),
+    (
+        "toFixed",
+        &[],
+        "function toFixed(number, digits) {
+  return number.toFixed(digits);
+}",
+    ),
+    (
+        "toPrecision",
+        &[],
+        "function toPrecision(number, digits) {
+  return number.toPrecision(digits);
+}",
+    ),
+    (
+        "toStringBase",
+        &[],
+        "function toStringBase(number, base) {
+  if (!Number.isInteger(number)) throw new Error(\"First argument of 'toStringBase' must be an integer.\");
+  return number.toString(base);
+}",
+    ),
+    (
+        "format",
+        &["toString"],
+        "function format(template, ...values) {
+  let index = 0;
+  return template.replace(/\\{\\{|\\}\\}|\\{\\}/g, (match) => match === \"{}\" ? toString(values[index++]) : match[0]);
+}",
+    ),
+    (
+        "eprint",
+        &["toString"],
+        "function eprint(value) {
+  console.error(toString(value));
+}",
+    ),
+    (
+        "list",
+        &[],
+        "function list() {
+  return [];
+}",
+    ),
+    (
+        "len",
+        &[],
+        "function len(value) {
+  return typeof value === \"string\" ? Array.from(value).length : value.length;
+}",
+    ),
+    (
+        "get",
+        &[],
+        "function get(value, index) {
+  if (typeof value === \"string\") {
+    const characters = Array.from(value);
+    if (!(index in characters)) throw new Error(`Index ${index} is out of range for a string of ${characters.length} characters.`);
+    return characters[index];
+  }
+  if (!(index in value)) throw new Error(`Index ${index} is out of range for a list of ${value.length} elements.`);
+  return value[index];
+}",
+    ),
+    (
+        "byteLen",
+        &[],
+        "function byteLen(text) {
+  return new TextEncoder().encode(text).length;
+}",
+    ),
+    (
+        "slice",
+        &[],
+        "function slice(value, start, end) {
+  const elements = typeof value === \"string\" ? Array.from(value) : value;
+  const kind = typeof value === \"string\" ? `a string of ${elements.length} characters` : `a list of ${elements.length} elements`;
+  if (end < start) throw new Error(`Range ${start}..${end} ends before it starts.`);
+  if (start < 0 || end > elements.length) throw new Error(`Range ${start}..${end} is out of range for ${kind}.`);
+  const part = elements.slice(start, end);
+  return typeof value === \"string\" ? part.join(\"\") : part;
+}",
+    ),
+    (
+        "codePointAt",
+        &["get"],
+        "function codePointAt(text, index) {
+  return get(text, index).codePointAt(0);
+}",
+    ),
+    (
+        "fromCodePoint",
+        &[],
+        "function fromCodePoint(code) {
+  if (!Number.isInteger(code) || code < 0 || code > 0x10FFFF || (code >= 0xD800 && code <= 0xDFFF)) throw new Error(`Code point ${code} is not a Unicode scalar value.`);
+  return String.fromCodePoint(code);
+}",
+    ),
+    (
+        "push",
+        &[],
+        "function push(list, value) {
+  list.push(value);
+  return null;
+}",
+    ),
+    (
+        "pop",
+        &[],
+        "function pop(list) {
+  if (list.length === 0) throw new Error(\"Cannot pop from an empty list.\");
+  return list.pop();
+}",
+    ),
+    (
+        "contains",
+        &[],
+        "function contains(container, value) {
+  return container.includes(value);
+}",
+    ),
+    (
+        "map",
+        &["__call"],
+        "function map(list, fn) {
+  return list.map((value) => __call(fn, value));
+}",
+    ),
+    (
+        "filter",
+        &["__call", "__truthy"],
+        "function filter(list, fn) {
+  return list.filter((value) => __truthy(__call(fn, value)));
+}",
+    ),
+    (
+        "reduce",
+        &["__call"],
+        "function reduce(list, fn, initial) {
+  return list.reduce((total, value) => __call(fn, total, value), initial);
+}",
+    ),
+    (
+        "sort",
+        &["__call"],
+        "function sort(list, comparator) {
+  return list.sort((a, b) => __call(comparator, a, b));
+}",
+    ),
+    (
+        "assert",
+        &["__truthy", "toString"],
+        "function assert(condition, message) {
+  if (!__truthy(condition)) throw new Error(`Assertion failed: ${toString(message)}`);
+  return null;
+}",
+    ),
+    (
+        "error",
+        &["toString"],
+        "function error(message) {
+  throw new Error(toString(message));
+}",
+    ),
+    (
+        "test",
+        &["__call"],
+        "function test(name, fn) {
+  try {
+    __call(fn);
+    console.log(`test ${name} ... ok`);
+    return true;
+  } catch (error) {
+    console.log(`test ${name} ... FAILED: ${error.message}`);
+    return false;
+  }
+}",
+    ),
+    (
+        "expectEq",
+        &["__str"],
+        "function expectEq(actual, expected) {
+  const equal = (a, b) =>
+    Array.isArray(a) && Array.isArray(b) ? a.length === b.length && a.every((value, i) => equal(value, b[i])) : a === b;
+  if (!equal(actual, expected)) throw new Error(`Expected ${__str(expected)}, got ${__str(actual)}.`);
+  return null;
+}",
+    ),
+    (
+        "exit",
+        &[],
+        "function exit(code) {
+  process.exit(code);
+}",
+    ),
+    (
+        "argc",
+        &["__args"],
+        "function argc() {
+  return __args.length;
+}",
+    ),
+    (
+        "arg",
+        &["__args"],
+        "function arg(index) {
+  return __args[index] ?? null;
+}",
+    ),
+    (
+        "next",
+        &[],
+        "function next(generator) {
+  const step = generator.next();
+  return step.done ? null : step.value;
+}",
+    ),
+    (
+        "inspect",
+        &["__str"],
+        "function inspect(value) {
+  // JavaScript closures can't be looked into, only the fields of an instance are listed
+  if (typeof value === \"function\") {
+    return __str(value);
+  }
+  if (value === null || typeof value !== \"object\" || Array.isArray(value)) {
+    throw new Error(\"Argument of 'inspect' must be a function or an instance.\");
+  }
+  const proto = Object.getPrototypeOf(value);
+  const fields = Object.keys(value).filter((name) => !(name in proto)).sort();
+  const lines = fields.map((name) => `  ${name} = ${__str(value[name])}`);
+  return [`<${proto.constructor.name} instance>`, \"[fields]\", ...lines].join(\"\\n\");
+}",
+    ),
+    // Functions of `prelude.lox`
+    (
+        "max",
+        &[],
+        "function max(a, b) {
+  return a > b ? a : b;
+}",
+    ),
+    (
+        "min",
+        &[],
+        "function min(a, b) {
+  return a < b ? a : b;
+}",
+    ),
+    (
+        "abs",
+        &[],
+        "function abs(x) {
+  return x < 0 ? -x : x;
+}",
+    ),
+    (
+        "range",
+        &[],
+        "function range(start, end) {
+  const numbers = [];
+  for (let i = start; i < end; i++) numbers.push(i);
+  return numbers;
+}",
+    ),
+];
+
+/// Natives of the interpreter and functions of the prelude, emitted as helpers when the
+/// program uses them
+const NATIVES: &[&str] = &[
+    "clock",
+    "clockMonotonic",
+    "bench",
+    "sleep",
+    "timeFormat",
+    "now",
+    "dateParts",
+    "dateFromParts",
+    "random",
+    "randomInt",
+    "randomSeed",
+    "readLine",
+    "readFile",
+    "writeFile",
+    "appendFile",
+    "split",
+    "join",
+    "parseNumber",
+    "int",
+    "float",
+    "toString",
+    "hash",
+    "toFixed",
+    "toPrecision",
+    "toStringBase",
+    "format",
+    "eprint",
+    "list",
+    "len",
+    "get",
+    "byteLen",
+    "slice",
+    "codePointAt",
+    "fromCodePoint",
+    "push",
+    "pop",
+    "contains",
+    "map",
+    "filter",
+    "reduce",
+    "sort",
+    "assert",
+    "error",
+    "test",
+    "expectEq",
+    "exit",
+    "argc",
+    "arg",
+    "next",
+    "inspect",
+    "max",
+    "min",
+    "abs",
+    "range",
+];
+
+/// Lox identifiers that can't name a JavaScript variable, they get a `_` suffix
+const RESERVED: &[&str] = &[
+    "arguments",
+    "await",
+    "case",
+    "catch",
+    "const",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "finally",
+    "function",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "null",
+    "static",
+    "switch",
+    "throw",
+    "try",
+    "typeof",
+    "undefined",
+    "void",
+    "with",
+    "yield",
+];
+
+/// What a name is bound to, calls to classes need `new`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Binding {
+    Function,
+    Class,
+    Value,
+}
+
+/// Emits JavaScript for parsed statements
+#[derive(Default)]
+pub struct JsTranspiler {
+    output: String,
+    indent: usize,
+    /// Innermost scope last, the first one holds the globals
+    scopes: Vec<HashMap<String, Binding>>,
+    /// Helpers used so far by the program
+    helpers: Vec<&'static str>,
+    /// Native functions the program doesn't declare again itself
+    natives: Vec<&'static str>,
+}
+
+impl JsTranspiler {
+    pub fn new() -> JsTranspiler {
+        JsTranspiler::default()
+    }
+
+    pub fn transpile(&mut self, statements: &[Stmt]) -> String {
+        self.output.clear();
+        self.helpers.clear();
+        let mut globals = HashMap::new();
+        // Functions and classes can be called before their declaration, from other functions
+        for statement in statements {
+            let (name, binding) = match statement {
+                Stmt::Function(stmt) => (&stmt.name, Binding::Function),
+                Stmt::Class(stmt) => (&stmt.name, Binding::Class),
+                Stmt::Var(stmt) => (&stmt.name, Binding::Value),
+                _ => continue,
+            };
+            globals.insert(name.lexeme.clone(), binding);
+        }
+        self.natives = NATIVES
+            .iter()
+            .copied()
+            .filter(|native| !globals.contains_key(*native))
+            .collect();
+        for native in &self.natives {
+            globals.insert(native.to_string(), Binding::Function);
+        }
+        self.scopes = vec![globals];
+        for (idx, statement) in statements.iter().enumerate() {
+            let is_declaration = |stmt: &Stmt| matches!(stmt, Stmt::Function(_) | Stmt::Class(_));
+            if idx > 0 && (is_declaration(statement) || is_declaration(&statements[idx - 1])) {
+                self.output.push('\n');
+            }
+            self.write_stmt(statement);
+        }
+
+        let program = std::mem::take(&mut self.output);
+        let prelude: Vec<&str> = HELPERS
+            .iter()
+            .filter(|(name, _, _)| self.helpers.contains(name))
+            .map(|(_, _, source)| *source)
+            .collect();
+        match prelude.is_empty() {
+            true => program,
+            false => format!("{}\n\n{program}", prelude.join("\n\n")),
+        }
+    }
+
+    fn use_helper(&mut self, name: &'static str) {
+        if self.helpers.contains(&name) {
+            return;
+        }
+        self.helpers.push(name);
+        if let Some((_, dependencies, _)) = HELPERS.iter().find(|(helper, _, _)| *helper == name) {
+            for dependency in *dependencies {
+                self.use_helper(dependency);
+            }
+        }
+    }
+
+    fn write_stmt(&mut self, statement: &Stmt) {
+        statement.accept(self)
+    }
+
+    fn write_line(&mut self, line: &str) {
+        for _ in 0..self.indent {
+            self.output.push_str(INDENT);
+        }
+        self.output.push_str(line);
+        self.output.push('\n');
+    }
+
+    /// Writes the statements one level deeper in a new scope
+    fn write_scoped(&mut self, statements: &[Stmt]) {
+        self.scopes.push(HashMap::new());
+        self.indent += 1;
+        for statement in statements {
+            self.write_stmt(statement);
+        }
+        self.indent -= 1;
+        self.scopes.pop();
+    }
+
+    /// Body of `if`/`while`/`for`, always written between braces
+    fn write_branch(&mut self, body: &Stmt) {
+        match body {
+            Stmt::Block(block) => self.write_scoped(&block.statements),
+            _ => self.write_scoped(std::slice::from_ref(body)),
+        }
+    }
+
+    /// Writes an `if`, `opener` is `} else ` for the branches of an `else if` chain
+    fn write_if(&mut self, stmt: &IfStmt, opener: &str) {
+        let condition = self.condition(&stmt.condition);
+        self.write_line(&format!("{opener}if ({condition}) {{"));
+        self.write_branch(&stmt.then_branch);
+        match stmt.else_branch.as_deref() {
+            None => self.write_line("}"),
+            Some(Stmt::If(nested)) => self.write_if(nested, "} else "),
+            Some(else_branch) => {
+                self.write_line("} else {");
+                self.write_branch(else_branch);
+                self.write_line("}");
+            }
+        }
+    }
+
+    /// Writes `header {`, the body with the parameters in scope and `closing`
+    fn write_function(&mut self, header: &str, function: &FunctionStmt, closing: &str) {
+        if function.body.is_empty() {
+            self.write_line(&format!("{header} {{{closing}"));
+            return;
+        }
+        self.write_line(&format!("{header} {{"));
+        let mut scope = HashMap::new();
+        for param in &function.params {
+            scope.insert(param.lexeme.clone(), Binding::Value);
+        }
+        self.scopes.push(scope);
+        self.write_scoped(&function.body);
+        self.scopes.pop();
+        self.write_line(closing);
+    }
+
+    fn params(function: &FunctionStmt) -> String {
+        function
+            .params
+            .iter()
+            .map(|param| js_name(&param.lexeme))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn declare(&mut self, name: &str, binding: Binding) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), binding);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Binding {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+            .unwrap_or(Binding::Value)
+    }
+
+    fn is_global_scope(&self) -> bool {
+        self.scopes.len() == 1
+    }
+
+    fn expr(&mut self, expression: &Expr) -> String {
+        expression.accept(self)
+    }
+
+    /// Expression used as a condition, converted with Lox truthiness unless it is
+    /// already a boolean
+    fn condition(&mut self, expression: &Expr) -> String {
+        match expression {
+            Expr::Grouping(expr) => format!("({})", self.condition(&expr.expression)),
+            Expr::Logical(expr) => {
+                let operator = match expr.operator.token_type {
+                    TokenType::Or => "||",
+                    _ => "&&",
+                };
+                format!(
+                    "{} {operator} {}",
+                    self.condition(&expr.left),
+                    self.condition(&expr.right)
+                )
+            }
+            _ if is_boolean(expression) => self.expr(expression),
+            _ => {
+                self.use_helper("__truthy");
+                format!("__truthy({})", self.expr(expression))
+            }
+        }
+    }
+
+    fn var_declaration(&mut self, stmt: &VarStmt, keyword: &str) -> String {
+        let initializer = match &stmt.initializer {
+            Some(initializer) => self.expr(initializer),
+            None => "null".to_string(),
+        };
+        self.declare(&stmt.name.lexeme, Binding::Value);
+        format!("{keyword} {} = {initializer}", js_name(&stmt.name.lexeme))
+    }
+}
+
+impl StmtVisitor<()> for JsTranspiler {
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) {
+        self.write_line("{");
+        self.write_scoped(&stmt.statements);
+        self.write_line("}");
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &ClassStmt) {
+        let name = js_name(&stmt.name.lexeme);
+        self.declare(&stmt.name.lexeme, Binding::Class);
+        let header = match &stmt.super_class {
+            Some(super_class) => format!("class {name} extends {}", self.expr(super_class)),
+            None => format!("class {name}"),
+        };
+        self.write_line(&format!("{header} {{"));
+        self.indent += 1;
+        // Subclasses inherit the constructor, it binds the methods so `obj.method`
+        // can be passed around like in Lox and calls `init` when there is one
+        if stmt.super_class.is_none() {
+            self.use_helper("__bindMethods");
+            self.write_line("constructor(...args) {");
+            self.indent += 1;
+            self.write_line("__bindMethods(this);");
+            self.write_line("if (this.init) this.init(...args);");
+            self.indent -= 1;
+            self.write_line("}");
+        }
+        for (idx, method) in stmt.methods.iter().enumerate() {
+            if idx > 0 || stmt.super_class.is_none() {
+                self.output.push('\n');
+            }
+            if let Stmt::Function(method) = method {
+                let header = format!(
+                    "{}{}({})",
+                    generator_star(method),
+                    method.name.lexeme,
+                    JsTranspiler::params(method)
+                );
+                self.write_function(&header, method, "}");
+            }
+        }
+        self.indent -= 1;
+        self.write_line("}");
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) {
+        let expression = self.expr(&stmt.expression);
+        self.write_line(&format!("{expression};"));
+    }
+
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) {
+        self.scopes.push(HashMap::new());
+        // A `let` in the clauses would give every iteration its own copy, closures made in
+        // the body would not share the variable as in Lox. It is declared once before instead.
+        let declaration = match stmt.initializer.as_deref() {
+            Some(Stmt::Var(var)) => Some(self.var_declaration(var, "let")),
+            _ => None,
+        };
+        let initializer = match stmt.initializer.as_deref() {
+            Some(Stmt::Expression(expression)) => self.expr(&expression.expression),
+            _ => String::new(),
+        };
+        let condition = match &stmt.condition {
+            Some(condition) => format!(" {}", self.condition(condition)),
+            None => String::new(),
+        };
+        let increment = match &stmt.increment {
+            Some(increment) => format!(" {}", self.expr(increment)),
+            None => String::new(),
+        };
+        let label = loop_label(&stmt.label);
+        if let Some(declaration) = &declaration {
+            self.write_line("{");
+            self.indent += 1;
+            self.write_line(&format!("{declaration};"));
+        }
+        self.write_line(&format!(
+            "{label}for ({initializer};{condition};{increment}) {{"
+        ));
+        self.write_branch(&stmt.body);
+        self.write_line("}");
+        if declaration.is_some() {
+            self.indent -= 1;
+            self.write_line("}");
+        }
+        self.scopes.pop();
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &FunctionStmt) {
+        let name = js_name(&stmt.name.lexeme);
+        self.declare(&stmt.name.lexeme, Binding::Function);
+        let params = JsTranspiler::params(stmt);
+        let star = generator_star(stmt);
+        // Nested functions are arrow functions so they see the `this` of an enclosing method,
+        // generators can't be arrow functions and are bound to it instead
+        match (self.is_global_scope(), contains_yield(&stmt.body)) {
+            (true, _) => {
+                self.write_function(&format!("function{star} {name}({params})"), stmt, "}")
+            }
+            (false, true) => {
+                let header = format!("const {name} = function* ({params})");
+                self.write_function(&header, stmt, "}.bind(this);")
+            }
+            (false, false) => {
+                let header = format!("const {name} = ({params}) =>");
+                self.write_function(&header, stmt, "};")
+            }
+        }
+    }
+
+    fn visit_for_in_stmt(&mut self, stmt: &ForInStmt) {
+        let iterable = self.expr(&stmt.iterable);
+        let mut scope = HashMap::new();
+        scope.insert(stmt.name.lexeme.clone(), Binding::Value);
+        self.scopes.push(scope);
+        let label = loop_label(&stmt.label);
+        let name = js_name(&stmt.name.lexeme);
+        self.write_line(&format!("{label}for (const {name} of {iterable}) {{"));
+        self.write_branch(&stmt.body);
+        self.write_line("}");
+        self.scopes.pop();
+    }
+
+    fn visit_yield_stmt(&mut self, stmt: &YieldStmt) {
+        let value = match &stmt.value {
+            Some(value) => self.expr(value),
+            None => "null".to_string(),
+        };
+        self.write_line(&format!("yield {value};"));
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) {
+        self.write_if(stmt, "");
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) {
+        self.use_helper("__print");
+        let expression = self.expr(&stmt.expression);
+        self.write_line(&format!("__print({expression});"));
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) {
+        match &stmt.value {
+            Some(value) => {
+                let value = self.expr(value);
+                self.write_line(&format!("return {value};"))
+            }
+            None => self.write_line("return;"),
+        }
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) {
+        // Globals can be declared again in Lox, which `let` doesn't allow
+        let keyword = match self.is_global_scope() {
+            true => "var",
+            false => "let",
+        };
+        let declaration = self.var_declaration(stmt, keyword);
+        self.write_line(&format!("{declaration};"));
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) {
+        let condition = self.condition(&stmt.condition);
+        let label = loop_label(&stmt.label);
+        self.write_line(&format!("{label}while ({condition}) {{"));
+        self.write_branch(&stmt.body);
+        self.write_line("}");
+    }
+
+    fn visit_break_stmt(&mut self, stmt: &BreakStmt) {
+        self.write_line(&jump("break", &stmt.label));
+    }
+
+    fn visit_continue_stmt(&mut self, stmt: &ContinueStmt) {
+        self.write_line(&jump("continue", &stmt.label));
+    }
+
+    fn visit_debugger_stmt(&mut self, _stmt: &DebuggerStmt) {
+        self.write_line("debugger;");
+    }
+}
+
+impl ExprVisitor<String> for JsTranspiler {
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> String {
+        // After an assignment the name may hold anything
+        if let Some(scope) = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.contains_key(&expr.name.lexeme))
+        {
+            scope.insert(expr.name.lexeme.clone(), Binding::Value);
+        }
+        format!(
+            "{} = {}",
+            js_name(&expr.name.lexeme),
+            self.expr(&expr.value)
+        )
+    }
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> String {
+        let is_nil =
+            |expr: &Expr| matches!(expr, Expr::Literal(literal) if literal.value == Object::Nil);
+        // `== null` also matches `undefined`, what functions without a return value give
+        let operator = match expr.operator.token_type {
+            TokenType::EqualEqual if is_nil(&expr.left) || is_nil(&expr.right) => "==",
+            TokenType::BangEqual if is_nil(&expr.left) || is_nil(&expr.right) => "!=",
+            TokenType::EqualEqual => "===",
+            TokenType::BangEqual => "!==",
+            TokenType::Star => return self.multiplication(expr),
+            _ => expr.operator.lexeme.as_str(),
+        };
+        format!(
+            "{} {operator} {}",
+            self.expr(&expr.left),
+            self.expr(&expr.right)
+        )
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> String {
+        let arguments: Vec<String> = expr
+            .arguments
+            .iter()
+            .map(|argument| self.expr(argument))
+            .collect();
+        let arguments = arguments.join(", ");
+        match expr.callee.as_ref() {
+            Expr::Variable(callee) => {
+                let name = self.visit_variable_expr(callee);
+                match self.lookup(&callee.name.lexeme) {
+                    Binding::Class => format!("new {name}({arguments})"),
+                    Binding::Function => format!("{name}({arguments})"),
+                    Binding::Value => self.dynamic_call(name, arguments),
+                }
+            }
+            Expr::Get(_) | Expr::Super(_) => {
+                format!("{}({arguments})", self.callee(&expr.callee))
+            }
+            callee => {
+                let callee = self.expr(callee);
+                self.dynamic_call(callee, arguments)
+            }
+        }
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> String {
+        format!("{}.{}", self.expr(&expr.object), expr.name.lexeme)
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> String {
+        format!("({})", self.expr(&expr.expression))
+    }
+
+    fn visit_literal_expr(&mut self, expr: &LiteralExpr) -> String {
+        match &expr.value {
+            Object::String(value) => Json::string(value).to_string(),
+            Object::Nil => "null".to_string(),
+            #[cfg(feature = "bigint")]
+            Object::BigInt(int) => format!("{int}n"),
+            value => value.to_string(),
+        }
+    }
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> String {
+        if is_boolean(&expr.left) && is_boolean(&expr.right) {
+            return self.condition(&Expr::Logical(expr.clone()));
+        }
+        let helper = match expr.operator.token_type {
+            TokenType::Or => "__or",
+            _ => "__and",
+        };
+        self.use_helper(helper);
+        format!(
+            "{helper}({}, () => {})",
+            self.expr(&expr.left),
+            self.expr(&expr.right)
+        )
+    }
+
+    fn visit_set_expr(&mut self, expr: &SetExpr) -> String {
+        format!(
+            "{}.{} = {}",
+            self.expr(&expr.object),
+            expr.name.lexeme,
+            self.expr(&expr.value)
+        )
+    }
+
+    fn visit_this_expr(&mut self, _expr: &ThisExpr) -> String {
+        "this".to_string()
+    }
+
+    fn visit_super_expr(&mut self, expr: &SuperExpr) -> String {
+        // Not called right away, so it has to keep its instance
+        format!("super.{}.bind(this)", expr.method.lexeme)
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> String {
+        match expr.operator.token_type {
+            TokenType::Bang => format!("!{}", self.condition(&expr.right)),
+            _ => {
+                let operand = self.expr(&expr.right);
+                // `- -x` must not become the `--` operator
+                match operand.starts_with('-') {
+                    true => format!("-({operand})"),
+                    false => format!("-{operand}"),
+                }
+            }
+        }
+    }
+
+    fn visit_ternary_expr(&mut self, expr: &TernaryExpr) -> String {
+        format!(
+            "{} ? {} : {}",
+            self.condition(&expr.condition),
+            self.expr(&expr.then_branch),
+            self.expr(&expr.else_branch)
+        )
+    }
+
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) -> String {
+        if let Some(native) = self.native(&expr.name.lexeme) {
+            self.use_helper(native);
+        }
+        js_name(&expr.name.lexeme)
+    }
+}
+
+impl JsTranspiler {
+    /// Callee of a method call, `super.method` is called directly instead of bound
+    fn callee(&mut self, callee: &Expr) -> String {
+        match callee {
+            Expr::Super(expr) => format!("super.{}", expr.method.lexeme),
+            _ => self.expr(callee),
+        }
+    }
+
+    /// `*` of Lox, which repeats a string when one operand is a string: `"ab" * 3` is "ababab"
+    fn multiplication(&mut self, expr: &BinaryExpr) -> String {
+        let is_string = |expr: &Expr| matches!(expr, Expr::Literal(literal) if matches!(literal.value, Object::String(_)));
+        let left = self.expr(&expr.left);
+        let right = self.expr(&expr.right);
+        if is_string(&expr.left) {
+            format!("{left}.repeat({right})")
+        } else if is_string(&expr.right) {
+            format!("{right}.repeat({left})")
+        } else if is_number(&expr.left) && is_number(&expr.right) {
+            format!("{left} * {right}")
+        } else {
+            self.use_helper("__mul");
+            format!("__mul({left}, {right})")
+        }
+    }
+
+    /// Call of a value that may hold a function or a class
+    fn dynamic_call(&mut self, callee: String, arguments: String) -> String {
+        self.use_helper("__call");
+        match arguments.is_empty() {
+            true => format!("__call({callee})"),
+            false => format!("__call({callee}, {arguments})"),
+        }
+    }
+
+    /// The native the name refers to, unless a local or global declaration hides it
+    fn native(&self, name: &str) -> Option<&'static str> {
+        let native = self.natives.iter().find(|native| **native == name)?;
+        let declared_in = self
+            .scopes
+            .iter()
+            .rposition(|scope| scope.contains_key(name));
+        (declared_in == Some(0)).then_some(*native)
+    }
+}
+
+/// Expressions that always give a number, `*` on them can't repeat a string
+fn is_number(expression: &Expr) -> bool {
+    match expression {
+        Expr::Literal(expr) => expr.value.as_number().is_some(),
+        Expr::Grouping(expr) => is_number(&expr.expression),
+        Expr::Unary(expr) => expr.operator.is(TokenType::Minus),
+        Expr::Binary(expr) => {
+            matches!(
+                expr.operator.token_type,
+                TokenType::Minus | TokenType::Slash | TokenType::Star
+            ) && is_number(&expr.left)
+                && is_number(&expr.right)
+        }
+        _ => false,
+    }
+}
+
+/// Expressions that always give a boolean, they can be used as conditions as they are
+fn is_boolean(expression: &Expr) -> bool {
+    match expression {
+        Expr::Literal(expr) => matches!(expr.value, Object::Bool(_)),
+        Expr::Grouping(expr) => is_boolean(&expr.expression),
+        Expr::Logical(expr) => is_boolean(&expr.left) && is_boolean(&expr.right),
+        Expr::Unary(expr) => expr.operator.is(TokenType::Bang),
+        Expr::Binary(expr) => matches!(
+            expr.operator.token_type,
+            TokenType::EqualEqual
+                | TokenType::BangEqual
+                | TokenType::Greater
+                | TokenType::GreaterEqual
+                | TokenType::Less
+                | TokenType::LessEqual
+        ),
+        _ => false,
+    }
+}
+
+fn js_name(name: &str) -> String {
+    match RESERVED.contains(&name) {
+        true => format!("{name}_"),
+        false => name.to_string(),
+    }
+}
+
+/// `*` marking a function containing `yield` as a JavaScript generator
+fn generator_star(function: &FunctionStmt) -> &'static str {
+    match contains_yield(&function.body) {
+        true => "*",
+        false => "",
+    }
+}
+
+/// JS labels share the Lox syntax, `outer: while (...)`
+fn loop_label(label: &Option<Token>) -> String {
+    match label {
+        Some(label) => format!("{}: ", js_name(&label.lexeme)),
+        None => String::new(),
+    }
+}
+
+fn jump(keyword: &str, label: &Option<Token>) -> String {
+    match label {
+        Some(label) => format!("{keyword} {};", js_name(&label.lexeme)),
+        None => format!("{keyword};"),
+    }
+}
+
+#[cfg(test)]
+mod transpiler_tests {
+    use crate::{parser::Parser, scanner::Scanner};
+
+    use super::*;
+
+    fn transpile_source(source: &str) -> String {
+        let tokens = Scanner::new(source.to_string()).scan_tokens();
+        let statements = Parser::new(tokens).parse();
+        JsTranspiler::new().transpile(&statements)
+    }
+
+    #[test]
+    fn test_transpile_classes_and_closures() {
+        // Arrange
+        let source = "class A { init(x) { this.x = x; } }
+class B < A { get() { fun inner() { return this.x; } return inner; } }
+var f = B(1).get();
+f();";
+        // Act
+        let javascript = transpile_source(source);
+        // Assert
+        assert!(javascript.starts_with("function __isClass(value) {"));
+        assert!(javascript.ends_with(
+            "class A {
+  constructor(...args) {
+    __bindMethods(this);
+    if (this.init) this.init(...args);
+  }
+
+  init(x) {
+    this.x = x;
+  }
+}
+
+class B extends A {
+  get() {
+    const inner = () => {
+      return this.x;
+    };
+    return inner;
+  }
+}
+
+var f = new B(1).get();
+__call(f);
+"
+        ));
+    }
+
+    #[test]
+    fn test_transpile_truthiness_and_natives() {
+        // Arrange
+        let source = "var new = 0;
+if (new and new < clock()) print new or nil; else if (!new) {}";
+        // Act
+        let javascript = transpile_source(source);
+        // Assert
+        let helpers: Vec<&str> = javascript
+            .lines()
+            .filter_map(|line| line.strip_prefix("function "))
+            .map(|line| &line[..line.find('(').unwrap_or(0)])
+            .collect();
+        assert_eq!(
+            helpers,
+            vec!["__truthy", "__or", "__isClass", "__str", "__print", "clock"]
+        );
+        assert!(javascript.ends_with(
+            "var new_ = 0;
+if (__truthy(new_) && new_ < clock()) {
+  __print(__or(new_, () => null));
+} else if (!__truthy(new_)) {
+}
+"
+        ));
+    }
+
+    #[test]
+    fn test_transpile_for_loops_and_string_repetition() {
+        // Arrange
+        let source = "fun f(n) {
+  for (var i = 0; i < n; i = i + 1) print \"ab\" * 3;
+  for (i = 0; i < 2;) print n * 2 + 2 * (3 - 1);
+}";
+        // Act
+        let javascript = transpile_source(source);
+        // Assert
+        assert!(javascript.starts_with("function __mul(left, right) {"));
+        assert!(javascript.ends_with(
+            "function f(n) {
+  {
+    let i = 0;
+    for (; i < n; i = i + 1) {
+      __print(\"ab\".repeat(3));
+    }
+  }
+  for (i = 0; i < 2;) {
+    __print(__mul(n, 2) + 2 * (3 - 1));
+  }
+}
+"
+        ));
+    }
+}
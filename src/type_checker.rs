@@ -0,0 +1,745 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+};
+
+use crate::{
+    analysis::SymbolKind,
+    error::*,
+    error_codes::{Message, T0401, T0402, T0403, T0404, T0405, T0406},
+    expr::*,
+    object::Object,
+    stmt::*,
+    token::Token,
+    walker::Walker,
+};
+
+/// Type of a value as far as the checker can tell
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoxType {
+    /// Annotated as taking any value
+    Any,
+    /// Neither annotated nor inferred, checked like `Any`
+    Unknown,
+    Nil,
+    Bool,
+    Number,
+    String,
+    List,
+    Function,
+    /// Instance of the class of that name
+    Instance(String),
+}
+
+impl Display for LoxType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            LoxType::Any => write!(f, "Any"),
+            LoxType::Unknown => write!(f, "unknown"),
+            LoxType::Nil => write!(f, "nil"),
+            LoxType::Bool => write!(f, "Bool"),
+            LoxType::Number => write!(f, "Number"),
+            LoxType::String => write!(f, "String"),
+            LoxType::List => write!(f, "List"),
+            LoxType::Function => write!(f, "Function"),
+            LoxType::Instance(class) => write!(f, "{class}"),
+        }
+    }
+}
+
+/// A declaration of the script and the type the checker gave it (`--types`)
+#[derive(Debug, Clone)]
+pub struct TypedDeclaration {
+    pub name: Token,
+    pub kind: SymbolKind,
+    /// `fun(Number, unknown) -> String` for functions and methods, `class` for classes
+    pub type_name: String,
+}
+
+impl Display for TypedDeclaration {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            SymbolKind::Class => "class",
+            SymbolKind::Method => "method",
+            SymbolKind::Function => "fun",
+            SymbolKind::Variable => "var",
+            SymbolKind::Parameter => "param",
+        };
+        // Columns count from 1, as in every other `line:column` the tool prints
+        write!(
+            f,
+            "{}:{} {kind} {}: {}",
+            self.name.line,
+            self.name.column + 1,
+            self.name.lexeme,
+            self.type_name
+        )
+    }
+}
+
+/// What a call to a function or a class gives, and what it takes when it has annotations
+#[derive(Debug, Clone)]
+struct Signature {
+    /// `None` when neither the parameters nor the result are annotated
+    params: Option<Vec<(Token, LoxType)>>,
+    result: LoxType,
+}
+
+#[derive(Debug, Clone)]
+struct Binding {
+    value: LoxType,
+    /// Set for the functions and classes declared by the script
+    signature: Option<Signature>,
+}
+
+impl Binding {
+    fn value(value: LoxType) -> Binding {
+        Binding {
+            value,
+            signature: None,
+        }
+    }
+}
+
+/// Checks the optional type annotations (`var x: Number`, `fun f(a: String) -> Bool`)
+/// against the obvious types of initializers, assigned values, arguments and returned values.
+/// Mismatches are warnings, or errors in strict mode, the interpreter ignores the annotations.
+/// Only functions with annotations are checked at their call sites, so untyped scripts
+/// get no new warnings. `nil` is accepted for every type.
+///
+/// A variable without annotation takes the type of its initializer when the script never
+/// assigns it, otherwise its type is unknown.
+pub struct TypeChecker {
+    strict: bool,
+    pub had_error: bool,
+    /// Innermost last, the first one holds the globals
+    scopes: Vec<HashMap<String, Binding>>,
+    /// Every class of the script by name, with the name of its super class
+    classes: HashMap<String, Option<String>>,
+    /// Name and annotated result of the function being checked
+    current_function: Option<(String, LoxType)>,
+    /// Names assigned anywhere in the script, their types are not inferred
+    assigned: HashSet<String>,
+    /// In the order of the source
+    pub declarations: Vec<TypedDeclaration>,
+}
+
+impl TypeChecker {
+    pub fn new(strict: bool) -> TypeChecker {
+        TypeChecker {
+            strict,
+            had_error: false,
+            scopes: Vec::new(),
+            classes: HashMap::new(),
+            current_function: None,
+            assigned: HashSet::new(),
+            declarations: Vec::new(),
+        }
+    }
+
+    pub fn check(&mut self, statements: &[Stmt]) {
+        collect_classes(statements, &mut self.classes);
+        let mut assignments = AssignedNames(HashSet::new());
+        for statement in statements {
+            assignments.walk_stmt(statement);
+        }
+        self.assigned = assignments.0;
+        self.scopes = vec![HashMap::new()];
+        self.check_statements(statements);
+    }
+
+    /// Functions and classes are declared before the statements run, so calls in the
+    /// bodies of the ones declared earlier are checked too
+    fn check_statements(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            match statement {
+                Stmt::Function(function) => {
+                    let binding = self.function_binding(function);
+                    self.declare(&function.name, binding);
+                }
+                Stmt::Class(class) => {
+                    let binding = self.class_binding(class);
+                    self.declare(&class.name, binding);
+                }
+                _ => {}
+            }
+        }
+        for statement in statements {
+            statement.accept(self);
+        }
+    }
+
+    fn scoped(&mut self, check: impl FnOnce(&mut TypeChecker)) {
+        self.scopes.push(HashMap::new());
+        check(self);
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token, binding: Binding) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), binding);
+        }
+    }
+
+    /// Natives and names the script doesn't declare are not known
+    fn look_up(&self, name: &Token) -> Option<&Binding> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme))
+    }
+
+    fn report(&mut self, token: &Token, message: Message) {
+        match self.strict {
+            true => {
+                LoxErrorResult::resolver_error(token.clone(), message);
+                self.had_error = true;
+            }
+            false => {
+                LoxErrorResult::warning(token.clone(), message);
+            }
+        }
+    }
+
+    /// Type named by `annotation`, `None` for a name that is neither built in nor a class
+    fn named_type(&self, annotation: &Token) -> Option<LoxType> {
+        match annotation.lexeme.as_str() {
+            "Any" => Some(LoxType::Any),
+            "nil" => Some(LoxType::Nil),
+            "Bool" => Some(LoxType::Bool),
+            "Number" => Some(LoxType::Number),
+            "String" => Some(LoxType::String),
+            "List" => Some(LoxType::List),
+            "Function" => Some(LoxType::Function),
+            name if self.classes.contains_key(name) => Some(LoxType::Instance(name.to_string())),
+            _ => None,
+        }
+    }
+
+    /// Like `named_type`, `Any` without an annotation or with an unknown one
+    fn annotated_type(&self, annotation: &Option<Token>) -> LoxType {
+        annotation
+            .as_ref()
+            .and_then(|annotation| self.named_type(annotation))
+            .unwrap_or(LoxType::Unknown)
+    }
+
+    fn check_annotation(&mut self, annotation: &Option<Token>) {
+        if let Some(annotation) = annotation {
+            if self.named_type(annotation).is_none() {
+                self.report(annotation, T0401.with([annotation.lexeme().into()]));
+            }
+        }
+    }
+
+    fn function_binding(&self, function: &FunctionStmt) -> Binding {
+        let annotated = function.return_annotation.is_some()
+            || function.param_annotations.iter().any(Option::is_some);
+        let params = function
+            .params
+            .iter()
+            .zip(&function.param_annotations)
+            .map(|(param, annotation)| (param.clone(), self.annotated_type(annotation)))
+            .collect();
+        Binding {
+            value: LoxType::Function,
+            signature: Some(Signature {
+                params: annotated.then_some(params),
+                result: self.annotated_type(&function.return_annotation),
+            }),
+        }
+    }
+
+    /// A class is called with the arguments of its `init`, checked when `init` has annotations
+    fn class_binding(&self, class: &ClassStmt) -> Binding {
+        let initializer = class.methods.iter().find_map(|method| match method {
+            Stmt::Function(method) if method.name.lexeme == "init" => Some(method),
+            _ => None,
+        });
+        let params = initializer
+            .and_then(|initializer| self.function_binding(initializer).signature)
+            .and_then(|signature| signature.params);
+        Binding {
+            value: LoxType::Unknown,
+            signature: Some(Signature {
+                params,
+                result: LoxType::Instance(class.name.lexeme.clone()),
+            }),
+        }
+    }
+
+    /// Whether a value of type `actual` can be given where `expected` is declared
+    fn accepts(&self, expected: &LoxType, actual: &LoxType) -> bool {
+        match (expected, actual) {
+            (LoxType::Any | LoxType::Unknown, _)
+            | (_, LoxType::Any | LoxType::Unknown | LoxType::Nil) => true,
+            (LoxType::Instance(expected), LoxType::Instance(actual)) => {
+                let mut class = Some(actual);
+                while let Some(name) = class {
+                    if name == expected {
+                        return true;
+                    }
+                    class = self.classes.get(name).and_then(Option::as_ref);
+                }
+                false
+            }
+            (expected, actual) => expected == actual,
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> LoxType {
+        expr.accept(self)
+    }
+
+    fn record(&mut self, name: &Token, kind: SymbolKind, type_name: String) {
+        self.declarations.push(TypedDeclaration {
+            name: name.clone(),
+            kind,
+            type_name,
+        });
+    }
+
+    fn function(&mut self, function: &FunctionStmt, kind: SymbolKind) {
+        for annotation in &function.param_annotations {
+            self.check_annotation(annotation);
+        }
+        self.check_annotation(&function.return_annotation);
+        let result = self.annotated_type(&function.return_annotation);
+        let params: Vec<String> = function
+            .param_annotations
+            .iter()
+            .map(|annotation| self.annotated_type(annotation).to_string())
+            .collect();
+        let signature = format!("fun({}) -> {result}", params.join(", "));
+        self.record(&function.name, kind, signature);
+        let enclosing = self
+            .current_function
+            .replace((function.name.lexeme.clone(), result));
+        self.scoped(|checker| {
+            for (param, annotation) in function.params.iter().zip(&function.param_annotations) {
+                let value = checker.annotated_type(annotation);
+                checker.record(param, SymbolKind::Parameter, value.to_string());
+                checker.declare(param, Binding::value(value));
+            }
+            checker.check_statements(&function.body);
+        });
+        self.current_function = enclosing;
+    }
+
+    fn call(&mut self, expr: &CallExpr, arguments: &[LoxType]) -> LoxType {
+        let Expr::Variable(callee) = expr.callee.as_ref() else {
+            return LoxType::Unknown;
+        };
+        let Some(signature) = self
+            .look_up(&callee.name)
+            .and_then(|binding| binding.signature.clone())
+        else {
+            return LoxType::Unknown;
+        };
+        let Some(params) = signature.params else {
+            return signature.result;
+        };
+        if params.len() != arguments.len() {
+            let message = T0406.with([
+                params.len().to_string().into(),
+                arguments.len().to_string().into(),
+            ]);
+            self.report(&callee.name, message);
+            return signature.result;
+        }
+        for ((param, expected), actual) in params.iter().zip(arguments) {
+            if !self.accepts(expected, actual) {
+                let message = T0404.with([
+                    param.lexeme().into(),
+                    callee.name.lexeme().into(),
+                    expected.to_string().into(),
+                    actual.to_string().into(),
+                ]);
+                self.report(&callee.name, message);
+            }
+        }
+        signature.result
+    }
+}
+
+/// Declarations of statements `compile` already checked, with their types (`--types`).
+/// The mismatches were reported then, they are not reported again.
+pub fn declared_types(statements: &[Stmt]) -> Vec<TypedDeclaration> {
+    let mut checker = TypeChecker::new(false);
+    collect_diagnostics(|| checker.check(statements));
+    checker.declarations
+}
+
+/// Names of the classes declared anywhere in `statements`, with their super class
+fn collect_classes(statements: &[Stmt], classes: &mut HashMap<String, Option<String>>) {
+    for statement in statements {
+        match statement {
+            Stmt::Class(class) => {
+                let super_class = match class.super_class.as_deref() {
+                    Some(Expr::Variable(super_class)) => Some(super_class.name.lexeme.clone()),
+                    _ => None,
+                };
+                classes.insert(class.name.lexeme.clone(), super_class);
+                collect_classes(&class.methods, classes);
+            }
+            Stmt::Function(function) => collect_classes(&function.body, classes),
+            Stmt::Block(block) => collect_classes(&block.statements, classes),
+            _ => {}
+        }
+    }
+}
+
+/// Collects the names of the variables assigned by the script
+struct AssignedNames(HashSet<String>);
+
+impl Walker for AssignedNames {
+    fn walk_assign_expr(&mut self, expr: &AssignExpr) {
+        self.0.insert(expr.name.lexeme.clone());
+        self.walk_expr(&expr.value);
+    }
+}
+
+impl StmtVisitor<()> for TypeChecker {
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) {
+        self.scoped(|checker| checker.check_statements(&stmt.statements));
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &ClassStmt) {
+        self.record(&stmt.name, SymbolKind::Class, "class".to_string());
+        if let Some(super_class) = &stmt.super_class {
+            self.expr(super_class);
+        }
+        self.scoped(|checker| {
+            for method in &stmt.methods {
+                if let Stmt::Function(method) = method {
+                    checker.function(method, SymbolKind::Method);
+                }
+            }
+        });
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) {
+        self.expr(&stmt.expression);
+    }
+
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) {
+        self.scoped(|checker| {
+            if let Some(initializer) = &stmt.initializer {
+                initializer.accept(checker);
+            }
+            if let Some(condition) = &stmt.condition {
+                checker.expr(condition);
+            }
+            if let Some(increment) = &stmt.increment {
+                checker.expr(increment);
+            }
+            stmt.body.accept(checker);
+        });
+    }
+
+    fn visit_for_in_stmt(&mut self, stmt: &ForInStmt) {
+        self.expr(&stmt.iterable);
+        self.scoped(|checker| {
+            checker.record(
+                &stmt.name,
+                SymbolKind::Variable,
+                LoxType::Unknown.to_string(),
+            );
+            checker.declare(&stmt.name, Binding::value(LoxType::Unknown));
+            stmt.body.accept(checker);
+        });
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &FunctionStmt) {
+        self.function(stmt, SymbolKind::Function);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) {
+        self.expr(&stmt.condition);
+        stmt.then_branch.accept(self);
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) {
+        self.expr(&stmt.expression);
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) {
+        let Some(value) = &stmt.value else {
+            return;
+        };
+        let actual = self.expr(value);
+        if let Some((name, expected)) = self.current_function.clone() {
+            if !self.accepts(&expected, &actual) {
+                let message = T0405.with([
+                    name.into(),
+                    expected.to_string().into(),
+                    actual.to_string().into(),
+                ]);
+                self.report(&stmt.keyword, message);
+            }
+        }
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) {
+        let actual = stmt
+            .initializer
+            .as_ref()
+            .map(|initializer| self.expr(initializer));
+        if stmt.annotation.is_none() {
+            let inferred = match (self.assigned.contains(&stmt.name.lexeme), actual) {
+                (true, _) => LoxType::Unknown,
+                (false, Some(actual)) => actual,
+                (false, None) => LoxType::Nil,
+            };
+            self.record(&stmt.name, SymbolKind::Variable, inferred.to_string());
+            self.declare(&stmt.name, Binding::value(inferred));
+            return;
+        }
+        self.check_annotation(&stmt.annotation);
+        let expected = self.annotated_type(&stmt.annotation);
+        if let Some(actual) = actual {
+            if !self.accepts(&expected, &actual) {
+                let message = T0402.with([
+                    stmt.name.lexeme().into(),
+                    expected.to_string().into(),
+                    actual.to_string().into(),
+                ]);
+                self.report(&stmt.name, message);
+            }
+        }
+        self.record(&stmt.name, SymbolKind::Variable, expected.to_string());
+        self.declare(&stmt.name, Binding::value(expected));
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) {
+        self.expr(&stmt.condition);
+        stmt.body.accept(self);
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &BreakStmt) {}
+
+    fn visit_continue_stmt(&mut self, _stmt: &ContinueStmt) {}
+
+    fn visit_yield_stmt(&mut self, stmt: &YieldStmt) {
+        if let Some(value) = &stmt.value {
+            self.expr(value);
+        }
+    }
+
+    fn visit_debugger_stmt(&mut self, _stmt: &DebuggerStmt) {}
+}
+
+impl ExprVisitor<LoxType> for TypeChecker {
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> LoxType {
+        let actual = self.expr(&expr.value);
+        let expected = self
+            .look_up(&expr.name)
+            .map_or(LoxType::Unknown, |binding| binding.value.clone());
+        if !self.accepts(&expected, &actual) {
+            let message = T0403.with([
+                expr.name.lexeme().into(),
+                expected.to_string().into(),
+                actual.to_string().into(),
+            ]);
+            self.report(&expr.name, message);
+        }
+        actual
+    }
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> LoxType {
+        let left = self.expr(&expr.left);
+        let right = self.expr(&expr.right);
+        match expr.operator.lexeme.as_str() {
+            "-" | "*" | "/" | "%" => LoxType::Number,
+            "==" | "!=" | "<" | "<=" | ">" | ">=" => LoxType::Bool,
+            "+" if left == right && matches!(left, LoxType::Number | LoxType::String) => left,
+            _ => LoxType::Unknown,
+        }
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> LoxType {
+        self.expr(&expr.callee);
+        let arguments: Vec<LoxType> = expr
+            .arguments
+            .iter()
+            .map(|argument| self.expr(argument))
+            .collect();
+        self.call(expr, &arguments)
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> LoxType {
+        self.expr(&expr.object);
+        LoxType::Unknown
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> LoxType {
+        self.expr(&expr.expression)
+    }
+
+    fn visit_literal_expr(&mut self, expr: &LiteralExpr) -> LoxType {
+        match expr.value {
+            Object::Nil => LoxType::Nil,
+            Object::Bool(_) => LoxType::Bool,
+            Object::Int(_) | Object::Number(_) => LoxType::Number,
+            #[cfg(feature = "bigint")]
+            Object::BigInt(_) => LoxType::Number,
+            Object::String(_) => LoxType::String,
+            _ => LoxType::Unknown,
+        }
+    }
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> LoxType {
+        let left = self.expr(&expr.left);
+        let right = self.expr(&expr.right);
+        match left == right {
+            true => left,
+            false => LoxType::Unknown,
+        }
+    }
+
+    fn visit_set_expr(&mut self, expr: &SetExpr) -> LoxType {
+        self.expr(&expr.object);
+        self.expr(&expr.value)
+    }
+
+    fn visit_this_expr(&mut self, _expr: &ThisExpr) -> LoxType {
+        LoxType::Unknown
+    }
+
+    fn visit_super_expr(&mut self, _expr: &SuperExpr) -> LoxType {
+        LoxType::Unknown
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> LoxType {
+        self.expr(&expr.right);
+        match expr.operator.lexeme.as_str() {
+            "!" => LoxType::Bool,
+            _ => LoxType::Number,
+        }
+    }
+
+    fn visit_ternary_expr(&mut self, expr: &TernaryExpr) -> LoxType {
+        self.expr(&expr.condition);
+        let then_branch = self.expr(&expr.then_branch);
+        let else_branch = self.expr(&expr.else_branch);
+        match then_branch == else_branch {
+            true => then_branch,
+            false => LoxType::Unknown,
+        }
+    }
+
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) -> LoxType {
+        self.look_up(&expr.name)
+            .map_or(LoxType::Unknown, |binding| binding.value.clone())
+    }
+}
+
+#[cfg(test)]
+mod type_checker_tests {
+    use crate::{error::capture_diagnostics, parser::Parser, scanner::Scanner};
+
+    use super::*;
+
+    fn check_source(source: &str, strict: bool) -> (String, bool) {
+        let mut had_error = false;
+        let diagnostics = capture_diagnostics(|| {
+            let tokens = Scanner::new(source.to_string()).scan_tokens();
+            let statements = Parser::new(tokens).parse();
+            let mut checker = TypeChecker::new(strict);
+            checker.check(&statements);
+            had_error = checker.had_error;
+        });
+        (diagnostics, had_error)
+    }
+
+    #[test]
+    fn test_reports_mismatched_annotations() {
+        // Arrange
+        let source = "var count: Number = \"none\";
+fun greet(name: String, times: Number) -> String { return times; }
+greet(1, 2);
+greet(\"lox\");
+var text: String = greet(\"lox\", 1);
+text = 2;
+var shape: Shape;";
+        // Act
+        let (diagnostics, had_error) = check_source(source, false);
+        // Assert
+        assert_eq!(
+            diagnostics,
+            "[Line 1] - Warning[T0402]: 'count': Variable 'count' is declared as Number but initialized with String.
+[Line 2] - Warning[T0405]: 'return': 'greet' returns String, cannot return Number.
+[Line 3] - Warning[T0404]: 'greet': Parameter 'name' of 'greet' expects String, got Number.
+[Line 4] - Warning[T0406]: 'greet': Expected 2 arguments but got 1.
+[Line 6] - Warning[T0403]: 'text': Variable 'text' is declared as String, cannot assign Number.
+[Line 7] - Warning[T0401]: 'Shape': Unknown type 'Shape'.
+"
+        );
+        assert!(!had_error);
+    }
+
+    #[test]
+    fn test_accepts_compatible_and_untyped_code() {
+        // Arrange
+        let source = "class Shape {} class Circle < Shape { init(r: Number) {} }
+var shape: Shape = Circle(1);
+var missing: Circle = nil;
+fun untyped(a, b) { return a; }
+untyped(1);
+fun area(shape: Shape) -> Number { return 3 * 2; }
+var total: Number = area(Circle(2)) + 1;
+var label: String = \"a\" + \"b\";";
+        // Act
+        let (diagnostics, _) = check_source(source, false);
+        let (strict, had_error) = check_source(
+            "var wrong: Circle = Shape(); class Shape {} class Circle < Shape {}",
+            true,
+        );
+        // Assert
+        assert_eq!(diagnostics, "");
+        assert_eq!(
+            strict,
+            "[Line 1] - Error[T0402] at 'wrong': Variable 'wrong' is declared as Circle but initialized with Shape.\n"
+        );
+        assert!(had_error);
+    }
+
+    #[test]
+    fn test_infers_unannotated_variables() {
+        // Arrange
+        let source = "var n = 1; var s = \"a\"; var later = 2; later = \"b\";
+fun f(a: Number, b) -> String { var t; return s; }
+var checked: String = n;
+var fine: String = later;";
+        let tokens = Scanner::new(source.to_string()).scan_tokens();
+        let statements = Parser::new(tokens).parse();
+        // Act
+        let diagnostics = capture_diagnostics(|| TypeChecker::new(false).check(&statements));
+        let declarations: Vec<String> = declared_types(&statements)
+            .iter()
+            .map(TypedDeclaration::to_string)
+            .collect();
+        // Assert
+        assert_eq!(
+            diagnostics,
+            "[Line 3] - Warning[T0402]: 'checked': Variable 'checked' is declared as String but initialized with Number.\n"
+        );
+        assert_eq!(
+            declarations,
+            [
+                "1:5 var n: Number",
+                "1:16 var s: String",
+                "1:29 var later: unknown",
+                "2:5 fun f: fun(Number, unknown) -> String",
+                "2:7 param a: Number",
+                "2:18 param b: unknown",
+                "2:37 var t: nil",
+                "3:5 var checked: String",
+                "4:5 var fine: String",
+            ]
+        );
+    }
+}
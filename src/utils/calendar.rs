@@ -0,0 +1,200 @@
+use crate::error_codes::{term, Message, N0540, N0541, N0542};
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+/// A UTC date and time, there is no time zone database to convert to local time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DateTime {
+    pub year: i64,
+    /// 1 to 12
+    pub month: i64,
+    pub day: i64,
+    pub hour: i64,
+    pub minute: i64,
+    pub second: i64,
+    pub millisecond: i64,
+    /// 0 for Sunday to 6 for Saturday
+    pub weekday: i64,
+}
+
+impl DateTime {
+    /// Date of a timestamp in milliseconds since 1970-01-01, fractions are dropped
+    pub fn from_epoch_millis(epoch_millis: f64) -> DateTime {
+        let millis = epoch_millis.floor() as i64;
+        let days = millis.div_euclid(MILLIS_PER_DAY);
+        let millis_of_day = millis.rem_euclid(MILLIS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        DateTime {
+            year,
+            month,
+            day,
+            hour: millis_of_day / 3_600_000,
+            minute: millis_of_day / 60_000 % 60,
+            second: millis_of_day / 1000 % 60,
+            millisecond: millis_of_day % 1000,
+            // 1970-01-01 was a Thursday
+            weekday: (days + 4).rem_euclid(7),
+        }
+    }
+
+    /// Timestamp in milliseconds of a date, or why its parts are out of range.
+    /// The weekday is ignored.
+    pub fn to_epoch_millis(&self) -> Result<f64, Message> {
+        if !(1..=12).contains(&self.month) {
+            return Err(out_of_range("Month", self.month, 1, 12));
+        }
+        let month_days = days_in_month(self.year, self.month);
+        if !(1..=month_days).contains(&self.day) {
+            return Err(out_of_range("Day", self.day, 1, month_days));
+        }
+        let limits = [
+            ("Hour", self.hour, 23),
+            ("Minute", self.minute, 59),
+            ("Second", self.second, 59),
+            ("Millisecond", self.millisecond, 999),
+        ];
+        for (name, value, max) in limits {
+            if !(0..=max).contains(&value) {
+                return Err(out_of_range(name, value, 0, max));
+            }
+        }
+        let days = days_from_civil(self.year, self.month, self.day);
+        let millis = days * MILLIS_PER_DAY
+            + self.hour * 3_600_000
+            + self.minute * 60_000
+            + self.second * 1000
+            + self.millisecond;
+        Ok(millis as f64)
+    }
+
+    /// Supports `%Y` (year), `%m`, `%d`, `%H`, `%M`, `%S` (two digits), `%f` (milliseconds,
+    /// three digits) and `%%`
+    pub fn format(&self, format: &str) -> Result<String, Message> {
+        let mut formatted = String::new();
+        let mut chars = format.chars();
+        while let Some(character) = chars.next() {
+            if character != '%' {
+                formatted.push(character);
+                continue;
+            }
+            match chars.next() {
+                Some('Y') => formatted.push_str(&format!("{:04}", self.year)),
+                Some('m') => formatted.push_str(&format!("{:02}", self.month)),
+                Some('d') => formatted.push_str(&format!("{:02}", self.day)),
+                Some('H') => formatted.push_str(&format!("{:02}", self.hour)),
+                Some('M') => formatted.push_str(&format!("{:02}", self.minute)),
+                Some('S') => formatted.push_str(&format!("{:02}", self.second)),
+                Some('f') => formatted.push_str(&format!("{:03}", self.millisecond)),
+                Some('%') => formatted.push('%'),
+                Some(other) => return Err(N0541.with([other.to_string().into()])),
+                None => return Err(N0542.with([])),
+            }
+        }
+        Ok(formatted)
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Year, month and day of the days since 1970-01-01 in the proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    // Counted from 0000-03-01 so the leap day is the last day of the (400 year) era
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Inverse of `civil_from_days`
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// A part of a date outside of `min..=max`, `name` as in `Month 13 is not between 1 and 12.`
+fn out_of_range(name: &'static str, value: i64, min: i64, max: i64) -> Message {
+    N0540.with([
+        term(name),
+        value.to_string().into(),
+        min.to_string().into(),
+        max.to_string().into(),
+    ])
+}
+
+#[cfg(test)]
+mod calendar_tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        // Arrange
+        let leap_day = 1_709_210_096_789.0;
+        // Act
+        let date = DateTime::from_epoch_millis(leap_day);
+        let before_epoch = DateTime::from_epoch_millis(-1.0);
+        // Assert
+        assert_eq!(
+            date,
+            DateTime {
+                year: 2024,
+                month: 2,
+                day: 29,
+                hour: 12,
+                minute: 34,
+                second: 56,
+                millisecond: 789,
+                weekday: 4,
+            }
+        );
+        assert_eq!(date.to_epoch_millis(), Ok(leap_day));
+        assert_eq!(
+            before_epoch.format("%Y-%m-%d %H:%M:%S.%f"),
+            Ok("1969-12-31 23:59:59.999".to_string())
+        );
+        assert_eq!(before_epoch.to_epoch_millis(), Ok(-1.0));
+    }
+
+    #[test]
+    fn test_invalid_dates() {
+        // Arrange
+        let date = DateTime::from_epoch_millis(0.0);
+        // Assert
+        assert!(DateTime {
+            day: 29,
+            month: 2,
+            year: 2023,
+            ..date
+        }
+        .to_epoch_millis()
+        .is_err());
+        assert!(DateTime { hour: 24, ..date }.to_epoch_millis().is_err());
+        assert!(date.format("%Q").is_err());
+    }
+}
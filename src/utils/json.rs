@@ -0,0 +1,327 @@
+use std::fmt::{self, Display, Formatter};
+
+/// Minimal JSON value, enough for the messages of the language server
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Keys keep their insertion order
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = JsonParser {
+            chars: text.chars().collect(),
+            current: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.current < parser.chars.len() {
+            true => Err(format!("Unexpected trailing data at {}.", parser.current)),
+            false => Ok(value),
+        }
+    }
+
+    /// Builds an object from `(key, value)` pairs
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub fn string(value: &str) -> Json {
+        Json::String(value.to_string())
+    }
+
+    /// Field of an object, `None` for missing keys and other values
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{value}"),
+            Json::Number(value) if value.fract() == 0.0 && value.abs() < 1e15 => {
+                write!(f, "{}", *value as i64)
+            }
+            Json::Number(value) if value.is_finite() => write!(f, "{value}"),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(value) => write_string(f, value),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (idx, (key, value)) in fields.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut Formatter, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for character in value.chars() {
+        match character {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    current: usize,
+}
+
+impl JsonParser {
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(format!("Unexpected character '{c}' at {}.", self.current)),
+            None => Err("Unexpected end of JSON.".to_string()),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        let end = self.current + word.len();
+        if end <= self.chars.len()
+            && self.chars[self.current..end]
+                .iter()
+                .copied()
+                .eq(word.chars())
+        {
+            self.current = end;
+            return Ok(value);
+        }
+        Err(format!("Invalid literal at {}.", self.current))
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.current;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.current += 1;
+        }
+        let text: String = self.chars[start..self.current].iter().collect();
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| format!("Invalid number '{text}'."))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        // The opening quote
+        self.current += 1;
+        let mut value = String::new();
+        loop {
+            let Some(character) = self.next() else {
+                return Err("Unterminated string.".to_string());
+            };
+            match character {
+                '"' => return Ok(value),
+                '\\' => match self.next() {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('/') => value.push('/'),
+                    Some('b') => value.push('\u{8}'),
+                    Some('f') => value.push('\u{c}'),
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some('u') => value.push(self.unicode_escape()?),
+                    _ => return Err(format!("Invalid escape at {}.", self.current)),
+                },
+                c => value.push(c),
+            }
+        }
+    }
+
+    /// The code after `\u`, joining surrogate pairs
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex_code()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or("Invalid unicode escape.".to_string());
+        }
+        if self.next() != Some('\\') || self.next() != Some('u') {
+            return Err("Unpaired surrogate in unicode escape.".to_string());
+        }
+        let low = self.hex_code()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err("Unpaired surrogate in unicode escape.".to_string());
+        }
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+            .ok_or("Invalid unicode escape.".to_string())
+    }
+
+    fn hex_code(&mut self) -> Result<u32, String> {
+        let end = self.current + 4;
+        if end > self.chars.len() {
+            return Err("Unterminated unicode escape.".to_string());
+        }
+        let digits: String = self.chars[self.current..end].iter().collect();
+        self.current = end;
+        u32::from_str_radix(&digits, 16).map_err(|_| format!("Invalid unicode escape '{digits}'."))
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        // The opening bracket
+        self.current += 1;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.current += 1;
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(Json::Array(values)),
+                _ => return Err(format!("Expected ',' or ']' at {}.", self.current)),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        // The opening brace
+        self.current += 1;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.current += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(format!("Expected a key at {}.", self.current));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if self.next() != Some(':') {
+                return Err(format!("Expected ':' at {}.", self.current));
+            }
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some('}') => return Ok(Json::Object(fields)),
+                _ => return Err(format!("Expected ',' or '}}' at {}.", self.current)),
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.current += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.current).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let character = self.peek();
+        self.current += 1;
+        character
+    }
+}
+
+#[cfg(test)]
+mod json_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_print_round_trip() {
+        // Arrange
+        let text = r#"{"id":1,"ok":true,"list":[null,-2.5,"a\"b\n\u00e9"],"empty":{}}"#;
+        // Act
+        let value = Json::parse(text).expect("Valid JSON");
+        // Assert
+        assert_eq!(value.get("id").and_then(Json::as_f64), Some(1.0));
+        assert_eq!(
+            value.get("list").and_then(Json::as_array).map(Vec::len),
+            Some(3)
+        );
+        assert_eq!(
+            value.to_string(),
+            "{\"id\":1,\"ok\":true,\"list\":[null,-2.5,\"a\\\"b\\né\"],\"empty\":{}}"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        // Assert
+        assert!(Json::parse("{\"a\" 1}").is_err());
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("\"\\ud83d\"").is_err());
+        assert!(Json::parse("1 2").is_err());
+    }
+}
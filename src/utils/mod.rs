@@ -1 +1,3 @@
-pub mod ast_printer;
+pub mod calendar;
+pub mod json;
+pub mod random;
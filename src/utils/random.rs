@@ -0,0 +1,81 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+/// Small pseudo-random generator (SplitMix64), good enough for games and simulations
+/// but not for anything that needs to be unpredictable
+#[derive(Debug, Clone, PartialEq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Default for Random {
+    /// Seeded differently on every run, from the keys of the standard library hasher
+    fn default() -> Self {
+        Random::new(RandomState::new().build_hasher().finish())
+    }
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut value = self.state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        value ^ (value >> 31)
+    }
+
+    /// Uniform number in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        // The 53 high bits fill the mantissa exactly
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform integer in `[low, high]`, `low` must not be greater than `high`
+    pub fn range(&mut self, low: i64, high: i64) -> i64 {
+        let span = high.abs_diff(low).wrapping_add(1);
+        // A span of 0 means the whole range of u64 was asked for
+        let offset = match span {
+            0 => self.next_u64(),
+            _ => self.next_u64() % span,
+        };
+        low.wrapping_add(offset as i64)
+    }
+}
+
+#[cfg(test)]
+mod random_tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        // Arrange
+        let mut first = Random::new(42);
+        let mut second = Random::new(42);
+        // Act
+        let first_values: Vec<u64> = (0..5).map(|_| first.next_u64()).collect();
+        let second_values: Vec<u64> = (0..5).map(|_| second.next_u64()).collect();
+        // Assert
+        assert_eq!(first_values, second_values);
+        assert_ne!(first_values[0], first_values[1]);
+    }
+
+    #[test]
+    fn test_ranges() {
+        // Arrange
+        let mut random = Random::new(7);
+        // Act
+        let numbers: Vec<f64> = (0..1000).map(|_| random.next_f64()).collect();
+        let dice: Vec<i64> = (0..1000).map(|_| random.range(1, 6)).collect();
+        // Assert
+        assert!(numbers.iter().all(|n| (0.0..1.0).contains(n)));
+        assert!(dice.iter().all(|n| (1..=6).contains(n)));
+        assert!((1..=6).all(|face| dice.contains(&face)));
+        assert_eq!(random.range(-3, -3), -3);
+    }
+}
@@ -0,0 +1,2 @@
+//! Visitor with a default traversal of every node, generated by `build.rs` from `lox.ast`
+include!(concat!(env!("OUT_DIR"), "/walker.rs"));
@@ -0,0 +1,46 @@
+use std::{
+    fs,
+    io::{self, Write},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{interpreter::Interpreter, RunError};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Runs the script again every time its modification time changes, in the interpreter made
+/// by `new_interpreter` with the options of the command line. Runs after the first one are
+/// reloads in the same interpreter, the global variables keep their value (see `crate::reload`).
+pub fn watch_file(path: &str, new_interpreter: &dyn Fn() -> Interpreter) {
+    let mut interpreter = new_interpreter();
+    let mut last_modified: Option<SystemTime> = None;
+    loop {
+        // Editors may replace the file while saving it, so a missing file is retried later
+        if let Ok(modified) = fs::metadata(path).and_then(|metadata| metadata.modified()) {
+            if last_modified != Some(modified) {
+                last_modified = Some(modified);
+                run_once(path, &mut interpreter);
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn run_once(path: &str, interpreter: &mut Interpreter) {
+    // Clear the screen and move the cursor to the top left corner
+    print!("\x1b[2J\x1b[H");
+    let _ = io::stdout().flush();
+
+    let started = Instant::now();
+    let result = fs::read_to_string(path)
+        .map_err(RunError::Io)
+        .and_then(|source| crate::reload(source, interpreter));
+    if let Err(RunError::Io(err)) = result {
+        eprintln!("Could not run '{path}': {err}");
+    }
+    println!(
+        "\n[Finished in {:.2?}] Watching '{path}' for changes...",
+        started.elapsed()
+    );
+}
@@ -0,0 +1,57 @@
+use std::{fs, path::Path};
+
+use rlox_interpreter::{error::capture_diagnostics, interpreter::Interpreter};
+
+const SNAPSHOT_DIR: &str = "tests/diagnostics";
+
+/// Diagnostics of a script exactly as a user would see them on stderr
+fn render_diagnostics(source: String) -> String {
+    let mut interpreter = Interpreter::new();
+    let captured = interpreter.capture_output();
+    let mut diagnostics = capture_diagnostics(|| {
+        rlox_interpreter::run(source, &mut interpreter, false).ok();
+    });
+    diagnostics.push_str(&captured.borrow().stderr);
+    diagnostics
+}
+
+/// Every `.lox` file in `tests/diagnostics` is compared against the `.stderr` file next to it.
+/// Run with `UPDATE_SNAPSHOTS=1` to write the snapshots again after changing a message.
+#[test]
+fn test_diagnostics_snapshots() {
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let mut scripts: Vec<_> = fs::read_dir(SNAPSHOT_DIR)
+        .expect("Could not read the snapshots directory")
+        .map(|entry| entry.expect("Could not read directory entry").path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "lox"))
+        .collect();
+    scripts.sort();
+    assert!(!scripts.is_empty(), "No scripts found in '{SNAPSHOT_DIR}'");
+
+    let mut mismatches = Vec::new();
+    for script in scripts {
+        let source = fs::read_to_string(&script).expect("Could not read script");
+        let actual = render_diagnostics(source);
+        let snapshot = script.with_extension("stderr");
+        if update {
+            fs::write(&snapshot, &actual).expect("Could not write snapshot");
+            continue;
+        }
+        match fs::read_to_string(&snapshot) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => mismatches.push(format!(
+                "{}\n--- expected\n{expected}--- actual\n{actual}",
+                display(&snapshot)
+            )),
+            Err(_) => mismatches.push(format!(
+                "{} is missing, run with UPDATE_SNAPSHOTS=1 to create it\n--- actual\n{actual}",
+                display(&snapshot)
+            )),
+        }
+    }
+    assert!(mismatches.is_empty(), "\n{}", mismatches.join("\n"));
+}
+
+fn display(path: &Path) -> String {
+    path.display().to_string()
+}
@@ -0,0 +1,72 @@
+use std::process::Command;
+
+/// Runs the binary with the options on a script file holding `source`, returns its stderr
+/// and exit code
+fn run_script(name: &str, options: &[&str], source: &str) -> (String, Option<i32>) {
+    let path = std::env::temp_dir().join(format!("rlox-{name}-{}.lox", std::process::id()));
+    std::fs::write(&path, source).expect("Could not write the script");
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox-interpreter"))
+        .args(options)
+        .arg(&path)
+        .output()
+        .expect("Could not run the interpreter");
+    std::fs::remove_file(&path).ok();
+    (
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+        output.status.code(),
+    )
+}
+
+#[test]
+fn test_compile_errors_show_the_source_line() {
+    // Act
+    let (stderr, code) = run_script("compile-error", &[], "var a = 1;\nprint a +;\n");
+    // Assert
+    assert_eq!(
+        stderr,
+        "[Line 2] - Error[P0101] at ';': Expect expression.
+ 2 | print a +;
+   |          ^
+"
+    );
+    assert_eq!(code, Some(65));
+}
+
+#[test]
+fn test_runtime_errors_show_the_source_line() {
+    // Act
+    let (stderr, code) = run_script("runtime-error", &[], "print 1;\nprint -\"a\";\n");
+    // Assert
+    assert_eq!(
+        stderr,
+        "[Line 2] - Error[I0301]: Operand must be a number.
+ 2 | print -\"a\";
+   | ^^^^^^^^^^^
+"
+    );
+    assert_eq!(code, Some(70));
+}
+
+#[test]
+fn test_snippets_line_up_after_tabs() {
+    // Arrange
+    let source = "fun f() {\n\tprint 1 +;\n}\n";
+    // Act
+    let (default, _) = run_script("default-tabs", &[], source);
+    let (narrow, _) = run_script("narrow-tabs", &["--tab-width=2"], source);
+    // Assert
+    assert_eq!(
+        default,
+        "[Line 2] - Error[P0101] at ';': Expect expression.
+ 2 |     print 1 +;
+   |              ^
+"
+    );
+    assert_eq!(
+        narrow,
+        "[Line 2] - Error[P0101] at ';': Expect expression.
+ 2 |   print 1 +;
+   |            ^
+"
+    );
+}
@@ -0,0 +1,57 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+const SCRIPT: &str = "var greeting = \"Hello\";
+fun greet(name) { return greeting + \", \" + name + \"!\"; }
+print greet(\"stdin\");
+";
+
+/// Runs the binary with the arguments and the script piped to its stdin
+fn run_piped(args: &[&str], script: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox-interpreter"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Could not start the interpreter");
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(script.as_bytes())
+        .expect("Could not write the script");
+    child
+        .wait_with_output()
+        .expect("Could not wait for the interpreter")
+}
+
+#[test]
+fn test_script_from_stdin_with_dash() {
+    // Act
+    let output = run_piped(&["-"], SCRIPT);
+    // Assert
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "\"Hello, stdin!\"\n"
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn test_script_from_piped_stdin_without_arguments() {
+    // Act
+    let output = run_piped(&[], SCRIPT);
+    let failing = run_piped(&[], "print 1;\nprint -\"a\";\n");
+    // Assert
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "\"Hello, stdin!\"\n"
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&failing.stdout), "1\n");
+    assert_eq!(failing.status.code(), Some(70));
+}
@@ -0,0 +1,41 @@
+use rlox_interpreter::{
+    interpreter::{FileSystem, Interpreter},
+    object::Object,
+    run_captured,
+};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = Date)]
+    fn now() -> f64;
+
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
+}
+
+/// What a script printed and reported, returned to JavaScript as `{ stdout, diagnostics }`
+#[wasm_bindgen(getter_with_clone)]
+pub struct RunResult {
+    pub stdout: String,
+    pub diagnostics: String,
+}
+
+/// Runs a script in a fresh interpreter, nothing is kept between calls
+#[wasm_bindgen]
+pub fn run(source: &str) -> RunResult {
+    let mut interpreter = Interpreter::new();
+    // The interpreter's `clock` and `now` need the system time, the browser's one is used instead
+    interpreter.define_native("clock", 0, |_, _, _| Ok(Object::Number(now())));
+    interpreter.define_native("now", 0, |_, _, _| Ok(Object::Number(now())));
+    interpreter.define_native("clockMonotonic", 0, |_, _, _| {
+        Ok(Object::Number(performance_now()))
+    });
+    // There is no disk in the browser, files only live during the run
+    interpreter.files = FileSystem::Memory(Default::default());
+    let output = run_captured(source, &mut interpreter);
+    RunResult {
+        stdout: output.stdout,
+        diagnostics: output.diagnostics,
+    }
+}